@@ -71,6 +71,9 @@ fn detect_cfgs(target: &mut Target) {
         ("apple", &(|_| target_os_is_apple())),
         ("bsd", &(|_| target_os_is_bsd())),
         ("cygwin", &(|_| target_os_is_cygwin())),
+        ("have_close_range", &|target| {
+            target.has_symbol("close_range")
+        }),
         ("have_eventfd", &|target| {
             // FIXME: NetBSD 10 has eventfd, but the libc crate does not expose it.
             if target_os() == "netbsd" {
@@ -94,6 +97,10 @@ fn detect_cfgs(target: &mut Target) {
                 target.has_header("spawn.h")
             }
         }),
+        // glibc 2.34+, Solaris/illumos and the Hurd.
+        ("have_posix_spawn_file_actions_addclosefrom_np", &|target| {
+            target.has_symbol("posix_spawn_file_actions_addclosefrom_np")
+        }),
         ("small_main_stack", &has_small_stack),
         ("using_cmake", &|_| {
             option_env!("FISH_CMAKE_BINARY_DIR").is_some()
@@ -33,4 +33,18 @@ endif()
 
 add_feature_info(Translation WITH_MESSAGE_LOCALIZATION "message localization (requires gettext)")
 
+option(WITH_SQLITE_HISTORY "Support storing the history in an SQLite database. Requires SQLite." OFF)
+if("${WITH_SQLITE_HISTORY}")
+    list(APPEND FISH_CARGO_FEATURES_LIST "sqlite-history")
+endif()
+
+add_feature_info(SQLiteHistory WITH_SQLITE_HISTORY "history stored in an SQLite database (requires SQLite)")
+
+option(WITH_HISTORY_SYNC "Support syncing the history between machines." OFF)
+if("${WITH_HISTORY_SYNC}")
+    list(APPEND FISH_CARGO_FEATURES_LIST "history-sync")
+endif()
+
+add_feature_info(HistorySync WITH_HISTORY_SYNC "syncing the history between machines")
+
 list(JOIN FISH_CARGO_FEATURES_LIST , FISH_CARGO_FEATURES)
@@ -0,0 +1,144 @@
+//! Glob qualifiers, like the "(.)" in "*(.)", which select the files a wildcard matches by their
+//! type, size or modification time. They are recognized here, so that unescaping and finding
+//! command substitutions agree on what is a qualifier; the files are checked when expanding.
+
+use fish_widestring::{WExt as _, wstr};
+use std::cmp::Ordering;
+
+/// The type of file a glob qualifier like the "." in "*(.)" selects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlobFileType {
+    /// `.`: regular files
+    Regular,
+    /// `/`: directories
+    Directory,
+    /// `@`: symbolic links
+    Symlink,
+    /// `=`: sockets
+    Socket,
+    /// `p`: named pipes
+    Fifo,
+    /// `*`: executable regular files
+    Executable,
+}
+
+/// A single glob qualifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlobQualifier {
+    /// The file has this type.
+    Type(GlobFileType),
+    /// `L`: the size in units of `unit` bytes, rounded up, compares to `amount` like `ordering`.
+    Size {
+        ordering: Ordering,
+        amount: u64,
+        unit: u64,
+    },
+    /// `m`: the time since the last modification in units of `unit` seconds, rounded down,
+    /// compares to `amount` like `ordering`.
+    Mtime {
+        ordering: Ordering,
+        amount: u64,
+        unit: u64,
+    },
+}
+
+/// Glob qualifiers, like the "(.)" in "*(.)", which select the files a wildcard matches by their
+/// type, size or modification time. A file must match all qualifiers.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GlobQualifiers(Vec<GlobQualifier>);
+
+impl GlobQualifiers {
+    /// Parse qualifiers without the surrounding parentheses, like "." or "m-7". Return None if
+    /// they are not valid.
+    pub fn parse(s: &wstr) -> Option<Self> {
+        let s = s.as_char_slice();
+        if s.is_empty() {
+            return None;
+        }
+        let mut result = vec![];
+        let mut pos = 0;
+        while pos < s.len() {
+            let c = s[pos];
+            pos += 1;
+            let file_type = match c {
+                '.' => Some(GlobFileType::Regular),
+                '/' => Some(GlobFileType::Directory),
+                '@' => Some(GlobFileType::Symlink),
+                '=' => Some(GlobFileType::Socket),
+                'p' => Some(GlobFileType::Fifo),
+                '*' => Some(GlobFileType::Executable),
+                _ => None,
+            };
+            if let Some(file_type) = file_type {
+                result.push(GlobQualifier::Type(file_type));
+                continue;
+            }
+            // Size and time qualifiers take an optional unit, an optional sign, and a number.
+            let unit = match (c, s.get(pos)) {
+                ('L', Some('k')) => Some(1024),
+                ('L', Some('m')) => Some(1024 * 1024),
+                ('L', Some('g')) => Some(1024 * 1024 * 1024),
+                ('m', Some('s')) => Some(1),
+                ('m', Some('m')) => Some(60),
+                ('m', Some('h')) => Some(60 * 60),
+                ('m', Some('d')) => Some(24 * 60 * 60),
+                ('m', Some('w')) => Some(7 * 24 * 60 * 60),
+                ('m', Some('M')) => Some(30 * 24 * 60 * 60),
+                ('L' | 'm', _) => None,
+                _ => return None,
+            };
+            if unit.is_some() {
+                pos += 1;
+            }
+            let ordering = match s.get(pos) {
+                Some('-') => Ordering::Less,
+                Some('+') => Ordering::Greater,
+                _ => Ordering::Equal,
+            };
+            if ordering != Ordering::Equal {
+                pos += 1;
+            }
+            let digits = s[pos..].iter().take_while(|c| c.is_ascii_digit()).count();
+            if digits == 0 {
+                return None;
+            }
+            let amount = s[pos..pos + digits].iter().try_fold(0_u64, |acc, c| {
+                acc.checked_mul(10)?
+                    .checked_add(u64::from(c.to_digit(10).unwrap()))
+            })?;
+            pos += digits;
+            result.push(if c == 'L' {
+                GlobQualifier::Size {
+                    ordering,
+                    amount,
+                    unit: unit.unwrap_or(1),
+                }
+            } else {
+                GlobQualifier::Mtime {
+                    ordering,
+                    amount,
+                    unit: unit.unwrap_or(24 * 60 * 60),
+                }
+            });
+        }
+        Some(Self(result))
+    }
+
+    /// Return whether there are no qualifiers, so every file matches.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The qualifiers, which must all match.
+    pub fn iter(&self) -> impl Iterator<Item = &GlobQualifier> {
+        self.0.iter()
+    }
+}
+
+/// Return whether `s` is a parenthesized list of valid glob qualifiers, like "(.)" or "(m-7)".
+pub fn is_glob_qualifier_list(s: &wstr) -> bool {
+    s.len() > 2
+        && s.char_at(0) == '('
+        && s.char_at(s.len() - 1) == ')'
+        && GlobQualifiers::parse(&s[1..s.len() - 1]).is_some()
+}
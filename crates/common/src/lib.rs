@@ -2,9 +2,10 @@ use bitflags::bitflags;
 use fish_feature_flags::{FeatureFlag, feature_test};
 use fish_widestring::{
     ANY_CHAR, ANY_STRING, ANY_STRING_RECURSIVE, ASCII_MAX, BRACE_BEGIN, BRACE_END, BRACE_SEP,
-    BRACE_SPACE, BYTE_MAX, HOME_DIRECTORY, INTERNAL_SEPARATOR, L, PROCESS_EXPAND_SELF,
-    PROCESS_EXPAND_SELF_STR, UCS2_MAX, VARIABLE_EXPAND, VARIABLE_EXPAND_SINGLE, WExt as _, WString,
-    bytes2wcstring, decode_byte_from_char, fish_reserved_codepoint, wcs2bytes, wstr,
+    BRACE_SPACE, BYTE_MAX, GLOB_QUALIFIER, HOME_DIRECTORY, INTERNAL_SEPARATOR, L,
+    PROCESS_EXPAND_SELF, PROCESS_EXPAND_SELF_STR, UCS2_MAX, VARIABLE_EXPAND,
+    VARIABLE_EXPAND_SINGLE, WExt as _, WString, bytes2wcstring, decode_byte_from_char,
+    fish_reserved_codepoint, wcs2bytes, wstr,
 };
 use libc::{SIG_IGN, SIGTTOU, STDIN_FILENO};
 use std::{
@@ -25,6 +26,8 @@ use std::{
     time,
 };
 
+pub mod glob_qualifiers;
+
 pub const PACKAGE_NAME: &str = env!("CARGO_PKG_NAME");
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -242,6 +245,12 @@ fn escape_string_script(input: &wstr, flags: EscapeFlags) -> WString {
             ANY_STRING_RECURSIVE => {
                 out += L!("**");
             }
+            GLOB_QUALIFIER => {
+                out.push('(');
+                out.push_utfstr(&input[i + 1..]);
+                out.push(')');
+                break;
+            }
             ',' => {
                 if escape_comma {
                     need_escape = true;
@@ -599,6 +608,19 @@ fn unescape_string_internal(input: &wstr, flags: UnescapeFlags) -> Option<WStrin
                             vars_or_seps.push(input_position);
                         }
                     }
+                '('
+                    if unescape_special
+                        && result
+                            .chars()
+                            .any(|c| matches!(c, ANY_CHAR | ANY_STRING | ANY_STRING_RECURSIVE))
+                        && glob_qualifiers::is_glob_qualifier_list(&input[input_position..]) => {
+                        // Glob qualifiers, like the "(.)" in "*(.)". They end the token, and their
+                        // parentheses would otherwise have been a command substitution.
+                        result.push(GLOB_QUALIFIER);
+                        result.push_utfstr(&input[input_position + 1..input.len() - 1]);
+                        input_position = input.len() - 1;
+                        to_append_or_none = None;
+                    }
                 '{'
                     if unescape_special => {
                         brace_count += 1;
@@ -620,8 +642,10 @@ fn unescape_string_internal(input: &wstr, flags: UnescapeFlags) -> Option<WStrin
                         if let Some(brace) = braces.pop() {
                             // HACK: To reduce accidental use of brace expansion, treat a brace
                             // with zero or one items as literal input. See #4632. (The hack is
-                            // doing it here and like this.)
-                            if vars_or_seps.last().is_none_or(|i| *i < brace) {
+                            // doing it here and like this.) Ranges like {1..10} are expanded.
+                            if vars_or_seps.last().is_none_or(|i| *i < brace)
+                                && BraceRange::parse(&result[brace + 1..]).is_none()
+                            {
                                 result.as_char_slice_mut()[brace] = '{';
                                 // We also need to turn all spaces back.
                                 for i in brace + 1..result.len() {
@@ -830,6 +854,87 @@ fn unescape_string_var(input: &wstr) -> Option<WString> {
     Some(bytes2wcstring(&result))
 }
 
+/// A numeric range between braces, like the `1..10` in `{1..10}` or the `00..20..5` in
+/// `{00..20..5}`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BraceRange {
+    pub start: i64,
+    pub end: i64,
+    /// The distance between values, which is never zero. The range counts down if `start` is
+    /// greater than `end`.
+    pub step: i64,
+    /// The width to pad values to with zeros, or 0 for no padding. Padding is used if one of the
+    /// bounds is written with a leading zero, and then it is the width of the longer bound.
+    pub width: usize,
+}
+
+impl BraceRange {
+    /// Parse the contents of a pair of braces as a range, or return None if they are not one.
+    pub fn parse(s: &wstr) -> Option<Self> {
+        fn parse_int(s: &wstr) -> Option<i64> {
+            let digits = s.strip_prefix('-').unwrap_or(s);
+            if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            s.to_string().parse().ok()
+        }
+        fn has_leading_zero(s: &wstr) -> bool {
+            let digits = s.strip_prefix('-').unwrap_or(s);
+            digits.len() > 1 && digits.char_at(0) == '0'
+        }
+
+        let mut parts = vec![];
+        let mut rest = s;
+        while let Some(pos) = rest.find(L!("..")) {
+            parts.push(&rest[..pos]);
+            rest = &rest[pos + 2..];
+        }
+        parts.push(rest);
+        let (start, end, step) = match parts[..] {
+            [start, end] => (start, end, 1),
+            [start, end, step] => (
+                start,
+                end,
+                parse_int(step)?.checked_abs().filter(|&step| step != 0)?,
+            ),
+            _ => return None,
+        };
+        let width = if has_leading_zero(start) || has_leading_zero(end) {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+        Some(BraceRange {
+            start: parse_int(start)?,
+            end: parse_int(end)?,
+            step,
+            width,
+        })
+    }
+
+    /// Return the number of values in the range.
+    pub fn value_count(self) -> u64 {
+        (self.start.abs_diff(self.end) / self.step.unsigned_abs()).saturating_add(1)
+    }
+
+    /// Return the values in the range, in order.
+    pub fn values(self) -> impl Iterator<Item = WString> {
+        let mut next = Some(self.start);
+        std::iter::from_fn(move || {
+            let value = next?;
+            next = if self.start <= self.end {
+                value.checked_add(self.step).filter(|&v| v <= self.end)
+            } else {
+                value.checked_sub(self.step).filter(|&v| v >= self.end)
+            };
+            Some(WString::from_str(&format!(
+                "{value:0width$}",
+                width = self.width
+            )))
+        })
+    }
+}
+
 /// Given a string starting with a backslash, read the escape as if it is unquoted, appending
 /// to result. Return the number of characters consumed, or none on error.
 pub fn read_unquoted_escape(
@@ -1546,6 +1651,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_brace_range() {
+        let values = |s: &wstr| -> Vec<String> {
+            BraceRange::parse(s)
+                .map(|range| range.values().map(|v| v.to_string()).collect())
+                .unwrap_or_default()
+        };
+        assert_eq!(values(L!("1..3")), ["1", "2", "3"]);
+        assert_eq!(values(L!("2..-2..2")), ["2", "0", "-2"]);
+        assert_eq!(values(L!("08..10")), ["08", "09", "10"]);
+        assert_eq!(values(L!("-1..1..-1")), ["-1", "0", "1"]);
+        assert_eq!(
+            values(L!("9223372036854775806..9223372036854775807..5")),
+            ["9223372036854775806"]
+        );
+        let count = |s: &wstr| BraceRange::parse(s).unwrap().value_count();
+        assert_eq!(count(L!("1..3")), 3);
+        assert_eq!(count(L!("2..-3..2")), 3);
+        assert_eq!(
+            count(L!("-9223372036854775808..9223372036854775807")),
+            u64::MAX
+        );
+        for not_a_range in ["1", "1..", "a..b", "1..2..0", "1..2..3..4", "--1..2"] {
+            assert_eq!(BraceRange::parse(&WString::from_str(not_a_range)), None);
+        }
+    }
+
     #[test]
     fn test_truncate_at_nul() {
         assert_eq!(truncate_at_nul(L!("abc\0def")), L!("abc"));
@@ -45,7 +45,12 @@ fn unescape_multiline_rust_string(s: String) -> String {
 
 // Each entry is written to a fresh file to avoid race conditions arising when there are multiple
 // unsynchronized writers to the same file.
-fn write_po_entry_to_file(message: &TokenStream, dir: &OsString) {
+fn write_po_entry_to_file(
+    message: &proc_macro2::TokenStream,
+    translator_note: Option<String>,
+    location: Option<String>,
+    dir: &OsString,
+) {
     let message_string = unescape_multiline_rust_string(message.to_string());
     assert!(
         !message_string.contains('\n'),
@@ -65,7 +70,11 @@ fn write_po_entry_to_file(message: &TokenStream, dir: &OsString) {
     } else {
         ""
     };
-    let po_entry = format!("{format_string_annotation}msgid {message_string}\nmsgstr \"\"\n\n");
+    let extracted_comment = translator_note.map_or(String::new(), |note| format!("#. {note}\n"));
+    let reference = location.map_or(String::new(), |location| format!("#: {location}\n"));
+    let po_entry = format!(
+        "{extracted_comment}{reference}{format_string_annotation}msgid {message_string}\nmsgstr \"\"\n\n"
+    );
 
     let dir = PathBuf::from(dir);
     let (path, result) =
@@ -76,11 +85,52 @@ fn write_po_entry_to_file(message: &TokenStream, dir: &OsString) {
     file.write_all(po_entry.as_bytes()).unwrap();
 }
 
+/// The location of `span` as `file:line`, if the compiler provides it.
+/// This requires Rust 1.88 or newer, older versions result in `None`.
+fn source_location(span: proc_macro2::Span) -> Option<String> {
+    let file = span.local_file()?;
+    let line = span.start().line;
+    (line != 0).then(|| format!("{}:{line}", file.display()))
+}
+
+/// The content of a string literal, which is used as a note for translators.
+fn translator_note(token: proc_macro2::TokenTree) -> String {
+    let literal = match token {
+        proc_macro2::TokenTree::Group(group) => {
+            let mut tokens = group.stream().into_iter();
+            let token = tokens
+                .next()
+                .expect("gettext_extract expected a translator note but got none.");
+            assert!(
+                tokens.next().is_none(),
+                "Invalid number of tokens in translator note passed to gettext_extract."
+            );
+            token
+        }
+        token => token,
+    };
+    let proc_macro2::TokenTree::Literal(literal) = literal else {
+        panic!("Expected literal as translator note in gettext_extract, but got: {literal:?}");
+    };
+    let literal = literal.to_string();
+    let note = literal
+        .strip_prefix('"')
+        .and_then(|literal| literal.strip_suffix('"'))
+        .unwrap_or_else(|| panic!("Translator note must be a plain string literal: {literal}"));
+    assert!(
+        !note.contains('\n'),
+        "Translator notes may not contain newlines. Newline found in '{note}'"
+    );
+    note.to_owned()
+}
+
 /// The `message` is passed through unmodified.
 /// If `FISH_GETTEXT_EXTRACTION_DIR` is defined in the environment,
-/// the message ID is written into a new file in this directory,
-/// so that it can then be used for generating gettext PO files.
+/// the message ID is written into a new file in this directory, together with the location it is
+/// used at, so that it can then be used for generating gettext PO files.
 /// The `message` must be a string literal.
+/// It may be followed by a comma and a second string literal, which is a note for translators,
+/// e.g. explaining the context of a short message. The note is not part of the expansion.
 ///
 /// # Panics
 ///
@@ -91,17 +141,26 @@ fn write_po_entry_to_file(message: &TokenStream, dir: &OsString) {
 /// string literal.
 #[proc_macro]
 pub fn gettext_extract(message: TokenStream) -> TokenStream {
+    let pm2_message = proc_macro2::TokenStream::from(message);
+    let mut token_trees = pm2_message.into_iter();
+    let first_token = token_trees
+        .next()
+        .expect("gettext_extract got empty token stream. Expected one token.");
+    let note = match token_trees.next() {
+        None => None,
+        Some(proc_macro2::TokenTree::Punct(punct)) if punct.as_char() == ',' => Some(
+            token_trees
+                .next()
+                .expect("gettext_extract expected a translator note after the comma."),
+        ),
+        Some(token) => panic!("Expected comma in gettext_extract, but got: {token:?}"),
+    };
+    assert!(
+        token_trees.next().is_none(),
+        "Invalid number of tokens passed to gettext_extract. Expected at most two tokens."
+    );
     if let Some(dir_path) = std::env::var_os("FISH_GETTEXT_EXTRACTION_DIR") {
-        let pm2_message = proc_macro2::TokenStream::from(message.clone());
-        let mut token_trees = pm2_message.into_iter();
-        let first_token = token_trees
-            .next()
-            .expect("gettext_extract got empty token stream. Expected one token.");
-        assert!(
-            token_trees.next().is_none(),
-            "Invalid number of tokens passed to gettext_extract. Expected one token, but got more."
-        );
-        let proc_macro2::TokenTree::Group(group) = first_token else {
+        let proc_macro2::TokenTree::Group(group) = &first_token else {
             panic!("Expected group in gettext_extract, but got: {first_token:?}");
         };
         let mut group_tokens = group.stream().into_iter();
@@ -112,11 +171,16 @@ pub fn gettext_extract(message: TokenStream) -> TokenStream {
             group_tokens.next().is_none(),
             "Invalid number of tokens in group passed to gettext_extract. Expected one token, but got more."
         );
-        if let proc_macro2::TokenTree::Literal(_) = first_group_token {
-            write_po_entry_to_file(&message, &dir_path);
-        } else {
+        let proc_macro2::TokenTree::Literal(literal) = first_group_token else {
             panic!("Expected literal in gettext_extract, but got: {first_group_token:?}");
-        }
+        };
+        write_po_entry_to_file(
+            &group.stream(),
+            note.map(translator_note),
+            source_location(literal.span()),
+            &dir_path,
+        );
     }
-    message
+    // The note is only relevant for extraction, so only the message is passed on.
+    TokenStream::from(proc_macro2::TokenStream::from(first_token))
 }
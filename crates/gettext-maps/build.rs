@@ -15,7 +15,10 @@ fn main() {
 }
 
 fn embed_localizations(cache_dir: &Path) {
-    use fish_gettext_mo_file_parser::parse_mo_file;
+    use fish_gettext_mo_file_parser::{
+        CONTEXT_SEPARATOR, PLURAL_SEPARATOR, PluralExpression, parse_mo_file,
+        plural_expression_source,
+    };
     use std::{
         fs::File,
         io::{BufWriter, Write as _},
@@ -32,6 +35,16 @@ fn embed_localizations(cache_dir: &Path) {
     // This will become a map which maps from language identifiers to maps containing localizations
     // for the respective language.
     let mut catalogs = phf_codegen::Map::new();
+    // Maps from language identifiers to the source of the `plural` expression of the language, if
+    // its catalog specifies one.
+    let mut plural_expressions = phf_codegen::Map::new();
+
+    // Cached maps are also outdated if this build script changed, since the format of the
+    // generated code might have changed with it.
+    let build_script_mtime = std::env::current_exe()
+        .and_then(std::fs::metadata)
+        .and_then(|metadata| metadata.modified())
+        .ok();
 
     match Command::new("msgfmt").arg("-h").output() {
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
@@ -62,6 +75,7 @@ fn embed_localizations(cache_dir: &Path) {
                 // Each language gets its own static map for the mapping from message in the source code to
                 // the localized version.
                 let map_name = format!("LANG_MAP_{language}");
+                let plural_name = format!("LANG_PLURAL_{language}");
 
                 let cached_map_path = cache_dir.join(lang);
 
@@ -74,13 +88,16 @@ fn embed_localizations(cache_dir: &Path) {
                 .unwrap();
                 // Map from the language identifier to the map containing the localizations for this
                 // language.
-                catalogs.entry(language, format!("&{map_name}"));
+                catalogs.entry(language.clone(), format!("&{map_name}"));
+                plural_expressions.entry(language, plural_name.clone());
 
                 if let Ok(metadata) = std::fs::metadata(&cached_map_path) {
                     // Cached map file exists, but might be outdated.
                     let cached_map_mtime = metadata.modified().unwrap();
                     let po_mtime = dir_entry.metadata().unwrap().modified().unwrap();
-                    if cached_map_mtime > po_mtime {
+                    if cached_map_mtime > po_mtime
+                        && build_script_mtime.is_none_or(|mtime| cached_map_mtime > mtime)
+                    {
                         // Cached map file is considered up-to-date.
                         continue;
                     }
@@ -116,7 +133,8 @@ fn embed_localizations(cache_dir: &Path) {
                     tmp_mo_file.map_or(output.stdout, |path| std::fs::read(path).unwrap());
 
                 // Extract map from MO data.
-                let language_localizations = parse_mo_file(&mo_data).unwrap();
+                let language_localizations = parse_mo_file(&mo_data)
+                    .unwrap_or_else(|e| panic!("Invalid MO data for {po_file_path:?}: {e}"));
 
                 // This file will contain the localization map for the current language.
                 let mut cached_map_file = File::create(&cached_map_path).unwrap();
@@ -129,12 +147,51 @@ fn embed_localizations(cache_dir: &Path) {
                     assert!(!s.contains("\"###"));
                     format!("r###\"{s}\"###")
                 }
-                for (msgid, msgstr) in language_localizations {
-                    single_language_localization_map.entry(
-                        String::from_utf8(msgid.into()).unwrap(),
-                        to_raw_str(&String::from_utf8(msgstr.into()).unwrap()),
-                    );
+                // Messages with a context are keyed the way gettext does it, by joining the context
+                // and the msgid with an EOT character. Messages without one are keyed by the msgid.
+                for (&(msgctxt, msgid), &msgstr) in &language_localizations {
+                    let mut key = Vec::from(msgctxt);
+                    if !key.is_empty() {
+                        key.push(CONTEXT_SEPARATOR);
+                    }
+                    key.extend_from_slice(msgid);
+                    // Plural forms are separated by NUL, which we keep out of the raw strings.
+                    let forms: Vec<String> = msgstr
+                        .split(|&b| b == PLURAL_SEPARATOR)
+                        .map(|form| to_raw_str(std::str::from_utf8(form).unwrap()))
+                        .collect();
+                    let value = if forms.len() == 1 {
+                        forms.into_iter().next().unwrap()
+                    } else {
+                        format!("concat!({})", forms.join(", \"\\0\", "))
+                    };
+                    single_language_localization_map.entry(String::from_utf8(key).unwrap(), value);
                 }
+
+                // The header is the translation of the empty msgid. Check that the plural
+                // expression in it is valid, so it can be parsed without errors at runtime.
+                let plural_expression =
+                    language_localizations
+                        .get(&(&b""[..], &b""[..]))
+                        .and_then(|header| {
+                            plural_expression_source(std::str::from_utf8(header).unwrap())
+                        });
+                if let Some(source) = plural_expression {
+                    if let Err(e) = PluralExpression::parse(source) {
+                        panic!("Invalid plural expression '{source}' in {po_file_path:?}: {e}");
+                    }
+                }
+                writeln!(&mut cached_map_file, "#[allow(non_upper_case_globals)]").unwrap();
+                writeln!(
+                    &mut cached_map_file,
+                    "const {}: Option<&str> = {};",
+                    plural_name,
+                    plural_expression.map_or("None".to_owned(), |source| format!(
+                        "Some({})",
+                        to_raw_str(source)
+                    ))
+                )
+                .unwrap();
                 writeln!(&mut cached_map_file, "#[allow(non_upper_case_globals)]").unwrap();
                 write!(
                     &mut cached_map_file,
@@ -155,4 +212,11 @@ fn embed_localizations(cache_dir: &Path) {
     )
     .unwrap();
     writeln!(&mut localization_map_file, ";").unwrap();
+    write!(
+        &mut localization_map_file,
+        "pub static PLURAL_EXPRESSIONS: phf::Map<&str, Option<&str>> = {}",
+        plural_expressions.build()
+    )
+    .unwrap();
+    writeln!(&mut localization_map_file, ";").unwrap();
 }
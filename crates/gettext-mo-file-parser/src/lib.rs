@@ -1,7 +1,83 @@
 use std::collections::HashMap;
 
+mod plural;
+pub use plural::{BinaryOp, PluralExpression, plural_expression_source};
+
 const U32_SIZE: usize = size_of::<u32>();
 
+/// Size of the header, up to and including the hash table offset.
+const HEADER_SIZE: usize = 7 * U32_SIZE;
+
+/// Size of an entry of a string table: length and offset of the string.
+const STRING_DESCRIPTOR_SIZE: usize = 2 * U32_SIZE;
+
+/// What is wrong with a MO file.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MoParseErrorKind {
+    /// The file is too short to contain the header.
+    TruncatedHeader,
+    /// The file does not start with the magic number, in either endianness.
+    BadMagicNumber,
+    /// The file has a major revision we do not know, which means we cannot read it.
+    UnsupportedRevision(u32),
+    /// A string table or the hash table extends past the end of the file.
+    TruncatedTable,
+    /// A string extends past the end of the file.
+    TruncatedString,
+}
+
+/// An error found when parsing a MO file.
+/// Errors with an `entry` only affect that message, the rest of the file is still usable.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MoParseError {
+    pub kind: MoParseErrorKind,
+    /// Byte offset in the file of the data which is invalid.
+    pub offset: usize,
+    /// Index of the affected message in the string tables, if the error is limited to one.
+    pub entry: Option<usize>,
+}
+
+impl MoParseError {
+    fn new(kind: MoParseErrorKind, offset: usize) -> Self {
+        Self {
+            kind,
+            offset,
+            entry: None,
+        }
+    }
+}
+
+impl std::fmt::Display for MoParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            MoParseErrorKind::TruncatedHeader => f.write_str("File too short to contain header"),
+            MoParseErrorKind::BadMagicNumber => f.write_str(
+                "First 4 bytes of MO file must correspond to magic number 0x950412de, either big or little endian",
+            ),
+            MoParseErrorKind::UnsupportedRevision(revision) => {
+                write!(f, "Major revision must be 0 or 1, not {}", revision >> 16)
+            }
+            MoParseErrorKind::TruncatedTable => f.write_str("Table extends past the end of the file"),
+            MoParseErrorKind::TruncatedString => {
+                f.write_str("String extends past the end of the file")
+            }
+        }?;
+        write!(f, " (at byte offset {}", self.offset)?;
+        if let Some(entry) = self.entry {
+            write!(f, ", message {entry}")?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl std::error::Error for MoParseError {}
+
+impl From<MoParseError> for std::io::Error {
+    fn from(err: MoParseError) -> Self {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+    }
+}
+
 fn read_le_u32(bytes: &[u8]) -> u32 {
     u32::from_le_bytes(bytes[..U32_SIZE].try_into().unwrap())
 }
@@ -10,40 +86,29 @@ fn read_be_u32(bytes: &[u8]) -> u32 {
     u32::from_be_bytes(bytes[..U32_SIZE].try_into().unwrap())
 }
 
-fn get_u32_reader_from_magic_number(magic_number: &[u8]) -> std::io::Result<fn(&[u8]) -> u32> {
+fn get_u32_reader_from_magic_number(magic_number: &[u8]) -> Option<fn(&[u8]) -> u32> {
     match magic_number {
-        [0x95, 0x04, 0x12, 0xde] => Ok(read_be_u32),
-        [0xde, 0x12, 0x04, 0x95] => Ok(read_le_u32),
-        _ => Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "First 4 bytes of MO file must correspond to magic number 0x950412de, either big or little endian.",
-        )),
+        [0x95, 0x04, 0x12, 0xde] => Some(read_be_u32),
+        [0xde, 0x12, 0x04, 0x95] => Some(read_le_u32),
+        _ => None,
     }
 }
 
-/// Returns an error if an unknown major revision is detected.
+/// Returns false if an unknown major revision is detected.
 /// There are no relevant differences between supported revisions.
-fn check_if_revision_is_supported(revision: u32) -> std::io::Result<()> {
+fn is_revision_supported(revision: u32) -> bool {
     // From the reference:
     // A program seeing an unexpected major revision number should stop reading the MO file entirely;
     // whereas an unexpected minor revision number means that the file can be read
     // but will not reveal its full contents,
     // when parsed by a program that supports only smaller minor revision numbers.
     let major_revision = revision >> 16;
-    match major_revision {
-        0 | 1 => {
-            // At time of writing, these are the only major revisions which exist.
-            // There is no documented difference and the GNU gettext code does not seem to
-            // differentiate between the two either.
-            // All features we care about are supported in minor revision 0,
-            // so we do not need to care about the minor revision.
-            Ok(())
-        }
-        _ => Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "Major revision must be 0 or 1",
-        )),
-    }
+    // At time of writing, 0 and 1 are the only major revisions which exist.
+    // There is no documented difference and the GNU gettext code does not seem to
+    // differentiate between the two either.
+    // All features we care about are supported in minor revision 0,
+    // so we do not need to care about the minor revision.
+    matches!(major_revision, 0 | 1)
 }
 
 fn as_usize(value: u32) -> usize {
@@ -55,80 +120,419 @@ fn as_usize(value: u32) -> usize {
     unsafe { usize::try_from(value).unwrap_unchecked() }
 }
 
-fn parse_strings(
+/// Separates the context (`msgctxt`) from the original string in the original strings table.
+pub const CONTEXT_SEPARATOR: u8 = 0x04;
+
+/// Separates plural forms, both in original strings and in translations.
+pub const PLURAL_SEPARATOR: u8 = 0;
+
+/// Key identifying a message in a MO file: its context and the original string.
+/// Messages without a `msgctxt` have an empty context.
+/// For messages with plural forms, the original string is the singular `msgid`.
+pub type MessageKey<'a> = (&'a [u8], &'a [u8]);
+
+/// Split an entry of the original strings table into its context and the original string,
+/// dropping the `msgid_plural`, if any.
+fn split_context(original: &[u8]) -> MessageKey<'_> {
+    let (context, msgid) = match original.iter().position(|&b| b == CONTEXT_SEPARATOR) {
+        Some(pos) => (&original[..pos], &original[pos + 1..]),
+        None => (&[][..], original),
+    };
+    (context, lookup_part(msgid))
+}
+
+/// The GNU gettext hash function, used for the hash table of MO files.
+fn hashpjw(key: &[u8]) -> u32 {
+    let mut hval: u32 = 0;
+    for &byte in key {
+        hval = (hval << 4).wrapping_add(u32::from(byte));
+        let g = hval & (0xf << 28);
+        if g != 0 {
+            hval ^= g >> 24;
+            hval ^= g;
+        }
+    }
+    hval
+}
+
+/// The part of an original string which is relevant for lookups, which is everything up to the
+/// `msgid_plural`. This is what the C implementation compares, using `strcmp`.
+fn lookup_part(original: &[u8]) -> &[u8] {
+    original.split(|&b| b == PLURAL_SEPARATOR).next().unwrap()
+}
+
+/// Check that a table of `count` elements of `element_size` bytes at `offset` fits into the file.
+fn check_table(
     file_content: &[u8],
-    num_strings: usize,
-    table_offset: usize,
+    offset: usize,
+    count: usize,
+    element_size: usize,
+) -> Result<(), MoParseError> {
+    count
+        .checked_mul(element_size)
+        .and_then(|len| len.checked_add(offset))
+        .filter(|&end| end <= file_content.len())
+        .map(|_| ())
+        .ok_or(MoParseError::new(MoParseErrorKind::TruncatedTable, offset))
+}
+
+/// A MO file, from which messages are read on demand.
+/// Only the header is checked up front. A message whose strings are invalid is reported when it is
+/// read, without affecting the others.
+/// Format reference used: <https://www.gnu.org/software/gettext/manual/html_node/MO-Files.html>
+pub struct MoFile<'a> {
+    file_content: &'a [u8],
     read_u32: fn(&[u8]) -> u32,
-) -> std::io::Result<Vec<&[u8]>> {
-    let file_too_short_error = || {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "MO file is too short.",
-        ))
-    };
-    if table_offset + num_strings * 2 * U32_SIZE > file_content.len() {
-        return file_too_short_error();
-    }
-    let mut strings = Vec::with_capacity(num_strings);
-    let mut offset = table_offset;
-    let mut get_next_u32 = || {
-        let val = read_u32(&file_content[offset..]);
-        offset += U32_SIZE;
-        val
-    };
-    for _ in 0..num_strings {
-        // not including NUL terminator
-        let string_length = as_usize(get_next_u32());
-        let string_offset = as_usize(get_next_u32());
-        let string_end = string_offset.checked_add(string_length).unwrap();
-        if string_end > file_content.len() {
-            return file_too_short_error();
+    num_strings: usize,
+    original_strings_offset: usize,
+    translation_strings_offset: usize,
+    /// Number of slots of the hash table, or zero if the file has no usable hash table.
+    /// Slots are indices into the string tables plus one, zero marks an empty slot.
+    hash_table_size: usize,
+    hash_table_offset: usize,
+}
+
+impl<'a> MoFile<'a> {
+    /// Parse the header of a MO file, in either endianness.
+    pub fn parse(file_content: &'a [u8]) -> Result<Self, MoParseError> {
+        if file_content.len() < HEADER_SIZE {
+            return Err(MoParseError::new(MoParseErrorKind::TruncatedHeader, 0));
+        }
+        // The first 4 bytes are a magic number, from which the endianness can be determined.
+        let read_u32 = get_u32_reader_from_magic_number(&file_content[0..U32_SIZE])
+            .ok_or(MoParseError::new(MoParseErrorKind::BadMagicNumber, 0))?;
+        let mut offset = U32_SIZE;
+        let mut get_next_u32 = || {
+            let val = read_u32(&file_content[offset..]);
+            offset += U32_SIZE;
+            val
+        };
+        let file_format_revision = get_next_u32();
+        if !is_revision_supported(file_format_revision) {
+            return Err(MoParseError::new(
+                MoParseErrorKind::UnsupportedRevision(file_format_revision),
+                U32_SIZE,
+            ));
+        }
+        let num_strings = as_usize(get_next_u32());
+        let original_strings_offset = as_usize(get_next_u32());
+        let translation_strings_offset = as_usize(get_next_u32());
+        let mut hash_table_size = as_usize(get_next_u32());
+        let hash_table_offset = as_usize(get_next_u32());
+        for table_offset in [original_strings_offset, translation_strings_offset] {
+            check_table(
+                file_content,
+                table_offset,
+                num_strings,
+                STRING_DESCRIPTOR_SIZE,
+            )?;
         }
+        // The lookup algorithm needs at least 3 slots. Smaller tables are ignored, in which case
+        // we fall back to binary search.
+        if hash_table_size < 3 {
+            hash_table_size = 0;
+        }
+        check_table(file_content, hash_table_offset, hash_table_size, U32_SIZE)?;
+        Ok(Self {
+            file_content,
+            read_u32,
+            num_strings,
+            original_strings_offset,
+            translation_strings_offset,
+            hash_table_size,
+            hash_table_offset,
+        })
+    }
+
+    /// The number of messages in the file, including the header entry.
+    pub fn len(&self) -> usize {
+        self.num_strings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_strings == 0
+    }
+
+    /// Read the string with the given index from the string table at `table_offset`.
+    fn string(&self, table_offset: usize, index: usize) -> Result<&'a [u8], MoParseError> {
+        let descriptor_offset = table_offset + index * STRING_DESCRIPTOR_SIZE;
+        let descriptor = &self.file_content[descriptor_offset..];
+        // not including NUL terminator
+        let string_length = as_usize((self.read_u32)(descriptor));
+        let string_offset = as_usize((self.read_u32)(&descriptor[U32_SIZE..]));
         // Contexts are stored by storing the concatenation of the context, a EOT byte, and the original string, instead of the original string.
-        // Contexts are not supported by this implementation.
+        // They are split off in `split_context`.
         // The format allows plural forms to appear behind singular forms, separated by a NUL byte,
         // where `string_length` includes the length of both.
-        // This is not supported here.
+        // For original strings, the plural is dropped in `split_context`. Translations keep all
+        // their forms.
         // Do not include the NUL terminator in the slice.
-        strings.push(&file_content[string_offset..string_end]);
+        string_offset
+            .checked_add(string_length)
+            .filter(|&end| end <= self.file_content.len())
+            .map(|end| &self.file_content[string_offset..end])
+            .ok_or(MoParseError {
+                kind: MoParseErrorKind::TruncatedString,
+                offset: descriptor_offset,
+                entry: Some(index),
+            })
+    }
+
+    fn original(&self, index: usize) -> Result<&'a [u8], MoParseError> {
+        self.string(self.original_strings_offset, index)
+    }
+
+    fn translation(&self, index: usize) -> Result<&'a [u8], MoParseError> {
+        self.string(self.translation_strings_offset, index)
+    }
+
+    /// Look up the translation of `msgid` in the given context, which is empty for messages
+    /// without a `msgctxt`. For messages with plural forms, `msgid` is the singular.
+    /// Translations with plural forms contain all forms, separated by [`PLURAL_SEPARATOR`].
+    /// Invalid messages are treated as missing.
+    pub fn get(&self, context: &[u8], msgid: &[u8]) -> Option<&'a [u8]> {
+        let key = if context.is_empty() {
+            msgid.to_vec()
+        } else {
+            [context, &[CONTEXT_SEPARATOR], msgid].concat()
+        };
+        let index = if self.hash_table_size == 0 {
+            self.binary_search(&key)?
+        } else {
+            self.hash_lookup(&key)?
+        };
+        self.translation(index).ok()
+    }
+
+    /// Find the index of `key`, relying on original strings being sorted, as the format requires.
+    fn binary_search(&self, key: &[u8]) -> Option<usize> {
+        let (mut low, mut high) = (0, self.num_strings);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match lookup_part(self.original(mid).ok()?).cmp(key) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    /// Find the index of `key` using double hashing, like GNU gettext does.
+    fn hash_lookup(&self, key: &[u8]) -> Option<usize> {
+        let size = u32::try_from(self.hash_table_size).ok()?;
+        let hval = hashpjw(key);
+        let mut idx = hval % size;
+        let incr = 1 + (hval % (size - 2));
+        // Give up after visiting every slot once, in case of a table without empty slots.
+        for _ in 0..size {
+            let slot_offset = self.hash_table_offset + as_usize(idx) * U32_SIZE;
+            let entry = (self.read_u32)(&self.file_content[slot_offset..]);
+            if entry == 0 {
+                return None;
+            }
+            let index = as_usize(entry - 1);
+            if index < self.num_strings && lookup_part(self.original(index).ok()?) == key {
+                return Some(index);
+            }
+            idx = if idx >= size - incr {
+                idx - (size - incr)
+            } else {
+                idx + incr
+            };
+        }
+        None
+    }
+
+    /// Iterate over all messages, keyed by `(context, msgid)`, see [`MessageKey`].
+    /// Invalid messages produce an error, after which iteration continues with the next message.
+    pub fn entries(
+        &self,
+    ) -> impl Iterator<Item = Result<(MessageKey<'a>, &'a [u8]), MoParseError>> + '_ {
+        (0..self.num_strings).map(|index| {
+            let original = self.original(index)?;
+            let translation = self.translation(index)?;
+            Ok((split_context(original), translation))
+        })
     }
-    Ok(strings)
 }
 
-/// Parse a MO file.
-/// Format reference used: <https://www.gnu.org/software/gettext/manual/html_node/MO-Files.html>
-pub fn parse_mo_file(file_content: &[u8]) -> std::io::Result<HashMap<&[u8], &[u8]>> {
-    if file_content.len() < 7 * U32_SIZE {
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::InvalidData,
-            "File too short to contain header.",
-        ));
-    }
-    // The first 4 bytes are a magic number, from which the endianness can be determined.
-    let read_u32 = get_u32_reader_from_magic_number(&file_content[0..U32_SIZE])?;
-    let mut offset = U32_SIZE;
-    let mut get_next_u32 = || {
-        let val = read_u32(&file_content[offset..]);
-        offset += U32_SIZE;
-        val
-    };
-    let file_format_revision = get_next_u32();
-    check_if_revision_is_supported(file_format_revision)?;
-    let num_strings = as_usize(get_next_u32());
-    let original_strings_offset = as_usize(get_next_u32());
-    let translation_strings_offset = as_usize(get_next_u32());
-    let original_strings =
-        parse_strings(file_content, num_strings, original_strings_offset, read_u32)?;
-    let translated_strings = parse_strings(
-        file_content,
-        num_strings,
-        translation_strings_offset,
-        read_u32,
-    )?;
-    let mut translation_map = HashMap::with_capacity(num_strings);
-    for i in 0..num_strings {
-        translation_map.insert(original_strings[i], translated_strings[i]);
-    }
-    Ok(translation_map)
+/// Parse a MO file into a map of all its messages, failing if any of them is invalid.
+/// The returned map is keyed by `(context, msgid)`, see [`MessageKey`].
+/// Translations with plural forms contain all forms, separated by [`PLURAL_SEPARATOR`].
+/// Which form to use is determined by the `Plural-Forms` header, see [`plural_expression_source`].
+pub fn parse_mo_file(file_content: &[u8]) -> Result<HashMap<MessageKey<'_>, &[u8]>, MoParseError> {
+    MoFile::parse(file_content)?.entries().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MoFile, hashpjw, lookup_part, parse_mo_file};
+
+    /// Build a MO file from pairs of original and translated strings, which must be sorted.
+    /// If `hash_table_size` is nonzero, a hash table of that size is included.
+    fn build_mo_file(
+        entries: &[(&[u8], &[u8])],
+        to_bytes: fn(u32) -> [u8; 4],
+        hash_table_size: u32,
+    ) -> Vec<u8> {
+        let num_strings = u32::try_from(entries.len()).unwrap();
+        let header_len = 7 * 4;
+        let originals_offset = header_len;
+        let translations_offset = originals_offset + entries.len() * 8;
+        let hash_table_offset = translations_offset + entries.len() * 8;
+        let mut string_offset = hash_table_offset + usize::try_from(hash_table_size).unwrap() * 4;
+        let mut header = vec![];
+        for val in [
+            0x950412de,
+            0,
+            num_strings,
+            u32::try_from(originals_offset).unwrap(),
+            u32::try_from(translations_offset).unwrap(),
+            hash_table_size,
+            u32::try_from(hash_table_offset).unwrap(),
+        ] {
+            header.extend_from_slice(&to_bytes(val));
+        }
+        let mut tables = vec![];
+        let mut strings = vec![];
+        for column in [0, 1] {
+            for entry in entries {
+                let s = if column == 0 { entry.0 } else { entry.1 };
+                tables.extend_from_slice(&to_bytes(u32::try_from(s.len()).unwrap()));
+                tables.extend_from_slice(&to_bytes(u32::try_from(string_offset).unwrap()));
+                strings.extend_from_slice(s);
+                strings.push(0);
+                string_offset += s.len() + 1;
+            }
+        }
+        // Insert the entries with the same probing sequence the lookup uses.
+        let mut hash_table = vec![0; usize::try_from(hash_table_size).unwrap()];
+        if hash_table_size != 0 {
+            for (i, entry) in entries.iter().enumerate() {
+                let hval = hashpjw(lookup_part(entry.0));
+                let mut idx = hval % hash_table_size;
+                let incr = 1 + (hval % (hash_table_size - 2));
+                while hash_table[usize::try_from(idx).unwrap()] != 0 {
+                    idx = (idx + incr) % hash_table_size;
+                }
+                hash_table[usize::try_from(idx).unwrap()] = u32::try_from(i + 1).unwrap();
+            }
+        }
+        let hash_table = hash_table.into_iter().flat_map(to_bytes).collect();
+        [header, tables, hash_table, strings].concat()
+    }
+
+    #[test]
+    fn test_context() {
+        let data = build_mo_file(
+            &[(b"Set", b"Festlegen"), (b"noun\x04Set", b"Menge")],
+            u32::to_le_bytes,
+            0,
+        );
+        let map = parse_mo_file(&data).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(&(&b""[..], &b"Set"[..])), Some(&&b"Festlegen"[..]));
+        assert_eq!(map.get(&(&b"noun"[..], &b"Set"[..])), Some(&&b"Menge"[..]));
+        assert_eq!(map.get(&(&b"verb"[..], &b"Set"[..])), None);
+    }
+
+    #[test]
+    fn test_plural() {
+        let data = build_mo_file(
+            &[(b"%d job\0%d jobs", b"%d Job\0%d Jobs")],
+            u32::to_le_bytes,
+            0,
+        );
+        let map = parse_mo_file(&data).unwrap();
+        assert_eq!(
+            map.get(&(&b""[..], &b"%d job"[..])),
+            Some(&&b"%d Job\0%d Jobs"[..])
+        );
+    }
+
+    #[test]
+    fn test_lookup() {
+        let entries: &[(&[u8], &[u8])] = &[
+            (b"", b"Plural-Forms: nplurals=2; plural=(n != 1);\n"),
+            (b"%d job\0%d jobs", b"%d Job\0%d Jobs"),
+            (b"Set", b"Festlegen"),
+            (b"hello", b"hallo"),
+            (b"noun\x04Set", b"Menge"),
+        ];
+        for to_bytes in [u32::to_le_bytes, u32::to_be_bytes] {
+            // Without a hash table, with a hash table, and with one that is completely full.
+            for hash_table_size in [0, 7, 5] {
+                let data = build_mo_file(entries, to_bytes, hash_table_size);
+                let mo = MoFile::parse(&data).unwrap();
+                assert_eq!(mo.get(b"", b"hello"), Some(&b"hallo"[..]));
+                assert_eq!(mo.get(b"", b"Set"), Some(&b"Festlegen"[..]));
+                assert_eq!(mo.get(b"noun", b"Set"), Some(&b"Menge"[..]));
+                assert_eq!(mo.get(b"", b"%d job"), Some(&b"%d Job\0%d Jobs"[..]));
+                assert_eq!(mo.get(b"", b"%d jobs"), None);
+                assert_eq!(mo.get(b"verb", b"Set"), None);
+                assert_eq!(mo.get(b"", b"goodbye"), None);
+                assert_eq!(mo.entries().filter(Result::is_ok).count(), entries.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        use super::{MoParseError, MoParseErrorKind};
+
+        let err = |kind, offset| MoParseError {
+            kind,
+            offset,
+            entry: None,
+        };
+        assert_eq!(
+            MoFile::parse(b"").err(),
+            Some(err(MoParseErrorKind::TruncatedHeader, 0))
+        );
+        let data = build_mo_file(&[(b"hello", b"hallo")], u32::to_le_bytes, 0);
+        let mut bad_magic = data.clone();
+        bad_magic[0] = 0;
+        assert_eq!(
+            MoFile::parse(&bad_magic).err(),
+            Some(err(MoParseErrorKind::BadMagicNumber, 0))
+        );
+        let mut bad_revision = data.clone();
+        bad_revision[6] = 2;
+        assert_eq!(
+            MoFile::parse(&bad_revision).err(),
+            Some(err(MoParseErrorKind::UnsupportedRevision(2 << 16), 4))
+        );
+        assert_eq!(
+            MoFile::parse(&data[..40]).err(),
+            Some(err(MoParseErrorKind::TruncatedTable, 36))
+        );
+        assert!(parse_mo_file(&data[..data.len() - 3]).is_err());
+    }
+
+    #[test]
+    fn test_skip_invalid_entry() {
+        use super::{MoParseError, MoParseErrorKind};
+
+        let mut data = build_mo_file(
+            &[(b"bad", b"schlecht"), (b"good", b"gut")],
+            u32::to_le_bytes,
+            0,
+        );
+        // Point the length of the first translation past the end of the file.
+        data[44..48].copy_from_slice(&u32::to_le_bytes(1000));
+        let mo = MoFile::parse(&data).unwrap();
+        let entries: Vec<_> = mo.entries().collect();
+        assert_eq!(
+            entries[0],
+            Err(MoParseError {
+                kind: MoParseErrorKind::TruncatedString,
+                offset: 44,
+                entry: Some(0),
+            })
+        );
+        assert_eq!(entries[1], Ok(((&b""[..], &b"good"[..]), &b"gut"[..])));
+        assert_eq!(mo.get(b"", b"good"), Some(&b"gut"[..]));
+        assert_eq!(mo.get(b"", b"bad"), None);
+    }
 }
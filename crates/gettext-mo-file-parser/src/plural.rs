@@ -0,0 +1,297 @@
+//! Support for plural forms.
+//! Which plural form to use for a count `n` is determined by the `plural` expression in the
+//! `Plural-Forms` header of a catalog, e.g. `Plural-Forms: nplurals=2; plural=(n != 1);`.
+//! The expression uses a subset of C syntax, which is parsed here.
+
+fn invalid_data(msg: &'static str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+/// Extract the source of the `plural` expression from the header of a catalog, which is the
+/// translation of the empty msgid.
+pub fn plural_expression_source(header: &str) -> Option<&str> {
+    let plural_forms = header
+        .lines()
+        .find_map(|line| line.strip_prefix("Plural-Forms:"))?;
+    plural_forms.split(';').find_map(|field| {
+        let (key, value) = field.split_once('=')?;
+        (key.trim() == "plural").then(|| value.trim())
+    })
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    fn apply(self, lhs: u64, rhs: u64) -> u64 {
+        match self {
+            BinaryOp::Or => u64::from(lhs != 0 || rhs != 0),
+            BinaryOp::And => u64::from(lhs != 0 && rhs != 0),
+            BinaryOp::Eq => u64::from(lhs == rhs),
+            BinaryOp::Ne => u64::from(lhs != rhs),
+            BinaryOp::Lt => u64::from(lhs < rhs),
+            BinaryOp::Le => u64::from(lhs <= rhs),
+            BinaryOp::Gt => u64::from(lhs > rhs),
+            BinaryOp::Ge => u64::from(lhs >= rhs),
+            BinaryOp::Add => lhs.wrapping_add(rhs),
+            BinaryOp::Sub => lhs.wrapping_sub(rhs),
+            BinaryOp::Mul => lhs.wrapping_mul(rhs),
+            // GNU gettext would crash on division by zero, we pick the first form instead.
+            BinaryOp::Div => lhs.checked_div(rhs).unwrap_or(0),
+            BinaryOp::Rem => lhs.checked_rem(rhs).unwrap_or(0),
+        }
+    }
+}
+
+/// A parsed `plural` expression, which maps a count `n` to the index of a plural form.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PluralExpression {
+    N,
+    Number(u64),
+    Not(Box<PluralExpression>),
+    Binary(BinaryOp, Box<PluralExpression>, Box<PluralExpression>),
+    Conditional(
+        Box<PluralExpression>,
+        Box<PluralExpression>,
+        Box<PluralExpression>,
+    ),
+}
+
+impl PluralExpression {
+    /// Parse the source of a `plural` expression, as returned by [`plural_expression_source`].
+    pub fn parse(source: &str) -> std::io::Result<Self> {
+        let mut parser = Parser {
+            input: source.as_bytes(),
+            pos: 0,
+        };
+        let expr = parser.parse_conditional()?;
+        parser.skip_whitespace();
+        if parser.pos != parser.input.len() {
+            return Err(invalid_data("Trailing characters in plural expression."));
+        }
+        Ok(expr)
+    }
+
+    /// Compute the index of the plural form to use for `n`.
+    pub fn evaluate(&self, n: u64) -> u64 {
+        match self {
+            PluralExpression::N => n,
+            PluralExpression::Number(value) => *value,
+            PluralExpression::Not(expr) => u64::from(expr.evaluate(n) == 0),
+            PluralExpression::Binary(op, lhs, rhs) => op.apply(lhs.evaluate(n), rhs.evaluate(n)),
+            PluralExpression::Conditional(cond, then, otherwise) => {
+                if cond.evaluate(n) != 0 {
+                    then.evaluate(n)
+                } else {
+                    otherwise.evaluate(n)
+                }
+            }
+        }
+    }
+}
+
+/// Recursive descent parser following C operator precedence.
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .input
+            .get(self.pos)
+            .is_some_and(u8::is_ascii_whitespace)
+        {
+            self.pos += 1;
+        }
+    }
+
+    /// Consume `token` if it comes next.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with(token.as_bytes()) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parse a left-associative chain of binary operators, each operand parsed by `operand`.
+    fn parse_binary(
+        &mut self,
+        ops: &[(&str, BinaryOp)],
+        operand: fn(&mut Self) -> std::io::Result<PluralExpression>,
+    ) -> std::io::Result<PluralExpression> {
+        let mut lhs = operand(self)?;
+        'outer: loop {
+            for &(token, op) in ops {
+                if self.eat(token) {
+                    let rhs = operand(self)?;
+                    lhs = PluralExpression::Binary(op, Box::new(lhs), Box::new(rhs));
+                    continue 'outer;
+                }
+            }
+            return Ok(lhs);
+        }
+    }
+
+    fn parse_conditional(&mut self) -> std::io::Result<PluralExpression> {
+        let cond = self.parse_or()?;
+        if !self.eat("?") {
+            return Ok(cond);
+        }
+        let then = self.parse_conditional()?;
+        if !self.eat(":") {
+            return Err(invalid_data("Expected ':' in plural expression."));
+        }
+        let otherwise = self.parse_conditional()?;
+        Ok(PluralExpression::Conditional(
+            Box::new(cond),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn parse_or(&mut self) -> std::io::Result<PluralExpression> {
+        self.parse_binary(&[("||", BinaryOp::Or)], Self::parse_and)
+    }
+
+    fn parse_and(&mut self) -> std::io::Result<PluralExpression> {
+        self.parse_binary(&[("&&", BinaryOp::And)], Self::parse_equality)
+    }
+
+    fn parse_equality(&mut self) -> std::io::Result<PluralExpression> {
+        self.parse_binary(
+            &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne)],
+            Self::parse_relational,
+        )
+    }
+
+    fn parse_relational(&mut self) -> std::io::Result<PluralExpression> {
+        // Longer tokens first, so that "<=" is not read as "<".
+        self.parse_binary(
+            &[
+                ("<=", BinaryOp::Le),
+                (">=", BinaryOp::Ge),
+                ("<", BinaryOp::Lt),
+                (">", BinaryOp::Gt),
+            ],
+            Self::parse_additive,
+        )
+    }
+
+    fn parse_additive(&mut self) -> std::io::Result<PluralExpression> {
+        self.parse_binary(
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            Self::parse_multiplicative,
+        )
+    }
+
+    fn parse_multiplicative(&mut self) -> std::io::Result<PluralExpression> {
+        self.parse_binary(
+            &[
+                ("*", BinaryOp::Mul),
+                ("/", BinaryOp::Div),
+                ("%", BinaryOp::Rem),
+            ],
+            Self::parse_unary,
+        )
+    }
+
+    fn parse_unary(&mut self) -> std::io::Result<PluralExpression> {
+        // Make sure "!=" is not mistaken for a negation.
+        self.skip_whitespace();
+        if self.input[self.pos..].starts_with(b"!") && !self.input[self.pos..].starts_with(b"!=") {
+            self.pos += 1;
+            let expr = self.parse_unary()?;
+            return Ok(PluralExpression::Not(Box::new(expr)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> std::io::Result<PluralExpression> {
+        if self.eat("(") {
+            let expr = self.parse_conditional()?;
+            if !self.eat(")") {
+                return Err(invalid_data("Expected ')' in plural expression."));
+            }
+            return Ok(expr);
+        }
+        if self.eat("n") {
+            return Ok(PluralExpression::N);
+        }
+        let start = self.pos;
+        while self.input.get(self.pos).is_some_and(u8::is_ascii_digit) {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(invalid_data("Unexpected token in plural expression."));
+        }
+        std::str::from_utf8(&self.input[start..self.pos])
+            .unwrap()
+            .parse()
+            .map(PluralExpression::Number)
+            .map_err(|_| invalid_data("Number out of range in plural expression."))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PluralExpression, plural_expression_source};
+
+    #[test]
+    fn test_plural_expression_source() {
+        let header = "Content-Type: text/plain; charset=UTF-8\n\
+                      Plural-Forms: nplurals=2; plural=(n != 1);\n";
+        assert_eq!(plural_expression_source(header), Some("(n != 1)"));
+        assert_eq!(
+            plural_expression_source("Plural-Forms: nnplurals=1; plural=0;\n"),
+            Some("0")
+        );
+        assert_eq!(plural_expression_source("Language: de\n"), None);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let eval = |source: &str, n: u64| PluralExpression::parse(source).unwrap().evaluate(n);
+        assert_eq!(eval("0", 5), 0);
+        assert_eq!(eval("(n != 1)", 1), 0);
+        assert_eq!(eval("(n != 1)", 0), 1);
+        assert_eq!(eval("(n > 1)", 1), 0);
+        assert_eq!(eval("!(n > 1)", 1), 1);
+
+        // Polish.
+        let polish = "(n==1 ? 0 : n%10>=2 && n%10<=4 && (n%100<10 || n%100>=20) ? 1 : 2)";
+        let forms: Vec<u64> = [1, 2, 4, 5, 12, 22, 25, 112]
+            .into_iter()
+            .map(|n| eval(polish, n))
+            .collect();
+        assert_eq!(forms, [0, 1, 1, 2, 2, 1, 2, 2]);
+
+        assert_eq!(eval("n / 0", 3), 0);
+        assert_eq!(eval("1 + 2 * 3 - 4", 0), 3);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for source in ["", "n ?", "n ? 1", "(n", "n != ", "x", "n n"] {
+            assert!(PluralExpression::parse(source).is_err(), "{source}");
+        }
+    }
+}
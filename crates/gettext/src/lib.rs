@@ -1,25 +1,170 @@
-use fish_gettext_maps::CATALOGS;
+use fish_gettext_maps::{CATALOGS, PLURAL_EXPRESSIONS};
+use fish_gettext_mo_file_parser::{MoFile, PluralExpression, plural_expression_source};
 use std::{
     collections::HashMap,
-    sync::{LazyLock, Mutex},
+    path::PathBuf,
+    sync::{LazyLock, Mutex, OnceLock},
 };
 
-type Catalog = &'static phf::Map<&'static str, &'static str>;
+/// A catalog installed as a MO file, for a language fish has no built-in catalog for.
+struct InstalledCatalog {
+    mo_file: MoFile<'static>,
+    plural_expression: Option<PluralExpression>,
+}
+
+#[derive(Clone, Copy)]
+enum Catalog {
+    BuiltIn(&'static phf::Map<&'static str, &'static str>),
+    Installed(&'static InstalledCatalog),
+}
+
+impl Catalog {
+    /// Look up a message, using the key format of the generated maps.
+    fn get(self, key: &str) -> Option<&'static str> {
+        match self {
+            Catalog::BuiltIn(map) => map.get(key).copied(),
+            // The key already has the context in front, the way MO files store it.
+            Catalog::Installed(catalog) => {
+                std::str::from_utf8(catalog.mo_file.get(b"", key.as_bytes())?).ok()
+            }
+        }
+    }
+
+    fn plural_expression(self, language: &str) -> Option<&'static PluralExpression> {
+        match self {
+            Catalog::BuiltIn(_) => PLURAL_EXPRESSION_MAP.get(language),
+            Catalog::Installed(catalog) => catalog.plural_expression.as_ref(),
+        }
+    }
+}
 
 static LANGUAGE_PRECEDENCE: Mutex<Vec<(&'static str, Catalog)>> = Mutex::new(Vec::new());
 
-pub fn gettext(message_str: &'static str) -> Option<&'static str> {
+/// The directory in which MO files are installed, as `<language>/LC_MESSAGES/fish.mo`.
+static INSTALLED_CATALOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Set the directory in which to look for installed MO files, as `<language>/LC_MESSAGES/fish.mo`.
+/// These are used for languages fish has no built-in catalog for, e.g. because it was built
+/// without gettext. This only has an effect before the available languages are first queried.
+pub fn set_installed_catalog_dir(dir: PathBuf) {
+    let _ = INSTALLED_CATALOG_DIR.set(dir);
+}
+
+fn installed_catalog_path(language: &str) -> Option<PathBuf> {
+    let path = INSTALLED_CATALOG_DIR
+        .get()?
+        .join(language)
+        .join("LC_MESSAGES")
+        .join("fish.mo");
+    Some(path)
+}
+
+/// The installed catalogs which have been loaded, or failed to load, so far.
+static INSTALLED_CATALOGS: Mutex<Option<HashMap<&'static str, Option<Catalog>>>> = Mutex::new(None);
+
+/// Load the installed catalog of a language. Its file is read only once and kept for the rest of
+/// the process's lifetime. Returns None if it can't be read or is not a valid MO file.
+fn installed_catalog(language: &'static str) -> Option<Catalog> {
+    let mut installed_catalogs = INSTALLED_CATALOGS.lock().unwrap();
+    *installed_catalogs
+        .get_or_insert_with(HashMap::new)
+        .entry(language)
+        .or_insert_with(|| {
+            let file_content = std::fs::read(installed_catalog_path(language)?).ok()?;
+            let mo_file = MoFile::parse(Vec::leak(file_content)).ok()?;
+            // The header is the translation of the empty string.
+            let plural_expression = mo_file
+                .get(b"", b"")
+                .and_then(|header| std::str::from_utf8(header).ok())
+                .and_then(plural_expression_source)
+                .and_then(|source| PluralExpression::parse(source).ok());
+            let catalog = Box::leak(Box::new(InstalledCatalog {
+                mo_file,
+                plural_expression,
+            }));
+            Some(Catalog::Installed(catalog))
+        })
+}
+
+/// Look up a message in the catalogs, using the key format of the generated maps.
+/// Returns the language whose catalog provides the localization, and the localization.
+/// For messages with plural forms, this returns the first form.
+fn lookup_with_language(key: &str) -> Option<(&'static str, &'static str)> {
     let language_precedence = LANGUAGE_PRECEDENCE.lock().unwrap();
 
     // Use the localization from the highest-precedence language that has one available.
-    for (_, catalog) in language_precedence.iter() {
-        if let Some(localized_str) = catalog.get(message_str) {
-            return Some(localized_str);
+    for &(language, catalog) in language_precedence.iter() {
+        if let Some(localized_str) = catalog.get(key) {
+            return localized_str
+                .split('\0')
+                .next()
+                .map(|localized_str| (language, localized_str));
         }
     }
     None
 }
 
+fn lookup(key: &str) -> Option<&'static str> {
+    lookup_with_language(key).map(|(_, localized_str)| localized_str)
+}
+
+/// The parsed plural expressions of all languages whose catalog has one.
+static PLURAL_EXPRESSION_MAP: LazyLock<HashMap<&'static str, PluralExpression>> =
+    LazyLock::new(|| {
+        PLURAL_EXPRESSIONS
+            .entries()
+            .filter_map(|(&language, source)| {
+                // The build script made sure that this parses.
+                Some((language, PluralExpression::parse((*source)?).unwrap()))
+            })
+            .collect()
+    });
+
+/// Look up the localization of a message which has no `msgctxt`.
+pub fn gettext(message_str: &'static str) -> Option<&'static str> {
+    lookup(message_str)
+}
+
+/// Look up the localization of a message with plural forms, choosing the form which is
+/// grammatically correct for the count `n`. `message_str` is the singular `msgid`.
+/// Languages without a `Plural-Forms` header are assumed to use the singular only for 1.
+pub fn lookup_plural(message_str: &str, n: u64) -> Option<&'static str> {
+    let language_precedence = LANGUAGE_PRECEDENCE.lock().unwrap();
+
+    for &(language, catalog) in language_precedence.iter() {
+        let Some(forms) = catalog.get(message_str) else {
+            continue;
+        };
+        let index = catalog
+            .plural_expression(language)
+            .map_or(u64::from(n != 1), |expr| expr.evaluate(n));
+        // A catalog which lacks the computed form is treated as not having a localization.
+        if let Some(form) = usize::try_from(index)
+            .ok()
+            .and_then(|index| forms.split('\0').nth(index))
+        {
+            return Some(form);
+        }
+    }
+    None
+}
+
+/// Look up the localization of a message with the given `msgctxt`.
+/// An empty context is the same as no context.
+pub fn pgettext(context: &str, message_str: &str) -> Option<&'static str> {
+    if context.is_empty() {
+        return lookup(message_str);
+    }
+    // Keep in sync with the key format in the build script of `fish_gettext_maps`.
+    lookup(&format!("{context}\u{4}{message_str}"))
+}
+
+/// The language of the catalog which provides the localization of a message without `msgctxt`,
+/// given the current language precedence.
+pub fn localizing_language(message_str: &str) -> Option<&'static str> {
+    lookup_with_language(message_str).map(|(language, _)| language)
+}
+
 #[derive(Clone, Copy)]
 pub struct GettextLocalizationLanguage {
     language: &'static str,
@@ -27,10 +172,23 @@ pub struct GettextLocalizationLanguage {
 
 static AVAILABLE_LANGUAGES: LazyLock<HashMap<&'static str, GettextLocalizationLanguage>> =
     LazyLock::new(|| {
+        let built_in = CATALOGS.entries().map(|(&language, _)| language);
+        // Only check whether installed catalogs exist, they are read once they are used.
+        let installed = INSTALLED_CATALOG_DIR
+            .get()
+            .and_then(|dir| dir.read_dir().ok())
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter(|language| {
+                !CATALOGS.contains_key(language.as_str())
+                    && installed_catalog_path(language).is_some_and(|path| path.is_file())
+            })
+            .map(|language| &*String::leak(language));
         HashMap::from_iter(
-            CATALOGS
-                .entries()
-                .map(|(&language, _)| (language, GettextLocalizationLanguage { language })),
+            built_in
+                .chain(installed)
+                .map(|language| (language, GettextLocalizationLanguage { language })),
         )
     });
 
@@ -41,13 +199,13 @@ pub fn get_available_languages() -> &'static HashMap<&'static str, GettextLocali
 pub fn set_language_precedence(new_precedence: &[GettextLocalizationLanguage]) {
     let catalogs = new_precedence
         .iter()
-        .map(|lang| {
-            (
-                lang.language,
-                *CATALOGS
-                    .get(lang.language)
-                    .expect("Only languages for which catalogs exist may be passed to gettext."),
-            )
+        .filter_map(|lang| {
+            let catalog = match CATALOGS.get(lang.language) {
+                Some(&map) => Catalog::BuiltIn(map),
+                // Installed catalogs which turn out to be invalid are skipped.
+                None => installed_catalog(lang.language)?,
+            };
+            Some((lang.language, catalog))
         })
         .collect();
     *LANGUAGE_PRECEDENCE.lock().unwrap() = catalogs;
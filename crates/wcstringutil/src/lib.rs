@@ -110,6 +110,81 @@ pub fn subsequence_in_string(needle: &wstr, haystack: &wstr) -> bool {
     needle_it.peek().is_none()
 }
 
+/// Score how well `needle` matches a case-insensitive subsequence of `haystack`, in the manner of
+/// fuzzy finders: matches at the start of the haystack or of a word, and runs of consecutive
+/// characters score higher, while gaps between matched characters score lower. Of all the ways the
+/// needle can be matched, the best one is scored.
+/// Returns None if `needle` is not a case-insensitive subsequence of `haystack`.
+pub fn subsequence_score(needle: &wstr, haystack: &wstr) -> Option<u32> {
+    const SCORE_MATCH: i64 = 16;
+    const SCORE_SAME_CASE: i64 = 1;
+    const BONUS_START: i64 = 24;
+    const BONUS_WORD_START: i64 = 16;
+    const BONUS_CAMEL_CASE: i64 = 12;
+    const BONUS_CONSECUTIVE: i64 = 8;
+    const PENALTY_GAP_START: i64 = 3;
+    const PENALTY_GAP_EXTENSION: i64 = 1;
+    // Marks positions where the needle so far cannot end.
+    const IMPOSSIBLE: i64 = i64::MIN / 2;
+
+    let needle = needle.as_char_slice();
+    let haystack = haystack.as_char_slice();
+    if needle.is_empty() {
+        return Some(0);
+    }
+    if needle.len() > haystack.len() {
+        return None;
+    }
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let score_at = |n: char, j: usize| -> i64 {
+        let h = haystack[j];
+        if fold(n) != fold(h) {
+            return IMPOSSIBLE;
+        }
+        let mut score = SCORE_MATCH;
+        if n == h {
+            score += SCORE_SAME_CASE;
+        }
+        if j == 0 {
+            score += BONUS_START;
+        } else {
+            let prev = haystack[j - 1];
+            if !prev.is_alphanumeric() {
+                score += BONUS_WORD_START;
+            } else if prev.is_lowercase() && h.is_uppercase() {
+                score += BONUS_CAMEL_CASE;
+            }
+        }
+        score
+    };
+
+    // best[j] is the best score of matching the needle so far with its last character at j.
+    let mut best: Vec<i64> = (0..haystack.len())
+        .map(|j| score_at(needle[0], j))
+        .collect();
+    for &n in &needle[1..] {
+        let mut next = vec![IMPOSSIBLE; haystack.len()];
+        // The best score of the previous characters ending before j - 1, minus the gap penalty.
+        let mut gapped = IMPOSSIBLE;
+        for j in 1..haystack.len() {
+            if j >= 2 {
+                gapped = (gapped - PENALTY_GAP_EXTENSION).max(best[j - 2] - PENALTY_GAP_START);
+            }
+            let here = score_at(n, j);
+            if here == IMPOSSIBLE {
+                continue;
+            }
+            let before = (best[j - 1] + BONUS_CONSECUTIVE).max(gapped);
+            if before > IMPOSSIBLE / 2 {
+                next[j] = before + here;
+            }
+        }
+        best = next;
+    }
+    let score = best.into_iter().max().unwrap();
+    (score > IMPOSSIBLE / 2).then(|| u32::try_from(score.max(0)).unwrap())
+}
+
 /// Case-insensitive string search, modeled after std::string::find().
 /// \param fuzzy indicates this is being used for fuzzy matching and case insensitivity is
 /// expanded to include symbolic characters (#3584).
@@ -169,12 +244,25 @@ pub enum CaseSensitivity {
     Insensitive,
 }
 
+/// Helper to lazily compute if case insensitive matches should use icase or smartcase.
+/// Use icase if the input contains any uppercase characters, smartcase otherwise.
+#[inline(always)]
+fn get_case_fold(s: &wstr) -> CaseSensitivity {
+    if s.chars().any(|c| c.is_uppercase()) {
+        CaseSensitivity::Insensitive
+    } else {
+        CaseSensitivity::Smart
+    }
+}
+
 /// A lightweight value-type describing how closely a string fuzzy-matches another string.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct StringFuzzyMatch {
     pub from_separator: bool,
     pub typ: ContainType,
     pub case_fold: CaseSensitivity,
+    /// How relevant a scored match is, higher is better. See [`subsequence_score`].
+    pub score: u32,
 }
 
 impl StringFuzzyMatch {
@@ -183,6 +271,7 @@ impl StringFuzzyMatch {
             from_separator: false,
             typ,
             case_fold,
+            score: 0,
         }
     }
     // Helper to return an exact match.
@@ -218,17 +307,6 @@ impl StringFuzzyMatch {
         match_against: &wstr,
         anchor_start: bool,
     ) -> Option<StringFuzzyMatch> {
-        // Helper to lazily compute if case insensitive matches should use icase or smartcase.
-        // Use icase if the input contains any uppercase characters, smartcase otherwise.
-        #[inline(always)]
-        fn get_case_fold(s: &wstr) -> CaseSensitivity {
-            if s.chars().any(|c| c.is_uppercase()) {
-                CaseSensitivity::Insensitive
-            } else {
-                CaseSensitivity::Smart
-            }
-        }
-
         // A string cannot fuzzy match against a shorter string.
         if string.len() > match_against.len() {
             return None;
@@ -303,6 +381,16 @@ impl StringFuzzyMatch {
         None
     }
 
+    /// Try creating a scored match for `string` against `match_against`, for matching like fuzzy
+    /// finders do: any case-insensitive subsequence matches, and all matches are alike except for
+    /// their score.
+    pub fn try_create_scored(string: &wstr, match_against: &wstr) -> Option<StringFuzzyMatch> {
+        let score = subsequence_score(string, match_against)?;
+        let mut result = StringFuzzyMatch::new(ContainType::Subseq, get_case_fold(string));
+        result.score = score;
+        Some(result)
+    }
+
     pub fn rank(&self) -> u32 {
         // Combine our type and our case fold into a single number, such that better matches are
         // smaller. Treat 'exact' types the same as 'prefix' types; this is because we do not
@@ -532,9 +620,10 @@ pub fn fish_wcwidth_visible(c: char) -> isize {
 #[cfg(test)]
 mod tests {
     use super::{
-        CaseSensitivity, ContainType, LineIterator, count_newlines, ifind, join_strings,
-        split_string_tok, string_fuzzy_match_string, string_prefixes_string_case_insensitive,
-        string_suffixes_string_case_insensitive,
+        CaseSensitivity, ContainType, LineIterator, StringFuzzyMatch, count_newlines, ifind,
+        join_strings, split_string_tok, string_fuzzy_match_string,
+        string_prefixes_string_case_insensitive, string_suffixes_string_case_insensitive,
+        subsequence_score,
     };
     use fish_widestring::prelude::*;
 
@@ -686,6 +775,33 @@ mod tests {
         validate!("BB", "ALPHA!", None);
     }
 
+    #[test]
+    fn test_subsequence_score() {
+        let score = |needle, haystack| subsequence_score(needle, haystack);
+        assert_eq!(score(L!(""), L!("anything")), Some(0));
+        assert_eq!(score(L!("abc"), L!("ab")), None);
+        assert_eq!(score(L!("cba"), L!("abc")), None);
+        assert!(score(L!("FBR"), L!("foobar")).is_some());
+        // Prefixes beat matches elsewhere, and word starts beat the middle of words.
+        assert!(score(L!("che"), L!("checkout")) > score(L!("che"), L!("recheck")));
+        assert!(score(L!("co"), L!("checkout")) < score(L!("co"), L!("commit")));
+        assert!(score(L!("gco"), L!("git-checkout")) > score(L!("gco"), L!("gitconfig")));
+        assert!(score(L!("fb"), L!("foo-bar")) > score(L!("fb"), L!("fooabar")));
+        assert!(score(L!("fb"), L!("fooBar")) > score(L!("fb"), L!("foobar")));
+        // Consecutive characters beat scattered ones.
+        assert!(score(L!("abc"), L!("xabcx")) > score(L!("abc"), L!("xaxbxc")));
+        // The best alignment is found, not just the leftmost one.
+        assert!(score(L!("ab"), L!("a-xb-ab")) > score(L!("ab"), L!("a-xb-xb")));
+        // Samecase matches beat icase ones.
+        assert!(score(L!("ab"), L!("ab")) > score(L!("ab"), L!("AB")));
+
+        let m = StringFuzzyMatch::try_create_scored(L!("gco"), L!("git-checkout")).unwrap();
+        assert_eq!(m.typ, ContainType::Subseq);
+        assert_eq!(m.case_fold, CaseSensitivity::Smart);
+        assert_eq!(m.score, score(L!("gco"), L!("git-checkout")).unwrap());
+        assert!(StringFuzzyMatch::try_create_scored(L!("xyz"), L!("git-checkout")).is_none());
+    }
+
     #[test]
     fn test_split_string_tok() {
         macro_rules! validate {
@@ -71,10 +71,13 @@ pub const ANY_CHAR: char = char_offset(WILDCARD_RESERVED_BASE, 0);
 pub const ANY_STRING: char = char_offset(WILDCARD_RESERVED_BASE, 1);
 /// Character representing any character string.
 pub const ANY_STRING_RECURSIVE: char = char_offset(WILDCARD_RESERVED_BASE, 2);
+/// Character replacing the opening parenthesis of glob qualifiers, like the "(" in "*(.)".
+/// The qualifiers follow it, without the closing parenthesis.
+pub const GLOB_QUALIFIER: char = char_offset(WILDCARD_RESERVED_BASE, 3);
 /// This is a special pseudo-char that is not used other than to mark the
 /// end of the special characters so we can sanity check the enum range.
 #[allow(dead_code)]
-pub const ANY_SENTINEL: char = char_offset(WILDCARD_RESERVED_BASE, 3);
+pub const ANY_SENTINEL: char = char_offset(WILDCARD_RESERVED_BASE, 4);
 
 /// Character representing a home directory.
 pub const HOME_DIRECTORY: char = char_offset(EXPAND_RESERVED_BASE, 0);
@@ -1,7 +1,10 @@
 use anstyle::{AnsiColor, Style};
 use anyhow::{Context, Result, bail};
 use clap::Args;
+use fish::builtins::fish_indent::format_script;
+use fish_widestring::{bytes2wcstring, wcs2bytes};
 use std::{
+    fs,
     io::{ErrorKind, Write},
     path::PathBuf,
     process::{Command, Stdio},
@@ -114,17 +117,28 @@ fn format_fish(args: &FormatArgs) -> Result<()> {
     if fish_paths.is_empty() {
         return Ok(());
     }
-    // TODO: make `fish_indent` available as a Rust library function, to avoid needing a
-    // `fish_indent` binary in `$PATH`.
-    let mut formatter = Command::new("fish_indent");
-    if args.check {
-        formatter.arg("--check");
-    } else {
-        formatter.arg("-w");
+    println!("=== Running {GREEN}fish_indent{GREEN:#}");
+    let mut unformatted = false;
+    for path in fish_paths {
+        let contents =
+            fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let src = bytes2wcstring(&contents);
+        let formatted = format_script(&src);
+        if formatted == src {
+            continue;
+        }
+        if args.check {
+            println!("{}", path.display());
+            unformatted = true;
+        } else {
+            fs::write(&path, wcs2bytes(&formatted))
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    if unformatted {
+        bail!("\"fish_indent\": Files are not formatted correctly.");
     }
-    formatter.arg("--");
-    formatter.args(fish_paths);
-    run_formatter(&mut formatter, "fish_indent")
+    Ok(())
 }
 
 fn format_python(args: &FormatArgs) -> Result<()> {
@@ -4,6 +4,7 @@ use clap::{Args, Subcommand};
 use fish_build_helper::po_dir;
 use pcre2::bytes::Regex;
 use std::{
+    collections::HashSet,
     fs::OpenOptions,
     io::{Write as _, stdout},
     path::{Path, PathBuf},
@@ -11,6 +12,11 @@ use std::{
     thread::spawn,
 };
 
+pub mod format_check;
+pub mod merge;
+pub mod po;
+pub mod stats;
+
 #[derive(Args)]
 pub struct GettextArgs {
     /// Path to the directory into which the messages from the Rust sources have been extracted.
@@ -44,7 +50,22 @@ enum Task {
     /// including fish script files, where many strings are implicitly localized.
     /// Considers all our PO files by default, also allows explicitly specifying which files to
     /// consider.
-    Update { paths: Vec<PathBuf> },
+    /// Show how many messages are translated, fuzzy, or untranslated, per language.
+    /// Unlike the other tasks, this does not extract messages from the sources, so run `update`
+    /// first if the PO files might be outdated.
+    Stats {
+        paths: Vec<PathBuf>,
+        /// Print the numbers as JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    Update {
+        paths: Vec<PathBuf>,
+        /// Instead of deleting translations of msgids which are no longer used, keep them as
+        /// obsolete entries at the end of the file, so they can still be consulted or reused.
+        #[arg(long)]
+        keep_obsolete: bool,
+    },
 }
 
 fn get_po_paths<P: AsRef<Path>>(specified_paths: &[P]) -> Result<Vec<PathBuf>> {
@@ -56,30 +77,110 @@ fn get_po_paths<P: AsRef<Path>>(specified_paths: &[P]) -> Result<Vec<PathBuf>> {
     }
 }
 
-fn update_po_file<P: AsRef<Path>, Q: AsRef<Path>>(file_to_update: P, template: Q) -> Result<()> {
-    Command::new("msgmerge")
-        .args([
-            "--no-wrap",
-            "--update",
-            "--no-fuzzy-matching",
-            "--backup=none",
-            "--quiet",
-        ])
-        .arg(file_to_update.as_ref())
-        .arg(template.as_ref())
-        .run()?;
-    let msgattrib_output_file = fish_tempfile::new_file().context("Failed to create temp file")?;
-    Command::new("msgattrib")
-        .args(["--no-wrap", "--no-obsolete"])
-        .arg("-o")
-        .arg(msgattrib_output_file.path())
-        .arg(file_to_update.as_ref())
-        .run()?;
-    crate::copy_file(msgattrib_output_file.path(), file_to_update.as_ref())?;
+fn read_po_file(path: &Path) -> Result<Vec<po::PoEntry>> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+    po::parse_po_file(&content).with_context(|| format!("Failed to parse {path:?}"))
+}
+
+/// Read the template, without the locations of the messages.
+/// These are only used for reporting, because they would make every change to the sources which
+/// moves a message result in a change to all PO files.
+fn read_template_without_references(path: &Path) -> Result<Vec<po::PoEntry>> {
+    let mut template = read_po_file(path)?;
+    for entry in &mut template {
+        entry.references.clear();
+    }
+    Ok(template)
+}
+
+/// Describe the msgids which are in `template` but not in `catalog`, with the locations they are
+/// used at, and the ones which are only in `catalog`.
+fn describe_changed_msgids<'a>(
+    catalog: &'a [po::PoEntry],
+    template: &'a [po::PoEntry],
+) -> Vec<String> {
+    let live_msgids = |entries: &'a [po::PoEntry]| -> HashSet<&'a str> {
+        entries
+            .iter()
+            .filter(|entry| !entry.obsolete)
+            .map(|entry| entry.msgid.as_str())
+            .collect()
+    };
+    let catalog_msgids = live_msgids(catalog);
+    let template_msgids = live_msgids(template);
+    let mut changes = vec![];
+    for entry in template {
+        if entry.is_header() || catalog_msgids.contains(entry.msgid.as_str()) {
+            continue;
+        }
+        let mut change = format!("missing msgid {:?}", entry.msgid);
+        if !entry.references.is_empty() {
+            change.push_str(&format!(", used at {}", entry.references.join(", ")));
+        }
+        changes.push(change);
+    }
+    for entry in catalog {
+        if entry.is_header() || entry.obsolete || template_msgids.contains(entry.msgid.as_str()) {
+            continue;
+        }
+        changes.push(format!("extra msgid {:?}", entry.msgid));
+    }
+    changes
+}
+
+fn update_po_file<P: AsRef<Path>, Q: AsRef<Path>>(
+    file_to_update: P,
+    template: Q,
+    keep_obsolete: bool,
+) -> Result<()> {
+    let file_to_update = file_to_update.as_ref();
+    let catalog = read_po_file(file_to_update)?;
+    let template = read_template_without_references(template.as_ref())?;
+    let options = merge::MergeOptions {
+        fuzzy_threshold: None,
+        keep_obsolete,
+        ..Default::default()
+    };
+    let mut content = vec![];
+    po::write_po_file(
+        &mut content,
+        &merge::merge(&catalog, &template, &options),
+        None,
+    )?;
+    std::fs::write(file_to_update, content)
+        .with_context(|| format!("Failed to write {file_to_update:?}"))?;
     Ok(())
 }
 
+fn print_stats<P: AsRef<Path>>(paths: &[P], json: bool) -> Result<()> {
+    let mut catalog_stats = vec![];
+    for path in get_po_paths(paths)? {
+        let language = path
+            .file_stem()
+            .with_context(|| format!("The path {path:?} does not have a file stem"))?
+            .to_string_lossy()
+            .into_owned();
+        catalog_stats.push((
+            language,
+            stats::CatalogStats::from_entries(&read_po_file(&path)?),
+        ));
+    }
+    catalog_stats.sort_by(|(a, _), (b, _)| a.cmp(b));
+    let output = if json {
+        stats::format_json(&catalog_stats)
+    } else {
+        stats::format_table(&catalog_stats)
+    };
+    stdout()
+        .write_all(output.as_bytes())
+        .context("Could not write to stdout")
+}
+
 pub fn gettext(args: GettextArgs) -> Result<()> {
+    if let Task::Stats { paths, json } = &args.task {
+        return print_stats(paths, *json);
+    }
     let template = match args.rust_extraction_dir {
         Some(dir) => template::Template::new(dir)?,
         None => {
@@ -99,7 +200,7 @@ pub fn gettext(args: GettextArgs) -> Result<()> {
     let mut template_file = fish_tempfile::new_file().context("Failed to create temp file")?;
     template_file
         .get_mut()
-        .write_all(template.serialize())
+        .write_all(&template.serialize())
         .with_context(|| format!("Failed to write to temp file {:?}", template_file.path()))?;
     template_file
         .get_mut()
@@ -115,7 +216,7 @@ pub fn gettext(args: GettextArgs) -> Result<()> {
                     let tmp_copy =
                         fish_tempfile::new_file().context("Failed to create temp file")?;
                     crate::copy_file(&path, tmp_copy.path())?;
-                    update_po_file(tmp_copy.path(), template_path_buf)?;
+                    update_po_file(tmp_copy.path(), &template_path_buf, false)?;
                     let diff_output = Command::new("diff")
                         .arg("-u")
                         .arg(&path)
@@ -123,10 +224,19 @@ pub fn gettext(args: GettextArgs) -> Result<()> {
                         .output()
                         .context("Failed to run diff")?;
                     if diff_output.status.success() {
-                        Ok(None)
-                    } else {
-                        Ok(Some(diff_output.stdout))
+                        return Ok(None);
+                    }
+                    // The diff does not show where new messages come from, so list them
+                    // separately.
+                    let mut report = diff_output.stdout;
+                    let changes = describe_changed_msgids(
+                        &read_po_file(&path)?,
+                        &read_po_file(&template_path_buf)?,
+                    );
+                    for change in changes {
+                        let _ = writeln!(report, "{}: {change}", path.display());
                     }
+                    Ok(Some(report))
                 });
                 thread_handles.push(handle);
             }
@@ -152,6 +262,20 @@ pub fn gettext(args: GettextArgs) -> Result<()> {
             if let Some(e) = error {
                 return Err(e);
             }
+            let mut found_format_problem = false;
+            for path in get_po_paths(&paths)? {
+                for problem in format_check::check_format_strings(&read_po_file(&path)?) {
+                    found_format_problem = true;
+                    println!("{}: {problem}", path.display());
+                }
+            }
+            if found_format_problem {
+                bail!(
+                    "Some translations do not use the same format directives as their msgid.\n\
+                     They need to expect the same arguments in the same order.\
+                    "
+                );
+            }
             if found_diff {
                 bail!(
                     "Not all PO files are up to date.\n\
@@ -196,17 +320,26 @@ pub fn gettext(args: GettextArgs) -> Result<()> {
             new_po_file
                 .write_all(header.as_bytes())
                 .with_context(|| format!("Failed to write to {po_path:?}"))?;
-            new_po_file
-                .write_all(template.serialize())
-                .with_context(|| format!("Failed to write to {po_path:?}"))?;
+            po::write_po_file(
+                &mut new_po_file,
+                &read_template_without_references(template_file.path())?,
+                None,
+            )
+            .with_context(|| format!("Failed to write to {po_path:?}"))?;
             Ok(())
         }
-        Task::Update { paths } => {
+        // Handled before creating the template, which is not needed for it.
+        Task::Stats { .. } => unreachable!(),
+        Task::Update {
+            paths,
+            keep_obsolete,
+        } => {
             let mut thread_handles = vec![];
             for path in get_po_paths(&paths)? {
                 let template_path_buf = template_file.path().to_owned();
-                let handle =
-                    spawn(move || -> Result<()> { update_po_file(path, template_path_buf) });
+                let handle = spawn(move || -> Result<()> {
+                    update_po_file(path, template_path_buf, keep_obsolete)
+                });
                 thread_handles.push(handle);
             }
             let mut error = None;
@@ -227,28 +360,20 @@ pub fn gettext(args: GettextArgs) -> Result<()> {
 }
 
 mod template {
-    use crate::{CommandExt as _, files_with_extension};
+    use super::po::{PoEntry, write_po_file};
+    use crate::files_with_extension;
     use anyhow::{Context as _, Result, bail};
     use fish_build_helper::workspace_root;
     use fish_common::{UnescapeFlags, unescape_string};
     use fish_widestring::{str2wcstring, wcs2bytes};
     use pcre2::bytes::Regex;
     use std::{
-        collections::{HashMap, HashSet},
+        collections::{BTreeMap, BTreeSet, HashMap},
         fmt::Display,
-        fs::OpenOptions,
-        io::Read as _,
         path::Path,
-        process::Command,
         sync::LazyLock,
     };
 
-    // Gettext tools require this header to know which encoding is used.
-    const MINIMAL_HEADER: &str = r#"msgid ""
-msgstr "Content-Type: text/plain; charset=UTF-8\n"
-
-"#;
-
     #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
     enum LocalizationTier {
         Tier1,
@@ -279,50 +404,54 @@ msgstr "Content-Type: text/plain; charset=UTF-8\n"
         }
     }
 
+    /// Maps the quoted messages found in fish scripts to the locations they are used at.
     #[derive(Default)]
     struct FishScriptMessages {
-        explicit: HashSet<String>,
-        implicit: HashSet<String>,
+        explicit: HashMap<String, Vec<String>>,
+        /// Singular and plural form of messages localized via `_ --count`.
+        explicit_plural: HashMap<(String, String), Vec<String>>,
+        implicit: HashMap<String, Vec<String>>,
     }
 
     pub struct Template {
-        content: Vec<u8>,
+        entries: Vec<PoEntry>,
+        /// The index of each msgid's entry in `entries`.
+        positions: HashMap<String, usize>,
     }
 
     impl Template {
-        pub fn serialize(&self) -> &[u8] {
-            &self.content
+        pub fn serialize(&self) -> Vec<u8> {
+            let mut content = vec![];
+            // Writing to a `Vec` cannot fail.
+            write_po_file(&mut content, &self.entries, None).unwrap();
+            content
         }
 
         /// Create a gettext template.
         /// `rust_extraction_dir` must be the path to a directory which contains the messages
         /// extracted from the Rust sources.
         pub fn new<P: AsRef<Path>>(rust_extraction_dir: P) -> Result<Self> {
+            // Gettext tools require this header to know which encoding is used.
+            let header = PoEntry {
+                msgstr: vec!["Content-Type: text/plain; charset=UTF-8\n".to_owned()],
+                ..PoEntry::new("")
+            };
             let mut template = Self {
-                content: Vec::from(MINIMAL_HEADER.as_bytes()),
+                entries: vec![],
+                positions: HashMap::new(),
             };
+            template.append_entry(header);
             template.add_rust_messages(rust_extraction_dir)?;
             template.add_fish_script_messages()?;
-            // TODO: keep internal set of msgids to avoid having to run msguniq. requires parsing
-            // gettext-extraction output
-            let msguniq_output = Command::new("msguniq")
-                .args(["--no-wrap"])
-                .run_with_stdio(template.content)?;
-            Ok(Template {
-                content: msguniq_output,
-            })
+            Ok(template)
         }
 
         /// Expects `extraction_dir` to contain only files whose content are single PO entries which can be
         /// concatenated into a valid PO file.
-        /// If this is the case, the messages are de-duplicated and sorted by `msguniq`.
-        /// The result is appended to `template`, with a leading section marker.
-        /// On failure, the process aborts.
+        /// The messages are sorted and appended to `template`, with a leading section marker.
         fn add_rust_messages<P: AsRef<Path>>(&mut self, extraction_dir: P) -> Result<()> {
             let extraction_dir = extraction_dir.as_ref();
-            let mut concatenated_content = Vec::from(MINIMAL_HEADER.as_bytes());
-
-            // Concatenate the content of all files in `extraction_dir` into `concatenated_content`.
+            let mut entries = vec![];
             for entry_result in extraction_dir
                 .read_dir()
                 .with_context(|| format!("Failed to read directory {extraction_dir:?}"))?
@@ -337,61 +466,82 @@ msgstr "Content-Type: text/plain; charset=UTF-8\n"
                 {
                     bail!("Entry in {extraction_dir:?} is not a regular file");
                 }
-                let mut file = OpenOptions::new()
-                    .read(true)
-                    .open(&entry_path)
-                    .with_context(|| format!("Failed to open file {entry_path:?}"))?;
-                file.read_to_end(&mut concatenated_content)
-                    .with_context(|| format!("Failed to read file {entry_path:?}"))?;
-            }
-
-            // Get rid of duplicates and sort.
-            let msguniq_output = Command::new("msguniq")
-                .args(["--no-wrap", "--sort-output"])
-                .env("LC_ALL", "C.UTF-8")
-                .run_with_stdio(concatenated_content)?;
-            // The Header entry needs to be removed again,
-            // because it is added outside of this function.
-            let expected_prefix = MINIMAL_HEADER.as_bytes();
-            let actual_prefix = &msguniq_output[..expected_prefix.len()];
-            if expected_prefix != actual_prefix {
-                bail!(
-                    "Prefix of msguniq output does not match expected header.\nExpected bytes:\n{expected_prefix:02x?}\nActual bytes:\n{actual_prefix:02x?}"
-                );
+                entries.extend(super::read_po_file(&entry_path)?);
             }
+            entries.sort_by(|a, b| a.msgid.cmp(&b.msgid));
             self.mark_section("tier1-from-rust");
-            self.content
-                .extend_from_slice(&msguniq_output[expected_prefix.len()..]);
-            self.content.push(b'\n');
+            for entry in entries {
+                self.append_entry(entry);
+            }
             Ok(())
         }
 
+        /// Append an entry. If there already is one with the same msgid, the comments, locations
+        /// and flags of the new entry are added to it instead.
+        fn append_entry(&mut self, entry: PoEntry) {
+            let Some(&position) = self.positions.get(&entry.msgid) else {
+                self.positions
+                    .insert(entry.msgid.clone(), self.entries.len());
+                self.entries.push(entry);
+                return;
+            };
+            let existing = &mut self.entries[position];
+            let merge = |existing: &mut Vec<String>, new: Vec<String>| {
+                for item in new {
+                    if !existing.contains(&item) {
+                        existing.push(item);
+                    }
+                }
+            };
+            merge(&mut existing.extracted_comments, entry.extracted_comments);
+            merge(&mut existing.references, entry.references);
+            merge(&mut existing.flags, entry.flags);
+        }
+
         fn mark_section(&mut self, section_name: &str) {
-            self.content
-                .extend_from_slice("msgid \"fish-section-".as_bytes());
-            self.content.extend_from_slice(section_name.as_bytes());
-            self.content
-                .extend_from_slice("\"\nmsgstr \"\"\n\n".as_bytes());
+            self.append_entry(PoEntry::new(format!("fish-section-{section_name}")));
         }
 
-        fn append_messages(&mut self, msgids: &HashSet<String>) -> Result<()> {
-            let mut unescaped_msgids = HashSet::new();
-            for msgid in msgids {
-                let unescaped_wstring = unescape_string(
-                    &str2wcstring(msgid),
-                    fish_common::UnescapeStringStyle::Script(UnescapeFlags::default()),
-                )
-                .with_context(|| format!("Failed to unescape the following string:\n{msgid}"))?;
-                unescaped_msgids.insert(
-                    String::from_utf8(wcs2bytes(&unescaped_wstring))
-                        .context("Parsed msgid is not valid UTF-8")?,
-                );
+        fn append_messages(&mut self, msgids: &HashMap<String, Vec<String>>) -> Result<()> {
+            // Differently quoted msgids can denote the same string.
+            let mut unescaped_msgids: BTreeMap<String, BTreeSet<&String>> = BTreeMap::new();
+            for (msgid, references) in msgids {
+                unescaped_msgids
+                    .entry(unescape_fish_string(msgid)?)
+                    .or_default()
+                    .extend(references);
+            }
+            for (msgid, references) in unescaped_msgids {
+                self.append_entry(PoEntry {
+                    references: references.into_iter().cloned().collect(),
+                    ..PoEntry::new(msgid)
+                });
             }
-            let mut unescaped_msgids = Vec::from_iter(unescaped_msgids);
-            unescaped_msgids.sort();
-            for msgid in &unescaped_msgids {
-                self.content
-                    .extend_from_slice(format_msgid_for_po(msgid).as_bytes());
+            Ok(())
+        }
+
+        fn append_plural_messages(
+            &mut self,
+            msgids: &HashMap<(String, String), Vec<String>>,
+        ) -> Result<()> {
+            let mut unescaped_msgids: BTreeMap<(String, String), BTreeSet<&String>> =
+                BTreeMap::new();
+            for ((singular, plural), references) in msgids {
+                unescaped_msgids
+                    .entry((
+                        unescape_fish_string(singular)?,
+                        unescape_fish_string(plural)?,
+                    ))
+                    .or_default()
+                    .extend(references);
+            }
+            for ((singular, plural), references) in unescaped_msgids {
+                self.append_entry(PoEntry {
+                    references: references.into_iter().cloned().collect(),
+                    msgid_plural: Some(plural),
+                    msgstr: vec![String::new(); 2],
+                    ..PoEntry::new(singular)
+                });
             }
             Ok(())
         }
@@ -401,9 +551,10 @@ msgstr "Content-Type: text/plain; charset=UTF-8\n"
             tier: LocalizationTier,
             messages: FishScriptMessages,
         ) -> Result<()> {
-            if !messages.explicit.is_empty() {
+            if !messages.explicit.is_empty() || !messages.explicit_plural.is_empty() {
                 self.mark_section(&format!("{tier}-from-script-explicitly-added"));
                 self.append_messages(&messages.explicit)?;
+                self.append_plural_messages(&messages.explicit_plural)?;
             }
             if !messages.implicit.is_empty() {
                 self.mark_section(&format!("{tier}-from-script-implicitly-added"));
@@ -435,6 +586,16 @@ msgstr "Content-Type: text/plain; charset=UTF-8\n"
         }
     }
 
+    /// Turn a quoted string from a fish script into the string it denotes.
+    fn unescape_fish_string(quoted: &str) -> Result<String> {
+        let unescaped_wstring = unescape_string(
+            &str2wcstring(quoted),
+            fish_common::UnescapeStringStyle::Script(UnescapeFlags::default()),
+        )
+        .with_context(|| format!("Failed to unescape the following string:\n{quoted}"))?;
+        String::from_utf8(wcs2bytes(&unescaped_wstring)).context("Parsed msgid is not valid UTF-8")
+    }
+
     fn find_localization_tier<P: AsRef<Path>>(
         input: &str,
         path: P,
@@ -522,31 +683,82 @@ msgstr "Content-Type: text/plain; charset=UTF-8\n"
         // TODO: use proper parser instead of regex
         static EXPLICIT_MESSAGE: LazyLock<Regex> =
             LazyLock::new(|| Regex::new(r#"\( *_ (?<message>(['"]).+?(?<!\\)\2) *\)"#).unwrap());
+        static EXPLICIT_PLURAL_MESSAGE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r#"\( *_ (?:-n|--count)[ =]\S+ (?<singular>(['"]).+?(?<!\\)\2) (?<plural>(['"]).+?(?<!\\)\4) *\)"#).unwrap()
+        });
         static IMPLICIT_MESSAGE: LazyLock<Regex> = LazyLock::new(|| {
             Regex::new(r#"(?:^|\n)(?:\s|and |or )*(?:complete|function).*? (?:-d|--description) (?<message>(['"]).+?(?<!\\)\2)"#).unwrap()
         });
+        // Referenced as `file:line`, with the file relative to the workspace root.
+        let relative_path = path.strip_prefix(workspace_root()).unwrap_or(path);
+        let reference = |offset: usize| {
+            let line = file_content[..offset].matches('\n').count() + 1;
+            format!("{}:{line}", relative_path.display())
+        };
         let messages_at_tier = extracted_messages.entry(tier).or_default();
         for message in EXPLICIT_MESSAGE.captures_iter(file_content.as_bytes()) {
-            let message =
-                std::str::from_utf8(message.unwrap().name("message").unwrap().as_bytes()).unwrap();
-            messages_at_tier.explicit.insert(message.to_owned());
+            let message = message.unwrap().name("message").unwrap();
+            messages_at_tier
+                .explicit
+                .entry(std::str::from_utf8(message.as_bytes()).unwrap().to_owned())
+                .or_default()
+                .push(reference(message.start()));
+        }
+        for message in EXPLICIT_PLURAL_MESSAGE.captures_iter(file_content.as_bytes()) {
+            let message = message.unwrap();
+            let form = |name| std::str::from_utf8(message.name(name).unwrap().as_bytes()).unwrap();
+            messages_at_tier
+                .explicit_plural
+                .entry((form("singular").to_owned(), form("plural").to_owned()))
+                .or_default()
+                .push(reference(message.name("singular").unwrap().start()));
         }
         for message in IMPLICIT_MESSAGE.captures_iter(file_content.as_bytes()) {
-            let message =
-                std::str::from_utf8(message.unwrap().name("message").unwrap().as_bytes()).unwrap();
-            messages_at_tier.implicit.insert(message.to_owned());
+            let message = message.unwrap().name("message").unwrap();
+            messages_at_tier
+                .implicit
+                .entry(std::str::from_utf8(message.as_bytes()).unwrap().to_owned())
+                .or_default()
+                .push(reference(message.start()));
         }
         Ok(())
     }
+}
 
-    fn format_msgid_for_po(msgid: &str) -> String {
-        let escaped_msgid = msgid.replace("\\", "\\\\").replace("\"", "\\\"");
-        format!(
-            "\
-            msgid \"{escaped_msgid}\"\n\
-            msgstr \"\"\n\
-            \n\
-            "
-        )
+#[cfg(test)]
+mod tests {
+    use super::{describe_changed_msgids, po::PoEntry};
+
+    #[test]
+    fn test_describe_changed_msgids() {
+        let catalog = [
+            PoEntry::new(""),
+            PoEntry::new("Kept"),
+            PoEntry::new("Removed"),
+            PoEntry {
+                obsolete: true,
+                ..PoEntry::new("Obsolete")
+            },
+        ];
+        let template = [
+            PoEntry::new(""),
+            PoEntry {
+                references: vec![
+                    "src/a.rs:1".to_owned(),
+                    "share/functions/b.fish:2".to_owned(),
+                ],
+                ..PoEntry::new("Added")
+            },
+            PoEntry::new("Kept"),
+            PoEntry::new("Obsolete"),
+        ];
+        assert_eq!(
+            describe_changed_msgids(&catalog, &template),
+            [
+                "missing msgid \"Added\", used at src/a.rs:1, share/functions/b.fish:2",
+                "missing msgid \"Obsolete\"",
+                "extra msgid \"Removed\"",
+            ]
+        );
     }
 }
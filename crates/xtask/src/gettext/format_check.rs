@@ -0,0 +1,159 @@
+//! Checks that translations of format strings expect the same arguments as the original message.
+//! Otherwise, formatting the translation fails at runtime, or shows the wrong values.
+//! The syntax of format directives is the one supported by `fish_printf`.
+
+use super::po::PoEntry;
+
+/// The type of value a format directive consumes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgumentKind {
+    Int,
+    UnsignedInt,
+    Float,
+    Char,
+    String,
+    Pointer,
+    Count,
+}
+
+/// Parse the directives in `format`, returning the kinds of arguments they consume, in order.
+pub fn argument_kinds(format: &str) -> Result<Vec<ArgumentKind>, String> {
+    let mut kinds = vec![];
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            continue;
+        }
+        if chars.next_if_eq(&'%').is_some() {
+            continue;
+        }
+        while chars.next_if(|c| "#0- +'".contains(*c)).is_some() {}
+        // Width and precision may be taken from an argument.
+        if chars.next_if_eq(&'*').is_some() {
+            kinds.push(ArgumentKind::Int);
+        }
+        while chars.next_if(char::is_ascii_digit).is_some() {}
+        if chars.next_if_eq(&'.').is_some() {
+            if chars.next_if_eq(&'*').is_some() {
+                kinds.push(ArgumentKind::Int);
+            }
+            while chars.next_if(char::is_ascii_digit).is_some() {}
+        }
+        while chars.next_if(|c| "hljtzL".contains(*c)).is_some() {}
+        let kind = match chars.next() {
+            Some('d' | 'i') => ArgumentKind::Int,
+            Some('o' | 'u' | 'x' | 'X') => ArgumentKind::UnsignedInt,
+            Some('a' | 'A' | 'e' | 'E' | 'f' | 'F' | 'g' | 'G') => ArgumentKind::Float,
+            Some('c' | 'C') => ArgumentKind::Char,
+            Some('s' | 'S') => ArgumentKind::String,
+            Some('p') => ArgumentKind::Pointer,
+            Some('n') => ArgumentKind::Count,
+            Some(c) => return Err(format!("unknown conversion '%{c}'")),
+            None => return Err("incomplete directive at the end".to_owned()),
+        };
+        kinds.push(kind);
+    }
+    Ok(kinds)
+}
+
+/// Check the translations of format strings in a catalog, returning a description of each problem.
+/// Only entries which are marked as `c-format` and not as `fuzzy` are considered, since these are
+/// the ones fish uses as format strings.
+pub fn check_format_strings(entries: &[PoEntry]) -> Vec<String> {
+    let mut problems = vec![];
+    for entry in entries {
+        if entry.obsolete || entry.is_fuzzy() || !entry.flags.iter().any(|f| f == "c-format") {
+            continue;
+        }
+        let msgid = entry.msgid_plural.as_ref().unwrap_or(&entry.msgid);
+        let expected = match argument_kinds(msgid) {
+            Ok(kinds) => kinds,
+            Err(e) => {
+                problems.push(format!("msgid {msgid:?}: {e}"));
+                continue;
+            }
+        };
+        for (i, msgstr) in entry.msgstr.iter().enumerate() {
+            if msgstr.is_empty() {
+                continue;
+            }
+            let keyword = match entry.msgid_plural {
+                Some(_) => format!("msgstr[{i}]"),
+                None => "msgstr".to_owned(),
+            };
+            match argument_kinds(msgstr) {
+                Ok(actual) if actual == expected => {}
+                Ok(actual) => problems.push(format!(
+                    "msgid {msgid:?}: {keyword} expects arguments {actual:?} instead of {expected:?}"
+                )),
+                Err(e) => problems.push(format!("msgid {msgid:?}: {keyword}: {e}")),
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ArgumentKind::*, argument_kinds, check_format_strings};
+    use crate::gettext::po::PoEntry;
+
+    #[test]
+    fn test_argument_kinds() {
+        assert_eq!(argument_kinds("no directives, 100%%").unwrap(), []);
+        assert_eq!(
+            argument_kinds("%s: %d jobs, %-*.*lf%% %5lu %'x %c %p").unwrap(),
+            [
+                String,
+                Int,
+                Int,
+                Int,
+                Float,
+                UnsignedInt,
+                UnsignedInt,
+                Char,
+                Pointer
+            ]
+        );
+        assert_eq!(argument_kinds("%ls %lS").unwrap(), [String, String]);
+        assert!(argument_kinds("%y").is_err());
+        assert!(argument_kinds("trailing %").is_err());
+    }
+
+    #[test]
+    fn test_check_format_strings() {
+        let entry = |msgid: &str, msgstr: &str| PoEntry {
+            flags: vec!["c-format".to_owned()],
+            msgstr: vec![msgstr.to_owned()],
+            ..PoEntry::new(msgid)
+        };
+        let mut fuzzy = entry("%s: %d", "%d");
+        fuzzy.set_fuzzy(true);
+        let unflagged = PoEntry {
+            flags: vec![],
+            ..entry("%s", "100%")
+        };
+        let plural = PoEntry {
+            msgid_plural: Some("%d jobs".to_owned()),
+            msgstr: vec!["%d Job".to_owned(), "%s Jobs".to_owned()],
+            ..entry("%d job", "")
+        };
+        let entries = [
+            entry("%s: Unknown option", "%s: Unbekannte Option"),
+            entry("%s: %d", ""),
+            entry("%s: Invalid job %d", "%s: Ungültiger Job"),
+            entry("%s", "%s %"),
+            fuzzy,
+            unflagged,
+            plural,
+        ];
+        assert_eq!(
+            check_format_strings(&entries),
+            [
+                "msgid \"%s: Invalid job %d\": msgstr expects arguments [String] instead of [String, Int]",
+                "msgid \"%s\": msgstr: incomplete directive at the end",
+                "msgid \"%d jobs\": msgstr[1] expects arguments [String] instead of [Int]",
+            ]
+        );
+    }
+}
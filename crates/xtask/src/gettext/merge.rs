@@ -0,0 +1,304 @@
+//! Merging of a translated catalog with a freshly extracted template, like `msgmerge` does.
+//! The result contains the entries of the template, in the template's order, with translations
+//! taken from the catalog where possible.
+
+use super::po::{PoEntry, escape};
+use std::collections::HashMap;
+
+pub struct MergeOptions {
+    /// Entries of the catalog whose msgid is at least this similar to a new msgid of the template
+    /// are used as a fuzzy translation for it. `None` disables fuzzy matching.
+    pub fuzzy_threshold: Option<f64>,
+    /// Measures the similarity of two msgids, from 0 (unrelated) to 1 (equal).
+    pub similarity: fn(&str, &str) -> f64,
+    /// Whether translations which are no longer used are kept as obsolete entries.
+    pub keep_obsolete: bool,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            fuzzy_threshold: Some(0.6),
+            similarity: levenshtein_similarity,
+            keep_obsolete: true,
+        }
+    }
+}
+
+/// One minus the edit distance of the strings, relative to the length of the longer one.
+pub fn levenshtein_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let max_len = a.len().max(b.len());
+    if max_len == 0 {
+        return 1.0;
+    }
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    1.0 - row[b.len()] as f64 / max_len as f64
+}
+
+type Key<'a> = (Option<&'a str>, &'a str);
+
+fn key(entry: &PoEntry) -> Key<'_> {
+    (entry.msgctxt.as_deref(), &entry.msgid)
+}
+
+/// The number of plural forms of the catalog's language, as declared in the `Plural-Forms` field
+/// of its header. Defaults to 2, as used by English.
+fn plural_form_count(catalog: &[PoEntry]) -> usize {
+    catalog
+        .iter()
+        .find(|entry| entry.is_header())
+        .and_then(|header| header.msgstr.first())
+        .and_then(|header| {
+            let plural_forms = header
+                .lines()
+                .find_map(|line| line.strip_prefix("Plural-Forms:"))?;
+            plural_forms.split(';').find_map(|field| {
+                let (key, value) = field.split_once('=')?;
+                (key.trim() == "nplurals").then(|| value.trim().parse().ok())?
+            })
+        })
+        .unwrap_or(2)
+}
+
+/// Untranslated strings with the number of forms this catalog uses for `entry`.
+fn untranslated_msgstr(entry: &PoEntry, plural_form_count: usize) -> Vec<String> {
+    match entry.msgid_plural {
+        Some(_) => vec![String::new(); plural_form_count],
+        None => vec![String::new()],
+    }
+}
+
+/// Take the data which belongs to the translation from `translated` and everything describing the
+/// message from `template`.
+fn merge_entry(translated: &PoEntry, template: &PoEntry, plural_form_count: usize) -> PoEntry {
+    let mut msgstr = translated.msgstr.clone();
+    // The number of forms is determined by the catalog's language, so it only changes when an
+    // entry gains or loses its plural form.
+    if template.msgid_plural.is_some() != translated.msgid_plural.is_some() {
+        msgstr = untranslated_msgstr(template, plural_form_count);
+    }
+    let mut merged = PoEntry {
+        translator_comments: translated.translator_comments.clone(),
+        msgstr,
+        previous: vec![],
+        obsolete: false,
+        ..template.clone()
+    };
+    merged.set_fuzzy(translated.is_fuzzy());
+    if merged.is_fuzzy() {
+        merged.previous = translated.previous.clone();
+    }
+    merged
+}
+
+/// Update `catalog` to the messages of `template`:
+/// - Translations of msgids which are still used are preserved.
+/// - New msgids get the translation of a similar old msgid, marked as fuzzy, if one exists.
+///   Otherwise, they are untranslated.
+/// - Translations of msgids which are no longer used become obsolete entries, or are dropped.
+///
+/// The header of `catalog` is kept, the one of `template` is ignored.
+pub fn merge(catalog: &[PoEntry], template: &[PoEntry], options: &MergeOptions) -> Vec<PoEntry> {
+    // Obsolete entries are revived if their msgid is used again, unless there is a live one.
+    let mut translations: HashMap<Key, &PoEntry> = HashMap::new();
+    for entry in catalog {
+        if !entry.obsolete || !translations.contains_key(&key(entry)) {
+            translations.insert(key(entry), entry);
+        }
+    }
+    let template_keys: HashMap<Key, &PoEntry> =
+        template.iter().map(|entry| (key(entry), entry)).collect();
+    // Translated entries which are not needed for an exact match, and could therefore serve as
+    // fuzzy matches. Obsolete entries are included, so that old translations can be revived.
+    let mut unused: Vec<&PoEntry> = catalog
+        .iter()
+        .filter(|entry| {
+            !entry.is_header() && entry.is_translated() && !template_keys.contains_key(&key(entry))
+        })
+        .collect();
+
+    let plural_form_count = plural_form_count(catalog);
+    let mut result = vec![];
+    if let Some(header) = catalog.iter().find(|entry| entry.is_header()) {
+        result.push(header.clone());
+    }
+    for entry in template.iter().filter(|entry| !entry.is_header()) {
+        if let Some(translated) = translations.get(&key(entry)) {
+            result.push(merge_entry(translated, entry, plural_form_count));
+            continue;
+        }
+        let fuzzy_match = options.fuzzy_threshold.and_then(|threshold| {
+            unused
+                .iter()
+                .enumerate()
+                .filter(|(_, old)| old.msgctxt == entry.msgctxt)
+                .map(|(i, old)| (i, (options.similarity)(&old.msgid, &entry.msgid)))
+                .filter(|&(_, similarity)| similarity >= threshold)
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        });
+        match fuzzy_match {
+            Some((i, _)) => {
+                let old = unused.remove(i);
+                let mut merged = merge_entry(old, entry, plural_form_count);
+                merged.set_fuzzy(true);
+                merged.previous = vec![format!("msgid \"{}\"", escape(&old.msgid))];
+                result.push(merged);
+            }
+            None => result.push(PoEntry {
+                msgstr: untranslated_msgstr(entry, plural_form_count),
+                ..entry.clone()
+            }),
+        }
+    }
+
+    if options.keep_obsolete {
+        result.extend(unused.into_iter().map(|entry| PoEntry {
+            obsolete: true,
+            ..entry.clone()
+        }));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MergeOptions, levenshtein_similarity, merge};
+    use crate::gettext::po::PoEntry;
+
+    fn translated(msgid: &str, msgstr: &str) -> PoEntry {
+        PoEntry {
+            msgstr: vec![msgstr.to_owned()],
+            ..PoEntry::new(msgid)
+        }
+    }
+
+    #[test]
+    fn test_similarity() {
+        assert_eq!(levenshtein_similarity("", ""), 1.0);
+        assert_eq!(levenshtein_similarity("abc", "abc"), 1.0);
+        assert_eq!(levenshtein_similarity("abc", "xyz"), 0.0);
+        assert_eq!(levenshtein_similarity("kitten", "sitting"), 1.0 - 3.0 / 7.0);
+    }
+
+    #[test]
+    fn test_merge() {
+        let header = translated("", "Language: de\n");
+        let mut kept = translated("Unknown option", "Unbekannte Option");
+        kept.translator_comments = vec!["Checked".to_owned()];
+        kept.references = vec!["src/old.rs:1".to_owned()];
+        let catalog = vec![
+            header.clone(),
+            kept,
+            translated("%s: Invalid job id", "%s: Ungültige Job-ID"),
+            translated("Removed message", "Entfernte Nachricht"),
+            PoEntry::new("Untranslated and removed"),
+        ];
+
+        let mut kept_template = PoEntry::new("Unknown option");
+        kept_template.references = vec!["src/new.rs:2".to_owned()];
+        let template = vec![
+            PoEntry::new(""),
+            PoEntry::new("Press any key"),
+            kept_template,
+            PoEntry::new("%s: Invalid job ID"),
+        ];
+
+        let merged = merge(&catalog, &template, &MergeOptions::default());
+        let mut expected_kept = translated("Unknown option", "Unbekannte Option");
+        expected_kept.translator_comments = vec!["Checked".to_owned()];
+        expected_kept.references = vec!["src/new.rs:2".to_owned()];
+        let mut expected_fuzzy = translated("%s: Invalid job ID", "%s: Ungültige Job-ID");
+        expected_fuzzy.set_fuzzy(true);
+        expected_fuzzy.previous = vec!["msgid \"%s: Invalid job id\"".to_owned()];
+        let expected_obsolete = PoEntry {
+            obsolete: true,
+            ..translated("Removed message", "Entfernte Nachricht")
+        };
+        assert_eq!(
+            merged,
+            [
+                header.clone(),
+                PoEntry::new("Press any key"),
+                expected_kept.clone(),
+                expected_fuzzy,
+                expected_obsolete,
+            ]
+        );
+
+        let options = MergeOptions {
+            fuzzy_threshold: None,
+            keep_obsolete: false,
+            ..MergeOptions::default()
+        };
+        let merged = merge(&catalog, &template, &options);
+        assert_eq!(
+            merged,
+            [
+                header,
+                PoEntry::new("Press any key"),
+                expected_kept,
+                PoEntry::new("%s: Invalid job ID"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_merge_plural_form_count() {
+        let header = translated(
+            "",
+            "Plural-Forms: nplurals=3; plural=(n==1 ? 0 : n<5 ? 1 : 2);\n",
+        );
+        let plural = PoEntry {
+            msgid_plural: Some("%d jobs".to_owned()),
+            msgstr: vec![String::new(); 2],
+            ..PoEntry::new("%d job")
+        };
+        let merged = merge(
+            std::slice::from_ref(&header),
+            std::slice::from_ref(&plural),
+            &MergeOptions::default(),
+        );
+        assert_eq!(
+            merged,
+            [
+                header,
+                PoEntry {
+                    msgstr: vec![String::new(); 3],
+                    ..plural.clone()
+                }
+            ]
+        );
+        // Without a header, English is assumed.
+        assert_eq!(
+            merge(&[], std::slice::from_ref(&plural), &MergeOptions::default()),
+            [plural]
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_fuzzy_flag_and_context() {
+        let mut fuzzy = translated("Job", "Auftrag");
+        fuzzy.set_fuzzy(true);
+        fuzzy.msgctxt = Some("noun".to_owned());
+        let template = vec![
+            PoEntry {
+                msgctxt: Some("noun".to_owned()),
+                ..PoEntry::new("Job")
+            },
+            PoEntry::new("Job"),
+        ];
+        let merged = merge(&[fuzzy.clone()], &template, &MergeOptions::default());
+        assert_eq!(merged, [fuzzy, PoEntry::new("Job")]);
+    }
+}
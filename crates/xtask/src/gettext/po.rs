@@ -0,0 +1,604 @@
+//! Parsing and serialization of PO files, following the formatting of the GNU gettext tools.
+//! Format reference used: <https://www.gnu.org/software/gettext/manual/html_node/PO-Files.html>
+
+use std::{
+    fmt::Display,
+    io::{self, Write},
+};
+
+/// The line width used by the GNU gettext tools when wrapping strings.
+pub const DEFAULT_WRAP_WIDTH: usize = 79;
+
+/// A single entry of a PO file.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PoEntry {
+    /// Comments by translators, written as `# comment`.
+    pub translator_comments: Vec<String>,
+    /// Comments extracted from the sources, written as `#. comment`.
+    pub extracted_comments: Vec<String>,
+    /// Source locations, written as `#: file:line`.
+    pub references: Vec<String>,
+    /// Flags such as `fuzzy` or `c-format`, written as `#, flag, flag`.
+    pub flags: Vec<String>,
+    /// The previous msgid of a fuzzy entry, written as `#| msgid "..."`. Kept verbatim.
+    pub previous: Vec<String>,
+    /// Whether the entry is obsolete, i.e. no longer used in the sources. Obsolete entries are
+    /// written with a `#~ ` prefix.
+    pub obsolete: bool,
+    pub msgctxt: Option<String>,
+    pub msgid: String,
+    pub msgid_plural: Option<String>,
+    /// The translation. Entries with a `msgid_plural` have one string per plural form,
+    /// others have exactly one.
+    pub msgstr: Vec<String>,
+}
+
+impl PoEntry {
+    /// Create an untranslated entry without comments.
+    pub fn new(msgid: impl Into<String>) -> Self {
+        Self {
+            msgid: msgid.into(),
+            msgstr: vec![String::new()],
+            ..Default::default()
+        }
+    }
+
+    /// The header is the entry with the empty msgid, containing metadata about the catalog.
+    pub fn is_header(&self) -> bool {
+        self.msgid.is_empty() && self.msgctxt.is_none()
+    }
+
+    /// Whether any translation is present.
+    pub fn is_translated(&self) -> bool {
+        self.msgstr.iter().any(|msgstr| !msgstr.is_empty())
+    }
+
+    pub fn is_fuzzy(&self) -> bool {
+        self.flags.iter().any(|flag| flag == "fuzzy")
+    }
+
+    /// Add or remove the `fuzzy` flag.
+    pub fn set_fuzzy(&mut self, fuzzy: bool) {
+        self.flags.retain(|flag| flag != "fuzzy");
+        if fuzzy {
+            self.flags.insert(0, "fuzzy".to_owned());
+        }
+    }
+}
+
+/// Escape a string the way it must appear between the quotes of a PO file string.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\x07' => escaped.push_str("\\a"),
+            '\x08' => escaped.push_str("\\b"),
+            '\x0b' => escaped.push_str("\\v"),
+            '\x0c' => escaped.push_str("\\f"),
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\{:03o}", u32::from(c))),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Split an escaped string into pieces which fit into `width` columns once quoted, breaking after
+/// spaces, like the GNU tools do.
+fn wrap(escaped: &str, width: usize) -> Vec<&str> {
+    // Room for the quotes.
+    let max_len = width.saturating_sub(2).max(1);
+    let mut lines = vec![];
+    let mut rest = escaped;
+    while rest.chars().count() > max_len {
+        // Break after the last space which keeps the line short enough. If there is none,
+        // the line has to be too long, so break at the first space after that.
+        let limit = rest
+            .char_indices()
+            .nth(max_len)
+            .map_or(rest.len(), |(i, _)| i);
+        let break_at = rest[..limit]
+            .rfind(' ')
+            .or_else(|| rest[limit..].find(' ').map(|i| limit + i))
+            .map(|i| i + 1);
+        match break_at {
+            Some(i) if i < rest.len() => {
+                lines.push(&rest[..i]);
+                rest = &rest[i..];
+            }
+            _ => break,
+        }
+    }
+    lines.push(rest);
+    lines
+}
+
+/// Write `keyword "value"`. Values which contain a newline before their end are split after each
+/// newline, onto lines following an empty string. Unless `width` is `None`, this is also done for
+/// values which are too long, which are wrapped at spaces.
+fn write_string(
+    out: &mut impl Write,
+    prefix: &str,
+    keyword: &str,
+    value: &str,
+    width: Option<usize>,
+) -> io::Result<()> {
+    let segments: Vec<String> = value.split_inclusive('\n').map(escape).collect();
+    let escaped = segments.concat();
+    let lines: Vec<&str> = match width {
+        None => segments.iter().map(String::as_str).collect(),
+        Some(width) => segments.iter().flat_map(|s| wrap(s, width)).collect(),
+    };
+    let fits_on_one_line = width.is_none_or(|width| {
+        prefix.len() + keyword.len() + 1 + escaped.chars().count() + 2 <= width
+    });
+    if lines.len() <= 1 && fits_on_one_line {
+        return writeln!(out, "{prefix}{keyword} \"{escaped}\"");
+    }
+    writeln!(out, "{prefix}{keyword} \"\"")?;
+    for line in lines {
+        writeln!(out, "{prefix}\"{line}\"")?;
+    }
+    Ok(())
+}
+
+fn write_entry(out: &mut impl Write, entry: &PoEntry, width: Option<usize>) -> io::Result<()> {
+    for comment in &entry.translator_comments {
+        if comment.is_empty() {
+            writeln!(out, "#")?;
+        } else {
+            writeln!(out, "# {comment}")?;
+        }
+    }
+    for comment in &entry.extracted_comments {
+        writeln!(out, "#. {comment}")?;
+    }
+    if !entry.references.is_empty() {
+        writeln!(out, "#: {}", entry.references.join(" "))?;
+    }
+    if !entry.flags.is_empty() {
+        writeln!(out, "#, {}", entry.flags.join(", "))?;
+    }
+    for previous in &entry.previous {
+        writeln!(out, "#| {previous}")?;
+    }
+    let prefix = if entry.obsolete { "#~ " } else { "" };
+    if let Some(msgctxt) = &entry.msgctxt {
+        write_string(out, prefix, "msgctxt", msgctxt, width)?;
+    }
+    write_string(out, prefix, "msgid", &entry.msgid, width)?;
+    match &entry.msgid_plural {
+        Some(msgid_plural) => {
+            write_string(out, prefix, "msgid_plural", msgid_plural, width)?;
+            for (i, msgstr) in entry.msgstr.iter().enumerate() {
+                write_string(out, prefix, &format!("msgstr[{i}]"), msgstr, width)?;
+            }
+        }
+        None => {
+            let msgstr = entry.msgstr.first().map_or("", String::as_str);
+            write_string(out, prefix, "msgstr", msgstr, width)?;
+        }
+    }
+    Ok(())
+}
+
+/// Serialize a catalog, separating entries by empty lines.
+/// Strings are wrapped at `wrap_width` columns, or not at all if it is `None`, which corresponds
+/// to the `--no-wrap` option of the GNU tools.
+pub fn write_po_file(
+    out: &mut impl Write,
+    entries: &[PoEntry],
+    wrap_width: Option<usize>,
+) -> io::Result<()> {
+    for (i, entry) in entries.iter().enumerate() {
+        if i != 0 {
+            writeln!(out)?;
+        }
+        write_entry(out, entry, wrap_width)?;
+    }
+    Ok(())
+}
+
+/// An error found when parsing a PO file.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PoParseError {
+    /// The line on which the error was found, starting at 1.
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl Display for PoParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PoParseError {}
+
+/// Undo the escaping of a quoted PO string, including the quotes.
+fn unescape(quoted: &str) -> Result<String, &'static str> {
+    let inner = quoted
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .filter(|_| quoted.len() >= 2)
+        .ok_or("Expected a string in double quotes")?;
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c == '"' {
+            return Err("Unescaped double quote in string");
+        }
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        let escaped = match chars.next().ok_or("Backslash at end of string")? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'a' => '\x07',
+            'b' => '\x08',
+            'v' => '\x0b',
+            'f' => '\x0c',
+            c @ ('\\' | '"' | '\'' | '?') => c,
+            c @ '0'..='7' => {
+                let mut value = c.to_digit(8).unwrap();
+                for _ in 0..2 {
+                    match chars.clone().next().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            value = value * 8 + digit;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                char::from_u32(value).ok_or("Invalid octal escape")?
+            }
+            _ => return Err("Unknown escape sequence"),
+        };
+        result.push(escaped);
+    }
+    Ok(result)
+}
+
+/// Which string of the current entry continuation lines are appended to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    None,
+    Msgctxt,
+    Msgid,
+    MsgidPlural,
+    Msgstr(usize),
+}
+
+/// Parse a PO file into its entries, in order. The header is the first entry, if present.
+pub fn parse_po_file(content: &str) -> Result<Vec<PoEntry>, PoParseError> {
+    let mut entries = vec![];
+    let mut entry = PoEntry::default();
+    let mut field = Field::None;
+
+    for (i, line) in content.lines().enumerate() {
+        let error = |message| PoParseError {
+            line: i + 1,
+            message,
+        };
+        let mut line = line.trim_end();
+        let mut obsolete = false;
+        // Previous msgids of obsolete entries are written as `#~|`, and treated like `#|`.
+        if let Some(rest) = line
+            .strip_prefix("#~")
+            .filter(|rest| !rest.starts_with('|'))
+        {
+            line = rest.trim_start();
+            obsolete = true;
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        // A comment or a new msgctxt/msgid after the msgstr begins the next entry.
+        let starts_entry = line.starts_with('#')
+            || line.starts_with("msgctxt")
+            || (line.starts_with("msgid ") && field != Field::Msgctxt);
+        if starts_entry && matches!(field, Field::Msgstr(_)) {
+            entries.push(std::mem::take(&mut entry));
+            field = Field::None;
+        }
+        if obsolete {
+            entry.obsolete = true;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(comment) = comment.strip_prefix('.') {
+                entry
+                    .extracted_comments
+                    .push(comment.trim_start().to_owned());
+            } else if let Some(references) = comment.strip_prefix(':') {
+                entry
+                    .references
+                    .extend(references.split_whitespace().map(str::to_owned));
+            } else if let Some(flags) = comment.strip_prefix(',') {
+                entry.flags.extend(
+                    flags
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|flag| !flag.is_empty())
+                        .map(str::to_owned),
+                );
+            } else if let Some(previous) = comment
+                .strip_prefix('|')
+                .or_else(|| comment.strip_prefix("~|"))
+            {
+                entry.previous.push(previous.trim_start().to_owned());
+            } else {
+                let comment = comment.strip_prefix(' ').unwrap_or(comment);
+                entry.translator_comments.push(comment.to_owned());
+            }
+            continue;
+        }
+
+        if line.starts_with('"') {
+            let value = unescape(line).map_err(error)?;
+            let target = match field {
+                Field::None => return Err(error("String without a keyword")),
+                Field::Msgctxt => entry.msgctxt.as_mut().unwrap(),
+                Field::Msgid => &mut entry.msgid,
+                Field::MsgidPlural => entry.msgid_plural.as_mut().unwrap(),
+                Field::Msgstr(index) => &mut entry.msgstr[index],
+            };
+            target.push_str(&value);
+            continue;
+        }
+
+        let (keyword, quoted) = line
+            .split_once(' ')
+            .ok_or_else(|| error("Expected a keyword followed by a string"))?;
+        let value = unescape(quoted.trim_start()).map_err(error)?;
+        match keyword {
+            "msgctxt" => {
+                entry.msgctxt = Some(value);
+                field = Field::Msgctxt;
+            }
+            "msgid" => {
+                entry.msgid = value;
+                field = Field::Msgid;
+            }
+            "msgid_plural" if field == Field::Msgid => {
+                entry.msgid_plural = Some(value);
+                field = Field::MsgidPlural;
+            }
+            "msgstr" if field == Field::Msgid => {
+                entry.msgstr = vec![value];
+                field = Field::Msgstr(0);
+            }
+            _ => {
+                let index = keyword
+                    .strip_prefix("msgstr[")
+                    .and_then(|rest| rest.strip_suffix(']'))
+                    .and_then(|index| index.parse::<usize>().ok())
+                    .filter(|&index| {
+                        entry.msgid_plural.is_some()
+                            && match field {
+                                Field::MsgidPlural => index == 0,
+                                Field::Msgstr(previous) => index == previous + 1,
+                                _ => false,
+                            }
+                    })
+                    .ok_or_else(|| error("Unexpected keyword"))?;
+                entry.msgstr.truncate(index);
+                entry.msgstr.push(value);
+                field = Field::Msgstr(index);
+            }
+        }
+    }
+
+    if field == Field::None && entry == PoEntry::default() {
+        // The file is empty.
+        return Ok(entries);
+    }
+    if !matches!(field, Field::Msgstr(_)) {
+        return Err(PoParseError {
+            line: content.lines().count(),
+            message: "Entry without msgstr at end of file",
+        });
+    }
+    entries.push(entry);
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DEFAULT_WRAP_WIDTH, PoEntry, PoParseError, parse_po_file, write_po_file};
+
+    fn serialize(entries: &[PoEntry], wrap_width: Option<usize>) -> String {
+        let mut out = vec![];
+        write_po_file(&mut out, entries, wrap_width).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_escaping() {
+        let entry = PoEntry::new("say \"hi\"\\\tnow\x01");
+        assert_eq!(
+            serialize(&[entry], None),
+            "msgid \"say \\\"hi\\\"\\\\\\tnow\\001\"\nmsgstr \"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_comments_and_flags() {
+        let mut entry = PoEntry::new("%s: Unknown option");
+        entry.translator_comments = vec!["Checked by a translator".to_owned(), String::new()];
+        entry.extracted_comments = vec!["Shown by builtins".to_owned()];
+        entry.references = vec!["src/a.rs:1".to_owned(), "src/b.rs:2".to_owned()];
+        entry.flags = vec!["c-format".to_owned()];
+        entry.set_fuzzy(true);
+        assert!(entry.is_fuzzy());
+        entry.msgctxt = Some("builtin".to_owned());
+        entry.msgstr = vec!["%s: Unbekannte Option".to_owned()];
+        let header = PoEntry {
+            msgstr: vec!["Content-Type: text/plain; charset=UTF-8\n".to_owned()],
+            ..PoEntry::new("")
+        };
+        assert_eq!(
+            serialize(&[header, entry], Some(DEFAULT_WRAP_WIDTH)),
+            "msgid \"\"\n\
+             msgstr \"Content-Type: text/plain; charset=UTF-8\\n\"\n\
+             \n\
+             # Checked by a translator\n\
+             #\n\
+             #. Shown by builtins\n\
+             #: src/a.rs:1 src/b.rs:2\n\
+             #, fuzzy, c-format\n\
+             msgctxt \"builtin\"\n\
+             msgid \"%s: Unknown option\"\n\
+             msgstr \"%s: Unbekannte Option\"\n"
+        );
+    }
+
+    #[test]
+    fn test_plural() {
+        let entry = PoEntry {
+            msgid_plural: Some("%d jobs".to_owned()),
+            msgstr: vec!["%d Job".to_owned(), "%d Jobs".to_owned()],
+            ..PoEntry::new("%d job")
+        };
+        assert_eq!(
+            serialize(&[entry], None),
+            "msgid \"%d job\"\n\
+             msgid_plural \"%d jobs\"\n\
+             msgstr[0] \"%d Job\"\n\
+             msgstr[1] \"%d Jobs\"\n"
+        );
+    }
+
+    #[test]
+    fn test_wrapping() {
+        let long = "This message is long enough that it does not fit on a single line, so it gets wrapped.";
+        let entry = PoEntry::new(long);
+        assert_eq!(
+            serialize(std::slice::from_ref(&entry), Some(DEFAULT_WRAP_WIDTH)),
+            "msgid \"\"\n\
+             \"This message is long enough that it does not fit on a single line, so it \"\n\
+             \"gets wrapped.\"\n\
+             msgstr \"\"\n"
+        );
+        assert_eq!(
+            serialize(&[entry], None),
+            format!("msgid \"{long}\"\nmsgstr \"\"\n")
+        );
+
+        // Embedded newlines always cause a break after them.
+        let entry = PoEntry::new("first\nsecond\n");
+        assert_eq!(
+            serialize(&[entry], Some(DEFAULT_WRAP_WIDTH)),
+            "msgid \"\"\n\
+             \"first\\n\"\n\
+             \"second\\n\"\n\
+             msgstr \"\"\n"
+        );
+
+        // Words longer than the line are not broken.
+        let word = "x".repeat(100);
+        let entry = PoEntry::new(format!("{word} y"));
+        assert_eq!(
+            serialize(&[entry], Some(DEFAULT_WRAP_WIDTH)),
+            format!("msgid \"\"\n\"{word} \"\n\"y\"\nmsgstr \"\"\n")
+        );
+    }
+
+    #[test]
+    fn test_newlines_without_wrapping() {
+        // Like the GNU tools with `--no-wrap`, break after newlines, but not after escaped ones.
+        let entry = PoEntry::new("a\\nb\nc");
+        assert_eq!(
+            serialize(&[entry], None),
+            "msgid \"\"\n\"a\\\\nb\\n\"\n\"c\"\nmsgstr \"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let content = r#"# fish-note-sections: A note
+#
+msgid ""
+msgstr ""
+"Language: de\n"
+"Plural-Forms: nplurals=2; plural=(n != 1);\n"
+
+#. Shown by builtins
+#: src/a.rs:1
+#: src/b.rs:2
+#, fuzzy, c-format
+#| msgid "%s: unknown option"
+msgctxt "builtin"
+msgid "%s: Unknown "
+"option\t\"%s\"\101"
+msgstr "%s: Unbekannte Option"
+
+msgid "%d job"
+msgid_plural "%d jobs"
+msgstr[0] "%d Job"
+msgstr[1] "%d Jobs"
+
+#~ msgid "Old"
+#~ msgstr "Alt"
+"#;
+        let entries = parse_po_file(content).unwrap();
+        let header = PoEntry {
+            translator_comments: vec!["fish-note-sections: A note".to_owned(), String::new()],
+            msgstr: vec!["Language: de\nPlural-Forms: nplurals=2; plural=(n != 1);\n".to_owned()],
+            ..PoEntry::new("")
+        };
+        let entry = PoEntry {
+            extracted_comments: vec!["Shown by builtins".to_owned()],
+            references: vec!["src/a.rs:1".to_owned(), "src/b.rs:2".to_owned()],
+            flags: vec!["fuzzy".to_owned(), "c-format".to_owned()],
+            previous: vec!["msgid \"%s: unknown option\"".to_owned()],
+            msgctxt: Some("builtin".to_owned()),
+            msgstr: vec!["%s: Unbekannte Option".to_owned()],
+            ..PoEntry::new("%s: Unknown option\t\"%s\"A")
+        };
+        let plural = PoEntry {
+            msgid_plural: Some("%d jobs".to_owned()),
+            msgstr: vec!["%d Job".to_owned(), "%d Jobs".to_owned()],
+            ..PoEntry::new("%d job")
+        };
+        let obsolete = PoEntry {
+            obsolete: true,
+            msgstr: vec!["Alt".to_owned()],
+            ..PoEntry::new("Old")
+        };
+        assert_eq!(entries, [header, entry, plural, obsolete]);
+        assert!(entries[0].is_header());
+        assert!(!entries[3].is_header() && entries[3].is_translated());
+
+        // Serializing and parsing again is lossless.
+        assert_eq!(parse_po_file(&serialize(&entries, None)).unwrap(), entries);
+        assert_eq!(
+            parse_po_file(&serialize(&entries, Some(DEFAULT_WRAP_WIDTH))).unwrap(),
+            entries
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |content| parse_po_file(content).unwrap_err();
+        assert_eq!(
+            error("msgid \"a\"\nmsgstr \"b\"\n\"c\n"),
+            PoParseError {
+                line: 3,
+                message: "Expected a string in double quotes"
+            }
+        );
+        assert_eq!(error("\"a\"\n").line, 1);
+        assert_eq!(error("msgid \"a\"\nmsgstr[1] \"b\"\n").line, 2);
+        assert_eq!(error("msgid \"a\\q\"\nmsgstr \"\"\n").line, 1);
+        assert_eq!(error("msgid \"a\"\n").line, 1);
+        assert_eq!(error("msgid \"a\"\nmsgstr \"b\"\nmsgid \"c\"\n").line, 3);
+    }
+}
@@ -0,0 +1,168 @@
+//! Translation coverage of catalogs, to see which languages are falling behind.
+
+use super::po::PoEntry;
+use std::fmt::Write as _;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CatalogStats {
+    pub translated: usize,
+    pub fuzzy: usize,
+    pub untranslated: usize,
+}
+
+impl CatalogStats {
+    /// Count the messages of a catalog. The header, obsolete entries, and the pseudo-messages
+    /// marking sections are not messages shown by fish, so they are ignored.
+    pub fn from_entries(entries: &[PoEntry]) -> Self {
+        let mut stats = Self::default();
+        for entry in entries {
+            if entry.is_header() || entry.obsolete || entry.msgid.starts_with("fish-section-") {
+                continue;
+            }
+            let fully_translated = entry.msgstr.iter().all(|msgstr| !msgstr.is_empty());
+            if !fully_translated {
+                stats.untranslated += 1;
+            } else if entry.is_fuzzy() {
+                stats.fuzzy += 1;
+            } else {
+                stats.translated += 1;
+            }
+        }
+        stats
+    }
+
+    pub fn total(&self) -> usize {
+        self.translated + self.fuzzy + self.untranslated
+    }
+
+    /// The percentage of messages which have a translation that is used, i.e. is not fuzzy.
+    pub fn coverage(&self) -> f64 {
+        match self.total() {
+            0 => 100.0,
+            total => 100.0 * self.translated as f64 / total as f64,
+        }
+    }
+}
+
+/// Format the statistics as a table with one row per language.
+pub fn format_table(stats: &[(String, CatalogStats)]) -> String {
+    let header = [
+        "Language",
+        "Translated",
+        "Fuzzy",
+        "Untranslated",
+        "Coverage",
+    ];
+    let rows: Vec<[String; 5]> = stats
+        .iter()
+        .map(|(language, stats)| {
+            [
+                language.clone(),
+                stats.translated.to_string(),
+                stats.fuzzy.to_string(),
+                stats.untranslated.to_string(),
+                format!("{:.1}%", stats.coverage()),
+            ]
+        })
+        .collect();
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let mut table = String::new();
+    let header = header.map(str::to_owned);
+    for row in std::iter::once(&header).chain(&rows) {
+        // The language is left-aligned, the numbers are right-aligned.
+        let mut line = format!("{:<width$}", row[0], width = widths[0]);
+        for (cell, width) in row.iter().zip(widths).skip(1) {
+            let _ = write!(line, "  {cell:>width$}");
+        }
+        table.push_str(&line);
+        table.push('\n');
+    }
+    table
+}
+
+/// Format the statistics as a JSON object mapping language names to their counts.
+pub fn format_json(stats: &[(String, CatalogStats)]) -> String {
+    let mut json = String::from("{");
+    for (i, (language, stats)) in stats.iter().enumerate() {
+        if i != 0 {
+            json.push(',');
+        }
+        // Language names consist of ASCII letters and underscores, so they need no escaping.
+        let _ = write!(
+            json,
+            "\n  \"{language}\": {{\"translated\": {}, \"fuzzy\": {}, \"untranslated\": {}, \"coverage\": {:.1}}}",
+            stats.translated,
+            stats.fuzzy,
+            stats.untranslated,
+            stats.coverage(),
+        );
+    }
+    json.push_str("\n}\n");
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CatalogStats, format_json, format_table};
+    use crate::gettext::po::PoEntry;
+
+    #[test]
+    fn test_stats() {
+        let translated = |msgid: &str| PoEntry {
+            msgstr: vec!["x".to_owned()],
+            ..PoEntry::new(msgid)
+        };
+        let mut fuzzy = translated("c");
+        fuzzy.set_fuzzy(true);
+        let partial_plural = PoEntry {
+            msgid_plural: Some("%d jobs".to_owned()),
+            msgstr: vec!["x".to_owned(), String::new()],
+            ..PoEntry::new("%d job")
+        };
+        let obsolete = PoEntry {
+            obsolete: true,
+            ..translated("d")
+        };
+        let entries = [
+            translated(""),
+            PoEntry::new("fish-section-tier1-from-rust"),
+            translated("a"),
+            translated("b"),
+            fuzzy,
+            PoEntry::new("e"),
+            partial_plural,
+            obsolete,
+        ];
+        let stats = CatalogStats::from_entries(&entries);
+        assert_eq!(
+            stats,
+            CatalogStats {
+                translated: 2,
+                fuzzy: 1,
+                untranslated: 2,
+            }
+        );
+        assert_eq!(stats.coverage(), 40.0);
+
+        let stats = [
+            ("de".to_owned(), stats),
+            ("pt_BR".to_owned(), CatalogStats::default()),
+        ];
+        assert_eq!(
+            format_table(&stats),
+            "Language  Translated  Fuzzy  Untranslated  Coverage\n\
+             de                 2      1             2     40.0%\n\
+             pt_BR              0      0             0    100.0%\n"
+        );
+        assert_eq!(
+            format_json(&stats),
+            "{\n  \"de\": {\"translated\": 2, \"fuzzy\": 1, \"untranslated\": 2, \"coverage\": 40.0},\n  \
+             \"pt_BR\": {\"translated\": 0, \"fuzzy\": 0, \"untranslated\": 0, \"coverage\": 100.0}\n}\n"
+        );
+    }
+}
@@ -79,3 +79,12 @@ complete --command argparse --short-option U --long-option unknown-arguments --n
     --description 'Whether unknown options can have arguments'
 complete --command argparse --short-option s --long-option stop-nonopt \
     --description 'Exit on subcommand'
+complete --command argparse --long-option subcommand --require-parameter --no-files \
+    --description 'Declare a subcommand'
+complete --command argparse --long-option generate-help \
+    -n '! __fish_seen_argument --long generate-completions' \
+    --description 'Print a usage message for the options'
+complete --command argparse --long-option generate-completions --require-parameter \
+    -n '! __fish_seen_argument --long generate-help' \
+    --arguments '(functions --all | string replace ", " "\n")' --no-files \
+    --description 'Print completions for the options'
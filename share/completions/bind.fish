@@ -5,6 +5,8 @@ set -l bind_optspecs \
     m/sets-mode= \
     preset \
     s/silent \
+    timeout= \
+    on-timeout= \
     user
 
 function __fish_bind_has_keys --inherit-variable bind_optspecs
@@ -17,6 +19,9 @@ complete -c bind -f
 complete -c bind -s a -l all -d 'Show unavailable key bindings/erase all bindings'
 complete -c bind -s e -l erase -d 'Erase mode'
 complete -c bind -s f -l function-names -d 'Print names of available functions'
+complete -c bind -l define-function -d 'Define an input function'
+complete -c bind -l translate -d 'Translate a sequence sent by the terminal to a key'
+complete -c bind -l term -x -d 'Terminal for --translate'
 complete -c bind -s h -l help -d "Display help and exit"
 complete -c bind -s K -l key-names -d 'Print names of available keys'
 complete -c bind -s M -l mode -d 'Specify the bind mode that the bind is used in' -xa '(bind -L)'
@@ -26,6 +31,8 @@ complete -c bind -s s -l silent -d 'Operate silently'
 complete -c bind -l preset -d 'Operate on preset bindings'
 complete -c bind -l user -d 'Operate on user bindings'
 complete -c bind -l color -d 'When to colorize output' -xa 'always never auto'
+complete -c bind -l timeout -d 'Require keys of the sequence to follow within MS milliseconds' -x
+complete -c bind -l on-timeout -d 'Command to run if the sequence times out' -xa '(bind --function-names)'
 
 complete -c bind -n '__fish_bind_has_keys (commandline -pcx)' -a '(bind --function-names)' -d 'Function name' -x
 
@@ -0,0 +1,13 @@
+# Completion for builtin breakpoint
+# This follows a strict command-then-options approach, so we can just test the number of tokens
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -le 2" -s h -l help -d "Display help and exit"
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -lt 2" -a step -d 'Run the next command and stop'
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -lt 2" -a next -d 'Run the next command and stop, stepping over functions'
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -lt 2" -a continue -d 'Run until a breakpoint'
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -lt 2" -a add -d 'Add a breakpoint at a line'
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -lt 2" -a watch -d 'Stop when a variable changes'
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -lt 2" -a delete -d 'Remove breakpoints and watchpoints'
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -lt 2" -a list -d 'List breakpoints and watchpoints'
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -lt 2" -a where -d 'Show the command about to run'
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] add" -s c -l condition -x -d 'Only stop if this command succeeds'
+complete -f -c breakpoint -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] watch" -a "(set -n)" -d Variable
@@ -3,6 +3,7 @@ complete -c commandline -s a -l append -d "Add text to the end of the selected a
 complete -c commandline -s i -l insert -d "Add text at cursor"
 complete -c commandline -s i -l insert-smart -d 'Add text at cursor but DWIM, stripping leading $'
 complete -c commandline -s r -l replace -d "Replace selected part"
+complete -c commandline -l insert-snippet -d "Add text with placeholders at cursor"
 
 complete -c commandline -s j -l current-job -d "Select job under cursor"
 complete -c commandline -s p -l current-process -d "Select process under cursor"
@@ -13,6 +14,7 @@ complete -c commandline -s b -l current-buffer -d "Select entire command line (d
 complete -c commandline -s c -l cut-at-cursor -d "Only return that part of the command line before the cursor"
 complete -c commandline -s f -l function -d "execute readline function"
 complete -c commandline -s x -l tokens-expanded -d "Print a list of expanded tokens"
+complete -c commandline -l tokens-json -d "Print tokens with their type and position as JSON"
 
 complete -c commandline -s I -l input -d "Specify command to operate on"
 complete -c commandline -s C -l cursor -d "Set/get cursor position, not buffer contents"
@@ -24,6 +26,7 @@ complete -c commandline -s S -l search-mode -d "Return true if performing a hist
 complete -c commandline -s P -l paging-mode -d "Return true if showing pager content"
 complete -c commandline -l paging-full-mode -d "Return true if pager is showing all content"
 complete -c commandline -l search-field -d "Operate on the pager search field"
+complete -c commandline -l register -x -a "(string split '' abcdefghijklmnopqrstuvwxyz0123456789)" -d "Operate on a register"
 complete -c commandline -l is-valid -d "Return true if the command line is syntactically valid and complete"
 complete -c commandline -l showing-suggestion -d "Return true if the command line has an autosuggestion"
 
@@ -16,6 +16,10 @@ complete -c complete -s e -l erase -d "Remove completion"
 complete -c complete -s h -l help -d "Display help and exit"
 complete -c complete -s C -l do-complete -d "Print completions for a commandline specified as a parameter"
 complete -c complete -l escape -d "Make -C escape special characters"
+complete -c complete -l load-json -d "Add completions described by a JSON file" -r
+complete -c complete -l cache-ttl -d "Cache arguments for this many seconds" -x
+complete -c complete -l cache-key -d "Only use cached arguments while this expands the same" -x
+complete -c complete -l clear-cache -d "Forget cached arguments"
 complete -c complete -s n -l condition -d "Completion only used if command has zero exit status" -x
 complete -c complete -s w -l wraps -d "Inherit completions from specified command" -xa '(__fish_complete_command)'
 complete -c complete -s k -l keep-order -d "Keep order of arguments instead of sorting alphabetically"
@@ -0,0 +1,3 @@
+complete -c count -n __fish_is_first_arg -l lines -d "Count lines, including a last one without newline"
+complete -c count -n __fish_is_first_arg -l bytes -d "Count bytes"
+complete -c count -n __fish_is_first_arg -l max-width -d "Print the width of the widest line"
@@ -6,11 +6,17 @@ complete -c fish -s N -l no-config -d "Do not read configuration files"
 complete -c fish -s n -l no-execute -d "Only parse input, do not execute"
 complete -c fish -s i -l interactive -d "Run in interactive mode"
 complete -c fish -s l -l login -d "Run as a login shell"
+complete -c fish -l lsp -d "Run as a language server"
+complete -c fish -l lint -d "Check scripts for likely mistakes instead of running them"
+complete -c fish -l lint-format -d "Output format for --lint" -x -a "text\t'FILE:LINE:COLUMN: MESSAGE' json\t'One JSON object per line'"
+complete -c fish -l lint-ignore -d "Checks to skip in --lint" -x -a "useless-eval unquoted-variable test-arguments deprecated-syntax"
 complete -c fish -s p -l profile -d "Output profiling information (excluding startup) to a file" -r
 complete -c fish -l profile-startup -d "Output startup profiling information to a file" -r
 complete -c fish -s d -l debug -d "Specify debug categories" -x -a "(fish --print-debug-categories | string replace ' ' \t)"
 complete -c fish -s o -l debug-output -d "Where to direct debug output to" -rF
+complete -c fish -l debug-script -d "Run the script in the debugger"
 complete -c fish -s P -l private -d "Do not persist history"
+complete -c fish -l record-input -d "Record terminal input to a file" -rF
 
 function __fish_complete_features
     set -l arg_comma (commandline -tc | string replace -rf '(.*,)[^,]*' '$1' | string replace -r -- '--.*=' '')
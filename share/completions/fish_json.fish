@@ -0,0 +1,10 @@
+# Completion for builtin fish_json
+# This follows a strict command-then-options approach, so we can just test the number of tokens
+complete -f -c fish_json -n "test (count (commandline -xpc)) -le 2" -s h -l help -d "Display help and exit"
+complete -f -c fish_json -n "test (count (commandline -xpc)) -lt 2" -a get -d 'Print the value at a query from JSON on stdin'
+complete -f -c fish_json -n "test (count (commandline -xpc)) -lt 2" -a keys -d 'Print the keys of an object from JSON on stdin'
+complete -f -c fish_json -n "test (count (commandline -xpc)) -lt 2" -a encode -d 'Encode arguments as a JSON array or object'
+complete -f -c fish_json -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] get" -s q -l quiet -d "Only return status, no output"
+complete -f -c fish_json -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] get" -s s -l set -d "Store the value in a variable" -x -a '(set -n)'
+complete -f -c fish_json -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] encode" -s o -l object -d "Encode pairs of keys and values as an object"
+complete -f -c fish_json -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] encode" -s r -l raw -d "Treat values as JSON instead of strings"
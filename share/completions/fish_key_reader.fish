@@ -2,3 +2,4 @@ complete -c fish_key_reader -s h -l help -d 'Display help and exit'
 complete -c fish_key_reader -s v -l version -d 'Display version and exit'
 complete -c fish_key_reader -s c -l continuous -d 'Start a continuous session'
 complete -c fish_key_reader -s V -l verbose -d 'Output timing and explain sequence'
+complete -c fish_key_reader -s r -l replay -d 'Decode keys recorded with fish --record-input' -rF
@@ -0,0 +1,6 @@
+# Completion for builtin fish_terminal_clipboard
+# This follows a strict command-then-options approach, so we can just test the number of tokens
+complete -f -c fish_terminal_clipboard -n "test (count (commandline -xpc)) -le 2" -s h -l help -d "Display help and exit"
+complete -f -c fish_terminal_clipboard -n "test (count (commandline -xpc)) -lt 2" -a copy -d 'Copy arguments or stdin to the clipboard'
+complete -f -c fish_terminal_clipboard -n "test (count (commandline -xpc)) -lt 2" -a paste -d 'Print the clipboard contents'
+complete -f -c fish_terminal_clipboard -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] copy" -s f -l force -d "Copy even if the terminal may not support it"
@@ -0,0 +1,8 @@
+complete -c fish_update_completions -s m -l manpath -d 'Parse all pages in the manpath'
+complete -c fish_update_completions -s d -l directory -x -a '(__fish_complete_directories)' -d 'Write completions into this directory'
+complete -c fish_update_completions -s s -l stdout -d 'Print completions instead of writing them'
+complete -c fish_update_completions -s k -l keep -d 'Keep previously generated completions'
+complete -c fish_update_completions -s c -l cleanup-in -x -a '(__fish_complete_directories)' -d 'Remove generated completions in this directory'
+complete -c fish_update_completions -s p -l progress -d 'Show progress'
+complete -c fish_update_completions -s v -l verbose -d 'Report pages that could not be parsed'
+complete -c fish_update_completions -s h -l help -d 'Display help and exit'
@@ -1,20 +1,22 @@
 function __fish_maybe_list_all_functions
     # if the current commandline token starts with an _, list all functions
     if string match -qr -- '^_' (commandline -ct)
-        functions -an
+        functions -anv
     else
-        functions -n
+        functions -nv
     end
 end
 
 complete -c functions -s e -l erase -d "Erase function" -x -a "(__fish_maybe_list_all_functions)"
-complete -c functions -xa "(functions -na)" -d Function
+complete -c functions -xa "(functions -nav)" -d Function
 complete -c functions -s a -l all -d "Show hidden functions"
 complete -c functions -s h -l help -d "Display help and exit"
 complete -c functions -s d -l description -d "Set function description" -x
 complete -c functions -s q -l query -d "Test if function is defined"
 complete -c functions -s n -l names -d "List the names of the functions, but not their definition"
 complete -c functions -s c -l copy -d "Copy the specified function to the specified new name"
+complete -c functions -l depends -d "List the commands the function invokes"
+complete -c functions -l recursive -d "With --depends, include the commands of invoked functions"
 complete -c functions -s D -l details -d "Display information about the function"
 complete -c functions -s v -l verbose -d "Print more output"
 complete -c functions -s H -l handlers -d "Show event handlers"
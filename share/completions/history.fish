@@ -1,19 +1,23 @@
 # Note that when a completion file is sourced a new block scope is created so `set -l` works.
-set -l __fish_history_all_commands search delete save merge clear clear-session append
+set -l __fish_history_all_commands search delete save merge clear clear-session append sync import export redact
 
 complete -c history -s h -l help -d "Display help and exit"
 
-# Note that these options are only valid with the "search" and "delete" subcommands.
-complete -c history -n '__fish_seen_subcommand_from search delete; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
+# Note that these options are only valid with the "search", "delete" and "redact" subcommands.
+complete -c history -n '__fish_seen_subcommand_from search delete redact; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
     -s p -l prefix -d "Match items beginning with the string"
-complete -c history -n '__fish_seen_subcommand_from search delete; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
+complete -c history -n '__fish_seen_subcommand_from search delete redact; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
     -s c -l contains -d "Match items containing the string"
-complete -c history -n '__fish_seen_subcommand_from search delete; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
+complete -c history -n '__fish_seen_subcommand_from search delete redact; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
     -s e -l exact -d "Match items identical to the string"
 complete -c history -n '__fish_seen_subcommand_from search delete; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
     -s t -l show-time -d "Output with timestamps"
-complete -c history -n '__fish_seen_subcommand_from search delete; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
+complete -c history -n '__fish_seen_subcommand_from search delete redact; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
     -s C -l case-sensitive -d "Match items in a case-sensitive manner"
+complete -c history -n '__fish_seen_subcommand_from search delete; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
+    -l cwd -d "Match items run in the directory" -xa "(__fish_complete_directories)"
+complete -c history -n '__fish_seen_subcommand_from search delete; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
+    -l failed-only -d "Match items which failed"
 
 # Note that these options are only valid with the "search" subcommand.
 complete -c history -n '__fish_seen_subcommand_from search; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
@@ -25,14 +29,26 @@ complete -c history -n '__fish_seen_subcommand_from search; or not __fish_seen_s
 complete -c history -n '__fish_seen_subcommand_from search; or not __fish_seen_subcommand_from $__fish_history_all_commands' \
     -l color -d "When to colorize output" -xa "always never auto"
 
+complete -c history -n '__fish_seen_subcommand_from import export' \
+    -l format -d "Format of the other shell's history" -xa "bash zsh atuin"
+complete -c history -n '__fish_seen_subcommand_from import' -F
+
 # We don't include a completion for the "save" subcommand because it should not be used
 # interactively.
 complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
     -a search -d "Prints commands from history matching the strings"
 complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
     -a delete -d "Deletes commands from history matching the strings"
+complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
+    -a redact -d "Removes commands containing a secret, also from disk"
 complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
     -a merge -d "Incorporate history changes from other sessions"
+complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
+    -a sync -d "Exchange history with other machines"
+complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
+    -a import -d "Add the history of another shell"
+complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
+    -a export -d "Print the history in the format of another shell"
 complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
     -a clear -d "Clears history file"
 complete -f -c history -n "not __fish_seen_subcommand_from $__fish_history_all_commands" \
@@ -2,5 +2,9 @@ complete -c jobs -s h -l help -d 'Display help and exit'
 complete -c jobs -s p -l pid -d "Show the process ID of each process in the job"
 complete -c jobs -s g -l group -d "Show group ID of job"
 complete -c jobs -s c -l command -d "Show commandname of each job"
+complete -c jobs -l format -x -d "Print each job according to a format"
+complete -c jobs -l json -d "Print each job as a JSON object"
 complete -c jobs -s l -l last -d "Only show status for last job to be started"
 complete -c jobs -s q -l quiet -l query -d "Check if a job exists without output"
+complete -c jobs -s o -l output -d "Show captured output of background job"
+complete -c jobs -l attach -d "Show captured output and stop capturing"
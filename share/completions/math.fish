@@ -1,3 +1,5 @@
 complete -f -c math -r
 complete -f -c math -s s -l scale -r
 complete -f -c math -s b -l base -r
+complete -f -c math -s f -l format -xa "number bytes duration" -d "Output format"
+complete -f -c math -s d -l define -r -d "Define a function or constant"
@@ -8,9 +8,12 @@ complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a change-extensi
 complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a mtime -d 'Show modification time'
 complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a normalize -d 'Normalize given paths (remove ./, resolve ../ against other components..)'
 complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a resolve -d 'Normalize given paths and resolve symlinks'
+complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a relative -d 'Give paths relative to a base directory'
+complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a common-prefix -d 'Give the deepest directory shared by all paths'
 complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a filter -d 'Print paths that match a filter'
 complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a is -d 'Return true if any path matched a filter'
 complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a sort -d 'Sort paths'
+complete -f -c path -n "test (count (commandline -xpc)) -lt 2" -a stat -d 'Show file metadata'
 complete -f -c path -n "test (count (commandline -xpc)) -ge 2" -s q -l quiet -d "Only return status, no output"
 complete -f -c path -n "test (count (commandline -xpc)) -ge 2" -s z -l null-in -d "Handle NULL-delimited input"
 complete -f -c path -n "test (count (commandline -xpc)) -ge 2" -s Z -l null-out -d "Print NULL-delimited output"
@@ -25,10 +28,13 @@ complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (
 complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] filter is" -s w -d "Filter writable paths"
 complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] filter is" -s x -d "Filter executable paths"
 complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] mtime" -s R -l relative -d "Show seconds since the modification time"
+complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] relative" -l base -x -a '(__fish_complete_directories)' -d "Directory to make paths relative to"
 complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] sort" \
     -l key -x -a 'basename\t"Sort only by basename" dirname\t"Sort only by dirname" path\t"Sort by full path"'
 complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] sort" -s u -l unique -d 'Only leave the first of each run with the same key'
 complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] sort" -s r -l reverse -d 'Reverse the order'
+complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] stat" -l format -d "Fields to print" -x -a '(__fish_append , type size mtime perm owner group)'
+complete -f -c path -n "test (count (commandline -xpc)) -ge 2; and contains -- (commandline -xpc)[2] stat" -l set -x -d "Store fields in variables with this prefix"
 
 # Turn on file completions again.
 # match takes a glob as first arg, expand takes only globs.
@@ -1 +1,4 @@
 complete -c random -s h -l help -d "Display help and exit"
+complete -c random -s s -l secure -d "Use the system's secure random number generator"
+complete -c random -n __fish_use_subcommand -f -a choice -d "Print a random item"
+complete -c random -n __fish_use_subcommand -f -a shuffle -d "Print items in random order"
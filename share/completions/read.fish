@@ -12,9 +12,13 @@ complete -c read -s c -l command -d "Initial contents of read buffer when readin
 complete -c read -s S -l shell -d "Read like the shell would"
 complete -c read -s s -l silent -d "Mask input with ●"
 complete -c read -s n -l nchars -d "Read the specified number of characters" -x
+complete -c read -l timeout -d "Give up after the given number of seconds" -x
 complete -c read -s a -l list -l array -d "Store the results as an array"
 complete -c read -s R -l right-prompt -d "Set right-hand prompt command" -x
 complete -c read -s z -l null -d "Use NUL character as line terminator"
 complete -c read -s L -l line -d "Read each line into its own variable"
 complete -c read -s d -l delimiter -d "Set string to use as delimiter" -x
 complete -c read -s t -l tokenize -d "Use shell tokenization rules when splitting"
+complete -c read -l fields -d "Read all records, storing one column per variable"
+complete -c read -l int -d "Require fields of this variable to be integers" -x
+complete -c read -l float -d "Require fields of this variable to be numbers" -x
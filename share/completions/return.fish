@@ -1,3 +1,4 @@
 complete -c return -s h -l help -d "Display help and exit"
 complete -c return -x -a 0 -d "Return from function with normal exit status"
 complete -c return -x -a 1 -d "Return from function with abnormal exit status"
+complete -c return -s v -l value -d "Return a list of values in \$fish_return_value"
@@ -62,6 +62,8 @@ function __fish_complete_special_vars
         umask "current file creation mask" \
         fish_ambiguous_width "affects computed width of east asian chars" \
         fish_autosuggestion_enabled "set to 0 to turn autosuggestions off" \
+        fish_command_duration_threshold "milliseconds after which commands are reported" \
+        fish_command_notification "set to 1 to send notifications about reported commands" \
         fish_cursor_end_mode "set to 'inclusive' to disallow moving the cursor beyond the command line end" \
         fish_cursor_selection_mode "set to 'inclusive' if selections should include the cursor" \
         fish_emoji_width "cols wide fish assumes emoji render as" \
@@ -70,6 +72,7 @@ function __fish_complete_special_vars
         fish_handle_reflow "if fish should repaint prompt when the term resizes" \
         fish_history "The session id to store history under" \
         fish_key_bindings "name of function that sets binds" \
+        fish_right_prompt_on_every_line "set to 1 to repeat the right prompt on every line" \
         fish_term24bit "set to 0 to use the color palette instead of true-colors" \
         fish_term256 "set to 0 to use the 16-color palette instead of 256" \
         fish_trace "Enables execution tracing (if set to non-empty value)" \
@@ -103,6 +106,12 @@ complete -c set -n "__fish_is_nth_token 1" -s S -l show -d "Show variable"
 complete -c set -n "__fish_is_nth_token 1" -l path -d "Make variable as a path variable"
 complete -c set -n "__fish_is_nth_token 1" -l unpath -d "Make variable not as a path variable"
 complete -c set -n "__fish_is_nth_token 1" -l no-event -d "Don't emit an event"
+complete -c set -n "__fish_is_nth_token 1" -l map -d "Set variable to a map from keys to values"
+complete -c set -n "__fish_is_nth_token 1" -l keys -d "List the keys of a map"
+complete -c set -n "__fish_is_nth_token 1" -l sorted -d "Keep variable sorted"
+complete -c set -n "__fish_is_nth_token 1" -l unsorted -d "Stop keeping variable sorted"
+complete -c set -n "__fish_is_nth_token 1" -l unique -d "Remove duplicates from variable"
+complete -c set -n "__fish_is_nth_token 1" -l nonunique -d "Stop removing duplicates from variable"
 
 #TODO: add CPP code to generate list of read-only variables and exclude them from the following completions
 
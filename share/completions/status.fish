@@ -24,11 +24,13 @@ set -l __fish_status_all_commands \
     is-no-job-control \
     job-control \
     language \
+    last-duration \
     line-number \
     list-files \
     print-stack-trace \
     stack-trace \
     terminal \
+    terminal-capabilities \
     terminal-os \
     test-feature \
     test-terminal-feature
@@ -61,6 +63,8 @@ complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_com
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a line-number -d "Print the line number of the currently running script"
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a print-stack-trace -d "Print a list of all function calls leading up to running the current command"
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a stack-trace -d "Print a list of all function calls leading up to running the current command"
+complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a last-duration -d "Print the wall-clock and CPU time of the last job"
+complete -f -c status -n "__fish_seen_subcommand_from last-duration" -l per-process -d "Print the times of each process in the pipeline"
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a features -d "List all feature flags"
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a test-feature -d "Test if a feature flag is enabled"
 complete -f -c status -n "__fish_seen_subcommand_from test-feature" -a '(status features | sed "s/[[:space:]]\+[^[:space:]]*[[:space:]]\+[^[:space:]]*/\t/")'
@@ -69,9 +73,14 @@ complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_com
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a list-files -d "List embedded files contained in the fish binary"
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a fish-path -d "Print the path to the current instance of fish"
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a terminal -d "Print name and version of the terminal fish is running in"
+complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a terminal-capabilities -d "Print the optional features the terminal supports"
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a terminal-os -d "Print the operating system the terminal is running on"
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a test-terminal-feature -d "Test if the terminal suports the given feature"
+complete -f -c status -n "__fish_seen_subcommand_from test-terminal-feature" -a 'kitty-keyboard\t"Kitty keyboard protocol"'
+complete -f -c status -n "__fish_seen_subcommand_from test-terminal-feature" -a 'synchronized-output\t"Updating the screen without flicker"'
+complete -f -c status -n "__fish_seen_subcommand_from test-terminal-feature" -a 'truecolor\t"24-bit colors"'
 complete -f -c status -n "__fish_seen_subcommand_from test-terminal-feature" -a 'scroll-content-up\t"Command for scrolling up terminal contents"'
+complete -f -c status -n "__fish_seen_subcommand_from test-terminal-feature" -a 'clipboard\t"Copying and pasting via OSC 52"'
 
 # The job-control command changes fish state.
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a job-control -d "Set which jobs are under job control"
@@ -95,5 +104,5 @@ function __fish_status_is_subcommand_prefix
     string match -rq -- "^$prefix" $line
 end
 complete -f -c status -n "not __fish_seen_subcommand_from $__fish_status_all_commands" -a language -d "Show or change fish's language settings"
-complete -f -c status -n "__fish_status_is_exact_subcommand language" -a "(echo list-available\tShow languages usable with \'status language set\'\nset\tSet the language\(s\) used for fish\'s messages\nunset\tUndo effects of \'status language set\'\n)"
+complete -f -c status -n "__fish_status_is_exact_subcommand language" -a "(echo list-available\tShow languages usable with \'status language set\'\nset\tSet the language\(s\) used for fish\'s messages\nunset\tUndo effects of \'status language set\'\nresolve\tShow the language used for messages\n)"
 complete -f -c status -n "__fish_status_is_subcommand_prefix language set" -a "(status language list-available)"
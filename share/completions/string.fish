@@ -2,11 +2,13 @@
 # This follows a strict command-then-options approach, so we can just test the number of tokens
 complete -f -c string
 complete -f -c string -n "test (count (commandline -xpc)) -le 2" -s h -l help -d "Display help and exit"
-complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "not contains -- (commandline -xpc)[2] escape collect pad" -s q -l quiet -d "Do not print output"
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "not contains -- (commandline -xpc)[2] escape collect hash pad" -s q -l quiet -d "Do not print output"
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a lower
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a upper
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a length
 complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] length" -s V -l visible -d "Use the visible width, excluding escape sequences"
+complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a width
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] width" -s b -l breakdown -d "Print the width of each grapheme cluster"
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a sub
 complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] sub" -s s -l start -xa "(seq 1 10)" -d "Specify start index"
 complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] sub" -s e -l end -xa "(seq 1 10)" -d "Specify end index"
@@ -17,10 +19,21 @@ complete -x -c string -n 'test (count (commandline -xpc)) -ge 2' -n 'string matc
 complete -x -c string -n 'test (count (commandline -xpc)) -ge 2' -n 'string match -qr split0\?\$ -- (commandline -xpc)[2]' -s f -l fields -a "(seq 1 10)" -d "Specify fields"
 complete -f -c string -n 'test (count (commandline -xpc)) -ge 2' -n 'string match -qr split0\?\$ -- (commandline -xpc)[2]' -s r -l right -d "Split right-to-left"
 complete -f -c string -n 'test (count (commandline -xpc)) -ge 2' -n 'string match -qr split0\?\$ -- (commandline -xpc)[2]' -s n -l no-empty -d "Empty results excluded"
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] split" -l regex -d "Separator is a regex"
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a collect
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] collect join join0 split" -s z -l null-in -d "Handle NUL-delimited input"
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] collect join join0 split split0" -s Z -l null-out -d "Print NUL-delimited output"
 complete -f -c string -n 'test (count (commandline -xpc)) -ge 2' -n 'string match -qr collect\$ -- (commandline -xpc)[2]' -s N -l no-trim-newlines -d "Don't trim trailing newlines"
 complete -f -c string -n 'test (count (commandline -xpc)) -ge 2' -n 'string match -qr collect\$ -- (commandline -xpc)[2]' -s a -l allow-empty -d "Always print empty argument"
 
+complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a hash
+complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] hash" -s a -l algorithm -a "sha256 sha1 md5 crc32" -d "Hash algorithm"
+complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a unicode
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] unicode" -l nfc -d "Normalize to canonical composition"
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] unicode" -l nfd -d "Normalize to canonical decomposition"
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] unicode" -l nfkc -d "Normalize to compatibility composition"
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] unicode" -l nfkd -d "Normalize to compatibility decomposition"
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] unicode" -s i -l inspect -d "Print name and category of each code point"
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a join
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a join0
 complete -f -c string -n 'test (count (commandline -xpc)) -ge 2' -n 'contains -- (commandline -xpc)[2] join' -s n -l no-empty -d "Empty strings excluded"
@@ -42,13 +55,21 @@ complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains --
 complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] match" -s v -l invert -d "Report only non-matches"
 complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] match" -s e -l entire -d "Show entire matching lines"
 complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] match" -s g -l groups-only -d "Only report capturing groups"
+complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] match" -l export-prefix -d "Prefix for variables of named groups"
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a replace
 complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] replace" -s f -l filter -d "Report only actual replacements"
+complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] replace" -l function -a "(functions)" -d "Function computing each replacement"
 # All replace options are also valid for match
 complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] match replace" -s a -l all -d "Report every match"
 complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] match replace" -s i -l ignore-case -d "Case insensitive"
 complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] match replace" -s r -l regex -d "Use regex instead of globs"
 
+complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a diff
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] diff" -s w -l words -d "Compare words instead of lines"
+complete -f -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] diff" -s f -l files -d "Compare the contents of files"
+complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] diff" -s U -l unified -xa "(seq 0 10)" -d "Number of unchanged lines around changes"
+complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] diff" -l color -xa "always never auto" -d "When to use color"
+complete -F -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] diff" -n "__fish_seen_argument -s f -l files"
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a repeat
 complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] repeat" -s n -l count -xa "(seq 1 10)" -d "Repetition count"
 complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] repeat" -s m -l max -xa "(seq 1 10)" -d "Maximum number of printed chars"
@@ -58,6 +79,8 @@ complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains --
 complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] pad" -s r -l center -d "Pad both left and right"
 complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] pad" -s c -l char -x -d "Character to use for padding"
 complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] pad" -s w -l width -x -d "Integer width of the result, default is maximum width of inputs"
+complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] pad" -s t -l truncate -d "Shorten inputs wider than the width"
+complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] pad" -l ellipsis -x -d "String to mark truncated inputs with"
 complete -f -c string -n "test (count (commandline -xpc)) -lt 2" -a shorten
 complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] shorten" -s l -l left -d "Remove from the left on"
 complete -x -c string -n "test (count (commandline -xpc)) -ge 2" -n "contains -- (commandline -xpc)[2] shorten" -s c -l char -x -d "Characters to use as ellipsis"
@@ -6,12 +6,20 @@ complete -c test -k -f -s n -d "String length is non-zero"
 complete -c test -k -f -s z -d "String length is zero"
 complete -c test -k -f -a = -d "Strings are identical"
 complete -c test -k -f -a != -d "Strings are not identical"
+complete -c test -k -f -a =~ -d "Regex matches string"
+complete -c test -k -f -a '==\*' -d "Glob matches string"
 complete -c test -k -f -o eq -d "Numbers are equal"
 complete -c test -k -f -o ge -d "Left number >= right number"
 complete -c test -k -f -o gt -d "Left number > right number"
 complete -c test -k -f -o le -d "Left number <= right number"
 complete -c test -k -f -o lt -d "Left number < right number"
 complete -c test -k -f -o ne -d "Left number != right number"
+complete -c test -k -f -o ver-eq -d "Versions are equal"
+complete -c test -k -f -o ver-ne -d "Versions are not equal"
+complete -c test -k -f -o ver-gt -d "Left version newer than right version"
+complete -c test -k -f -o ver-ge -d "Left version newer than or same as right version"
+complete -c test -k -f -o ver-lt -d "Left version older than right version"
+complete -c test -k -f -o ver-le -d "Left version older than or same as right version"
 complete -c test -k -o ef -d "Left file equal to right file"
 complete -c test -k -o nt -d "Left file newer than right file"
 complete -c test -k -o ot -d "Left file older than right file"
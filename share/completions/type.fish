@@ -6,9 +6,10 @@ complete -c type -s p -l path -d "Print path to command, or nothing if name is n
 complete -c type -s P -l force-path -d "Print path to command"
 complete -c type -s q -l query -l quiet -d "Check if something exists without output"
 complete -c type -s s -l short -d "Don't print function definition"
+complete -c type -l json -d "Print how the name resolves as JSON"
 complete -c type -l color -d "When to colorize output" -xa "always never auto"
 
 complete -c type -a "(builtin -n)" -d Builtin
-complete -c type -a "(functions -n)" -d Function
+complete -c type -a "(functions -nv)" -d Function
 
 complete -c type -a "(__fish_complete_command)" -x
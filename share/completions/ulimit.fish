@@ -2,6 +2,7 @@ complete -c ulimit -s S -l soft -d "Set or get soft limit"
 complete -c ulimit -s H -l hard -d "Set or get hard limit"
 
 complete -c ulimit -s a -l all -d "Get all current limits"
+complete -c ulimit -l json -d "Get all current limits as JSON"
 complete -c ulimit -s b -l socket-buffers -d "Maximum size of socket buffers"
 complete -c ulimit -s c -l core-size -d "Maximum size of core files created"
 complete -c ulimit -s d -l data-size -d "Maximum size of a process's data segment"
@@ -18,6 +19,7 @@ complete -c ulimit -s t -l cpu-time -d "Maximum amount of cpu time in seconds"
 complete -c ulimit -s u -l process-count -d "Maximum number of processes available to a single user"
 complete -c ulimit -s v -l virtual-memory-size -d "Maximum amount of virtual memory available to the shell"
 complete -c ulimit -s w -l swap-size -d "Maximum swap space"
+complete -c ulimit -s x -l file-locks -d "Maximum number of file locks"
 complete -c ulimit -s y -l realtime-maxtime -d "Maximum contiguous realtime CPU time"
 complete -c ulimit -s K -l kernel-queues -d "Maximum number of kqueues"
 complete -c ulimit -s P -l ptys -d "Maximum number of pseudo-terminals"
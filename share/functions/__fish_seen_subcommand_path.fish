@@ -0,0 +1,33 @@
+# localization: skip(private)
+
+# Test if the subcommands given so far on the commandline are exactly PATH, i.e. the first
+# non-switch arguments are PATH, and the one after them, if any, is none of CHILDREN.
+# Usage: __fish_seen_subcommand_path PATH... -- CHILDREN...
+# This is used for the completions loaded with `complete --load-json`.
+
+function __fish_seen_subcommand_path
+    set -l sep (contains --index -- -- $argv)
+    or return 2
+    set -l path
+    set -l children
+    for i in (seq (count $argv))
+        if test $i -lt $sep
+            set -a path $argv[$i]
+        else if test $i -gt $sep
+            set -a children $argv[$i]
+        end
+    end
+
+    set -l args
+    for token in (commandline -pxc)[2..]
+        string match -q -- '-*' $token
+        or set -a args $token
+    end
+
+    for i in (seq (count $path))
+        test "$args[$i]" = "$path[$i]"
+        or return 1
+    end
+    set -l next $args[(math (count $path) + 1)]
+    not contains -- "$next" $children
+end
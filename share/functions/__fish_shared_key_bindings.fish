@@ -24,6 +24,11 @@ __fish_per_os_bind --preset $argv ctrl-left backward-token backward-word
 bind --preset $argv pageup beginning-of-history
 bind --preset $argv pagedown end-of-history
 
+ # Mouse support, see $fish_mouse_reporting.
+bind --preset $argv mouse-left-click move-to-mouse
+bind --preset $argv scroll-up pager-page-up
+bind --preset $argv scroll-down pager-page-down
+
  # Interaction with the system clipboard.
 bind --preset $argv ctrl-x fish_clipboard_copy
 bind --preset $argv ctrl-v fish_clipboard_paste
@@ -96,8 +101,8 @@ bind --preset $argv alt-p __fish_paginate
 bind --preset $argv alt-# __fish_toggle_comment_commandline
 
  # These keystrokes invoke an external editor on the command buffer.
-bind --preset $argv alt-e edit_command_buffer
-bind --preset $argv alt-v edit_command_buffer
+bind --preset $argv alt-e edit-command-buffer
+bind --preset $argv alt-v edit-command-buffer
 
  # Bindings that are shared in text-insertion modes.
 if not set -l index (contains --index -- -M $argv)
@@ -118,7 +123,8 @@ if not set -l index (contains --index -- -M $argv)
     bind --preset $argv shift-enter "commandline -i \n $maybe_search_field"  expand-abbr
     bind --preset $argv alt-enter "commandline -i \n $maybe_search_field"  expand-abbr
     bind --preset $argv ")" self-insert expand-abbr  # Closing a command substitution.
-    bind --preset $argv ctrl-space \'test -n "$(commandline)" && commandline -i " " \'$maybe_search_field
+     # In the pager, ctrl-space marks the selected completion, to insert all marked ones at once.
+    bind --preset $argv ctrl-space pager-toggle-mark or \'test -n "$(commandline)" && commandline -i " " \'$maybe_search_field
      # Shift-space behaves like space because it\'s easy to mistype.
     bind --preset $argv shift-space \'commandline -i " " \'$maybe_search_field expand-abbr
 
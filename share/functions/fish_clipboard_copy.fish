@@ -12,6 +12,11 @@ function fish_clipboard_copy
         end
     end
 
+    # Prefer the terminal's clipboard (OSC 52), which also works over SSH or in a container.
+    if printf '%s' $cmdline | fish_terminal_clipboard copy
+        return
+    end
+
     if type -q pbcopy
         printf '%s' $cmdline | pbcopy
     else if set -q WAYLAND_DISPLAY; and type -q wl-copy
@@ -25,25 +30,15 @@ function fish_clipboard_copy
         printf '%s' $cmdline | clip.exe
     end
 
-    # Copy with OSC 52; useful if we are running in an SSH session or in
-    # a container.
-    if type -q base64 && [ "$TERM" != dumb ]
-        if not isatty stdout
-            echo "fish_clipboard_copy: stdout is not a terminal" >&2
-            return 1
-        end
-        set -l encoded (printf %s $cmdline | base64 | string join '')
-        printf '\e]52;c;%s\a' "$encoded"
-        # tmux requires user configuration to interpret OSC 52 on stdout.
-        # Luckily we can still make this work for the common case by bypassing
-        # tmux and writing to its underlying terminal.
-        if set -q TMUX
-            set -l tmux_tty (tmux display-message -p '#{client_tty}')
-            or return 1
-            # The terminal might not be writable if we switched user.
-            if test -w $tmux_tty
-                printf '\e]52;c;%s\a' "$encoded" >$tmux_tty
-            end
+    # tmux requires user configuration to interpret OSC 52 on stdout.
+    # Luckily we can still make this work for the common case by bypassing
+    # tmux and writing to its underlying terminal.
+    if set -q TMUX; and [ "$TERM" != dumb ]
+        set -l tmux_tty (tmux display-message -p '#{client_tty}')
+        or return 1
+        # The terminal might not be writable if we switched user.
+        if test -w $tmux_tty
+            printf '%s' $cmdline | fish_terminal_clipboard copy --force >$tmux_tty
         end
     end
 end
@@ -1,6 +1,9 @@
 function fish_clipboard_paste
     set -l data
-    if type -q pbpaste
+    # Prefer the terminal's clipboard (OSC 52), which also works over SSH or in a container.
+    if set data (fish_terminal_clipboard paste | string collect -N)
+        # Nothing else to do.
+    else if type -q pbpaste
         set data (pbpaste 2>/dev/null | string collect -N)
     else if set -q WAYLAND_DISPLAY; and type -q wl-paste
         set data (wl-paste -n 2>/dev/null | string collect -N)
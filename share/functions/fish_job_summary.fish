@@ -33,6 +33,10 @@ function fish_job_summary -a job_id is_foreground cmd_line signal_or_end_name si
             set message (printf ( _ "fish: Job %s, '%s' has stopped\n" ) $job_id $cmd_line)
         case ENDED
             set message (printf ( _ "fish: Job %s, '%s' has ended\n" ) $job_id $cmd_line)
+            # Point at the output if it was captured instead of being printed (see fish_background_output).
+            if test $is_foreground -eq 0; and jobs --query --output %$job_id
+                set -a message (printf ( _ "Its output was captured, view it with 'jobs --output %%%s'\n" ) $job_id)
+            end
         case 'SIG*'
             if test -n "$proc_pid"
                 set message (printf ( _ "fish: Process %s, '%s' from job %s, '%s' terminated by signal %s (%s)\n" ) \
@@ -28,7 +28,7 @@ end
 
 # The `fish_opt` command.
 function fish_opt -d 'Produce an option specification suitable for use with `argparse`.'
-    set -l options h/help 's/short=' 'l/long=' d/delete o/optional-val r/required-val m/multiple-vals long-only v/validate
+    set -l options h/help 's/short=' 'l/long=' d/delete o/optional-val r/required-val m/multiple-vals long-only description= v/validate
     argparse -n fish_opt --stop-nonopt --exclusive=r,o $options -- $argv
     or return
 
@@ -68,5 +68,9 @@ function fish_opt -d 'Produce an option specification suitable for use with `arg
         set opt_spec "$opt_spec!$argv"
     end
 
+    if set -q _flag_description
+        set opt_spec "$opt_spec"\t"$_flag_description"
+    end
+
     echo $opt_spec
 end
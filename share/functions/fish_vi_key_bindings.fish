@@ -416,6 +416,14 @@ function fish_vi_key_bindings --description 'vi-like key bindings for fish'
 
     bind --preset u undo
     bind --preset ctrl-r redo
+    bind --preset g,- undo-tree-prev-branch
+    bind --preset g,+ undo-tree-next-branch
+
+    # Like in Vim, "x makes the next kill or yank use register x.
+    for register in (string split '' abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789)
+        bind --preset -M default \",$register "commandline --register $register -f"
+        bind --preset -M visual \",$register "commandline --register $register -f"
+    end
 
     bind --preset [ history-token-search-backward
     bind --preset ] history-token-search-forward
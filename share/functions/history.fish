@@ -7,6 +7,7 @@ function history --description "display or manipulate interactive command histor
     set -l options --exclusive 'c,e,p' --exclusive 'S,D,M,V,X'
     set -a options h/help c/contains e/exact p/prefix
     set -a options C/case-sensitive R/reverse z/null 't/show-time=?' 'n#max' 'color='
+    set -a options 'cwd=' failed-only 'format='
     # The following options are deprecated and will be removed in the next major release.
     # Note that they do not have usable short flags.
     set -a options S-search D-delete M-merge V-save X-clear
@@ -28,6 +29,12 @@ function history --description "display or manipulate interactive command histor
 
     set color_opt --color=$_flag_color
 
+    set -l filter
+    set -q _flag_cwd
+    and set -a filter --cwd=$_flag_cwd
+    set -q _flag_failed_only
+    and set -a filter --failed-only
+
     set -q _flag_with_time
     and set -l _flag_show_time $_flag_with_time
     if set -q _flag_show_time[1]
@@ -61,7 +68,7 @@ function history --description "display or manipulate interactive command histor
     # command. This allows the flags to appear before or after the subcommand.
     if not set -q hist_cmd[1]
         and set -q argv[1]
-        if contains $argv[1] search delete merge save clear clear-session append
+        if contains $argv[1] search delete merge save clear clear-session append sync import export redact
             set hist_cmd $argv[1]
             set -e argv[1]
         end
@@ -94,9 +101,9 @@ function history --description "display or manipulate interactive command histor
                     set color_opt --color=always
                 end
 
-                builtin history search $color_opt $search_mode $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv | $pager
+                builtin history search $color_opt $search_mode $filter $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv | $pager
             else
-                builtin history search $color_opt $search_mode $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
+                builtin history search $color_opt $search_mode $filter $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
             end
 
         case delete # interactively delete history
@@ -115,7 +122,7 @@ function history --description "display or manipulate interactive command histor
             # TODO: Fix this so that requesting history entries with a timestamp works:
             #   set -l found_items (builtin history search $color_opt $search_mode $show_time -- $argv)
             set -l found_items
-            set found_items (builtin history search $color_opt $search_mode $_flag_case_sensitive --null -- $searchterm | string split0)
+            set found_items (builtin history search $color_opt $search_mode $filter $_flag_case_sensitive --null -- $searchterm | string split0)
             if set -q found_items[1]
                 set -l found_items_count (count $found_items)
                 for i in (seq $found_items_count)
@@ -186,12 +193,28 @@ function history --description "display or manipulate interactive command histor
             end
 
         case save # save our interactive command history to the persistent history
-            builtin history save $color_opt $search_mode $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
+            builtin history save $color_opt $search_mode $filter $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
         case merge # merge the persistent interactive command history with our history
-            builtin history merge $color_opt $search_mode $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
+            builtin history merge $color_opt $search_mode $filter $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
+        case sync # sync the persistent interactive command history with other machines
+            builtin history sync $color_opt $search_mode $filter $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
+        case redact # remove commands containing a secret, also from disk
+            set -l terms $argv
+            if not set -q argv[1]
+                # Reading the secret keeps it out of the history and off the screen.
+                read --silent -P (_ "Text to redact: ") terms
+                or return $status
+            end
+            builtin history redact $search_mode $filter $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $terms
+        case import export # convert from or to the history of other shells
+            set -l format
+            set -q _flag_format
+            and set format --format=$_flag_format
+            builtin history $hist_cmd $format $color_opt $search_mode $filter $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
         case clear # clear the interactive command history
             if test -n "$search_mode"
                 or set -q show_time[1]
+                or set -q filter[1]
                 printf (_ "%s: %s: subcommand takes no options\n") history $hist_cmd >&2
                 return 1
             end
@@ -210,7 +233,7 @@ function history --description "display or manipulate interactive command histor
                 printf (_ "You did not say 'yes' so I will not clear your command history\n")
             end
         case clear-session # clears only session
-            builtin history clear-session $color_opt $search_mode $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
+            builtin history clear-session $color_opt $search_mode $filter $show_time $max_count $_flag_case_sensitive $_flag_reverse $_flag_null -- $argv
             and printf (_ "Command history for session cleared!\n")
         case append
             set -l newitem $argv
@@ -38,6 +38,8 @@ pub struct Abbreviation {
     pub name: WString,
 
     /// The key (recognized token) - either a literal or a regex pattern.
+    /// A literal key may consist of several words separated by single spaces, which then match
+    /// that many consecutive tokens.
     pub key: WString,
 
     /// If set, use this regex to recognize tokens.
@@ -61,6 +63,9 @@ pub struct Abbreviation {
     /// If set, then move the cursor to the first instance of this string in the expansion.
     pub set_cursor_marker: Option<WString>,
 
+    /// If set, fish script which must succeed for the abbreviation to expand.
+    pub condition: Option<WString>,
+
     /// Mark if we came from a universal variable.
     pub from_universal: bool,
 }
@@ -84,6 +89,7 @@ impl Abbreviation {
             replacement_is_function: false,
             position,
             set_cursor_marker: None,
+            condition: None,
             from_universal,
         }
     }
@@ -93,8 +99,17 @@ impl Abbreviation {
         self.regex.is_some()
     }
 
-    // Return true if we match a token at a given position.
-    pub fn matches(&self, token: &wstr, position: Position, command: &wstr) -> bool {
+    // Return the number of consecutive tokens we match.
+    pub fn word_count(&self) -> usize {
+        if self.is_regex() {
+            1
+        } else {
+            self.key.split(' ').count()
+        }
+    }
+
+    // Return true if we match a sequence of tokens, the first of which is at a given position.
+    pub fn matches(&self, tokens: &[&wstr], position: Position, command: &wstr) -> bool {
         if !self.matches_position(position) {
             return false;
         }
@@ -104,10 +119,14 @@ impl Abbreviation {
         }
 
         match &self.regex {
-            Some(r) => r
-                .is_match(token.as_char_slice())
-                .expect("regex match should not error"),
-            None => self.key == token,
+            Some(r) => {
+                let [token] = tokens else {
+                    return false;
+                };
+                r.is_match(token.as_char_slice())
+                    .expect("regex match should not error")
+            }
+            None => self.key.split(' ').eq(tokens.iter().copied()),
         }
     }
 
@@ -128,6 +147,9 @@ pub struct Replacer {
 
     /// If set, the cursor should be moved to the first instance of this string in the expansion.
     pub set_cursor_marker: Option<WString>,
+
+    /// If set, fish script which must succeed for the replacement to be used.
+    pub condition: Option<WString>,
 }
 
 pub struct Replacement {
@@ -178,18 +200,19 @@ pub struct AbbreviationSet {
 }
 
 impl AbbreviationSet {
-    /// Return the list of replacers for an input token, in priority order.
-    /// The `position` is given to describe where the token was found.
-    pub fn r#match(&self, token: &wstr, position: Position, cmd: &wstr) -> Vec<Replacer> {
+    /// Return the list of replacers for a sequence of input tokens, in priority order.
+    /// The `position` is given to describe where the first token was found.
+    pub fn r#match(&self, tokens: &[&wstr], position: Position, cmd: &wstr) -> Vec<Replacer> {
         let mut result = vec![];
 
         // Later abbreviations take precedence so walk backwards.
         for abbr in self.abbrs.iter().rev() {
-            if abbr.matches(token, position, cmd) {
+            if abbr.matches(tokens, position, cmd) {
                 result.push(Replacer {
                     replacement: abbr.replacement.clone(),
                     is_function: abbr.replacement_is_function,
                     set_cursor_marker: abbr.set_cursor_marker.clone(),
+                    condition: abbr.condition.clone(),
                 });
             }
         }
@@ -200,7 +223,16 @@ impl AbbreviationSet {
     pub fn has_match(&self, token: &wstr, position: Position, cmd: &wstr) -> bool {
         self.abbrs
             .iter()
-            .any(|abbr| abbr.matches(token, position, cmd))
+            .any(|abbr| abbr.matches(&[token], position, cmd))
+    }
+
+    /// Return the largest number of tokens matched by any abbreviation.
+    pub fn max_word_count(&self) -> usize {
+        self.abbrs
+            .iter()
+            .map(Abbreviation::word_count)
+            .max()
+            .unwrap_or(1)
     }
 
     /// Add an abbreviation. Any abbreviation with the same name is replaced.
@@ -278,10 +310,10 @@ impl AbbreviationSet {
     }
 }
 
-/// Return the list of replacers for an input token, in priority order, using the global set.
-/// The `position` is given to describe where the token was found.
-pub fn abbrs_match(token: &wstr, position: Position, cmd: &wstr) -> Vec<Replacer> {
-    with_abbrs(|set| set.r#match(token, position, cmd))
+/// Return the list of replacers for a sequence of input tokens, in priority order, using the
+/// global set. The `position` is given to describe where the first token was found.
+pub fn abbrs_match(tokens: &[&wstr], position: Position, cmd: &wstr) -> Vec<Replacer> {
+    with_abbrs(|set| set.r#match(tokens, position, cmd))
         .into_iter()
         .collect()
 }
@@ -334,11 +366,11 @@ mod tests {
         // Helper to expand an abbreviation, enforcing we have no more than one result.
         macro_rules! abbr_expand_1 {
             ($token:expr, $position:expr) => {
-                let result = abbrs_match(L!($token), $position, L!(""));
+                let result = abbrs_match(&[L!($token)], $position, L!(""));
                 assert_eq!(result, vec![]);
             };
             ($token:expr, $position:expr, $expected:expr) => {
-                let result = abbrs_match(L!($token), $position, L!(""));
+                let result = abbrs_match(&[L!($token)], $position, L!(""));
                 assert_eq!(
                     result
                         .into_iter()
@@ -424,6 +456,47 @@ mod tests {
         // yin/yang expands everywhere.
         validate!("command yin", None, "command yang");
 
+        // Abbreviations only expand if their condition succeeds.
+        with_abbrs_mut(|abbrs| {
+            for (name, condition) in [(L!("always"), L!("true")), (L!("never"), L!("false"))] {
+                let mut abbr = Abbreviation::new(
+                    name.to_owned(),
+                    name.to_owned(),
+                    L!("expanded").to_owned(),
+                    Position::Command,
+                    false,
+                );
+                abbr.condition = Some(condition.to_owned());
+                abbrs.add(abbr);
+            }
+        });
+        validate!("always x", Some(0), "expanded x");
+        validate!("never x", Some(0));
+
+        // Abbreviations may match several tokens, preferring the longest match.
+        with_abbrs_mut(|abbrs| {
+            abbrs.add(Abbreviation::new(
+                L!("git co").to_owned(),
+                L!("git co").to_owned(),
+                L!("git checkout").to_owned(),
+                Position::Command,
+                false,
+            ));
+            abbrs.add(Abbreviation::new(
+                L!("co").to_owned(),
+                L!("co").to_owned(),
+                L!("commit").to_owned(),
+                Position::Anywhere,
+                false,
+            ));
+        });
+        validate!("git co", None, "git checkout");
+        validate!("git  co main", Some("git  co".len()), "git checkout main");
+        validate!("echo (git co", None, "echo (git checkout");
+        validate!("hg co", None, "hg commit");
+        validate!("echo git co", None, "echo git commit");
+        validate!("git; co", None, "git; commit");
+
         with_abbrs_mut(|abbrset| abbrset.clear());
     }
 
@@ -443,6 +516,7 @@ mod tests {
                     replacement_is_function: false,
                     position,
                     set_cursor_marker: None,
+                    condition: None,
                     from_universal: false,
                 });
             };
@@ -628,10 +628,30 @@ macro_rules! Acceptor {
 
 /// A redirection has an operator like > or 2>, and a target like /dev/null or &1.
 /// Note that pipes are not redirections.
-#[derive(Default, Debug, Node!, Acceptor!)]
+#[derive(Default, Debug, Node!)]
 pub struct Redirection {
     pub oper: TokenRedirection,
     pub target: String_,
+    /// For a here-document, the range of its body, which is not a node. The target is the
+    /// delimiter.
+    pub heredoc_body: Option<SourceRange>,
+}
+
+impl Acceptor for Redirection {
+    fn accept<'a>(&'a self, visitor: &mut dyn NodeVisitor<'a>) {
+        self.oper.do_visit(visitor);
+        self.target.do_visit(visitor);
+    }
+}
+impl AcceptorMut for Redirection {
+    fn accept_mut<V: NodeVisitorMut>(&mut self, visitor: &mut V) {
+        visitor.will_visit_fields_of(self);
+        let mut flow = self.oper.do_visit_mut(visitor);
+        if flow.is_continue() {
+            flow = self.target.do_visit_mut(visitor);
+        }
+        visitor.did_visit_fields_of(self, flow);
+    }
 }
 
 impl CheckParse for Redirection {
@@ -734,7 +754,7 @@ impl Statement {
     }
 
     // Return the node embedded in this statement.
-    fn embedded_node(&self) -> &dyn Node {
+    pub fn embedded_node(&self) -> &dyn Node {
         match self {
             Self::Not(child) => &**child,
             Self::Block(child) => &**child,
@@ -1622,6 +1642,9 @@ impl<'a> TokenStream<'a> {
         result.is_newline = result.typ == ParseTokenType::End && text == "\n";
         result.may_be_variable_assignment = variable_assignment_equals_pos(text).is_some();
         result.tok_error = token.error;
+        result.heredoc_body = token
+            .heredoc_body()
+            .map(|body| SourceRange::new(body.start, body.len()));
 
         assert!(token.offset() < SOURCE_OFFSET_INVALID);
         result.set_source_start(token.offset());
@@ -1765,7 +1788,7 @@ impl<'s> NodeVisitorMut for Populator<'s> {
             KM::ArgumentOrRedirection(node) => self.visit_argument_or_redirection(node),
             KM::BlockStatementHeader(node) => self.visit_block_statement_header(node),
             KM::Statement(node) => self.visit_statement(node),
-            KM::Redirection(node) => node.accept_mut(self),
+            KM::Redirection(node) => self.visit_redirection(node),
             KM::JobPipeline(node) => node.accept_mut(self),
             KM::JobConjunction(node) => node.accept_mut(self),
             KM::ForHeader(node) => node.accept_mut(self),
@@ -2589,6 +2612,15 @@ impl<'s> Populator<'s> {
             );
         }
     }
+    fn visit_redirection(&mut self, redir: &mut Redirection) {
+        // The tokenizer finds the body of a here-document along with the delimiter.
+        redir.heredoc_body = if self.unsource_leaves() {
+            None
+        } else {
+            self.peek_token(1).heredoc_body
+        };
+        redir.accept_mut(self);
+    }
     fn visit_block_statement_header(&mut self, node: &mut BlockStatementHeader) {
         *node = self.allocate_populate_block_header();
     }
@@ -2645,7 +2677,9 @@ impl<'s> Populator<'s> {
             if self.flags.leave_unterminated
                 && matches!(
                     self.peek_token(0).tok_error,
-                    TokenizerError::UnterminatedQuote | TokenizerError::UnterminatedSubshell
+                    TokenizerError::UnterminatedQuote
+                        | TokenizerError::UnterminatedSubshell
+                        | TokenizerError::UnterminatedHeredoc
                 )
             {
                 return;
@@ -2680,7 +2714,9 @@ impl<'s> Populator<'s> {
             if self.flags.leave_unterminated
                 && matches!(
                     self.peek_token(0).tok_error,
-                    TokenizerError::UnterminatedQuote | TokenizerError::UnterminatedSubshell
+                    TokenizerError::UnterminatedQuote
+                        | TokenizerError::UnterminatedSubshell
+                        | TokenizerError::UnterminatedHeredoc
                 )
             {
                 return VisitResult::Continue(());
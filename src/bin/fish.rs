@@ -25,13 +25,15 @@ use fish::{
     },
     common::{PACKAGE_NAME, PROFILING_ACTIVE, PROGRAM_NAME},
     env::{EnvMode, EnvStack, Environment as _, Statuses, config_paths::ConfigPaths, env_init},
-    eprintf, err_fmt,
+    eprintf, err_fmt, err_str,
     event::{self, Event},
-    fds::heightenize_fd,
+    fds::{heightenize_fd, record_inherited_fds},
     flog::{self, activate_flog_categories_by_pattern, flog, flogf, set_flog_file_fd},
     fprintf, function,
     history::{self, start_private_mode},
+    input::start_input_recording,
     io::{FdOutputStream, IoChain, OutputStream},
+    lint::{Check, LintFormat},
     locale::set_libc_locales,
     nix::isatty,
     panic::panic_handler,
@@ -54,7 +56,7 @@ use fish::{
 };
 use fish_common::{escape, save_term_foreground_process_group};
 use fish_widestring::{bytes2wcstring, osstr2wcstring, wcs2bytes};
-use libc::{STDERR_FILENO, STDIN_FILENO};
+use libc::{STDERR_FILENO, STDIN_FILENO, STDOUT_FILENO};
 use nix::{
     sys::resource::{UsageWho, getrusage},
     unistd::{AccessFlags, getpid},
@@ -79,6 +81,8 @@ struct FishCmdOpts {
     /// File path for profiling output, or empty for none.
     profile_output: Option<OsString>,
     profile_startup_output: Option<OsString>,
+    /// File path for recording the input read from the terminal.
+    record_input_output: Option<OsString>,
     /// Commands to be executed in place of interactive shell.
     batch_cmds: Vec<OsString>,
     /// Commands to execute after the shell's config has been read.
@@ -95,6 +99,16 @@ struct FishCmdOpts {
     is_interactive_session: bool,
     /// Whether to enable private mode.
     enable_private_mode: bool,
+    /// Whether to run as a language server.
+    lsp: bool,
+    /// Whether to check the given scripts instead of running them.
+    lint: bool,
+    /// How to print the issues found by --lint.
+    lint_format: LintFormat,
+    /// Checks that --lint should skip.
+    lint_ignore: Vec<Check>,
+    /// Whether to run the script in the debugger.
+    debug_script: bool,
 }
 
 /// Return a timeval converted to milliseconds.
@@ -217,6 +231,12 @@ fn fish_parse_opt(args: &mut [WString], opts: &mut FishCmdOpts) -> ControlFlow<i
     const RUSAGE_ARG: char = 1 as char;
     const PRINT_DEBUG_CATEGORIES_ARG: char = 2 as char;
     const PROFILE_STARTUP_ARG: char = 3 as char;
+    const RECORD_INPUT_ARG: char = 4 as char;
+    const LSP_ARG: char = 5 as char;
+    const LINT_ARG: char = 6 as char;
+    const LINT_FORMAT_ARG: char = 7 as char;
+    const LINT_IGNORE_ARG: char = 8 as char;
+    const DEBUG_SCRIPT_ARG: char = 9 as char;
 
     const SHORT_OPTS: &wstr = L!("+hPilNnvc:C:p:d:f:D:o:");
     const LONG_OPTS: &[WOption<'static>] = &[
@@ -225,9 +245,14 @@ fn fish_parse_opt(args: &mut [WString], opts: &mut FishCmdOpts) -> ControlFlow<i
         wopt(L!("features"), RequiredArgument, 'f'),
         wopt(L!("debug"), RequiredArgument, 'd'),
         wopt(L!("debug-output"), RequiredArgument, 'o'),
+        wopt(L!("debug-script"), NoArgument, DEBUG_SCRIPT_ARG),
         wopt(L!("debug-stack-frames"), RequiredArgument, 'D'),
         wopt(L!("interactive"), NoArgument, 'i'),
+        wopt(L!("lint"), NoArgument, LINT_ARG),
+        wopt(L!("lint-format"), RequiredArgument, LINT_FORMAT_ARG),
+        wopt(L!("lint-ignore"), RequiredArgument, LINT_IGNORE_ARG),
         wopt(L!("login"), NoArgument, 'l'),
+        wopt(L!("lsp"), NoArgument, LSP_ARG),
         wopt(L!("no-config"), NoArgument, 'N'),
         wopt(L!("no-execute"), NoArgument, 'n'),
         wopt(L!("print-rusage-self"), NoArgument, RUSAGE_ARG),
@@ -239,6 +264,7 @@ fn fish_parse_opt(args: &mut [WString], opts: &mut FishCmdOpts) -> ControlFlow<i
         wopt(L!("profile"), RequiredArgument, 'p'),
         wopt(L!("profile-startup"), RequiredArgument, PROFILE_STARTUP_ARG),
         wopt(L!("private"), NoArgument, 'P'),
+        wopt(L!("record-input"), RequiredArgument, RECORD_INPUT_ARG),
         wopt(L!("help"), NoArgument, 'h'),
         wopt(L!("version"), NoArgument, 'v'),
     ];
@@ -261,11 +287,39 @@ fn fish_parse_opt(args: &mut [WString], opts: &mut FishCmdOpts) -> ControlFlow<i
                     }
                 }
             }
+            DEBUG_SCRIPT_ARG => opts.debug_script = true,
             'o' => opts.debug_output = Some(OsString::from_vec(wcs2bytes(w.woptarg.unwrap()))),
             'f' => opts.features = w.woptarg.unwrap().to_owned(),
             'h' => opts.batch_cmds.push("__fish_print_help fish".into()),
             'i' => opts.is_interactive_session = true,
             'l' => opts.is_login = true,
+            LSP_ARG => opts.lsp = true,
+            LINT_ARG => opts.lint = true,
+            LINT_FORMAT_ARG => {
+                opts.lint_format = match w.woptarg.unwrap() {
+                    s if s == "text" => LintFormat::Text,
+                    s if s == "json" => LintFormat::Json,
+                    s => {
+                        err_fmt!("Unknown lint format '%s'", s)
+                            .cmd(L!("fish"))
+                            .append_to_msg('\n')
+                            .write_to(&mut OutputStream::Fd(FdOutputStream::new(STDERR_FILENO)));
+                        return ControlFlow::Break(1);
+                    }
+                }
+            }
+            LINT_IGNORE_ARG => {
+                for name in w.woptarg.unwrap().split(',') {
+                    let Some(check) = Check::from_name(name) else {
+                        err_fmt!("Unknown lint check '%s'", name)
+                            .cmd(L!("fish"))
+                            .append_to_msg('\n')
+                            .write_to(&mut OutputStream::Fd(FdOutputStream::new(STDERR_FILENO)));
+                        return ControlFlow::Break(1);
+                    };
+                    opts.lint_ignore.push(check);
+                }
+            }
             'N' => {
                 opts.no_config = true;
                 // --no-config implies private mode, we won't be saving history
@@ -292,6 +346,9 @@ fn fish_parse_opt(args: &mut [WString], opts: &mut FishCmdOpts) -> ControlFlow<i
             // "--profile" - this does not activate profiling right away,
             // rather it's done after startup is finished.
             'p' => opts.profile_output = Some(OsString::from_vec(wcs2bytes(w.woptarg.unwrap()))),
+            RECORD_INPUT_ARG => {
+                opts.record_input_output = Some(OsString::from_vec(wcs2bytes(w.woptarg.unwrap())));
+            }
             PROFILE_STARTUP_ARG => {
                 // With "--profile-startup" we immediately turn profiling on.
                 opts.profile_startup_output =
@@ -336,13 +393,26 @@ fn fish_parse_opt(args: &mut [WString], opts: &mut FishCmdOpts) -> ControlFlow<i
     }
     let optind = w.wopt_index;
 
+    if opts.debug_script && opts.batch_cmds.is_empty() && optind == args.len() {
+        err_str!("--debug-script needs a script or commands to debug")
+            .cmd(L!("fish"))
+            .append_to_msg('\n')
+            .write_to(&mut OutputStream::Fd(FdOutputStream::new(STDERR_FILENO)));
+        return ControlFlow::Break(1);
+    }
+
     // If our command name begins with a dash that implies we're a login shell.
     opts.is_login |= args[0].char_at(0) == '-';
 
     // We are an interactive session if we have not been given an explicit
     // command or file to execute and stdin is a tty. Note that the -i or
     // --interactive options also force interactive mode.
-    if opts.batch_cmds.is_empty() && optind == args.len() && isatty(STDIN_FILENO) {
+    if opts.batch_cmds.is_empty()
+        && optind == args.len()
+        && !opts.lsp
+        && !opts.lint
+        && isatty(STDIN_FILENO)
+    {
         set_interactive_session(true);
     }
 
@@ -374,6 +444,7 @@ fn throwing_main() -> i32 {
     let mut res = Err(STATUS_CMD_ERROR);
 
     signal_unblock_all();
+    record_inherited_fds();
     topic_monitor::topic_monitor_init();
     threads::init();
 
@@ -428,6 +499,26 @@ fn throwing_main() -> i32 {
         }
     }
 
+    if let Some(record_path) = &opts.record_input_output {
+        let result = File::options()
+            .write(true)
+            .truncate(true)
+            .create(true)
+            .open(record_path)
+            .and_then(start_input_recording);
+        if let Err(e) = result {
+            let record_path_string = format!("{record_path:?}");
+            eprintf!("Could not open file %s\n", record_path_string);
+            eprintf!("%s\n", e);
+            return 1;
+        }
+    }
+
+    // Linting only parses the scripts, so it needs none of the setup below.
+    if opts.lint {
+        return fish::lint::run(&args[my_optind..], opts.lint_format, &opts.lint_ignore);
+    }
+
     // No-exec is prohibited when in interactive mode.
     if opts.is_interactive_session && opts.no_exec {
         flog!(
@@ -451,6 +542,27 @@ fn throwing_main() -> i32 {
         start_private_mode(EnvStack::globals());
     }
 
+    // The language server talks to its client over stdout, so anything else written there, like
+    // output from config.fish or completions, goes to stderr instead.
+    let lsp_output = if opts.lsp {
+        let stdout = unsafe { BorrowedFd::borrow_raw(STDOUT_FILENO) };
+        match stdout
+            .try_clone_to_owned()
+            .and_then(|fd| heightenize_fd(fd, true).map_err(std::io::Error::from))
+        {
+            Ok(fd) => {
+                unsafe { libc::dup2(STDERR_FILENO, STDOUT_FILENO) };
+                Some(File::from(fd))
+            }
+            Err(e) => {
+                eprintf!("Could not duplicate stdout: %s\n", e);
+                return 1;
+            }
+        }
+    } else {
+        None
+    };
+
     // Only save (and therefore restore) the fg process group if we are interactive. See issues
     // #197 and #1002.
     if is_interactive_session() {
@@ -522,7 +634,19 @@ fn throwing_main() -> i32 {
     // Clear signals in case we were interrupted (#9024).
     signal_clear_cancel();
 
-    if !opts.batch_cmds.is_empty() {
+    if opts.debug_script {
+        let script = opts
+            .batch_cmds
+            .is_empty()
+            .then(|| args[my_optind].as_utfstr());
+        fish::debugger::start(script);
+    }
+
+    if let Some(output) = lsp_output {
+        let status = fish::lsp::run(parser, output);
+        parser.set_last_statuses(Statuses::just(status));
+        res = Ok(());
+    } else if !opts.batch_cmds.is_empty() {
         // Run the commands specified as arguments, if any.
         if get_login() {
             // Do something nasty to support OpenSUSE assuming we're bash. This may modify cmds.
@@ -588,6 +712,8 @@ fn throwing_main() -> i32 {
         }
     }
 
+    fish::debugger::finish();
+
     let exit_status = if res.is_err() {
         STATUS_CMD_UNKNOWN
     } else {
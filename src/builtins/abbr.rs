@@ -4,8 +4,10 @@ use crate::{
     builtins::Error,
     common::valid_func_name,
     env::{EnvMode, EnvStackSetResult},
-    err_fmt, err_str,
+    err_fmt, err_raw, err_str,
     highlight::highlight_and_colorize,
+    parse_constants::ParseErrorList,
+    parse_util::detect_parse_errors,
     parser::ParserEnvSetMode,
     re::{regex_make_anchored, to_boxed_chars},
 };
@@ -19,6 +21,9 @@ localizable_consts! {
 
     ABBR_CANNOT_HAVE_SPACES
     "Abbreviation '%s' cannot have spaces in the word"
+
+    ABBR_INVALID_WORDS
+    "Abbreviation '%s' must be words separated by single spaces"
 }
 
 const CMD: &wstr = L!("abbr");
@@ -36,6 +41,7 @@ struct Options {
     commands: Vec<WString>,
     position: Option<Position>,
     set_cursor_marker: Option<WString>,
+    condition: Option<WString>,
     args: Vec<WString>,
     color: ColorEnabled,
 }
@@ -90,6 +96,9 @@ impl Options {
         if !self.add && self.set_cursor_marker.is_some() {
             return Some(err_fmt!(OPTION_REQUIRES_ARG, "--set-cursor", "--add"));
         }
+        if !self.add && self.condition.is_some() {
+            return Some(err_fmt!(OPTION_REQUIRES_ARG, "--condition", "--add"));
+        }
         if self
             .set_cursor_marker
             .as_ref()
@@ -155,6 +164,10 @@ fn abbr_show(opts: &Options, streams: &mut IoStreams, parser: &mut Parser) -> Bu
                 add_arg(L!("--command"));
                 add_arg(&escape_string(cmd, style));
             }
+            if let Some(ref condition) = abbr.condition {
+                add_arg(L!("--condition"));
+                add_arg(&escape_string(condition, style));
+            }
             add_arg(L!("--"));
             // Literal abbreviations have the name and key as the same.
             // Regex abbreviations have a pattern separate from the name.
@@ -301,7 +314,7 @@ fn abbr_query(opts: &Options) -> BuiltinResult {
 }
 
 // Add a named abbreviation.
-fn abbr_add(opts: &Options, streams: &mut IoStreams) -> BuiltinResult {
+fn abbr_add(opts: &Options, streams: &mut IoStreams, parser: &Parser) -> BuiltinResult {
     let subcmd = L!("--add");
 
     if opts.args.len() < 2 && opts.function.is_none() {
@@ -318,12 +331,22 @@ fn abbr_add(opts: &Options, streams: &mut IoStreams) -> BuiltinResult {
         return Err(STATUS_INVALID_ARGS);
     }
     let name = &opts.args[0];
-    if name.chars().any(|c| c.is_whitespace()) {
+    if opts.regex_pattern.is_some() && name.chars().any(|c| c.is_whitespace()) {
         err_fmt!(ABBR_CANNOT_HAVE_SPACES, name.as_utfstr())
             .subcmd(CMD, subcmd)
             .finish(streams);
         return Err(STATUS_INVALID_ARGS);
     }
+    // A literal abbreviation may match several tokens, given as words separated by single spaces.
+    if name
+        .split(' ')
+        .any(|word| word.is_empty() || word.chars().any(|c| c.is_whitespace()))
+    {
+        err_fmt!(ABBR_INVALID_WORDS, name.as_utfstr())
+            .subcmd(CMD, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
 
     let key: &wstr;
     let regex: Option<Box<Regex>>;
@@ -404,6 +427,24 @@ fn abbr_add(opts: &Options, streams: &mut IoStreams) -> BuiltinResult {
         return Err(STATUS_INVALID_ARGS);
     }
 
+    if let Some(condition) = &opts.condition {
+        let mut errors = ParseErrorList::new();
+        if detect_parse_errors(condition, Some(&mut errors), false).is_err() {
+            let prefix = WString::from(L!("--condition '")) + &condition[..] + L!("'");
+            for error in errors {
+                err_raw!(&error.describe_with_prefix(
+                    condition,
+                    &prefix,
+                    parser.is_interactive(),
+                    false,
+                ))
+                .cmd(CMD)
+                .finish(streams);
+            }
+            return Err(STATUS_INVALID_ARGS);
+        }
+    }
+
     // Note historically we have allowed overwriting existing abbreviations.
     abbrs::with_abbrs_mut(move |abbrs| {
         abbrs.add(Abbreviation {
@@ -414,6 +455,7 @@ fn abbr_add(opts: &Options, streams: &mut IoStreams) -> BuiltinResult {
             replacement_is_function: opts.function.is_some(),
             position,
             set_cursor_marker: opts.set_cursor_marker.clone(),
+            condition: opts.condition.clone(),
             from_universal: false,
             commands: opts.commands.clone(),
         });
@@ -463,6 +505,7 @@ pub fn abbr(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
     const NON_OPTION_ARGUMENT: char = 1 as char;
     const SET_CURSOR_SHORT: char = 2 as char;
     const RENAME_SHORT: char = 3 as char;
+    const CONDITION_SHORT: char = 4 as char;
 
     // Note the leading '-' causes wgetopter to return arguments in order, instead of permuting
     // them. We need this behavior for compatibility with pre-builtin abbreviations where options
@@ -480,6 +523,7 @@ pub fn abbr(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
             SET_CURSOR_SHORT,
         ),
         wopt(L!("function"), ArgType::RequiredArgument, 'f'),
+        wopt(L!("condition"), ArgType::RequiredArgument, CONDITION_SHORT),
         wopt(L!("rename"), ArgType::NoArgument, RENAME_SHORT),
         wopt(L!("erase"), ArgType::NoArgument, 'e'),
         wopt(L!("query"), ArgType::NoArgument, 'q'),
@@ -561,6 +605,15 @@ pub fn abbr(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
                     .insert(w.woptarg.unwrap_or(L!("%")).to_owned());
             }
             'f' => opts.function = w.woptarg.map(ToOwned::to_owned),
+            CONDITION_SHORT => {
+                if opts.condition.is_some() {
+                    err_str!("Cannot specify multiple conditions")
+                        .cmd(CMD)
+                        .finish(streams);
+                    return Err(STATUS_INVALID_ARGS);
+                }
+                opts.condition = w.woptarg.map(ToOwned::to_owned);
+            }
             RENAME_SHORT => opts.rename = true,
             'e' => opts.erase = true,
             'q' => opts.query = true,
@@ -615,7 +668,7 @@ pub fn abbr(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
     }
 
     if opts.add {
-        return abbr_add(&opts, streams);
+        return abbr_add(&opts, streams, parser);
     }
     if opts.show {
         return abbr_show(&opts, streams, parser);
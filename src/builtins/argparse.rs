@@ -8,6 +8,7 @@ use crate::exec::exec_subshell;
 use crate::parser::ParserEnvSetMode;
 use crate::wutil::fish_iswalnum;
 use crate::{err_fmt, err_str};
+use fish_common::escape;
 
 const VAR_NAME_PREFIX: &wstr = L!("_flag_");
 
@@ -23,6 +24,7 @@ localizable_consts!(
 struct OptionSpec<'args> {
     short_flag: char,
     long_flag: &'args wstr,
+    description: &'args wstr,
     validation_command: &'args wstr,
     vals: Vec<WString>,
     short_flag_valid: bool,
@@ -58,6 +60,12 @@ struct Options<'args> {
     unknown_arguments: ArgType,
     strict_long_opts: bool,
     print_help: bool,
+    generate_help: bool,
+    generate_completions: Option<&'args wstr>,
+    /// The names and descriptions of the subcommands.
+    subcommands: Vec<(&'args wstr, &'args wstr)>,
+    /// The subcommand that was given, if any.
+    subcommand: Option<&'args wstr>,
     stop_nonopt: bool,
     min_args: usize,
     max_args: usize,
@@ -67,6 +75,8 @@ struct Options<'args> {
     args: Vec<Cow<'args, wstr>>,
     args_opts: Vec<Cow<'args, wstr>>,
     options: HashMap<char, OptionSpec<'args>>,
+    /// The short flags of the options, in the order they were specified.
+    options_order: Vec<char>,
     long_to_short_flag: HashMap<WString, char>,
     exclusive_flag_sets: Vec<Vec<char>>,
 }
@@ -93,6 +103,13 @@ const LONG_OPTIONS: &[WOption] = &[
     wopt(L!("help"), ArgType::NoArgument, 'h'),
     wopt(L!("min-args"), ArgType::RequiredArgument, 'N'),
     wopt(L!("max-args"), ArgType::RequiredArgument, 'X'),
+    wopt(L!("generate-help"), ArgType::NoArgument, '\x01'),
+    wopt(
+        L!("generate-completions"),
+        ArgType::RequiredArgument,
+        '\x02',
+    ),
+    wopt(L!("subcommand"), ArgType::RequiredArgument, '\x03'),
 ];
 
 // Check if any pair of mutually exclusive options was seen. Note that since every option must have
@@ -376,6 +393,12 @@ fn parse_option_spec<'args>(
     counter: &mut u32,
     streams: &mut IoStreams,
 ) -> bool {
+    // Anything after a tab is the option's description, like in completions.
+    let (option_spec, description) = match option_spec.find_char('\t') {
+        Some(pos) => (&option_spec[..pos], &option_spec[pos + 1..]),
+        None => (option_spec, L!("")),
+    };
+
     if option_spec.is_empty() {
         err_str!("An option spec must have at least a short or a long flag")
             .cmd(&opts.name)
@@ -396,6 +419,7 @@ fn parse_option_spec<'args>(
     }
 
     let mut opt_spec = OptionSpec::new(s.char_at(0));
+    opt_spec.description = description;
 
     // Try parsing stuff after the short flag.
     if s.char_count() > 1
@@ -436,6 +460,7 @@ fn parse_option_spec<'args>(
     }
 
     // Record our option under its short flag.
+    opts.options_order.push(opt_spec.short_flag);
     opts.options.insert(opt_spec.short_flag, opt_spec);
 
     true
@@ -545,6 +570,23 @@ fn parse_cmd_opts<'args>(
             // definitions we'll parse these strings into a more useful data structure.
             'x' => opts.raw_exclusive_flags.push(w.woptarg.unwrap()),
             'h' => opts.print_help = true,
+            '\x01' => opts.generate_help = true,
+            '\x02' => opts.generate_completions = Some(w.woptarg.unwrap()),
+            '\x03' => {
+                // Like option specs, anything after a tab is the description.
+                let arg = w.woptarg.unwrap();
+                let (name, description) = match arg.find_char('\t') {
+                    Some(pos) => (&arg[..pos], &arg[pos + 1..]),
+                    None => (arg, L!("")),
+                };
+                if name.is_empty() || name.starts_with('-') {
+                    err_fmt!("Invalid subcommand '%s'", name)
+                        .cmd(cmd)
+                        .finish(streams);
+                    return Err(STATUS_INVALID_ARGS);
+                }
+                opts.subcommands.push((name, description));
+            }
             'N' => {
                 opts.min_args = {
                     let x = fish_wcstol(w.woptarg.unwrap()).unwrap_or(-1);
@@ -607,6 +649,22 @@ fn parse_cmd_opts<'args>(
         return Ok(SUCCESS);
     }
 
+    // Options are only parsed up to the subcommand, the rest is left for it.
+    if !opts.subcommands.is_empty() {
+        opts.stop_nonopt = true;
+    }
+
+    if opts.generate_help && opts.generate_completions.is_some() {
+        err_fmt!(
+            Error::COMBO_EXCLUSIVE,
+            "--generate-help",
+            "--generate-completions"
+        )
+        .cmd(cmd)
+        .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
     if "--" == args_read[w.wopt_index - 1] {
         w.wopt_index -= 1;
     }
@@ -1076,8 +1134,21 @@ fn argparse_parse_args<'args>(
 
     check_for_mutually_exclusive_flags(opts, streams)?;
 
-    opts.args
-        .extend(args[optind..].iter().map(|&s| Cow::Borrowed(s)));
+    let mut rest = &args[optind..];
+    if !opts.subcommands.is_empty() {
+        if let Some((&word, remaining)) = rest.split_first() {
+            if !opts.subcommands.iter().any(|&(name, _)| name == word) {
+                err_fmt!("%s: unknown subcommand", word)
+                    .cmd(&opts.name)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            opts.subcommand = Some(word);
+            rest = remaining;
+        }
+    }
+
+    opts.args.extend(rest.iter().map(|&s| Cow::Borrowed(s)));
 
     Ok(SUCCESS)
 }
@@ -1127,12 +1198,182 @@ fn set_argparse_result_vars(vars: &EnvStack, local_mode: EnvSetMode, opts: Optio
         }
     }
 
+    if let Some(subcommand) = opts.subcommand {
+        let var_name = WString::from(VAR_NAME_PREFIX) + "subcommand";
+        vars.set_one(&var_name, local_mode, subcommand.to_owned());
+    }
+
     let args = opts.args.into_iter().map(|s| s.into_owned()).collect();
     vars.set(L!("argv"), local_mode, args);
     let args_opts = opts.args_opts.into_iter().map(|s| s.into_owned()).collect();
     vars.set(L!("argv_opts"), local_mode, args_opts);
 }
 
+/// The placeholder for an option's value in the generated help, like `NAME` for `--name`.
+fn value_placeholder(opt_spec: &OptionSpec) -> WString {
+    if opt_spec.long_flag.is_empty() {
+        return L!("VALUE").to_owned();
+    }
+    opt_spec
+        .long_flag
+        .chars()
+        .map(|c| {
+            if fish_iswalnum(c) {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Describe how an option is given, like `-n, --name=NAME`.
+fn help_flags(opts: &Options, opt_spec: &OptionSpec) -> WString {
+    let value = value_placeholder(opt_spec);
+    let mut flags = vec![];
+    if opt_spec.short_flag == opts.implicit_int_flag {
+        flags.push(L!("-NUMBER").to_owned());
+    }
+    if opt_spec.short_flag_valid {
+        let mut short = WString::from_chars(['-', opt_spec.short_flag]);
+        if opt_spec.long_flag.is_empty() {
+            match opt_spec.arg_type {
+                ArgType::RequiredArgument => short.push_utfstr(&sprintf!(" %s", value)),
+                ArgType::OptionalArgument => short.push_utfstr(&sprintf!("[%s]", value)),
+                ArgType::NoArgument => (),
+            }
+        }
+        flags.push(short);
+    }
+    if !opt_spec.long_flag.is_empty() {
+        let mut long = WString::from("--") + opt_spec.long_flag;
+        match opt_spec.arg_type {
+            ArgType::RequiredArgument => long.push_utfstr(&sprintf!("=%s", value)),
+            ArgType::OptionalArgument => long.push_utfstr(&sprintf!("[=%s]", value)),
+            ArgType::NoArgument => (),
+        }
+        if flags.is_empty() {
+            // Line long options up with those that have a short flag.
+            return L!("    ").to_owned() + &long[..];
+        }
+        flags.push(long);
+    }
+
+    let mut result = WString::new();
+    for flag in flags {
+        if !result.is_empty() {
+            result.push_str(", ");
+        }
+        result.push_utfstr(&flag);
+    }
+    result
+}
+
+/// Print a usage message listing the options in the order they were specified.
+fn print_generated_help(opts: &Options, streams: &mut IoStreams) {
+    let mut synopsis = opts.name.clone();
+    if !opts.options.is_empty() {
+        synopsis.push_str(" [OPTIONS]");
+    }
+    if !opts.subcommands.is_empty() {
+        synopsis.push_str(" SUBCOMMAND");
+    }
+    if opts.min_args > 0 {
+        synopsis.push_str(if opts.max_args == 1 {
+            " ARG"
+        } else {
+            " ARG ..."
+        });
+    } else if opts.max_args > 0 {
+        synopsis.push_str(if opts.max_args == 1 {
+            " [ARG]"
+        } else {
+            " [ARG ...]"
+        });
+    }
+    streams.out.appendln(&wgettext_fmt!("Usage: %s", synopsis));
+
+    let options: Vec<(WString, &wstr)> = opts
+        .options_order
+        .iter()
+        .map(|short| {
+            let opt_spec = &opts.options[short];
+            (help_flags(opts, opt_spec), opt_spec.description)
+        })
+        .collect();
+    let subcommands: Vec<(WString, &wstr)> = opts
+        .subcommands
+        .iter()
+        .map(|&(name, description)| (name.to_owned(), description))
+        .collect();
+    // Both sections share the same column for the descriptions.
+    let width = options
+        .iter()
+        .chain(&subcommands)
+        .map(|(flags, _)| flags.char_count())
+        .max()
+        .unwrap_or_default();
+
+    for (title, lines) in [
+        (wgettext!("Options:"), options),
+        (wgettext!("Subcommands:"), subcommands),
+    ] {
+        if lines.is_empty() {
+            continue;
+        }
+        streams.out.append('\n');
+        streams.out.appendln(title);
+        for (flags, description) in lines {
+            if description.is_empty() {
+                streams.out.appendln(&sprintf!("  %s", flags));
+            } else {
+                streams
+                    .out
+                    .appendln(&sprintf!("  %-*s  %s", width, flags, description));
+            }
+        }
+    }
+}
+
+/// Print `complete` commands for the options, for the given command.
+fn print_generated_completions(opts: &Options, command: &wstr, streams: &mut IoStreams) {
+    for short in &opts.options_order {
+        let opt_spec = &opts.options[short];
+        // An implicit int flag without a long flag can't be completed.
+        if !opt_spec.short_flag_valid && opt_spec.long_flag.is_empty() {
+            continue;
+        }
+        let mut line = sprintf!("complete -c %s", escape(command));
+        if opt_spec.short_flag_valid {
+            line.push_utfstr(&sprintf!(
+                " -s %s",
+                escape(&WString::from_chars([opt_spec.short_flag]))
+            ));
+        }
+        if !opt_spec.long_flag.is_empty() {
+            line.push_utfstr(&sprintf!(" -l %s", escape(opt_spec.long_flag)));
+        }
+        if opt_spec.arg_type == ArgType::RequiredArgument {
+            line.push_str(" -r");
+        }
+        if !opt_spec.description.is_empty() {
+            line.push_utfstr(&sprintf!(" -d %s", escape(opt_spec.description)));
+        }
+        streams.out.appendln(&line);
+    }
+    for &(name, description) in &opts.subcommands {
+        let mut line = sprintf!(
+            "complete -c %s -n __fish_use_subcommand -f -a %s",
+            escape(command),
+            escape(name)
+        );
+        if !description.is_empty() {
+            line.push_utfstr(&sprintf!(" -d %s", escape(description)));
+        }
+        streams.out.appendln(&line);
+    }
+}
+
 /// The argparse builtin. This is explicitly not compatible with the BSD or GNU version of this
 /// command. That's because fish doesn't have the weird quoting problems of POSIX shells. So we
 /// don't need to support flags like `--unquoted`. Similarly we don't want to support introducing
@@ -1163,6 +1404,16 @@ pub fn argparse(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]
         return Ok(SUCCESS);
     }
 
+    // Only describe the options, without parsing any arguments.
+    if opts.generate_help {
+        print_generated_help(&opts, streams);
+        return Ok(SUCCESS);
+    }
+    if let Some(command) = opts.generate_completions {
+        print_generated_completions(&opts, command, streams);
+        return Ok(SUCCESS);
+    }
+
     parse_exclusive_args(&mut opts, streams)?;
 
     // wgetopt expects the first argument to be the command, and skips it.
@@ -6,16 +6,25 @@ use crate::{
     common::valid_var_name,
     err_fmt, err_raw, err_str,
     highlight::{colorize, highlight_and_colorize, highlight_shell},
-    input::{Binding, BindingSet, KeyNameStyle, bindings, input_function_get_names},
+    input::{
+        Binding, BindingSet, KeyNameStyle, KeyTranslation, SequenceTimeout, UserInputFunction,
+        bindings, input_function_get_code, input_function_get_names, key_translations,
+    },
     key::{
         self, KEY_NAMES, Key, MAX_FUNCTION_KEY, Modifiers, char_to_symbol, function_key, parse_keys,
     },
 };
 use fish_common::{EscapeFlags, EscapeStringStyle, escape, escape_string, help_section};
-use fish_widestring::bytes2wcstring;
+use fish_widestring::{bytes2wcstring, wcs2bytes};
 use std::sync::MutexGuard;
+use std::time::Duration;
 
 const DEFAULT_BIND_MODE: &wstr = L!("default");
+const TIMEOUT_OPTION_CHAR: char = '\x01';
+const ON_TIMEOUT_OPTION_CHAR: char = '\x02';
+const DEFINE_FUNC_OPTION_CHAR: char = '\x03';
+const TRANSLATE_OPTION_CHAR: char = '\x04';
+const TERM_OPTION_CHAR: char = '\x05';
 
 enum BindMode {
     Insert,
@@ -31,11 +40,16 @@ struct Options {
     silent: bool,
     have_user: bool,
     user: bool,
+    define_function: bool,
+    translate: bool,
+    term: Option<WString>,
     have_preset: bool,
     preset: bool,
     mode: BindMode,
     bind_mode: Option<WString>,
     sets_bind_mode: Option<WString>,
+    timeout: Option<Duration>,
+    on_timeout: Vec<WString>,
     color: ColorEnabled,
 }
 
@@ -48,11 +62,16 @@ impl Options {
             silent: false,
             have_user: false,
             user: false,
+            define_function: false,
+            translate: false,
+            term: None,
             have_preset: false,
             preset: false,
             mode: BindMode::Insert,
             bind_mode: None,
             sets_bind_mode: None,
+            timeout: None,
+            on_timeout: Vec::new(),
             color: ColorEnabled::default(),
         }
     }
@@ -95,6 +114,15 @@ impl BuiltinBind {
             }
         }
 
+        if let Some(timeout) = &bind.timeout {
+            out.push_str(" --timeout ");
+            out.push_utfstr(&timeout.delay.as_millis().to_wstring());
+            for cmd in &timeout.fallback {
+                out.push_str(" --on-timeout ");
+                out.push_utfstr(&escape(cmd));
+            }
+        }
+
         out.push(' ');
         match bind.key_name_style {
             KeyNameStyle::Plain => {
@@ -255,12 +283,247 @@ impl BuiltinBind {
 
     /// Print all the special key binding functions to string buffer used for standard output.
     fn function_names(&self, streams: &mut IoStreams) {
-        let names = input_function_get_names();
+        let mut names = input_function_get_names();
+        names.extend(self.bindings.functions().iter().map(|f| f.name.as_utfstr()));
+        names.sort_unstable();
         for name in names {
             streams.out.appendln(name);
         }
     }
 
+    /// List the user-defined input functions, or only the one with the given name.
+    /// Returns false if there is no such function.
+    fn list_functions(
+        &self,
+        name: Option<&wstr>,
+        parser: &mut Parser,
+        streams: &mut IoStreams,
+    ) -> bool {
+        let mut found = false;
+        for function in self.bindings.functions() {
+            if name.is_some_and(|name| name != function.name) {
+                continue;
+            }
+            found = true;
+            let out = sprintf!(
+                "bind --define-function %s %s\n",
+                escape(&function.name),
+                escape(&function.script)
+            );
+            if self.opts.color.enabled(streams) {
+                streams.out.append(&bytes2wcstring(&highlight_and_colorize(
+                    &out,
+                    &mut parser.context(),
+                )));
+            } else {
+                streams.out.append(&out);
+            }
+        }
+        found
+    }
+
+    /// Define, or list the definitions of, user-defined input functions.
+    fn define_function(
+        &mut self,
+        optind: usize,
+        argv: &[&wstr],
+        parser: &mut Parser,
+        streams: &mut IoStreams,
+    ) -> bool {
+        let cmd = argv[0];
+        let args = &argv[optind..];
+        match args {
+            [] => {
+                self.list_functions(None, parser, streams);
+            }
+            [name] => {
+                if !self.list_functions(Some(name), parser, streams) {
+                    if !self.opts.silent {
+                        err_fmt!("No input function named '%s'", name)
+                            .cmd(cmd)
+                            .finish(streams);
+                    }
+                    return true;
+                }
+            }
+            [name, script] => {
+                let is_valid_name = name.chars().next().is_some_and(|c| c != '-')
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+                if !is_valid_name {
+                    err_fmt!("'%s' is not a valid input function name", name)
+                        .cmd(cmd)
+                        .finish(streams);
+                    return true;
+                }
+                if input_function_get_code(name).is_some() {
+                    err_fmt!("'%s' is a built-in input function", name)
+                        .cmd(cmd)
+                        .finish(streams);
+                    return true;
+                }
+                self.bindings.define_function(UserInputFunction {
+                    name: (*name).to_owned(),
+                    script: (*script).to_owned(),
+                });
+            }
+            _ => {
+                err_str!(Error::TOO_MANY_ARGUMENTS).cmd(cmd).finish(streams);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// List the key translations for the terminal given with --term, or for all terminals, or only
+    /// the one for the given sequence. Returns false if there is no such translation.
+    fn list_translations(
+        &self,
+        sequence: Option<&[u8]>,
+        parser: &mut Parser,
+        streams: &mut IoStreams,
+    ) -> bool {
+        let translations = key_translations();
+        let term = match sequence {
+            Some(_) => Some(self.opts.term.as_deref().unwrap_or(translations.term())),
+            None => self.opts.term.as_deref(),
+        };
+        let mut found = false;
+        for translation in translations.all() {
+            if term.is_some_and(|term| term != translation.term)
+                || sequence.is_some_and(|seq| seq != translation.sequence)
+            {
+                continue;
+            }
+            found = true;
+            let out = sprintf!(
+                "bind --translate --term %s %s %s\n",
+                escape(&translation.term),
+                escape(&bytes2wcstring(&translation.sequence)),
+                translation.key
+            );
+            if self.opts.color.enabled(streams) {
+                streams.out.append(&bytes2wcstring(&highlight_and_colorize(
+                    &out,
+                    &mut parser.context(),
+                )));
+            } else {
+                streams.out.append(&out);
+            }
+        }
+        found
+    }
+
+    /// Declare, or list, translations of raw sequences sent by a terminal to keys.
+    fn translate(
+        &mut self,
+        optind: usize,
+        argv: &[&wstr],
+        parser: &mut Parser,
+        streams: &mut IoStreams,
+    ) -> bool {
+        let cmd = argv[0];
+        let args = &argv[optind..];
+        match args {
+            [] => {
+                self.list_translations(None, parser, streams);
+            }
+            [sequence] => {
+                if !self.list_translations(Some(&wcs2bytes(*sequence)), parser, streams) {
+                    if !self.opts.silent {
+                        err_fmt!(
+                            "No translation found for sequence '%s'",
+                            escape_string(
+                                sequence,
+                                EscapeStringStyle::Script(EscapeFlags::NO_PRINTABLES)
+                            )
+                        )
+                        .cmd(cmd)
+                        .finish(streams);
+                    }
+                    return true;
+                }
+            }
+            [sequence, key] => {
+                if sequence.is_empty() {
+                    err_str!("The sequence to translate must not be empty")
+                        .cmd(cmd)
+                        .finish(streams);
+                    return true;
+                }
+                let Some(keys) = self.compute_seq(streams, key) else {
+                    return true;
+                };
+                let [key] = keys[..] else {
+                    err_fmt!("'%s' is not a single key", key)
+                        .cmd(cmd)
+                        .finish(streams);
+                    return true;
+                };
+                if key::EVENT_KEYS.contains(&key.codepoint) {
+                    err_fmt!("Cannot translate to '%s'", key)
+                        .cmd(cmd)
+                        .finish(streams);
+                    return true;
+                }
+                let mut translations = key_translations();
+                let term = self
+                    .opts
+                    .term
+                    .clone()
+                    .unwrap_or_else(|| translations.term().to_owned());
+                translations.add(KeyTranslation {
+                    term,
+                    sequence: wcs2bytes(*sequence),
+                    key,
+                });
+            }
+            _ => {
+                err_str!(Error::TOO_MANY_ARGUMENTS).cmd(cmd).finish(streams);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Erase the given key translations, or all of them with --all.
+    fn erase_translations(
+        &mut self,
+        optind: usize,
+        argv: &[&wstr],
+        streams: &mut IoStreams,
+    ) -> bool {
+        let mut translations = key_translations();
+        if self.opts.all {
+            translations.clear(self.opts.term.as_deref());
+            return false;
+        }
+        let term = self
+            .opts
+            .term
+            .clone()
+            .unwrap_or_else(|| translations.term().to_owned());
+        let mut ok = true;
+        for sequence in &argv[optind..] {
+            if !translations.erase(&term, &wcs2bytes(*sequence)) {
+                if !self.opts.silent {
+                    err_fmt!(
+                        "No translation found for sequence '%s'",
+                        escape_string(
+                            sequence,
+                            EscapeStringStyle::Script(EscapeFlags::NO_PRINTABLES)
+                        )
+                    )
+                    .cmd(argv[0])
+                    .finish(streams);
+                }
+                ok = false;
+            }
+        }
+        !ok
+    }
+
     /// Add specified key binding.
     #[allow(clippy::too_many_arguments)]
     fn add(
@@ -278,6 +541,40 @@ impl BuiltinBind {
         let Some(key_seq) = self.compute_seq(streams, seq) else {
             return true;
         };
+        if let Some(event_key) = key_seq
+            .iter()
+            .find(|key| key::EVENT_KEYS.contains(&key.codepoint))
+        {
+            let allows_modifiers = key::MOUSE_KEYS.contains(&event_key.codepoint);
+            if key_seq.len() != 1
+                || (event_key.modifiers.is_some() && !allows_modifiers)
+                || event_key.release
+            {
+                err_fmt!(
+                    "'%s' can only be bound on its own",
+                    Key::from_raw(event_key.codepoint)
+                )
+                .cmd(L!("bind"))
+                .finish(streams);
+                return true;
+            }
+        }
+        if self.opts.timeout.is_none() && !self.opts.on_timeout.is_empty() {
+            err_str!("--on-timeout requires --timeout")
+                .cmd(L!("bind"))
+                .finish(streams);
+            return true;
+        }
+        let timeout = self.opts.timeout.map(|delay| SequenceTimeout {
+            delay,
+            fallback: self.opts.on_timeout.clone(),
+        });
+        if timeout.is_some() && key_seq.len() < 2 {
+            err_str!("--timeout requires a sequence of at least two keys")
+                .cmd(L!("bind"))
+                .finish(streams);
+            return true;
+        }
         let key_name_style = if is_raw_escape_sequence {
             KeyNameStyle::RawEscapeSequence
         } else {
@@ -292,6 +589,7 @@ impl BuiltinBind {
             sets_mode,
             user,
             definition_file,
+            timeout,
         );
         false
     }
@@ -366,6 +664,11 @@ impl BuiltinBind {
                 self.list(bind_mode, false, parser, streams);
             }
             if self.opts.user {
+                // Translations and functions come first, so the output can be sourced again.
+                if bind_mode.is_none() {
+                    self.list_translations(None, parser, streams);
+                    self.list_functions(None, parser, streams);
+                }
                 self.list(bind_mode, true, parser, streams);
             }
         } else if arg_count == 1 {
@@ -461,7 +764,12 @@ fn parse_cmd_opts(
         wopt(L!("sets-mode"), RequiredArgument, 'm'),
         wopt(L!("silent"), NoArgument, 's'),
         wopt(L!("user"), NoArgument, 'u'),
+        wopt(L!("timeout"), RequiredArgument, TIMEOUT_OPTION_CHAR),
+        wopt(L!("on-timeout"), RequiredArgument, ON_TIMEOUT_OPTION_CHAR),
         wopt(L!("color"), RequiredArgument, COLOR_OPTION_CHAR),
+        wopt(L!("define-function"), NoArgument, DEFINE_FUNC_OPTION_CHAR),
+        wopt(L!("translate"), NoArgument, TRANSLATE_OPTION_CHAR),
+        wopt(L!("term"), RequiredArgument, TERM_OPTION_CHAR),
     ];
 
     let check_mode_name = |streams: &mut IoStreams, mode_name: &wstr| -> Result<(), ErrorCode> {
@@ -529,6 +837,24 @@ fn parse_cmd_opts(
                 builtin_unknown_option(parser, streams, cmd, argv[w.wopt_index - 1], true);
                 return Err(STATUS_INVALID_ARGS);
             }
+            TIMEOUT_OPTION_CHAR => {
+                let arg = w.woptarg.unwrap();
+                match fish_wcstoi(arg) {
+                    Ok(ms) if ms > 0 => {
+                        opts.timeout = Some(Duration::from_millis(ms.unsigned_abs().into()));
+                    }
+                    _ => {
+                        err_fmt!("Invalid timeout '%s'", arg)
+                            .cmd(cmd)
+                            .finish(streams);
+                        return Err(STATUS_INVALID_ARGS);
+                    }
+                }
+            }
+            ON_TIMEOUT_OPTION_CHAR => opts.on_timeout.push(w.woptarg.unwrap().to_owned()),
+            DEFINE_FUNC_OPTION_CHAR => opts.define_function = true,
+            TRANSLATE_OPTION_CHAR => opts.translate = true,
+            TERM_OPTION_CHAR => opts.term = Some(w.woptarg.unwrap().to_owned()),
             COLOR_OPTION_CHAR => {
                 opts.color = ColorEnabled::parse_from_opt(streams, cmd, w.woptarg.unwrap())?;
             }
@@ -568,7 +894,37 @@ impl BuiltinBind {
             self.opts.user = true;
         }
 
+        if self.opts.term.is_some() && !self.opts.translate {
+            err_str!("--term requires --translate")
+                .cmd(cmd)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+
         match self.opts.mode {
+            BindMode::Erase if self.opts.translate => {
+                if self.erase_translations(optind, argv, streams) {
+                    return Err(STATUS_CMD_ERROR);
+                }
+            }
+            BindMode::Insert if self.opts.translate => {
+                if self.translate(optind, argv, parser, streams) {
+                    return Err(STATUS_CMD_ERROR);
+                }
+            }
+            BindMode::Erase if self.opts.define_function => {
+                if self.opts.all {
+                    self.bindings.clear_functions();
+                }
+                for name in &argv[optind..] {
+                    self.bindings.erase_function(name);
+                }
+            }
+            BindMode::Insert if self.opts.define_function => {
+                if self.define_function(optind, argv, parser, streams) {
+                    return Err(STATUS_CMD_ERROR);
+                }
+            }
             BindMode::Erase => {
                 // If we get both, we erase both.
                 if self.opts.user
@@ -1,10 +1,189 @@
 use super::prelude::*;
 use crate::builtins::Error;
+use crate::common::valid_var_name;
+use crate::debugger;
+use crate::parse_execution::varname_error;
 use crate::parser::{Block, BlockType};
 use crate::reader::reader_read;
+use crate::wutil::fish_wcstoul;
 use crate::{err_fmt, err_str};
 use libc::STDIN_FILENO;
 
+/// Parse a breakpoint location, `[FILE:]LINE`.
+fn parse_location(arg: &wstr) -> Option<(Option<&wstr>, u32)> {
+    let (file, line) = match arg.as_char_slice().iter().rposition(|&c| c == ':') {
+        Some(pos) => (Some(&arg[..pos]), &arg[pos + 1..]),
+        None => (None, arg),
+    };
+    let line = fish_wcstoul(line)
+        .ok()
+        .and_then(|n| u32::try_from(n).ok())?;
+    if line == 0 || file.is_some_and(|f| f.is_empty()) {
+        return None;
+    }
+    Some((file, line))
+}
+
+/// Add a breakpoint: `breakpoint add [--condition COMMAND] [FILE:]LINE`.
+fn add(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) -> BuiltinResult {
+    const SHORT_OPTIONS: &wstr = L!("c:");
+    const LONG_OPTIONS: &[WOption] = &[wopt(L!("condition"), RequiredArgument, 'c')];
+    let (cmd, subcmd) = (L!("breakpoint"), args[0]);
+
+    let mut condition = None;
+    let args_read = args.to_vec();
+    let mut w = WGetopter::new(SHORT_OPTIONS, LONG_OPTIONS, args);
+    while let Some(c) = w.next_opt() {
+        match c {
+            'c' => condition = Some(w.woptarg.unwrap()),
+            ':' => {
+                builtin_missing_argument(
+                    parser,
+                    streams,
+                    cmd,
+                    Some(subcmd),
+                    args_read[w.wopt_index - 1],
+                    true,
+                );
+                return Err(STATUS_INVALID_ARGS);
+            }
+            ';' => {
+                builtin_unexpected_argument(
+                    parser,
+                    streams,
+                    cmd,
+                    args_read[w.wopt_index - 1],
+                    true,
+                );
+                return Err(STATUS_INVALID_ARGS);
+            }
+            '?' => {
+                builtin_unknown_option(parser, streams, cmd, args_read[w.wopt_index - 1], true);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            _ => panic!("unexpected retval from WGetopter"),
+        }
+    }
+    let optind = w.wopt_index;
+
+    let args = &args_read[optind..];
+    if args.len() != 1 {
+        err_fmt!(Error::UNEXP_ARG_COUNT, 1, args.len())
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+    let Some((file, line)) = parse_location(args[0]) else {
+        err_fmt!("%s: invalid location, expected [FILE:]LINE", args[0])
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    };
+    let id = debugger::add_breakpoint(file, line, condition);
+    streams.out.appendln(&match file {
+        Some(file) => wgettext_fmt!("Breakpoint %d at %s:%d", id, file, line),
+        None => wgettext_fmt!("Breakpoint %d at line %d", id, line),
+    });
+    Ok(SUCCESS)
+}
+
+/// Run a debugger command, like `breakpoint step`.
+fn debugger_command(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    argv: &mut [&wstr],
+) -> BuiltinResult {
+    let cmd = argv[0];
+    let subcmd = argv[1];
+    if subcmd == "-h" || subcmd == "--help" {
+        builtin_print_help(parser, streams, cmd);
+        return Ok(SUCCESS);
+    }
+
+    if !debugger::is_active() {
+        err_str!("No script is being debugged, see `fish --debug-script`")
+            .cmd(cmd)
+            .finish(streams);
+        return Err(STATUS_CMD_ERROR);
+    }
+
+    match subcmd.to_string().as_str() {
+        "step" | "next" | "continue" => {
+            if argv.len() != 2 {
+                err_fmt!(Error::UNEXP_ARG_COUNT, 0, argv.len() - 2)
+                    .subcmd(cmd, subcmd)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            if !debugger::is_stopped() {
+                err_str!("Command only valid at the debugger prompt")
+                    .subcmd(cmd, subcmd)
+                    .finish(streams);
+                return Err(STATUS_ILLEGAL_CMD);
+            }
+            match subcmd.to_string().as_str() {
+                "step" => debugger::resume_step(),
+                "next" => debugger::resume_next(),
+                _ => debugger::resume_continue(),
+            }
+            // Leave the prompt.
+            parser.libdata_mut().exit_current_script = true;
+        }
+        "add" => return add(parser, streams, &mut argv[1..]),
+        "watch" => {
+            if argv.len() < 3 {
+                err_fmt!(Error::MIN_ARG_COUNT, 1, 0)
+                    .subcmd(cmd, subcmd)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            for &name in &argv[2..] {
+                if !valid_var_name(name) {
+                    varname_error(cmd, name).subcmd(cmd, subcmd).finish(streams);
+                    return Err(STATUS_INVALID_ARGS);
+                }
+            }
+            for &name in &argv[2..] {
+                let id = debugger::add_watchpoint(name, parser.vars());
+                streams
+                    .out
+                    .appendln(&wgettext_fmt!("Watchpoint %d: $%s", id, name));
+            }
+        }
+        "delete" => {
+            let mut ids = vec![];
+            for &arg in &argv[2..] {
+                let Ok(id) = fish_wcstoul(arg) else {
+                    err_fmt!(Error::NOT_NUMBER, arg)
+                        .subcmd(cmd, subcmd)
+                        .finish(streams);
+                    return Err(STATUS_INVALID_ARGS);
+                };
+                ids.push(id);
+            }
+            let mut result = Ok(SUCCESS);
+            for id in ids {
+                if !usize::try_from(id).is_ok_and(debugger::delete) {
+                    err_fmt!("No breakpoint or watchpoint %d", id)
+                        .subcmd(cmd, subcmd)
+                        .finish(streams);
+                    result = Err(STATUS_CMD_ERROR);
+                }
+            }
+            return result;
+        }
+        "list" => streams.out.append(&debugger::list()),
+        "where" => streams.out.append(&debugger::location(parser)),
+        _ => {
+            err_str!(Error::INVALID_SUBCMD)
+                .subcmd(cmd, subcmd)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+    }
+    Ok(SUCCESS)
+}
+
 /// Implementation of the builtin breakpoint command, used to launch the interactive debugger.
 pub fn breakpoint(
     parser: &mut Parser,
@@ -13,10 +192,13 @@ pub fn breakpoint(
 ) -> BuiltinResult {
     let cmd = argv[0];
     if argv.len() != 1 {
-        err_fmt!(Error::UNEXP_ARG_COUNT, 0, argv.len() - 1)
-            .cmd(cmd)
-            .finish(streams);
-        return Err(STATUS_INVALID_ARGS);
+        return debugger_command(parser, streams, argv);
+    }
+
+    // When debugging a script, stop in the debugger.
+    if debugger::is_active() && !parser.is_breakpoint() {
+        debugger::stop(parser, wgettext!("Breakpoint"));
+        return BuiltinResult::from_dynamic(parser.last_status());
     }
 
     // If we're not interactive then we can't enter the debugger. So treat this command as a no-op.
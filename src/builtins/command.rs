@@ -1,4 +1,5 @@
 use super::prelude::*;
+use super::r#type::resolution_json;
 use crate::path::{path_get_path, path_get_paths};
 
 #[derive(Default)]
@@ -6,8 +7,11 @@ struct Options {
     all: bool,
     quiet: bool,
     find_path: bool,
+    json: bool,
 }
 
+const JSON_SHORT: char = '\x01';
+
 pub fn r#command(
     parser: &mut Parser,
     streams: &mut IoStreams,
@@ -25,6 +29,7 @@ pub fn r#command(
         wopt(L!("query"), ArgType::NoArgument, 'q'),
         wopt(L!("quiet"), ArgType::NoArgument, 'q'),
         wopt(L!("search"), ArgType::NoArgument, 's'),
+        wopt(L!("json"), ArgType::NoArgument, JSON_SHORT),
     ];
 
     let mut w = WGetopter::new(shortopts, longopts, argv);
@@ -35,6 +40,7 @@ pub fn r#command(
             's' => opts.find_path = true,
             // -s and -v are aliases
             'v' => opts.find_path = true,
+            JSON_SHORT => opts.json = true,
             'h' => {
                 builtin_print_help(parser, streams, cmd);
                 return Ok(SUCCESS);
@@ -71,13 +77,26 @@ pub fn r#command(
     }
 
     // Quiet implies find_path.
-    if !opts.find_path && !opts.all && !opts.quiet {
+    if !opts.find_path && !opts.all && !opts.quiet && !opts.json {
         builtin_print_help(parser, streams, cmd);
         return Err(STATUS_INVALID_ARGS);
     }
 
     let mut res = false;
     let optind = w.wopt_index;
+    if opts.json {
+        for arg in argv.iter().take(argc).skip(optind) {
+            let (json, found) = resolution_json(parser, arg, false, false);
+            streams.out.appendln(&json);
+            res |= found;
+        }
+        return if res {
+            Ok(SUCCESS)
+        } else {
+            Err(STATUS_CMD_UNKNOWN)
+        };
+    }
+
     for arg in argv.iter().take(argc).skip(optind) {
         let paths = if opts.all {
             path_get_paths(arg, parser.vars())
@@ -6,9 +6,10 @@ use crate::{
     complete::Completion,
     err_fmt, err_str,
     expand::{ExpandFlags, ExpandResultCode, expand_string},
-    input::{CharEvent, ReadlineCmd, input_function_get_code},
+    input::{CharEvent, ReadlineCmd, bindings, input_function_get_code},
+    kill::{is_register, kill_select_register, register_get, register_set},
     operation_context::{OperationContext, no_cancel},
-    parse_constants::ParseTreeFlags,
+    parse_constants::{ParseTreeFlags, SourceRange},
     parse_util::{
         detect_parse_errors, get_job_extent, get_offset_from_line, get_process_extent,
         get_token_extent, lineno,
@@ -17,13 +18,14 @@ use crate::{
     proc::is_interactive_session,
     reader::{
         JumpDirection, JumpPrecision, commandline_get_state, commandline_set_buffer,
-        commandline_set_search_field, reader_execute_readline_cmd, reader_jump,
-        reader_showing_suggestion,
+        commandline_set_search_field, reader_execute_readline_cmd, reader_insert_snippet,
+        reader_jump, reader_showing_suggestion, snippet::parse_snippet,
     },
     tokenizer::{TOK_ACCEPT_UNFINISHED, TokenType, Tokenizer},
 };
 use fish_common::{UnescapeFlags, UnescapeStringStyle, unescape_string};
 use fish_wcstringutil::join_strings;
+use std::collections::HashMap;
 use std::ops::Range;
 
 /// Which part of the comandbuffer are we operating on.
@@ -35,6 +37,15 @@ enum TextScope {
     Token,
 }
 
+/// How to print the tokens of the command line.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum TokenFormat {
+    /// One line per token.
+    Lines(TokenOutputMode),
+    /// One JSON object per token, with what the parser knows about it.
+    Json,
+}
+
 /// For text insertion, how should it be done.
 #[derive(Eq, PartialEq)]
 enum AppendMode {
@@ -137,6 +148,125 @@ fn strip_dollar_prefixes(insert_mode: AppendMode, prefix: &wstr, insert: &wstr)
     Some(stripped)
 }
 
+/// Expand a token like `commandline --tokens-expanded` does, without command substitutions.
+fn expand_token(parser: &mut Parser, token_text: &wstr, args: &mut Vec<Completion>) {
+    const COMMANDLINE_TOKENS_MAX_EXPANSION: usize = 512;
+
+    match expand_string(
+        token_text.to_owned(),
+        args,
+        ExpandFlags::SKIP_CMDSUBST,
+        &mut OperationContext::foreground(
+            parser,
+            Box::new(no_cancel),
+            COMMANDLINE_TOKENS_MAX_EXPANSION,
+        ),
+        None,
+    )
+    .result
+    {
+        ExpandResultCode::Error
+        | ExpandResultCode::Overflow
+        | ExpandResultCode::WildcardNoMatch => {
+            // Maybe hit expansion limit, forward the unexpanded string.
+            args.push(Completion::from_completion(token_text.to_owned()));
+        }
+        ExpandResultCode::Cancel => {}
+        ExpandResultCode::Ok => (),
+    }
+}
+
+/// Return the role of each token the parser knows about, keyed by the token's start offset.
+fn classify_tokens(buffer: &wstr) -> HashMap<usize, &'static str> {
+    let flags = ParseTreeFlags {
+        continue_after_error: true,
+        accept_incomplete_tokens: true,
+        leave_unterminated: true,
+        ..Default::default()
+    };
+    let ast = ast::parse(buffer, flags, None);
+    let mut roles = HashMap::new();
+    let mut add = |range: Option<SourceRange>, role| {
+        if let Some(range) = range {
+            // Nodes are visited before their children, so the more specific role wins.
+            roles.entry(range.start()).or_insert(role);
+        }
+    };
+    for node in ast.walk() {
+        match node.kind() {
+            Kind::DecoratedStatement(ds) => add(ds.command.range(), "command"),
+            Kind::Redirection(redir) => {
+                add(redir.oper.range(), "redirection");
+                add(redir.target.range(), "redirection-target");
+            }
+            Kind::VariableAssignment(assignment) => add(assignment.range(), "variable-assignment"),
+            Kind::Keyword(keyword) => add(keyword.range(), "keyword"),
+            Kind::Argument(arg) => {
+                let is_option = arg.range().is_some_and(|range| {
+                    unescape_string(
+                        &buffer[range.as_usize()],
+                        UnescapeStringStyle::Script(UnescapeFlags::INCOMPLETE),
+                    )
+                    .is_some_and(|arg| arg.starts_with('-'))
+                });
+                add(arg.range(), if is_option { "option" } else { "argument" });
+            }
+            _ => (),
+        }
+    }
+    roles
+}
+
+/// Print one JSON object per token in the given range, with its text, expansion, offsets
+/// (relative to the start of the range) and role.
+fn write_tokens_json(
+    parser: &mut Parser,
+    range: Range<usize>,
+    cut_at_cursor: bool,
+    buffer: &wstr,
+    cursor_pos: usize,
+    streams: &mut IoStreams,
+) {
+    let roles = classify_tokens(buffer);
+    let mut tok = Tokenizer::new(buffer, TOK_ACCEPT_UNFINISHED);
+    while let Some(token) = tok.next() {
+        if token.offset() < range.start || token.end() > range.end {
+            continue;
+        }
+        if cut_at_cursor && token.end() >= cursor_pos {
+            break;
+        }
+        let token_text = tok.text_of(&token);
+        let role = match token.type_ {
+            TokenType::String => roles.get(&token.offset()).copied().unwrap_or("argument"),
+            TokenType::Redirect => "redirection",
+            TokenType::Pipe => "pipe",
+            TokenType::AndAnd | TokenType::OrOr => "conjunction",
+            TokenType::End => "end",
+            TokenType::Background => "background",
+            TokenType::LeftBrace | TokenType::RightBrace => "brace",
+            TokenType::Comment => "comment",
+            TokenType::Error => "error",
+        };
+        let mut expanded = vec![];
+        if token.type_ == TokenType::String {
+            expand_token(parser, token_text, &mut expanded);
+        }
+        let expanded: Vec<WString> = expanded
+            .iter()
+            .map(|arg| json_string(&arg.completion))
+            .collect();
+        streams.out.append(&sprintf!(
+            "{\"text\":%s,\"expanded\":[%s],\"start\":%d,\"end\":%d,\"type\":\"%s\"}\n",
+            json_string(token_text),
+            join_strings(&expanded, ','),
+            token.offset() - range.start,
+            token.end() - range.start,
+            role
+        ));
+    }
+}
+
 /// Output the specified selection.
 ///
 /// \param begin start of selection
@@ -151,7 +281,7 @@ fn write_part(
     range: Range<usize>,
     range_is_single_token: bool,
     cut_at_cursor: bool,
-    token_mode: Option<TokenOutputMode>,
+    token_mode: Option<TokenFormat>,
     buffer: &wstr,
     cursor_pos: usize,
     streams: &mut IoStreams,
@@ -168,46 +298,27 @@ fn write_part(
         return;
     };
 
+    let token_mode = match token_mode {
+        TokenFormat::Lines(token_mode) => token_mode,
+        TokenFormat::Json => {
+            write_tokens_json(parser, range, cut_at_cursor, buffer, cursor_pos, streams);
+            return;
+        }
+    };
+
     let mut args = vec![];
-    let mut add_token = |token_text: &wstr| {
-        match token_mode {
-            TokenOutputMode::Expanded => {
-                const COMMANDLINE_TOKENS_MAX_EXPANSION: usize = 512;
-
-                match expand_string(
-                    token_text.to_owned(),
-                    &mut args,
-                    ExpandFlags::SKIP_CMDSUBST,
-                    &mut OperationContext::foreground(
-                        parser,
-                        Box::new(no_cancel),
-                        COMMANDLINE_TOKENS_MAX_EXPANSION,
-                    ),
-                    None,
-                )
-                .result
-                {
-                    ExpandResultCode::Error
-                    | ExpandResultCode::Overflow
-                    | ExpandResultCode::WildcardNoMatch => {
-                        // Maybe hit expansion limit, forward the unexpanded string.
-                        args.push(Completion::from_completion(token_text.to_owned()));
-                    }
-                    ExpandResultCode::Cancel => {}
-                    ExpandResultCode::Ok => (),
-                }
-            }
-            TokenOutputMode::Raw => {
-                args.push(Completion::from_completion(token_text.to_owned()));
-            }
-            TokenOutputMode::Unescaped => {
-                let unescaped = unescape_string(
-                    token_text,
-                    UnescapeStringStyle::Script(UnescapeFlags::INCOMPLETE),
-                )
-                .unwrap();
-                args.push(Completion::from_completion(unescaped));
-            }
+    let mut add_token = |token_text: &wstr| match token_mode {
+        TokenOutputMode::Expanded => expand_token(parser, token_text, &mut args),
+        TokenOutputMode::Raw => {
+            args.push(Completion::from_completion(token_text.to_owned()));
+        }
+        TokenOutputMode::Unescaped => {
+            let unescaped = unescape_string(
+                token_text,
+                UnescapeStringStyle::Script(UnescapeFlags::INCOMPLETE),
+            )
+            .unwrap();
+            args.push(Completion::from_completion(unescaped));
         }
     };
 
@@ -275,6 +386,8 @@ pub fn commandline(
     let mut forward_jump_till = false;
     let mut backward_jump_till = false;
     let mut jump_target = None;
+    let mut register = None;
+    let mut snippet_mode = false;
 
     let short_options = L!("abijpctfxorhI:CBELSsP");
     let long_options: &[WOption] = &[
@@ -291,6 +404,7 @@ pub fn commandline(
         wopt(L!("function"), ArgType::NoArgument, 'f'),
         wopt(L!("tokens-expanded"), ArgType::NoArgument, 'x'),
         wopt(L!("tokens-raw"), ArgType::NoArgument, '\x02'),
+        wopt(L!("tokens-json"), ArgType::NoArgument, '\x0b'),
         wopt(L!("tokenize"), ArgType::NoArgument, 'o'),
         wopt(L!("help"), ArgType::NoArgument, 'h'),
         wopt(L!("input"), ArgType::RequiredArgument, 'I'),
@@ -309,6 +423,8 @@ pub fn commandline(
         wopt(L!("backward-jump"), ArgType::RequiredArgument, '\x08'),
         wopt(L!("forward-jump-till"), ArgType::RequiredArgument, '\x09'),
         wopt(L!("backward-jump-till"), ArgType::RequiredArgument, '\x0a'),
+        wopt(L!("register"), ArgType::RequiredArgument, '\x0c'),
+        wopt(L!("insert-snippet"), ArgType::NoArgument, '\x0d'),
     ];
 
     let mut w = WGetopter::new(short_options, long_options, args);
@@ -325,7 +441,7 @@ pub fn commandline(
             'j' => buffer_part = Some(TextScope::Job),
             'p' => buffer_part = Some(TextScope::Process),
             'f' => function_mode = true,
-            'x' | '\x02' | 'o' => {
+            'x' | '\x02' | 'o' | '\x0b' => {
                 if token_mode.is_some() {
                     err_fmt!(
                         Error::INVALID_OPT_COMBO_WITH_CTX,
@@ -337,9 +453,10 @@ pub fn commandline(
                     return Err(STATUS_INVALID_ARGS);
                 }
                 token_mode = Some(match c {
-                    'x' => TokenOutputMode::Expanded,
-                    '\x02' => TokenOutputMode::Raw,
-                    'o' => TokenOutputMode::Unescaped,
+                    'x' => TokenFormat::Lines(TokenOutputMode::Expanded),
+                    '\x02' => TokenFormat::Lines(TokenOutputMode::Raw),
+                    'o' => TokenFormat::Lines(TokenOutputMode::Unescaped),
+                    '\x0b' => TokenFormat::Json,
                     _ => unreachable!(),
                 });
             }
@@ -375,6 +492,20 @@ pub fn commandline(
                 backward_jump_till = true;
                 jump_target = Some(w.woptarg.unwrap().to_owned());
             }
+            '\x0c' => {
+                let arg = w.woptarg.unwrap();
+                let mut chars = arg.chars();
+                let name = chars.next().filter(|&c| is_register(c));
+                let (Some(name), None) = (name, chars.next()) else {
+                    err_fmt!("Invalid register '%s'", arg)
+                        .cmd(cmd)
+                        .full_trailer(parser)
+                        .finish(streams);
+                    return Err(STATUS_INVALID_ARGS);
+                };
+                register = Some(name);
+            }
+            '\x0d' => snippet_mode = true,
             'h' => {
                 builtin_print_help(parser, streams, cmd);
                 return Ok(SUCCESS);
@@ -446,7 +577,11 @@ pub fn commandline(
             return Err(STATUS_INVALID_ARGS);
         }
 
-        if positional_args == 0 {
+        // The register is used by the next input function that kills or yanks, even if that
+        // happens in a later call.
+        if let Some(register) = register {
+            kill_select_register(register);
+        } else if positional_args == 0 {
             builtin_missing_argument(parser, streams, cmd, None, L!("--function"), true);
             return Err(STATUS_INVALID_ARGS);
         }
@@ -454,6 +589,12 @@ pub fn commandline(
         type RL = ReadlineCmd;
         for arg in &w.argv[w.wopt_index..] {
             let Some(cmd) = input_function_get_code(arg) else {
+                // User-defined input functions are evaluated right away.
+                let script = bindings().get_function(arg).map(|f| f.script.clone());
+                if let Some(script) = script {
+                    parser.eval(&script, streams.io_chain);
+                    continue;
+                }
                 err_fmt!("Unknown input function '%s'", arg)
                     .cmd(cmd)
                     .full_trailer(parser)
@@ -475,6 +616,105 @@ pub fn commandline(
         return Ok(SUCCESS);
     }
 
+    if let Some(register) = register {
+        if buffer_part.is_some()
+            || cut_at_cursor
+            || token_mode.is_some()
+            || matches!(
+                append_mode,
+                Some(AppendMode::Insert | AppendMode::InsertSmart)
+            )
+            || selection_mode
+            || cursor_mode
+            || line_mode
+            || column_mode
+            || search_mode
+            || paging_mode
+            || paging_full_mode
+            || search_field_mode
+            || selection_start_mode
+            || selection_end_mode
+        {
+            err_str!(Error::INVALID_OPT_COMBO)
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        if positional_args == 0 {
+            let contents = register_get(register);
+            if contents.is_empty() {
+                return Err(STATUS_CMD_ERROR);
+            }
+            streams.out.appendln(&contents);
+            return Ok(SUCCESS);
+        }
+        let mut text = join_strings(&w.argv[w.wopt_index..], '\n');
+        if append_mode == Some(AppendMode::Append) {
+            text = register_get(register) + &text[..];
+        }
+        if !register_set(register.to_ascii_lowercase(), &text) {
+            err_fmt!("register '%s' is read-only", register.to_string())
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        return Ok(SUCCESS);
+    }
+
+    if snippet_mode {
+        if buffer_part.is_some()
+            || cut_at_cursor
+            || append_mode.is_some()
+            || token_mode.is_some()
+            || selection_mode
+            || cursor_mode
+            || line_mode
+            || column_mode
+            || search_mode
+            || paging_mode
+            || paging_full_mode
+            || search_field_mode
+            || selection_start_mode
+            || selection_end_mode
+            || override_buffer.is_some()
+        {
+            err_str!(Error::INVALID_OPT_COMBO)
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        if positional_args == 0 {
+            builtin_missing_argument(parser, streams, cmd, None, L!("--insert-snippet"), true);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        let text = join_strings(&w.argv[w.wopt_index..], '\n');
+        let snippet = match parse_snippet(&text) {
+            Ok(snippet) => snippet,
+            Err(offset) => {
+                err_fmt!("invalid placeholder at character %d of snippet", offset + 1)
+                    .cmd(cmd)
+                    .full_trailer(parser)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+        };
+        if !(parser.interactive_initialized || is_interactive_session()) {
+            err_str!("Can not set commandline in non-interactive mode")
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_CMD_ERROR);
+        }
+        return if reader_insert_snippet(parser, &snippet) {
+            Ok(SUCCESS)
+        } else {
+            Err(STATUS_CMD_ERROR)
+        };
+    }
+
     if selection_mode {
         if let Some(selection) = rstate.selection {
             streams.out.append(&rstate.text[selection]);
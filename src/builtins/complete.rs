@@ -1,10 +1,12 @@
+use super::fish_json::parse_json;
 use super::prelude::*;
 use crate::{
     builtins::Error,
     complete::{
-        CompleteFlags, CompleteOptionType, CompletionMode, CompletionRequestOptions, complete_add,
-        complete_add_wrapper, complete_print, complete_remove, complete_remove_all,
-        complete_remove_wrapper,
+        CompleteFlags, CompleteOptionType, CompletionCachePolicy, CompletionMode,
+        CompletionRequestOptions, complete_add, complete_add_wrapper, complete_clear_cache,
+        complete_load_json, complete_print, complete_remove, complete_remove_all,
+        complete_remove_wrapper, complete_set_cache_policy,
     },
     err_fmt, err_raw, err_str,
     highlight::highlight_and_colorize,
@@ -14,9 +16,10 @@ use crate::{
     proc::is_interactive_session,
     reader::{commandline_get_state, completion_apply_to_command_line},
 };
-use fish_common::{UnescapeFlags, UnescapeStringStyle, unescape_string};
+use fish_common::{ReadExt as _, UnescapeFlags, UnescapeStringStyle, unescape_string};
 use fish_wcstringutil::string_suffixes_string;
-use fish_widestring::bytes2wcstring;
+use fish_widestring::{bytes2wcstring, wcs2osstring};
+use std::time::Duration;
 
 // builtin_complete_* are a set of rather silly looping functions that make sure that all the proper
 // combinations of complete_add or complete_remove get called. This is needed since complete allows
@@ -239,6 +242,75 @@ fn builtin_complete_print(
 
 /// Values used for long-only options.
 const OPT_ESCAPE: char = '\x01';
+const OPT_LOAD_JSON: char = '\x02';
+const OPT_CACHE_TTL: char = '\x03';
+const OPT_CACHE_KEY: char = '\x04';
+const OPT_CLEAR_CACHE: char = '\x05';
+
+/// Add the completions described by the JSON document in the file at `path`, or on stdin if it is
+/// `-`.
+fn builtin_complete_load_json(
+    parser: &Parser,
+    streams: &mut IoStreams,
+    cmd: &wstr,
+    path: &wstr,
+    cmd_to_complete: Option<&wstr>,
+) -> BuiltinResult {
+    let contents = if path == "-" {
+        let stdin_file = if streams.stdin_is_directly_redirected {
+            streams.stdin_file.as_mut()
+        } else {
+            None
+        };
+        let Some(stdin_file) = stdin_file else {
+            err_str!("expected a JSON document on standard input")
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        };
+        let mut buf = vec![];
+        if let Err(err) = stdin_file.read_to_end_interruptible(&mut buf) {
+            return if err.kind() == std::io::ErrorKind::Interrupted {
+                Err(128 + libc::SIGINT)
+            } else {
+                Err(STATUS_CMD_ERROR)
+            };
+        }
+        buf
+    } else {
+        match std::fs::read(wcs2osstring(path)) {
+            Ok(contents) => contents,
+            Err(err) => {
+                err_fmt!("Could not read '%s': %s", path, err.to_string())
+                    .cmd(cmd)
+                    .finish(streams);
+                return Err(STATUS_CMD_ERROR);
+            }
+        }
+    };
+    let document = match parse_json(&bytes2wcstring(&contents)) {
+        Ok(document) => document,
+        Err(err) => {
+            err_fmt!(
+                "Invalid JSON in '%s' at offset %d: %s",
+                path,
+                err.offset,
+                err.describe()
+            )
+            .cmd(cmd)
+            .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+    };
+    if let Err(err) = complete_load_json(&document, cmd_to_complete) {
+        err_fmt!("Invalid completion description in '%s': %s", path, err)
+            .cmd(cmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+    Ok(SUCCESS)
+}
 
 /// The complete builtin. Used for specifying programmable tab-completions. Calls the functions in
 /// complete.rs for any heavy lifting.
@@ -266,6 +338,10 @@ pub fn complete(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]
     let mut preserve_order = false;
     let mut unescape_output = true;
     let mut color = ColorEnabled::default();
+    let mut load_json = None;
+    let mut cache_ttl = None;
+    let mut cache_key = None;
+    let mut clear_cache = false;
 
     let short_options: &wstr = L!("a:c:p:s:l:o:d:fFrxeuAn:C::w:hk");
     let long_options: &[WOption] = &[
@@ -289,6 +365,10 @@ pub fn complete(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]
         wopt(L!("help"), ArgType::NoArgument, 'h'),
         wopt(L!("keep-order"), ArgType::NoArgument, 'k'),
         wopt(L!("escape"), ArgType::NoArgument, OPT_ESCAPE),
+        wopt(L!("load-json"), ArgType::RequiredArgument, OPT_LOAD_JSON),
+        wopt(L!("cache-ttl"), ArgType::RequiredArgument, OPT_CACHE_TTL),
+        wopt(L!("cache-key"), ArgType::RequiredArgument, OPT_CACHE_KEY),
+        wopt(L!("clear-cache"), ArgType::NoArgument, OPT_CLEAR_CACHE),
         wopt(L!("color"), ArgType::RequiredArgument, COLOR_OPTION_CHAR),
     ];
 
@@ -382,6 +462,25 @@ pub fn complete(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]
             OPT_ESCAPE => {
                 unescape_output = false;
             }
+            OPT_LOAD_JSON => {
+                load_json = Some(w.woptarg.unwrap());
+            }
+            OPT_CACHE_TTL => {
+                let arg = w.woptarg.unwrap();
+                match fish_wcstoul(arg) {
+                    Ok(secs) => cache_ttl = Some(secs),
+                    Err(_) => {
+                        err_fmt!(Error::NOT_NUMBER, arg).cmd(cmd).finish(streams);
+                        return Err(STATUS_INVALID_ARGS);
+                    }
+                }
+            }
+            OPT_CACHE_KEY => {
+                cache_key = Some(w.woptarg.unwrap().to_owned());
+            }
+            OPT_CLEAR_CACHE => {
+                clear_cache = true;
+            }
             'h' => {
                 builtin_print_help(parser, streams, cmd);
                 return Ok(SUCCESS);
@@ -426,6 +525,109 @@ pub fn complete(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]
         return Err(STATUS_INVALID_ARGS);
     }
 
+    // Whether anything but the commands to complete was given, other than the caching options.
+    let has_rule_options = do_complete
+        || remove
+        || load_json.is_some()
+        || !short_opt.is_empty()
+        || !gnu_opt.is_empty()
+        || !old_opt.is_empty()
+        || !comp.is_empty()
+        || !desc.is_empty()
+        || !condition.is_empty()
+        || !wrap_targets.is_empty()
+        || result_mode != CompletionMode::default()
+        || preserve_order;
+
+    if clear_cache {
+        if w.wopt_index != argc || has_rule_options || cache_ttl.is_some() || cache_key.is_some() {
+            err_str!(Error::INVALID_OPT_COMBO)
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        if cmd_to_complete.is_empty() && path.is_empty() {
+            complete_clear_cache(None);
+        }
+        for name in cmd_to_complete.iter().chain(&path) {
+            complete_clear_cache(Some(name));
+        }
+        return Ok(SUCCESS);
+    }
+
+    if cache_key.is_some() && cache_ttl.is_none() {
+        err_str!("--cache-key requires --cache-ttl")
+            .cmd(cmd)
+            .full_trailer(parser)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
+    if let Some(secs) = cache_ttl {
+        if w.wopt_index != argc
+            || has_rule_options
+            || (cmd_to_complete.is_empty() && path.is_empty())
+        {
+            err_str!(Error::INVALID_OPT_COMBO)
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        let key = cache_key.unwrap_or_default();
+        if let Err(err_text) = detect_errors_in_argument_list(&key, cmd) {
+            let mut err = err_fmt!("%s: contains a syntax error", key);
+            err.append_assign_to_msg('\n');
+            err.append_assign_to_msg(&err_text);
+            err.cmd(cmd).finish(streams);
+            return Err(STATUS_CMD_ERROR);
+        }
+        let policy = (secs != 0).then(|| CompletionCachePolicy {
+            ttl: Duration::from_secs(secs),
+            key,
+        });
+        for name in cmd_to_complete {
+            complete_set_cache_policy(name, false, policy.clone());
+        }
+        for name in path {
+            complete_set_cache_policy(name, true, policy.clone());
+        }
+        return Ok(SUCCESS);
+    }
+
+    if let Some(json_path) = load_json {
+        // Only the command to complete may be given along with the document.
+        if w.wopt_index != argc
+            || cmd_to_complete.len() > 1
+            || do_complete
+            || remove
+            || !path.is_empty()
+            || !short_opt.is_empty()
+            || !gnu_opt.is_empty()
+            || !old_opt.is_empty()
+            || !comp.is_empty()
+            || !desc.is_empty()
+            || !condition.is_empty()
+            || !wrap_targets.is_empty()
+            || result_mode != CompletionMode::default()
+            || preserve_order
+        {
+            err_str!(Error::INVALID_OPT_COMBO)
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        return builtin_complete_load_json(
+            parser,
+            streams,
+            cmd,
+            json_path,
+            cmd_to_complete.first().map(|c| c.as_utfstr()),
+        );
+    }
+
     if w.wopt_index != argc {
         // Use one left-over arg as the do-complete argument
         // to enable `complete -C "git check"`.
@@ -1,27 +1,60 @@
 use super::prelude::*;
+use super::string::width_without_escapes;
+use fish_widestring::wcs2bytes;
 
 // How many bytes we read() at once.
 // Since this is just for counting, it can be massive.
 const COUNT_CHUNK_SIZE: usize = 512 * 256;
 
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Count arguments and newlines, like `wc -l`.
+    Newlines,
+    /// Count arguments and lines, including a final line without a newline.
+    Lines,
+    /// Count bytes, like `wc -c`. Each argument counts as a line.
+    Bytes,
+    /// The width of the widest line, like `wc -L`. Each argument counts as a line.
+    MaxWidth,
+}
+
 /// Implementation of the builtin count command, used to count the number of arguments sent to it.
 pub fn count(_parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) -> BuiltinResult {
-    // Always add the size of argv (minus 0, which is "count").
+    // count takes no real options, because anything could be in the list it counts.
+    // The modes are only recognized as the very first argument, spelled out in full.
+    let mode = match argv.get(1) {
+        Some(&arg) if arg == "--lines" => Mode::Lines,
+        Some(&arg) if arg == "--bytes" => Mode::Bytes,
+        Some(&arg) if arg == "--max-width" => Mode::MaxWidth,
+        _ => Mode::Newlines,
+    };
+    let args = &argv[if mode == Mode::Newlines { 1 } else { 2 }..];
+
+    // Always add the arguments.
     // That means if you call `something | count a b c`, you'll get the count of something _plus 3_.
-    let mut numargs = argv.len() - 1;
+    let mut result = 0;
+    let mut add_line = |line: &wstr, want_newline: bool| match mode {
+        // Count the newlines coming in via stdin like `wc -l`.
+        // This means excluding lines that don't end in a newline!
+        Mode::Newlines => result += usize::from(want_newline),
+        Mode::Lines => result += 1,
+        Mode::Bytes => result += wcs2bytes(line).len() + usize::from(want_newline),
+        Mode::MaxWidth => result = result.max(width_without_escapes(line, 0)),
+    };
+
+    for arg in args {
+        add_line(arg, true);
+    }
 
     // (silly variable for Arguments to do nothing with)
     let mut zero = 0;
+    for input_value in Arguments::new(&[] as _, &mut zero, streams, COUNT_CHUNK_SIZE) {
+        add_line(&input_value.arg, input_value.want_newline);
+    }
 
-    // Count the newlines coming in via stdin like `wc -l`.
-    // This means excluding lines that don't end in a newline!
-    numargs += Arguments::new(&[] as _, &mut zero, streams, COUNT_CHUNK_SIZE)
-        .filter(|input_value| input_value.want_newline)
-        .count();
-
-    streams.out.appendln(&numargs.to_wstring());
+    streams.out.appendln(&result.to_wstring());
 
-    if numargs == 0 {
+    if result == 0 {
         return Err(STATUS_CMD_ERROR);
     }
     Ok(SUCCESS)
@@ -3,7 +3,10 @@
 use super::prelude::*;
 use crate::{
     ast::{self, AsNode as _, Ast, Kind, Leaf as _, Node, NodeVisitor, SourceRangeList, Traversal},
-    builtins::Error,
+    builtins::{
+        Error,
+        string::diff::{Edit, edit_script, lines},
+    },
     common::{PROGRAM_NAME, get_program_name},
     env::{EnvStack, Environment as _, env_init},
     err_fmt, err_str,
@@ -13,11 +16,13 @@ use crate::{
     operation_context::OperationContext,
     panic::panic_handler,
     parse_constants::{ParseTokenType, ParseTreeFlags, SourceRange},
-    parse_util::{SPACES_PER_INDENT, apply_indents, compute_indents},
+    parse_util::{SPACES_PER_INDENT, apply_indents, compute_indents, remove_indents},
     prelude::*,
     print_help::print_help,
     threads,
-    tokenizer::{TOK_SHOW_BLANK_LINES, TOK_SHOW_COMMENTS, TokenType, Tokenizer},
+    tokenizer::{
+        TOK_SHOW_BLANK_LINES, TOK_SHOW_COMMENTS, TokenType, Tokenizer, heredoc_delimiter_line_end,
+    },
     topic_monitor::topic_monitor_init,
     wutil::fish_iswalnum,
 };
@@ -31,6 +36,7 @@ use std::{
     fmt::Write as _,
     fs,
     io::{Read, Write as _},
+    ops::Range,
     os::unix::ffi::OsStrExt as _,
 };
 
@@ -77,6 +83,10 @@ struct PrettyPrinterState<'source, 'ast> {
     // This is computed ahead of time for convenience.
     preferred_semi_locations: Vec<usize>,
 
+    // The sorted ranges of here-document bodies, including the line with the delimiter. These are
+    // in the gap text, and emitted unchanged.
+    heredocs: Vec<SourceRange>,
+
     errors: Option<&'ast SourceRangeList>,
 }
 
@@ -173,6 +183,7 @@ impl<'source, 'ast> PrettyPrinter<'source, 'ast> {
                 gaps: vec![],
                 multi_line_brace_statement_locations: vec![],
                 preferred_semi_locations: vec![],
+                heredocs: vec![],
                 errors: None,
             },
         };
@@ -180,6 +191,7 @@ impl<'source, 'ast> PrettyPrinter<'source, 'ast> {
         zelf.state.multi_line_brace_statement_locations =
             zelf.compute_multi_line_brace_statement_locations();
         zelf.state.preferred_semi_locations = zelf.compute_preferred_semi_locations();
+        zelf.state.heredocs = zelf.compute_heredocs();
         zelf
     }
 
@@ -241,6 +253,24 @@ impl<'source, 'ast> PrettyPrinter<'source, 'ast> {
         gaps
     }
 
+    // Return the ranges of here-documents, from the start of the body to after the delimiter line.
+    fn compute_heredocs(&self) -> Vec<SourceRange> {
+        let source = self.state.source;
+        let mut result = vec![];
+        for node in Traversal::new(self.ast.top()) {
+            let Kind::Redirection(redir) = node.kind() else {
+                continue;
+            };
+            let Some(body) = redir.heredoc_body else {
+                continue;
+            };
+            let end = (heredoc_delimiter_line_end(source, body.as_usize()) + 1).min(source.len());
+            result.push(SourceRange::new(body.start(), end - body.start()));
+        }
+        result.sort_unstable_by_key(|range| range.start());
+        result
+    }
+
     // Return sorted list of semi-preferring semi_nl nodes.
     fn compute_preferred_semi_locations(&self) -> Vec<usize> {
         let mut result = vec![];
@@ -488,6 +518,26 @@ impl<'source, 'ast> PrettyPrinterState<'source, 'ast> {
     //
     //  We do not handle errors here - instead our caller does.
     fn emit_gap_text(&mut self, range: SourceRange, flags: GapFlags) -> bool {
+        // Here-documents are emitted as they are, on their own lines.
+        let heredoc = self
+            .heredocs
+            .iter()
+            .find(|heredoc| heredoc.start() >= range.start() && heredoc.end() <= range.end());
+        if let Some(&heredoc) = heredoc {
+            let before = SourceRange::new(range.start(), heredoc.start() - range.start());
+            self.emit_gap_text(before, flags);
+            if !self.at_line_start() {
+                self.emit_newline();
+            }
+            self.output
+                .push_utfstr(&self.source[heredoc.start()..heredoc.end()]);
+            if !self.at_line_start() {
+                self.emit_newline();
+            }
+            let after = SourceRange::new(heredoc.end(), range.end() - heredoc.end());
+            return self.emit_gap_text(after, flags);
+        }
+
         let gap_text = &self.source[range.start()..range.end()];
         // Common case: if we are only spaces, do nothing.
         if !gap_text.chars().any(|c| c != ' ') {
@@ -805,6 +855,13 @@ impl<'source, 'ast> PrettyPrinterState<'source, 'ast> {
         let Some(trange) = node.target.range() else {
             return;
         };
+        if node.heredoc_body.is_some() {
+            // Whether the delimiter is quoted matters, so keep it.
+            self.emit_gap_text_before(trange, GapFlags::default());
+            self.output
+                .push_utfstr(&self.source[trange.start()..trange.end()]);
+            return;
+        }
         self.emit_text(
             trange,
             GapFlags {
@@ -980,6 +1037,7 @@ fn do_indent(
     let mut do_indent = true;
     let mut only_indent = false;
     let mut only_unindent = false;
+    let mut line_range = None;
 
     let short_opts: &wstr = L!("+hvwic");
     let long_opts: &[WOption] = &[
@@ -994,6 +1052,7 @@ fn do_indent(
         wopt(L!("ansi"), ArgType::NoArgument, '\x02'),
         wopt(L!("pygments"), ArgType::NoArgument, '\x03'),
         wopt(L!("check"), ArgType::NoArgument, 'c'),
+        wopt(L!("range"), ArgType::RequiredArgument, '\x06'),
     ];
 
     let mut shim_args: Vec<&wstr> = args.iter().map(|s| s.as_ref()).collect();
@@ -1026,6 +1085,22 @@ fn do_indent(
             '\x02' => output_type = OutputType::Ansi,
             '\x03' => output_type = OutputType::PygmentsCsv,
             'c' => output_type = OutputType::Check,
+            '\x06' => {
+                let arg = w.woptarg.unwrap();
+                let Some(range) = parse_line_range(arg) else {
+                    err_fmt!("Invalid line range '%s'", arg)
+                        .cmd(L!("fish_indent"))
+                        .finish(streams);
+                    return Err(STATUS_CMD_ERROR);
+                };
+                line_range = Some(range);
+            }
+            ':' => {
+                err_fmt!(Error::MISSING_OPT_ARG, w.argv[w.wopt_index - 1])
+                    .cmd(L!("fish_indent"))
+                    .finish(streams);
+                return Err(STATUS_CMD_ERROR);
+            }
             ';' => {
                 err_fmt!(Error::UNEXP_OPT_ARG, w.argv[w.wopt_index - 1])
                     .cmd(L!("fish_indent"))
@@ -1102,40 +1177,15 @@ fn do_indent(
             if only_indent {
                 apply_indents(&src, &indents)
             } else {
-                // Only unindent.
-                let mut indented_everywhere = true;
-                for (i, c) in src.chars().enumerate() {
-                    if c != '\n' || i + 1 == src.len() {
-                        continue;
-                    }
-                    let num_spaces = SPACES_PER_INDENT * usize::try_from(indents[i + 1]).unwrap();
-                    if src.len() < i + 1 + num_spaces
-                        || !src[i + 1..].chars().take(num_spaces).all(|c| c == ' ')
-                    {
-                        indented_everywhere = false;
-                        break;
-                    }
-                }
-                if indented_everywhere {
-                    let mut out = WString::new();
-                    let mut i = 0;
-                    while i < src.len() {
-                        let c = src.as_char_slice()[i];
-                        out.push(c);
-                        i += 1;
-                        if c != '\n' || i == src.len() {
-                            continue;
-                        }
-                        i += SPACES_PER_INDENT * usize::try_from(indents[i]).unwrap();
-                    }
-                    out
-                } else {
-                    src.clone()
-                }
+                remove_indents(&src, &indents)
             }
         } else {
             prettify(streams, &src, do_indent)
         };
+        let output_wtext = match &line_range {
+            Some(range) => restrict_to_lines(&src, &output_wtext, range.clone()),
+            None => output_wtext,
+        };
 
         // Maybe colorize.
         let mut colors = vec![];
@@ -1281,11 +1331,107 @@ fn prettify(streams: &mut IoStreams, src: &wstr, do_indent: bool) -> WString {
         metrics.visit(ast.top());
         streams.err.appendln(&format!("{}", metrics));
     }
+    format_impl(src, do_indent)
+}
+
+fn format_impl(src: &wstr, do_indent: bool) -> WString {
     let ast = ast::parse(src, parse_flags(), None);
     let mut printer = PrettyPrinter::new(src, &ast, do_indent);
     printer.prettify()
 }
 
+/// Format fish script the way `fish_indent` does by default.
+pub fn format_script(src: &wstr) -> WString {
+    format_impl(src, true)
+}
+
+/// Format only the given lines of fish script, counted from 0, leaving the others as they are.
+/// This is what editors want after the user changed a few lines.
+pub fn format_script_lines(src: &wstr, lines: Range<usize>) -> WString {
+    restrict_to_lines(src, &format_script(src), lines)
+}
+
+/// Parse the argument to `--range`, like `3:7`, `3:` or `:7`. The line numbers start at 1 and
+/// the end is included, so this returns the lines counted from 0 as a half-open range.
+fn parse_line_range(arg: &wstr) -> Option<Range<usize>> {
+    let colon = arg.find_char(':')?;
+    let parse_bound = |s: &wstr, default: usize| -> Option<usize> {
+        if s.is_empty() {
+            return Some(default);
+        }
+        fish_wcstoul(s).ok().and_then(|n| usize::try_from(n).ok())
+    };
+    let start = parse_bound(&arg[..colon], 1)?;
+    let end = parse_bound(&arg[colon + 1..], usize::MAX)?;
+    if start == 0 || end < start {
+        return None;
+    }
+    Some(start - 1..end)
+}
+
+/// Given some source and its formatted version, return the source with only the changes that
+/// touch the given lines applied.
+/// Changed lines are matched up one by one where possible, so a block that was reindented as a
+/// whole is only reindented within the range. Lines that were joined or split are taken as a
+/// whole if any of them is in the range.
+fn restrict_to_lines(src: &wstr, formatted: &wstr, range: Range<usize>) -> WString {
+    let old = lines(src);
+    let new = lines(formatted);
+    let script = edit_script(&old, &new);
+
+    let mut result: Vec<&wstr> = vec![];
+    let (mut x, mut y) = (0, 0);
+    let mut i = 0;
+    while i < script.len() {
+        if script[i] == Edit::Keep {
+            result.push(old[x]);
+            x += 1;
+            y += 1;
+            i += 1;
+            continue;
+        }
+        // Collect a hunk of changes.
+        let (deleted, inserted) = {
+            let hunk_len = script[i..]
+                .iter()
+                .take_while(|&&edit| edit != Edit::Keep)
+                .count();
+            let hunk = &script[i..i + hunk_len];
+            i += hunk_len;
+            let deleted = hunk.iter().filter(|&&edit| edit == Edit::Delete).count();
+            (x..x + deleted, y..y + hunk_len - deleted)
+        };
+        let in_range = |line: usize| range.contains(&line);
+        if deleted.len() == inserted.len() {
+            for (old_line, new_line) in deleted.clone().zip(inserted.clone()) {
+                result.push(if in_range(old_line) {
+                    new[new_line]
+                } else {
+                    old[old_line]
+                });
+            }
+        } else if deleted.clone().any(in_range)
+            || (deleted.is_empty() && (range.start..=range.end).contains(&deleted.start))
+        {
+            result.extend(&new[inserted.clone()]);
+        } else {
+            result.extend(&old[deleted.clone()]);
+        }
+        x = deleted.end;
+        y = inserted.end;
+    }
+
+    let mut output = WString::new();
+    for line in result {
+        output.push_utfstr(line);
+        output.push('\n');
+    }
+    if !src.is_empty() && !src.ends_with('\n') && !output.is_empty() {
+        output.pop();
+    }
+    output
+}
+
 /// Given a string and list of colors of the same size, return the string with HTML span elements
 /// for the various colors.
 fn html_class_name_for_color(spec: HighlightSpec) -> &'static wstr {
@@ -0,0 +1,817 @@
+//! Implementation of the fish_json builtin, which reads values out of JSON documents and encodes
+//! lists as JSON.
+
+use super::prelude::*;
+use crate::builtins::Error;
+use crate::common::valid_var_name;
+use crate::parse_execution::varname_error;
+use crate::parser::ParserEnvSetMode;
+use crate::{err_fmt, err_str};
+use fish_common::{EscapeFlags, EscapeStringStyle, ReadExt as _, escape_string};
+use fish_widestring::bytes2wcstring;
+
+/// How deeply arrays and objects may be nested, to protect our stack.
+const MAX_DEPTH: usize = 512;
+
+/// A parsed JSON value. Object members keep their order, and numbers keep their original spelling.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool(bool),
+    Number(WString),
+    String(WString),
+    Array(Vec<Value>),
+    Object(Vec<(WString, Value)>),
+}
+
+impl Value {
+    /// Append the compact JSON encoding of this value.
+    fn encode(&self, out: &mut WString) {
+        match self {
+            Value::Null => out.push_str("null"),
+            Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+            Value::Number(n) => out.push_utfstr(n),
+            Value::String(s) => out.push_utfstr(&json_string(s)),
+            Value::Array(items) => {
+                out.push('[');
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    item.encode(out);
+                }
+                out.push(']');
+            }
+            Value::Object(members) => {
+                out.push('{');
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    out.push_utfstr(&json_string(key));
+                    out.push(':');
+                    value.encode(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    pub(crate) fn to_json(&self) -> WString {
+        let mut out = WString::new();
+        self.encode(&mut out);
+        out
+    }
+
+    /// How the value is shown to fish: strings without quotes, anything else as JSON.
+    fn to_text(&self) -> WString {
+        match self {
+            Value::String(s) => s.clone(),
+            _ => self.to_json(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseErrorKind {
+    UnexpectedEnd,
+    UnexpectedChar(char),
+    InvalidNumber,
+    InvalidEscape,
+    TooDeep,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ParseError {
+    kind: ParseErrorKind,
+    /// Offset of the error in characters.
+    pub(crate) offset: usize,
+}
+
+impl ParseError {
+    pub(crate) fn describe(&self) -> WString {
+        match self.kind {
+            ParseErrorKind::UnexpectedEnd => wgettext!("unexpected end of input").to_owned(),
+            ParseErrorKind::UnexpectedChar(c) => {
+                let mut shown = WString::from_chars([c]);
+                // Show control characters like newlines in a readable way.
+                if c.is_control() {
+                    shown =
+                        escape_string(&shown, EscapeStringStyle::Script(EscapeFlags::NO_QUOTED));
+                }
+                wgettext_fmt!("unexpected character '%s'", shown)
+            }
+            ParseErrorKind::InvalidNumber => wgettext!("invalid number").to_owned(),
+            ParseErrorKind::InvalidEscape => wgettext!("invalid escape sequence").to_owned(),
+            ParseErrorKind::TooDeep => wgettext!("nested too deeply").to_owned(),
+        }
+    }
+}
+
+/// A recursive descent parser for JSON as described in RFC 8259.
+struct JsonParser<'a> {
+    chars: &'a [char],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'a> JsonParser<'a> {
+    fn new(chars: &'a [char]) -> Self {
+        JsonParser {
+            chars,
+            pos: 0,
+            depth: 0,
+        }
+    }
+
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            offset: self.pos,
+        }
+    }
+
+    /// An error for the character at the current position.
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(c) => self.error(ParseErrorKind::UnexpectedChar(c)),
+            None => self.error(ParseErrorKind::UnexpectedEnd),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t' | '\n' | '\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if self.peek() != Some(c) {
+            return Err(self.unexpected());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), ParseError> {
+        for c in word.chars() {
+            self.expect(c)?;
+        }
+        Ok(())
+    }
+
+    /// Parse a complete document, which must consist of exactly one value.
+    fn parse_document(mut self) -> Result<Value, ParseError> {
+        let value = self.parse_value()?;
+        self.skip_whitespace();
+        if self.peek().is_some() {
+            return Err(self.unexpected());
+        }
+        Ok(value)
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('n') => self.expect_word("null").map(|()| Value::Null),
+            Some('t') => self.expect_word("true").map(|()| Value::Bool(true)),
+            Some('f') => self.expect_word("false").map(|()| Value::Bool(false)),
+            Some('"') => self.parse_string().map(Value::String),
+            Some('-' | '0'..='9') => self.parse_number().map(Value::Number),
+            Some('[') => self.nested(Self::parse_array),
+            Some('{') => self.nested(Self::parse_object),
+            _ => Err(self.unexpected()),
+        }
+    }
+
+    /// Parse an array or object, keeping track of the nesting depth.
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Value, ParseError>,
+    ) -> Result<Value, ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(ParseErrorKind::TooDeep));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_array(&mut self) -> Result<Value, ParseError> {
+        self.expect('[')?;
+        let mut items = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some(']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<Value, ParseError> {
+        self.expect('{')?;
+        let mut members = vec![];
+        self.skip_whitespace();
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            self.expect(':')?;
+            let value = self.parse_value()?;
+            members.push((key, value));
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                Some('}') => {
+                    self.pos += 1;
+                    return Ok(Value::Object(members));
+                }
+                _ => return Err(self.unexpected()),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<WString, ParseError> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let digits_start = parser.pos;
+            while matches!(parser.peek(), Some('0'..='9')) {
+                parser.pos += 1;
+            }
+            if parser.pos == digits_start {
+                Err(parser.error(ParseErrorKind::InvalidNumber))
+            } else {
+                Ok(())
+            }
+        };
+        if self.peek() == Some('-') {
+            self.pos += 1;
+        }
+        // No leading zeros.
+        if self.peek() == Some('0') {
+            self.pos += 1;
+        } else {
+            digits(self)?;
+        }
+        if self.peek() == Some('.') {
+            self.pos += 1;
+            digits(self)?;
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.pos += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.pos += 1;
+            }
+            digits(self)?;
+        }
+        Ok(WString::from_chars(&self.chars[start..self.pos]))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, ParseError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) else {
+                return Err(self.error(ParseErrorKind::InvalidEscape));
+            };
+            value = value * 16 + digit;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+
+    fn parse_string(&mut self) -> Result<WString, ParseError> {
+        self.expect('"')?;
+        let mut result = WString::new();
+        loop {
+            let Some(c) = self.peek() else {
+                return Err(self.error(ParseErrorKind::UnexpectedEnd));
+            };
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    return Ok(result);
+                }
+                '\\' => {
+                    self.pos += 1;
+                    let escaped = match self.peek() {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\x08',
+                        Some('f') => '\x0C',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            self.pos += 1;
+                            result.push(self.parse_unicode_escape()?);
+                            continue;
+                        }
+                        _ => return Err(self.error(ParseErrorKind::InvalidEscape)),
+                    };
+                    self.pos += 1;
+                    result.push(escaped);
+                }
+                c if u32::from(c) < 0x20 => return Err(self.unexpected()),
+                c => {
+                    self.pos += 1;
+                    result.push(c);
+                }
+            }
+        }
+    }
+
+    /// Parse the digits of a `\u` escape, including a following low surrogate if needed.
+    fn parse_unicode_escape(&mut self) -> Result<char, ParseError> {
+        let high = self.parse_hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if self.chars.get(self.pos..self.pos + 2) != Some(&['\\', 'u']) {
+                return Err(self.error(ParseErrorKind::InvalidEscape));
+            }
+            self.pos += 2;
+            let low = self.parse_hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error(ParseErrorKind::InvalidEscape));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error(ParseErrorKind::InvalidEscape))
+    }
+}
+
+pub(crate) fn parse_json(s: &wstr) -> Result<Value, ParseError> {
+    JsonParser::new(s.as_char_slice()).parse_document()
+}
+
+/// One step of a query like `.items[3].name`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum QueryStep {
+    Key(WString),
+    /// An array index, where negative indices count from the end.
+    Index(isize),
+}
+
+/// Parse a query made of `.key`, `["key"]` and `[index]` steps. A lone `.` or an empty query
+/// refers to the whole document.
+fn parse_query(query: &wstr) -> Option<Vec<QueryStep>> {
+    let chars = query.as_char_slice();
+    let mut steps = vec![];
+    if chars == ['.'] {
+        return Some(steps);
+    }
+    let mut pos = 0;
+    while pos < chars.len() {
+        match chars[pos] {
+            '.' => {
+                pos += 1;
+                let start = pos;
+                while pos < chars.len() && !matches!(chars[pos], '.' | '[') {
+                    pos += 1;
+                }
+                if pos == start {
+                    return None;
+                }
+                steps.push(QueryStep::Key(WString::from_chars(&chars[start..pos])));
+            }
+            '[' => {
+                pos += 1;
+                if chars.get(pos) == Some(&'"') {
+                    // A quoted key, using JSON string syntax.
+                    let mut parser = JsonParser::new(&chars[pos..]);
+                    let key = parser.parse_string().ok()?;
+                    pos += parser.pos;
+                    steps.push(QueryStep::Key(key));
+                } else {
+                    let start = pos;
+                    while pos < chars.len() && chars[pos] != ']' {
+                        pos += 1;
+                    }
+                    let index = fish_wcstol(wstr::from_char_slice(&chars[start..pos])).ok()?;
+                    steps.push(QueryStep::Index(isize::try_from(index).ok()?));
+                }
+                if chars.get(pos) != Some(&']') {
+                    return None;
+                }
+                pos += 1;
+            }
+            _ => return None,
+        }
+    }
+    Some(steps)
+}
+
+/// Look up the value a query refers to.
+fn query_value<'a>(mut value: &'a Value, steps: &[QueryStep]) -> Option<&'a Value> {
+    for step in steps {
+        value = match (value, step) {
+            (Value::Object(members), QueryStep::Key(key)) => {
+                // Like most JSON implementations, the last duplicate key wins.
+                &members.iter().rev().find(|(k, _)| k == key)?.1
+            }
+            (Value::Array(items), QueryStep::Index(index)) => {
+                let index = if *index < 0 {
+                    items.len().checked_sub(index.unsigned_abs())?
+                } else {
+                    index.unsigned_abs()
+                };
+                items.get(index)?
+            }
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+/// Read and parse the JSON document on stdin.
+fn read_document(
+    parser: &Parser,
+    streams: &mut IoStreams,
+    cmd: &wstr,
+    subcmd: &wstr,
+) -> Result<Value, ErrorCode> {
+    let stdin_file = if streams.stdin_is_directly_redirected {
+        streams.stdin_file.as_mut()
+    } else {
+        None
+    };
+    let Some(stdin_file) = stdin_file else {
+        err_str!("expected a JSON document on standard input")
+            .subcmd(cmd, subcmd)
+            .full_trailer(parser)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    };
+    let mut buf = vec![];
+    if let Err(err) = stdin_file.read_to_end_interruptible(&mut buf) {
+        return if err.kind() == std::io::ErrorKind::Interrupted {
+            Err(128 + libc::SIGINT)
+        } else {
+            Err(STATUS_CMD_ERROR)
+        };
+    }
+    parse_json(&bytes2wcstring(&buf)).map_err(|err| {
+        err_fmt!("Invalid JSON at offset %d: %s", err.offset, err.describe())
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        STATUS_INVALID_ARGS
+    })
+}
+
+#[derive(Default)]
+struct Options<'args> {
+    quiet: bool,
+    set_var: Option<&'args wstr>,
+    object: bool,
+    raw: bool,
+}
+
+/// Parse the options of a subcommand. Only the options in `shortopts` are accepted.
+fn parse_opts<'args>(
+    parser: &Parser,
+    streams: &mut IoStreams,
+    args: &mut [&'args wstr],
+    shortopts: &wstr,
+) -> Result<(Options<'args>, usize), ErrorCode> {
+    const LONG_OPTIONS: &[WOption] = &[
+        wopt(L!("quiet"), NoArgument, 'q'),
+        wopt(L!("set"), RequiredArgument, 's'),
+        wopt(L!("object"), NoArgument, 'o'),
+        wopt(L!("raw"), NoArgument, 'r'),
+    ];
+    let cmd = L!("fish_json");
+    let subcmd = args[0];
+    let mut opts = Options::default();
+    let args_read = args.to_vec();
+    let mut w = WGetopter::new(shortopts, LONG_OPTIONS, args);
+    while let Some(c) = w.next_opt() {
+        match c {
+            ':' => {
+                builtin_missing_argument(
+                    parser,
+                    streams,
+                    cmd,
+                    Some(subcmd),
+                    args_read[w.wopt_index - 1],
+                    false,
+                );
+                return Err(STATUS_INVALID_ARGS);
+            }
+            ';' => {
+                builtin_unexpected_argument(
+                    parser,
+                    streams,
+                    cmd,
+                    args_read[w.wopt_index - 1],
+                    false,
+                );
+                return Err(STATUS_INVALID_ARGS);
+            }
+            c if !shortopts.contains(c) => {
+                err_fmt!(Error::UNKNOWN_OPT, args_read[w.wopt_index - 1])
+                    .subcmd(cmd, subcmd)
+                    .full_trailer(parser)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            'q' => opts.quiet = true,
+            's' => {
+                let name = w.woptarg.unwrap();
+                if !valid_var_name(name) {
+                    varname_error(cmd, name)
+                        .subcmd(cmd, subcmd)
+                        .full_trailer(parser)
+                        .finish(streams);
+                    return Err(STATUS_INVALID_ARGS);
+                }
+                opts.set_var = Some(name);
+            }
+            'o' => opts.object = true,
+            'r' => opts.raw = true,
+            _ => panic!("unexpected retval from WGetopter"),
+        }
+    }
+    Ok((opts, w.wopt_index))
+}
+
+/// Parse the optional query argument of `get` and `keys`.
+fn parse_query_arg(
+    parser: &Parser,
+    streams: &mut IoStreams,
+    args: &[&wstr],
+    optind: usize,
+) -> Result<Vec<QueryStep>, ErrorCode> {
+    let (cmd, subcmd) = (L!("fish_json"), args[0]);
+    match &args[optind..] {
+        [] => Ok(vec![]),
+        [query] => parse_query(query).ok_or_else(|| {
+            err_fmt!("Invalid query '%s'", query)
+                .subcmd(cmd, subcmd)
+                .full_trailer(parser)
+                .finish(streams);
+            STATUS_INVALID_ARGS
+        }),
+        _ => {
+            err_str!(Error::TOO_MANY_ARGUMENTS)
+                .subcmd(cmd, subcmd)
+                .full_trailer(parser)
+                .finish(streams);
+            Err(STATUS_INVALID_ARGS)
+        }
+    }
+}
+
+fn fish_json_get(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let (opts, optind) = parse_opts(parser, streams, args, L!("qs:"))?;
+    let steps = parse_query_arg(parser, streams, args, optind)?;
+    let document = read_document(parser, streams, L!("fish_json"), args[0])?;
+
+    let found = query_value(&document, &steps);
+    // Arrays are turned into lists, everything else into a single element.
+    let values: Vec<WString> = match found {
+        None => vec![],
+        Some(Value::Array(items)) => items.iter().map(Value::to_text).collect(),
+        Some(value) => vec![value.to_text()],
+    };
+    if let Some(name) = opts.set_var {
+        // Set the variable even if nothing was found, so no stale value is left over.
+        parser.set_var(name, ParserEnvSetMode::default(), values);
+    } else if !opts.quiet {
+        for value in &values {
+            streams.out.appendln(value);
+        }
+    }
+
+    if found.is_some() {
+        Ok(SUCCESS)
+    } else {
+        Err(STATUS_CMD_ERROR)
+    }
+}
+
+fn fish_json_keys(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let (_opts, optind) = parse_opts(parser, streams, args, L!(""))?;
+    let steps = parse_query_arg(parser, streams, args, optind)?;
+    let document = read_document(parser, streams, L!("fish_json"), args[0])?;
+
+    match query_value(&document, &steps) {
+        Some(Value::Object(members)) => {
+            for (key, _) in members {
+                streams.out.appendln(key);
+            }
+        }
+        Some(Value::Array(items)) => {
+            for i in 0..items.len() {
+                streams.out.appendln(&i.to_wstring());
+            }
+        }
+        _ => return Err(STATUS_CMD_ERROR),
+    }
+    Ok(SUCCESS)
+}
+
+fn fish_json_encode(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let (opts, optind) = parse_opts(parser, streams, args, L!("or"))?;
+    let (cmd, subcmd) = (L!("fish_json"), args[0]);
+    let args = &args[optind..];
+
+    let mut values = Vec::with_capacity(args.len());
+    for (i, &arg) in args.iter().enumerate() {
+        // Object keys are always strings.
+        if !opts.raw || (opts.object && i % 2 == 0) {
+            values.push(Value::String(arg.to_owned()));
+            continue;
+        }
+        match parse_json(arg) {
+            Ok(value) => values.push(value),
+            Err(err) => {
+                err_fmt!("Invalid JSON '%s': %s", arg, err.describe())
+                    .subcmd(cmd, subcmd)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+        }
+    }
+
+    let value = if opts.object {
+        if values.len() % 2 != 0 {
+            err_str!("expected pairs of keys and values")
+                .subcmd(cmd, subcmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        let mut members = vec![];
+        let mut values = values.into_iter();
+        while let (Some(Value::String(key)), Some(value)) = (values.next(), values.next()) {
+            members.push((key, value));
+        }
+        Value::Object(members)
+    } else {
+        Value::Array(values)
+    };
+    streams.out.appendln(&value.to_json());
+    Ok(SUCCESS)
+}
+
+/// The fish_json builtin, for reading values out of JSON documents and encoding lists as JSON.
+pub fn fish_json(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let Some(&cmd) = args.first() else {
+        return Err(STATUS_INVALID_ARGS);
+    };
+    if args.len() <= 1 {
+        err_str!(Error::MISSING_SUBCMD)
+            .cmd(cmd)
+            .full_trailer(parser)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
+    if args[1] == "-h" || args[1] == "--help" {
+        builtin_print_help(parser, streams, cmd);
+        return Ok(SUCCESS);
+    }
+
+    let subcmd_name = args[1];
+    let subcmd: BuiltinCmd = match subcmd_name.to_string().as_str() {
+        "encode" => fish_json_encode,
+        "get" => fish_json_get,
+        "keys" => fish_json_keys,
+        _ => {
+            err_str!(Error::INVALID_SUBCMD)
+                .subcmd(cmd, subcmd_name)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+    };
+
+    if args.len() >= 3 && (args[2] == "-h" || args[2] == "--help") {
+        builtin_print_help(parser, streams, cmd);
+        return Ok(SUCCESS);
+    }
+    subcmd(parser, streams, &mut args[1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ParseError, ParseErrorKind, QueryStep, Value, parse_json, parse_query, query_value,
+    };
+    use fish_widestring::prelude::*;
+
+    fn s(s: &str) -> WString {
+        WString::from_str(s)
+    }
+
+    #[test]
+    fn test_parse_json() {
+        assert_eq!(
+            parse_json(L!(r#" {"a": [1, -2.5e3, true, null], "b": "xé\n🐟"} "#)),
+            Ok(Value::Object(vec![
+                (
+                    s("a"),
+                    Value::Array(vec![
+                        Value::Number(s("1")),
+                        Value::Number(s("-2.5e3")),
+                        Value::Bool(true),
+                        Value::Null,
+                    ])
+                ),
+                (s("b"), Value::String(s("x\u{e9}\n\u{1F41F}"))),
+            ]))
+        );
+        assert_eq!(parse_json(L!("[]")), Ok(Value::Array(vec![])));
+        let err = |kind, offset| Err(ParseError { kind, offset });
+        assert_eq!(parse_json(L!("")), err(ParseErrorKind::UnexpectedEnd, 0));
+        assert_eq!(
+            parse_json(L!("[1,]")),
+            err(ParseErrorKind::UnexpectedChar(']'), 3)
+        );
+        assert_eq!(
+            parse_json(L!("01")),
+            err(ParseErrorKind::UnexpectedChar('1'), 1)
+        );
+        assert_eq!(parse_json(L!("1.")), err(ParseErrorKind::InvalidNumber, 2));
+        assert_eq!(
+            parse_json(L!(r#""\x""#)),
+            err(ParseErrorKind::InvalidEscape, 2)
+        );
+        assert_eq!(
+            parse_json(L!("{} {}")),
+            err(ParseErrorKind::UnexpectedChar('{'), 3)
+        );
+        let deep = "[".repeat(1000);
+        assert_eq!(parse_json(&s(&deep)), err(ParseErrorKind::TooDeep, 512));
+    }
+
+    #[test]
+    fn test_encode() {
+        let value = parse_json(L!(r#"{ "a" : [1, "two\t"], "b": {} }"#)).unwrap();
+        assert_eq!(value.to_json(), L!(r#"{"a":[1,"two\t"],"b":{}}"#));
+    }
+
+    #[test]
+    fn test_query() {
+        assert_eq!(parse_query(L!(".")), Some(vec![]));
+        assert_eq!(parse_query(L!("")), Some(vec![]));
+        assert_eq!(
+            parse_query(L!(r#".items[-1]["a b"].c"#)),
+            Some(vec![
+                QueryStep::Key(s("items")),
+                QueryStep::Index(-1),
+                QueryStep::Key(s("a b")),
+                QueryStep::Key(s("c")),
+            ])
+        );
+        assert_eq!(parse_query(L!("items")), None);
+        assert_eq!(parse_query(L!(".a.")), None);
+        assert_eq!(parse_query(L!("[x]")), None);
+        assert_eq!(parse_query(L!("[1")), None);
+
+        let doc = parse_json(L!(r#"{"items": [{"name": "a"}, {"name": "b"}]}"#)).unwrap();
+        let get = |query| query_value(&doc, &parse_query(query).unwrap()).map(Value::to_text);
+        assert_eq!(get(L!(".items[1].name")), Some(s("b")));
+        assert_eq!(get(L!(".items[-2].name")), Some(s("a")));
+        assert_eq!(get(L!(".items[2]")), None);
+        assert_eq!(get(L!(".items[-3]")), None);
+        assert_eq!(get(L!(".items.name")), None);
+    }
+}
@@ -6,8 +6,11 @@
 //! carriage-return (\cM) and newline (\cJ).
 //!
 //! Type "exit" or "quit" to terminate the program.
+//!
+//! With `--replay`, it instead decodes the input recorded by `fish --record-input`.
 
 use std::ops::ControlFlow;
+use std::os::fd::AsRawFd as _;
 
 use libc::{STDIN_FILENO, VEOF, VINTR};
 
@@ -18,7 +21,7 @@ use crate::{
     err_fmt, err_str,
     input::{
         CharEvent, ImplicitEvent, InputEventQueue, InputEventQueuer as _, KeyEvent,
-        QueryResultEvent, match_key_event_to_key,
+        QueryResultEvent, match_key_event_to_key, parse_input_recording, replay_input_recording,
     },
     key::{Key, char_to_symbol},
     nix::isatty,
@@ -35,7 +38,7 @@ use crate::{
     tty_handoff::TtyHandoff,
 };
 use fish_wgetopt::{ArgType, WGetopter, WOption, wopt};
-use fish_widestring::osstr2wcstring;
+use fish_widestring::{osstr2wcstring, wcs2osstring};
 
 use super::prelude::*;
 
@@ -81,23 +84,25 @@ fn should_exit(
 }
 
 /// Process the characters we receive as the user presses keys.
+/// When replaying a recording, all of it is processed and nothing is interpreted as a request to
+/// exit.
 fn process_input(
     streams: &mut IoStreams,
     continuous_mode: bool,
     verbose: bool,
+    replaying: bool,
     mut input_queue: InputEventQueue,
 ) -> BuiltinResult {
     let mut first_char_seen = false;
     let mut recent_chars = vec![];
-    streams.err.appendln("Press a key:\n");
-
-    let mut handoff = TtyHandoff::new(|| {});
-    handoff.enable_tty_protocols();
 
     while (!first_char_seen || continuous_mode) && !check_exit_loop_maybe_warning(None) {
         use QueryResultEvent::*;
         let kevt = match input_queue.readch() {
             CharEvent::Implicit(ImplicitEvent::Eof) => {
+                if replaying {
+                    break;
+                }
                 signal_safe_reader_set_exit_signal(libc::SIGHUP);
                 continue;
             }
@@ -135,7 +140,7 @@ fn process_input(
             ));
         }
 
-        if continuous_mode && should_exit(streams, &mut recent_chars, kevt.key) {
+        if continuous_mode && !replaying && should_exit(streams, &mut recent_chars, kevt.key) {
             streams.err.appendln("\nExiting at your request.");
             break;
         }
@@ -170,7 +175,42 @@ fn setup_and_process_keys(
         streams.err.appendln(L!("\n"));
     }
 
-    process_input(streams, continuous_mode, verbose, input_queue)
+    streams.err.appendln("Press a key:\n");
+
+    let mut handoff = TtyHandoff::new(|| {});
+    handoff.enable_tty_protocols();
+
+    process_input(streams, continuous_mode, verbose, false, input_queue)
+}
+
+/// Decode the keys in a recording made with `fish --record-input`, with the recorded timing.
+fn replay_keys(streams: &mut IoStreams, path: &wstr, verbose: bool) -> BuiltinResult {
+    let contents = match std::fs::read(wcs2osstring(path)) {
+        Ok(contents) => contents,
+        Err(err) => {
+            err_fmt!("Could not read recording '%s': %s", path, err.to_string())
+                .cmd(L!("fish_key_reader"))
+                .finish(streams);
+            return Err(STATUS_CMD_ERROR);
+        }
+    };
+    let reads = match parse_input_recording(&contents) {
+        Ok(reads) => reads,
+        Err(line) => {
+            err_fmt!("Invalid recording '%s' on line %d", path, line)
+                .cmd(L!("fish_key_reader"))
+                .finish(streams);
+            return Err(STATUS_CMD_ERROR);
+        }
+    };
+    let Ok(replay_fd) = replay_input_recording(reads) else {
+        err_str!("Could not create a pipe for replaying")
+            .cmd(L!("fish_key_reader"))
+            .finish(streams);
+        return Err(STATUS_CMD_ERROR);
+    };
+    let input_queue = InputEventQueue::new(replay_fd.as_raw_fd(), None);
+    process_input(streams, true, verbose, true, input_queue)
 }
 
 fn parse_flags(
@@ -179,13 +219,15 @@ fn parse_flags(
     args: Vec<WString>,
     continuous_mode: &mut bool,
     verbose: &mut bool,
+    replay: &mut Option<WString>,
 ) -> ControlFlow<BuiltinResult> {
-    let short_opts: &wstr = L!("+chvV");
+    let short_opts: &wstr = L!("+chvVr:");
     let long_opts: &[WOption] = &[
         wopt(L!("continuous"), ArgType::NoArgument, 'c'),
         wopt(L!("help"), ArgType::NoArgument, 'h'),
         wopt(L!("version"), ArgType::NoArgument, 'v'),
         wopt(L!("verbose"), ArgType::NoArgument, 'V'),
+        wopt(L!("replay"), ArgType::RequiredArgument, 'r'),
     ];
     let mut shim_args: Vec<&wstr> = args.iter().map(|s| s.as_ref()).collect();
     let mut w = WGetopter::new(short_opts, long_opts, &mut shim_args);
@@ -213,6 +255,15 @@ fn parse_flags(
             'V' => {
                 *verbose = true;
             }
+            'r' => {
+                *replay = Some(w.woptarg.unwrap().to_owned());
+            }
+            ':' => {
+                err_fmt!(Error::MISSING_OPT_ARG, w.argv[w.wopt_index - 1])
+                    .cmd(L!("fish_key_reader"))
+                    .finish(streams);
+                return ControlFlow::Break(Err(STATUS_CMD_ERROR));
+            }
             ';' => {
                 err_fmt!(Error::UNEXP_OPT_ARG, w.argv[w.wopt_index - 1])
                     .cmd(L!("fish_key_reader"))
@@ -245,6 +296,7 @@ pub fn fish_key_reader(
 ) -> BuiltinResult {
     let mut continuous_mode = false;
     let mut verbose = false;
+    let mut replay = None;
 
     let args = args.iter_mut().map(|x| x.to_owned()).collect();
     if let ControlFlow::Break(s) = parse_flags(
@@ -253,10 +305,15 @@ pub fn fish_key_reader(
         args,
         &mut continuous_mode,
         &mut verbose,
+        &mut replay,
     ) {
         return s;
     }
 
+    if let Some(path) = replay {
+        return replay_keys(streams, &path, verbose);
+    }
+
     if streams.stdin_fd() < 0 || !isatty(streams.stdin_fd()) {
         err_str!("Stdin must be attached to a tty.").finish(streams);
         return Err(STATUS_CMD_ERROR);
@@ -302,14 +359,24 @@ fn throwing_main() -> i32 {
 
     let mut continuous_mode = false;
     let mut verbose = false;
+    let mut replay = None;
 
     let args: Vec<WString> = std::env::args_os().map(osstr2wcstring).collect();
-    if let ControlFlow::Break(s) =
-        parse_flags(None, streams, args, &mut continuous_mode, &mut verbose)
-    {
+    if let ControlFlow::Break(s) = parse_flags(
+        None,
+        streams,
+        args,
+        &mut continuous_mode,
+        &mut verbose,
+        &mut replay,
+    ) {
         return s.builtin_status_code();
     }
 
+    if let Some(path) = replay {
+        return replay_keys(streams, &path, verbose).builtin_status_code();
+    }
+
     if !isatty(STDIN_FILENO) {
         err_str!("Stdin must be attached to a tty.").finish(streams);
         return 1;
@@ -0,0 +1,249 @@
+//! Implementation of the fish_terminal_clipboard builtin, which copies and pastes via the terminal's
+//! clipboard (OSC 52), so it also works over SSH.
+
+use super::prelude::*;
+use crate::builtins::Error;
+use crate::input::{
+    CharEvent, ImplicitEvent, InputEventQueue, InputEventQueuer as _, QueryResponse,
+    QueryResultEvent, TerminalQuery,
+};
+use crate::nix::isatty;
+use crate::reader::{
+    check_exit_loop_maybe_warning, querying_allowed, set_shell_modes_temporarily,
+    signal_safe_reader_set_exit_signal,
+};
+use crate::terminal::{BufferedOutputter, Outputter, TerminalCommand};
+use crate::tty_handoff::get_clipboard_capability;
+use crate::{err_fmt, err_str};
+use fish_common::ReadExt as _;
+use fish_widestring::{bytes2wcstring, wcs2bytes};
+use nix::sys::termios::{SetArg, tcsetattr};
+use std::os::fd::{BorrowedFd, RawFd};
+use std::time::Duration;
+
+/// The longest text we copy, in bytes. Some terminals silently drop longer sequences.
+pub(crate) const MAX_COPY_LEN: usize = 75_000;
+
+/// How long to wait for the terminal if we never had to wait for it before.
+const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+struct Options {
+    force: bool,
+}
+
+fn parse_opts(
+    parser: &Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+    shortopts: &wstr,
+) -> Result<(Options, usize), ErrorCode> {
+    const LONG_OPTIONS: &[WOption] = &[wopt(L!("force"), NoArgument, 'f')];
+    let cmd = L!("fish_terminal_clipboard");
+    let subcmd = args[0];
+    let mut opts = Options::default();
+    let args_read = args.to_vec();
+    let mut w = WGetopter::new(shortopts, LONG_OPTIONS, args);
+    while let Some(c) = w.next_opt() {
+        match c {
+            ';' => {
+                builtin_unexpected_argument(
+                    parser,
+                    streams,
+                    cmd,
+                    args_read[w.wopt_index - 1],
+                    false,
+                );
+                return Err(STATUS_INVALID_ARGS);
+            }
+            c if !shortopts.contains(c) => {
+                err_fmt!(Error::UNKNOWN_OPT, args_read[w.wopt_index - 1])
+                    .subcmd(cmd, subcmd)
+                    .full_trailer(parser)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            'f' => opts.force = true,
+            _ => panic!("unexpected retval from WGetopter"),
+        }
+    }
+    Ok((opts, w.wopt_index))
+}
+
+fn clipboard_copy(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let (opts, optind) = parse_opts(parser, streams, args, L!("f"))?;
+    let (cmd, subcmd) = (L!("fish_terminal_clipboard"), args[0]);
+    let args = &args[optind..];
+
+    let text = if !args.is_empty() {
+        let mut text = WString::new();
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                text.push('\n');
+            }
+            text.push_utfstr(arg);
+        }
+        wcs2bytes(&text)
+    } else {
+        let stdin_file = if streams.stdin_is_directly_redirected {
+            streams.stdin_file.as_mut()
+        } else {
+            None
+        };
+        let Some(stdin_file) = stdin_file else {
+            err_str!(Error::MISSING_ARG)
+                .subcmd(cmd, subcmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        };
+        let mut text = vec![];
+        if let Err(err) = stdin_file.read_to_end_interruptible(&mut text) {
+            return if err.kind() == std::io::ErrorKind::Interrupted {
+                Err(128 + libc::SIGINT)
+            } else {
+                Err(STATUS_CMD_ERROR)
+            };
+        }
+        text
+    };
+
+    // Without --force, only write the sequence where it will be understood, so the caller can fall
+    // back to other ways of copying.
+    let supported = streams.out_is_terminal() && get_clipboard_capability() == Some(true);
+    if !opts.force && !supported {
+        return Err(STATUS_CMD_ERROR);
+    }
+    if text.len() > MAX_COPY_LEN {
+        return Err(STATUS_CMD_ERROR);
+    }
+
+    let mut outp = Outputter::new_buffering();
+    outp.write_command(TerminalCommand::Osc52CopyToClipboard(&text));
+    streams.out.append(&bytes2wcstring(outp.contents()));
+    Ok(SUCCESS)
+}
+
+/// Ask the terminal for the clipboard contents and wait for the answer. Return None if the
+/// terminal does not answer, for example because it does not allow reading the clipboard.
+fn query_clipboard(inputfd: RawFd, timeout: Duration) -> Option<Vec<u8>> {
+    {
+        let mut out = BufferedOutputter::new(Outputter::stdoutput());
+        out.write_command(TerminalCommand::QueryClipboard);
+        // Terminals answer in order, so once this is answered we know there is nothing to wait for.
+        out.write_command(TerminalCommand::QueryPrimaryDeviceAttribute);
+    }
+    // Keys pressed in the meantime are dropped along with this queue.
+    let mut input_queue = InputEventQueue::new(inputfd, Some(timeout));
+    input_queue
+        .blocking_query_mut()
+        .replace(TerminalQuery::Clipboard);
+
+    let mut contents = None;
+    while !check_exit_loop_maybe_warning(None) {
+        use QueryResultEvent::*;
+        match input_queue.readch() {
+            CharEvent::Implicit(ImplicitEvent::Eof) => {
+                signal_safe_reader_set_exit_signal(libc::SIGHUP);
+            }
+            CharEvent::QueryResult(Response(QueryResponse::Clipboard(data))) => {
+                contents = Some(data);
+            }
+            CharEvent::QueryResult(Response(QueryResponse::PrimaryDeviceAttribute)) => break,
+            CharEvent::QueryResult(Timeout | Interrupted) => return None,
+            _ => (),
+        }
+    }
+    contents
+}
+
+fn clipboard_paste(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let (_opts, optind) = parse_opts(parser, streams, args, L!(""))?;
+    let (cmd, subcmd) = (L!("fish_terminal_clipboard"), args[0]);
+    if optind != args.len() {
+        err_fmt!(Error::UNEXP_ARG_COUNT, 0, args.len() - optind)
+            .subcmd(cmd, subcmd)
+            .full_trailer(parser)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
+    let inputfd = streams.stdin_fd();
+    if inputfd < 0
+        || !isatty(inputfd)
+        || !querying_allowed(parser.vars())
+        || get_clipboard_capability() != Some(true)
+    {
+        return Err(STATUS_CMD_ERROR);
+    }
+
+    // The terminal must not echo its answer, so we need the same modes as when reading keys.
+    let old_modes = set_shell_modes_temporarily(inputfd);
+    let timeout = parser
+        .blocking_query_timeout
+        .unwrap_or(DEFAULT_QUERY_TIMEOUT);
+    let contents = query_clipboard(inputfd, timeout);
+    if let Some(old_modes) = old_modes {
+        let _ = tcsetattr(
+            unsafe { BorrowedFd::borrow_raw(inputfd) },
+            SetArg::TCSANOW,
+            &old_modes,
+        );
+    }
+
+    let Some(contents) = contents else {
+        return Err(STATUS_CMD_ERROR);
+    };
+    streams.out.append(&bytes2wcstring(&contents));
+    Ok(SUCCESS)
+}
+
+/// The fish_terminal_clipboard builtin, for copying and pasting with OSC 52.
+pub fn fish_terminal_clipboard(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let Some(&cmd) = args.first() else {
+        return Err(STATUS_INVALID_ARGS);
+    };
+    if args.len() <= 1 {
+        err_str!(Error::MISSING_SUBCMD)
+            .cmd(cmd)
+            .full_trailer(parser)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
+    if args[1] == "-h" || args[1] == "--help" {
+        builtin_print_help(parser, streams, cmd);
+        return Ok(SUCCESS);
+    }
+
+    let subcmd_name = args[1];
+    let subcmd: BuiltinCmd = match subcmd_name.to_string().as_str() {
+        "copy" => clipboard_copy,
+        "paste" => clipboard_paste,
+        _ => {
+            err_str!(Error::INVALID_SUBCMD)
+                .subcmd(cmd, subcmd_name)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+    };
+
+    if args.len() >= 3 && (args[2] == "-h" || args[2] == "--help") {
+        builtin_print_help(parser, streams, cmd);
+        return Ok(SUCCESS);
+    }
+    subcmd(parser, streams, &mut args[1..])
+}
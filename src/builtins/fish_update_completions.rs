@@ -0,0 +1,233 @@
+//! Implementation of the fish_update_completions builtin, which generates completions from the
+//! installed man pages.
+
+use super::prelude::*;
+use crate::env::{EnvMode, Environment as _};
+use crate::err_fmt;
+use crate::manpage_completions::{
+    command_name, manpage_completions, manpath_pages, read_manpage, remove_autogenerated,
+};
+use crate::path::{path_apply_working_directory, path_get_cache};
+use crate::signal::signal_check_cancel;
+use fish_widestring::{osstr2wcstring, str2wcstring, wcs2osstring};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Default)]
+struct Options {
+    print_help: bool,
+    directory: Option<WString>,
+    cleanup_in: Vec<WString>,
+    keep: bool,
+    manpath: bool,
+    progress: bool,
+    stdout: bool,
+    verbose: bool,
+}
+
+const SHORT_OPTIONS: &wstr = L!("c:d:hkmpsv");
+const LONG_OPTIONS: &[WOption] = &[
+    wopt(L!("cleanup-in"), RequiredArgument, 'c'),
+    wopt(L!("directory"), RequiredArgument, 'd'),
+    wopt(L!("help"), NoArgument, 'h'),
+    wopt(L!("keep"), NoArgument, 'k'),
+    wopt(L!("manpath"), NoArgument, 'm'),
+    wopt(L!("progress"), NoArgument, 'p'),
+    wopt(L!("stdout"), NoArgument, 's'),
+    wopt(L!("verbose"), NoArgument, 'v'),
+];
+
+fn parse_options(
+    args: &mut [&wstr],
+    parser: &Parser,
+    streams: &mut IoStreams,
+) -> Result<(Options, usize), ErrorCode> {
+    let cmd = args[0];
+
+    let mut opts = Options::default();
+
+    let mut w = WGetopter::new(SHORT_OPTIONS, LONG_OPTIONS, args);
+
+    while let Some(c) = w.next_opt() {
+        match c {
+            'c' => opts.cleanup_in.push(w.woptarg.unwrap().to_owned()),
+            'd' => opts.directory = Some(w.woptarg.unwrap().to_owned()),
+            'h' => opts.print_help = true,
+            'k' => opts.keep = true,
+            'm' => opts.manpath = true,
+            'p' => opts.progress = true,
+            's' => opts.stdout = true,
+            'v' => opts.verbose = true,
+            ':' => {
+                builtin_missing_argument(parser, streams, cmd, None, args[w.wopt_index - 1], false);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            ';' => {
+                builtin_unexpected_argument(parser, streams, cmd, args[w.wopt_index - 1], false);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            '?' => {
+                builtin_unknown_option(parser, streams, cmd, args[w.wopt_index - 1], false);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            _ => panic!("unexpected retval from WGetopter"),
+        }
+    }
+
+    Ok((opts, w.wopt_index))
+}
+
+/// Generate completion files from the given man pages, or the ones in the manpath with
+/// `--manpath`. The `fish_update_completions` function calls this with the usual directories.
+pub fn fish_update_completions(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let cmd = args[0];
+    let (opts, optind) = parse_options(args, parser, streams)?;
+
+    if opts.print_help {
+        builtin_print_help(parser, streams, cmd);
+        return Ok(SUCCESS);
+    }
+
+    let pwd = parser.vars().get_pwd_slash();
+    let absolute =
+        |path: &wstr| PathBuf::from(wcs2osstring(&path_apply_working_directory(path, &pwd)));
+
+    let mut pages: Vec<PathBuf> = args[optind..].iter().map(|arg| absolute(arg)).collect();
+    if opts.manpath {
+        let vars = parser.vars();
+        let env: Vec<_> = vars
+            .get_names(EnvMode::EXPORT)
+            .into_iter()
+            .filter_map(|name| {
+                let value = vars.get(&name)?.as_string();
+                Some((wcs2osstring(&name), wcs2osstring(&value)))
+            })
+            .collect();
+        pages.extend(manpath_pages(&env));
+    }
+
+    // This used to write completions into the configuration directory, so old ones may be there.
+    for directory in &opts.cleanup_in {
+        remove_autogenerated(&absolute(directory));
+    }
+
+    if pages.is_empty() {
+        streams.out.appendln(wgettext!("No man pages found"));
+        return Ok(SUCCESS);
+    }
+
+    let directory = match &opts.directory {
+        Some(directory) => absolute(directory),
+        None => {
+            let mut cache = path_get_cache().path.to_owned();
+            cache.push_utfstr(L!("/generated_completions"));
+            PathBuf::from(wcs2osstring(&cache))
+        }
+    };
+    if !opts.stdout {
+        if let Err(err) = fs::create_dir_all(&directory) {
+            err_fmt!(
+                "Could not create directory '%s': %s",
+                osstr2wcstring(&directory),
+                err.to_string()
+            )
+            .cmd(cmd)
+            .finish(streams);
+            return Err(STATUS_CMD_ERROR);
+        }
+        if !opts.keep {
+            remove_autogenerated(&directory);
+        }
+    }
+
+    pages.sort();
+    let show_progress = opts.progress && !opts.stdout;
+    if show_progress {
+        streams.out.append(&wgettext_fmt!(
+            "Parsing man pages and writing completions to %s\n",
+            osstr2wcstring(&directory)
+        ));
+    }
+
+    let total = pages.len();
+    let width = total.to_string().len();
+    let mut last_progress_len: usize = 0;
+    let mut parsed = 0;
+    let mut had_error = false;
+    for (i, page) in pages.iter().enumerate() {
+        if signal_check_cancel() != 0 {
+            return Err(STATUS_CMD_ERROR);
+        }
+        let Some(file_name) = page.file_name() else {
+            continue;
+        };
+        let file_name = osstr2wcstring(file_name);
+        if show_progress {
+            let progress = sprintf!("  %*d / %d : %s", width, i + 1, total, file_name);
+            let padding = last_progress_len.saturating_sub(progress.len());
+            last_progress_len = progress.len();
+            streams
+                .out
+                .append(&sprintf!("\r%s%s\r", progress, " ".repeat(padding)));
+        }
+
+        let Some(name) = command_name(page) else {
+            continue;
+        };
+        let path = page.to_string_lossy();
+        let completions = match read_manpage(page) {
+            Ok(manpage) => manpage_completions(&name, &path, &manpage),
+            Err(err) => {
+                if opts.verbose {
+                    err_fmt!("Cannot read '%s': %s", path.as_ref(), err.to_string())
+                        .cmd(cmd)
+                        .finish(streams);
+                }
+                continue;
+            }
+        };
+        let Some(completions) = completions else {
+            if opts.verbose {
+                err_fmt!("%s contains no options or is unparsable", path.as_ref())
+                    .cmd(cmd)
+                    .finish(streams);
+            }
+            continue;
+        };
+
+        if opts.stdout {
+            streams.out.appendln(&str2wcstring(completions));
+        } else if let Err(err) = fs::write(directory.join(format!("{name}.fish")), completions) {
+            err_fmt!(
+                "Could not write completions for '%s': %s",
+                name.as_str(),
+                err.to_string()
+            )
+            .cmd(cmd)
+            .finish(streams);
+            had_error = true;
+            continue;
+        }
+        parsed += 1;
+    }
+    if show_progress {
+        streams.out.append(L!("\n"));
+    }
+    if opts.verbose {
+        streams.err.append(&wgettext_fmt!(
+            "Successfully parsed %d / %d pages\n",
+            parsed,
+            total
+        ));
+    }
+
+    if had_error {
+        Err(STATUS_CMD_ERROR)
+    } else {
+        Ok(SUCCESS)
+    }
+}
@@ -21,6 +21,7 @@ struct Options {
     description: WString,
     events: Vec<EventDescription>,
     named_arguments: Vec<WString>,
+    params: Vec<function::FunctionParam>,
     inherit_vars: Vec<WString>,
     wrap_targets: Vec<WString>,
 }
@@ -33,6 +34,7 @@ impl Default for Options {
             description: WString::new(),
             events: Vec::new(),
             named_arguments: Vec::new(),
+            params: Vec::new(),
             inherit_vars: Vec::new(),
             wrap_targets: Vec::new(),
         }
@@ -42,6 +44,7 @@ impl Default for Options {
 // This command is atypical in using the "-" (RETURN_IN_ORDER) option for flag parsing.
 // This is needed due to the semantics of the -a/--argument-names flag.
 const SHORT_OPTIONS: &wstr = L!("-a:d:e:hj:p:s:v:w:SV:");
+const PARAM_OPTION_CHAR: char = '\x02';
 #[rustfmt::skip]
 const LONG_OPTIONS: &[WOption] = &[
     wopt(L!("description"), ArgType::RequiredArgument, 'd'),
@@ -55,6 +58,7 @@ const LONG_OPTIONS: &[WOption] = &[
     wopt(L!("argument-names"), ArgType::RequiredArgument, 'a'),
     wopt(L!("no-scope-shadowing"), ArgType::NoArgument, 'S'),
     wopt(L!("inherit-variable"), ArgType::RequiredArgument, 'V'),
+    wopt(L!("param"), ArgType::RequiredArgument, PARAM_OPTION_CHAR),
 ];
 
 /// Return the internal_job_id for a pid, or None if none.
@@ -192,6 +196,37 @@ fn parse_cmd_opts(
                     w.woptarg.unwrap(),
                 )?;
             }
+            PARAM_OPTION_CHAR => {
+                let param = function::FunctionParam::parse(w.woptarg.unwrap());
+                validate_variable_name(streams, &param.name, /*read_only_ok=*/ false)?;
+                if let Some(last) = opts.params.last() {
+                    if last.variadic {
+                        err_fmt!(
+                            "%s: only the last parameter may take the remaining arguments",
+                            last.name
+                        )
+                        .cmd(cmd)
+                        .finish(streams);
+                        return Err(STATUS_INVALID_ARGS);
+                    }
+                    if param.is_required() && !last.is_required() {
+                        err_fmt!(
+                            "%s: required parameter after a parameter with a default value",
+                            param.name
+                        )
+                        .cmd(cmd)
+                        .finish(streams);
+                        return Err(STATUS_INVALID_ARGS);
+                    }
+                }
+                if opts.params.iter().any(|p| p.name == param.name) {
+                    err_fmt!("%s: parameter declared twice", param.name)
+                        .cmd(cmd)
+                        .finish(streams);
+                    return Err(STATUS_INVALID_ARGS);
+                }
+                opts.params.push(param);
+            }
             'S' => {
                 opts.shadow_scope = false;
             }
@@ -252,6 +287,13 @@ fn parse_cmd_opts(
         }
     }
 
+    if !opts.params.is_empty() && !opts.named_arguments.is_empty() {
+        err_str!("--param and --argument-names cannot be used together")
+            .cmd(cmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
     Ok(SUCCESS)
 }
 
@@ -336,6 +378,7 @@ pub fn function(
     let props = function::FunctionProperties {
         func_node,
         named_arguments: opts.named_arguments,
+        params: opts.params,
         // Function descriptions are extracted from scripts in `share` via
         // `cargo xtask gettext update`.
         description: LocalizableString::from_external_source(opts.description),
@@ -1,16 +1,20 @@
 use super::prelude::*;
 use crate::{
+    ast::{self, Kind, Node, Traversal},
     builtins::Error,
     common::{reformat_for_screen, valid_func_name},
     err_fmt, err_str,
     event::{self},
     function,
     highlight::highlight_and_colorize,
-    parse_util::{apply_indents, compute_indents},
+    parse_constants::ParseTreeFlags,
+    parse_util::{MaybeParentheses, apply_indents, compute_indents, locate_cmdsubst_range},
     parser_keywords::parser_keywords_is_reserved,
     termsize::termsize_last,
 };
-use fish_common::{EscapeFlags, EscapeStringStyle, escape_string};
+use fish_common::{
+    EscapeFlags, EscapeStringStyle, UnescapeStringStyle, escape_string, unescape_string,
+};
 use fish_widestring::bytes2wcstring;
 
 #[derive(Default)]
@@ -25,12 +29,16 @@ struct Options<'args> {
     no_metadata: bool,
     verbose: bool,
     handlers: bool,
+    depends: bool,
+    recursive: bool,
     color: ColorEnabled,
     handlers_type: Option<&'args wstr>,
     description: Option<&'args wstr>,
 }
 
 const NO_METADATA_SHORT: char = 2 as char;
+const DEPENDS_SHORT: char = 3 as char;
+const RECURSIVE_SHORT: char = 4 as char;
 
 const SHORT_OPTIONS: &wstr = L!("Ht:Dacd:ehnqv");
 #[rustfmt::skip]
@@ -47,6 +55,8 @@ const LONG_OPTIONS: &[WOption] = &[
     wopt(L!("verbose"), ArgType::NoArgument, 'v'),
     wopt(L!("handlers"), ArgType::NoArgument, 'H'),
     wopt(L!("handlers-type"), ArgType::RequiredArgument, 't'),
+    wopt(L!("depends"), ArgType::NoArgument, DEPENDS_SHORT),
+    wopt(L!("recursive"), ArgType::NoArgument, RECURSIVE_SHORT),
     wopt(L!("color"), ArgType::RequiredArgument, COLOR_OPTION_CHAR),
 ];
 
@@ -68,6 +78,8 @@ fn parse_cmd_opts<'args>(
             'e' => opts.erase = true,
             'D' => opts.report_metadata = true,
             NO_METADATA_SHORT => opts.no_metadata = true,
+            DEPENDS_SHORT => opts.depends = true,
+            RECURSIVE_SHORT => opts.recursive = true,
             'd' => {
                 opts.description = Some(w.woptarg.unwrap());
             }
@@ -119,6 +131,77 @@ fn parse_cmd_opts<'args>(
     Ok(SUCCESS)
 }
 
+/// Add the commands invoked in `node`, which is part of `src`, to `out`, unless already present.
+/// This includes commands in command substitutions. Commands whose name is not literal, like
+/// `$cmd` or `(echo ls)`, cannot be known without running the code and are skipped.
+fn add_invoked_commands(node: &dyn Node, src: &wstr, out: &mut Vec<WString>) {
+    for node in Traversal::new(node) {
+        match node.kind() {
+            Kind::DecoratedStatement(stmt) => {
+                let source = stmt.command.source(src);
+                if source.chars().any(|c| "$(*?{".contains(c)) {
+                    continue;
+                }
+                let Some(command) = unescape_string(source, UnescapeStringStyle::default()) else {
+                    continue;
+                };
+                if !command.is_empty() && !out.contains(&command) {
+                    out.push(command);
+                }
+            }
+            Kind::Argument(arg) => {
+                let arg_src = arg.source(src);
+                let mut cursor = 0;
+                while let MaybeParentheses::CommandSubstitution(parens) =
+                    locate_cmdsubst_range(arg_src, &mut cursor, false, None, None)
+                {
+                    let cmdsub_src = &arg_src[parens.command()];
+                    let cmdsub_ast = ast::parse(cmdsub_src, ParseTreeFlags::default(), None);
+                    add_invoked_commands(cmdsub_ast.top(), cmdsub_src, out);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Print the commands invoked by the given functions, and with `recursive`, by the functions
+/// they invoke in turn.
+fn print_dependencies(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    names: &[&wstr],
+    recursive: bool,
+) {
+    let mut commands = vec![];
+    // The functions to look at, in order. With `recursive`, invoked functions are appended.
+    let mut functions: Vec<WString> = names.iter().map(|&name| name.to_owned()).collect();
+    let mut i = 0;
+    while let Some(name) = functions.get(i) {
+        i += 1;
+        let Some(props) = function::get_props_autoload(name, parser) else {
+            continue;
+        };
+        let mut invoked = vec![];
+        add_invoked_commands(
+            &*props.func_node,
+            props.func_node.source_str(),
+            &mut invoked,
+        );
+        for command in invoked {
+            if recursive && !functions.contains(&command) {
+                functions.push(command.clone());
+            }
+            if !commands.contains(&command) {
+                commands.push(command);
+            }
+        }
+    }
+    for command in commands {
+        streams.out.appendln(&command);
+    }
+}
+
 pub fn functions(
     parser: &mut Parser,
     streams: &mut IoStreams,
@@ -147,10 +230,17 @@ pub fn functions(
     }
 
     let describe = opts.description.is_some();
-    if [describe, opts.erase, opts.list, opts.query, opts.copy]
-        .into_iter()
-        .filter(|b| *b)
-        .count()
+    if [
+        describe,
+        opts.erase,
+        opts.list,
+        opts.query,
+        opts.copy,
+        opts.depends,
+    ]
+    .into_iter()
+    .filter(|b| *b)
+    .count()
         > 1
     {
         err_str!(Error::INVALID_OPT_COMBO)
@@ -198,6 +288,27 @@ pub fn functions(
         return Ok(SUCCESS);
     }
 
+    if opts.depends {
+        if args.is_empty() {
+            err_str!("Expected at least one function name")
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        for &name in args {
+            if !function::exists(name, parser) {
+                err_fmt!(FUNCTION_DOES_NOT_EXIST, name)
+                    .cmd(cmd)
+                    .full_trailer(parser)
+                    .finish(streams);
+                return Err(STATUS_CMD_ERROR);
+            }
+        }
+        print_dependencies(parser, streams, args, opts.recursive);
+        return Ok(SUCCESS);
+    }
+
     if opts.report_metadata {
         if args.len() != 1 {
             err_fmt!(
@@ -304,7 +415,24 @@ pub fn functions(
     if opts.list || args.is_empty() {
         let mut names = function::get_names(opts.show_hidden, parser.vars());
         names.sort();
-        if opts.color.enabled(streams) {
+        if opts.verbose {
+            // List the descriptions of functions that are loaded, for completions.
+            for name in names {
+                let mut line = name.clone();
+                if let Some(props) = function::get_props(&name) {
+                    let description = props.description.localize();
+                    if !description.is_empty() {
+                        // Keep it on one line.
+                        line.push('\t');
+                        line.extend(description.chars().map(|c| match c {
+                            '\n' | '\t' => ' ',
+                            c => c,
+                        }));
+                    }
+                }
+                streams.out.appendln(&line);
+            }
+        } else if opts.color.enabled(streams) {
             let mut buff = WString::new();
             let mut first: bool = true;
             for name in names {
@@ -1,11 +1,61 @@
 use super::prelude::*;
+use crate::builtins::Error;
+use crate::{err_fmt, err_str};
+
+/// Parse a leading `-n COUNT`, `--count COUNT` or `--count=COUNT`, returning the count and the
+/// index of the first message. No other options are recognized, so messages may start with a dash.
+fn parse_count(
+    args: &[&wstr],
+    parser: &Parser,
+    streams: &mut IoStreams,
+) -> Result<(Option<u64>, usize), ErrorCode> {
+    let cmd = args[0];
+    let Some(&opt) = args.get(1) else {
+        return Ok((None, 1));
+    };
+
+    let (arg, optind) = if opt == "-n" || opt == "--count" {
+        let Some(&arg) = args.get(2) else {
+            builtin_missing_argument(parser, streams, cmd, None, opt, false);
+            return Err(STATUS_INVALID_ARGS);
+        };
+        (arg, 3)
+    } else if let Some(arg) = opt.strip_prefix("--count=") {
+        (arg, 2)
+    } else {
+        return Ok((None, 1));
+    };
+
+    let Ok(count) = fish_wcstoul(arg) else {
+        err_fmt!(Error::NOT_NUMBER, arg).cmd(cmd).finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    };
+    Ok((Some(count), optind))
+}
 
 /// Used for the fish `_` builtin for requesting translations.
 /// For scripts in `share/`, the corresponding strings are extracted from the scripts using
 /// `cargo xtask gettext update`.
 /// Strings not present in our repo would require a custom MO file for translation to be possible.
-pub fn gettext(_parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) -> BuiltinResult {
-    for arg in &argv[1..] {
+pub fn gettext(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) -> BuiltinResult {
+    let cmd = argv[0];
+    let (count, optind) = parse_count(argv, parser, streams)?;
+
+    if let Some(n) = count {
+        // With a count, the arguments are the singular and plural form of a single message.
+        let &[singular, plural] = &argv[optind..] else {
+            err_str!("--count requires a singular and a plural message")
+                .cmd(cmd)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        };
+        streams
+            .out
+            .append(&crate::localization::localize_plural(singular, plural, n));
+        return Ok(SUCCESS);
+    }
+
+    for arg in &argv[optind..] {
         streams.out.append(
             crate::localization::LocalizableString::from_external_source((*arg).to_owned())
                 .localize(),
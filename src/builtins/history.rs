@@ -1,10 +1,17 @@
 //! Implementation of the history builtin.
 
 use crate::builtins::Error;
+use crate::env::Environment as _;
 use crate::history::in_private_mode;
-use crate::history::{self, History, history_id};
+use crate::history::{
+    self, ForeignFormat, History, HistoryFilter, export_history, history_id, import_history,
+};
+use crate::path::path_apply_working_directory;
 use crate::reader::commandline_get_state;
+use crate::wutil::normalize_path;
 use crate::{err_fmt, err_str};
+use fish_widestring::{bytes2wcstring, wcs2osstring};
+use std::path::Path;
 
 use super::prelude::*;
 
@@ -19,6 +26,10 @@ enum HistCmd {
     None,
     ClearSession,
     Append,
+    Sync,
+    Import,
+    Export,
+    Redact,
 }
 
 impl HistCmd {
@@ -32,6 +43,10 @@ impl HistCmd {
             HistCmd::None => panic!(),
             HistCmd::ClearSession => L!("clear-session"),
             HistCmd::Append => L!("append"),
+            HistCmd::Sync => L!("sync"),
+            HistCmd::Import => L!("import"),
+            HistCmd::Export => L!("export"),
+            HistCmd::Redact => L!("redact"),
         }
     }
 }
@@ -47,6 +62,10 @@ impl TryFrom<&wstr> for HistCmd {
             _ if val == "save" => Ok(HistCmd::Save),
             _ if val == "clear-session" => Ok(HistCmd::ClearSession),
             _ if val == "append" => Ok(HistCmd::Append),
+            _ if val == "sync" => Ok(HistCmd::Sync),
+            _ if val == "import" => Ok(HistCmd::Import),
+            _ if val == "export" => Ok(HistCmd::Export),
+            _ if val == "redact" => Ok(HistCmd::Redact),
             _ => Err(()),
         }
     }
@@ -63,6 +82,8 @@ struct Options {
     null_terminate: bool,
     reverse: bool,
     color: ColorEnabled,
+    filter: HistoryFilter,
+    format: Option<WString>,
 }
 
 /// Note: Do not add new flags that represent subcommands. We're encouraging people to switch to
@@ -86,6 +107,9 @@ const LONG_OPTIONS: &[WOption] = &[
     wopt(L!("merge"), ArgType::NoArgument, '\x05'),
     wopt(L!("reverse"), ArgType::NoArgument, 'R'),
     wopt(L!("color"), ArgType::RequiredArgument, COLOR_OPTION_CHAR),
+    wopt(L!("cwd"), ArgType::RequiredArgument, '\x06'),
+    wopt(L!("failed-only"), ArgType::NoArgument, '\x07'),
+    wopt(L!("format"), ArgType::RequiredArgument, '\x08'),
 ];
 
 /// Remember the history subcommand and disallow selecting more than one history subcommand.
@@ -115,7 +139,12 @@ fn check_for_unexpected_hist_args(
     args: &[&wstr],
     streams: &mut IoStreams,
 ) -> bool {
-    if opts.search_type.is_some() || opts.show_time_format.is_some() || opts.null_terminate {
+    if opts.search_type.is_some()
+        || opts.show_time_format.is_some()
+        || opts.null_terminate
+        || opts.filter != HistoryFilter::default()
+        || (opts.format.is_some() && !matches!(opts.hist_cmd, HistCmd::Import | HistCmd::Export))
+    {
         let subcmd_str = opts.hist_cmd.to_wstr();
         err_str!("subcommand takes no options")
             .subcmd(cmd, subcmd_str)
@@ -171,6 +200,20 @@ fn parse_cmd_opts(
                     return Err(STATUS_CMD_ERROR);
                 }
             }
+            '\x06' => {
+                // Commands record $PWD, which is absolute and normalized.
+                let dir = path_apply_working_directory(
+                    w.woptarg.unwrap(),
+                    &parser.vars().get_pwd_slash(),
+                );
+                opts.filter.cwd = Some(normalize_path(&dir, true));
+            }
+            '\x07' => {
+                opts.filter.failed_only = true;
+            }
+            '\x08' => {
+                opts.format = Some(w.woptarg.unwrap().to_owned());
+            }
             'C' => {
                 opts.case_sensitive = true;
             }
@@ -283,6 +326,7 @@ pub fn history(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
                 opts.search_type
                     .unwrap_or(history::SearchType::ContainsGlob),
                 args,
+                &opts.filter,
                 opts.show_time_format.as_deref(),
                 opts.max_items.unwrap_or(usize::MAX),
                 opts.case_sensitive,
@@ -350,7 +394,198 @@ pub fn history(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
                 history.add_commandline(arg.to_owned());
             }
         }
+        HistCmd::Sync => {
+            if check_for_unexpected_hist_args(&opts, cmd, args, streams) {
+                return Err(STATUS_INVALID_ARGS);
+            }
+
+            if in_private_mode(parser.vars()) {
+                err_str!("can't sync history in private mode")
+                    .cmd(cmd)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            status = sync(cmd, parser, streams, &history);
+        }
+        HistCmd::Import | HistCmd::Export => {
+            status = import_export(cmd, &opts, parser, streams, &history, args);
+        }
+        HistCmd::Redact => {
+            status = redact(cmd, &opts, streams, &history, args);
+        }
     }
 
     status
 }
+
+/// Import the history of another shell or tool, or export ours in its format.
+fn import_export(
+    cmd: &wstr,
+    opts: &Options,
+    parser: &Parser,
+    streams: &mut IoStreams,
+    history: &History,
+    args: &[&wstr],
+) -> BuiltinResult {
+    let subcmd = opts.hist_cmd.to_wstr();
+    let Some(format_name) = &opts.format else {
+        err_str!("missing --format")
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    };
+    let Some(format) = ForeignFormat::from_name(format_name) else {
+        err_fmt!("unknown format '%s'", format_name)
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    };
+    if check_for_unexpected_hist_args(opts, cmd, &[], streams) {
+        return Err(STATUS_INVALID_ARGS);
+    }
+    let expected_args = usize::from(opts.hist_cmd == HistCmd::Import);
+    if args.len() != expected_args {
+        err_fmt!(Error::UNEXP_ARG_COUNT, expected_args, args.len())
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
+    if opts.hist_cmd == HistCmd::Export {
+        let Some(exported) = export_history(history, format) else {
+            err_str!("can't export to atuin, use 'atuin import fish' instead")
+                .subcmd(cmd, subcmd)
+                .finish(streams);
+            return Err(STATUS_CMD_ERROR);
+        };
+        streams.out.append(&bytes2wcstring(&exported));
+        return Ok(SUCCESS);
+    }
+
+    if in_private_mode(parser.vars()) {
+        err_str!("can't import history in private mode")
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+    if format == ForeignFormat::Atuin && !cfg!(feature = "sqlite-history") {
+        err_str!("fish was built without SQLite support, which is needed to import from atuin")
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_CMD_ERROR);
+    }
+    // Make our own commands part of the file, so they are sorted with the imported ones.
+    history.save();
+    let file = args[0];
+    let path = path_apply_working_directory(file, &parser.vars().get_pwd_slash());
+    if let Err(err) = import_history(history, format, Path::new(&wcs2osstring(&path))) {
+        err_fmt!("can't import '%s': %s", file, err.to_string())
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_CMD_ERROR);
+    }
+    Ok(SUCCESS)
+}
+
+/// Delete the commands containing one of the arguments from the history, on disk too.
+fn redact(
+    cmd: &wstr,
+    opts: &Options,
+    streams: &mut IoStreams,
+    history: &History,
+    args: &[&wstr],
+) -> BuiltinResult {
+    let subcmd = opts.hist_cmd.to_wstr();
+    // Secrets are matched literally, even if they contain wildcards.
+    let search_type = match opts.search_type {
+        None | Some(history::SearchType::ContainsGlob) => history::SearchType::Contains,
+        Some(history::SearchType::PrefixGlob) => history::SearchType::Prefix,
+        Some(search_type) => search_type,
+    };
+    if opts.show_time_format.is_some()
+        || opts.null_terminate
+        || opts.filter != HistoryFilter::default()
+        || opts.format.is_some()
+    {
+        err_str!("subcommand takes no options")
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+    if args.is_empty() {
+        err_fmt!(Error::MIN_ARG_COUNT, 1, 0)
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+    if args.iter().any(|arg| arg.is_empty()) {
+        err_str!("can't redact the empty string")
+            .subcmd(cmd, subcmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+    for term in args {
+        if let Err(err) = history.redact(term, search_type, opts.case_sensitive) {
+            err_fmt!("can't rewrite the history: %s", err.to_string())
+                .subcmd(cmd, subcmd)
+                .finish(streams);
+            return Err(STATUS_CMD_ERROR);
+        }
+    }
+    Ok(SUCCESS)
+}
+
+/// Sync the history with the location in `$fish_history_sync`.
+#[cfg(feature = "history-sync")]
+fn sync(cmd: &wstr, parser: &Parser, streams: &mut IoStreams, history: &History) -> BuiltinResult {
+    use crate::err_raw;
+    use crate::history::{SyncError, sync_history};
+
+    let Some(location) = parser.vars().get_unless_empty(L!("fish_history_sync")) else {
+        err_str!("$fish_history_sync is not set")
+            .cmd(cmd)
+            .finish(streams);
+        return Err(STATUS_CMD_ERROR);
+    };
+    let location = location.as_string();
+    // Push what was run in this session, too.
+    history.save();
+    let err = match sync_history(history, &location) {
+        Ok(None) => return Ok(SUCCESS),
+        Ok(Some(key)) => {
+            streams.err.append(&wgettext_fmt!(
+                "%s: created the key '%s'. Copy it to the same place on your other machines.\n",
+                cmd,
+                key
+            ));
+            return Ok(SUCCESS);
+        }
+        Err(SyncError::InvalidLocation) => {
+            err_fmt!("invalid location '%s' in $fish_history_sync", location)
+        }
+        Err(SyncError::Local(err)) => err_raw!(WString::from_str(&err.to_string())),
+        Err(SyncError::Remote(err)) => {
+            err_fmt!("can't sync with '%s': %s", location, err.to_string())
+        }
+        Err(SyncError::WrongKey(count, key)) => err_fmt!(
+            "can't decrypt %d deltas, which were probably stored with another key than '%s'",
+            count,
+            key
+        ),
+    };
+    err.cmd(cmd).finish(streams);
+    Err(STATUS_CMD_ERROR)
+}
+
+#[cfg(not(feature = "history-sync"))]
+fn sync(
+    cmd: &wstr,
+    _parser: &Parser,
+    streams: &mut IoStreams,
+    _history: &History,
+) -> BuiltinResult {
+    err_str!("fish was built without support for syncing the history")
+        .cmd(cmd)
+        .finish(streams);
+    Err(STATUS_CMD_ERROR)
+}
@@ -2,8 +2,8 @@
 
 use super::prelude::*;
 use crate::{
-    err_fmt,
-    io::IoStreams,
+    err_fmt, err_str,
+    io::{IoBufferfill, IoStreams, forward_to_stdout},
     job_group::{JobId, MaybeJobId},
     localization::{wgettext, wgettext_fmt},
     parser::Parser,
@@ -11,19 +11,121 @@ use crate::{
     wutil::fish_wcstoi,
 };
 use fish_common::{EscapeFlags, EscapeStringStyle, escape_string, timef};
+use fish_wcstringutil::join_strings;
 use fish_wgetopt::{ArgType, WGetopter, WOption, wopt};
-use fish_widestring::{L, WExt as _, WString, wstr};
+use fish_widestring::{L, WExt as _, WString, bytes2wcstring, wstr};
 use std::num::NonZeroU32;
+use std::os::fd::OwnedFd;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Print modes for the jobs builtin.
 
 #[derive(Clone, Copy, Eq, PartialEq)]
-enum JobsPrintMode {
-    Default,      // print lots of general info
-    PrintPid,     // print pid of each process in job
-    PrintCommand, // print command name of each process in job
-    PrintGroup,   // print group ID of job
-    PrintNothing, // print nothing (exit status only)
+enum JobsPrintMode<'a> {
+    Default,                        // print lots of general info
+    PrintPid,                       // print pid of each process in job
+    PrintCommand,                   // print command name of each process in job
+    PrintGroup,                     // print group ID of job
+    PrintNothing,                   // print nothing (exit status only)
+    PrintJson,                      // print a JSON object per job
+    PrintFormat(&'a [FormatPiece]), // print a line per job according to a format
+}
+
+/// The job fields available to `--format`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum JobField {
+    Id,
+    Pid,
+    Pgid,
+    State,
+    StartTime,
+    Command,
+}
+
+impl TryFrom<&wstr> for JobField {
+    type Error = ();
+    fn try_from(name: &wstr) -> Result<Self, ()> {
+        match name.to_string().as_str() {
+            "id" => Ok(Self::Id),
+            "pid" => Ok(Self::Pid),
+            "pgid" => Ok(Self::Pgid),
+            "state" => Ok(Self::State),
+            "start_time" => Ok(Self::StartTime),
+            "command" => Ok(Self::Command),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Clone, Eq, PartialEq)]
+enum FormatPiece {
+    Literal(WString),
+    Field(JobField),
+}
+
+/// Parse a `--format` argument into literal text and fields.
+/// Fields are written as `{name}`, and `\n`, `\t` and `\\` are unescaped.
+/// On error, return the offending part of the format.
+fn parse_format(format: &wstr) -> Result<Vec<FormatPiece>, WString> {
+    let mut pieces = vec![];
+    let mut literal = WString::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => literal.push('\n'),
+                Some('t') => literal.push('\t'),
+                Some(c @ ('\\' | '{' | '}')) => literal.push(c),
+                Some(c) => {
+                    literal.push('\\');
+                    literal.push(c);
+                }
+                None => literal.push('\\'),
+            },
+            '{' => {
+                let mut name = WString::new();
+                let mut terminated = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        terminated = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                if !terminated {
+                    return Err(name);
+                }
+                let Ok(field) = JobField::try_from(name.as_utfstr()) else {
+                    return Err(name);
+                };
+                if !literal.is_empty() {
+                    pieces.push(FormatPiece::Literal(std::mem::take(&mut literal)));
+                }
+                pieces.push(FormatPiece::Field(field));
+            }
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        pieces.push(FormatPiece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+/// The time the job was started, in seconds since the epoch.
+fn start_time(j: &Job) -> u64 {
+    SystemTime::now()
+        .checked_sub(j.wall_duration())
+        .and_then(|start| start.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs())
+}
+
+fn state(j: &Job) -> &'static wstr {
+    if j.is_stopped() {
+        L!("stopped")
+    } else {
+        L!("running")
+    }
 }
 
 /// Calculates the cpu usage (as a fraction of 1) of the specified job.
@@ -57,7 +159,11 @@ fn builtin_jobs_print(j: &Job, mode: JobsPrintMode, header: bool, streams: &mut
                 // Print table header before first job.
                 out += wgettext!("Job");
                 out.push('\t');
-                out += wgettext!("Group");
+                out += wgettext!(
+                    "Group",
+                    translator_note =
+                        "Column header for the ID of the process group a job belongs to"
+                );
                 out.push('\t');
                 if *HAVE_PROC_STAT {
                     out += wgettext!("CPU");
@@ -124,15 +230,150 @@ fn builtin_jobs_print(j: &Job, mode: JobsPrintMode, header: bool, streams: &mut
             }
             streams.out.append(&out);
         }
+        JobsPrintMode::PrintJson => {
+            let pgid = match j.pgid() {
+                Some(pgid) => pgid.to_string(),
+                None => "null".to_owned(),
+            };
+            let pids: Vec<WString> = j
+                .external_procs()
+                .map(|p| p.pid().unwrap().to_wstring())
+                .collect();
+            sprintf!(
+                => &mut out,
+                "{\"id\":%d,\"pids\":[%s],\"pgid\":%s,\"state\":\"%s\",\"start_time\":%d,\"command\":%s}\n",
+                j.job_id(),
+                join_strings(&pids, ','),
+                pgid,
+                state(j),
+                start_time(j),
+                json_string(j.command())
+            );
+            streams.out.append(&out);
+        }
+        JobsPrintMode::PrintFormat(pieces) => {
+            for piece in pieces {
+                match piece {
+                    FormatPiece::Literal(text) => out += &text[..],
+                    FormatPiece::Field(JobField::Id) => sprintf!(=> &mut out, "%d", j.job_id()),
+                    FormatPiece::Field(JobField::Pid) => {
+                        let pids: Vec<WString> = j
+                            .external_procs()
+                            .map(|p| p.pid().unwrap().to_wstring())
+                            .collect();
+                        out += &join_strings(&pids, ' ')[..];
+                    }
+                    FormatPiece::Field(JobField::Pgid) => out += &pgid[..],
+                    FormatPiece::Field(JobField::State) => out += state(j),
+                    FormatPiece::Field(JobField::StartTime) => {
+                        out += &start_time(j).to_wstring()[..];
+                    }
+                    FormatPiece::Field(JobField::Command) => {
+                        out += &escape_string(
+                            j.command(),
+                            EscapeStringStyle::Script(EscapeFlags::NO_PRINTABLES),
+                        )[..];
+                    }
+                }
+            }
+            out.push('\n');
+            streams.out.append(&out);
+        }
+    }
+}
+
+/// Print the output captured from the given background jobs, see `fish_background_output`.
+/// Running jobs print what they have written so far. With `attach`, their further output is no
+/// longer captured but goes to our stdout.
+fn builtin_jobs_output(
+    parser: &Parser,
+    streams: &mut IoStreams,
+    cmd: &wstr,
+    args: &[&wstr],
+    quiet: bool,
+    attach: bool,
+) -> BuiltinResult {
+    if args.is_empty() {
+        err_str!("--output requires a job ID")
+            .cmd(cmd)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
+    for arg in args {
+        let output = if arg.char_at(0) == '%' {
+            let Some(job_id) = fish_wcstoi(&arg[1..])
+                .ok()
+                .and_then(|job_id| u32::try_from(job_id).ok())
+                .and_then(NonZeroU32::new)
+                .map(JobId::new)
+            else {
+                err_fmt!("'%s' is not a valid job ID", arg)
+                    .cmd(cmd)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            };
+            match parser.job_with_id(MaybeJobId(Some(job_id))) {
+                Some(j) => running_job_output(&j, attach),
+                None => parser
+                    .captured_output()
+                    .get(&job_id)
+                    .map(|output| (output.clone(), None)),
+            }
+        } else {
+            let pid = parse_pid(streams, cmd, arg)?;
+            parser
+                .job_get_from_pid(pid)
+                .and_then(|j| running_job_output(&j, attach))
+        };
+
+        let Some((output, pipe)) = output else {
+            if !quiet {
+                err_fmt!("No captured output for job: %s", arg)
+                    .cmd(cmd)
+                    .finish(streams);
+            }
+            return Err(STATUS_CMD_ERROR);
+        };
+        if !quiet {
+            streams.out.append(&bytes2wcstring(&output));
+        }
+        // Only pass on new output after the old output was printed.
+        if let Some(pipe) = pipe {
+            forward_to_stdout(pipe);
+        }
+    }
+    Ok(SUCCESS)
+}
+
+/// Return the output a job has written so far, if it is being captured. With `attach`, stop
+/// capturing and also return the pipe which receives the job's further output.
+fn running_job_output(j: &Job, attach: bool) -> Option<(Vec<u8>, Option<OwnedFd>)> {
+    if attach {
+        let filler = j.captured_output.borrow_mut().take()?;
+        let (buffer, pipe) = IoBufferfill::finish_keeping_pipe(filler);
+        return Some((buffer.newline_serialized(), pipe));
     }
+    let captured = j.captured_output.borrow();
+    let filler = captured.as_ref()?;
+    filler.read_all_available();
+    Some((filler.buffer().contents(), None))
 }
 
-const SHORT_OPTIONS: &wstr = L!("cghlpq");
+const FORMAT_SHORT: char = '\x01';
+const JSON_SHORT: char = '\x02';
+const ATTACH_SHORT: char = '\x03';
+
+const SHORT_OPTIONS: &wstr = L!("cghlopq");
 const LONG_OPTIONS: &[WOption] = &[
+    wopt(L!("attach"), ArgType::NoArgument, ATTACH_SHORT),
     wopt(L!("command"), ArgType::NoArgument, 'c'),
+    wopt(L!("format"), ArgType::RequiredArgument, FORMAT_SHORT),
+    wopt(L!("json"), ArgType::NoArgument, JSON_SHORT),
     wopt(L!("group"), ArgType::NoArgument, 'g'),
     wopt(L!("help"), ArgType::NoArgument, 'h'),
     wopt(L!("last"), ArgType::NoArgument, 'l'),
+    wopt(L!("output"), ArgType::NoArgument, 'o'),
     wopt(L!("pid"), ArgType::NoArgument, 'p'),
     wopt(L!("quiet"), ArgType::NoArgument, 'q'),
     wopt(L!("query"), ArgType::NoArgument, 'q'),
@@ -149,6 +390,9 @@ pub fn jobs(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
     let mut found = false;
     let mut mode = JobsPrintMode::Default;
     let mut print_last = false;
+    let mut print_output = false;
+    let mut attach = false;
+    let mut format = vec![];
 
     let mut w = WGetopter::new(SHORT_OPTIONS, LONG_OPTIONS, argv);
     while let Some(c) = w.next_opt() {
@@ -165,9 +409,32 @@ pub fn jobs(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
             'g' => {
                 mode = JobsPrintMode::PrintGroup;
             }
+            JSON_SHORT => {
+                mode = JobsPrintMode::PrintJson;
+            }
+            FORMAT_SHORT => {
+                let arg = w.woptarg.unwrap();
+                match parse_format(arg) {
+                    Ok(pieces) => format = pieces,
+                    Err(field) => {
+                        err_fmt!("Invalid format field '%s'", field)
+                            .cmd(cmd)
+                            .finish(streams);
+                        return Err(STATUS_INVALID_ARGS);
+                    }
+                }
+                mode = JobsPrintMode::PrintFormat(&[]);
+            }
             'l' => {
                 print_last = true;
             }
+            'o' => {
+                print_output = true;
+            }
+            ATTACH_SHORT => {
+                print_output = true;
+                attach = true;
+            }
             'h' => {
                 builtin_print_help(parser, streams, cmd);
                 return Ok(SUCCESS);
@@ -188,6 +455,15 @@ pub fn jobs(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
         }
     }
 
+    if let JobsPrintMode::PrintFormat(pieces) = &mut mode {
+        *pieces = &format;
+    }
+
+    if print_output {
+        let quiet = mode == JobsPrintMode::PrintNothing;
+        return builtin_jobs_output(parser, streams, cmd, &w.argv[w.wopt_index..], quiet, attach);
+    }
+
     if print_last {
         // Ignore unconstructed jobs, i.e. ourself.
         for j in &parser.jobs()[..] {
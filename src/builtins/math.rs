@@ -1,8 +1,14 @@
+use fish_wcstringutil::join_strings;
 use fish_widestring::L;
 use num_traits::pow;
 
 use super::prelude::*;
-use crate::{builtins::Error, err_fmt, tinyexpr::te_interp};
+use crate::{
+    builtins::Error,
+    env::Environment as _,
+    err_fmt,
+    tinyexpr::{self, Definitions, te_interp},
+};
 
 /// The maximum number of points after the decimal that we'll print.
 const DEFAULT_SCALE: usize = 6;
@@ -21,11 +27,24 @@ enum ScaleMode {
     Default,
 }
 
+/// The number of decimal places for results with a unit, unless given explicitly.
+const DEFAULT_UNIT_SCALE: usize = 2;
+
+enum Format {
+    Number,
+    /// A size in bytes, printed with a binary prefix like "1.5 KiB".
+    Bytes,
+    /// A number of seconds, printed like "1d 2h 3m 4s".
+    Duration,
+}
+
 struct Options {
     print_help: bool,
     scale: usize,
     base: usize,
     scale_mode: ScaleMode,
+    format: Format,
+    definitions: Vec<WString>,
 }
 
 fn parse_cmd_opts(
@@ -38,12 +57,14 @@ fn parse_cmd_opts(
 
     // This command is atypical in using the "+" (REQUIRE_ORDER) option for flag parsing.
     // This is needed because of the minus, `-`, operator in math expressions.
-    const SHORT_OPTS: &wstr = L!("+hs:b:m:");
+    const SHORT_OPTS: &wstr = L!("+hs:b:m:d:f:");
     const LONG_OPTS: &[WOption] = &[
         wopt(L!("scale"), ArgType::RequiredArgument, 's'),
         wopt(L!("base"), ArgType::RequiredArgument, 'b'),
         wopt(L!("help"), ArgType::NoArgument, 'h'),
         wopt(L!("scale-mode"), ArgType::RequiredArgument, 'm'),
+        wopt(L!("define"), ArgType::RequiredArgument, 'd'),
+        wopt(L!("format"), ArgType::RequiredArgument, 'f'),
     ];
 
     let mut opts = Options {
@@ -51,6 +72,8 @@ fn parse_cmd_opts(
         scale: DEFAULT_SCALE,
         base: 10,
         scale_mode: DEFAULT_SCALE_MODE,
+        format: Format::Number,
+        definitions: vec![],
     };
 
     let mut have_scale = false;
@@ -111,6 +134,24 @@ fn parse_cmd_opts(
                     opts.base = base as usize;
                 }
             }
+            'd' => {
+                opts.definitions.push(w.woptarg.unwrap().to_owned());
+            }
+            'f' => {
+                let optarg = w.woptarg.unwrap();
+                opts.format = if optarg == "number" {
+                    Format::Number
+                } else if optarg == "bytes" {
+                    Format::Bytes
+                } else if optarg == "duration" {
+                    Format::Duration
+                } else {
+                    err_fmt!("%s: invalid format", optarg)
+                        .cmd(cmd)
+                        .finish(streams);
+                    return Err(STATUS_INVALID_ARGS);
+                };
+            }
             'h' => {
                 opts.print_help = true;
             }
@@ -156,6 +197,21 @@ fn parse_cmd_opts(
         return Err(STATUS_INVALID_ARGS);
     }
 
+    if !matches!(opts.format, Format::Number) {
+        if opts.base != 10 {
+            err_fmt!(
+                Error::INVALID_OPT_COMBO_WITH_CTX,
+                "--format is only valid for base 10"
+            )
+            .cmd(cmd)
+            .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        if !have_scale {
+            opts.scale = DEFAULT_UNIT_SCALE;
+        }
+    }
+
     Ok((opts, w.wopt_index))
 }
 
@@ -220,13 +276,87 @@ fn format_double(mut v: f64, opts: &Options) -> WString {
     ret
 }
 
+/// Print an error in `text`, which is an expression or a definition, marking where it happened.
+fn print_error(cmd: &wstr, streams: &mut IoStreams, err: tinyexpr::Error, text: &wstr) {
+    let mut error = err_fmt!("Error: %s", err.kind.describe_wstr());
+    error.append_assign_to_msg(&sprintf!("\n'%s'", text));
+    let padding = WString::from_chars(vec![' '; err.position + 1]);
+    if err.len >= 2 {
+        let tildes = WString::from_chars(vec!['~'; err.len - 2]);
+        error.append_assign_to_msg(&sprintf!("\n%s^%s^", padding, tildes));
+    } else {
+        error.append_assign_to_msg(&sprintf!("\n%s^", padding));
+    }
+    error.cmd(cmd).finish(streams);
+}
+
+/// Collect the user-defined functions, first from `$fish_math_functions`, then from `--define`,
+/// so the latter can use and override the former.
+fn read_definitions(
+    cmd: &wstr,
+    parser: &Parser,
+    streams: &mut IoStreams,
+    opts: &Options,
+) -> Result<Definitions, ErrorCode> {
+    let mut definitions = Definitions::default();
+    let from_var = parser
+        .vars()
+        .get(L!("fish_math_functions"))
+        .map(|var| var.as_list().to_vec())
+        .unwrap_or_default();
+    for definition in from_var.iter().chain(&opts.definitions) {
+        if let Err(err) = definitions.add(definition) {
+            print_error(cmd, streams, err, definition);
+            return Err(STATUS_INVALID_ARGS);
+        }
+    }
+    Ok(definitions)
+}
+
+/// Return `v` bytes formatted with the largest binary prefix that keeps the number at least 1.
+fn format_bytes(v: f64, opts: &Options) -> WString {
+    const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let mut v = v;
+    let mut unit = 0;
+    while v.abs() >= 1024.0 && unit + 1 < UNITS.len() {
+        v /= 1024.0;
+        unit += 1;
+    }
+    sprintf!("%s %s", format_double(v, opts), UNITS[unit])
+}
+
+/// Return `v` seconds formatted as days, hours, minutes and seconds, leaving out zero parts.
+fn format_duration(v: f64, opts: &Options) -> WString {
+    let mut ret = WString::new();
+    if v.is_sign_negative() && v != 0.0 {
+        ret.push('-');
+    }
+    let whole = v.abs().trunc() as u64;
+    let mut parts = vec![];
+    let mut rest = whole;
+    for (unit, seconds) in [("d", 86400), ("h", 3600), ("m", 60)] {
+        let n = rest / seconds;
+        rest %= seconds;
+        if n > 0 {
+            parts.push(sprintf!("%d%s", n, unit));
+        }
+    }
+    let seconds = rest as f64 + v.abs().fract();
+    if seconds != 0.0 || parts.is_empty() {
+        parts.push(format_double(seconds, opts) + L!("s"));
+    }
+    ret.push_utfstr(&join_strings(&parts, ' '));
+    ret
+}
+
 fn evaluate_expression(
     cmd: &wstr,
     streams: &mut IoStreams,
     opts: &Options,
+    definitions: &Definitions,
     expression: &wstr,
 ) -> BuiltinResult {
-    let ret = te_interp(expression);
+    let ret = te_interp(expression, definitions);
 
     match ret {
         Ok(n) => {
@@ -241,7 +371,11 @@ fn evaluate_expression(
             } else if n.abs() >= MAX_CONTIGUOUS_INTEGER {
                 L!("Result magnitude is too large")
             } else {
-                let mut s = format_double(n, opts);
+                let mut s = match opts.format {
+                    Format::Number => format_double(n, opts),
+                    Format::Bytes => format_bytes(n, opts),
+                    Format::Duration => format_duration(n, opts),
+                };
                 s.push('\n');
 
                 streams.out.append(&s);
@@ -255,17 +389,7 @@ fn evaluate_expression(
             Err(STATUS_CMD_ERROR)
         }
         Err(err) => {
-            let mut error = err_fmt!("Error: %s", err.kind.describe_wstr());
-            error.append_assign_to_msg(&sprintf!("\n'%s'", expression));
-            let padding = WString::from_chars(vec![' '; err.position + 1]);
-            if err.len >= 2 {
-                let tildes = WString::from_chars(vec!['~'; err.len - 2]);
-                error.append_assign_to_msg(&sprintf!("\n%s^%s^", padding, tildes));
-            } else {
-                error.append_assign_to_msg(&sprintf!("\n%s^", padding));
-            }
-            error.cmd(cmd).finish(streams);
-
+            print_error(cmd, streams, err, expression);
             Err(STATUS_CMD_ERROR)
         }
     }
@@ -285,6 +409,8 @@ pub fn math(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
         return Ok(SUCCESS);
     }
 
+    let definitions = read_definitions(cmd, parser, streams, &opts)?;
+
     let mut expression = WString::new();
     for InputValue { arg, .. } in Arguments::new(argv, &mut optind, streams, MATH_CHUNK_SIZE) {
         if !expression.is_empty() {
@@ -300,5 +426,5 @@ pub fn math(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
         return Err(STATUS_CMD_ERROR);
     }
 
-    evaluate_expression(cmd, streams, &opts, &expression)
+    evaluate_expression(cmd, streams, &opts, &definitions, &expression)
 }
@@ -23,7 +23,10 @@ pub mod exit;
 pub mod r#false;
 pub mod fg;
 pub mod fish_indent;
+pub mod fish_json;
 pub mod fish_key_reader;
+pub mod fish_terminal_clipboard;
+pub mod fish_update_completions;
 pub mod function;
 pub mod functions;
 pub mod r#gettext;
@@ -43,6 +46,7 @@ pub mod source;
 pub mod status;
 pub mod string;
 pub mod test;
+pub mod trap;
 pub mod r#true;
 pub mod r#type;
 pub mod ulimit;
@@ -6,6 +6,9 @@ use std::os::unix::prelude::{FileTypeExt as _, MetadataExt as _};
 use std::time::SystemTime;
 
 use super::prelude::*;
+use crate::common::valid_var_name;
+use crate::parse_execution::varname_error;
+use crate::parser::ParserEnvSetMode;
 use crate::path::path_apply_working_directory;
 use crate::wutil::{
     INVALID_FILE_ID, file_id_for_path, lwstat, normalize_path, waccess, wbasename, wdirname,
@@ -14,8 +17,9 @@ use crate::wutil::{
 use bitflags::bitflags;
 use fish_util::wcsfilecmp_glob;
 use fish_wcstringutil::split_string_tok;
+use fish_widestring::str2wcstring;
 use libc::{PATH_MAX, S_ISGID, S_ISUID, mode_t};
-use nix::unistd::{AccessFlags, Gid, Uid};
+use nix::unistd::{AccessFlags, Gid, Group, Uid, User};
 
 // How many bytes we read() at once.
 // We use PATH_MAX here so we always get at least one path,
@@ -107,6 +111,50 @@ impl TryFrom<&wstr> for PermFlags {
     }
 }
 
+/// A piece of metadata `path stat` can print.
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum StatField {
+    Type,
+    Size,
+    Mtime,
+    Perm,
+    Owner,
+    Group,
+}
+
+impl StatField {
+    const ALL: [Self; 6] = [
+        Self::Type,
+        Self::Size,
+        Self::Mtime,
+        Self::Perm,
+        Self::Owner,
+        Self::Group,
+    ];
+
+    fn name(self) -> &'static wstr {
+        match self {
+            Self::Type => L!("type"),
+            Self::Size => L!("size"),
+            Self::Mtime => L!("mtime"),
+            Self::Perm => L!("perm"),
+            Self::Owner => L!("owner"),
+            Self::Group => L!("group"),
+        }
+    }
+}
+
+impl TryFrom<&wstr> for StatField {
+    type Error = ();
+
+    fn try_from(value: &wstr) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|field| field.name() == value)
+            .ok_or(())
+    }
+}
+
 /// This is used by the subcommands to communicate with the option parser which flags are
 /// valid and get the result of parsing the command for flags.
 #[derive(Default)]
@@ -142,6 +190,13 @@ struct Options<'args> {
 
     all_valid: bool,
     all: bool,
+
+    base_valid: bool,
+    base: Option<&'args wstr>,
+
+    stat_valid: bool,
+    fields: Option<Vec<StatField>>,
+    set_prefix: Option<&'args wstr>,
 }
 
 #[inline]
@@ -195,7 +250,7 @@ fn construct_short_opts(opts: &Options) -> WString {
 /// Note that several long flags share the same short flag. That is okay. The caller is expected
 /// to indicate that a max of one of the long flags sharing a short flag is valid.
 /// Remember: adjust the completions in share/completions/ when options change
-const LONG_OPTIONS: [WOption<'static>; 12] = [
+const LONG_OPTIONS: [WOption<'static>; 15] = [
     wopt(L!("quiet"), NoArgument, 'q'),
     wopt(L!("null-in"), NoArgument, 'z'),
     wopt(L!("null-out"), NoArgument, 'Z'),
@@ -208,6 +263,9 @@ const LONG_OPTIONS: [WOption<'static>; 12] = [
     wopt(L!("key"), RequiredArgument, NON_OPTION_CHAR),
     wopt(L!("no-extension"), NoArgument, 'E'),
     wopt(L!("all"), NoArgument, '\x02'),
+    wopt(L!("base"), RequiredArgument, '\x03'),
+    wopt(L!("format"), RequiredArgument, '\x04'),
+    wopt(L!("set"), RequiredArgument, '\x05'),
 ];
 
 fn parse_opts<'args>(
@@ -352,6 +410,36 @@ fn parse_opts<'args>(
                 opts.all = true;
                 continue;
             }
+            '\x03' if opts.base_valid => {
+                opts.base = w.woptarg;
+                continue;
+            }
+            '\x04' if opts.stat_valid => {
+                let fields = opts.fields.get_or_insert_default();
+                let fields_args = split_string_tok(w.woptarg.unwrap(), L!(","), None);
+                for f in fields_args {
+                    let Ok(field) = f.try_into() else {
+                        err_fmt!("Invalid field '%s'", f)
+                            .subcmd(cmd, subcmd)
+                            .finish(streams);
+                        return Err(STATUS_INVALID_ARGS);
+                    };
+                    fields.push(field);
+                }
+                continue;
+            }
+            '\x05' if opts.stat_valid => {
+                let prefix = w.woptarg.unwrap();
+                if !valid_var_name(prefix) {
+                    varname_error(cmd, prefix)
+                        .subcmd(cmd, subcmd)
+                        .full_trailer(parser)
+                        .finish(streams);
+                    return Err(STATUS_INVALID_ARGS);
+                }
+                opts.set_prefix = Some(prefix);
+                continue;
+            }
             _ => {
                 err_fmt!(Error::UNKNOWN_OPT, args_read[w.wopt_index - 1])
                     .subcmd(cmd, subcmd)
@@ -519,6 +607,94 @@ fn path_mtime(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
     }
 }
 
+fn stat_type(md: &Metadata) -> &'static wstr {
+    let file_type = md.file_type();
+    if file_type.is_symlink() {
+        L!("link")
+    } else if file_type.is_dir() {
+        L!("dir")
+    } else if file_type.is_block_device() {
+        L!("block")
+    } else if file_type.is_char_device() {
+        L!("char")
+    } else if file_type.is_fifo() {
+        L!("fifo")
+    } else if file_type.is_socket() {
+        L!("socket")
+    } else {
+        L!("file")
+    }
+}
+
+fn stat_field(md: &Metadata, field: StatField) -> WString {
+    match field {
+        StatField::Type => stat_type(md).to_owned(),
+        StatField::Size => md.len().to_wstring(),
+        StatField::Mtime => md.mtime().to_wstring(),
+        // Like `stat -c %a`, including the set-user-ID, set-group-ID and sticky bits.
+        StatField::Perm => sprintf!("%o", md.mode() & 0o7777),
+        // Fall back to the numeric ID if there is no name.
+        StatField::Owner => match User::from_uid(Uid::from_raw(md.uid())) {
+            Ok(Some(user)) => str2wcstring(user.name),
+            _ => md.uid().to_wstring(),
+        },
+        StatField::Group => match Group::from_gid(Gid::from_raw(md.gid())) {
+            Ok(Some(group)) => str2wcstring(group.name),
+            _ => md.gid().to_wstring(),
+        },
+    }
+}
+
+fn path_stat(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) -> BuiltinResult {
+    let mut opts = Options {
+        stat_valid: true,
+        ..Default::default()
+    };
+    let mut optind = 0;
+
+    parse_opts(&mut opts, &mut optind, 0, args, parser, streams)?;
+
+    let fields = opts.fields.take().unwrap_or(StatField::ALL.to_vec());
+    let mut values: Vec<Vec<WString>> = vec![vec![]; fields.len()];
+    let mut n_found = 0;
+
+    let arguments = arguments(args, &mut optind, streams).with_split_behavior(match opts.null_in {
+        true => SplitBehavior::Null,
+        false => SplitBehavior::InferNull,
+    });
+    for InputValue { arg, .. } in arguments {
+        // A single lstat, so links are described rather than followed.
+        let Ok(md) = lwstat(&arg) else {
+            continue;
+        };
+        if opts.quiet {
+            return Ok(SUCCESS);
+        }
+        n_found += 1;
+        for (field, field_values) in fields.iter().zip(values.iter_mut()) {
+            let value = stat_field(&md, *field);
+            if opts.set_prefix.is_none() {
+                path_out(streams, &opts, &value);
+            }
+            field_values.push(value);
+        }
+    }
+
+    if let Some(prefix) = opts.set_prefix {
+        // Set the variables even if no path was found, so no stale values are left over.
+        for (field, field_values) in fields.iter().zip(values) {
+            let name = prefix.to_owned() + field.name();
+            parser.set_var(&name, ParserEnvSetMode::default(), field_values);
+        }
+    }
+
+    if n_found > 0 {
+        Ok(SUCCESS)
+    } else {
+        Err(STATUS_CMD_ERROR)
+    }
+}
+
 fn find_extension(path: &wstr) -> Option<usize> {
     // The extension belongs to the basename,
     // if there is a "." before the last component it doesn't matter.
@@ -628,6 +804,42 @@ fn path_change_extension(
     }
 }
 
+/// Resolve a path like `path resolve` does: symlinks are resolved as far as the path exists,
+/// and the result is absolute and normalized.
+fn resolve_path(parser: &Parser, arg: &wstr) -> Option<WString> {
+    let real = match wrealpath(arg) {
+        Some(p) => p,
+        None => {
+            // The path doesn't exist, isn't readable or a symlink loop.
+            // We go up until we find something that works.
+            let mut next = arg.to_owned();
+            // First add $PWD if we're relative
+            if !next.is_empty() && next.char_at(0) != '/' {
+                next = path_apply_working_directory(&next, &parser.vars().get_pwd_slash());
+            }
+            let mut rest = wbasename(&next).to_owned();
+            let mut real = None;
+            while !next.is_empty() && next != "/" {
+                next = wdirname(&next).to_owned();
+                real = wrealpath(&next);
+                if let Some(ref mut real) = real {
+                    real.push('/');
+                    real.push_utfstr(&rest);
+                    *real = normalize_path(real, false);
+                    break;
+                }
+                rest = (wbasename(&next).to_owned() + L!("/")) + rest.as_utfstr();
+            }
+            real?
+        }
+    };
+
+    // Normalize the path so "../" components are eliminated even after
+    // nonexistent or non-directory components.
+    // Otherwise `path resolve foo/../` will be `$PWD/foo/../` if foo is a file.
+    Some(normalize_path(&real, false))
+}
+
 fn path_resolve(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) -> BuiltinResult {
     let mut opts = Options::default();
     let mut optind = 0;
@@ -640,42 +852,10 @@ fn path_resolve(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]
         false => SplitBehavior::InferNull,
     });
     for InputValue { arg, .. } in arguments {
-        let mut real = match wrealpath(&arg) {
-            Some(p) => p,
-            None => {
-                // The path doesn't exist, isn't readable or a symlink loop.
-                // We go up until we find something that works.
-                let mut next = arg.into_owned();
-                // First add $PWD if we're relative
-                if !next.is_empty() && next.char_at(0) != '/' {
-                    next = path_apply_working_directory(&next, &parser.vars().get_pwd_slash());
-                }
-                let mut rest = wbasename(&next).to_owned();
-                let mut real = None;
-                while !next.is_empty() && next != "/" {
-                    next = wdirname(&next).to_owned();
-                    real = wrealpath(&next);
-                    if let Some(ref mut real) = real {
-                        real.push('/');
-                        real.push_utfstr(&rest);
-                        *real = normalize_path(real, false);
-                        break;
-                    }
-                    rest = (wbasename(&next).to_owned() + L!("/")) + rest.as_utfstr();
-                }
-
-                match real {
-                    Some(p) => p,
-                    None => continue,
-                }
-            }
+        let Some(real) = resolve_path(parser, &arg) else {
+            continue;
         };
 
-        // Normalize the path so "../" components are eliminated even after
-        // nonexistent or non-directory components.
-        // Otherwise `path resolve foo/../` will be `$PWD/foo/../` if foo is a file.
-        real = normalize_path(&real, false);
-
         // Return 0 if we found a realpath.
         if opts.quiet {
             return Ok(SUCCESS);
@@ -691,6 +871,155 @@ fn path_resolve(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]
     }
 }
 
+/// The components of a normalized path, without the leading "/" of absolute paths.
+fn path_components(path: &wstr) -> Vec<&wstr> {
+    path.split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .collect()
+}
+
+fn path_relative(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let mut opts = Options {
+        base_valid: true,
+        ..Default::default()
+    };
+    let mut optind = 0;
+
+    parse_opts(&mut opts, &mut optind, 0, args, parser, streams)?;
+
+    // Both the base and the paths are resolved, so symlinks and ".." can't trip us up.
+    let base = opts.base.unwrap_or(L!("."));
+    let Some(base) = resolve_path(parser, base) else {
+        err_fmt!("Invalid base directory '%s'", base)
+            .subcmd(L!("path"), args[0])
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    };
+    let base_components = path_components(&base);
+
+    let mut n_printed = 0usize;
+    let arguments = arguments(args, &mut optind, streams).with_split_behavior(match opts.null_in {
+        true => SplitBehavior::Null,
+        false => SplitBehavior::InferNull,
+    });
+    for InputValue { arg, .. } in arguments {
+        if arg.is_empty() {
+            continue;
+        }
+        let Some(real) = resolve_path(parser, &arg) else {
+            continue;
+        };
+        let components = path_components(&real);
+        let common = components
+            .iter()
+            .zip(base_components.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        let mut relative = WString::new();
+        for _ in common..base_components.len() {
+            relative.push_str("../");
+        }
+        for component in &components[common..] {
+            relative.push_utfstr(component);
+            relative.push('/');
+        }
+        if relative.is_empty() {
+            relative.push('.');
+        } else {
+            relative.pop();
+        }
+        // Like `path normalize`, don't let the output look like an option.
+        if relative.char_at(0) == '-' {
+            relative.insert_utfstr(0, L!("./"));
+        }
+
+        if opts.quiet {
+            return Ok(SUCCESS);
+        }
+        path_out(streams, &opts, relative);
+        n_printed += 1;
+    }
+
+    if n_printed > 0 {
+        Ok(SUCCESS)
+    } else {
+        Err(STATUS_CMD_ERROR)
+    }
+}
+
+fn path_common_prefix(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    args: &mut [&wstr],
+) -> BuiltinResult {
+    let mut opts = Options::default();
+    let mut optind = 0;
+
+    parse_opts(&mut opts, &mut optind, 0, args, parser, streams)?;
+
+    let arguments = arguments(args, &mut optind, streams).with_split_behavior(match opts.null_in {
+        true => SplitBehavior::Null,
+        false => SplitBehavior::InferNull,
+    });
+    let paths: Vec<WString> = arguments
+        .map(|input_value| input_value.arg)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| normalize_path(&arg, false))
+        .collect();
+    if paths.is_empty() {
+        return Err(STATUS_CMD_ERROR);
+    }
+
+    // If relative and absolute paths are mixed, the relative ones are taken relative to $PWD.
+    let absolute = paths.iter().any(|path| path.char_at(0) == '/');
+    let paths: Vec<WString> = if absolute {
+        let pwd = parser.vars().get_pwd_slash();
+        paths
+            .into_iter()
+            .map(|path| normalize_path(&path_apply_working_directory(&path, &pwd), false))
+            .collect()
+    } else {
+        paths
+    };
+
+    // The prefix has to be made of whole components: the common prefix of "/foo/bar" and "/foo/baz"
+    // is "/foo", not "/foo/ba".
+    let mut prefix = path_components(&paths[0]);
+    for path in &paths[1..] {
+        let common = prefix
+            .iter()
+            .zip(path_components(path).iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        prefix.truncate(common);
+    }
+
+    let mut result = WString::new();
+    if absolute {
+        result.push('/');
+    }
+    for component in prefix {
+        result.push_utfstr(component);
+        result.push('/');
+    }
+    if result.len() > 1 {
+        result.pop();
+    }
+    if result.is_empty() {
+        result.push('.');
+    } else if result.char_at(0) == '-' {
+        result.insert_utfstr(0, L!("./"));
+    }
+
+    path_out(streams, &opts, result);
+    Ok(SUCCESS)
+}
+
 fn path_sort(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) -> BuiltinResult {
     let mut opts = Options {
         reverse_valid: true,
@@ -981,14 +1310,17 @@ pub fn path(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) ->
     let subcmd: BuiltinCmd = match subcmd_name.to_string().as_str() {
         "basename" => path_basename,
         "change-extension" => path_change_extension,
+        "common-prefix" => path_common_prefix,
         "dirname" => path_dirname,
         "extension" => path_extension,
         "filter" => path_filter,
         "is" => path_is,
         "mtime" => path_mtime,
         "normalize" => path_normalize,
+        "relative" => path_relative,
         "resolve" => path_resolve,
         "sort" => path_sort,
+        "stat" => path_stat,
         _ => {
             err_str!(Error::INVALID_SUBCMD)
                 .subcmd(cmd, subcmd_name)
@@ -36,10 +36,13 @@
 // \uhhhh = 16-bit Unicode character (hhhh is 4 digits)
 // \Uhhhhhhhh = 32-bit Unicode character (hhhhhhhh is 8 digits)
 //
-// Additional directive:
+// Additional directives:
 //
 // %b = print an argument string, interpreting backslash escapes,
 //   except that octal escapes are of the form \0 or \0ooo.
+// %q = print an argument string quoted for fish.
+// %(fmt)T = print an argument of seconds since the epoch formatted with strftime(3),
+//   like in bash.
 //
 // The `format' argument is re-used as many times as necessary
 // to convert all of the given arguments.
@@ -58,8 +61,11 @@ use crate::wutil::{
     wstr_offset_in,
 };
 use crate::{err_fmt, err_str};
+use fish_common::{EscapeFlags, EscapeStringStyle, escape_string};
 use fish_printf::{ToArg as _, sprintf_locale};
-use fish_widestring::{decode_byte_from_char, encode_byte_to_char};
+use fish_widestring::{cstr2wcstring, decode_byte_from_char, encode_byte_to_char, wcs2zstring};
+use std::ffi::CStr;
+use std::mem::MaybeUninit;
 
 /// Return true if `c` is an octal digit.
 fn is_octal_digit(c: char) -> bool {
@@ -193,6 +199,38 @@ fn string_to_scalar_type<T: RawStringToScalarType>(s: &wstr, state: &mut State)
 }
 
 /// For each character in str, set the corresponding boolean in the array to the given flag.
+/// Format `time`, in seconds since the epoch, in the local timezone according to the strftime(3)
+/// `format`. Return None if the time can't be represented.
+fn format_time(format: &wstr, time: libc::time_t) -> Option<WString> {
+    let mut tm = MaybeUninit::uninit();
+    if unsafe { libc::localtime_r(&time, tm.as_mut_ptr()) }.is_null() {
+        return None;
+    }
+    if format.is_empty() {
+        return Some(WString::new());
+    }
+    let format = wcs2zstring(format);
+    // strftime returns 0 if the buffer is too small, but also if the result is empty,
+    // so give up at some point.
+    let mut buf = vec![0_u8; 256];
+    while buf.len() <= 64 * 1024 {
+        let len = unsafe {
+            libc::strftime(
+                buf.as_mut_ptr().cast(),
+                buf.len(),
+                format.as_ptr(),
+                tm.as_ptr(),
+            )
+        };
+        if len != 0 {
+            // SAFETY: strftime terminates the string with a null byte if it succeeds.
+            return Some(cstr2wcstring(CStr::from_bytes_until_nul(&buf).unwrap()));
+        }
+        buf.resize(buf.len() * 2, 0);
+    }
+    Some(WString::new())
+}
+
 fn modify_allowed_format_specifiers(ok: &mut [bool; 256], str: &str, flag: bool) {
     for c in str.chars() {
         ok[c as usize] = flag;
@@ -431,12 +469,12 @@ impl<'a, 'b> State<'a, 'b> {
                         continue;
                     }
 
-                    modify_allowed_format_specifiers(&mut ok, "aAcdeEfFgGiosuxX", true);
+                    modify_allowed_format_specifiers(&mut ok, "aAcdeEfFgGioqsuxX", true);
                     let mut continue_looking_for_flags = true;
                     while continue_looking_for_flags {
                         match f.char_at(0) {
                             '\'' => {
-                                modify_allowed_format_specifiers(&mut ok, "aAceEosxX", false);
+                                modify_allowed_format_specifiers(&mut ok, "aAceEoqsxX", false);
                             }
 
                             '-' | '+' | ' ' => {
@@ -444,11 +482,11 @@ impl<'a, 'b> State<'a, 'b> {
                             }
 
                             '#' => {
-                                modify_allowed_format_specifiers(&mut ok, "cdisu", false);
+                                modify_allowed_format_specifiers(&mut ok, "cdiqsu", false);
                             }
 
                             '0' => {
-                                modify_allowed_format_specifiers(&mut ok, "cs", false);
+                                modify_allowed_format_specifiers(&mut ok, "cqs", false);
                             }
 
                             _ => {
@@ -518,8 +556,22 @@ impl<'a, 'b> State<'a, 'b> {
                         f = &f[1..];
                     }
 
+                    // The strftime format of a %(...)T directive.
+                    let mut time_format = None;
+                    if f.char_at(0) == '(' {
+                        if let Some(close) = f.find_char(')') {
+                            time_format = Some(&f[1..close]);
+                            f = &f[close + 1..];
+                        }
+                    }
+
                     let conversion = f.char_at(0);
-                    if (conversion as usize) > 0xFF || !ok[conversion as usize] {
+                    let valid = match time_format {
+                        // The time is formatted as a string.
+                        Some(_) => conversion == 'T' && ok['s' as usize],
+                        None => (conversion as usize) <= 0xFF && ok[conversion as usize],
+                    };
+                    if !valid {
                         let directive = &directive_start[0..directive_start
                             .len()
                             .min(wstr_offset_in(f, directive_start) + 1)];
@@ -536,9 +588,42 @@ impl<'a, 'b> State<'a, 'b> {
                         argv = &argv[1..];
                         argc -= 1;
                     }
+                    // %q and %T are printed like %s, with the argument converted first.
+                    let converted;
+                    let (conversion, argument) = match conversion {
+                        'q' => {
+                            converted = escape_string(
+                                argument,
+                                EscapeStringStyle::Script(EscapeFlags::default()),
+                            );
+                            ('s', converted.as_utfstr())
+                        }
+                        'T' => {
+                            // Like in bash, an empty argument or -1 means the current time.
+                            let time: i64 = if argument.is_empty() {
+                                -1
+                            } else {
+                                string_to_scalar_type(argument, self)
+                            };
+                            // This warns for musl, but there is nothing we can do about it.
+                            #[allow(deprecated)]
+                            let time = if time == -1 {
+                                unsafe { libc::time(std::ptr::null_mut()) }
+                            } else {
+                                time as libc::time_t
+                            };
+                            let Some(formatted) = format_time(time_format.unwrap(), time) else {
+                                self.fatal_error(err_fmt!("%s: invalid time", argument));
+                                return 0;
+                            };
+                            converted = formatted;
+                            ('s', converted.as_utfstr())
+                        }
+                        c => (c, argument),
+                    };
                     self.print_directive(
                         &directive_start[..directive_length],
-                        f.char_at(0),
+                        conversion,
                         field_width,
                         precision,
                         argument,
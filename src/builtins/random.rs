@@ -3,29 +3,61 @@ use super::prelude::*;
 use crate::builtins::Error;
 use crate::{err_fmt, err_str, wutil};
 use fish_util::get_seeded_rng;
-use rand::rngs::SmallRng;
-use rand::{Rng as _, RngExt as _};
+use rand::rand_core::UnwrapErr;
+use rand::rngs::{SmallRng, SysRng};
+use rand::seq::SliceRandom as _;
+use rand::{Rng, RngExt as _};
 use std::sync::{LazyLock, Mutex};
 
+// How many bytes we read() at once when reading items from stdin.
+const RANDOM_CHUNK_SIZE: usize = 1024;
+
 static RNG: LazyLock<Mutex<SmallRng>> =
     LazyLock::new(|| Mutex::new(get_seeded_rng(rand::rng().next_u64())));
 
+/// Run `f` with either our seedable engine or, if `secure` is set, the operating system's
+/// cryptographically secure generator.
+fn with_rng<T>(secure: bool, f: impl FnOnce(&mut dyn Rng) -> T) -> T {
+    if secure {
+        f(&mut UnwrapErr(SysRng))
+    } else {
+        f(&mut *RNG.lock().unwrap())
+    }
+}
+
+/// The items for `random choice` and `random shuffle`: the arguments, or the lines of stdin if
+/// there are none.
+fn items(args: &[&wstr], streams: &mut IoStreams) -> Vec<WString> {
+    if !args.is_empty() {
+        return args.iter().map(|&arg| arg.to_owned()).collect();
+    }
+    let mut zero = 0;
+    Arguments::new(&[] as _, &mut zero, streams, RANDOM_CHUNK_SIZE)
+        .map(|input_value| input_value.arg.into_owned())
+        .collect()
+}
+
 pub fn random(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) -> BuiltinResult {
     let cmd = argv[0];
     let argc = argv.len();
     let print_hints = false;
 
-    let shortopts: &wstr = L!("+h");
-    let longopts: &[WOption] = &[wopt(L!("help"), ArgType::NoArgument, 'h')];
+    let mut secure = false;
+
+    let shortopts: &wstr = L!("+hs");
+    let longopts: &[WOption] = &[
+        wopt(L!("help"), ArgType::NoArgument, 'h'),
+        wopt(L!("secure"), ArgType::NoArgument, 's'),
+    ];
 
     let mut w = WGetopter::new(shortopts, longopts, argv);
-    #[allow(clippy::never_loop)]
     while let Some(c) = w.next_opt() {
         match c {
             'h' => {
                 builtin_print_help(parser, streams, cmd);
                 return Ok(SUCCESS);
             }
+            's' => secure = true,
             ':' => {
                 builtin_missing_argument(
                     parser,
@@ -63,13 +95,26 @@ pub fn random(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr])
     let arg_count = argc - w.wopt_index;
     let i = w.wopt_index;
     if arg_count >= 1 && argv[i] == "choice" {
-        if arg_count == 1 {
+        let items = items(&argv[i + 1..], streams);
+        if items.is_empty() {
             err_str!("nothing to choose from").cmd(cmd).finish(streams);
             return Err(STATUS_INVALID_ARGS);
         }
 
-        let rand = RNG.lock().unwrap().random_range(0..arg_count - 1);
-        streams.out.appendln(argv[i + 1 + rand]);
+        let rand = with_rng(secure, |rng| rng.random_range(0..items.len()));
+        streams.out.appendln(&items[rand]);
+        return Ok(SUCCESS);
+    }
+    if arg_count >= 1 && argv[i] == "shuffle" {
+        let mut items = items(&argv[i + 1..], streams);
+        if items.is_empty() {
+            return Err(STATUS_CMD_ERROR);
+        }
+
+        with_rng(secure, |rng| items.shuffle(rng));
+        for item in items {
+            streams.out.appendln(&item);
+        }
         return Ok(SUCCESS);
     }
     fn parse_ll(streams: &mut IoStreams, cmd: &wstr, num: &wstr) -> Result<i64, wutil::Error> {
@@ -93,6 +138,12 @@ pub fn random(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr])
         }
         1 => {
             // Seed the engine persistently
+            if secure {
+                err_str!("the secure generator cannot be seeded")
+                    .cmd(cmd)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
             let num = parse_ll(streams, cmd, argv[i]);
             match num {
                 Err(_) => return Err(STATUS_INVALID_ARGS),
@@ -154,10 +205,7 @@ pub fn random(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr])
     // Using abs_diff() avoids an i64 overflow if start is i64::MIN and end is i64::MAX
     let possibilities = end.abs_diff(start) / step;
 
-    let rand = {
-        let mut engine = RNG.lock().unwrap();
-        engine.random_range(0..=possibilities)
-    };
+    let rand = with_rng(secure, |rng| rng.random_range(0..=possibilities));
 
     // Safe because end was a valid i64 and the result here is in the range start..=end.
     let result: i64 = start.checked_add_unsigned(rand * step).unwrap();
@@ -6,6 +6,7 @@ use crate::{
     common::valid_var_name,
     env::{EnvMode, EnvVar, EnvVarFlags, Environment as _, READ_BYTE_LIMIT},
     err_fmt, err_str,
+    fd_readable_set::{Timeout, is_fd_readable},
     history::{HistoryId, MemoryHistoryId},
     input::{DecodeState, InvalidPolicy, decode_utf8},
     nix::isatty,
@@ -15,15 +16,21 @@ use crate::{
         ReaderConfig, commandline_set_buffer, reader_pop, reader_push, reader_readline,
         set_shell_modes_temporarily,
     },
+    signal::signal_check_cancel,
     tokenizer::{TOK_ACCEPT_UNFINISHED, TOK_ARGUMENT_LIST, Tok, Tokenizer},
-    wutil,
+    wutil::{self, wcstod::wcstod},
 };
 use fish_common::{UnescapeStringStyle, escape, read_blocked, unescape_string};
 use fish_util::perror;
 use fish_wcstringutil::{split_about, split_string_tok};
 use fish_widestring::bytes2wcstring;
 use libc::SEEK_CUR;
-use std::{num::NonZeroUsize, os::fd::RawFd, sync::atomic::Ordering};
+use std::{
+    num::NonZeroUsize,
+    os::fd::RawFd,
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
+};
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub(crate) enum TokenOutputMode {
@@ -32,6 +39,13 @@ pub(crate) enum TokenOutputMode {
     Unescaped,
 }
 
+/// How to convert a field read with `--fields`.
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum FieldType {
+    Int,
+    Float,
+}
+
 #[derive(Default)]
 struct Options {
     print_help: bool,
@@ -49,7 +63,11 @@ struct Options {
     silent: bool,
     split_null: bool,
     nchars: Option<NonZeroUsize>,
+    timeout: Option<Duration>,
     one_line: bool,
+    fields: bool,
+    // The variables given to --int and --float.
+    field_types: Vec<(WString, FieldType)>,
 }
 
 impl Options {
@@ -67,9 +85,12 @@ const LONG_OPTIONS: &[WOption] = &[
     wopt(L!("command"), ArgType::RequiredArgument, 'c'),
     wopt(L!("delimiter"), ArgType::RequiredArgument, 'd'),
     wopt(L!("export"), ArgType::NoArgument, 'x'),
+    wopt(L!("fields"), ArgType::NoArgument, '\x03'),
+    wopt(L!("float"), ArgType::RequiredArgument, '\x05'),
     wopt(L!("function"), ArgType::NoArgument, 'f'),
     wopt(L!("global"), ArgType::NoArgument, 'g'),
     wopt(L!("help"), ArgType::NoArgument, 'h'),
+    wopt(L!("int"), ArgType::RequiredArgument, '\x04'),
     wopt(L!("line"), ArgType::NoArgument, 'L'),
     wopt(L!("list"), ArgType::NoArgument, 'a'),
     wopt(L!("local"), ArgType::NoArgument, 'l'),
@@ -80,6 +101,7 @@ const LONG_OPTIONS: &[WOption] = &[
     wopt(L!("right-prompt"), ArgType::RequiredArgument, 'R'),
     wopt(L!("shell"), ArgType::NoArgument, 'S'),
     wopt(L!("silent"), ArgType::NoArgument, 's'),
+    wopt(L!("timeout"), ArgType::RequiredArgument, '\x02'),
     wopt(L!("tokenize"), ArgType::NoArgument, 't'),
     wopt(L!("tokenize-raw"), ArgType::NoArgument, '\x01'),
     wopt(L!("unexport"), ArgType::NoArgument, 'u'),
@@ -147,6 +169,29 @@ fn parse_cmd_opts(
                     }
                 }
             }
+            '\x02' => {
+                opts.timeout = match parse_timeout(w.woptarg.unwrap()) {
+                    Some(timeout) => Some(timeout),
+                    None => {
+                        err_fmt!("%s: invalid timeout", w.woptarg.unwrap())
+                            .cmd(cmd)
+                            .full_trailer(parser)
+                            .finish(streams);
+                        return Err(STATUS_INVALID_ARGS);
+                    }
+                };
+            }
+            '\x03' => {
+                opts.fields = true;
+            }
+            '\x04' => {
+                let var = w.woptarg.unwrap().to_owned();
+                opts.field_types.push((var, FieldType::Int));
+            }
+            '\x05' => {
+                let var = w.woptarg.unwrap().to_owned();
+                opts.field_types.push((var, FieldType::Float));
+            }
             'P' => {
                 opts.prompt_str = Some(w.woptarg.unwrap().to_owned());
             }
@@ -219,6 +264,33 @@ fn parse_cmd_opts(
     Ok((opts, w.wopt_index))
 }
 
+/// The longest timeout we accept. Polling takes the timeout in milliseconds as a C int.
+const MAX_TIMEOUT_SECS: f64 = (libc::c_int::MAX / 1000) as f64;
+
+/// Parse a timeout given as a non-negative (possibly fractional) number of seconds.
+fn parse_timeout(arg: &wstr) -> Option<Duration> {
+    let mut consumed = 0;
+    let secs = wcstod(arg, '.', &mut consumed).ok()?;
+    if consumed != arg.len() || !(0.0..=MAX_TIMEOUT_SECS).contains(&secs) {
+        return None;
+    }
+    Some(Duration::from_secs_f64(secs))
+}
+
+/// Wait until the fd is readable. Return false if the deadline passed or we were interrupted.
+fn wait_readable_until(fd: RawFd, deadline: Instant) -> bool {
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if is_fd_readable(fd, Timeout::Duration(remaining)) {
+            return true;
+        }
+        // A signal may wake us up early; keep waiting unless it asked us to cancel.
+        if remaining.is_zero() || Instant::now() >= deadline || signal_check_cancel() != 0 {
+            return false;
+        }
+    }
+}
+
 /// Read from the tty. This is only valid when the stream is stdin and it is attached to a tty and
 /// we weren't asked to split on null characters.
 #[allow(clippy::too_many_arguments)]
@@ -233,6 +305,7 @@ fn read_interactive(
     right_prompt: &wstr,
     commandline: Option<&WString>,
     inputfd: RawFd,
+    deadline: Option<Instant>,
 ) -> BuiltinResult {
     let mut exit_res = Ok(SUCCESS);
 
@@ -255,6 +328,7 @@ fn read_interactive(
         event: L!("fish_read"),
 
         inputfd,
+        deadline,
 
         ..Default::default()
     };
@@ -366,12 +440,13 @@ fn read_in_chunks(fd: RawFd, buff: &mut WString, split_null: bool, do_seek: bool
 
 /// Read from the fd on char at a time until we've read the requested number of characters or a
 /// newline or null, as appropriate, is seen. This is inefficient so should only be used when the
-/// fd is not seekable.
+/// fd is not seekable, or when we need to give up at a deadline.
 fn read_one_char_at_a_time(
     fd: RawFd,
     buff: &mut WString,
     nchars: Option<NonZeroUsize>,
     split_null: bool,
+    deadline: Option<Instant>,
 ) -> BuiltinResult {
     let mut exit_res = Ok(SUCCESS);
     let mut nbytes = 0;
@@ -381,6 +456,9 @@ fn read_one_char_at_a_time(
     loop {
         let chars_read = buff.len();
         let res = loop {
+            if deadline.is_some_and(|deadline| !wait_readable_until(fd, deadline)) {
+                return Err(STATUS_CMD_ERROR);
+            }
             let mut b = [0_u8; 1];
             match read_blocked(fd, &mut b) {
                 Ok(0) | Err(_) => {
@@ -427,6 +505,199 @@ fn read_one_char_at_a_time(
     exit_res
 }
 
+/// Read a single record (a line, or up to a null character with `--null`) into `buff`, using the
+/// best method for the kind of input we have.
+fn read_record(
+    parser: &mut Parser,
+    streams: &IoStreams,
+    opts: &Options,
+    buff: &mut WString,
+    stdin_is_a_tty: bool,
+    deadline: Option<Instant>,
+) -> BuiltinResult {
+    if stdin_is_a_tty && !opts.split_null {
+        // Read interactively using reader_readline(). This does not support splitting on null.
+        read_interactive(
+            parser,
+            buff,
+            opts.nchars,
+            opts.shell,
+            opts.silent,
+            opts.prompt.as_ref().unwrap(),
+            opts.prompt_str.as_ref().is_some_and(|ps| ps.is_empty()),
+            &opts.right_prompt,
+            opts.commandline.as_ref(),
+            streams.stdin_fd(),
+            deadline,
+        )
+    } else if opts.nchars.is_none() && opts.timeout.is_none() && !stdin_is_a_tty &&
+        // "one_line" is implemented as reading n-times to a new line,
+        // if we're chunking we could get multiple lines so we would have to advance
+        // more than 1 per run through the loop. Let's skip that for now.
+        // The same goes for reading all records with --fields.
+        !opts.one_line && !opts.fields &&
+        (streams.stdin_is_directly_redirected ||
+                unsafe {libc::lseek(streams.stdin_fd(), 0, SEEK_CUR)} != -1)
+    {
+        // We read in chunks when we either can seek (so we put the bytes back),
+        // or we have the bytes to ourselves (because it's directly redirected).
+        //
+        // Note we skip seeking back even if we're directly redirected to a seekable stream,
+        // under the assumption that the stream will be closed soon anyway.
+        // You don't rewind VHS tapes before throwing them in the trash.
+        // TODO: Do this when nchars is set by seeking back.
+        read_in_chunks(
+            streams.stdin_fd(),
+            buff,
+            opts.split_null,
+            !streams.stdin_is_directly_redirected,
+        )
+    } else {
+        read_one_char_at_a_time(
+            streams.stdin_fd(),
+            buff,
+            opts.nchars,
+            opts.split_null,
+            deadline,
+        )
+    }
+}
+
+/// Split a record into exactly `n` fields for `--fields`. The last field gets any remainder and
+/// missing fields are empty, so every variable gets a value for every record.
+fn split_record(record: &wstr, delimiter: &wstr, use_ifs: bool, n: usize) -> Vec<WString> {
+    let mut fields: Vec<WString> = if delimiter.is_empty() {
+        // Like without --fields, every character is a separate field.
+        let split = n.min(record.len());
+        let mut fields: Vec<WString> = record
+            .chars()
+            .take(split)
+            .map(|c| WString::from_chars([c]))
+            .collect();
+        if split > 0 {
+            *fields.last_mut().unwrap() = record[split - 1..].to_owned();
+        }
+        fields
+    } else if use_ifs {
+        split_string_tok(record, delimiter, Some(n))
+            .into_iter()
+            .map(|s| s.to_owned())
+            .collect()
+    } else {
+        split_about(record, delimiter, n - 1, false)
+            .into_iter()
+            .map(|s| s.to_owned())
+            .collect()
+    };
+    fields.resize(n, WString::new());
+    fields
+}
+
+/// Convert a field for `--int` or `--float`, ignoring surrounding whitespace.
+/// Return None if it isn't a valid number.
+fn convert_field(field: &wstr, field_type: FieldType) -> Option<WString> {
+    match field_type {
+        FieldType::Int => fish_wcstol(field).ok().map(|n| n.to_wstring()),
+        FieldType::Float => {
+            let mut consumed = 0;
+            let value = wcstod(field, '.', &mut consumed).ok()?;
+            if !value.is_finite() || !field[consumed..].chars().all(char::is_whitespace) {
+                return None;
+            }
+            let start = field.chars().take_while(|c| c.is_whitespace()).count();
+            Some(field[start..consumed].to_owned())
+        }
+    }
+}
+
+/// Read all records until the end of the input for `--fields`. Each record is split into one
+/// field per variable, and every variable is set to the list of its fields, i.e. one column.
+#[allow(clippy::too_many_arguments)]
+fn read_fields(
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    cmd: &wstr,
+    opts: &Options,
+    vars: &[&wstr],
+    stdin_is_a_tty: bool,
+    deadline: Option<Instant>,
+) -> BuiltinResult {
+    let clear_vars = |parser: &mut Parser| {
+        for var in vars {
+            parser.set_empty(var, opts.place);
+        }
+    };
+
+    let ifs = parser
+        .vars()
+        .get_unless_empty(L!("IFS"))
+        .map(|var| var.as_string())
+        .unwrap_or_default();
+    let delimiter = opts.delimiter.as_deref().unwrap_or(&ifs);
+    // The last --int or --float for a variable wins.
+    let field_types: Vec<Option<FieldType>> = vars
+        .iter()
+        .map(|var| {
+            opts.field_types
+                .iter()
+                .rev()
+                .find(|(name, _)| name == var)
+                .map(|&(_, field_type)| field_type)
+        })
+        .collect();
+
+    let mut columns: Vec<Vec<WString>> = vec![vec![]; vars.len()];
+    let mut buff = WString::new();
+    let mut row = 0;
+    loop {
+        buff.clear();
+        if let Err(err) = read_record(parser, streams, opts, &mut buff, stdin_is_a_tty, deadline) {
+            let timed_out = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            // Reaching the end of the input after at least one record is how we stop.
+            if err != STATUS_CMD_ERROR || timed_out || row == 0 {
+                clear_vars(parser);
+                return Err(err);
+            }
+            break;
+        }
+        row += 1;
+
+        let fields = split_record(&buff, delimiter, opts.delimiter.is_none(), vars.len());
+        for (col, (field, field_type)) in fields.into_iter().zip(&field_types).enumerate() {
+            let value = match field_type {
+                None => field,
+                Some(field_type) => match convert_field(&field, *field_type) {
+                    Some(value) => value,
+                    None => {
+                        let err = match field_type {
+                            FieldType::Int => wgettext!("invalid integer"),
+                            FieldType::Float => wgettext!("invalid number"),
+                        };
+                        err_fmt!(
+                            "row %d, column %d (%s): %s: %s",
+                            row,
+                            col + 1,
+                            vars[col],
+                            field,
+                            err
+                        )
+                        .cmd(cmd)
+                        .finish(streams);
+                        clear_vars(parser);
+                        return Err(STATUS_CMD_ERROR);
+                    }
+                },
+            };
+            columns[col].push(value);
+        }
+    }
+
+    for (var, column) in vars.iter().zip(columns) {
+        parser.set_var_and_fire(var, opts.place, column);
+    }
+    Ok(SUCCESS)
+}
+
 /// Validate the arguments given to `read` and provide defaults where needed.
 fn validate_read_args(
     cmd: &wstr,
@@ -489,6 +760,38 @@ fn validate_read_args(
         return Err(STATUS_INVALID_ARGS);
     }
 
+    if opts.fields {
+        let conflict = if opts.array {
+            Some(L!("--list"))
+        } else if opts.one_line {
+            Some(L!("--line"))
+        } else if opts.nchars.is_some() {
+            Some(L!("--nchars"))
+        } else {
+            opts.token_mode.map(tokenize_flag)
+        };
+        if let Some(conflict) = conflict {
+            err_fmt!(OPTIONS_CANNOT_BE_COMBINED, "--fields", conflict)
+                .cmd(cmd)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        if argv.is_empty() {
+            err_fmt!(Error::MIN_ARG_COUNT, 1, 0)
+                .cmd(cmd)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+    } else if !opts.field_types.is_empty() {
+        err_fmt!(
+            Error::INVALID_OPT_COMBO_WITH_CTX,
+            wgettext!("--int and --float require --fields")
+        )
+        .cmd(cmd)
+        .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
     if opts.array && argv.len() != 1 {
         err_fmt!(Error::UNEXP_ARG_COUNT, 1, argv.len())
             .cmd(cmd)
@@ -538,6 +841,14 @@ fn validate_read_args(
             return Err(STATUS_INVALID_ARGS);
         }
     }
+    for (var, _) in &opts.field_types {
+        if !argv.contains(&&var[..]) {
+            err_fmt!("%s: not one of the variables to read", var)
+                .cmd(cmd)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+    }
 
     Ok(SUCCESS)
 }
@@ -582,7 +893,13 @@ pub fn read(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
         }
     };
 
-    let stream_stdin_is_a_tty = streams.stdin_fd() >= 0 && isatty(streams.stdin_fd());
+    let stdin_is_a_tty = streams.stdin_fd() >= 0 && isatty(streams.stdin_fd());
+    // The timeout covers all of the input, not each line or character.
+    let deadline = opts.timeout.map(|timeout| Instant::now() + timeout);
+
+    if opts.fields {
+        return read_fields(parser, streams, cmd, &opts, argv, stdin_is_a_tty, deadline);
+    }
 
     // Normally, we either consume a line of input or all available input. But if we are reading a
     // line at a time, we need a middle ground where we only consume as many lines as we need to
@@ -590,49 +907,7 @@ pub fn read(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) ->
     loop {
         buff.clear();
 
-        if stream_stdin_is_a_tty && !opts.split_null {
-            // Read interactively using reader_readline(). This does not support splitting on null.
-            exit_res = read_interactive(
-                parser,
-                &mut buff,
-                opts.nchars,
-                opts.shell,
-                opts.silent,
-                opts.prompt.as_ref().unwrap(),
-                opts.prompt_str.as_ref().is_some_and(|ps| ps.is_empty()),
-                &opts.right_prompt,
-                opts.commandline.as_ref(),
-                streams.stdin_fd(),
-            );
-        } else if opts.nchars.is_none() && !stream_stdin_is_a_tty &&
-            // "one_line" is implemented as reading n-times to a new line,
-            // if we're chunking we could get multiple lines so we would have to advance
-            // more than 1 per run through the loop. Let's skip that for now.
-            !opts.one_line &&
-            (streams.stdin_is_directly_redirected ||
-                    unsafe {libc::lseek(streams.stdin_fd(), 0, SEEK_CUR)} != -1)
-        {
-            // We read in chunks when we either can seek (so we put the bytes back),
-            // or we have the bytes to ourselves (because it's directly redirected).
-            //
-            // Note we skip seeking back even if we're directly redirected to a seekable stream,
-            // under the assumption that the stream will be closed soon anyway.
-            // You don't rewind VHS tapes before throwing them in the trash.
-            // TODO: Do this when nchars is set by seeking back.
-            exit_res = read_in_chunks(
-                streams.stdin_fd(),
-                &mut buff,
-                opts.split_null,
-                !streams.stdin_is_directly_redirected,
-            );
-        } else {
-            exit_res = read_one_char_at_a_time(
-                streams.stdin_fd(),
-                &mut buff,
-                opts.nchars,
-                opts.split_null,
-            );
-        }
+        exit_res = read_record(parser, streams, &opts, &mut buff, stdin_is_a_tty, deadline);
 
         if exit_res.is_err() {
             clear_remaining_vars(parser, &mut var_ptr);
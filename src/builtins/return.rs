@@ -9,25 +9,42 @@ use super::prelude::*;
 #[derive(Debug, Clone, Copy, Default)]
 struct Options {
     print_help: bool,
+    values: bool,
 }
 
 fn parse_options(
     args: &mut [&wstr],
     parser: &Parser,
     streams: &mut IoStreams,
+    allow_values: bool,
 ) -> ControlFlow<ErrorCode, (Options, usize)> {
     let cmd = args[0];
 
-    const SHORT_OPTS: &wstr = L!("h");
-    const LONG_OPTS: &[WOption] = &[wopt(L!("help"), ArgType::NoArgument, 'h')];
+    const SHORT_OPTS: &wstr = L!("hv");
+    const LONG_OPTS: &[WOption] = &[
+        wopt(L!("help"), ArgType::NoArgument, 'h'),
+        wopt(L!("value"), ArgType::NoArgument, 'v'),
+    ];
+    // `exit` shares this parser but has no use for --value.
+    const EXIT_SHORT_OPTS: &wstr = L!("h");
+    const EXIT_LONG_OPTS: &[WOption] = &[wopt(L!("help"), ArgType::NoArgument, 'h')];
 
     let mut opts = Options::default();
 
-    let mut w = WGetopter::new(SHORT_OPTS, LONG_OPTS, args);
+    let mut w = if allow_values {
+        WGetopter::new(SHORT_OPTS, LONG_OPTS, args)
+    } else {
+        WGetopter::new(EXIT_SHORT_OPTS, EXIT_LONG_OPTS, args)
+    };
 
     while let Some(c) = w.next_opt() {
         match c {
             'h' => opts.print_help = true,
+            'v' => {
+                // Everything after --value is a value, even if it looks like an option.
+                opts.values = true;
+                return ControlFlow::Continue((opts, w.wopt_index));
+            }
             ':' => {
                 builtin_missing_argument(parser, streams, cmd, None, args[w.wopt_index - 1], true);
                 return ControlFlow::Break(STATUS_INVALID_ARGS);
@@ -53,9 +70,19 @@ fn parse_options(
 
 /// Function for handling the return builtin.
 pub fn r#return(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) -> BuiltinResult {
-    let mut retval = match parse_return_value(args, parser, streams) {
+    let (opts, optind) = match parse_options(args, parser, streams, true) {
         ControlFlow::Continue(r) => r,
-        ControlFlow::Break(result) => return result,
+        ControlFlow::Break(err) => return Err(err),
+    };
+
+    let mut retval = if opts.values && !opts.print_help {
+        // Returning values always succeeds.
+        0
+    } else {
+        match status_from_args(args, opts, optind, parser, streams) {
+            ControlFlow::Continue(r) => r,
+            ControlFlow::Break(result) => return result,
+        }
     };
 
     let has_function_block = parser.blocks_iter_rev().any(|b| b.is_function_call());
@@ -72,6 +99,16 @@ pub fn r#return(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]
 
     let retval = BuiltinResult::from_dynamic(retval);
 
+    if opts.values {
+        let values: Vec<WString> = args[optind..].iter().map(|&s| s.to_owned()).collect();
+        if has_function_block {
+            // Handed to the caller once the function is done.
+            parser.libdata_mut().return_value = Some(values);
+        } else {
+            parser.vars().set_last_return_value(values);
+        }
+    }
+
     // If we're not in a function, exit the current script (but not an interactive shell).
     if !has_function_block {
         if !parser.scope().is_interactive {
@@ -91,9 +128,19 @@ pub fn parse_return_value(
     parser: &mut Parser,
     streams: &mut IoStreams,
 ) -> ControlFlow<BuiltinResult, i32> {
-    let cmd = args[0];
-    let (opts, optind) = parse_options(args, parser, streams).map_break(BuiltinResult::Err)?;
+    let (opts, optind) =
+        parse_options(args, parser, streams, false).map_break(BuiltinResult::Err)?;
+    status_from_args(args, opts, optind, parser, streams)
+}
 
+fn status_from_args(
+    args: &[&wstr],
+    opts: Options,
+    optind: usize,
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+) -> ControlFlow<BuiltinResult, i32> {
+    let cmd = args[0];
     if opts.print_help {
         builtin_print_help(parser, streams, cmd);
         return ControlFlow::Break(Ok(SUCCESS));
@@ -34,6 +34,12 @@ struct Options {
     prepend: bool,
     preserve_failure_exit_status: bool,
     no_event: bool,
+    map: bool,
+    keys: bool,
+    sorted: bool,
+    unsorted: bool,
+    unique: bool,
+    nonunique: bool,
 }
 
 impl Default for Options {
@@ -57,6 +63,12 @@ impl Default for Options {
             prepend: false,
             preserve_failure_exit_status: true,
             no_event: false,
+            map: false,
+            keys: false,
+            sorted: false,
+            unsorted: false,
+            unique: false,
+            nonunique: false,
         }
     }
 }
@@ -73,6 +85,10 @@ impl Options {
             (self.universal, EnvMode::UNIVERSAL),
             (self.pathvar, EnvMode::PATHVAR),
             (self.unpathvar, EnvMode::UNPATHVAR),
+            (self.sorted, EnvMode::SORTED),
+            (self.unsorted, EnvMode::UNSORTED),
+            (self.unique, EnvMode::UNIQUE),
+            (self.nonunique, EnvMode::NONUNIQUE),
         ] {
             if is_mode {
                 scope |= mode;
@@ -91,10 +107,17 @@ impl Options {
         const PATH_ARG: char = 1 as char;
         const UNPATH_ARG: char = 2 as char;
         const NO_EVENT_ARG: char = 3 as char;
+        const MAP_ARG: char = 4 as char;
+        const KEYS_ARG: char = 5 as char;
+        const SORTED_ARG: char = 6 as char;
+        const UNSORTED_ARG: char = 7 as char;
+        const UNIQUE_ARG: char = 8 as char;
+        const NONUNIQUE_ARG: char = 9 as char;
         // Variables used for parsing the argument list. This command is atypical in using the "+"
         // (REQUIRE_ORDER) option for flag parsing. This is not typical of most fish commands. It means
         // we stop scanning for flags when the first non-flag argument is seen.
-        const SHORT_OPTS: &wstr = L!("+LSUaefghlnpqux");
+        // `-s` used to be an unambiguous abbreviation of --show, before --sorted was added.
+        const SHORT_OPTS: &wstr = L!("+LSUaefghlnpqsux");
         const LONG_OPTS: &[WOption] = &[
             wopt(L!("export"), NoArgument, 'x'),
             wopt(L!("global"), NoArgument, 'g'),
@@ -112,6 +135,12 @@ impl Options {
             wopt(L!("path"), NoArgument, PATH_ARG),
             wopt(L!("unpath"), NoArgument, UNPATH_ARG),
             wopt(L!("no-event"), NoArgument, NO_EVENT_ARG),
+            wopt(L!("map"), NoArgument, MAP_ARG),
+            wopt(L!("keys"), NoArgument, KEYS_ARG),
+            wopt(L!("sorted"), NoArgument, SORTED_ARG),
+            wopt(L!("unsorted"), NoArgument, UNSORTED_ARG),
+            wopt(L!("unique"), NoArgument, UNIQUE_ARG),
+            wopt(L!("nonunique"), NoArgument, NONUNIQUE_ARG),
             wopt(L!("help"), NoArgument, 'h'),
         ];
 
@@ -143,9 +172,18 @@ impl Options {
                 PATH_ARG => opts.pathvar = true,
                 UNPATH_ARG => opts.unpathvar = true,
                 NO_EVENT_ARG => opts.no_event = true,
+                MAP_ARG => opts.map = true,
+                KEYS_ARG => {
+                    opts.keys = true;
+                    opts.preserve_failure_exit_status = false;
+                }
+                SORTED_ARG => opts.sorted = true,
+                UNSORTED_ARG => opts.unsorted = true,
+                UNIQUE_ARG => opts.unique = true,
+                NONUNIQUE_ARG => opts.nonunique = true,
                 'U' => opts.universal = true,
                 'L' => opts.shorten_ok = false,
-                'S' => {
+                'S' | 's' => {
                     opts.show = true;
                     opts.preserve_failure_exit_status = false;
                 }
@@ -276,6 +314,15 @@ impl Options {
             return Err(STATUS_INVALID_ARGS);
         }
 
+        // Variables are either kept sorted or not, and either unique or not.
+        if (opts.sorted && opts.unsorted) || (opts.unique && opts.nonunique) {
+            err_str!(Error::INVALID_OPT_COMBO)
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+
         // Trying to erase and (un)export at the same time doesn't make sense.
         if opts.erase && (opts.exportv || opts.unexport) {
             err_str!(Error::INVALID_OPT_COMBO)
@@ -302,6 +349,33 @@ impl Options {
             return Err(STATUS_INVALID_ARGS);
         }
 
+        // --map and --keys are modes of their own.
+        if (opts.map || opts.keys)
+            && (opts.erase
+                || opts.list
+                || opts.query
+                || opts.show
+                || opts.append
+                || opts.prepend
+                || (opts.map && opts.keys)
+                || (opts.map && (opts.pathvar || opts.sorted || opts.unique)))
+        {
+            err_str!(Error::INVALID_OPT_COMBO)
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+
+        // Universal variables can only store lists.
+        if opts.map && opts.universal {
+            err_str!("maps cannot be universal")
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+
         if args.len() == optind && opts.erase {
             err_fmt!(Error::MISSING_OPT_ARG, L!("--erase"))
                 .cmd(cmd)
@@ -369,17 +443,26 @@ fn handle_env_return(retval: EnvStackSetResult, cmd: &wstr, key: &wstr, streams:
 
 /// Call vars.set. If this is a path variable, e.g. PATH, validate the elements. On error, print a
 /// description of the problem to stderr.
+/// If `map_keys` is given, the variable is set to a map with these keys.
+#[allow(clippy::too_many_arguments)]
 fn env_set_reporting_errors(
     cmd: &wstr,
     opts: &Options,
     key: &wstr,
     mode: EnvMode,
+    map_keys: Option<Vec<WString>>,
     list: Vec<WString>,
     streams: &mut IoStreams,
     parser: &mut Parser,
 ) -> EnvStackSetResult {
     let mode = ParserEnvSetMode::user(mode);
-    let retval = if opts.no_event {
+    let retval = if let Some(map_keys) = map_keys {
+        let retval = parser.set_map_var(key, mode, map_keys, list);
+        if retval == EnvStackSetResult::Ok && !opts.no_event {
+            event::fire(parser, Event::variable_set(key.to_owned()));
+        }
+        retval
+    } else if opts.no_event {
         parser.set_var(key, mode, list)
     } else {
         parser.set_var_and_fire(key, mode, list)
@@ -414,6 +497,8 @@ struct SplitVar<'a> {
     varname: &'a wstr,
     var: Option<EnvVar>,
     indexes: Vec<isize>,
+    /// The keys given in brackets, if the variable is a map.
+    keys: Vec<WString>,
 }
 
 impl<'a> SplitVar<'a> {
@@ -460,6 +545,23 @@ fn split_var_and_indexes_internal<'a>(
         return Ok(res);
     };
 
+    // Maps are indexed by whitespace-separated keys.
+    if res.var.as_ref().is_some_and(EnvVar::is_map) {
+        let c = arg.slice_from(open_bracket + 1);
+        let Some(close_bracket) = c.find_char(']') else {
+            return Err(EnvArrayParseError::InvalidIndex(c.to_owned()));
+        };
+        let mut key = WString::new();
+        for c in c[..close_bracket].chars().chain([' ']) {
+            if !c.is_whitespace() {
+                key.push(c);
+            } else if !key.is_empty() {
+                res.keys.push(std::mem::take(&mut key));
+            }
+        }
+        return Ok(res);
+    }
+
     // We need the length of the array to validate the indexes.
     let len = res
         .var
@@ -554,6 +656,34 @@ fn erased_at_indexes(mut input: Vec<WString>, mut indexes: Vec<isize>) -> Vec<WS
     input
 }
 
+/// Return whether a string can be used as the key of a map. Keys are separated by whitespace when
+/// indexing, so they can't contain whitespace or brackets.
+fn valid_map_key(key: &wstr) -> bool {
+    !key.is_empty()
+        && !key
+            .chars()
+            .any(|c| c.is_whitespace() || c == '[' || c == ']')
+}
+
+/// Given a map's keys and values, return them with `new_keys` set to `new_values`. Existing keys
+/// keep their position, new keys are added at the end.
+fn updated_map(
+    mut keys: Vec<WString>,
+    mut values: Vec<WString>,
+    new_keys: &[WString],
+    new_values: &[&wstr],
+) -> (Vec<WString>, Vec<WString>) {
+    for (key, value) in new_keys.iter().zip(new_values) {
+        if let Some(pos) = keys.iter().position(|k| k == key) {
+            values[pos] = (*value).to_owned();
+        } else {
+            keys.push(key.clone());
+            values.push((*value).to_owned());
+        }
+    }
+    (keys, values)
+}
+
 /// Print the names of all environment variables in the scope. It will include the values unless the
 /// `set --names` flag was used.
 fn list(opts: &Options, parser: &Parser, streams: &mut IoStreams) -> BuiltinResult {
@@ -624,7 +754,14 @@ fn query(
             return Err(STATUS_CMD_ERROR);
         };
 
-        if split.indexes.is_empty() {
+        if let Some(var) = split.var.as_ref().filter(|_| !split.keys.is_empty()) {
+            // Increment for every key not in the map.
+            for key in &split.keys {
+                if var.get_by_key(key).is_none() {
+                    retval += 1;
+                }
+            }
+        } else if split.indexes.is_empty() {
             // No indexes, just increment if our variable is missing.
             if split.var.is_none() {
                 retval += 1;
@@ -659,7 +796,11 @@ fn show_scope(var_name: &wstr, scope: EnvMode, streams: &mut IoStreams, vars: &d
     } else {
         wgettext!("unexported")
     };
-    let pathvarv = if var.is_pathvar() {
+    let pathvarv = if var.is_map() {
+        let mut pathvarv = L!(" ").to_owned();
+        pathvarv.push_utfstr(wgettext!("a map"));
+        pathvarv
+    } else if var.is_pathvar() {
         let mut pathvarv = L!(" ").to_owned();
         pathvarv.push_utfstr(wgettext!("a path variable"));
         pathvarv
@@ -675,6 +816,14 @@ fn show_scope(var_name: &wstr, scope: EnvMode, streams: &mut IoStreams, vars: &d
         pathvarv,
         vals.len()
     ));
+    if var.is_sorted() {
+        streams.out.append(L!(", "));
+        streams.out.append(wgettext!("kept sorted"));
+    }
+    if var.is_unique() {
+        streams.out.append(L!(", "));
+        streams.out.append(wgettext!("kept unique"));
+    }
     // HACK: PWD can be set, depending on how you ask.
     // For our purposes it's read-only.
     if EnvVar::flags_for(var_name).contains(EnvVarFlags::READ_ONLY) {
@@ -700,9 +849,22 @@ fn show_scope(var_name: &wstr, scope: EnvMode, streams: &mut IoStreams, vars: &d
             value,
             EscapeStringStyle::Script(EscapeFlags::NO_PRINTABLES | EscapeFlags::NO_QUOTED),
         );
-        streams
-            .out
-            .append(&sprintf!("$%s[%d]: |%s|\n", var_name, i + 1, &escaped_val));
+        if let Some(keys) = var.map_keys() {
+            let escaped_key = escape_string(
+                &keys[i],
+                EscapeStringStyle::Script(EscapeFlags::NO_PRINTABLES | EscapeFlags::NO_QUOTED),
+            );
+            streams.out.append(&sprintf!(
+                "$%s[%s]: |%s|\n",
+                var_name,
+                &escaped_key,
+                &escaped_val
+            ));
+        } else {
+            streams
+                .out
+                .append(&sprintf!("$%s[%d]: |%s|\n", var_name, i + 1, &escaped_val));
+        }
     }
 }
 
@@ -798,7 +960,27 @@ fn erase(
                 return Err(STATUS_INVALID_ARGS);
             }
             let retval;
-            if split.indexes.is_empty() {
+            if let Some(var) = split.var.as_ref().filter(|_| !split.keys.is_empty()) {
+                // remove just the specified keys of the map
+                let (keys, values) = var
+                    .map_keys()
+                    .unwrap()
+                    .iter()
+                    .zip(var.as_list())
+                    .filter(|(key, _)| !split.keys.contains(key))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .unzip();
+                retval = env_set_reporting_errors(
+                    cmd,
+                    opts,
+                    split.varname,
+                    mode,
+                    Some(keys),
+                    values,
+                    streams,
+                    parser,
+                );
+            } else if split.indexes.is_empty() {
                 // unset the var
                 retval = parser.remove_var(split.varname, ParserEnvSetMode::new(mode));
                 // When a non-existent-variable is unset, return NotFound as $status
@@ -821,6 +1003,7 @@ fn erase(
                     opts,
                     split.varname,
                     mode,
+                    None,
                     result,
                     streams,
                     parser,
@@ -945,6 +1128,95 @@ fn new_var_values_by_index(split: &SplitVar, argv: &[&wstr]) -> Vec<WString> {
     result
 }
 
+/// Set a variable to a map, given alternating keys and values.
+fn set_map(
+    cmd: &wstr,
+    opts: &Options,
+    parser: &mut Parser,
+    streams: &mut IoStreams,
+    split: &SplitVar,
+    argv: &[&wstr],
+) -> BuiltinResult {
+    if !split.indexes.is_empty() || !split.keys.is_empty() {
+        err_str!("Cannot use --map when assigning to a slice")
+            .cmd(cmd)
+            .full_trailer(parser)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+    if argv.len() % 2 != 0 {
+        err_fmt!("expected key-value pairs; got %d arguments", argv.len())
+            .cmd(cmd)
+            .full_trailer(parser)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
+    let mut new_keys = vec![];
+    let mut new_values = vec![];
+    for pair in argv.chunks_exact(2) {
+        if !valid_map_key(pair[0]) {
+            err_fmt!("%s: invalid map key", pair[0])
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        new_keys.push(pair[0].to_owned());
+        new_values.push(pair[1]);
+    }
+    // Later values for the same key win.
+    let (keys, values) = updated_map(vec![], vec![], &new_keys, &new_values);
+
+    env_set_reporting_errors(
+        cmd,
+        opts,
+        split.varname,
+        opts.env_mode(),
+        Some(keys),
+        values,
+        streams,
+        parser,
+    )
+    .into()
+}
+
+/// Print the keys of the given maps, or the indexes of the given lists.
+fn keys(
+    cmd: &wstr,
+    opts: &Options,
+    parser: &Parser,
+    streams: &mut IoStreams,
+    args: &[&wstr],
+) -> BuiltinResult {
+    // No variables given, this is an error.
+    if args.is_empty() {
+        return Err(STATUS_NO_VARIABLES_GIVEN);
+    }
+
+    let mut retval = Ok(SUCCESS);
+    for arg in args.iter().copied() {
+        if !valid_var_name(arg) {
+            varname_error(cmd, arg).full_trailer(parser).finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        let Some(var) = parser.vars().getf(arg, opts.env_mode()) else {
+            retval = Err(STATUS_CMD_ERROR);
+            continue;
+        };
+        if let Some(keys) = var.map_keys() {
+            for key in keys.iter() {
+                streams.out.appendln(key);
+            }
+        } else {
+            for i in 1..=var.as_list().len() {
+                streams.out.appendln(&i.to_wstring());
+            }
+        }
+    }
+    retval
+}
+
 /// Set a variable.
 fn set_internal(
     cmd: &wstr,
@@ -988,6 +1260,66 @@ fn set_internal(
         return Err(STATUS_INVALID_ARGS);
     }
 
+    if opts.map {
+        return set_map(cmd, opts, parser, streams, &split, argv);
+    }
+
+    // Setting with keys like `set foo[key] ...` updates or adds entries of a map.
+    if let Some(var) = split.var.as_ref().filter(|_| !split.keys.is_empty()) {
+        if opts.append || opts.prepend {
+            err_str!("Cannot use --append or --prepend when assigning to a slice")
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        if split.keys.len() != argv.len() {
+            err_fmt!("given %d keys but %d values", split.keys.len(), argv.len())
+                .cmd(cmd)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        if let Some(key) = split.keys.iter().find(|key| !valid_map_key(key)) {
+            err_fmt!("%s: invalid map key", key)
+                .cmd(cmd)
+                .full_trailer(parser)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        let (keys, values) = updated_map(
+            var.map_keys().unwrap().to_vec(),
+            var.as_list().to_vec(),
+            &split.keys,
+            argv,
+        );
+        return env_set_reporting_errors(
+            cmd,
+            opts,
+            split.varname,
+            mode,
+            Some(keys),
+            values,
+            streams,
+            parser,
+        )
+        .into();
+    }
+
+    // Maps have no order that appending or prepending could respect.
+    if (opts.append || opts.prepend)
+        && split.indexes.is_empty()
+        && parser
+            .vars()
+            .get(split.varname)
+            .is_some_and(|var| var.is_map())
+    {
+        err_str!("cannot append or prepend to a map")
+            .cmd(cmd)
+            .full_trailer(parser)
+            .finish(streams);
+        return Err(STATUS_INVALID_ARGS);
+    }
+
     // Setting with explicit indexes like `set foo[3] ...` has additional error handling.
     if !split.indexes.is_empty() {
         // Indexes must be > 0. (Note split_var_and_indexes negates negative values).
@@ -1031,8 +1363,16 @@ fn set_internal(
     };
 
     // Set the value back in the variable stack and fire any events.
-    let retval =
-        env_set_reporting_errors(cmd, opts, split.varname, mode, new_values, streams, parser);
+    let retval = env_set_reporting_errors(
+        cmd,
+        opts,
+        split.varname,
+        mode,
+        None,
+        new_values,
+        streams,
+        parser,
+    );
 
     if retval == EnvStackSetResult::Ok {
         warn_if_uvar_shadows_global(cmd, opts, split.varname, streams, parser);
@@ -1059,6 +1399,8 @@ pub fn set(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) ->
         list(&opts, parser, streams)
     } else if opts.show {
         show(cmd, parser, streams, args)
+    } else if opts.keys {
+        keys(cmd, &opts, parser, streams, args)
     } else if args.is_empty() && !(opts.append || opts.prepend) {
         list(&opts, parser, streams)
     } else {
@@ -240,10 +240,22 @@ const BUILTIN_DATAS: &[BuiltinData] = &[
         name: L!("fish_indent"),
         func: fish_indent::fish_indent,
     },
+    BuiltinData {
+        name: L!("fish_json"),
+        func: fish_json::fish_json,
+    },
     BuiltinData {
         name: L!("fish_key_reader"),
         func: fish_key_reader::fish_key_reader,
     },
+    BuiltinData {
+        name: L!("fish_terminal_clipboard"),
+        func: fish_terminal_clipboard::fish_terminal_clipboard,
+    },
+    BuiltinData {
+        name: L!("fish_update_completions"),
+        func: fish_update_completions::fish_update_completions,
+    },
     BuiltinData {
         name: L!("for"),
         func: builtin_generic,
@@ -340,6 +352,10 @@ const BUILTIN_DATAS: &[BuiltinData] = &[
         name: L!("time"),
         func: builtin_generic,
     },
+    BuiltinData {
+        name: L!("trap"),
+        func: trap::trap,
+    },
     BuiltinData {
         name: L!("true"),
         func: r#true::r#true,
@@ -505,7 +521,12 @@ pub fn builtin_get_desc(name: &wstr) -> Option<&'static wstr> {
         _ if name == "exit" => wgettext!("Exit the shell"),
         _ if name == "false" => wgettext!("Return an unsuccessful result"),
         _ if name == "fg" => wgettext!("Send job to foreground"),
+        _ if name == "fish_json" => wgettext!("Read values from JSON or encode lists as JSON"),
         _ if name == "fish_key_reader" => wgettext!("explore what characters keyboard keys send"),
+        _ if name == "fish_terminal_clipboard" => wgettext!("Copy and paste via the terminal"),
+        _ if name == "fish_update_completions" => {
+            wgettext!("Generate completions from manual pages")
+        }
         _ if name == "for" => wgettext!("Perform a set of commands multiple times"),
         _ if name == "function" => wgettext!("Define a new function"),
         _ if name == "functions" => wgettext!("List or remove functions"),
@@ -530,6 +551,7 @@ pub fn builtin_get_desc(name: &wstr) -> Option<&'static wstr> {
         _ if name == "switch" => wgettext!("Conditionally run blocks of code"),
         _ if name == "test" => wgettext!("Test a condition"),
         _ if name == "time" => wgettext!("Measure how long a command or block takes"),
+        _ if name == "trap" => wgettext!("Perform an action when the shell receives a signal"),
         _ if name == "true" => wgettext!("Return a successful result"),
         _ if name == "type" => wgettext!("Check if a thing is a thing"),
         _ if name == "ulimit" => wgettext!("Get/set resource usage limits"),
@@ -867,6 +889,24 @@ impl<'args> Iterator for Arguments<'args, '_> {
     }
 }
 
+/// Quote a string for JSON.
+pub fn json_string(s: &wstr) -> WString {
+    let mut out = WString::from_str("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if u32::from(c) < 0x20 => sprintf!(=> &mut out, "\\u%04x", u32::from(c)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
 pub fn parse_pid(streams: &mut IoStreams, cmd: &wstr, arg: &wstr) -> Result<Pid, ErrorCode> {
     parsed_pid(streams, cmd, arg, fish_wcstoi(arg))
 }
@@ -6,10 +6,12 @@ use crate::err_fmt;
 #[cfg(not(feature = "localize-messages"))]
 use crate::err_raw;
 use crate::proc::{
-    JobControl, get_job_control_mode, get_login, is_interactive_session, set_job_control_mode,
+    CpuTime, JobControl, get_job_control_mode, get_login, is_interactive_session,
+    set_job_control_mode,
 };
 use crate::reader::reader_in_interactive_read;
-use crate::tty_handoff::{TERMINAL_OS_NAME, get_scroll_content_up_capability, xtversion};
+use crate::terminal_capabilities::Capability;
+use crate::tty_handoff::{TERMINAL_OS_NAME, xtversion};
 use crate::wutil::{self, waccess, wbasename, wdirname, wrealpath};
 use cfg_if::cfg_if;
 use fish_feature_flags::{self as features, feature_test};
@@ -17,6 +19,7 @@ use fish_util::wcsfilecmp_glob;
 use fish_widestring::{bytes2wcstring, osstr2wcstring, str2wcstring, wcs2bytes};
 use nix::unistd::AccessFlags;
 use rust_embed::RustEmbed;
+use std::time::Duration;
 
 /// Create an enum with name `$name`.
 /// Its variants are given as comma-separated tuples, with each tuple containing the variant name,
@@ -77,12 +80,14 @@ str_enum!(
     (IsInteractiveRead, "is-interactive-read"),
     (IsLogin, "is-login"),
     (IsNoJobControl, "is-no-job-control"),
+    (LastDuration, "last-duration"),
     (LineNumber, "line-number", "current-line-number"),
     (ListFiles, "list-files"),
     (Language, "language"),
     (JobControl, "job-control"),
     (StackTrace, "stack-trace", "print-stack-trace"),
     (Terminal, "terminal"),
+    (TerminalCapabilities, "terminal-capabilities"),
     (TerminalOs, "terminal-os"),
     (TestFeature, "test-feature"),
     (TestTerminalFeature, "test-terminal-feature"),
@@ -100,6 +105,7 @@ struct Options {
     level: i32,
     new_job_control_mode: Option<JobControl>,
     status_cmd: Option<StatusCmd>,
+    per_process: bool,
     print_help: bool,
 }
 
@@ -129,6 +135,7 @@ impl Default for Options {
             level: 1,
             new_job_control_mode: None,
             status_cmd: None,
+            per_process: false,
             print_help: false,
         }
     }
@@ -140,6 +147,7 @@ const IS_FULL_JOB_CTRL_SHORT: char = '\x02';
 const IS_INTERACTIVE_JOB_CTRL_SHORT: char = '\x03';
 const IS_NO_JOB_CTRL_SHORT: char = '\x04';
 const IS_INTERACTIVE_READ_SHORT: char = '\x05';
+const PER_PROCESS_SHORT: char = '\x06';
 
 const SHORT_OPTIONS: &wstr = L!("L:cbilfnhj:t");
 const LONG_OPTIONS: &[WOption] = &[
@@ -172,6 +180,7 @@ const LONG_OPTIONS: &[WOption] = &[
     wopt(L!("level"), RequiredArgument, 'L'),
     wopt(L!("line"), NoArgument, 'n'),
     wopt(L!("line-number"), NoArgument, 'n'),
+    wopt(L!("per-process"), NoArgument, PER_PROCESS_SHORT),
     wopt(L!("print-stack-trace"), NoArgument, 't'),
 ];
 
@@ -203,6 +212,16 @@ fn print_features(streams: &mut IoStreams) {
     }
 }
 
+/// Format a wall-clock and CPU time as tab-separated seconds.
+fn format_timing(wall: Duration, cpu: CpuTime) -> WString {
+    sprintf!(
+        "%.6f\t%.6f\t%.6f",
+        wall.as_secs_f64(),
+        cpu.user.as_secs_f64(),
+        cpu.sys.as_secs_f64()
+    )
+}
+
 fn parse_cmd_opts(
     opts: &mut Options,
     optind: &mut usize,
@@ -286,6 +305,7 @@ fn parse_cmd_opts(
                     return Err(STATUS_CMD_ERROR);
                 }
             }
+            PER_PROCESS_SHORT => opts.per_process = true,
             'h' => opts.print_help = true,
             ':' => {
                 builtin_missing_argument(parser, streams, cmd, None, args[w.wopt_index - 1], false);
@@ -493,6 +513,17 @@ pub fn status(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
                             crate::localization::unset_from_status_language_builtin(parser.vars());
                             return Ok(SUCCESS);
                         }
+                        "resolve" => {
+                            // Print the language of each message, or an empty line if it is not
+                            // localized.
+                            let mut all_localized = true;
+                            for message in &args[1..] {
+                                let language = crate::localization::message_language(message);
+                                all_localized &= language.is_some();
+                                streams.out.appendln(language.unwrap_or_default());
+                            }
+                            return if all_localized { Ok(SUCCESS) } else { Err(STATUS_CMD_ERROR) };
+                        }
                         invalid => {
                             err_fmt!(builtins::Error::INVALID_SUBSUBCMD,  invalid)
                                 .subcmd(cmd, subcmd.to_wstr())
@@ -543,13 +574,13 @@ pub fn status(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
                     .finish(streams);
                 return Err(STATUS_INVALID_ARGS);
             }
-            if args[0] != "scroll-content-up" {
+            let Some(capability) = Capability::from_name(args[0]) else {
                 err_fmt!("unrecognized feature '%s'", args[0])
                     .subcmd(cmd, c.to_wstr())
                     .finish(streams);
                 return Err(STATUS_INVALID_ARGS);
-            }
-            return if get_scroll_content_up_capability() == Some(true) {
+            };
+            return if capability.is_supported() {
                 Ok(SUCCESS)
             } else {
                 Err(STATUS_CMD_ERROR)
@@ -701,6 +732,23 @@ pub fn status(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
                         streams.out.appendln(get_program_name());
                     }
                 }
+                LastDuration => {
+                    let Some(timing) = &parser.libdata().last_job_timing else {
+                        return Err(STATUS_CMD_ERROR);
+                    };
+                    if !opts.per_process {
+                        streams
+                            .out
+                            .appendln(&format_timing(timing.wall, timing.cpu));
+                        return Ok(SUCCESS);
+                    }
+                    for (description, wall, cpu) in &timing.processes {
+                        let mut line = format_timing(*wall, *cpu);
+                        line.push('\t');
+                        line.push_utfstr(description);
+                        streams.out.appendln(&line);
+                    }
+                }
                 CurrentCommandline => {
                     let commandline = &parser.libdata().status_vars.commandline;
                     streams.out.appendln(commandline);
@@ -725,6 +773,13 @@ pub fn status(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
                     let xtversion = xtversion().unwrap_or_default();
                     streams.out.appendln(xtversion);
                 }
+                TerminalCapabilities => {
+                    for capability in Capability::ALL {
+                        if capability.is_supported() {
+                            streams.out.appendln(capability.name());
+                        }
+                    }
+                }
                 TerminalOs => {
                     let Some(Some(terminal_os_name)) = TERMINAL_OS_NAME.get() else {
                         return Err(STATUS_CMD_ERROR);
@@ -4,7 +4,9 @@ use fish_wcstringutil::fish_wcwidth_visible;
 use super::prelude::*;
 
 mod collect;
+pub(crate) mod diff;
 mod escape;
+mod hash;
 mod join;
 mod length;
 mod r#match;
@@ -17,6 +19,8 @@ mod sub;
 mod transform;
 mod trim;
 mod unescape;
+mod unicode;
+mod width;
 
 #[cfg(test)]
 mod test_helpers;
@@ -236,7 +240,8 @@ enum Direction {
     Right,
 }
 
-fn width_without_escapes(ins: &wstr, start_pos: usize) -> usize {
+/// The number of columns `ins` occupies from `start_pos` on, not counting escape sequences.
+pub(crate) fn width_without_escapes(ins: &wstr, start_pos: usize) -> usize {
     let mut width: isize = 0;
     for c in ins[start_pos..].chars() {
         let w = fish_wcwidth_visible(c);
@@ -279,6 +284,21 @@ fn arguments<'iter, 'args>(
     Arguments::new(args, argidx, streams, STRING_CHUNK_SIZE)
 }
 
+/// Print one element of the output. With `null_out`, it is terminated by a NUL instead of a
+/// newline, so that it can be passed on losslessly to e.g. `xargs -0`.
+fn append_element(streams: &mut IoStreams, s: &wstr, null_out: bool, want_newline: bool) {
+    if null_out {
+        let mut output = WString::with_capacity(s.len() + 1);
+        output.push_utfstr(s);
+        output.push('\0');
+        streams.out.append(&output);
+    } else {
+        streams
+            .out
+            .append_with_separation(s, SeparationType::Explicitly, want_newline);
+    }
+}
+
 /// The string builtin, for manipulating strings.
 pub fn string(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) -> BuiltinResult {
     let cmd = args[0];
@@ -301,7 +321,9 @@ pub fn string(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
 
     match subcmd_name.to_string().as_str() {
         "collect" => collect::Collect::default().run(parser, streams, args),
+        "diff" => diff::Diff::default().run(parser, streams, args),
         "escape" => escape::Escape::default().run(parser, streams, args),
+        "hash" => hash::Hash::default().run(parser, streams, args),
         "join" => join::Join::default().run(parser, streams, args),
         "join0" => {
             let mut cmd = join::Join::default();
@@ -328,11 +350,13 @@ pub fn string(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
         "sub" => sub::Sub::default().run(parser, streams, args),
         "trim" => trim::Trim::default().run(parser, streams, args),
         "unescape" => unescape::Unescape::default().run(parser, streams, args),
+        "unicode" => unicode::Unicode::default().run(parser, streams, args),
         "upper" => transform::Transform {
             quiet: false,
             func: wstr::to_uppercase,
         }
         .run(parser, streams, args),
+        "width" => width::Width::default().run(parser, streams, args),
         _ => {
             err_fmt!(Error::INVALID_SUBCMD)
                 .subcmd(cmd, subcmd_name)
@@ -4,19 +4,25 @@ use super::*;
 pub struct Collect {
     allow_empty: bool,
     no_trim_newlines: bool,
+    null_in: bool,
+    null_out: bool,
 }
 
 impl StringSubCommand<'_> for Collect {
     const LONG_OPTIONS: &'static [WOption<'static>] = &[
         wopt(L!("allow-empty"), NoArgument, 'a'),
         wopt(L!("no-trim-newlines"), NoArgument, 'N'),
+        wopt(L!("null-in"), NoArgument, 'z'),
+        wopt(L!("null-out"), NoArgument, 'Z'),
     ];
-    const SHORT_OPTIONS: &'static wstr = L!("Na");
+    const SHORT_OPTIONS: &'static wstr = L!("NazZ");
 
     fn parse_opt(&mut self, c: char, _arg: Option<&wstr>) -> Result<(), StringError<'_>> {
         match c {
             'a' => self.allow_empty = true,
             'N' => self.no_trim_newlines = true,
+            'z' => self.null_in = true,
+            'Z' => self.null_out = true,
             _ => return Err(StringError::UnknownOption),
         }
         Ok(())
@@ -31,8 +37,13 @@ impl StringSubCommand<'_> for Collect {
     ) -> Result<(), ErrorCode> {
         let mut appended = 0usize;
 
+        // With --null-in, each NUL-terminated record is collected separately.
+        let split_behavior = match self.null_in {
+            true => SplitBehavior::Null,
+            false => SplitBehavior::Never,
+        };
         for InputValue { arg, want_newline } in
-            arguments(args, optind, streams).with_split_behavior(SplitBehavior::Never)
+            arguments(args, optind, streams).with_split_behavior(split_behavior)
         {
             let arg = if !self.no_trim_newlines {
                 let trim_len = arg.len() - arg.chars().rev().take_while(|&c| c == '\n').count();
@@ -41,9 +52,8 @@ impl StringSubCommand<'_> for Collect {
                 &arg
             };
 
-            streams
-                .out
-                .append_with_separation(arg, SeparationType::Explicitly, want_newline);
+            // NUL-terminated input says nothing about whether a newline is wanted.
+            append_element(streams, arg, self.null_out, want_newline || self.null_in);
             appended += arg.len();
         }
 
@@ -52,9 +62,10 @@ impl StringSubCommand<'_> for Collect {
         // echo (true | string collect --allow-empty)"bar"
         // prints "bar".
         if self.allow_empty && appended == 0 {
-            streams.out.append_with_separation(
+            append_element(
+                streams,
                 L!(""),
-                SeparationType::Explicitly,
+                self.null_out,
                 true, /* historical behavior is to always print a newline */
             );
         }
@@ -0,0 +1,460 @@
+use std::ffi::OsStr;
+use std::os::unix::ffi::OsStrExt as _;
+
+use super::*;
+use crate::{
+    builtins::{COLOR_OPTION_CHAR, ColorEnabled},
+    terminal::Outputter,
+    text_face::{TextFace, TextStyling},
+};
+use fish_color::Color;
+use fish_widestring::{bytes2wcstring, wcs2bytes};
+
+pub struct Diff<'args> {
+    words: bool,
+    files: bool,
+    quiet: bool,
+    context: usize,
+    color: Option<&'args wstr>,
+    old: &'args wstr,
+    new: &'args wstr,
+}
+
+impl Default for Diff<'_> {
+    fn default() -> Self {
+        Self {
+            words: false,
+            files: false,
+            quiet: false,
+            context: 3,
+            color: None,
+            old: L!(""),
+            new: L!(""),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Edit {
+    Keep,
+    Delete,
+    Insert,
+}
+
+/// The shortest sequence of edits turning `old` into `new`, using Myers' algorithm.
+pub(crate) fn edit_script<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    // The common prefix and suffix are kept as-is, which makes the search below a lot cheaper
+    // for the typical case of a few changes in a long text.
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+
+    let n = old_mid.len() as isize;
+    let m = new_mid.len() as isize;
+    let offset = n + m + 1;
+    // v[k + offset] is the furthest x reached on diagonal k.
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // The relevant part of v, i.e. diagonals -d..=d, before each round d.
+    let mut trace: Vec<Vec<isize>> = vec![];
+    'search: for d in 0..=n + m {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old_mid[x as usize] == new_mid[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut script = vec![Edit::Keep; suffix];
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let (prev_x, prev_y) = if d == 0 {
+            (0, 0)
+        } else {
+            let prev_x = at(prev_k);
+            (prev_x, prev_x - prev_k)
+        };
+        while x > prev_x && y > prev_y {
+            script.push(Edit::Keep);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            script.push(if x == prev_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    script.extend(std::iter::repeat_n(Edit::Keep, prefix));
+    script.reverse();
+    script
+}
+
+/// Split text into lines. A trailing newline does not start another line.
+pub(crate) fn lines(text: &wstr) -> Vec<&wstr> {
+    if text.is_empty() {
+        return vec![];
+    }
+    let text = match text.as_char_slice().last() {
+        Some('\n') => &text[..text.len() - 1],
+        _ => text,
+    };
+    text.split('\n').collect()
+}
+
+/// Split text into words and the whitespace between them, so that joining the result yields the
+/// text again.
+fn words(text: &wstr) -> Vec<&wstr> {
+    let mut result = vec![];
+    let mut start = 0;
+    let chars = text.as_char_slice();
+    for i in 1..=chars.len() {
+        if i == chars.len() || chars[i].is_whitespace() != chars[i - 1].is_whitespace() {
+            result.push(&text[start..i]);
+            start = i;
+        }
+    }
+    result
+}
+
+/// Format the range of a hunk like `diff -u`: the count is omitted if it is 1, and an empty range
+/// starts at the line before it.
+fn hunk_range(start: usize, count: usize) -> WString {
+    match count {
+        0 => sprintf!("%u,0", start),
+        1 => sprintf!("%u", start + 1),
+        _ => sprintf!("%u,%u", start + 1, count),
+    }
+}
+
+struct Painter {
+    outp: Outputter,
+    color: bool,
+}
+
+impl Painter {
+    fn write(&mut self, color: &wstr, text: &wstr) {
+        if self.color && !color.is_empty() {
+            let fg = Color::from_wstr(color).unwrap();
+            self.outp.set_text_face(TextFace::new(
+                fg,
+                Color::None,
+                Color::None,
+                TextStyling::terminal_default(),
+            ));
+            self.outp.write_wstr(text);
+            self.outp.reset_text_face();
+        } else {
+            self.outp.write_wstr(text);
+        }
+    }
+
+    fn writeln(&mut self, color: &wstr, text: &wstr) {
+        self.write(color, text);
+        self.outp.writech('\n');
+    }
+}
+
+impl Diff<'_> {
+    fn unified_diff(&self, painter: &mut Painter, old: &[&wstr], new: &[&wstr]) {
+        let script = edit_script(old, new);
+        // The position in both texts before each edit.
+        let mut positions = Vec::with_capacity(script.len() + 1);
+        let (mut i, mut j) = (0, 0);
+        for edit in &script {
+            positions.push((i, j));
+            match edit {
+                Edit::Keep => (i, j) = (i + 1, j + 1),
+                Edit::Delete => i += 1,
+                Edit::Insert => j += 1,
+            }
+        }
+        positions.push((i, j));
+
+        let changes: Vec<usize> = (0..script.len())
+            .filter(|&i| script[i] != Edit::Keep)
+            .collect();
+        let mut changes = changes.iter().peekable();
+        while let Some(&first) = changes.next() {
+            // Hunks whose context would overlap are merged.
+            let mut last = first;
+            while let Some(&&next) = changes.peek() {
+                if next - last > 2 * self.context + 1 {
+                    break;
+                }
+                last = next;
+                changes.next();
+            }
+            let start = first.saturating_sub(self.context);
+            let end = (last + 1 + self.context).min(script.len());
+
+            let (old_start, new_start) = positions[start];
+            let (old_end, new_end) = positions[end];
+            painter.writeln(
+                L!("cyan"),
+                &sprintf!(
+                    "@@ -%s +%s @@",
+                    hunk_range(old_start, old_end - old_start),
+                    hunk_range(new_start, new_end - new_start)
+                ),
+            );
+            for (edit, &(i, j)) in script[start..end].iter().zip(&positions[start..end]) {
+                match edit {
+                    Edit::Keep => painter.writeln(L!(""), &(L!(" ").to_owned() + old[i])),
+                    Edit::Delete => painter.writeln(L!("red"), &(L!("-").to_owned() + old[i])),
+                    Edit::Insert => painter.writeln(L!("green"), &(L!("+").to_owned() + new[j])),
+                }
+            }
+        }
+    }
+
+    fn word_diff(&self, painter: &mut Painter, old: &[&wstr], new: &[&wstr]) {
+        let script = edit_script(old, new);
+        let (mut i, mut j) = (0, 0);
+        let mut deleted = WString::new();
+        let mut inserted = WString::new();
+        let mut last = L!("");
+        for edit in script.iter().chain(std::iter::once(&Edit::Keep)) {
+            match edit {
+                Edit::Delete => {
+                    deleted.push_utfstr(old[i]);
+                    i += 1;
+                    continue;
+                }
+                Edit::Insert => {
+                    inserted.push_utfstr(new[j]);
+                    j += 1;
+                    continue;
+                }
+                Edit::Keep => (),
+            }
+            // A run of changes is shown as all deleted words followed by all inserted ones.
+            for (text, color, prefix, suffix) in [
+                (&deleted, L!("red"), L!("[-"), L!("-]")),
+                (&inserted, L!("green"), L!("{+"), L!("+}")),
+            ] {
+                if text.is_empty() {
+                    continue;
+                }
+                // Colors make the markers unnecessary, unless the change is only whitespace,
+                // which would not be visible otherwise.
+                if painter.color && !text.chars().all(char::is_whitespace) {
+                    painter.write(color, text);
+                } else {
+                    painter.write(color, &(prefix.to_owned() + text.as_utfstr() + suffix));
+                }
+                last = suffix;
+            }
+            deleted.clear();
+            inserted.clear();
+            if i < old.len() {
+                painter.write(L!(""), old[i]);
+                last = old[i];
+                (i, j) = (i + 1, j + 1);
+            }
+        }
+        if !last.ends_with('\n') {
+            painter.outp.writech('\n');
+        }
+    }
+}
+
+impl<'args> StringSubCommand<'args> for Diff<'args> {
+    const LONG_OPTIONS: &'static [WOption<'static>] = &[
+        wopt(L!("words"), NoArgument, 'w'),
+        wopt(L!("files"), NoArgument, 'f'),
+        wopt(L!("quiet"), NoArgument, 'q'),
+        wopt(L!("unified"), RequiredArgument, 'U'),
+        wopt(L!("color"), RequiredArgument, COLOR_OPTION_CHAR),
+    ];
+    const SHORT_OPTIONS: &'static wstr = L!("wfqU:");
+
+    fn parse_opt(&mut self, c: char, arg: Option<&'args wstr>) -> Result<(), StringError<'_>> {
+        match c {
+            'w' => self.words = true,
+            'f' => self.files = true,
+            'q' => self.quiet = true,
+            'U' => {
+                let arg = arg.unwrap();
+                self.context = Self::parse_arg_number(arg)?
+                    .try_into()
+                    .map_err(|_| err_fmt!("Invalid context length value '%s'", arg))?;
+            }
+            COLOR_OPTION_CHAR => self.color = arg,
+            _ => return Err(StringError::UnknownOption),
+        }
+        Ok(())
+    }
+
+    fn take_args(
+        &mut self,
+        optind: &mut usize,
+        args: &[&'args wstr],
+        streams: &mut IoStreams,
+    ) -> Result<(), ErrorCode> {
+        let cmd = L!("string");
+        let subcmd = args[0];
+        let given = args.len() - *optind;
+        if given != 2 {
+            err_fmt!(Error::UNEXP_ARG_COUNT, 2, given)
+                .subcmd(cmd, subcmd)
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+        self.old = args[*optind];
+        self.new = args[*optind + 1];
+        *optind += 2;
+        Ok(())
+    }
+
+    fn handle(
+        &mut self,
+        _parser: &mut Parser,
+        streams: &mut IoStreams,
+        _optind: &mut usize,
+        args: &[&'args wstr],
+    ) -> Result<(), ErrorCode> {
+        let cmd = L!("string");
+        let subcmd = args[0];
+
+        let color = match self.color {
+            Some(arg) => ColorEnabled::parse_from_opt(streams, cmd, arg)?,
+            None => ColorEnabled::default(),
+        };
+
+        let (old, new) = if self.files {
+            let mut contents = [WString::new(), WString::new()];
+            for (path, content) in [self.old, self.new].into_iter().zip(&mut contents) {
+                match std::fs::read(OsStr::from_bytes(&wcs2bytes(path))) {
+                    Ok(bytes) => *content = bytes2wcstring(&bytes),
+                    Err(err) => {
+                        err_fmt!("Opening \"%s\" failed: %s", path, err.to_string())
+                            .subcmd(cmd, subcmd)
+                            .finish(streams);
+                        return Err(STATUS_CMD_ERROR);
+                    }
+                }
+            }
+            let [old, new] = contents;
+            (Cow::Owned(old), Cow::Owned(new))
+        } else {
+            (Cow::Borrowed(self.old), Cow::Borrowed(self.new))
+        };
+
+        if old == new {
+            return Ok(());
+        }
+        if self.quiet {
+            return Err(STATUS_CMD_ERROR);
+        }
+
+        let mut painter = Painter {
+            outp: Outputter::new_buffering(),
+            color: color.enabled(streams),
+        };
+        if self.words {
+            self.word_diff(&mut painter, &words(&old), &words(&new));
+        } else {
+            if self.files {
+                painter.writeln(L!("brblack"), &(L!("--- ").to_owned() + self.old));
+                painter.writeln(L!("brblack"), &(L!("+++ ").to_owned() + self.new));
+            }
+            self.unified_diff(&mut painter, &lines(&old), &lines(&new));
+        }
+        streams.out.append(&bytes2wcstring(painter.outp.contents()));
+
+        // Like diff(1), report a difference as failure, so that `string diff -q` can be used as
+        // a condition.
+        Err(STATUS_CMD_ERROR)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Edit, edit_script, lines, words};
+    use crate::builtins::{STATUS_CMD_ERROR, STATUS_CMD_OK, STATUS_INVALID_ARGS};
+    use crate::tests::prelude::*;
+    use crate::validate;
+    use fish_widestring::prelude::*;
+
+    #[test]
+    fn test_edit_script() {
+        use Edit::*;
+        let script = |old: &str, new: &str| {
+            let old: Vec<char> = old.chars().collect();
+            let new: Vec<char> = new.chars().collect();
+            edit_script(&old, &new)
+        };
+        assert_eq!(script("", ""), []);
+        assert_eq!(script("abc", "abc"), [Keep, Keep, Keep]);
+        assert_eq!(script("", "ab"), [Insert, Insert]);
+        assert_eq!(script("ab", ""), [Delete, Delete]);
+        assert_eq!(script("abc", "axc"), [Keep, Delete, Insert, Keep]);
+        assert_eq!(
+            script("abcabba", "cbabac"),
+            [
+                Delete, Delete, Keep, Insert, Keep, Keep, Delete, Keep, Insert
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(lines(L!("")), Vec::<&wstr>::new());
+        assert_eq!(lines(L!("a\nb\n")), [L!("a"), L!("b")]);
+        assert_eq!(lines(L!("a\n\n")), [L!("a"), L!("")]);
+        assert_eq!(
+            words(L!("a  b\nc")),
+            [L!("a"), L!("  "), L!("b"), L!("\n"), L!("c")]
+        );
+    }
+
+    #[test]
+    #[serial]
+    #[rustfmt::skip]
+    fn plain() {
+        test_init();
+        validate!(["string", "diff", "a"], STATUS_INVALID_ARGS, "");
+        validate!(["string", "diff", "a\nb", "a\nb"], STATUS_CMD_OK, "");
+        validate!(["string", "diff", "a\nb\nc", "a\nx\nc"], STATUS_CMD_ERROR, "@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n");
+        validate!(["string", "diff", "-U0", "a\nb\nc", "a\nx\nc"], STATUS_CMD_ERROR, "@@ -2 +2 @@\n-b\n+x\n");
+        validate!(["string", "diff", "-U0", "a", "a\nb"], STATUS_CMD_ERROR, "@@ -1,0 +2 @@\n+b\n");
+        validate!(["string", "diff", "-U1", "1\n2\n3\n4\n5\n6\n7", "x\n2\n3\n4\n5\n6\ny"], STATUS_CMD_ERROR,
+                  "@@ -1,2 +1,2 @@\n-1\n+x\n 2\n@@ -6,2 +6,2 @@\n 6\n-7\n+y\n");
+        validate!(["string", "diff", "-U2", "1\n2\n3\n4\n5\n6\n7", "x\n2\n3\n4\n5\n6\ny"], STATUS_CMD_ERROR,
+                  "@@ -1,3 +1,3 @@\n-1\n+x\n 2\n 3\n@@ -5,3 +5,3 @@\n 5\n 6\n-7\n+y\n");
+        validate!(["string", "diff", "-U3", "1\n2\n3\n4\n5\n6\n7", "x\n2\n3\n4\n5\n6\ny"], STATUS_CMD_ERROR,
+                  "@@ -1,7 +1,7 @@\n-1\n+x\n 2\n 3\n 4\n 5\n 6\n-7\n+y\n");
+        validate!(["string", "diff", "-q", "a", "b"], STATUS_CMD_ERROR, "");
+        validate!(["string", "diff", "-w", "the quick fox", "the slow fox"], STATUS_CMD_ERROR, "the [-quick-]{+slow+} fox\n");
+    }
+}
@@ -3,6 +3,8 @@ use super::*;
 pub struct Join<'args> {
     quiet: bool,
     no_empty: bool,
+    null_in: bool,
+    null_out: bool,
     pub is_join0: bool,
     sep: &'args wstr,
 }
@@ -12,6 +14,8 @@ impl Default for Join<'_> {
         Self {
             quiet: false,
             no_empty: false,
+            null_in: false,
+            null_out: false,
             is_join0: false,
             sep: L!("\0"),
         }
@@ -22,13 +26,17 @@ impl<'args> StringSubCommand<'args> for Join<'args> {
     const LONG_OPTIONS: &'static [WOption<'static>] = &[
         wopt(L!("quiet"), NoArgument, 'q'),
         wopt(L!("no-empty"), NoArgument, 'n'),
+        wopt(L!("null-in"), NoArgument, 'z'),
+        wopt(L!("null-out"), NoArgument, 'Z'),
     ];
-    const SHORT_OPTIONS: &'static wstr = L!("qn");
+    const SHORT_OPTIONS: &'static wstr = L!("qnzZ");
 
     fn parse_opt(&mut self, c: char, _arg: Option<&wstr>) -> Result<(), StringError<'_>> {
         match c {
             'q' => self.quiet = true,
             'n' => self.no_empty = true,
+            'z' => self.null_in = true,
+            'Z' => self.null_out = true,
             _ => return Err(StringError::UnknownOption),
         }
         Ok(())
@@ -69,7 +77,13 @@ impl<'args> StringSubCommand<'args> for Join<'args> {
         let sep = self.sep;
         let mut nargs = 0usize;
         let mut print_trailing_newline = true;
-        for InputValue { arg, want_newline } in arguments(args, optind, streams) {
+        let split_behavior = match self.null_in {
+            true => SplitBehavior::Null,
+            false => SplitBehavior::Newline,
+        };
+        for InputValue { arg, want_newline } in
+            arguments(args, optind, streams).with_split_behavior(split_behavior)
+        {
             if !self.quiet {
                 if self.no_empty && arg.is_empty() {
                     continue;
@@ -84,11 +98,12 @@ impl<'args> StringSubCommand<'args> for Join<'args> {
                 return Ok(());
             }
             nargs += 1;
-            print_trailing_newline = want_newline;
+            // NUL-terminated input says nothing about whether a newline is wanted.
+            print_trailing_newline = want_newline || self.null_in;
         }
 
         if nargs > 0 && !self.quiet {
-            if self.is_join0 {
+            if self.is_join0 || self.null_out {
                 streams.out.append('\0');
             } else if print_trailing_newline {
                 streams.out.append('\n');
@@ -125,5 +140,6 @@ mod tests {
         validate!(["string", "join", "-q"], STATUS_INVALID_ARGS, "");
         validate!(["string", "join", "-q", "."], STATUS_CMD_ERROR, "");
         validate!(["string", "join", "-q", ".", "."], STATUS_CMD_ERROR, "");
+        validate!(["string", "join", "-Z", ",", "a", "b"], STATUS_CMD_OK, "a,b\0");
     }
 }
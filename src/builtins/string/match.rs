@@ -4,8 +4,10 @@ use std::num::NonZeroUsize;
 
 use super::*;
 use crate::{
+    common::valid_var_name,
     env::{EnvVar, EnvVarFlags},
     flog::flog,
+    parse_execution::varname_error,
     parse_util::unescape_wildcards,
     parser::ParserEnvSetMode,
     wildcard::wildcard_match,
@@ -24,6 +26,7 @@ pub struct Match<'args> {
     index: bool,
     pattern: &'args wstr,
     max_matches: Option<NonZeroUsize>,
+    export_prefix: Option<&'args wstr>,
 }
 
 impl<'args> StringSubCommand<'args> for Match<'args> {
@@ -37,10 +40,11 @@ impl<'args> StringSubCommand<'args> for Match<'args> {
         wopt(L!("regex"), NoArgument, 'r'),
         wopt(L!("index"), NoArgument, 'n'),
         wopt(L!("max-matches"), RequiredArgument, 'm'),
+        wopt(L!("export-prefix"), RequiredArgument, '\x01'),
     ];
     const SHORT_OPTIONS: &'static wstr = L!("aegivqrnm:");
 
-    fn parse_opt(&mut self, c: char, arg: Option<&wstr>) -> Result<(), StringError<'_>> {
+    fn parse_opt(&mut self, c: char, arg: Option<&'args wstr>) -> Result<(), StringError<'_>> {
         match c {
             'a' => self.all = true,
             'e' => self.entire = true,
@@ -62,6 +66,7 @@ impl<'args> StringSubCommand<'args> for Match<'args> {
                     Some(max)
                 }
             }
+            '\x01' => self.export_prefix = arg,
             _ => return Err(StringError::UnknownOption),
         }
         Ok(())
@@ -126,6 +131,24 @@ impl<'args> StringSubCommand<'args> for Match<'args> {
             return Err(STATUS_INVALID_ARGS);
         }
 
+        if let Some(prefix) = self.export_prefix {
+            if !self.regex {
+                err_fmt!(
+                    Error::INVALID_OPT_COMBO_WITH_CTX,
+                    wgettext!("--export-prefix requires --regex")
+                )
+                .subcmd(cmd, subcmd)
+                .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            if !valid_var_name(prefix) {
+                varname_error(cmd, prefix)
+                    .full_trailer(parser)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+        }
+
         let mut matcher = match StringMatcher::new(self.pattern, self) {
             Ok(m) => m,
             Err(e) => {
@@ -153,7 +176,8 @@ impl<'args> StringSubCommand<'args> for Match<'args> {
         }) = matcher
         {
             for (name, vals) in first_match_captures {
-                parser.set_var(&WString::from(name), ParserEnvSetMode::default(), vals);
+                let name = self.var_name(&name);
+                parser.set_var(&name, ParserEnvSetMode::default(), vals);
             }
         }
 
@@ -165,6 +189,15 @@ impl<'args> StringSubCommand<'args> for Match<'args> {
     }
 }
 
+impl Match<'_> {
+    /// The name of the variable which receives the captures of the group called `group`.
+    fn var_name(&self, group: &str) -> WString {
+        let mut name = self.export_prefix.unwrap_or_default().to_owned();
+        name.push_utfstr(&str2wcstring(group));
+        name
+    }
+}
+
 struct RegexMatcher<'opts, 'args> {
     regex: Regex,
     total_matched: usize,
@@ -239,7 +272,7 @@ impl<'opts, 'args> RegexMatcher<'opts, 'args> {
                 error,
             })?;
 
-        Self::validate_capture_group_names(regex.capture_names())?;
+        Self::validate_capture_group_names(regex.capture_names(), opts)?;
 
         let first_match_captures = regex
             .capture_names()
@@ -314,9 +347,10 @@ impl<'opts, 'args> RegexMatcher<'opts, 'args> {
 
     fn validate_capture_group_names(
         capture_group_names: &[Option<String>],
+        opts: &Match<'args>,
     ) -> Result<(), RegexError> {
         for name in capture_group_names.iter().filter_map(|n| n.as_ref()) {
-            let wname = str2wcstring(name);
+            let wname = opts.var_name(name);
             if EnvVar::flags_for(&wname).contains(EnvVarFlags::READ_ONLY) {
                 return Err(RegexError::InvalidCaptureGroupName { name: wname });
             }
@@ -1,15 +1,22 @@
 use super::*;
 use fish_fallback::fish_wcwidth;
+use fish_widestring::{ELLIPSIS_CHAR, decoded_width};
 
-pub struct Pad {
+pub struct Pad<'args> {
     char_to_pad: char,
     pad_char_width: usize,
     pad_from: Direction,
     center: bool,
     width: usize,
+    truncate: bool,
+    ellipsis: &'args wstr,
+    ellipsis_width: usize,
 }
 
-impl Default for Pad {
+/// The character to use where the text has been truncated, in a [`wstr`].
+const ELLIPSIS_WSTR: &wstr = wstr::from_char_slice(&[ELLIPSIS_CHAR]);
+
+impl Default for Pad<'_> {
     fn default() -> Self {
         Self {
             char_to_pad: ' ',
@@ -17,11 +24,49 @@ impl Default for Pad {
             pad_from: Direction::Left,
             center: false,
             width: 0,
+            truncate: false,
+            ellipsis: ELLIPSIS_WSTR,
+            ellipsis_width: decoded_width(ELLIPSIS_WSTR),
         }
     }
 }
 
-impl StringSubCommand<'_> for Pad {
+/// Cut `input` so that, followed by `ellipsis`, it is at most `max_width` wide.
+/// Escape sequences before the cut are kept, since they take up no space.
+fn truncate_to_width(
+    input: &wstr,
+    max_width: usize,
+    ellipsis: &wstr,
+    ellipsis_width: usize,
+) -> WString {
+    // If not even the ellipsis fits, cut without one.
+    let (ellipsis, ellipsis_width) = if ellipsis_width > max_width {
+        (L!(""), 0)
+    } else {
+        (ellipsis, ellipsis_width)
+    };
+    let mut width = 0usize;
+    let mut pos = 0usize;
+    while pos < input.len() {
+        if input.char_at(pos) == '\x1B' {
+            if let Some(len) = escape_code_length(input.slice_from(pos)) {
+                pos += len;
+                continue;
+            }
+        }
+        let w = fish_wcwidth_visible(input.char_at(pos));
+        if w > 0 && width + w as usize + ellipsis_width > max_width {
+            break;
+        }
+        width = width.saturating_add_signed(w);
+        pos += 1;
+    }
+    let mut truncated = input[..pos].to_owned();
+    truncated.push_utfstr(ellipsis);
+    truncated
+}
+
+impl<'args> StringSubCommand<'args> for Pad<'args> {
     const LONG_OPTIONS: &'static [WOption<'static>] = &[
         // Support both spellings: docs use --char, older fish accepted --chars.
         wopt(L!("char"), RequiredArgument, 'c'),
@@ -29,10 +74,12 @@ impl StringSubCommand<'_> for Pad {
         wopt(L!("right"), NoArgument, 'r'),
         wopt(L!("center"), NoArgument, 'C'),
         wopt(L!("width"), RequiredArgument, 'w'),
+        wopt(L!("truncate"), NoArgument, 't'),
+        wopt(L!("ellipsis"), RequiredArgument, '\x01'),
     ];
-    const SHORT_OPTIONS: &'static wstr = L!("c:rCw:");
+    const SHORT_OPTIONS: &'static wstr = L!("c:rCw:t");
 
-    fn parse_opt(&mut self, c: char, arg: Option<&wstr>) -> Result<(), StringError<'_>> {
+    fn parse_opt(&mut self, c: char, arg: Option<&'args wstr>) -> Result<(), StringError<'_>> {
         match c {
             'c' => {
                 let arg = arg.unwrap();
@@ -57,24 +104,50 @@ impl StringSubCommand<'_> for Pad {
                     .map_err(|_| err_fmt!("Invalid width value '%s'", arg))?;
             }
             'C' => self.center = true,
+            't' => self.truncate = true,
+            '\x01' => {
+                self.truncate = true;
+                self.ellipsis = arg.unwrap();
+                self.ellipsis_width = width_without_escapes(self.ellipsis, 0);
+            }
             _ => return Err(StringError::UnknownOption),
         }
         Ok(())
     }
 
-    fn handle<'args>(
+    fn handle(
         &mut self,
         _parser: &mut Parser,
         streams: &mut IoStreams,
         optind: &mut usize,
         args: &[&'args wstr],
     ) -> Result<(), ErrorCode> {
+        if self.truncate && self.width == 0 {
+            err_fmt!(
+                Error::INVALID_OPT_COMBO_WITH_CTX,
+                wgettext!("--truncate requires --width")
+            )
+            .subcmd(L!("string"), args[0])
+            .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        }
+
         let mut max_width = 0usize;
         let mut inputs: Vec<(Cow<'args, wstr>, usize)> = Vec::new();
         let mut print_trailing_newline = true;
 
         for InputValue { arg, want_newline } in arguments(args, optind, streams) {
-            let width = width_without_escapes(&arg, 0);
+            let mut arg = arg;
+            let mut width = width_without_escapes(&arg, 0);
+            if self.truncate && width > self.width {
+                arg = Cow::Owned(truncate_to_width(
+                    &arg,
+                    self.width,
+                    self.ellipsis,
+                    self.ellipsis_width,
+                ));
+                width = width_without_escapes(&arg, 0);
+            }
             max_width = max_width.max(width);
             inputs.push((arg, width));
             print_trailing_newline = want_newline;
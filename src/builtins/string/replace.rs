@@ -1,9 +1,13 @@
 use std::num::NonZeroUsize;
 
-use pcre2::utf32::{Regex, RegexBuilder};
+use pcre2::utf32::{Captures, Regex, RegexBuilder};
 
 use super::*;
+use crate::exec::exec_subshell;
+use crate::function;
+use fish_common::{EscapeFlags, EscapeStringStyle, escape_string};
 use fish_feature_flags::{FeatureFlag, feature_test};
+use fish_wcstringutil::join_strings;
 
 #[derive(Default)]
 pub struct Replace<'args> {
@@ -15,6 +19,7 @@ pub struct Replace<'args> {
     pattern: &'args wstr,
     replacement: &'args wstr,
     max_matches: Option<NonZeroUsize>,
+    function: Option<&'args wstr>,
 }
 
 impl<'args> StringSubCommand<'args> for Replace<'args> {
@@ -25,10 +30,11 @@ impl<'args> StringSubCommand<'args> for Replace<'args> {
         wopt(L!("quiet"), NoArgument, 'q'),
         wopt(L!("regex"), NoArgument, 'r'),
         wopt(L!("max-matches"), RequiredArgument, 'm'),
+        wopt(L!("function"), RequiredArgument, '\x01'),
     ];
     const SHORT_OPTIONS: &'static wstr = L!("afiqrm:");
 
-    fn parse_opt(&mut self, c: char, arg: Option<&wstr>) -> Result<(), StringError<'_>> {
+    fn parse_opt(&mut self, c: char, arg: Option<&'args wstr>) -> Result<(), StringError<'_>> {
         match c {
             'a' => self.all = true,
             'f' => self.filter = true,
@@ -47,6 +53,7 @@ impl<'args> StringSubCommand<'args> for Replace<'args> {
                     Some(max)
                 }
             }
+            '\x01' => self.function = Some(arg.unwrap()),
             _ => return Err(StringError::UnknownOption),
         }
         Ok(())
@@ -67,6 +74,11 @@ impl<'args> StringSubCommand<'args> for Replace<'args> {
             return Err(STATUS_INVALID_ARGS);
         };
         *optind += 1;
+        self.pattern = pattern;
+        // With --function, the replacement is computed for each match.
+        if self.function.is_some() {
+            return Ok(());
+        }
         let Some(replacement) = args.get(*optind).copied() else {
             err_fmt!(Error::UNEXP_ARG_COUNT, 2, 1)
                 .subcmd(cmd, subcmd)
@@ -75,14 +87,13 @@ impl<'args> StringSubCommand<'args> for Replace<'args> {
         };
         *optind += 1;
 
-        self.pattern = pattern;
         self.replacement = replacement;
         Ok(())
     }
 
     fn handle(
         &mut self,
-        _parser: &mut Parser,
+        parser: &mut Parser,
         streams: &mut IoStreams,
         optind: &mut usize,
         args: &[&wstr],
@@ -90,6 +101,24 @@ impl<'args> StringSubCommand<'args> for Replace<'args> {
         let cmd = L!("string");
         let subcmd = args[0];
 
+        if let Some(function) = self.function {
+            if !self.regex {
+                err_fmt!(
+                    Error::INVALID_OPT_COMBO_WITH_CTX,
+                    wgettext!("--function requires --regex")
+                )
+                .subcmd(cmd, subcmd)
+                .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            if !function::exists(function, parser) {
+                err_fmt!("Function '%s' does not exist", function)
+                    .subcmd(cmd, subcmd)
+                    .finish(streams);
+                return Err(STATUS_INVALID_ARGS);
+            }
+        }
+
         let replacer = match StringReplacer::new(self.pattern, self.replacement, self) {
             Ok(x) => x,
             Err(e) => {
@@ -101,14 +130,15 @@ impl<'args> StringSubCommand<'args> for Replace<'args> {
         let mut replace_count = 0;
 
         for InputValue { arg, want_newline } in arguments(args, optind, streams) {
-            let (replaced, result) = match replacer.replace(arg) {
+            let (replaced, result) = match replacer.replace(arg, parser) {
                 Ok(x) => x,
-                Err(e) => {
+                Err(ReplaceError::Regex(e)) => {
                     err_fmt!("Regular expression substitute error: %s", e.error_message())
                         .subcmd(cmd, subcmd)
                         .finish(streams);
                     return Err(STATUS_INVALID_ARGS);
                 }
+                Err(ReplaceError::Function(code)) => return Err(code),
             };
             replace_count += replaced as usize;
 
@@ -138,6 +168,18 @@ impl<'args> StringSubCommand<'args> for Replace<'args> {
     }
 }
 
+enum ReplaceError {
+    Regex(pcre2::Error),
+    /// Running the function given with --function was aborted, e.g. by a signal.
+    Function(ErrorCode),
+}
+
+impl From<pcre2::Error> for ReplaceError {
+    fn from(error: pcre2::Error) -> Self {
+        ReplaceError::Regex(error)
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 enum StringReplacer<'args, 'opts> {
     Regex {
@@ -145,6 +187,11 @@ enum StringReplacer<'args, 'opts> {
         regex: Regex,
         opts: &'opts Replace<'args>,
     },
+    Function {
+        function: &'args wstr,
+        regex: Regex,
+        opts: &'opts Replace<'args>,
+    },
     Literal {
         pattern: Cow<'args, wstr>,
         replacement: Cow<'args, wstr>,
@@ -188,6 +235,14 @@ impl<'args, 'opts> StringReplacer<'args, 'opts> {
                         error,
                     })?;
 
+                if let Some(function) = opts.function {
+                    return Ok(Self::Function {
+                        function,
+                        regex,
+                        opts,
+                    });
+                }
+
                 let replacement = if feature_test(FeatureFlag::StringReplaceBackslash) {
                     replacement.to_owned()
                 } else {
@@ -220,7 +275,32 @@ impl<'args, 'opts> StringReplacer<'args, 'opts> {
         Ok(r)
     }
 
-    fn replace<'a>(&self, arg: Cow<'a, wstr>) -> Result<(bool, Cow<'a, wstr>), pcre2::Error> {
+    /// Run `function` with the text of the match and its capture groups as arguments, and return
+    /// its output.
+    fn call_function(
+        function: &wstr,
+        arg: &wstr,
+        captures: &Captures,
+        parser: &mut Parser,
+    ) -> Result<WString, ReplaceError> {
+        let escape = |s: &wstr| escape_string(s, EscapeStringStyle::Script(EscapeFlags::default()));
+        let mut cmd = escape(function);
+        for i in 0..captures.len() {
+            cmd.push(' ');
+            // Groups that did not participate in the match are passed as empty arguments.
+            let group = captures.get(i).map_or(L!(""), |m| &arg[m.start()..m.end()]);
+            cmd.push_utfstr(&escape(group));
+        }
+        let mut output = vec![];
+        exec_subshell(&cmd, parser, Some(&mut output), false).map_err(ReplaceError::Function)?;
+        Ok(join_strings(&output, '\n'))
+    }
+
+    fn replace<'a>(
+        &self,
+        arg: Cow<'a, wstr>,
+        parser: &mut Parser,
+    ) -> Result<(bool, Cow<'a, wstr>), ReplaceError> {
         match self {
             StringReplacer::Regex {
                 replacement,
@@ -239,6 +319,31 @@ impl<'args, 'opts> StringReplacer<'args, 'opts> {
                 };
                 Ok(res)
             }
+            StringReplacer::Function {
+                function,
+                regex,
+                opts,
+            } => {
+                let mut result = WString::with_capacity(arg.len());
+                let mut offset = 0;
+                let mut replaced = false;
+                for captures in regex.captures_iter(arg.as_char_slice()) {
+                    let captures = captures?;
+                    let m = captures.get(0).unwrap();
+                    result.push_utfstr(&arg[offset..m.start()]);
+                    result.push_utfstr(&Self::call_function(function, &arg, &captures, parser)?);
+                    offset = m.end();
+                    replaced = true;
+                    if !opts.all {
+                        break;
+                    }
+                }
+                if !replaced {
+                    return Ok((false, arg));
+                }
+                result.push_utfstr(&arg[offset..]);
+                Ok((true, Cow::Owned(result)))
+            }
             StringReplacer::Literal {
                 pattern,
                 replacement,
@@ -335,5 +440,6 @@ mod tests {
         validate!(["string", "replace", "-r", "*", ".", "a"], STATUS_INVALID_ARGS, "");
         validate!(["string", "replace", "-ra", "x", "\\c"], STATUS_CMD_ERROR, "");
         validate!(["string", "replace", "-r", "^(.)", "\t$1", "abc", "x"], STATUS_CMD_OK, "\tabc\n\tx\n");
+        validate!(["string", "replace", "--function", "f", "a", "abc"], STATUS_INVALID_ARGS, "");
     }
 }
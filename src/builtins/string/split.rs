@@ -1,6 +1,8 @@
+use pcre2::utf32::{Regex, RegexBuilder};
 use std::ops::Deref;
 
 use super::*;
+use crate::flog::flog;
 use fish_wcstringutil::split_about;
 
 pub struct Split<'args> {
@@ -10,6 +12,9 @@ pub struct Split<'args> {
     no_empty: bool,
     fields: Fields,
     allow_empty: bool,
+    regex: bool,
+    null_in: bool,
+    null_out: bool,
     pub is_split0: bool,
     sep: &'args wstr,
 }
@@ -23,6 +28,9 @@ impl Default for Split<'_> {
             no_empty: false,
             fields: Fields(Vec::new()),
             allow_empty: false,
+            regex: false,
+            null_in: false,
+            null_out: false,
             is_split0: false,
             sep: L!("\0"),
         }
@@ -101,6 +109,50 @@ impl<'args> TryFrom<&'args wstr> for Fields {
     }
 }
 
+/// Like [`split_about`], but splits about the matches of `regex`.
+/// Matches are always searched from the left. When splitting from the right, the last `max`
+/// matches are used.
+/// Like an empty separator, an empty match splits between two characters, so empty matches at the
+/// start and the end of the haystack are ignored.
+fn split_about_regex<'haystack>(
+    haystack: &'haystack wstr,
+    regex: &Regex,
+    max: usize,
+    no_empty: bool,
+    split_from: Direction,
+) -> Vec<&'haystack wstr> {
+    let len = haystack.len();
+    let mut separators = vec![];
+    for m in regex.find_iter(haystack.as_char_slice()) {
+        let m = match m {
+            Ok(m) => m,
+            Err(e) => {
+                flog!(error, "pcre2_match unexpected error:", e.error_message());
+                break;
+            }
+        };
+        if m.start() == m.end() && (m.start() == 0 || m.end() == len) {
+            continue;
+        }
+        separators.push((m.start(), m.end()));
+    }
+    let separators = match split_from {
+        Direction::Left => &separators[..max.min(separators.len())],
+        Direction::Right => &separators[separators.len().saturating_sub(max)..],
+    };
+
+    let mut output = vec![];
+    let mut start = 0;
+    for &(sep_start, sep_end) in separators.iter().chain(std::iter::once(&(len, len))) {
+        let field = &haystack[start..sep_start];
+        if !no_empty || !field.is_empty() {
+            output.push(field);
+        }
+        start = sep_end;
+    }
+    output
+}
+
 impl<'args> StringSubCommand<'args> for Split<'args> {
     const LONG_OPTIONS: &'static [WOption<'static>] = &[
         wopt(L!("quiet"), NoArgument, 'q'),
@@ -109,8 +161,11 @@ impl<'args> StringSubCommand<'args> for Split<'args> {
         wopt(L!("no-empty"), NoArgument, 'n'),
         wopt(L!("fields"), RequiredArgument, 'f'),
         wopt(L!("allow-empty"), NoArgument, 'a'),
+        wopt(L!("regex"), NoArgument, '\x01'),
+        wopt(L!("null-in"), NoArgument, 'z'),
+        wopt(L!("null-out"), NoArgument, 'Z'),
     ];
-    const SHORT_OPTIONS: &'static wstr = L!("qrm:nf:a");
+    const SHORT_OPTIONS: &'static wstr = L!("qrm:nf:azZ");
 
     fn parse_opt(&mut self, c: char, arg: Option<&wstr>) -> Result<(), StringError<'_>> {
         match c {
@@ -136,6 +191,9 @@ impl<'args> StringSubCommand<'args> for Split<'args> {
                 })?;
             }
             'a' => self.allow_empty = true,
+            '\x01' => self.regex = true,
+            'z' => self.null_in = true,
+            'Z' => self.null_out = true,
             _ => return Err(StringError::UnknownOption),
         }
         Ok(())
@@ -184,17 +242,50 @@ impl<'args> StringSubCommand<'args> for Split<'args> {
         }
 
         let sep = self.sep;
+        let regex = if self.regex {
+            match RegexBuilder::new()
+                .block_utf_pattern_directive(true)
+                .build(sep.as_char_slice())
+            {
+                Ok(regex) => Some(regex),
+                Err(error) => {
+                    RegexError::Compile {
+                        pattern: sep.to_owned(),
+                        error,
+                    }
+                    .print_error(args, streams);
+                    return Err(STATUS_INVALID_ARGS);
+                }
+            }
+        } else {
+            None
+        };
         let mut all_splits: Vec<Vec<Cow<'args, wstr>>> = Vec::new();
         let mut split_count = 0usize;
         let mut arg_count = 0usize;
 
-        let argiter = arguments(args, optind, streams).with_split_behavior(match self.is_split0 {
-            false => SplitBehavior::Newline,
-            true => SplitBehavior::Never,
-        });
+        let argiter = arguments(args, optind, streams).with_split_behavior(
+            match (self.is_split0, self.null_in) {
+                (true, _) => SplitBehavior::Never,
+                (false, true) => SplitBehavior::Null,
+                (false, false) => SplitBehavior::Newline,
+            },
+        );
         for InputValue { arg, .. } in argiter {
-            let splits: Vec<Cow<'args, wstr>> = match (self.split_from, arg) {
-                (Direction::Right, arg) => {
+            let splits: Vec<Cow<'args, wstr>> = match (&regex, self.split_from, arg) {
+                (Some(regex), split_from, Cow::Borrowed(arg)) => {
+                    split_about_regex(arg, regex, self.max, self.no_empty, split_from)
+                        .into_iter()
+                        .map(Cow::Borrowed)
+                        .collect()
+                }
+                (Some(regex), split_from, Cow::Owned(arg)) => {
+                    split_about_regex(&arg, regex, self.max, self.no_empty, split_from)
+                        .into_iter()
+                        .map(|s| Cow::Owned(s.to_owned()))
+                        .collect()
+                }
+                (None, Direction::Right, arg) => {
                     let mut rev = arg.into_owned();
                     rev.as_char_slice_mut().reverse();
                     let sep: WString = sep.chars().rev().collect();
@@ -209,13 +300,13 @@ impl<'args> StringSubCommand<'args> for Split<'args> {
                 // let arg: &'args wstr = &arg;
                 // does not compile since `arg` can be dropped at the end of this scope
                 // making the reference invalid if it is owned.
-                (Direction::Left, Cow::Borrowed(arg)) => {
+                (None, Direction::Left, Cow::Borrowed(arg)) => {
                     split_about(arg, sep, self.max, self.no_empty)
                         .into_iter()
                         .map(Cow::Borrowed)
                         .collect()
                 }
-                (Direction::Left, Cow::Owned(arg)) => {
+                (None, Direction::Left, Cow::Owned(arg)) => {
                     split_about(&arg, sep, self.max, self.no_empty)
                         .into_iter()
                         .map(|s| Cow::Owned(s.to_owned()))
@@ -266,16 +357,12 @@ impl<'args> StringSubCommand<'args> for Split<'args> {
                 }
                 for field in self.fields.iter() {
                     if let Some(val) = splits.get(*field) {
-                        streams
-                            .out
-                            .append_with_separation(val, SeparationType::Explicitly, true);
+                        append_element(streams, val, self.null_out, true);
                     }
                 }
             } else {
                 for split in splits {
-                    streams
-                        .out
-                        .append_with_separation(&split, SeparationType::Explicitly, true);
+                    append_element(streams, &split, self.null_out, true);
                 }
             }
         }
@@ -326,5 +413,14 @@ mod tests {
         validate!(["string", "split", "-q"], STATUS_INVALID_ARGS, "");
         validate!(["string", "split", "-q", ":"], STATUS_CMD_ERROR, "");
         validate!(["string", "split", "-q", "x", "axbxc"], STATUS_CMD_OK, "");
+        validate!(["string", "split", "--regex", "[,;]+", "a,b;;c"], STATUS_CMD_OK, "a\nb\nc\n");
+        validate!(["string", "split", "--regex", "-m1", "[,;]+", "a,b;;c"], STATUS_CMD_OK, "a\nb;;c\n");
+        validate!(["string", "split", "--regex", "-r", "-m1", "[,;]+", "a,b;;c"], STATUS_CMD_OK, "a,b\nc\n");
+        validate!(["string", "split", "--regex", "-n", ",", ",a,,b,"], STATUS_CMD_OK, "a\nb\n");
+        validate!(["string", "split", "--regex", ",", ",a,,b,"], STATUS_CMD_OK, "\na\n\nb\n\n");
+        validate!(["string", "split", "--regex", "x*", "abc"], STATUS_CMD_OK, "a\nb\nc\n");
+        validate!(["string", "split", "--regex", "-m0", ",", "a,b"], STATUS_CMD_ERROR, "a,b\n");
+        validate!(["string", "split", "--regex", "(", "a"], STATUS_INVALID_ARGS, "");
+        validate!(["string", "split", "-Z", ":", "a:b\nc", "d"], STATUS_CMD_OK, "a\0b\nc\0d\0");
     }
 }
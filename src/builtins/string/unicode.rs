@@ -0,0 +1,229 @@
+use super::*;
+use fish_common::{EscapeFlags, EscapeStringStyle, escape_string};
+use unicode_normalization::UnicodeNormalization as _;
+use unicode_properties::{GeneralCategory, UnicodeGeneralCategory as _};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+    Inspect,
+}
+
+#[derive(Default)]
+pub struct Unicode {
+    mode: Option<Mode>,
+    quiet: bool,
+}
+
+fn normalize(s: &wstr, mode: Mode) -> WString {
+    let chars = s.chars();
+    match mode {
+        Mode::Nfc => chars.nfc().collect(),
+        Mode::Nfd => chars.nfd().collect(),
+        Mode::Nfkc => chars.nfkc().collect(),
+        Mode::Nfkd => chars.nfkd().collect(),
+        Mode::Inspect => unreachable!(),
+    }
+}
+
+/// The two-letter abbreviation of a general category, as used in the Unicode Character Database.
+fn category_abbreviation(category: GeneralCategory) -> &'static str {
+    use GeneralCategory::*;
+    match category {
+        UppercaseLetter => "Lu",
+        LowercaseLetter => "Ll",
+        TitlecaseLetter => "Lt",
+        ModifierLetter => "Lm",
+        OtherLetter => "Lo",
+        NonspacingMark => "Mn",
+        SpacingMark => "Mc",
+        EnclosingMark => "Me",
+        DecimalNumber => "Nd",
+        LetterNumber => "Nl",
+        OtherNumber => "No",
+        ConnectorPunctuation => "Pc",
+        DashPunctuation => "Pd",
+        OpenPunctuation => "Ps",
+        ClosePunctuation => "Pe",
+        InitialPunctuation => "Pi",
+        FinalPunctuation => "Pf",
+        OtherPunctuation => "Po",
+        MathSymbol => "Sm",
+        CurrencySymbol => "Sc",
+        ModifierSymbol => "Sk",
+        OtherSymbol => "So",
+        SpaceSeparator => "Zs",
+        LineSeparator => "Zl",
+        ParagraphSeparator => "Zp",
+        Control => "Cc",
+        Format => "Cf",
+        Surrogate => "Cs",
+        PrivateUse => "Co",
+        Unassigned => "Cn",
+    }
+}
+
+/// Describe a codepoint as the character itself, its codepoint, general category and name,
+/// separated by tabs. Characters without a name (like control characters) only get three fields.
+fn inspect(c: char) -> WString {
+    let mut shown = WString::new();
+    shown.push(c);
+    // Show control characters in a readable way.
+    if c.is_control() {
+        shown = escape_string(&shown, EscapeStringStyle::Script(EscapeFlags::NO_QUOTED));
+    }
+    let mut output = sprintf!(
+        "%s\tU+%04X\t%s",
+        shown,
+        u32::from(c),
+        category_abbreviation(c.general_category())
+    );
+    if let Some(name) = unicode_names2::name(c) {
+        output.push('\t');
+        output.push_str(&name.to_string());
+    }
+    output
+}
+
+impl StringSubCommand<'_> for Unicode {
+    const LONG_OPTIONS: &'static [WOption<'static>] = &[
+        wopt(L!("nfc"), NoArgument, 'c'),
+        wopt(L!("nfd"), NoArgument, 'd'),
+        wopt(L!("nfkc"), NoArgument, 'C'),
+        wopt(L!("nfkd"), NoArgument, 'D'),
+        wopt(L!("inspect"), NoArgument, 'i'),
+        wopt(L!("quiet"), NoArgument, 'q'),
+    ];
+    const SHORT_OPTIONS: &'static wstr = L!("iq");
+
+    fn parse_opt(&mut self, c: char, _arg: Option<&wstr>) -> Result<(), StringError<'_>> {
+        let mode = match c {
+            'c' => Mode::Nfc,
+            'd' => Mode::Nfd,
+            'C' => Mode::Nfkc,
+            'D' => Mode::Nfkd,
+            'i' => Mode::Inspect,
+            'q' => {
+                self.quiet = true;
+                return Ok(());
+            }
+            _ => return Err(StringError::UnknownOption),
+        };
+        if self.mode.is_some_and(|m| m != mode) {
+            return Err(err_fmt!(
+                Error::INVALID_OPT_COMBO_WITH_CTX,
+                wgettext!("--nfc, --nfd, --nfkc, --nfkd and --inspect are mutually exclusive")
+            )
+            .into());
+        }
+        self.mode = Some(mode);
+        Ok(())
+    }
+
+    fn handle(
+        &mut self,
+        _parser: &mut Parser,
+        streams: &mut IoStreams,
+        optind: &mut usize,
+        args: &[&wstr],
+    ) -> Result<(), ErrorCode> {
+        let Some(mode) = self.mode else {
+            err_fmt!("expected one of --nfc, --nfd, --nfkc, --nfkd or --inspect")
+                .subcmd(L!("string"), args[0])
+                .finish(streams);
+            return Err(STATUS_INVALID_ARGS);
+        };
+
+        if mode == Mode::Inspect {
+            let mut ncodepoints = 0usize;
+            for InputValue { arg, .. } in arguments(args, optind, streams) {
+                for c in arg.chars() {
+                    ncodepoints += 1;
+                    if self.quiet {
+                        return Ok(());
+                    }
+                    streams.out.appendln(&inspect(c));
+                }
+            }
+            return if ncodepoints > 0 {
+                Ok(())
+            } else {
+                Err(STATUS_CMD_ERROR)
+            };
+        }
+
+        let mut n_normalized = 0usize;
+        for InputValue { arg, want_newline } in arguments(args, optind, streams) {
+            let normalized = normalize(&arg, mode);
+            if normalized != arg {
+                n_normalized += 1;
+            }
+            if !self.quiet {
+                streams.out.append_with_separation(
+                    &normalized,
+                    SeparationType::Explicitly,
+                    want_newline,
+                );
+            } else if n_normalized > 0 {
+                return Ok(());
+            }
+        }
+
+        if n_normalized > 0 {
+            Ok(())
+        } else {
+            Err(STATUS_CMD_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mode, inspect, normalize};
+    use crate::builtins::{STATUS_CMD_ERROR, STATUS_CMD_OK, STATUS_INVALID_ARGS};
+    use crate::tests::prelude::*;
+    use crate::validate;
+    use fish_widestring::prelude::*;
+
+    #[test]
+    fn test_normalize() {
+        let composed = L!("\u{E9}t\u{E9}");
+        let decomposed = L!("e\u{301}te\u{301}");
+        assert_eq!(normalize(composed, Mode::Nfd), decomposed);
+        assert_eq!(normalize(decomposed, Mode::Nfc), composed);
+        // Compatibility decomposition only happens in the K forms.
+        assert_eq!(normalize(L!("\u{FB01}"), Mode::Nfc), L!("\u{FB01}"));
+        assert_eq!(normalize(L!("\u{FB01}"), Mode::Nfkc), L!("fi"));
+        assert_eq!(normalize(L!("\u{2460}"), Mode::Nfkd), L!("1"));
+        // The angstrom sign is canonically equivalent to the letter.
+        assert_eq!(normalize(L!("\u{212B}"), Mode::Nfc), L!("\u{C5}"));
+    }
+
+    #[test]
+    fn test_inspect() {
+        assert_eq!(inspect('a'), L!("a\tU+0061\tLl\tLATIN SMALL LETTER A"));
+        assert_eq!(
+            inspect('\u{301}'),
+            L!("\u{301}\tU+0301\tMn\tCOMBINING ACUTE ACCENT")
+        );
+        assert_eq!(inspect('\u{1F41F}'), L!("\u{1F41F}\tU+1F41F\tSo\tFISH"));
+        assert_eq!(inspect('\n'), L!("\\n\tU+000A\tCc"));
+    }
+
+    #[test]
+    #[serial]
+    #[rustfmt::skip]
+    fn plain() {
+        test_init();
+        validate!(["string", "unicode", "--nfd", "\u{E9}"], STATUS_CMD_OK, "e\u{301}\n");
+        validate!(["string", "unicode", "--nfc", "\u{E9}"], STATUS_CMD_ERROR, "\u{E9}\n");
+        validate!(["string", "unicode", "-q", "--nfc", "e\u{301}"], STATUS_CMD_OK, "");
+        validate!(["string", "unicode", "--inspect", "a"], STATUS_CMD_OK, "a\tU+0061\tLl\tLATIN SMALL LETTER A\n");
+        validate!(["string", "unicode", "--inspect"], STATUS_CMD_ERROR, "");
+        validate!(["string", "unicode", "a"], STATUS_INVALID_ARGS, "");
+        validate!(["string", "unicode", "--nfc", "--nfd", "a"], STATUS_INVALID_ARGS, "");
+    }
+}
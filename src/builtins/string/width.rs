@@ -0,0 +1,165 @@
+use super::*;
+use fish_common::{EscapeFlags, EscapeStringStyle, escape_string};
+use fish_wcstringutil::join_strings;
+use unicode_segmentation::UnicodeSegmentation as _;
+
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+#[derive(Default)]
+pub struct Width {
+    breakdown: bool,
+    quiet: bool,
+}
+
+/// The number of columns a grapheme cluster occupies.
+/// Emoji joined via zero width joiners are drawn as a single emoji, so such a sequence is as wide
+/// as its widest part, rather than the sum of all parts.
+fn grapheme_width(grapheme: &wstr) -> usize {
+    let sum = |part: &wstr| {
+        part.chars()
+            .map(|c| fish_wcwidth_visible(c).max(0).unsigned_abs())
+            .sum::<usize>()
+    };
+    grapheme
+        .split(ZERO_WIDTH_JOINER)
+        .map(sum)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Split a line into escape sequences, which are not displayed, and grapheme clusters.
+/// Escape sequences are returned with `is_escape` set.
+fn graphemes(line: &wstr) -> Vec<(WString, bool)> {
+    let mut result = vec![];
+    let mut text_start = 0;
+    let mut pos = 0;
+    let flush_text = |result: &mut Vec<(WString, bool)>, text: &wstr| {
+        let text = text.to_string();
+        result.extend(text.graphemes(true).map(|g| (WString::from_str(g), false)));
+    };
+    while pos < line.len() {
+        if line.char_at(pos) == '\x1B' {
+            if let Some(len) = escape_code_length(line.slice_from(pos)) {
+                flush_text(&mut result, &line[text_start..pos]);
+                result.push((line[pos..pos + len].to_owned(), true));
+                pos += len;
+                text_start = pos;
+                continue;
+            }
+        }
+        pos += 1;
+    }
+    flush_text(&mut result, &line[text_start..]);
+    result
+}
+
+impl StringSubCommand<'_> for Width {
+    const LONG_OPTIONS: &'static [WOption<'static>] = &[
+        wopt(L!("breakdown"), NoArgument, 'b'),
+        wopt(L!("quiet"), NoArgument, 'q'),
+    ];
+    const SHORT_OPTIONS: &'static wstr = L!("bq");
+
+    fn parse_opt(&mut self, c: char, _arg: Option<&wstr>) -> Result<(), StringError<'_>> {
+        match c {
+            'b' => self.breakdown = true,
+            'q' => self.quiet = true,
+            _ => return Err(StringError::UnknownOption),
+        }
+        Ok(())
+    }
+
+    fn handle(
+        &mut self,
+        _parser: &mut Parser,
+        streams: &mut IoStreams,
+        optind: &mut usize,
+        args: &[&wstr],
+    ) -> Result<(), ErrorCode> {
+        let mut nnonempty = 0usize;
+
+        for InputValue { arg, .. } in arguments(args, optind, streams) {
+            // Width only makes sense line-wise.
+            for line in arg.split('\n') {
+                let graphemes = graphemes(line);
+                let width: usize = graphemes
+                    .iter()
+                    .filter(|(_, is_escape)| !is_escape)
+                    .map(|(grapheme, _)| grapheme_width(grapheme))
+                    .sum();
+                if width > 0 {
+                    nnonempty += 1;
+                }
+                if self.quiet {
+                    if nnonempty > 0 {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                if !self.breakdown {
+                    streams.out.appendln(&width.to_wstring());
+                    continue;
+                }
+                for (grapheme, is_escape) in graphemes {
+                    let width = if is_escape {
+                        0
+                    } else {
+                        grapheme_width(&grapheme)
+                    };
+                    // Show control characters and escape sequences in a readable way.
+                    let shown = if grapheme.chars().any(char::is_control) {
+                        escape_string(&grapheme, EscapeStringStyle::Script(EscapeFlags::NO_QUOTED))
+                    } else {
+                        grapheme.clone()
+                    };
+                    let mut output = sprintf!("%s\t%d\t", shown, width);
+                    let codepoints: Vec<WString> = grapheme
+                        .chars()
+                        .map(|c| sprintf!("U+%04X", u32::from(c)))
+                        .collect();
+                    output.push_utfstr(&join_strings(&codepoints, ' '));
+                    streams.out.appendln(&output);
+                }
+            }
+        }
+
+        if nnonempty > 0 {
+            Ok(())
+        } else {
+            Err(STATUS_CMD_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{grapheme_width, graphemes};
+    use fish_widestring::prelude::*;
+
+    #[test]
+    fn test_grapheme_width() {
+        assert_eq!(grapheme_width(L!("a")), 1);
+        assert_eq!(grapheme_width(L!("漢")), 2);
+        // Combining acute accent.
+        assert_eq!(grapheme_width(L!("e\u{301}")), 1);
+        // Family: man, woman, girl.
+        assert_eq!(
+            grapheme_width(L!("\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}")),
+            grapheme_width(L!("\u{1F468}"))
+        );
+    }
+
+    #[test]
+    fn test_graphemes() {
+        let line = L!("e\u{301}\x1B[31mx\u{1F468}\u{200D}\u{1F469}");
+        assert_eq!(
+            graphemes(line),
+            [
+                (L!("e\u{301}").to_owned(), false),
+                (L!("\x1B[31m").to_owned(), true),
+                (L!("x").to_owned(), false),
+                (L!("\u{1F468}\u{200D}\u{1F469}").to_owned(), false),
+            ]
+        );
+    }
+}
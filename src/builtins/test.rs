@@ -10,10 +10,14 @@ mod test_expressions {
     use crate::builtins;
     use crate::err_raw;
     use crate::nix::isatty;
+    use crate::parse_util::unescape_wildcards;
+    use crate::wildcard::wildcard_match;
     use crate::wutil::{
         self, file_id_for_path, lwstat, waccess, wcstod::wcstod, wcstoi, wcstoi_opts, wstat,
     };
     use fish_fallback::fish_wcswidth;
+    use pcre2::utf32::RegexBuilder;
+    use std::cmp::Ordering;
     use std::collections::HashMap;
     use std::os::unix::prelude::*;
     use std::sync::LazyLock;
@@ -133,6 +137,8 @@ mod test_expressions {
         String(StringComparison) {
             Equal    // "=", true if strings are identical
             NotEqual // "!=", true if strings are not identical
+            Regex    // "=~", true if the regular expression on the right matches the left string
+            Glob     // "==*", true if the glob on the right matches the entire left string
         }
 
         Number(NumberComparison) {
@@ -143,6 +149,15 @@ mod test_expressions {
             Lesser       // "-lt", true if first number is smaller than second
             LesserEqual  // "-le", true if first number is at most second
         }
+
+        Version(VersionComparison) {
+            Equal        // "-ver-eq", true if versions are equal
+            NotEqual     // "-ver-ne", true if versions are not equal
+            Greater      // "-ver-gt", true if first version is newer than second
+            GreaterEqual // "-ver-ge", true if first version is at least second
+            Lesser       // "-ver-lt", true if first version is older than second
+            LesserEqual  // "-ver-le", true if first version is at most second
+        }
     }
 
     /// Our number type. We support both doubles and long longs. We have to support these separately
@@ -214,6 +229,8 @@ mod test_expressions {
             (L!("-z"), StringPredicate::z.into()),
             (L!("="), StringComparison::Equal.into()),
             (L!("!="), StringComparison::NotEqual.into()),
+            (L!("=~"), StringComparison::Regex.into()),
+            (L!("==*"), StringComparison::Glob.into()),
             (L!("-nt"), FileComparison::Newer.into()),
             (L!("-ot"), FileComparison::Older.into()),
             (L!("-ef"), FileComparison::Same.into()),
@@ -223,6 +240,12 @@ mod test_expressions {
             (L!("-ge"), NumberComparison::GreaterEqual.into()),
             (L!("-lt"), NumberComparison::Lesser.into()),
             (L!("-le"), NumberComparison::LesserEqual.into()),
+            (L!("-ver-eq"), VersionComparison::Equal.into()),
+            (L!("-ver-ne"), VersionComparison::NotEqual.into()),
+            (L!("-ver-gt"), VersionComparison::Greater.into()),
+            (L!("-ver-ge"), VersionComparison::GreaterEqual.into()),
+            (L!("-ver-lt"), VersionComparison::Lesser.into()),
+            (L!("-ver-le"), VersionComparison::LesserEqual.into()),
             (L!("-a"), Token::BinaryBoolean(Combiner::And)),
             (L!("-o"), Token::BinaryBoolean(Combiner::Or)),
             (L!("("), Token::ParenOpen),
@@ -722,35 +745,56 @@ mod test_expressions {
             }
         }
 
-        pub fn parse_args(args: &[WString]) -> Result<Box<dyn Expression>, builtins::Error<'_>> {
+        /// Parse all the arguments. Returns None if there were errors.
+        fn parse_all(&mut self) -> Option<Box<dyn Expression>> {
+            let args = self.strings;
+            let result_opt = self.parse_expression(0, args.len());
+
+            // Historic assumption from C++: if we have no errors then we must have a result.
+            assert!(!self.errors.is_empty() || result_opt.is_some());
+
+            let result = result_opt?;
+            let range_end = result.range().end;
+            assert!(range_end <= args.len());
+
+            // The result is valid only if we consumed all the arguments.
+            // This is not detected by parse_expression(), so in that case
+            // we need to create our own error.
+            if range_end == args.len() {
+                return Some(result);
+            }
+            if self.errors.is_empty() {
+                self.error_idx = range_end;
+                self.errors = vec![sprintf!(
+                    "unexpected argument at index %u: '%s'",
+                    range_end + 1,
+                    args[range_end],
+                )];
+            }
+            None
+        }
+
+        /// Return the first error in parsing the arguments, without evaluating anything.
+        pub fn syntax_error(args: &[WString]) -> Option<WString> {
             let mut parser = TestParser {
                 strings: args,
                 errors: Vec::new(),
                 error_idx: 0,
             };
-            let result_opt = parser.parse_expression(0, args.len());
-
-            // Historic assumption from C++: if we have no errors then we must have a result.
-            assert!(!parser.errors.is_empty() || result_opt.is_some());
-
-            if let Some(result) = result_opt {
-                let range_end = result.range().end;
-                assert!(range_end <= args.len());
+            match parser.parse_all() {
+                Some(_) => None,
+                None => parser.errors.into_iter().next(),
+            }
+        }
 
-                // The result is valid only if we consumed all the arguments.
-                // This is not detected by parse_expression(), so in that case
-                // we need to create our own error.
-                if range_end == args.len() {
-                    return Ok(result);
-                }
-                if parser.errors.is_empty() {
-                    parser.error_idx = range_end;
-                    parser.errors = vec![sprintf!(
-                        "unexpected argument at index %u: '%s'",
-                        range_end + 1,
-                        args[range_end],
-                    )];
-                }
+        pub fn parse_args(args: &[WString]) -> Result<Box<dyn Expression>, builtins::Error<'_>> {
+            let mut parser = TestParser {
+                strings: args,
+                errors: Vec::new(),
+                error_idx: 0,
+            };
+            if let Some(result) = parser.parse_all() {
+                return Ok(result);
             }
 
             // Handle errors.
@@ -866,6 +910,109 @@ mod test_expressions {
         }
     }
 
+    /// A version like "v1.2.3-rc.1+build5", split into its release and pre-release components.
+    /// Build metadata (after a "+") does not take part in comparisons and is dropped.
+    struct Version<'a> {
+        release: Vec<&'a wstr>,
+        prerelease: Vec<&'a wstr>,
+    }
+
+    // Parse a version, returning None if it is not one. A version must start with a digit,
+    // optionally preceded by a "v", and must not contain empty components.
+    fn parse_version(arg: &wstr) -> Option<Version<'_>> {
+        let mut arg = arg;
+        if arg.len() > 1 && matches!(arg.char_at(0), 'v' | 'V') && arg.char_at(1).is_ascii_digit() {
+            arg = arg.slice_from(1);
+        }
+        if arg.is_empty() || !arg.char_at(0).is_ascii_digit() {
+            return None;
+        }
+        if let Some(pos) = arg.chars().position(|c| c == '+') {
+            arg = arg.slice_to(pos);
+        }
+        let (release, prerelease) = match arg.chars().position(|c| c == '-') {
+            Some(pos) => (arg.slice_to(pos), Some(arg.slice_from(pos + 1))),
+            None => (arg, None),
+        };
+        let release: Vec<&wstr> = release.split('.').collect();
+        let prerelease: Vec<&wstr> = prerelease.map_or(vec![], |p| p.split('.').collect());
+        if release
+            .iter()
+            .chain(prerelease.iter())
+            .any(|c| c.is_empty())
+        {
+            return None;
+        }
+        Some(Version {
+            release,
+            prerelease,
+        })
+    }
+
+    // Compare two version components. Runs of digits are compared numerically and sort before
+    // other text, so "2" < "10" and "1" < "1a" < "1b".
+    fn compare_version_components(left: &wstr, right: &wstr) -> Ordering {
+        fn runs(s: &wstr) -> Vec<&wstr> {
+            let mut result = vec![];
+            let mut start = 0;
+            for idx in 1..=s.len() {
+                if idx == s.len()
+                    || s.char_at(idx).is_ascii_digit() != s.char_at(start).is_ascii_digit()
+                {
+                    result.push(&s[start..idx]);
+                    start = idx;
+                }
+            }
+            result
+        }
+        let (lruns, rruns) = (runs(left), runs(right));
+        for (l, r) in lruns.iter().zip(rruns.iter()) {
+            let ordering = match (l.char_at(0).is_ascii_digit(), r.char_at(0).is_ascii_digit()) {
+                (true, true) => {
+                    // Compare without parsing, so that arbitrarily long numbers work.
+                    let l = l.slice_from(l.chars().take_while(|&c| c == '0').count());
+                    let r = r.slice_from(r.chars().take_while(|&c| c == '0').count());
+                    l.len().cmp(&r.len()).then_with(|| l.cmp(r))
+                }
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                (false, false) => l.cmp(r),
+            };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+        lruns.len().cmp(&rruns.len())
+    }
+
+    // Compare two versions, in the spirit of semantic versioning: missing release components count
+    // as zero, and a pre-release is older than the corresponding release.
+    fn compare_versions(left: &Version, right: &Version) -> Ordering {
+        let len = left.release.len().max(right.release.len());
+        for idx in 0..len {
+            let l = left.release.get(idx).copied().unwrap_or(L!("0"));
+            let r = right.release.get(idx).copied().unwrap_or(L!("0"));
+            let ordering = compare_version_components(l, r);
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+        match (left.prerelease.is_empty(), right.prerelease.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                for (l, r) in left.prerelease.iter().zip(right.prerelease.iter()) {
+                    let ordering = compare_version_components(l, r);
+                    if ordering.is_ne() {
+                        return ordering;
+                    }
+                }
+                left.prerelease.len().cmp(&right.prerelease.len())
+            }
+        }
+    }
+
     fn binary_primary_evaluate(
         token: BinaryToken,
         left: &wstr,
@@ -875,6 +1022,26 @@ mod test_expressions {
         match token {
             BinaryToken::String(StringComparison::Equal) => left == right,
             BinaryToken::String(StringComparison::NotEqual) => left != right,
+            BinaryToken::String(StringComparison::Regex) => {
+                // Like bash, the regex is not anchored, so it only needs to match part of the string.
+                match RegexBuilder::new()
+                    .block_utf_pattern_directive(true)
+                    .build(right.as_char_slice())
+                    .and_then(|regex| regex.is_match(left.as_char_slice()))
+                {
+                    Ok(matched) => matched,
+                    Err(error) => {
+                        errors.push(wgettext_fmt!(
+                            builtins::Error::REGEX_COMPILE,
+                            error.error_message()
+                        ));
+                        false
+                    }
+                }
+            }
+            BinaryToken::String(StringComparison::Glob) => {
+                wildcard_match(left, unescape_wildcards(right), false)
+            }
             BinaryToken::FileId(comparison) => {
                 let left = file_id_for_path(left);
                 let right = file_id_for_path(right);
@@ -899,6 +1066,27 @@ mod test_expressions {
                     NumberComparison::LesserEqual => ln <= rn,
                 }
             }
+            BinaryToken::Version(comparison) => {
+                let versions = [left, right].map(|arg| {
+                    let version = parse_version(arg);
+                    if version.is_none() {
+                        errors.push(wgettext_fmt!("Invalid version: '%s'", arg));
+                    }
+                    version
+                });
+                let [Some(lv), Some(rv)] = versions else {
+                    return false;
+                };
+                let ordering = compare_versions(&lv, &rv);
+                match comparison {
+                    VersionComparison::Equal => ordering.is_eq(),
+                    VersionComparison::NotEqual => ordering.is_ne(),
+                    VersionComparison::Greater => ordering.is_gt(),
+                    VersionComparison::GreaterEqual => ordering.is_ge(),
+                    VersionComparison::Lesser => ordering.is_lt(),
+                    VersionComparison::LesserEqual => ordering.is_le(),
+                }
+            }
         }
     }
 
@@ -982,6 +1170,13 @@ mod test_expressions {
         }
     }
 }
+/// Return the error `test` would report for these arguments, not counting the command name or the
+/// closing bracket. Only the syntax is checked, so this can be used on arguments that are not
+/// known yet.
+pub(crate) fn syntax_error(args: &[WString]) -> Option<WString> {
+    test_expressions::TestParser::syntax_error(args)
+}
+
 /// Evaluate a conditional expression given the arguments. For POSIX conformance this
 /// supports a more limited range of functionality.
 /// Return status is the final shell status, i.e. 0 for true, 1 for false and 2 for error.
@@ -1257,6 +1452,27 @@ mod tests {
         // test out-of-range numbers
         assert!(run_test_test(2, &["99999999999999999999999999", "-ge", "1"]));
         assert!(run_test_test(2, &["1", "-eq", "-99999999999999999999999999.9"]));
+
+        // Pattern matching.
+        assert!(run_test_test(0, &["foobar", "=~", "o+b"]));
+        assert!(run_test_test(1, &["foobar", "=~", "^b"]));
+        assert!(run_test_test(2, &["foobar", "=~", "("]));
+        assert!(run_test_test(0, &["foobar", "==*", "foo*"]));
+        assert!(run_test_test(1, &["foobar", "==*", "bar*"]));
+        assert!(run_test_test(0, &["!", "foobar", "==*", "bar*"]));
+
+        // Version comparisons.
+        assert!(run_test_test(0, &["1.10", "-ver-gt", "1.9"]));
+        assert!(run_test_test(0, &["v1.2", "-ver-eq", "1.2.0"]));
+        assert!(run_test_test(0, &["1.0.0-rc.1", "-ver-lt", "1.0.0"]));
+        assert!(run_test_test(0, &["1.0.0-alpha", "-ver-lt", "1.0.0-alpha.1"]));
+        assert!(run_test_test(0, &["1.0.0-2", "-ver-lt", "1.0.0-10"]));
+        assert!(run_test_test(0, &["1.0.0+build1", "-ver-eq", "1.0.0+build2"]));
+        assert!(run_test_test(0, &["3.7b", "-ver-gt", "3.7a"]));
+        assert!(run_test_test(1, &["2.0", "-ver-le", "1.99999999999999999999999"]));
+        assert!(run_test_test(2, &["", "-ver-eq", "1"]));
+        assert!(run_test_test(2, &["1..2", "-ver-eq", "1"]));
+        assert!(run_test_test(2, &["1", "-ver-ne", "latest"]));
     }
 
     #[test]
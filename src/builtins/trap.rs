@@ -0,0 +1,220 @@
+// Implementation of the trap builtin.
+
+use super::prelude::*;
+use crate::err_fmt;
+use crate::event::{self, EventDescription};
+use crate::signal::{
+    RawSignal, signal_ignored_signals, signal_is_ignored, signal_names, signal_set_ignored,
+};
+use fish_common::escape;
+use fish_fallback::wcscasecmp;
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Options {
+    print_help: bool,
+    list_signals: bool,
+    print: bool,
+}
+
+fn parse_options(
+    args: &mut [&wstr],
+    parser: &Parser,
+    streams: &mut IoStreams,
+) -> Result<(Options, usize), ErrorCode> {
+    let cmd = args[0];
+
+    const SHORT_OPTS: &wstr = L!("hlp");
+    const LONG_OPTS: &[WOption] = &[
+        wopt(L!("help"), ArgType::NoArgument, 'h'),
+        wopt(L!("list-signals"), ArgType::NoArgument, 'l'),
+        wopt(L!("print"), ArgType::NoArgument, 'p'),
+    ];
+
+    let mut opts = Options::default();
+
+    let mut w = WGetopter::new(SHORT_OPTS, LONG_OPTS, args);
+    while let Some(c) = w.next_opt() {
+        match c {
+            'h' => opts.print_help = true,
+            'l' => opts.list_signals = true,
+            'p' => opts.print = true,
+            ';' => {
+                builtin_unexpected_argument(parser, streams, cmd, args[w.wopt_index - 1], false);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            '?' => {
+                builtin_unknown_option(parser, streams, cmd, args[w.wopt_index - 1], false);
+                return Err(STATUS_INVALID_ARGS);
+            }
+            _ => {
+                panic!("unexpected retval from WGetopter");
+            }
+        }
+    }
+
+    Ok((opts, w.wopt_index))
+}
+
+/// What a trap reacts to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum TrapReason {
+    /// The shell exiting, as the `fish_exit` event.
+    Exit,
+    Signal(RawSignal),
+}
+
+impl TrapReason {
+    /// Parse a signal name or number, with or without the SIG prefix, or EXIT (also 0).
+    fn parse(name: &wstr) -> Option<Self> {
+        if wcscasecmp(name, L!("EXIT")).is_eq() || name == "0" {
+            return Some(TrapReason::Exit);
+        }
+        RawSignal::parse(name).map(TrapReason::Signal)
+    }
+
+    fn name(self) -> &'static wstr {
+        match self {
+            TrapReason::Exit => L!("EXIT"),
+            TrapReason::Signal(signal) => signal.name(),
+        }
+    }
+
+    fn event(self) -> EventDescription {
+        match self {
+            TrapReason::Exit => EventDescription::Generic {
+                param: L!("fish_exit").to_owned(),
+            },
+            TrapReason::Signal(signal) => EventDescription::Signal { signal },
+        }
+    }
+
+    fn from_event(desc: &EventDescription) -> Option<Self> {
+        match desc {
+            EventDescription::Signal { signal } => Some(TrapReason::Signal(*signal)),
+            EventDescription::Generic { param } if param == "fish_exit" => Some(TrapReason::Exit),
+            _ => None,
+        }
+    }
+}
+
+/// Return the current traps, including ignored signals with an empty command, in the order of
+/// their reasons.
+fn current_traps() -> Vec<(TrapReason, WString)> {
+    let mut traps: Vec<_> = event::get_traps()
+        .iter()
+        .filter_map(|(desc, command)| Some((TrapReason::from_event(desc)?, command.clone())))
+        .collect();
+    for signal in signal_ignored_signals() {
+        traps.push((TrapReason::Signal(signal), WString::new()));
+    }
+    traps.sort();
+    traps
+}
+
+/// Print the traps in a form that can be read back in.
+fn print_traps(streams: &mut IoStreams, reasons: Option<&[TrapReason]>) {
+    for (reason, command) in current_traps() {
+        if reasons.is_some_and(|reasons| !reasons.contains(&reason)) {
+            continue;
+        }
+        streams.out.append(&sprintf!(
+            "trap -- %s %s\n",
+            escape(&command),
+            reason.name()
+        ));
+    }
+}
+
+/// Parse the reasons given on the commandline, printing an error for the first invalid one.
+fn parse_reasons(
+    cmd: &wstr,
+    streams: &mut IoStreams,
+    names: &[&wstr],
+) -> Result<Vec<TrapReason>, ErrorCode> {
+    let mut reasons = vec![];
+    for &name in names {
+        let Some(reason) = TrapReason::parse(name) else {
+            err_fmt!("Unknown signal '%s'", name)
+                .cmd(cmd)
+                .finish(streams);
+            return Err(STATUS_CMD_ERROR);
+        };
+        reasons.push(reason);
+    }
+    Ok(reasons)
+}
+
+/// The trap builtin, which runs a command when the shell receives a signal or exits.
+pub fn trap(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) -> BuiltinResult {
+    let cmd = args[0];
+
+    let (opts, optind) = parse_options(args, parser, streams)?;
+    let args = &args[optind..];
+
+    if opts.print_help {
+        builtin_print_help(parser, streams, cmd);
+        return Ok(SUCCESS);
+    }
+
+    if opts.list_signals {
+        for name in signal_names() {
+            streams.out.appendln(name);
+        }
+        return Ok(SUCCESS);
+    }
+
+    if opts.print || args.is_empty() {
+        if args.is_empty() {
+            print_traps(streams, None);
+        } else {
+            let reasons = parse_reasons(cmd, streams, args)?;
+            print_traps(streams, Some(&reasons[..]));
+        }
+        return Ok(SUCCESS);
+    }
+
+    // A lone reason, or a command of "-", resets the traps. An empty command ignores the signals.
+    let (command, names) = if args.len() == 1 {
+        (L!("-"), args)
+    } else {
+        (args[0], &args[1..])
+    };
+    let reasons = parse_reasons(cmd, streams, names)?;
+
+    for &reason in &reasons {
+        if let TrapReason::Signal(signal) = reason {
+            // These cannot be caught, and fish needs SIGCHLD to know when its jobs are done.
+            let untrappable = signal == libc::SIGKILL
+                || signal == libc::SIGSTOP
+                || (command.is_empty() && signal == libc::SIGCHLD);
+            if untrappable {
+                err_fmt!("Cannot trap signal '%s'", signal.name())
+                    .cmd(cmd)
+                    .finish(streams);
+                return Err(STATUS_CMD_ERROR);
+            }
+        }
+    }
+
+    for reason in reasons {
+        event::remove_trap(&reason.event());
+        let TrapReason::Signal(signal) = reason else {
+            if command != "-" && !command.is_empty() {
+                event::set_trap(reason.event(), command.to_owned());
+            }
+            continue;
+        };
+        if command == "-" {
+            signal_set_ignored(signal, false);
+        } else if command.is_empty() {
+            signal_set_ignored(signal, true);
+        } else {
+            if signal_is_ignored(signal) {
+                signal_set_ignored(signal, false);
+            }
+            event::set_trap(reason.event(), command.to_owned());
+        }
+    }
+
+    Ok(SUCCESS)
+}
@@ -1,11 +1,13 @@
 use super::prelude::*;
 use crate::{
+    abbrs::{self, Position},
     builtins::Error,
     err_fmt, err_str, function,
     highlight::highlight_and_colorize,
     parse_util::{apply_indents, compute_indents},
     path::{path_get_path, path_get_paths},
 };
+use fish_wcstringutil::join_strings;
 use fish_widestring::bytes2wcstring;
 
 #[derive(Default)]
@@ -17,9 +19,91 @@ struct Options {
     path: bool,
     force_path: bool,
     query: bool,
+    json: bool,
     color: ColorEnabled,
 }
 
+/// Describe how `name` is resolved as a command, as a JSON object listing each step that could
+/// provide it: an abbreviation, a function, a builtin and every executable in $PATH, in order of
+/// precedence. All steps after the first one that would run are marked as shadowed.
+/// Abbreviations, functions and builtins are left out without `internal`, and functions are
+/// left out without `functions`.
+/// Return the object and whether anything was found.
+pub(crate) fn resolution_json(
+    parser: &mut Parser,
+    name: &wstr,
+    internal: bool,
+    functions: bool,
+) -> (WString, bool) {
+    let mut steps = vec![];
+    let mut found = false;
+    let mut push_step = |mut step: WString, runs: bool| {
+        if runs {
+            sprintf!(=> &mut step, ",\"shadowed\":%s", if found { "true" } else { "false" });
+            found = true;
+        }
+        step.push('}');
+        steps.push(step);
+    };
+
+    if internal {
+        let replacer = abbrs::with_abbrs(|set| {
+            set.r#match(&[name], Position::Command, L!(""))
+                .into_iter()
+                .next()
+        });
+        if let Some(replacer) = replacer {
+            push_step(
+                sprintf!(
+                    "{\"type\":\"abbr\",\"replacement\":%s,\"function\":%s",
+                    json_string(&replacer.replacement),
+                    if replacer.is_function {
+                        "true"
+                    } else {
+                        "false"
+                    }
+                ),
+                false,
+            );
+        }
+        if let Some(props) = functions
+            .then(|| function::get_props_autoload(name, parser))
+            .flatten()
+        {
+            let path = match props.definition_file() {
+                Some(path) => json_string(path),
+                None => L!("null").to_owned(),
+            };
+            push_step(
+                sprintf!(
+                    "{\"type\":\"function\",\"path\":%s,\"line\":%d",
+                    path,
+                    props.definition_lineno()
+                ),
+                true,
+            );
+        }
+        if builtin_exists(name) {
+            push_step(L!("{\"type\":\"builtin\"").to_owned(), true);
+        }
+    }
+    for path in path_get_paths(name, parser.vars()) {
+        push_step(
+            sprintf!("{\"type\":\"file\",\"path\":%s", json_string(&path)),
+            true,
+        );
+    }
+
+    let json = sprintf!(
+        "{\"name\":%s,\"steps\":[%s]}",
+        json_string(name),
+        join_strings(&steps, ',')
+    );
+    (json, found)
+}
+
+const JSON_SHORT: char = '\x01';
+
 pub fn r#type(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr]) -> BuiltinResult {
     let cmd = argv[0];
     let argc = argv.len();
@@ -37,6 +121,7 @@ pub fn r#type(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr])
         wopt(L!("force-path"), ArgType::NoArgument, 'P'),
         wopt(L!("query"), ArgType::NoArgument, 'q'),
         wopt(L!("quiet"), ArgType::NoArgument, 'q'),
+        wopt(L!("json"), ArgType::NoArgument, JSON_SHORT),
         wopt(L!("color"), ArgType::RequiredArgument, COLOR_OPTION_CHAR),
     ];
 
@@ -50,6 +135,7 @@ pub fn r#type(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr])
             'p' => opts.path = true,
             'P' => opts.force_path = true,
             'q' => opts.query = true,
+            JSON_SHORT => opts.json = true,
             'h' => {
                 builtin_print_help(parser, streams, cmd);
                 return Ok(SUCCESS);
@@ -88,10 +174,16 @@ pub fn r#type(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr])
         }
     }
 
-    if [opts.query, opts.path, opts.get_type, opts.force_path]
-        .into_iter()
-        .filter(|&b| b)
-        .count()
+    if [
+        opts.query,
+        opts.path,
+        opts.get_type,
+        opts.force_path,
+        opts.json,
+    ]
+    .into_iter()
+    .filter(|&b| b)
+    .count()
         > 1
     {
         err_str!(Error::INVALID_OPT_COMBO).cmd(cmd).finish(streams);
@@ -101,6 +193,19 @@ pub fn r#type(parser: &mut Parser, streams: &mut IoStreams, argv: &mut [&wstr])
     let mut res = false;
 
     let optind = w.wopt_index;
+    if opts.json {
+        for arg in argv.iter().take(argc).skip(optind) {
+            let (json, found) = resolution_json(parser, arg, true, !opts.no_functions);
+            streams.out.appendln(&json);
+            res |= found;
+        }
+        return if res {
+            Ok(SUCCESS)
+        } else {
+            Err(STATUS_CMD_ERROR)
+        };
+    }
+
     for arg in argv.iter().take(argc).skip(optind) {
         let mut found = 0;
         if !opts.force_path && !opts.no_functions {
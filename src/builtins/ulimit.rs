@@ -6,6 +6,7 @@ use nix::sys::resource::Resource as ResourceEnum;
 
 use crate::{builtins::Error, err_fmt, err_raw, err_str, wutil::perror_nix};
 use fish_fallback::{fish_wcswidth, wcscasecmp};
+use fish_wcstringutil::join_strings;
 
 use super::prelude::*;
 
@@ -58,6 +59,7 @@ pub mod limits {
     define_on!(MSGQUEUE, RLIMIT_MSGQUEUE; "linux");
     define_on!(RTPRIO, RLIMIT_RTPRIO; "linux");
     define_on!(RTTIME, RLIMIT_RTTIME; "linux");
+    define_on!(LOCKS, RLIMIT_LOCKS; "linux");
     define_on!(RSS, RLIMIT_RSS; "linux", "freebsd", "netbsd", "openbsd", "dragonfly");
     // TODO(MSRV >= 1.86): target_os = "cygwin" triggers a warning on Rust 1.85.
     #[cfg(any(
@@ -161,6 +163,51 @@ fn print_all(hard: bool, streams: &mut IoStreams) {
     }
 }
 
+/// Print the soft and hard values of all resource limits as a JSON object, keyed by the long
+/// option name. Sizes are in bytes and unlimited values are null.
+fn print_json(streams: &mut IoStreams) {
+    let value = |l: rlim_t| {
+        if l == RLIM_INFINITY {
+            L!("null").to_owned()
+        } else {
+            l.to_wstring()
+        }
+    };
+    let mut entries = vec![];
+    for resource in RESOURCE_ARR.iter() {
+        let Some((rlim_cur, rlim_max)) = getrlimit(resource.resource) else {
+            continue;
+        };
+        entries.push(sprintf!(
+            "\"%s\":{\"soft\":%s,\"hard\":%s}",
+            resource.name,
+            value(rlim_cur),
+            value(rlim_max)
+        ));
+    }
+    streams
+        .out
+        .appendln(&sprintf!("{%s}", join_strings(&entries, ',')));
+}
+
+/// Parse a limit with a size suffix like `512M` into bytes. Only `K`, `M`, `G` and `T` are
+/// recognized, in either case, as powers of 1024.
+fn parse_size(arg: &wstr) -> Option<rlim_t> {
+    let shift = match arg.chars().next_back()?.to_ascii_uppercase() {
+        'K' => 10,
+        'M' => 20,
+        'G' => 30,
+        'T' => 40,
+        _ => return None,
+    };
+    let number = &arg[..arg.len() - 1];
+    if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let number = fish_wcstoul(number).ok()? as rlim_t;
+    number.checked_mul(1 << shift)
+}
+
 /// Returns the description for the specified resource limit.
 fn get_desc(what: c_uint) -> &'static wstr {
     for resource in RESOURCE_ARR.iter() {
@@ -235,6 +282,7 @@ fn get(resource: c_uint, hard: bool) -> Option<rlim_t> {
 struct Options {
     what: c_int,
     report_all: bool,
+    json: bool,
     hard: bool,
     soft: bool,
 }
@@ -244,6 +292,7 @@ impl Default for Options {
         Options {
             what: limits::FSIZE,
             report_all: false,
+            json: false,
             hard: false,
             soft: false,
         }
@@ -253,7 +302,8 @@ impl Default for Options {
 pub fn ulimit(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr]) -> BuiltinResult {
     let cmd = args[0];
 
-    const SHORT_OPTS: &wstr = L!("HSabcdefilmnqrstuvwyKPTh");
+    const SHORT_OPTS: &wstr = L!("HSabcdefilmnqrstuvwxyKPTh");
+    const JSON_SHORT: char = '\x01';
 
     const LONG_OPTS: &[WOption] = &[
         wopt(L!("all"), ArgType::NoArgument, 'a'),
@@ -275,10 +325,12 @@ pub fn ulimit(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
         wopt(L!("process-count"), ArgType::NoArgument, 'u'),
         wopt(L!("virtual-memory-size"), ArgType::NoArgument, 'v'),
         wopt(L!("swap-size"), ArgType::NoArgument, 'w'),
+        wopt(L!("file-locks"), ArgType::NoArgument, 'x'),
         wopt(L!("realtime-maxtime"), ArgType::NoArgument, 'y'),
         wopt(L!("kernel-queues"), ArgType::NoArgument, 'K'),
         wopt(L!("ptys"), ArgType::NoArgument, 'P'),
         wopt(L!("threads"), ArgType::NoArgument, 'T'),
+        wopt(L!("json"), ArgType::NoArgument, JSON_SHORT),
         wopt(L!("help"), ArgType::NoArgument, 'h'),
     ];
 
@@ -307,10 +359,12 @@ pub fn ulimit(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
             'u' => opts.what = limits::NPROC,
             'v' => opts.what = limits::AS,
             'w' => opts.what = limits::SWAP,
+            'x' => opts.what = limits::LOCKS,
             'y' => opts.what = limits::RTTIME,
             'K' => opts.what = limits::KQUEUES,
             'P' => opts.what = limits::NPTS,
             'T' => opts.what = limits::NTHR,
+            JSON_SHORT => opts.json = true,
             'h' => {
                 builtin_print_help(parser, streams, cmd);
                 return Ok(SUCCESS);
@@ -340,6 +394,11 @@ pub fn ulimit(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
         }
     }
 
+    if opts.json {
+        print_json(streams);
+        return Ok(SUCCESS);
+    }
+
     if opts.report_all {
         print_all(opts.hard, streams);
     }
@@ -400,6 +459,10 @@ pub fn ulimit(parser: &mut Parser, streams: &mut IoStreams, args: &mut [&wstr])
             return Err(STATUS_INVALID_ARGS);
         };
         x
+    } else if let Some(bytes) =
+        parse_size(w.argv[w.wopt_index]).filter(|_| get_multiplier(what) == 1024)
+    {
+        bytes
     } else {
         err_fmt!(BUILTIN_ULIMIT_INVALID, w.argv[w.wopt_index])
             .cmd(cmd)
@@ -416,6 +479,7 @@ struct Resource {
     resource: c_uint,    // resource ID
     desc: &'static wstr, // description of resource
     switch_char: char,   // switch used on commandline to specify resource
+    name: &'static wstr, // long option used on commandline to specify resource
     multiplier: c_uint,  // the implicit multiplier used when setting getting values
 }
 
@@ -424,12 +488,14 @@ impl Resource {
         resource: c_uint,
         desc: &'static wstr,
         switch_char: char,
+        name: &'static wstr,
         multiplier: c_uint,
     ) -> Resource {
         Resource {
             resource,
             desc,
             switch_char,
+            name,
             multiplier,
         }
     }
@@ -442,95 +508,147 @@ static RESOURCE_ARR: LazyLock<Box<[Resource]>> = LazyLock::new(|| {
             limits::SBSIZE,
             L!("Maximum size of socket buffers"),
             'b',
+            L!("socket-buffers"),
             1024,
         ),
         (
             limits::CORE,
             L!("Maximum size of core files created"),
             'c',
+            L!("core-size"),
             1024,
         ),
         (
             limits::DATA,
             L!("Maximum size of a process’s data segment"),
             'd',
+            L!("data-size"),
             1024,
         ),
-        (limits::NICE, L!("Control of maximum nice priority"), 'e', 1),
+        (
+            limits::NICE,
+            L!("Control of maximum nice priority"),
+            'e',
+            L!("nice"),
+            1,
+        ),
         (
             limits::FSIZE,
             L!("Maximum size of files created by the shell"),
             'f',
+            L!("file-size"),
             1024,
         ),
         (
             limits::SIGPENDING,
             L!("Maximum number of pending signals"),
             'i',
+            L!("pending-signals"),
             1,
         ),
         (
             limits::MEMLOCK,
             L!("Maximum size that may be locked into memory"),
             'l',
+            L!("lock-size"),
+            1024,
+        ),
+        (
+            limits::RSS,
+            L!("Maximum resident set size"),
+            'm',
+            L!("resident-set-size"),
             1024,
         ),
-        (limits::RSS, L!("Maximum resident set size"), 'm', 1024),
         (
             limits::NOFILE,
             L!("Maximum number of open file descriptors"),
             'n',
+            L!("file-descriptor-count"),
             1,
         ),
         (
             limits::MSGQUEUE,
             L!("Maximum bytes in POSIX message queues"),
             'q',
+            L!("queue-size"),
             1024,
         ),
         (
             limits::RTPRIO,
             L!("Maximum realtime scheduling priority"),
             'r',
+            L!("realtime-priority"),
             1,
         ),
-        (limits::STACK, L!("Maximum stack size"), 's', 1024),
+        (
+            limits::STACK,
+            L!("Maximum stack size"),
+            's',
+            L!("stack-size"),
+            1024,
+        ),
         (
             limits::CPU,
             L!("Maximum amount of CPU time in seconds"),
             't',
+            L!("cpu-time"),
             1,
         ),
         (
             limits::NPROC,
             L!("Maximum number of processes available to current user"),
             'u',
+            L!("process-count"),
             1,
         ),
         (
             limits::AS,
             L!("Maximum amount of virtual memory available to each process"),
             'v',
+            L!("virtual-memory-size"),
             1024,
         ),
-        (limits::SWAP, L!("Maximum swap space"), 'w', 1024),
+        (
+            limits::SWAP,
+            L!("Maximum swap space"),
+            'w',
+            L!("swap-size"),
+            1024,
+        ),
+        (
+            limits::LOCKS,
+            L!("Maximum number of file locks"),
+            'x',
+            L!("file-locks"),
+            1,
+        ),
         (
             limits::RTTIME,
             L!("Maximum contiguous realtime CPU time"),
             'y',
+            L!("realtime-maxtime"),
+            1,
+        ),
+        (
+            limits::KQUEUES,
+            L!("Maximum number of kqueues"),
+            'K',
+            L!("kernel-queues"),
             1,
         ),
-        (limits::KQUEUES, L!("Maximum number of kqueues"), 'K', 1),
         (
             limits::NPTS,
             L!("Maximum number of pseudo-terminals"),
             'P',
+            L!("ptys"),
             1,
         ),
         (
             limits::NTHR,
             L!("Maximum number of simultaneous threads"),
             'T',
+            L!("threads"),
             1,
         ),
     ];
@@ -539,12 +657,13 @@ static RESOURCE_ARR: LazyLock<Box<[Resource]>> = LazyLock::new(|| {
 
     let mut resources = Vec::new();
     for resource in resources_info {
-        let (resource, desc, switch_char, multiplier) = resource;
+        let (resource, desc, switch_char, name, multiplier) = resource;
         if resource != unknown {
             resources.push(Resource::new(
                 resource as c_uint,
                 desc,
                 switch_char,
+                name,
                 multiplier,
             ));
         }
@@ -2,7 +2,7 @@ use crate::{
     abbrs::{Position, with_abbrs},
     ast::unescape_keyword,
     autoload::{Autoload, AutoloadResult},
-    builtins::{builtin_exists, builtin_get_desc, builtin_get_names},
+    builtins::{builtin_exists, builtin_get_desc, builtin_get_names, fish_json::Value},
     common::valid_var_name_char,
     env::{EnvMode, EnvStack, Environment},
     exec::exec_subshell,
@@ -12,11 +12,15 @@ use crate::{
     },
     flog::{flog, flogf},
     function,
+    global_safety::RelaxedAtomicBool,
+    help_parser,
     history::{History, history_id},
     localization::{LocalizableString, localizable_string},
     operation_context::OperationContext,
     parse_constants::SourceRange,
-    parse_util::{get_cmdsubst_extent, get_process_extent, unescape_wildcards},
+    parse_util::{
+        detect_errors_in_argument_list, get_cmdsubst_extent, get_process_extent, unescape_wildcards,
+    },
     parser::{Block, BlockId, Parser, ParserEnvSetMode},
     parser_keywords::parser_keywords_is_subcommand,
     path::{path_get_path, path_try_get_path},
@@ -31,12 +35,12 @@ use bitflags::bitflags;
 use fish_common::{ScopeGuard, UnescapeFlags, UnescapeStringStyle, escape, unescape_string};
 use fish_util::wcsfilecmp;
 use fish_wcstringutil::{
-    StringFuzzyMatch, string_fuzzy_match_string, string_prefixes_string,
+    StringFuzzyMatch, join_strings, string_fuzzy_match_string, string_prefixes_string,
     string_suffixes_string_case_insensitive, strip_executable_suffix,
 };
 use fish_widestring::{WExt as _, charptr2wcstring};
 use std::{
-    cmp::Ordering,
+    cmp::{Ordering, Reverse},
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     mem,
     ops::{Deref, DerefMut},
@@ -60,6 +64,11 @@ localizable_consts!(
     /// Description for short variables. The value is concatenated to this description.
     COMPLETE_VAR_DESC_VAL "Variable: %s"
 
+    /// Descriptions for variables in the scope they are found in.
+    COMPLETE_LOCAL_VAR_DESC_VAL "Local variable: %s"
+    COMPLETE_GLOBAL_VAR_DESC_VAL "Global variable: %s"
+    COMPLETE_UNIVERSAL_VAR_DESC_VAL "Universal variable: %s"
+
     /// Description for abbreviations.
     ABBR_DESC "Abbreviation: %s"
 );
@@ -330,18 +339,21 @@ impl CompletionReceiver {
         std::mem::take(&mut self.completions)
     }
 
+    /// Returns how many more completions may be added.
+    pub fn remaining_capacity(&self) -> usize {
+        self.limit
+            .checked_sub(self.completions.len())
+            .expect("length should never be larger than limit")
+    }
+
     /// Returns a new, empty receiver whose limit is our remaining capacity.
     /// This is useful for e.g. recursive calls when you want to act on the result before adding it.
     pub fn subreceiver(&self) -> Self {
-        let remaining_capacity = self
-            .limit
-            .checked_sub(self.completions.len())
-            .expect("length should never be larger than limit");
-        Self::new(remaining_capacity)
+        Self::new(self.remaining_capacity())
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum CompleteOptionType {
     /// no option
     ArgsOnly,
@@ -396,9 +408,23 @@ impl CompleteEntryOpt {
 /// Last value used in the order field of [`CompletionEntry`].
 static COMPLETE_ORDER: AtomicUsize = AtomicUsize::new(0);
 
+/// How the expanded arguments of the completions of a command are cached, so that slow commands
+/// in them are not run on every completion request. See `complete --cache-ttl`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CompletionCachePolicy {
+    /// How long cached arguments are used.
+    pub ttl: Duration,
+    /// An argument list expanded at completion time, cached arguments are only used while it
+    /// expands to the same strings. Typically this contains something like the modification time
+    /// of a file the arguments depend on.
+    pub key: WString,
+}
+
 struct CompletionEntry {
     /// List of all options.
     options: Vec<CompleteEntryOpt>,
+    /// Whether the arguments of the options are cached, and how.
+    cache: Option<CompletionCachePolicy>,
     /// Order for when this completion was created. This aids in outputting completions sorted by
     /// time.
     order: usize,
@@ -408,6 +434,7 @@ impl CompletionEntry {
     pub fn new() -> Self {
         Self {
             options: vec![],
+            cache: None,
             order: COMPLETE_ORDER.fetch_add(1, atomic::Ordering::Relaxed),
         }
     }
@@ -442,10 +469,55 @@ type CompletionEntryMap = BTreeMap<CompletionEntryIndex, CompletionEntry>;
 static COMPLETION_MAP: Mutex<CompletionEntryMap> = Mutex::new(BTreeMap::new());
 static COMPLETION_TOMBSTONES: Mutex<BTreeSet<WString>> = Mutex::new(BTreeSet::new());
 
+/// The cached expansions of completion arguments, for commands with a [`CompletionCachePolicy`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CachedArgumentsKey {
+    /// The command the completion belongs to.
+    index: CompletionEntryIndex,
+    /// The unexpanded arguments.
+    args: WString,
+    /// The command line before the token being completed, since the arguments often depend on it.
+    context: WString,
+    /// The working directory.
+    pwd: WString,
+    /// The expansion of the key of the cache policy.
+    key: Vec<WString>,
+}
+
+struct CachedArguments {
+    expires: Instant,
+    completions: CompletionList,
+}
+
+static ARGUMENT_CACHE: LazyLock<Mutex<HashMap<CachedArgumentsKey, CachedArguments>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
 /// Completion "wrapper" support. The map goes from wrapping-command to wrapped-command-list.
 type WrapperMap = HashMap<WString, Vec<WString>>;
 static WRAPPER_MAP: LazyLock<Mutex<WrapperMap>> = LazyLock::new(|| Mutex::new(HashMap::new()));
 
+/// Whether completions are matched like in fuzzy finders, per `$fish_completion_match_mode`.
+static FUZZY_MATCH_MODE: RelaxedAtomicBool = RelaxedAtomicBool::new(false);
+
+/// Set whether completions are matched like in fuzzy finders.
+pub fn complete_set_fuzzy_match_mode(fuzzy: bool) {
+    FUZZY_MATCH_MODE.store(fuzzy);
+}
+
+/// Match the string being completed against a candidate. Unless `anchor_start` is set, in fuzzy
+/// matching mode any subsequence matches, with a score to rank the candidates.
+pub(crate) fn completion_fuzzy_match(
+    string: &wstr,
+    match_against: &wstr,
+    anchor_start: bool,
+) -> Option<StringFuzzyMatch> {
+    if FUZZY_MATCH_MODE.load() && !anchor_start && !string.is_empty() {
+        StringFuzzyMatch::try_create_scored(string, match_against)
+    } else {
+        string_fuzzy_match_string(string, match_against, anchor_start)
+    }
+}
+
 /// Clear the [`CompleteFlags::AUTO_SPACE`] flag, and set [`CompleteFlags::NO_SPACE`] appropriately
 /// depending on the suffix of the string.
 fn resolve_auto_space(comp: &wstr, mut flags: CompleteFlags) -> CompleteFlags {
@@ -533,6 +605,9 @@ pub fn sort_and_prioritize(comps: &mut Vec<Completion>, flags: CompletionRequest
     // Here we do not pass suppress_exact, so that exact matches appear first.
     comps.sort_by(natural_compare_completions);
 
+    // Scored matches are ordered by relevance.
+    comps.sort_by_key(|c| Reverse(c.r#match.score));
+
     // Lastly, if this is for an autosuggestion, prefer to avoid completions that duplicate
     // arguments, and penalize files that end in tilde - they're frequently autosave files from e.g.
     // emacs. Also prefer samecase to smartcase.
@@ -597,6 +672,8 @@ struct Completer<'ctx, 'parser> {
     /// Table of completions conditions that have already been tested and the corresponding test
     /// results.
     condition_cache: HashMap<WString, bool>,
+    /// The command line before the token being completed, for keying cached arguments.
+    argument_cache_context: WString,
 }
 
 static COMPLETION_AUTOLOADER: LazyLock<Mutex<Autoload>> =
@@ -611,6 +688,7 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
             completions: CompletionReceiver::new(expansion_limit),
             needs_load: vec![],
             condition_cache: HashMap::new(),
+            argument_cache_context: WString::new(),
         }
     }
 
@@ -816,6 +894,8 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
             if let (Some(prev), Some(cur)) = (prev, cur) {
                 arg_data.previous_argument = prev;
                 arg_data.current_argument = cur;
+                let context = cmdline[..cmdline.len() - current_argument.len()].to_owned();
+                let saved_context = mem::replace(&mut self.argument_cache_context, context);
                 // Have to walk over the command and its entire wrap chain. If any command
                 // disables do_file, then they all do.
                 self.walk_wrap_chain(
@@ -824,6 +904,7 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
                     command_range,
                     &mut arg_data,
                 );
+                self.argument_cache_context = saved_context;
                 do_file = if arg_data.do_file {
                     DoFile::Yes
                 } else {
@@ -1200,7 +1281,15 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
     /// - `args`: The list of option arguments to be evaluated.
     /// - `desc`: Description of the completion
     /// - `flags`: The flags
-    fn complete_from_args(&mut self, s: &wstr, args: &wstr, desc: &wstr, flags: CompleteFlags) {
+    /// - `cache`: The command the arguments belong to and its cache policy, if they are cached.
+    fn complete_from_args(
+        &mut self,
+        s: &wstr,
+        args: &wstr,
+        desc: &wstr,
+        flags: CompleteFlags,
+        cache: Option<(&CompletionEntryIndex, &CompletionCachePolicy)>,
+    ) {
         let is_autosuggest = self.flags.autosuggestion;
 
         let mut saved_statuses = None;
@@ -1216,7 +1305,48 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
             ExpandFlags::empty()
         };
 
-        let possible_comp = Parser::expand_argument_list(args, eflags, self.ctx);
+        let cache_key = cache.map(|(index, policy)| CachedArgumentsKey {
+            index: index.clone(),
+            args: args.to_owned(),
+            context: self.argument_cache_context.clone(),
+            pwd: self.ctx.vars().get_pwd_slash(),
+            key: Parser::expand_argument_list(&policy.key, eflags, self.ctx)
+                .into_iter()
+                .map(|c| c.completion)
+                .collect(),
+        });
+        let cached = cache_key.as_ref().and_then(|key| {
+            let cache = ARGUMENT_CACHE.lock().unwrap();
+            cache
+                .get(key)
+                .filter(|cached| cached.expires > Instant::now())
+                .map(|cached| cached.completions.clone())
+        });
+        let possible_comp = match cached {
+            Some(possible_comp) => {
+                flog!(complete, "Using cached arguments for", args);
+                possible_comp
+            }
+            None => {
+                let possible_comp = Parser::expand_argument_list(args, eflags, self.ctx);
+                // Autosuggestions don't run command substitutions, so they only read the cache.
+                if let (Some(key), Some((_, policy))) = (cache_key, cache) {
+                    if !is_autosuggest && !self.ctx.check_cancel() {
+                        let now = Instant::now();
+                        let mut cache = ARGUMENT_CACHE.lock().unwrap();
+                        cache.retain(|_, cached| cached.expires > now);
+                        cache.insert(
+                            key,
+                            CachedArguments {
+                                expires: now + policy.ttl,
+                                completions: possible_comp.clone(),
+                            },
+                        );
+                    }
+                }
+                possible_comp
+            }
+        };
 
         if let Some(parser) = self.ctx.maybe_parser() {
             parser.set_last_statuses(saved_statuses.unwrap());
@@ -1272,6 +1402,7 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
             flog!(complete, "Skipping completions for non-existent command");
         } else if let Some(parser) = self.ctx.maybe_parser() {
             complete_load(&cmd, parser);
+            complete_load_from_help(&cmd, parser.vars());
         } else if !COMPLETION_AUTOLOADER
             .lock()
             .unwrap()
@@ -1280,8 +1411,13 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
             self.needs_load.push(cmd.clone());
         }
 
-        // Make a list of lists of all options that we care about.
-        let all_options: Vec<Vec<CompleteEntryOpt>> = COMPLETION_MAP
+        // Make a list of lists of all options that we care about, along with their command and its
+        // cache policy.
+        let all_options: Vec<(
+            CompletionEntryIndex,
+            Option<CompletionCachePolicy>,
+            Vec<CompleteEntryOpt>,
+        )> = COMPLETION_MAP
             .lock()
             .unwrap()
             .iter()
@@ -1300,7 +1436,7 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
                     let mut options = completion.get_options().to_vec();
                     // We have to copy them in reverse order to preserve legacy behavior (#9221).
                     options.reverse();
-                    Some(options)
+                    Some((idx.clone(), completion.cache.clone(), options))
                 } else {
                     None
                 }
@@ -1309,7 +1445,8 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
 
         // Now release the lock and test each option that we captured above. We have to do this outside
         // the lock because callouts (like the condition) may add or remove completions. See issue #2.
-        for options in all_options {
+        for (index, cache_policy, options) in all_options {
+            let cache = cache_policy.as_ref().map(|policy| (&index, policy));
             let short_opt_pos = short_option_pos(s, &options);
             // We want last_option_requires_param to default to false but distinguish between when
             // a previous completion has set it to false and when it has its default value.
@@ -1352,7 +1489,13 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
                                 }
                                 let (arg_prefix, arg) = s.split_once(arg_offset);
                                 let first_new = self.completions.completions.len();
-                                self.complete_from_args(arg, &o.comp, o.desc.localize(), o.flags);
+                                self.complete_from_args(
+                                    arg,
+                                    &o.comp,
+                                    o.desc.localize(),
+                                    o.flags,
+                                    cache,
+                                );
                                 for compl in &mut self.completions.completions[first_new..] {
                                     if compl.replaces_token() {
                                         compl.completion.insert_utfstr(0, arg_prefix);
@@ -1383,7 +1526,7 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
                             if o.result_mode.force_files {
                                 has_force = true;
                             }
-                            self.complete_from_args(s, &o.comp, o.desc.localize(), o.flags);
+                            self.complete_from_args(s, &o.comp, o.desc.localize(), o.flags, cache);
                         }
                     }
 
@@ -1419,7 +1562,13 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
                                 if o.result_mode.force_files {
                                     has_force = true;
                                 }
-                                self.complete_from_args(s, &o.comp, o.desc.localize(), o.flags);
+                                self.complete_from_args(
+                                    s,
+                                    &o.comp,
+                                    o.desc.localize(),
+                                    o.flags,
+                                    cache,
+                                );
                             }
                         }
                     }
@@ -1442,7 +1591,7 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
                 if o.option.is_empty() {
                     use_files &= !o.result_mode.no_files;
                     has_force |= o.result_mode.force_files;
-                    self.complete_from_args(s, &o.comp, o.desc.localize(), o.flags);
+                    self.complete_from_args(s, &o.comp, o.desc.localize(), o.flags, cache);
                 }
 
                 if !use_switches || s.is_empty() {
@@ -1500,7 +1649,7 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
                     continue;
                 }
                 let anchor_start = !self.flags.fuzzy_match;
-                let Some(r#match) = string_fuzzy_match_string(s, &whole_opt, anchor_start) else {
+                let Some(r#match) = completion_fuzzy_match(s, &whole_opt, anchor_start) else {
                     continue;
                 };
 
@@ -1676,7 +1825,7 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
 
         for env_name in self.ctx.vars().get_names(EnvMode::empty()) {
             let anchor_start = !self.flags.fuzzy_match;
-            let Some(r#match) = string_fuzzy_match_string(var, &env_name, anchor_start) else {
+            let Some(r#match) = completion_fuzzy_match(var, &env_name, anchor_start) else {
                 continue;
             };
 
@@ -1710,7 +1859,16 @@ impl<'ctx, 'parser> Completer<'ctx, 'parser> {
                     };
 
                     let value = expand_escape_variable(&var);
-                    desc = wgettext_fmt!(COMPLETE_VAR_DESC_VAL, value);
+                    let vars = self.ctx.vars();
+                    desc = if vars.getf(&env_name, EnvMode::LOCAL).is_some() {
+                        wgettext_fmt!(COMPLETE_LOCAL_VAR_DESC_VAL, value)
+                    } else if vars.getf(&env_name, EnvMode::GLOBAL).is_some() {
+                        wgettext_fmt!(COMPLETE_GLOBAL_VAR_DESC_VAL, value)
+                    } else if vars.getf(&env_name, EnvMode::UNIVERSAL).is_some() {
+                        wgettext_fmt!(COMPLETE_UNIVERSAL_VAR_DESC_VAL, value)
+                    } else {
+                        wgettext_fmt!(COMPLETE_VAR_DESC_VAL, value)
+                    };
                 }
             }
 
@@ -2326,6 +2484,360 @@ pub fn complete_add(
     c.add_option(opt);
 }
 
+/// A completion rule read from a JSON description. They are collected before any is added, so
+/// that an invalid description adds nothing.
+struct JsonCompletionRule {
+    option: WString,
+    option_type: CompleteOptionType,
+    result_mode: CompletionMode,
+    condition: Vec<WString>,
+    comp: WString,
+    desc: WString,
+}
+
+/// The keys allowed in the description of a command or subcommand.
+const JSON_NODE_KEYS: &[&str] = &[
+    "name",
+    "description",
+    "options",
+    "arguments",
+    "files",
+    "subcommands",
+];
+
+/// Returns the members of a JSON object, checking that it has only the allowed keys.
+fn json_members<'a>(
+    value: &'a Value,
+    path: &wstr,
+    allowed: &[&str],
+) -> Result<&'a [(WString, Value)], WString> {
+    let Value::Object(members) = value else {
+        return Err(wgettext_fmt!("%s: expected an object", path));
+    };
+    if let Some((key, _)) = members
+        .iter()
+        .find(|(key, _)| !allowed.iter().any(|k| key == k))
+    {
+        return Err(wgettext_fmt!("%s: unknown key '%s'", path, key));
+    }
+    Ok(members)
+}
+
+fn json_get<'a>(members: &'a [(WString, Value)], key: &str) -> Option<&'a Value> {
+    members.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+fn json_get_string<'a>(
+    members: &'a [(WString, Value)],
+    path: &wstr,
+    key: &str,
+) -> Result<Option<&'a wstr>, WString> {
+    match json_get(members, key) {
+        None => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(wgettext_fmt!("%s.%s: expected a string", path, key)),
+    }
+}
+
+fn json_bool(
+    members: &[(WString, Value)],
+    path: &wstr,
+    key: &str,
+) -> Result<Option<bool>, WString> {
+    match json_get(members, key) {
+        None => Ok(None),
+        Some(Value::Bool(b)) => Ok(Some(*b)),
+        Some(_) => Err(wgettext_fmt!("%s.%s: expected true or false", path, key)),
+    }
+}
+
+/// Returns the names given as a string or an array of strings.
+fn json_names(
+    members: &[(WString, Value)],
+    path: &wstr,
+    key: &str,
+) -> Result<Vec<WString>, WString> {
+    let names = match json_get(members, key) {
+        None => return Ok(vec![]),
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => Ok(s.clone()),
+                _ => Err(()),
+            })
+            .collect::<Result<_, _>>()
+            .map_err(|()| {
+                wgettext_fmt!("%s.%s: expected a string or an array of strings", path, key)
+            })?,
+        Some(_) => {
+            return Err(wgettext_fmt!(
+                "%s.%s: expected a string or an array of strings",
+                path,
+                key
+            ));
+        }
+    };
+    if names.iter().any(|name| name.is_empty()) {
+        return Err(wgettext_fmt!("%s.%s: names must not be empty", path, key));
+    }
+    Ok(names)
+}
+
+/// Returns the argument completions as pairs of an argument list like for `complete --arguments`,
+/// and a description. A string is an argument list itself, which may contain command
+/// substitutions to compute the arguments at completion time. An array may contain plain values,
+/// and objects with a value and a description.
+fn json_arguments(
+    members: &[(WString, Value)],
+    path: &wstr,
+) -> Result<Vec<(WString, WString)>, WString> {
+    let path = path.to_owned() + L!(".arguments");
+    let mut result = vec![];
+    match json_get(members, "arguments") {
+        None => (),
+        Some(Value::String(s)) => {
+            detect_errors_in_argument_list(s, &path)?;
+            result.push((s.clone(), WString::new()));
+        }
+        Some(Value::Array(items)) => {
+            let mut plain = vec![];
+            for (i, item) in items.iter().enumerate() {
+                match item {
+                    Value::String(s) => plain.push(escape(s)),
+                    _ => {
+                        let item_path = sprintf!("%s[%u]", path, i);
+                        let members = json_members(item, &item_path, &["value", "description"])?;
+                        let Some(value) = json_get_string(members, &item_path, "value")? else {
+                            return Err(wgettext_fmt!("%s: missing 'value'", item_path));
+                        };
+                        let desc = json_get_string(members, &item_path, "description")?;
+                        result.push((escape(value), desc.unwrap_or_default().to_owned()));
+                    }
+                }
+            }
+            if !plain.is_empty() {
+                result.push((join_strings(&plain, ' '), WString::new()));
+            }
+        }
+        Some(_) => {
+            return Err(wgettext_fmt!("%s: expected a string or an array", path));
+        }
+    }
+    Ok(result)
+}
+
+/// Collect the completion rules for a command or subcommand, and recursively for its subcommands.
+/// `subcommands` are the names of the subcommands leading to this one.
+fn json_collect_rules(
+    node: &Value,
+    path: &wstr,
+    subcommands: &[WString],
+    rules: &mut Vec<JsonCompletionRule>,
+) -> Result<(), WString> {
+    let members = json_members(node, path, JSON_NODE_KEYS)?;
+
+    let children = match json_get(members, "subcommands") {
+        None => &[][..],
+        Some(Value::Array(children)) => &children[..],
+        Some(_) => {
+            return Err(wgettext_fmt!("%s.subcommands: expected an array", path));
+        }
+    };
+    let mut child_names = vec![];
+    for (i, child) in children.iter().enumerate() {
+        let child_path = sprintf!("%s.subcommands[%u]", path, i);
+        let child_members = json_members(child, &child_path, JSON_NODE_KEYS)?;
+        match json_get_string(child_members, &child_path, "name")? {
+            Some(name) if !name.is_empty() => child_names.push(name.to_owned()),
+            _ => return Err(wgettext_fmt!("%s: missing 'name'", child_path)),
+        }
+    }
+
+    // The rules for this node apply once exactly its subcommands have been given.
+    let condition = if subcommands.is_empty() && child_names.is_empty() {
+        vec![]
+    } else {
+        let words: Vec<WString> = [L!("__fish_seen_subcommand_path").to_owned()]
+            .into_iter()
+            .chain(subcommands.iter().map(|name| escape(name)))
+            .chain([L!("--").to_owned()])
+            .chain(child_names.iter().map(|name| escape(name)))
+            .collect();
+        vec![join_strings(&words, ' ')]
+    };
+    let mut add_rule = |option: WString,
+                        option_type: CompleteOptionType,
+                        result_mode: CompletionMode,
+                        comp: WString,
+                        desc: WString| {
+        rules.push(JsonCompletionRule {
+            option,
+            option_type,
+            result_mode,
+            condition: condition.clone(),
+            comp,
+            desc,
+        });
+    };
+
+    let no_files = !child_names.is_empty() || json_bool(members, path, "files")? == Some(false);
+    if no_files {
+        let result_mode = CompletionMode {
+            no_files: true,
+            ..Default::default()
+        };
+        add_rule(
+            WString::new(),
+            CompleteOptionType::ArgsOnly,
+            result_mode,
+            WString::new(),
+            WString::new(),
+        );
+    }
+    for (comp, desc) in json_arguments(members, path)? {
+        add_rule(
+            WString::new(),
+            CompleteOptionType::ArgsOnly,
+            CompletionMode::default(),
+            comp,
+            desc,
+        );
+    }
+    for (child, name) in children.iter().zip(&child_names) {
+        let Value::Object(child_members) = child else {
+            unreachable!();
+        };
+        let desc = match json_get(child_members, "description") {
+            Some(Value::String(desc)) => desc.clone(),
+            _ => WString::new(),
+        };
+        add_rule(
+            WString::new(),
+            CompleteOptionType::ArgsOnly,
+            CompletionMode::default(),
+            escape(name),
+            desc,
+        );
+    }
+
+    let options = match json_get(members, "options") {
+        None => &[][..],
+        Some(Value::Array(options)) => &options[..],
+        Some(_) => {
+            return Err(wgettext_fmt!("%s.options: expected an array", path));
+        }
+    };
+    for (i, option) in options.iter().enumerate() {
+        let option_path = sprintf!("%s.options[%u]", path, i);
+        let option_members = json_members(
+            option,
+            &option_path,
+            &[
+                "short",
+                "long",
+                "old",
+                "description",
+                "arguments",
+                "requires_argument",
+                "files",
+            ],
+        )?;
+        let mut names = vec![];
+        for short in json_names(option_members, &option_path, "short")? {
+            names.extend(
+                short
+                    .chars()
+                    .map(|c| (WString::from_chars([c]), CompleteOptionType::Short)),
+            );
+        }
+        for long in json_names(option_members, &option_path, "long")? {
+            names.push((long, CompleteOptionType::DoubleLong));
+        }
+        for old in json_names(option_members, &option_path, "old")? {
+            names.push((old, CompleteOptionType::SingleLong));
+        }
+        if names.is_empty() {
+            return Err(wgettext_fmt!(
+                "%s: expected at least one of 'short', 'long' or 'old'",
+                option_path
+            ));
+        }
+        let desc = json_get_string(option_members, &option_path, "description")?
+            .unwrap_or_default()
+            .to_owned();
+        let arguments = json_arguments(option_members, &option_path)?;
+        let files = json_bool(option_members, &option_path, "files")?;
+        let result_mode = CompletionMode {
+            // Offering arguments implies that files are not wanted, unless asked for.
+            no_files: files == Some(false) || (!arguments.is_empty() && files.is_none()),
+            force_files: false,
+            requires_param: !arguments.is_empty()
+                || json_bool(option_members, &option_path, "requires_argument")? == Some(true),
+        };
+        for (name, option_type) in names {
+            if arguments.is_empty() {
+                add_rule(
+                    name.clone(),
+                    option_type,
+                    result_mode,
+                    WString::new(),
+                    desc.clone(),
+                );
+            }
+            for (comp, arg_desc) in &arguments {
+                let desc = if arg_desc.is_empty() { &desc } else { arg_desc };
+                add_rule(
+                    name.clone(),
+                    option_type,
+                    result_mode,
+                    comp.clone(),
+                    desc.clone(),
+                );
+            }
+        }
+    }
+
+    for (i, (child, name)) in children.iter().zip(&child_names).enumerate() {
+        let child_path = sprintf!("%s.subcommands[%u]", path, i);
+        let mut child_subcommands = subcommands.to_vec();
+        child_subcommands.push(name.clone());
+        json_collect_rules(child, &child_path, &child_subcommands, rules)?;
+    }
+    Ok(())
+}
+
+/// Add the completions described by a JSON document, which describes the options, arguments and
+/// subcommands of a command as a nested tree. The command is named by the document's "name", unless
+/// `cmd` is given. Returns the name of the command, or a description of what is wrong with the
+/// document, in which case nothing is added.
+pub(crate) fn complete_load_json(document: &Value, cmd: Option<&wstr>) -> Result<WString, WString> {
+    let path = L!("$");
+    let members = json_members(document, path, JSON_NODE_KEYS)?;
+    let cmd = match (cmd, json_get_string(members, path, "name")?) {
+        (Some(cmd), _) => cmd.to_owned(),
+        (None, Some(name)) if !name.is_empty() => name.to_owned(),
+        (None, _) => return Err(wgettext_fmt!("%s: missing 'name'", path)),
+    };
+    let mut rules = vec![];
+    json_collect_rules(document, path, &[], &mut rules)?;
+    let flags = CompleteFlags::AUTO_SPACE | CompleteFlags::DONT_ESCAPE_TILDES;
+    for rule in rules {
+        complete_add(
+            cmd.clone(),
+            false,
+            rule.option,
+            rule.option_type,
+            rule.result_mode,
+            rule.condition,
+            rule.comp,
+            rule.desc,
+            flags,
+        );
+    }
+    Ok(cmd)
+}
+
 /// Remove a previously defined completion.
 pub fn complete_remove(cmd: WString, cmd_is_path: bool, option: &wstr, typ: CompleteOptionType) {
     let mut completion_map = COMPLETION_MAP.lock().expect("mutex poisoned");
@@ -2335,7 +2847,7 @@ pub fn complete_remove(cmd: WString, cmd_is_path: bool, option: &wstr, typ: Comp
     };
     if let Some(c) = completion_map.get_mut(&idx) {
         let delete_it = c.remove_option(option, typ);
-        if delete_it {
+        if delete_it && c.cache.is_none() {
             completion_map.remove(&idx);
         }
     }
@@ -2355,6 +2867,45 @@ pub fn complete_remove_all(cmd: WString, cmd_is_path: bool, explicit: bool) {
     }
 }
 
+/// Set how the arguments of the completions for a command are cached, or stop caching them if
+/// `policy` is `None`.
+pub fn complete_set_cache_policy(
+    cmd: WString,
+    cmd_is_path: bool,
+    policy: Option<CompletionCachePolicy>,
+) {
+    let mut completion_map = COMPLETION_MAP.lock().expect("mutex poisoned");
+    let idx = CompletionEntryIndex {
+        name: cmd,
+        is_path: cmd_is_path,
+    };
+    match policy {
+        Some(policy) => {
+            completion_map
+                .entry(idx)
+                .or_insert_with(CompletionEntry::new)
+                .cache = Some(policy);
+        }
+        None => {
+            if let Some(c) = completion_map.get_mut(&idx) {
+                c.cache = None;
+                if c.options.is_empty() {
+                    completion_map.remove(&idx);
+                }
+            }
+        }
+    }
+}
+
+/// Forget the cached arguments of the given command, or of all commands if it is `None`.
+pub fn complete_clear_cache(cmd: Option<&wstr>) {
+    let mut cache = ARGUMENT_CACHE.lock().unwrap();
+    match cmd {
+        Some(cmd) => cache.retain(|key, _| key.index.name != cmd),
+        None => cache.clear(),
+    }
+}
+
 /// Returns all completions of the command cmd.
 /// If `ctx` contains a parser, this will autoload functions and completions as needed.
 /// If it does not contain a parser, then any completions which need autoloading will be returned.
@@ -2503,6 +3054,94 @@ pub fn complete_load(cmd: &wstr, parser: &mut Parser) -> bool {
     loaded_new
 }
 
+/// Commands that are never run to get their help, since they may not support `--help` and do
+/// something else instead, or would prompt for a password.
+const HELP_COMPLETION_BLOCKLIST: &[&wstr] = &[
+    L!("doas"),
+    L!("halt"),
+    L!("init"),
+    L!("kexec"),
+    L!("pkexec"),
+    L!("poweroff"),
+    L!("reboot"),
+    L!("run0"),
+    L!("shutdown"),
+    L!("su"),
+    L!("sudo"),
+    L!("telinit"),
+];
+
+/// The commands we already tried to generate completions for from their `--help` output.
+static HELP_COMPLETIONS_ATTEMPTED: Mutex<BTreeSet<WString>> = Mutex::new(BTreeSet::new());
+
+/// If `$fish_complete_from_help` is enabled and the external command `cmd` has no completions, run
+/// `cmd --help` and add completions for the options it lists. These are only kept for the session.
+/// This is tried once per command. Returns `true` if completions were added.
+pub fn complete_load_from_help(cmd: &wstr, vars: &dyn Environment) -> bool {
+    if vars
+        .get_unless_empty(L!("fish_complete_from_help"))
+        .is_none_or(|var| var.as_string() != "1")
+    {
+        return false;
+    }
+    if HELP_COMPLETION_BLOCKLIST.contains(&cmd)
+        || vars
+            .get(L!("fish_complete_from_help_blocklist"))
+            .is_some_and(|var| {
+                var.as_list()
+                    .iter()
+                    .any(|pattern| wildcard_match(cmd, unescape_wildcards(pattern), false))
+            })
+        || builtin_exists(cmd)
+        || function::exists_no_autoload(cmd)
+        || complete_wrap_map().contains_key(cmd)
+        || COMPLETION_MAP
+            .lock()
+            .unwrap()
+            .keys()
+            .any(|idx| !idx.is_path && wildcard_match(cmd, &idx.name, false))
+        || !HELP_COMPLETIONS_ATTEMPTED
+            .lock()
+            .unwrap()
+            .insert(cmd.to_owned())
+    {
+        return false;
+    }
+    let Some(path) = path_get_path(cmd, vars) else {
+        return false;
+    };
+
+    flog!(
+        complete,
+        "Generating completions from --help output of",
+        path
+    );
+    let Some(help) = help_parser::run_help(&path, vars) else {
+        return false;
+    };
+    let options = help_parser::parse_help_output(&help);
+    for option in &options {
+        let result_mode = CompletionMode {
+            requires_param: option.requires_param,
+            ..Default::default()
+        };
+        for (name, typ) in &option.names {
+            complete_add(
+                cmd.to_owned(),
+                false,
+                name.clone(),
+                *typ,
+                result_mode,
+                vec![],
+                WString::new(),
+                option.description.clone(),
+                CompleteFlags::AUTO_SPACE | CompleteFlags::DONT_ESCAPE_TILDES,
+            );
+        }
+    }
+    !options.is_empty()
+}
+
 /// Return a list of all current completions.
 /// Used by the bare `complete`, loaded completions are printed out as commands
 pub fn complete_print(cmd: &wstr) -> WString {
@@ -2518,6 +3157,19 @@ pub fn complete_print(cmd: &wstr) -> WString {
             continue;
         }
 
+        if let Some(policy) = &entry.cache {
+            out.push_utfstr(L!("complete"));
+            if key.is_path {
+                append_switch_short_arg(&mut out, 'p', &key.name);
+            } else {
+                out.push(' ');
+                out.push_utfstr(&escape(&key.name));
+            }
+            sprintf!(=> &mut out, " --cache-ttl %u", policy.ttl.as_secs());
+            append_switch_long_arg(&mut out, L!("cache-key"), &policy.key);
+            out.push('\n');
+        }
+
         // Output in reverse order to preserve legacy behavior (see #9221).
         for o in entry.get_options().iter().rev() {
             out.push_utfstr(&completion2string(key, o));
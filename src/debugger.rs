@@ -0,0 +1,488 @@
+//! The script debugger, started with `fish --debug-script`.
+//!
+//! The debugger stops before running a job when it is single-stepping, when the job is on a line
+//! with a breakpoint, or when a watched variable changed since the last job. While stopped, the
+//! user gets a `breakpoint` prompt, and resumes with `breakpoint step`, `next` or `continue`.
+
+use crate::{
+    ast::Node as _,
+    env::{EnvStack, Environment as _},
+    expand::replace_home_directory_with_tilde,
+    global_safety::RelaxedAtomicBool,
+    io::IoChain,
+    nix::isatty,
+    parser::{Block, BlockType, Parser},
+    prelude::*,
+    reader::reader_read,
+};
+use fish_common::{FilenameRef, escape, read_blocked};
+use fish_wcstringutil::join_strings;
+use fish_widestring::bytes2wcstring;
+use libc::STDIN_FILENO;
+use std::sync::Mutex;
+
+static ACTIVE: RelaxedAtomicBool = RelaxedAtomicBool::new(false);
+
+/// Set while evaluating a breakpoint condition, so that doesn't stop itself.
+static EVALUATING: RelaxedAtomicBool = RelaxedAtomicBool::new(false);
+
+static DEBUGGER: Mutex<Debugger> = Mutex::new(Debugger::new());
+
+/// How to go on after a stop.
+#[derive(Clone, Copy)]
+enum Resume {
+    /// Run until a breakpoint or watchpoint.
+    Continue,
+    /// Stop before the next job.
+    Step,
+    /// Stop before the next job that is not in a function or command substitution started by the
+    /// current one.
+    Next { depth: usize },
+}
+
+struct Breakpoint {
+    id: usize,
+    /// The file, or None for the script being debugged.
+    file: Option<WString>,
+    line: u32,
+    /// Only stop if this command succeeds.
+    condition: Option<WString>,
+}
+
+struct Watchpoint {
+    id: usize,
+    name: WString,
+    /// The last value we saw, or None if the variable is not set.
+    value: Option<Vec<WString>>,
+}
+
+struct Debugger {
+    resume: Resume,
+    breakpoints: Vec<Breakpoint>,
+    watchpoints: Vec<Watchpoint>,
+    /// The watchpoints that triggered since the last job, with their message.
+    triggered: Vec<(usize, WString)>,
+    /// The script being debugged, or None for commands given with -c.
+    script: Option<WString>,
+    /// The number of jobs we have seen, and the one we last stopped at.
+    jobs: u64,
+    stopped_at_job: u64,
+    /// The file, line and offset of the last job, to stop only once for a line unless we loop
+    /// back to it.
+    last_location: Option<(Option<WString>, u32, usize)>,
+    /// Whether we are at the debugger prompt.
+    stopped: bool,
+    /// The call depth where we last stopped.
+    stopped_depth: usize,
+    /// Where we stopped, for `breakpoint where`. Commands at the prompt are jobs of their own.
+    location: WString,
+    next_id: usize,
+}
+
+impl Debugger {
+    const fn new() -> Self {
+        Self {
+            resume: Resume::Continue,
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            triggered: Vec::new(),
+            script: None,
+            jobs: 0,
+            stopped_at_job: 0,
+            last_location: None,
+            stopped: false,
+            stopped_depth: 0,
+            location: WString::new(),
+            next_id: 1,
+        }
+    }
+
+    fn script_name(&self) -> WString {
+        self.script
+            .clone()
+            .unwrap_or_else(|| wgettext!("Standard input").to_owned())
+    }
+}
+
+fn debugger() -> std::sync::MutexGuard<'static, Debugger> {
+    DEBUGGER.lock().unwrap()
+}
+
+/// Start debugging, stopping before the first job. The script is the file that breakpoints
+/// without a file refer to.
+pub fn start(script: Option<&wstr>) {
+    let mut debugger = debugger();
+    *debugger = Debugger::new();
+    debugger.script = script.map(|s| s.to_owned());
+    debugger.resume = Resume::Step;
+    ACTIVE.store(true);
+}
+
+/// Stop debugging, for example because the script is done.
+pub fn finish() {
+    ACTIVE.store(false);
+}
+
+/// Return whether a script is being debugged.
+pub fn is_active() -> bool {
+    ACTIVE.load()
+}
+
+/// Return whether we are at the debugger prompt.
+pub fn is_stopped() -> bool {
+    is_active() && debugger().stopped
+}
+
+/// Whether a block runs code from somewhere else: a function, command substitution, sourced file
+/// or event handler.
+fn is_call(block: &Block) -> bool {
+    block.is_function_call()
+        || [BlockType::Subst, BlockType::Source, BlockType::Event].contains(&block.typ())
+}
+
+/// The number of functions, command substitutions and sourced files we are in.
+fn call_depth(parser: &Parser) -> usize {
+    parser.blocks_iter_rev().filter(|b| is_call(b)).count()
+}
+
+/// Whether we are running a command substitution of the current function or script. Its jobs are
+/// parsed on their own, so we don't know their line.
+fn in_command_substitution(parser: &Parser) -> bool {
+    parser
+        .blocks_iter_rev()
+        .find(|b| is_call(b))
+        .is_some_and(|b| b.typ() == BlockType::Subst)
+}
+
+/// Whether a breakpoint's file is the given one. A relative path matches the end of the file.
+fn file_matches(bp_file: Option<&wstr>, file: Option<&wstr>) -> bool {
+    match (bp_file, file) {
+        (Some(bp_file), Some(file)) => {
+            file == bp_file
+                || (file.len() > bp_file.len()
+                    && file.ends_with(bp_file)
+                    && file.char_at(file.len() - bp_file.len() - 1) == '/')
+        }
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Called before running each job. Stops if we are stepping, the job is on a line with a
+/// breakpoint, or a watched variable changed.
+pub fn before_job(parser: &mut Parser) {
+    // Don't debug the commands typed at the debugger prompt, or the breakpoint conditions.
+    if EVALUATING.load() || parser.is_breakpoint() || in_command_substitution(parser) {
+        return;
+    }
+    let file = parser.current_filename().map(|f| (*f).clone());
+    let line = parser.lineno_for_display();
+    let offset = parser
+        .current_node()
+        .borrow()
+        .as_ref()
+        .and_then(|node| node.source_offset())
+        .unwrap_or(0);
+
+    let mut reason = None;
+    let mut conditional = vec![];
+    {
+        let mut debugger = debugger();
+        debugger.jobs += 1;
+        let new_line = match &debugger.last_location {
+            Some((last_file, last_line, last_offset)) => {
+                *last_file != file || *last_line != line || *last_offset >= offset
+            }
+            None => true,
+        };
+        debugger.last_location = Some((file.clone(), line, offset));
+
+        let triggered = std::mem::take(&mut debugger.triggered);
+        for (id, message) in &triggered {
+            eprintf!("%s\n", message);
+            if reason.is_none() {
+                reason = Some(wgettext_fmt!("Watchpoint %d", id));
+            }
+        }
+        if reason.is_none() {
+            match debugger.resume {
+                Resume::Step => reason = Some(wgettext!("Stopped").to_owned()),
+                Resume::Next { depth } if call_depth(parser) <= depth => {
+                    reason = Some(wgettext!("Stopped").to_owned())
+                }
+                _ => (),
+            }
+        }
+        if reason.is_none() && new_line {
+            for bp in &debugger.breakpoints {
+                let bp_file = bp.file.as_deref().or(debugger.script.as_deref());
+                if bp.line != line || !file_matches(bp_file, file.as_deref()) {
+                    continue;
+                }
+                match &bp.condition {
+                    Some(condition) => conditional.push((bp.id, condition.clone())),
+                    None => {
+                        reason = Some(wgettext_fmt!("Breakpoint %d", bp.id));
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    // Evaluate the conditions without holding the lock, since they may change variables.
+    if reason.is_none() {
+        for (id, condition) in conditional {
+            let statuses = parser.last_statuses();
+            EVALUATING.store(true);
+            let res = parser.eval(&condition, &IoChain::new());
+            EVALUATING.store(false);
+            let hit = !res.break_expand && parser.last_status() == STATUS_CMD_OK;
+            parser.set_last_statuses(statuses);
+            if hit {
+                reason = Some(wgettext_fmt!("Breakpoint %d", id));
+                break;
+            }
+        }
+    }
+
+    if let Some(reason) = reason {
+        stop(parser, &reason);
+    }
+}
+
+/// Stop at the current job and read debugger commands until one of them resumes. This is also
+/// used by the `breakpoint` builtin.
+pub fn stop(parser: &mut Parser, reason: &wstr) {
+    {
+        let mut debugger = debugger();
+        // The breakpoint builtin does nothing if we already stopped at its job.
+        if debugger.stopped || (debugger.jobs != 0 && debugger.stopped_at_job == debugger.jobs) {
+            return;
+        }
+        debugger.stopped = true;
+        debugger.stopped_at_job = debugger.jobs;
+        debugger.stopped_depth = call_depth(parser);
+        // If the user leaves the prompt without saying how to go on, continue.
+        debugger.resume = Resume::Continue;
+    }
+
+    let location = describe_location(parser);
+    debugger().location = location;
+    let (file, line) = (parser.current_filename(), parser.lineno_for_display());
+    eprintf!(
+        "%s\n",
+        wgettext_fmt!(
+            "%s at %s (line %d):",
+            reason,
+            display_file(parser, file),
+            line
+        )
+    );
+    if let Some(source) = current_source(parser) {
+        eprintf!("    %s\n", source);
+    }
+
+    let statuses = parser.last_statuses();
+    let bpb = parser.push_block(Block::breakpoint_block());
+    if isatty(STDIN_FILENO) {
+        let _ = reader_read(parser, STDIN_FILENO, &IoChain::new());
+    } else {
+        // Read commands a line at a time, so the script can read the rest of stdin.
+        while let Some(command) = read_line(STDIN_FILENO) {
+            let _ = parser.eval(&command, &IoChain::new());
+            if std::mem::take(&mut parser.libdata_mut().exit_current_script) {
+                break;
+            }
+        }
+    }
+    parser.pop_block(bpb);
+    parser.set_last_statuses(statuses);
+    debugger().stopped = false;
+}
+
+/// Read a line from the fd, without reading past it.
+fn read_line(fd: i32) -> Option<WString> {
+    let mut line = vec![];
+    loop {
+        let mut b = [0_u8; 1];
+        match read_blocked(fd, &mut b) {
+            Ok(1) if b[0] != b'\n' => line.push(b[0]),
+            Ok(1) => break,
+            _ if line.is_empty() => return None,
+            _ => break,
+        }
+    }
+    Some(bytes2wcstring(&line))
+}
+
+fn display_file(parser: &Parser, file: Option<FilenameRef>) -> WString {
+    match file {
+        Some(file) => replace_home_directory_with_tilde(&*file, parser.vars()),
+        None => wgettext!("Standard input").to_owned(),
+    }
+}
+
+/// The first line of the job that is about to run.
+fn current_source(parser: &mut Parser) -> Option<WString> {
+    let node = parser.current_node().borrow();
+    let node = node.as_ref()?;
+    let source = node.source(node.source_str());
+    let end = source.find_char('\n').unwrap_or(source.len());
+    Some(source[..end].to_owned())
+}
+
+/// Go on after a stop.
+pub fn resume_step() {
+    debugger().resume = Resume::Step;
+}
+
+pub fn resume_next() {
+    let mut debugger = debugger();
+    debugger.resume = Resume::Next {
+        depth: debugger.stopped_depth,
+    };
+}
+
+pub fn resume_continue() {
+    debugger().resume = Resume::Continue;
+}
+
+/// Add a breakpoint, returning its id.
+pub fn add_breakpoint(file: Option<&wstr>, line: u32, condition: Option<&wstr>) -> usize {
+    let mut debugger = debugger();
+    let id = debugger.next_id;
+    debugger.next_id += 1;
+    debugger.breakpoints.push(Breakpoint {
+        id,
+        file: file.map(|f| f.to_owned()),
+        line,
+        condition: condition.map(|c| c.to_owned()),
+    });
+    id
+}
+
+/// Watch a variable, returning the watchpoint's id.
+pub fn add_watchpoint(name: &wstr, vars: &EnvStack) -> usize {
+    let mut debugger = debugger();
+    let id = debugger.next_id;
+    debugger.next_id += 1;
+    debugger.watchpoints.push(Watchpoint {
+        id,
+        name: name.to_owned(),
+        value: vars.get(name).map(|var| var.as_list().to_vec()),
+    });
+    id
+}
+
+/// Remove a breakpoint or watchpoint. Returns false if there is none with this id.
+pub fn delete(id: usize) -> bool {
+    let mut debugger = debugger();
+    let (bps, wps) = (debugger.breakpoints.len(), debugger.watchpoints.len());
+    debugger.breakpoints.retain(|bp| bp.id != id);
+    debugger.watchpoints.retain(|wp| wp.id != id);
+    bps != debugger.breakpoints.len() || wps != debugger.watchpoints.len()
+}
+
+/// Describe the breakpoints and watchpoints, one per line.
+pub fn list() -> WString {
+    let debugger = debugger();
+    let mut entries: Vec<(usize, WString)> = debugger
+        .breakpoints
+        .iter()
+        .map(|bp| {
+            let file = bp.file.clone().unwrap_or_else(|| debugger.script_name());
+            let mut entry = sprintf!("%d\t%s:%d", bp.id, file, bp.line);
+            if let Some(condition) = &bp.condition {
+                entry.push_utfstr(&wgettext_fmt!(" if %s", condition));
+            }
+            (bp.id, entry)
+        })
+        .chain(
+            debugger
+                .watchpoints
+                .iter()
+                .map(|wp| (wp.id, sprintf!("%d\t$%s", wp.id, wp.name))),
+        )
+        .collect();
+    entries.sort_by_key(|(id, _)| *id);
+    let mut result = WString::new();
+    for (_, entry) in entries {
+        result.push_utfstr(&entry);
+        result.push('\n');
+    }
+    result
+}
+
+/// Describe where we are, or where we stopped if at the debugger prompt.
+pub fn location(parser: &mut Parser) -> WString {
+    {
+        let debugger = debugger();
+        if debugger.stopped {
+            return debugger.location.clone();
+        }
+    }
+    describe_location(parser)
+}
+
+/// Describe where we are: the kind of node, its line and the stack.
+fn describe_location(parser: &mut Parser) -> WString {
+    let kind = {
+        let node = parser.current_node().borrow();
+        node.as_ref()
+            .map(|node| crate::ast::ast_kind_to_string(node.statement.embedded_node().kind()))
+    };
+    let Some(kind) = kind else {
+        return WString::new();
+    };
+    let (file, line) = (parser.current_filename(), parser.lineno_for_display());
+    let mut result = wgettext_fmt!(
+        "%s at %s (line %d):",
+        kind,
+        display_file(parser, file),
+        line
+    );
+    result.push('\n');
+    if let Some(source) = current_source(parser) {
+        result.push_utfstr(&sprintf!("    %s\n", source));
+    }
+    result.push_utfstr(&parser.stack_trace());
+    result
+}
+
+/// React to a variable change, from env dispatch.
+pub fn var_changed(name: &wstr, vars: &EnvStack) {
+    if !is_active() {
+        return;
+    }
+    let mut debugger = debugger();
+    // Changes made at the debugger prompt or by breakpoint conditions don't stop.
+    let quiet = debugger.stopped || EVALUATING.load();
+    let mut triggered = vec![];
+    for wp in debugger.watchpoints.iter_mut().filter(|wp| wp.name == name) {
+        let value = vars.get(name).map(|var| var.as_list().to_vec());
+        if value == wp.value {
+            continue;
+        }
+        if !quiet {
+            let message = match &value {
+                None => wgettext_fmt!("Watchpoint %d: $%s was erased", wp.id, name),
+                Some(values) if values.is_empty() => {
+                    wgettext_fmt!("Watchpoint %d: $%s changed to an empty list", wp.id, name)
+                }
+                Some(values) => {
+                    let values: Vec<WString> = values.iter().map(|v| escape(v)).collect();
+                    wgettext_fmt!(
+                        "Watchpoint %d: $%s changed to %s",
+                        wp.id,
+                        name,
+                        join_strings(&values, ' ')
+                    )
+                }
+            };
+            triggered.push((wp.id, message));
+        }
+        wp.value = value;
+    }
+    debugger.triggered.extend(triggered);
+}
@@ -54,21 +54,40 @@ pub fn apply_edit(target: &mut WString, colors: &mut Vec<HighlightSpec>, edit: &
     );
 }
 
+/// Return the edit that reverts the given one.
+fn inverse_edit(edit: &Edit) -> Edit {
+    let range = &edit.range;
+    Edit::new(
+        range.start..range.start + edit.replacement.len(),
+        edit.old.clone(),
+    )
+}
+
+/// An edit in the undo tree.
+#[derive(Clone)]
+struct UndoNode {
+    edit: Edit,
+    /// The edit this one was applied on top of.
+    parent: usize,
+    /// The edits made after this one, in the order they were made.
+    children: Vec<usize>,
+    /// The index into `children` of the branch that redo follows: the one created or visited most
+    /// recently.
+    redo_child: usize,
+}
+
 /// The history of all edits to some command line.
-#[derive(Clone, Default)]
+///
+/// Like in Vim, this is a tree rather than a stack: making an edit after undoing some others
+/// starts a new branch, so the undone edits can still be reached by switching branches.
+#[derive(Clone)]
 pub struct UndoHistory {
-    /// The stack of edits that can be undone or redone atomically.
-    pub edits: Vec<Edit>,
-
-    /// The position in the undo stack that corresponds to the current
-    /// state of the input line.
-    /// Invariants:
-    ///     edits_applied - 1 is the index of the next edit to undo.
-    ///     edits_applied     is the index of the next edit to redo.
-    ///
-    /// For example, if nothing was undone, edits_applied is edits.size().
-    /// If every single edit was undone, edits_applied is 0.
-    pub edits_applied: usize,
+    /// All edits. The first node is a placeholder for the state before any edit.
+    nodes: Vec<UndoNode>,
+
+    /// The node of the last edit that is applied to the input line.
+    /// This is 0 if nothing was done, or if every single edit was undone.
+    current: usize,
 
     /// Whether we allow the next edit to be grouped together with the
     /// last one.
@@ -80,13 +99,51 @@ pub struct UndoHistory {
     try_coalesce: bool,
 }
 
+impl Default for UndoHistory {
+    fn default() -> Self {
+        Self {
+            nodes: vec![UndoNode {
+                edit: Edit::new(0..0, WString::new()),
+                parent: 0,
+                children: vec![],
+                redo_child: 0,
+            }],
+            current: 0,
+            may_coalesce: false,
+            try_coalesce: false,
+        }
+    }
+}
+
 impl UndoHistory {
     /// Empty the history.
     pub fn clear(&mut self) {
-        self.edits.clear();
-        self.edits_applied = 0;
+        self.nodes.truncate(1);
+        self.nodes[0].children.clear();
+        self.nodes[0].redo_child = 0;
+        self.current = 0;
         self.may_coalesce = false;
     }
+
+    /// The edit that the next redo applies.
+    fn redo_target(&self, node: usize) -> Option<usize> {
+        let node = &self.nodes[node];
+        node.children.get(node.redo_child).copied()
+    }
+
+    /// The closest node at or above the current one where the history branches.
+    fn fork(&self) -> Option<usize> {
+        let mut node = self.current;
+        loop {
+            if self.nodes[node].children.len() > 1 {
+                return Some(node);
+            }
+            if node == 0 {
+                return None;
+            }
+            node = self.nodes[node].parent;
+        }
+    }
 }
 
 /// Helper class for storing a command line.
@@ -155,7 +212,7 @@ impl EditableLine {
         // Coalescing insertion does not create a new undo entry but adds to the last insertion.
         if allow_coalesce && is_insertion && self.want_to_coalesce_insertion_of(&edit.replacement) {
             assert_eq!(range.start, self.position());
-            let last_edit = self.undo_history.edits.last_mut().unwrap();
+            let last_edit = &mut self.undo_history.nodes[self.undo_history.current].edit;
             last_edit.replacement.push_utfstr(&edit.replacement);
             apply_edit(&mut self.text, &mut self.colors, &edit);
             self.set_position(self.position() + edit.replacement.len());
@@ -173,27 +230,26 @@ impl EditableLine {
             edit.group_id = Some(self.edit_group_id);
         }
 
-        if self.undo_history.edits_applied != self.undo_history.edits.len() {
-            // After undoing some edits, the user is making a new edit;
-            // we are about to create a new edit branch.
-            // Discard all edits that were undone because we only support
-            // linear undo/redo, they will be unreachable.
-            self.undo_history
-                .edits
-                .truncate(self.undo_history.edits_applied);
-        }
         edit.cursor_position_before_edit = self.pending_position.take().unwrap_or(self.position());
         edit.old = self.text[range.clone()].to_owned();
         apply_edit(&mut self.text, &mut self.colors, &edit);
         self.set_position(cursor_position_after_edit(&edit));
-        assert_eq!(
-            self.undo_history.edits_applied,
-            self.undo_history.edits.len()
-        );
-        self.undo_history.may_coalesce =
-            is_insertion && (self.undo_history.try_coalesce || edit.replacement.len() == 1);
-        self.undo_history.edits_applied += 1;
-        self.undo_history.edits.push(edit);
+        let history = &mut self.undo_history;
+        history.may_coalesce =
+            is_insertion && (history.try_coalesce || edit.replacement.len() == 1);
+        // If the user undid some edits before, this starts a new branch. The undone edits are
+        // kept, and the new branch is the one that redo follows.
+        let parent = history.current;
+        history.current = history.nodes.len();
+        history.nodes.push(UndoNode {
+            edit,
+            parent,
+            children: vec![],
+            redo_child: 0,
+        });
+        let parent = &mut history.nodes[parent];
+        parent.redo_child = parent.children.len();
+        parent.children.push(history.current);
     }
 
     /// Undo the most recent edit that was not yet undone. Returns true on success.
@@ -201,9 +257,10 @@ impl EditableLine {
         let mut did_undo = false;
         let mut last_group_id = None;
         let position_before_undo = self.position();
-        let end = self.undo_history.edits_applied;
-        while self.undo_history.edits_applied != 0 {
-            let edit = &self.undo_history.edits[self.undo_history.edits_applied - 1];
+        let end = self.undo_history.current;
+        while self.undo_history.current != 0 {
+            let node = &self.undo_history.nodes[self.undo_history.current];
+            let edit = &node.edit;
             if did_undo
                 && edit
                     .group_id
@@ -213,20 +270,14 @@ impl EditableLine {
                 break;
             }
             last_group_id = edit.group_id;
-            self.undo_history.edits_applied -= 1;
-            let range = &edit.range;
-            let mut inverse = Edit::new(
-                range.start..range.start + edit.replacement.len(),
-                L!("").to_owned(),
-            );
-            inverse.replacement = edit.old.clone();
+            self.undo_history.current = node.parent;
             let old_position = edit.cursor_position_before_edit;
-            apply_edit(&mut self.text, &mut self.colors, &inverse);
+            apply_edit(&mut self.text, &mut self.colors, &inverse_edit(edit));
             self.set_position(old_position);
             did_undo = true;
         }
         if did_undo {
-            let edit = &mut self.undo_history.edits[end - 1];
+            let edit = &mut self.undo_history.nodes[end].edit;
             edit.cursor_position_before_undo = Some(position_before_undo);
         }
 
@@ -240,7 +291,8 @@ impl EditableLine {
         let mut did_redo = false;
 
         let mut last_group_id = None;
-        while let Some(edit) = self.undo_history.edits.get(self.undo_history.edits_applied) {
+        while let Some(node) = self.undo_history.redo_target(self.undo_history.current) {
+            let edit = &self.undo_history.nodes[node].edit;
             if did_redo
                 && edit
                     .group_id
@@ -250,7 +302,7 @@ impl EditableLine {
                 break;
             }
             last_group_id = edit.group_id;
-            self.undo_history.edits_applied += 1;
+            self.undo_history.current = node;
             apply_edit(&mut self.text, &mut self.colors, edit);
             edit.cursor_position_before_undo
                 .map(|pos| self.set_position(pos));
@@ -261,6 +313,66 @@ impl EditableLine {
         did_redo
     }
 
+    /// Go back to the closest point where the undo history branches, and follow the next (or
+    /// previous) branch to its last edit. Returns true on success.
+    pub fn switch_undo_branch(&mut self, forward: bool) -> bool {
+        let Some(fork) = self.undo_history.fork() else {
+            return false;
+        };
+        while self.undo_history.current != fork {
+            let node = &self.undo_history.nodes[self.undo_history.current];
+            apply_edit(&mut self.text, &mut self.colors, &inverse_edit(&node.edit));
+            self.undo_history.current = node.parent;
+        }
+        let fork = &mut self.undo_history.nodes[fork];
+        let branches = fork.children.len();
+        fork.redo_child = if forward {
+            (fork.redo_child + 1) % branches
+        } else {
+            (fork.redo_child + branches - 1) % branches
+        };
+        while let Some(node) = self.undo_history.redo_target(self.undo_history.current) {
+            self.undo_history.current = node;
+            let edit = &self.undo_history.nodes[node].edit;
+            apply_edit(&mut self.text, &mut self.colors, edit);
+            self.position = cursor_position_after_edit(edit);
+        }
+
+        self.end_edit_group();
+        self.undo_history.may_coalesce = false;
+        true
+    }
+
+    /// Return the text at the end of each branch at the closest point where the undo history
+    /// branches, along with the index of the branch we are on.
+    pub fn undo_branches(&self) -> Option<(Vec<WString>, usize)> {
+        let history = &self.undo_history;
+        let fork = history.fork()?;
+        let mut text_at_fork = self.text.clone();
+        let mut node = history.current;
+        while node != fork {
+            let inverse = inverse_edit(&history.nodes[node].edit);
+            text_at_fork.replace_range(inverse.range, &inverse.replacement);
+            node = history.nodes[node].parent;
+        }
+        let fork = &history.nodes[fork];
+        let branches = fork
+            .children
+            .iter()
+            .map(|&child| {
+                let mut text = text_at_fork.clone();
+                let mut node = Some(child);
+                while let Some(n) = node {
+                    let edit = &history.nodes[n].edit;
+                    text.replace_range(edit.range.clone(), &edit.replacement);
+                    node = history.redo_target(n);
+                }
+                text
+            })
+            .collect();
+        Some((branches, fork.redo_child))
+    }
+
     /// Start a logical grouping of command line edits that should be undone/redone together.
     pub fn begin_edit_group(&mut self) {
         if self.edit_group_level.is_some() {
@@ -309,7 +421,7 @@ impl EditableLine {
         if s.as_char_slice()[0] == ' ' && !self.undo_history.try_coalesce {
             return false;
         }
-        let last_edit = self.undo_history.edits.last().unwrap();
+        let last_edit = &self.undo_history.nodes[self.undo_history.current].edit;
         // Don't add to the last edit if it deleted something.
         if !last_edit.range.is_empty() {
             return false;
@@ -418,7 +530,7 @@ mod tests {
         line.undo();
         line.redo();
         assert_eq!(line.text(), L!("abc").to_owned());
-        // This removes the space insertion from the history, but does not coalesce with the first edit.
+        // This starts a new branch without the space insertion, but does not coalesce with the first edit.
         line.push_edit(Edit::new(insert(&line), L!("d").to_owned()), true);
         line.push_edit(Edit::new(insert(&line), L!("e").to_owned()), true);
         assert_eq!(line.text(), L!("abcde").to_owned());
@@ -438,4 +550,45 @@ mod tests {
         line.undo();
         assert_eq!(line.text(), "a");
     }
+    #[test]
+    fn test_undo_tree() {
+        let mut line = EditableLine::default();
+        assert!(!line.switch_undo_branch(true)); // no branches
+        line.push_edit(Edit::new(0..0, L!("echo ").to_owned()), true);
+        line.push_edit(Edit::new(5..5, L!("foo").to_owned()), true);
+        line.undo();
+        assert_eq!(line.text(), "echo ");
+        // A new edit starts a second branch.
+        line.push_edit(Edit::new(5..5, L!("bar").to_owned()), true);
+        line.push_edit(Edit::new(8..8, L!("!").to_owned()), true);
+        assert_eq!(line.text(), "echo bar!");
+        assert_eq!(
+            line.undo_branches(),
+            Some((
+                vec![L!("echo foo").to_owned(), L!("echo bar!").to_owned()],
+                1
+            ))
+        );
+
+        assert!(line.switch_undo_branch(true));
+        assert_eq!(line.text(), "echo foo");
+        assert_eq!(line.position(), 8);
+        assert!(line.switch_undo_branch(false));
+        assert_eq!(line.text(), "echo bar!");
+        assert_eq!(line.position(), 9);
+
+        // Undo and redo follow the branch we are on.
+        line.switch_undo_branch(false);
+        line.undo();
+        assert_eq!(line.text(), "echo ");
+        line.redo();
+        assert_eq!(line.text(), "echo foo");
+        line.undo();
+        line.undo();
+        assert!(line.text().is_empty());
+        line.redo();
+        line.redo();
+        assert_eq!(line.text(), "echo foo");
+        assert!(!line.redo());
+    }
 }
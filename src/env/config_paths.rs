@@ -161,6 +161,14 @@ impl ConfigPaths {
     }
 }
 
+/// The directory where message catalogs are installed, as `<language>/LC_MESSAGES/fish.mo`.
+/// e.g., /usr/local/share/locale
+pub fn locale_dir() -> PathBuf {
+    DATADIR
+        .map_or_else(|| Path::new(PREFIX).join("share"), PathBuf::from)
+        .join("locale")
+}
+
 pub enum FishPath {
     Absolute(PathBuf),
     LookUpInPath,
@@ -217,8 +217,40 @@ impl EnvStack {
         self.lock().base.set_last_statuses(statuses);
     }
 
+    /// Helpers to get and set the values passed to `return --value`.
+    /// These correspond to $fish_return_value.
+    pub fn last_return_value(&self) -> Vec<WString> {
+        self.lock().base.last_return_value().to_vec()
+    }
+
+    pub fn set_last_return_value(&self, vals: Vec<WString>) {
+        self.lock().base.set_last_return_value(vals);
+    }
+
     /// Sets the variable with the specified name to the given values.
-    pub fn set(&self, key: &wstr, mode: EnvSetMode, mut vals: Vec<WString>) -> EnvStackSetResult {
+    pub fn set(&self, key: &wstr, mode: EnvSetMode, vals: Vec<WString>) -> EnvStackSetResult {
+        self.set_impl(key, mode, None, vals)
+    }
+
+    /// Sets the variable with the specified name to a map. Each value has the key at the same
+    /// position. Maps can't be universal or electric variables.
+    pub fn set_map(
+        &self,
+        key: &wstr,
+        mode: EnvSetMode,
+        keys: Vec<WString>,
+        vals: Vec<WString>,
+    ) -> EnvStackSetResult {
+        self.set_impl(key, mode, Some(keys), vals)
+    }
+
+    fn set_impl(
+        &self,
+        key: &wstr,
+        mode: EnvSetMode,
+        keys: Option<Vec<WString>>,
+        mut vals: Vec<WString>,
+    ) -> EnvStackSetResult {
         // Historical behavior.
         if vals.len() == 1 && (key == "PWD" || key == "HOME") {
             path_make_canonical(vals.first_mut().unwrap());
@@ -227,7 +259,7 @@ impl EnvStack {
         // Hacky stuff around PATH and CDPATH: #3914.
         // Not MANPATH; see #4158.
         // Replace empties with dot. Note we ignore pathvar here.
-        if key == "PATH" || key == "CDPATH" {
+        if keys.is_none() && (key == "PATH" || key == "CDPATH") {
             // Split on colons.
             let mut munged_vals = colon_split(&vals);
             // Replace empties with dots.
@@ -239,7 +271,7 @@ impl EnvStack {
             vals = munged_vals;
         }
 
-        let ret: ModResult = self.lock().set(key, mode, vals);
+        let ret: ModResult = self.lock().set(key, mode, keys, vals);
         if ret.status == EnvStackSetResult::Ok {
             // Dispatch changes if we modified the global state or have 'dispatches_var_changes' set.
             // Important to not hold the lock here.
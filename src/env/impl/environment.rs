@@ -42,6 +42,19 @@ pub fn colon_split<T: AsRef<wstr>>(val: &[T]) -> Vec<WString> {
     split_val
 }
 
+/// Apply the sorted and unique behaviors. Without sorting, the first of each duplicate is kept.
+pub fn sort_and_dedup(val: &mut Vec<WString>, sorted: bool, unique: bool) {
+    if sorted {
+        val.sort();
+        if unique {
+            val.dedup();
+        }
+    } else if unique {
+        let mut seen = HashSet::new();
+        val.retain(|v| seen.insert(v.clone()));
+    }
+}
+
 /// Return true if a variable should become a path variable by default. See #436.
 fn variable_should_auto_pathvar(name: &wstr) -> bool {
     name.ends_with("PATH") || name == "LANGUAGE"
@@ -102,6 +115,16 @@ struct Query {
     pub pathvar: bool,
     pub unpathvar: bool,
 
+    /// Whether sorted or unsorted was set.
+    pub has_sorted_unsorted: bool,
+    pub sorted: bool,
+    pub unsorted: bool,
+
+    /// Whether unique or nonunique was set.
+    pub has_unique_nonunique: bool,
+    pub unique: bool,
+    pub nonunique: bool,
+
     /// Whether this is a "user" set.
     pub user: bool,
 }
@@ -137,6 +160,14 @@ impl Query {
             pathvar: mode.contains(EnvMode::PATHVAR),
             unpathvar: mode.contains(EnvMode::UNPATHVAR),
 
+            has_sorted_unsorted: mode.intersects(EnvMode::SORTED | EnvMode::UNSORTED),
+            sorted: mode.contains(EnvMode::SORTED),
+            unsorted: mode.contains(EnvMode::UNSORTED),
+
+            has_unique_nonunique: mode.intersects(EnvMode::UNIQUE | EnvMode::NONUNIQUE),
+            unique: mode.contains(EnvMode::UNIQUE),
+            nonunique: mode.contains(EnvMode::NONUNIQUE),
+
             user,
         }
     }
@@ -166,6 +197,23 @@ impl Query {
             true
         }
     }
+
+    /// Returns whether an environment variable matches the query's sorted and unique criteria.
+    fn order_matches(&self, var: &EnvVar) -> bool {
+        let sorted_matches = !self.has_sorted_unsorted
+            || if var.is_sorted() {
+                self.sorted
+            } else {
+                self.unsorted
+            };
+        let unique_matches = !self.has_unique_nonunique
+            || if var.is_unique() {
+                self.unique
+            } else {
+                self.nonunique
+            };
+        sorted_matches && unique_matches
+    }
 }
 
 // Struct representing one level in the function variable stack.
@@ -307,6 +355,7 @@ fn copy_node_chain(node: &EnvNodeRef) -> EnvNodeRef {
 pub(super) struct PerprocData {
     pub(super) pwd: WString,
     pub(super) statuses: Statuses,
+    pub(super) return_value: Vec<WString>,
 }
 
 #[derive(Clone)]
@@ -348,6 +397,14 @@ impl EnvScopedImpl {
         self.perproc_data.statuses = s;
     }
 
+    pub fn last_return_value(&self) -> &[WString] {
+        &self.perproc_data.return_value
+    }
+
+    pub fn set_last_return_value(&mut self, vals: Vec<WString>) {
+        self.perproc_data.return_value = vals;
+    }
+
     fn try_get_computed(&self, key: &wstr) -> Option<EnvVar> {
         ElectricVar::for_name(key).and_then(|ev| ev.compute(self))
     }
@@ -418,6 +475,10 @@ impl EnvScopedImpl {
         if result.is_some() && !query.pathvar_matches(result.as_ref().unwrap()) {
             result = None;
         }
+        // And for sorted and unique variables.
+        if result.is_some() && !query.order_matches(result.as_ref().unwrap()) {
+            result = None;
+        }
         result
     }
 
@@ -603,6 +664,12 @@ struct VarFlags {
     /// If set, whether the variable should be a path variable; otherwise guess based on the name.
     pub pathvar: Option<bool>,
 
+    /// Whether the variable's elements should be kept sorted.
+    pub sorted: bool,
+
+    /// Whether duplicate elements should be removed from the variable.
+    pub unique: bool,
+
     /// If set, the new export value; otherwise inherit any existing export value.
     pub exports: Option<bool>,
 
@@ -654,21 +721,48 @@ impl EnvStackImpl {
     }
 
     /// Set a variable under the name `key`, using the given `mode`, setting its value to `val`.
-    pub fn set(&mut self, key: &wstr, mode: EnvSetMode, mut val: Vec<WString>) -> ModResult {
+    /// If `keys` is given, the variable becomes a map with these keys.
+    pub fn set(
+        &mut self,
+        key: &wstr,
+        mode: EnvSetMode,
+        keys: Option<Vec<WString>>,
+        mut val: Vec<WString>,
+    ) -> ModResult {
         let query = Query::from(mode);
+        // Electric variables can't be maps.
+        if keys.is_some() && ElectricVar::for_name(key).is_some() {
+            return ModResult::new(EnvStackSetResult::Invalid);
+        }
         // Handle electric and read-only variables.
         if let Some(ret) = self.try_set_electric(key, &query, &mut val) {
             return ModResult::new(ret);
         }
+        // Universal variables can't be maps.
+        if keys.is_some() && !UVAR_SCOPE_IS_GLOBAL.load() {
+            let targets_universal = if query.has_scope {
+                query.universal
+            } else {
+                Self::find_in_chain(&self.base.locals, key).is_none()
+                    && Self::find_in_chain(&self.base.globals, key).is_none()
+                    && uvars().get(key).is_some()
+            };
+            if targets_universal {
+                return ModResult::new(EnvStackSetResult::Scope);
+            }
+        }
 
         // Resolve as much of our flags as we can. Note these contain maybes, and we may defer the final
         // decision until the set_in_node call. Also note that we only inherit pathvar, not export. For
         // example, if you have a global exported variable, a local variable with the same name will not
         // automatically be exported. But if you have a global pathvar, a local variable with the same
-        // name will be a pathvar. This is historical.
+        // name will be a pathvar. This is historical. The sorted and unique behaviors are inherited
+        // the same way.
         let mut flags = VarFlags::default();
         if let Some(existing) = self.find_variable(key) {
             flags.pathvar = Some(existing.is_pathvar());
+            flags.sorted = existing.is_sorted();
+            flags.unique = existing.is_unique();
             flags.parent_exports = existing.exports();
         }
         if query.has_export_unexport {
@@ -677,6 +771,12 @@ impl EnvStackImpl {
         if query.has_pathvar_unpathvar {
             flags.pathvar = Some(query.pathvar);
         }
+        if query.has_sorted_unsorted {
+            flags.sorted = query.sorted;
+        }
+        if query.has_unique_nonunique {
+            flags.unique = query.unique;
+        }
 
         let mut result = ModResult::new(EnvStackSetResult::Ok);
         if query.has_scope {
@@ -686,14 +786,14 @@ impl EnvStackImpl {
                 self.set_universal(key, val, query);
                 result.uvar_modified = true;
             } else if query.global || (query.universal && UVAR_SCOPE_IS_GLOBAL.load()) {
-                Self::set_in_node(&mut self.base.globals, key, val, flags);
+                Self::set_in_node(&mut self.base.globals, key, keys, val, flags);
                 result.global_modified = true;
             } else if query.local {
                 assert!(
                     !self.base.locals.ptr_eq(&self.base.globals),
                     "Locals should not be globals"
                 );
-                Self::set_in_node(&mut self.base.locals, key, val, flags);
+                Self::set_in_node(&mut self.base.locals, key, keys, val, flags);
             } else if query.function {
                 // "Function" scope is:
                 // Either the topmost local scope of the nearest function,
@@ -711,16 +811,16 @@ impl EnvStackImpl {
                         break;
                     }
                 }
-                Self::set_in_node(&mut node, key, val, flags);
+                Self::set_in_node(&mut node, key, keys, val, flags);
             } else {
                 panic!("Unknown scope");
             }
         } else if let Some(mut node) = Self::find_in_chain(&self.base.locals, key) {
             // Existing local variable.
-            Self::set_in_node(&mut node, key, val, flags);
+            Self::set_in_node(&mut node, key, keys, val, flags);
         } else if let Some(mut node) = Self::find_in_chain(&self.base.globals, key) {
             // Existing global variable.
-            Self::set_in_node(&mut node, key, val, flags);
+            Self::set_in_node(&mut node, key, keys, val, flags);
             result.global_modified = true;
         } else if !UVAR_SCOPE_IS_GLOBAL.load() && uvars().get(key).is_some() {
             // Existing universal variable.
@@ -729,7 +829,7 @@ impl EnvStackImpl {
         } else {
             // Unspecified scope with no existing variables.
             let mut node = self.resolve_unspecified_scope();
-            Self::set_in_node(&mut node, key, val, flags);
+            Self::set_in_node(&mut node, key, keys, val, flags);
             result.global_modified = node.ptr_eq(&self.base.globals);
         }
         result
@@ -918,8 +1018,9 @@ impl EnvStackImpl {
             exports: Some(ev.exports()),
             parent_exports: ev.exports(),
             pathvar: Some(false),
+            ..Default::default()
         };
-        Self::set_in_node(&mut self.base.globals, key, val, flags);
+        Self::set_in_node(&mut self.base.globals, key, None, val, flags);
         Some(EnvStackSetResult::Ok)
     }
 
@@ -953,17 +1054,35 @@ impl EnvStackImpl {
             val = colon_split(&val);
         }
 
+        let sorted = match query.has_sorted_unsorted {
+            true => query.sorted,
+            false => oldvar.is_some_and(EnvVar::is_sorted),
+        };
+        let unique = match query.has_unique_nonunique {
+            true => query.unique,
+            false => oldvar.is_some_and(EnvVar::is_unique),
+        };
+        sort_and_dedup(&mut val, sorted, unique);
+
         // Construct and set the new variable.
         let mut varflags = EnvVarFlags::empty();
         varflags.set(EnvVarFlags::EXPORT, exports);
         varflags.set(EnvVarFlags::PATHVAR, pathvar);
+        varflags.set(EnvVarFlags::SORTED, sorted);
+        varflags.set(EnvVarFlags::UNIQUE, unique);
         let new_var = EnvVar::new_vec(val, varflags);
 
         locked_uvars.set(key, new_var);
     }
 
-    /// Set a variable in a given node `node`.
-    fn set_in_node(node: &mut EnvNodeRef, key: &wstr, mut val: Vec<WString>, flags: VarFlags) {
+    /// Set a variable in a given node `node`, making it a map if `keys` is given.
+    fn set_in_node(
+        node: &mut EnvNodeRef,
+        key: &wstr,
+        keys: Option<Vec<WString>>,
+        mut val: Vec<WString>,
+        flags: VarFlags,
+    ) {
         // Read the var from the node. In C++ this was node->env[key] which establishes a default.
         let mut node_ref = node.borrow_mut();
         let var = node_ref.env.entry(key.to_owned()).or_default();
@@ -975,18 +1094,31 @@ impl EnvStackImpl {
         };
 
         // Pathvar is inferred from the name. If set, split our entry about colons.
-        let res_pathvar = match flags.pathvar {
-            Some(pathvar) => pathvar,
-            None => variable_should_auto_pathvar(key),
-        };
+        // Maps are never path variables, since splitting would break the keys.
+        let res_pathvar = keys.is_none()
+            && match flags.pathvar {
+                Some(pathvar) => pathvar,
+                None => variable_should_auto_pathvar(key),
+            };
         if res_pathvar {
             val = colon_split(&val);
         }
 
-        *var = var
-            .setting_vals(val)
+        // Keys would get out of step with their values, so maps are never sorted or unique.
+        let (sorted, unique) = match keys {
+            Some(_) => (false, false),
+            None => (flags.sorted, flags.unique),
+        };
+        sort_and_dedup(&mut val, sorted, unique);
+
+        let new_var = match keys {
+            Some(keys) => var.setting_map(keys, val),
+            None => var.setting_vals(val),
+        };
+        *var = new_var
             .setting_exports(res_exports)
-            .setting_pathvar(res_pathvar);
+            .setting_pathvar(res_pathvar)
+            .setting_order(sorted, unique);
 
         // Perhaps mark that this node contains an exported variable, or shadows an exported variable.
         // If so regenerate the export list.
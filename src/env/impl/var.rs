@@ -122,6 +122,7 @@ pub(super) mod electric_values {
         var(L!("fish_kill_signal"), Computed(GET_FISH_KILL_SIGNAL)),
         var(L!("fish_killring"), Computed(GET_FISH_KILLRING)),
         var(L!("fish_pid"), Regular),
+        var(L!("fish_return_value"), Computed(GET_RETURN_VALUE)),
         var(FISH_TERMINAL_COLOR_THEME_VAR, Regular),
         var(L!("history"), Computed(GET_HISTORY)),
         var(L!("hostname"), Regular),
@@ -149,7 +150,8 @@ pub(super) mod electric_values {
         }
         let history = commandline_get_state(true).history.unwrap_or_else(|| {
             let fish_history_var = env.getf(L!("fish_history"), EnvMode::default());
-            let history_id = history_id_from_var(fish_history_var);
+            let backend_var = env.getf(L!("fish_history_backend"), EnvMode::default());
+            let history_id = history_id_from_var(fish_history_var, backend_var);
             History::new(history_id)
         });
         EnvVar::new_from_name_vec(L!("history"), history.get_history())
@@ -162,6 +164,12 @@ pub(super) mod electric_values {
         }
         EnvVar::new_from_name_vec(L!("pipestatus"), result)
     };
+    const GET_RETURN_VALUE: Getter = |env| {
+        EnvVar::new_from_name_vec(
+            L!("fish_return_value"),
+            env.perproc_data.return_value.clone(),
+        )
+    };
     const GET_STATUS: Getter = |env| {
         let js = &env.perproc_data.statuses;
         EnvVar::new_from_name(L!("status"), js.status.to_wstring())
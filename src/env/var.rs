@@ -32,6 +32,14 @@ bitflags! {
         const PATHVAR = 1 << 6;
         /// Flag to unmark a variable as a path variable.
         const UNPATHVAR = 1 << 7;
+        /// Flag to keep a variable's elements sorted.
+        const SORTED = 1 << 8;
+        /// Flag to stop keeping a variable's elements sorted.
+        const UNSORTED = 1 << 9;
+        /// Flag to remove duplicate elements from a variable.
+        const UNIQUE = 1 << 10;
+        /// Flag to stop removing duplicate elements from a variable.
+        const NONUNIQUE = 1 << 11;
     }
 }
 
@@ -114,6 +122,8 @@ bitflags! {
         const EXPORT = 1 << 0;    // whether the variable is exported
         const READ_ONLY = 1 << 1; // whether the variable is read only
         const PATHVAR = 1 << 2;   // whether the variable is a path variable
+        const SORTED = 1 << 3;    // whether the variable's elements are kept sorted
+        const UNIQUE = 1 << 4;    // whether duplicate elements are removed from the variable
     }
 }
 
@@ -124,6 +134,8 @@ pub struct EnvVar {
     /// The list of values in this variable.
     /// Arc allows for cheap copying
     values: Arc<[WString]>,
+    /// For maps, the key of each value. None for ordinary lists.
+    keys: Option<Arc<[WString]>>,
     /// The variable's flags.
     flags: EnvVarFlags,
 }
@@ -136,6 +148,7 @@ impl Default for EnvVar {
 
         EnvVar {
             values: Arc::clone(&*EMPTY_LIST),
+            keys: None,
             flags: EnvVarFlags::empty(),
         }
     }
@@ -151,6 +164,17 @@ impl EnvVar {
     pub fn new_vec(values: Vec<WString>, flags: EnvVarFlags) -> Self {
         EnvVar {
             values: values.into(),
+            keys: None,
+            flags,
+        }
+    }
+
+    /// Creates a new map `EnvVar`, where each value has the key at the same position.
+    pub fn new_map(keys: Vec<WString>, values: Vec<WString>, flags: EnvVarFlags) -> Self {
+        assert_eq!(keys.len(), values.len(), "Each value should have a key");
+        EnvVar {
+            values: values.into(),
+            keys: Some(keys.into()),
             flags,
         }
     }
@@ -180,6 +204,16 @@ impl EnvVar {
         self.flags.contains(EnvVarFlags::PATHVAR)
     }
 
+    /// Returns whether the variable's elements are kept sorted.
+    pub fn is_sorted(&self) -> bool {
+        self.flags.contains(EnvVarFlags::SORTED)
+    }
+
+    /// Returns whether duplicate elements are removed from the variable.
+    pub fn is_unique(&self) -> bool {
+        self.flags.contains(EnvVarFlags::UNIQUE)
+    }
+
     /// Returns whether the variable is read-only.
     pub fn is_read_only(&self) -> bool {
         self.flags.contains(EnvVarFlags::READ_ONLY)
@@ -200,6 +234,22 @@ impl EnvVar {
         &self.values
     }
 
+    /// Returns whether the variable is a map.
+    pub fn is_map(&self) -> bool {
+        self.keys.is_some()
+    }
+
+    /// Returns the keys of a map, in the same order as the values.
+    pub fn map_keys(&self) -> Option<&[WString]> {
+        self.keys.as_deref()
+    }
+
+    /// Returns the value for a key of a map.
+    pub fn get_by_key(&self, key: &wstr) -> Option<&WString> {
+        let pos = self.map_keys()?.iter().position(|k| k == key)?;
+        Some(&self.values[pos])
+    }
+
     /// Returns the delimiter character used when converting from a list to a string.
     pub fn delimiter(&self) -> char {
         if self.is_pathvar() {
@@ -209,20 +259,27 @@ impl EnvVar {
         }
     }
 
-    /// Returns a copy of the variable with new values.
+    /// Returns a copy of the variable with new values. This turns a map into a list.
     pub fn setting_vals(&self, values: Vec<WString>) -> Self {
         EnvVar {
             values: values.into(),
+            keys: None,
             flags: self.flags,
         }
     }
 
+    /// Returns a copy of the variable as a map with the given keys and values.
+    pub fn setting_map(&self, keys: Vec<WString>, values: Vec<WString>) -> Self {
+        Self::new_map(keys, values, self.flags)
+    }
+
     /// Returns a copy of the variable with the export flag changed.
     pub fn setting_exports(&self, export: bool) -> Self {
         let mut flags = self.flags;
         flags.set(EnvVarFlags::EXPORT, export);
         EnvVar {
             values: self.values.clone(),
+            keys: self.keys.clone(),
             flags,
         }
     }
@@ -233,6 +290,19 @@ impl EnvVar {
         flags.set(EnvVarFlags::PATHVAR, pathvar);
         EnvVar {
             values: self.values.clone(),
+            keys: self.keys.clone(),
+            flags,
+        }
+    }
+
+    /// Returns a copy of the variable with the sorted and unique flags changed.
+    pub fn setting_order(&self, sorted: bool, unique: bool) -> Self {
+        let mut flags = self.flags;
+        flags.set(EnvVarFlags::SORTED, sorted);
+        flags.set(EnvVarFlags::UNIQUE, unique);
+        EnvVar {
+            values: self.values.clone(),
+            keys: self.keys.clone(),
             flags,
         }
     }
@@ -318,5 +388,19 @@ mod tests {
         assert_eq!(v1, v2);
         assert_ne!(v1, v3);
         assert_ne!(v1, v4);
+
+        let map = EnvVar::new_map(
+            vec![L!("k1").to_owned(), L!("k2").to_owned()],
+            vec![L!("v1").to_owned(), L!("v2").to_owned()],
+            EnvVarFlags::empty(),
+        );
+        assert!(map.is_map());
+        assert_eq!(map.get_by_key(L!("k2")).unwrap(), L!("v2"));
+        assert!(map.get_by_key(L!("k3")).is_none());
+        assert_ne!(
+            map,
+            EnvVar::new_vec(map.as_list().to_vec(), EnvVarFlags::empty())
+        );
+        assert!(!map.setting_vals(vec![]).is_map());
     }
 }
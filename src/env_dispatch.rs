@@ -1,10 +1,14 @@
 use crate::common::init_special_chars_once;
-use crate::complete::complete_invalidate_path;
+use crate::complete::{complete_invalidate_path, complete_set_fuzzy_match_mode};
+use crate::debugger;
 use crate::env::{DEFAULT_READ_BYTE_LIMIT, READ_BYTE_LIMIT};
 use crate::env::{EnvMode, EnvStack, Environment as _, setenv_lock, unsetenv_lock};
 use crate::flog::flog;
 use crate::function;
-use crate::input::{update_wait_on_escape_ms, update_wait_on_sequence_key_ms};
+use crate::highlight::highlight_rule_var_changed;
+use crate::history::HistoryBackend;
+use crate::input::{key_translations, update_wait_on_escape_ms, update_wait_on_sequence_key_ms};
+use crate::kill::set_killring_clipboard;
 use crate::locale::{invalidate_numeric_locale, set_libc_locales};
 use crate::prelude::*;
 use crate::reader::{
@@ -14,6 +18,7 @@ use crate::reader::{
 };
 use crate::screen::{IS_DUMB, ONLY_GRAYSCALE, screen_set_midnight_commander_hack};
 use crate::terminal::ColorSupport;
+use crate::tty_handoff::{get_truecolor_capability, set_focus_reporting, set_mouse_reporting};
 use crate::wutil::fish_wcstoi;
 use fish_wcstringutil::{bool_from_string, string_prefixes_string};
 use std::collections::HashMap;
@@ -71,6 +76,10 @@ static VAR_DISPATCH_TABLE: once_cell::sync::Lazy<VarDispatchTable> =
         table.add_anon(L!("fish_function_path"), vars!(handle_function_path_change));
         table.add_anon(L!("fish_read_limit"), vars!(handle_read_limit_change));
         table.add_anon(L!("fish_history"), vars!(handle_fish_history_change));
+        table.add_anon(
+            L!("fish_history_backend"),
+            vars!(handle_fish_history_backend_change),
+        );
         table.add_anon(
             L!("fish_autosuggestion_enabled"),
             vars!(handle_autosuggestion_change),
@@ -92,6 +101,22 @@ static VAR_DISPATCH_TABLE: once_cell::sync::Lazy<VarDispatchTable> =
             L!("fish_cursor_end_mode"),
             vars!(handle_fish_cursor_end_mode_change),
         );
+        table.add_anon(
+            L!("fish_mouse_reporting"),
+            vars!(handle_mouse_reporting_change),
+        );
+        table.add_anon(
+            L!("fish_command_notification"),
+            vars!(handle_command_notification_change),
+        );
+        table.add_anon(
+            L!("fish_killring_clipboard"),
+            vars!(handle_killring_clipboard_change),
+        );
+        table.add_anon(
+            L!("fish_completion_match_mode"),
+            vars!(handle_completion_match_mode_change),
+        );
 
         table
     });
@@ -194,6 +219,8 @@ pub fn env_dispatch_var_change(milieu: VarChangeMilieu, key: &wstr, vars: &EnvSt
     if let Some(dispatch_table) = Lazy::get(&VAR_DISPATCH_TABLE) {
         dispatch_table.dispatch(key, vars, suppress_repaint);
     }
+    highlight_rule_var_changed(key);
+    debugger::var_changed(key, vars);
 
     if !suppress_repaint {
         if let Some(data) = reader_current_data() {
@@ -235,6 +262,16 @@ fn handle_fish_history_change(vars: &EnvStack) {
     reader_change_history(history_id);
 }
 
+fn handle_fish_history_backend_change(vars: &EnvStack) {
+    if let Some(var) = vars.get(L!("fish_history_backend")) {
+        let name = var.as_string();
+        if HistoryBackend::from_name(&name).is_none() {
+            flog!(warning, "Ignoring unsupported $fish_history_backend", name);
+        }
+    }
+    handle_fish_history_change(vars);
+}
+
 pub fn handle_fish_cursor_selection_mode_change(vars: &EnvStack) {
     use crate::reader::CursorSelectionMode;
 
@@ -277,6 +314,35 @@ fn handle_transient_prompt_change(vars: &EnvStack) {
     reader_set_transient_prompt(vars);
 }
 
+fn handle_killring_clipboard_change(vars: &EnvStack) {
+    let enable = vars
+        .get(L!("fish_killring_clipboard"))
+        .is_some_and(|var| bool_from_string(&var.as_string()));
+    set_killring_clipboard(enable);
+}
+
+fn handle_completion_match_mode_change(vars: &EnvStack) {
+    let fuzzy = vars
+        .get(L!("fish_completion_match_mode"))
+        .is_some_and(|var| var.as_string() == "fuzzy");
+    complete_set_fuzzy_match_mode(fuzzy);
+}
+
+fn handle_mouse_reporting_change(vars: &EnvStack) {
+    let enable = vars
+        .get(L!("fish_mouse_reporting"))
+        .is_some_and(|var| bool_from_string(&var.as_string()));
+    set_mouse_reporting(enable);
+}
+
+fn handle_command_notification_change(vars: &EnvStack) {
+    // Notifications are only sent while the terminal is not focused.
+    let enable = vars
+        .get(L!("fish_command_notification"))
+        .is_some_and(|var| bool_from_string(&var.as_string()));
+    set_focus_reporting(enable);
+}
+
 fn handle_function_path_change(_: &EnvStack) {
     function::invalidate_path();
 }
@@ -367,10 +433,12 @@ fn run_inits(vars: &EnvStack) {
     handle_read_limit_change(vars);
     handle_fish_use_posix_spawn_change(vars);
     handle_fish_trace(vars);
+    handle_killring_clipboard_change(vars);
+    handle_completion_match_mode_change(vars);
 }
 
 /// Updates our idea of whether we support term256 and term24bit (see issue #10222).
-fn update_fish_color_support(vars: &EnvStack) {
+pub fn update_fish_color_support(vars: &EnvStack) {
     // Detect or infer term256 support. If fish_term256 is set, we respect it. Otherwise, infer it
     // from $TERM.
 
@@ -428,6 +496,12 @@ fn update_fish_color_support(vars: &EnvStack) {
             "per $COLORTERM",
             ct
         );
+    } else if get_truecolor_capability() == Some(true) {
+        supports_24bit = true;
+        flog!(
+            term_support,
+            "True-color support: enabled per terminal response"
+        );
     } else {
         supports_24bit = !is_xterm_16color && {
             // TODO(term-workaround)
@@ -463,6 +537,7 @@ fn init_terminal(vars: &EnvStack) {
         .unwrap_or(L!(""));
 
     IS_DUMB.store(term == "dumb");
+    key_translations().set_term(term);
     ONLY_GRAYSCALE.store(term == "ansi-m" || term == "linux-m" || term == "xterm-mono");
 
     // TODO(term-workaround)
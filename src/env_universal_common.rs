@@ -557,6 +557,10 @@ impl EnvUniversal {
                 flags |= EnvVarFlags::EXPORT;
             } else if r#match(&mut cursor, f3::PATH) {
                 flags |= EnvVarFlags::PATHVAR;
+            } else if r#match(&mut cursor, f3::SORTED) {
+                flags |= EnvVarFlags::SORTED;
+            } else if r#match(&mut cursor, f3::UNIQUE) {
+                flags |= EnvVarFlags::UNIQUE;
             } else {
                 // Skip this unknown flag, for future proofing.
                 while !cursor.is_empty() && !matches!(cursor.char_at(0), ' ' | '\t') {
@@ -650,6 +654,8 @@ mod fish3_uvars {
     pub const SETUVAR: &[u8] = b"SETUVAR";
     pub const EXPORT: &[u8] = b"--export";
     pub const PATH: &[u8] = b"--path";
+    pub const SORTED: &[u8] = b"--sorted";
+    pub const UNIQUE: &[u8] = b"--unique";
 }
 
 /// Test if the message msg contains the command cmd.
@@ -734,6 +740,14 @@ fn append_file_entry(
         result.extend_from_slice(f3::PATH);
         result.push(b' ');
     }
+    if flags.contains(EnvVarFlags::SORTED) {
+        result.extend_from_slice(f3::SORTED);
+        result.push(b' ');
+    }
+    if flags.contains(EnvVarFlags::UNIQUE) {
+        result.extend_from_slice(f3::UNIQUE);
+        result.push(b' ');
+    }
 
     // Append variable name like "fish_color_cwd".
     if !valid_var_name(key_in) {
@@ -924,6 +938,13 @@ mod tests {
                 EnvVarFlags::empty(),
             ),
         );
+        vars.insert(
+            L!("varG").to_owned(),
+            EnvVar::new_vec(
+                vec![L!("ValG1").to_owned(), L!("ValG2").to_owned()],
+                EnvVarFlags::SORTED | EnvVarFlags::UNIQUE,
+            ),
+        );
 
         let text = EnvUniversal::serialize_with_vars(&vars);
         let expected = concat!(
@@ -935,6 +956,7 @@ mod tests {
             "SETUVAR --export --path varD:ValD1\n",
             "SETUVAR --path varE:ValE1\\x1eValE2\n",
             "SETUVAR varF:\\xfc\n",
+            "SETUVAR --sorted --unique varG:ValG1\\x1eValG2\n",
         )
         .as_bytes();
         assert_eq!(text, expected);
@@ -952,6 +974,7 @@ mod tests {
             "SETUVAR --nonsenseflag varC:ValC1\n",
             "SETUVAR --export --path varD:ValD1\n",
             "SETUVAR --path --path varE:ValE1\\x1eValE2\n",
+            "SETUVAR --unique --sorted varG:ValG1\\x1eValG2\n",
         )
         .as_bytes();
 
@@ -986,6 +1009,13 @@ mod tests {
                 flag_pathvar,
             ),
         );
+        vars.insert(
+            L!("varG").to_owned(),
+            EnvVar::new_vec(
+                vec![L!("ValG1").to_owned(), L!("ValG2").to_owned()],
+                EnvVarFlags::SORTED | EnvVarFlags::UNIQUE,
+            ),
+        );
 
         let mut parsed_vars = VarTable::new();
         EnvUniversal::populate_variables(input, &mut parsed_vars);
@@ -10,15 +10,18 @@ use crate::{
     job_group::MaybeJobId,
     parser::{Block, Parser},
     prelude::*,
-    proc::{InternalJobId, Pid},
+    proc::{InternalJobId, Job, Pid},
     reader::reader_update_termsize,
     signal::{RawSignal, signal_check_cancel, signal_handle},
 };
 use fish_common::{ScopeGuard, escape};
 use fish_widestring::str2wcstring;
-use std::sync::{
-    Arc, Mutex,
-    atomic::{AtomicBool, AtomicU32, Ordering},
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    },
+    time::Duration,
 };
 
 pub enum EventType {
@@ -129,6 +132,8 @@ pub struct EventHandler {
     pub desc: EventDescription,
     /// Name of the function to invoke.
     pub function_name: WString,
+    /// For handlers installed by `trap`, the command to run. These have no function name.
+    pub trap_command: Option<WString>,
     /// A flag set when an event handler is removed from the global list.
     /// Once set, this is never cleared.
     pub removed: AtomicBool,
@@ -141,11 +146,20 @@ impl EventHandler {
         Self {
             desc,
             function_name: name.unwrap_or_default(),
+            trap_command: None,
             removed: AtomicBool::new(false),
             fired: AtomicBool::new(false),
         }
     }
 
+    /// Create a handler which runs `command`, as installed by `trap`.
+    pub fn trap(desc: EventDescription, command: WString) -> Self {
+        Self {
+            trap_command: Some(command),
+            ..Self::new(desc, None)
+        }
+    }
+
     /// Return true if a handler is "one shot": it fires at most once.
     fn is_one_shot(&self) -> bool {
         match self.desc {
@@ -266,6 +280,30 @@ impl Event {
         }
     }
 
+    /// The generic `fish_job_complete` event, fired when a background job finishes.
+    /// The arguments are the job id, the pgid (empty if the job has none), the command line, the
+    /// exit status, and the wall duration in milliseconds.
+    pub fn job_complete(
+        job_id: MaybeJobId,
+        pgid: Option<Pid>,
+        command: WString,
+        status: i32,
+        duration: Duration,
+    ) -> Self {
+        Self {
+            desc: EventDescription::Generic {
+                param: L!("fish_job_complete").to_owned(),
+            },
+            arguments: vec![
+                job_id.to_wstring(),
+                pgid.map(|pgid| pgid.to_string().into()).unwrap_or_default(),
+                command,
+                status.to_wstring(),
+                duration.as_millis().to_wstring(),
+            ],
+        }
+    }
+
     /// Test if specified event is blocked.
     fn is_blocked(&self, parser: &Parser) -> bool {
         for block in parser.blocks_iter_rev() {
@@ -303,6 +341,15 @@ impl PendingSignals {
         }
     }
 
+    /// Return whether a signal may have been received since the last `acquire_pending()`.
+    pub fn has_pending(&self) -> bool {
+        let current = self
+            .last_counter
+            .lock()
+            .expect("mutex should not be poisoned");
+        self.counter.load(Ordering::Acquire) != *current
+    }
+
     /// Return the list of signals that were set as the bits in a u64, clearing them.
     pub fn acquire_pending(&self) -> u64 {
         let mut current = self
@@ -335,6 +382,8 @@ impl PendingSignals {
 const ATOMIC_BOOL_FALSE: AtomicBool = AtomicBool::new(false);
 #[allow(clippy::declare_interior_mutable_const)]
 const ATOMIC_U32_0: AtomicU32 = AtomicU32::new(0);
+#[allow(clippy::declare_interior_mutable_const)]
+const ATOMIC_U64_0: AtomicU64 = AtomicU64::new(0);
 
 static PENDING_SIGNALS: PendingSignals = PendingSignals {
     counter: AtomicU32::new(0),
@@ -342,6 +391,63 @@ static PENDING_SIGNALS: PendingSignals = PendingSignals {
     last_counter: Mutex::new(0),
 };
 
+/// The foreground job fish is waiting for, as a number that is different for each wait, or 0 if
+/// there is none. This is read by the signal handler.
+static FOREGROUND_JOB: AtomicU64 = AtomicU64::new(0);
+
+/// For each signal, the value of `FOREGROUND_JOB` when it was last received.
+static PENDING_SIGNAL_JOBS: [AtomicU64; SIGNAL_COUNT] = [ATOMIC_U64_0; SIGNAL_COUNT];
+
+/// What signal handlers learn about the foreground job: the job ID, the process ID of its first
+/// process and its command line.
+struct ForegroundJobInfo {
+    serial: u64,
+    arguments: [WString; 3],
+}
+
+/// The most recent foreground job. It is kept after the job is done, because signal handlers only
+/// run after that.
+static FOREGROUND_JOB_INFO: Mutex<Option<ForegroundJobInfo>> = Mutex::new(None);
+
+/// Note that fish is about to wait for the foreground job `job`, so that handlers for signals
+/// received in the meantime can be told about it.
+pub fn enter_foreground_job(job: &Job) {
+    static SERIAL: AtomicU64 = AtomicU64::new(0);
+    let serial = SERIAL.fetch_add(1, Ordering::Relaxed) + 1;
+    let pid = job
+        .external_procs()
+        .next()
+        .and_then(|p| p.pid())
+        .map(|pid| pid.to_string().into())
+        .unwrap_or_default();
+    *FOREGROUND_JOB_INFO.lock().expect("Mutex poisoned!") = Some(ForegroundJobInfo {
+        serial,
+        arguments: [job.job_id().to_wstring(), pid, job.command().to_owned()],
+    });
+    FOREGROUND_JOB.store(serial, Ordering::Relaxed);
+}
+
+/// Note that fish is done waiting for the foreground job.
+pub fn leave_foreground_job() {
+    FOREGROUND_JOB.store(0, Ordering::Relaxed);
+}
+
+/// The arguments for a handler of `sig`: the signal name, followed by the foreground job if there
+/// was one when the signal arrived.
+fn signal_arguments(sig: RawSignal) -> Vec<WString> {
+    let mut arguments = vec![sig.name().into()];
+    let serial = PENDING_SIGNAL_JOBS
+        .get(usize::from(sig))
+        .map_or(0, |serial| serial.swap(0, Ordering::Relaxed));
+    if serial != 0 {
+        let info = FOREGROUND_JOB_INFO.lock().expect("Mutex poisoned!");
+        if let Some(info) = info.as_ref().filter(|info| info.serial == serial) {
+            arguments.extend(info.arguments.iter().cloned());
+        }
+    }
+    arguments
+}
+
 /// List of event handlers. **While this is locked to allow safely accessing/modifying the vector,
 /// note that it does NOT provide exclusive access to the [`EventHandler`] objects which are shared
 /// references (in an `Arc<T>`).**
@@ -449,6 +555,27 @@ pub fn remove_function_handlers(name: &wstr) -> usize {
     remove_handlers_if(|h| h.function_name == name)
 }
 
+/// Install `command` as the trap for `desc`, replacing any previous trap for it.
+pub fn set_trap(desc: EventDescription, command: WString) {
+    remove_trap(&desc);
+    add_handler(EventHandler::trap(desc, command));
+}
+
+/// Remove the trap for `desc`. Return whether there was one.
+pub fn remove_trap(desc: &EventDescription) -> bool {
+    remove_handlers_if(|h| h.trap_command.is_some() && h.desc == *desc) > 0
+}
+
+/// Return the installed traps, with their commands.
+pub fn get_traps() -> Vec<(EventDescription, WString)> {
+    EVENT_HANDLERS
+        .lock()
+        .expect("event handler list should not be poisoned")
+        .iter()
+        .filter_map(|h| Some((h.desc.clone(), h.trap_command.clone()?)))
+        .collect()
+}
+
 /// Return all event handlers for the given function.
 pub fn get_function_handlers(name: &wstr) -> EventHandlerList {
     EVENT_HANDLERS
@@ -488,19 +615,26 @@ fn fire_internal(parser: &mut Parser, event: &Event) {
         }
 
         // Construct a buffer to evaluate, starting with the function name and then all the
-        // arguments.
-        let mut buffer = handler.function_name.clone();
-        for arg in &event.arguments {
-            buffer.push(' ');
-            buffer.push_utfstr(&escape(arg));
-        }
+        // arguments. Traps run their command as is.
+        let buffer = if let Some(command) = &handler.trap_command {
+            command.clone()
+        } else {
+            let mut buffer = handler.function_name.clone();
+            for arg in &event.arguments {
+                buffer.push(' ');
+                buffer.push_utfstr(&escape(arg));
+            }
+            buffer
+        };
 
         // Event handlers are not part of the main flow of code, so they are marked as
         // non-interactive.
         let _non_interactive = parser.push_scope(|s| s.is_interactive = false);
         let saved_statuses = parser.last_statuses();
+        let saved_return_value = parser.vars().last_return_value();
         let parser = &mut **ScopeGuard::new(&mut *parser, |parser| {
             parser.set_last_statuses(saved_statuses);
+            parser.vars().set_last_return_value(saved_return_value);
         });
 
         flog!(
@@ -557,7 +691,7 @@ pub fn fire_delayed(parser: &mut Parser) {
         }
         let event = Event {
             desc: EventDescription::Signal { signal: sig },
-            arguments: vec![sig.name().into()],
+            arguments: signal_arguments(sig),
         };
         to_send.push(event);
     }
@@ -581,9 +715,20 @@ pub fn fire_delayed(parser: &mut Parser) {
 /// Enqueue a signal event. Invoked from a signal handler.
 pub fn enqueue_signal(signal: libc::c_int) {
     // Beware, we are in a signal handler
+    if let Some(job) = PENDING_SIGNAL_JOBS.get(usize::try_from(signal).unwrap()) {
+        job.store(FOREGROUND_JOB.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
     PENDING_SIGNALS.mark(signal);
 }
 
+/// Fire the events for signals received since the last time, if any.
+/// This lets scripts react to signals even if they only run builtins and functions.
+pub fn fire_pending_signals(parser: &mut Parser) {
+    if PENDING_SIGNALS.has_pending() {
+        fire_delayed(parser);
+    }
+}
+
 /// Fire the specified event event, executing it on `parser`.
 pub fn fire(parser: &mut Parser, event: Event) {
     // Fire events triggered by signals.
@@ -621,6 +766,11 @@ pub fn print(streams: &mut IoStreams, type_filter: &wstr) {
         if !evt.desc.matches_filter(type_filter) {
             continue;
         }
+        // Traps have no function, so show their command like `trap --print` does.
+        let handler = match &evt.trap_command {
+            Some(command) => sprintf!("trap -- %s", escape(command)),
+            None => evt.function_name.clone(),
+        };
 
         // Print a "Event $TYPE" header for each event type.
         // This compares only the event *type*, not the entire event,
@@ -640,20 +790,14 @@ pub fn print(streams: &mut IoStreams, type_filter: &wstr) {
         match &evt.desc {
             EventDescription::Signal { signal } => {
                 let name: WString = signal.name().into();
-                streams
-                    .out
-                    .append(&sprintf!("%s %s\n", name, evt.function_name));
+                streams.out.append(&sprintf!("%s %s\n", name, handler));
             }
             EventDescription::ProcessExit { .. } | EventDescription::JobExit { .. } => {}
             EventDescription::CallerExit { .. } => {
-                streams
-                    .out
-                    .append(&sprintf!("caller-exit %s\n", evt.function_name));
+                streams.out.append(&sprintf!("caller-exit %s\n", handler));
             }
             EventDescription::Variable { name: param } | EventDescription::Generic { param } => {
-                streams
-                    .out
-                    .append(&sprintf!("%s %s\n", param, evt.function_name));
+                streams.out.append(&sprintf!("%s %s\n", param, handler));
             }
             EventDescription::Any => unreachable!(),
         }
@@ -11,7 +11,8 @@ use crate::env::{EnvMode, EnvSetMode, EnvStack, Environment as _, READ_BYTE_LIMI
 #[cfg(have_posix_spawn)]
 use crate::env_dispatch::use_posix_spawn;
 use crate::fds::{
-    BorrowedFdFile, PIPE_ERROR, make_autoclose_pipes, make_fd_blocking, open_cloexec,
+    BorrowedFdFile, PIPE_ERROR, inherited_fds_end, make_autoclose_pipes, make_fd_blocking,
+    open_cloexec,
 };
 use crate::flog::{flog, flogf};
 #[cfg(have_posix_spawn)]
@@ -19,13 +20,13 @@ use crate::fork_exec::spawn::PosixSpawner;
 use crate::fork_exec::{
     PATH_BSHELL, blocked_signals_for_job,
     postfork::{
-        child_setup_process, execute_fork, execute_setpgid, report_setpgid_error,
+        child_setup_process, close_fds_from, execute_fork, execute_setpgid, report_setpgid_error,
         signal_safe_report_exec_error,
     },
 };
 use crate::function::{self, FunctionProperties};
 use crate::io::{
-    BufferedOutputStream, FdOutputStream, IoBufferfill, IoChain, IoClose, IoMode, IoPipe,
+    BufferedOutputStream, FdOutputStream, IoBufferfill, IoChain, IoClose, IoFd, IoMode, IoPipe,
     IoStreams, OutputStream, SeparatedBuffer, StringOutputStream,
 };
 use crate::nix::isatty;
@@ -121,6 +122,9 @@ pub fn exec_job(parser: &mut Parser, job: &Job, block_io: IoChain) -> bool {
         return false;
     }
 
+    // Background jobs may have their output captured instead of interleaving with the prompt.
+    let block_io = maybe_capture_background_output(parser, job, block_io);
+
     // Get the deferred process, if any. We will have to remember its pipes.
     let mut deferred_pipes = PartialPipes::default();
     let deferred_process = get_deferred_process(job);
@@ -485,6 +489,33 @@ fn can_use_posix_spawn_for_job(job: &Job, dup2s: &Dup2List) -> bool {
     !wants_terminal
 }
 
+/// If `fish_background_output` is set to "capture", redirect stdout and stderr of a background job
+/// into a buffer stored on the job, unless they are already redirected for the whole block.
+/// The captured output may be viewed with `jobs --output`.
+fn maybe_capture_background_output(parser: &Parser, job: &Job, mut block_io: IoChain) -> IoChain {
+    if !job.is_initially_background() || block_io.io_for_fd(STDOUT_FILENO).is_some() {
+        return block_io;
+    }
+    if parser
+        .vars()
+        .get(L!("fish_background_output"))
+        .is_none_or(|var| var.as_string() != "capture")
+    {
+        return block_io;
+    }
+    let Ok(bufferfill) =
+        IoBufferfill::create_opts(READ_BYTE_LIMIT.load(Ordering::Relaxed), STDOUT_FILENO)
+    else {
+        return block_io;
+    };
+    block_io.push(bufferfill.clone());
+    if block_io.io_for_fd(STDERR_FILENO).is_none() {
+        block_io.push(Arc::new(IoFd::new(STDERR_FILENO, STDOUT_FILENO)));
+    }
+    *job.captured_output.borrow_mut() = Some(bufferfill);
+    block_io
+}
+
 fn internal_exec(vars: &EnvStack, is_repainting: bool, j: &Job, block_io: IoChain) {
     // Do a regular launch -  but without forking first...
     let mut all_ios = block_io;
@@ -660,7 +691,7 @@ fn run_internal_process_or_short_circuit(
     ios: &IoChain,
 ) {
     if outdata.is_empty() && errdata.is_empty() {
-        p.completed.store(true);
+        p.mark_completed();
         if p.is_last_in_job {
             flogf!(
                 exec_job_status,
@@ -941,7 +972,12 @@ fn exec_external_command(
         return Ok(());
     }
 
+    // Keep the fds we inherited without CLOEXEC, they are meant to be passed on.
+    let first_fd_to_close = inherited_fds_end().map(|end| end.max(dup2s.first_unused_fd()));
     fork_child_for_process(j, p, &dup2s, pgroup_policy, |p| {
+        if let Some(fd) = first_fd_to_close {
+            close_fds_from(fd);
+        }
         signal_safe_launch_process(p, &actual_cmd, &argv, &envv)
     })
 }
@@ -967,10 +1003,11 @@ fn function_prepare_environment(
     ));
     let vars = parser.vars();
 
-    // Setup the environment for the function. There are three components of the environment:
+    // Setup the environment for the function. There are four components of the environment:
     // 1. named arguments
-    // 2. inherited variables
-    // 3. argv
+    // 2. parameters
+    // 3. inherited variables
+    // 4. argv
 
     let mode = parser.convert_env_set_mode(ParserEnvSetMode::user(EnvMode::LOCAL));
 
@@ -986,6 +1023,26 @@ fn function_prepare_environment(
         }
     }
 
+    // The number of arguments was checked when the call was executed.
+    for (idx, param) in props.params.iter().enumerate() {
+        if param.name == L!("argv") {
+            overwrite_argv = true;
+        }
+        if param.variadic {
+            vars.set(
+                &param.name,
+                mode,
+                argv.get(idx..).unwrap_or_default().to_vec(),
+            );
+        } else if let Some(arg) = argv.get(idx) {
+            vars.set_one(&param.name, mode, arg.clone());
+        } else if let Some(default) = &param.default {
+            vars.set_one(&param.name, mode, default.clone());
+        } else {
+            vars.set_empty(&param.name, mode);
+        }
+    }
+
     for (key, value) in &*props.inherit_vars {
         if key == L!("argv") {
             overwrite_argv = true;
@@ -1005,6 +1062,11 @@ fn function_restore_environment(parser: &mut Parser, block: BlockId) {
 
     // If we returned due to a return statement, then stop returning now.
     parser.libdata_mut().returning = false;
+
+    // Hand any values from `return --value` to the caller. A function that returned no values
+    // clears them, so $fish_return_value always belongs to the last function call.
+    let return_value = parser.libdata_mut().return_value.take().unwrap_or_default();
+    parser.vars().set_last_return_value(return_value);
 }
 
 // The "performer" function of a block or function process.
@@ -1344,6 +1406,7 @@ fn exec_process_in_job(
 
     // Execute the process.
     p.check_generations_before_launch();
+    p.mark_launched();
     match p.typ {
         ProcessType::Function | ProcessType::BlockNode(_) => exec_block_or_func_process(
             parser,
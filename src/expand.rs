@@ -16,23 +16,26 @@ use crate::{
     history::{History, history_id},
     operation_context::OperationContext,
     parse_constants::{ParseError, ParseErrorCode, ParseErrorList, SOURCE_LOCATION_UNKNOWN},
-    parse_util::{MaybeParentheses, expand_variable_error, locate_cmdsubst_range},
+    parse_util::{
+        MaybeParentheses, cmdsubst_is_collected, expand_variable_error, locate_cmdsubst_range,
+    },
     path::path_apply_working_directory,
     prelude::*,
-    wildcard::{WildcardResult, wildcard_expand_string, wildcard_has_internal},
+    wildcard::{GlobstarOptions, WildcardResult, wildcard_expand_string, wildcard_has_internal},
     wutil::{normalize_path, wcstoi, wcstoi_partial},
 };
 use bitflags::bitflags;
 use fish_common::{
-    EscapeFlags, EscapeStringStyle, UnescapeFlags, UnescapeStringStyle, escape, escape_string,
-    escape_string_for_double_quotes, unescape_string,
+    BraceRange, EscapeFlags, EscapeStringStyle, UnescapeFlags, UnescapeStringStyle, escape,
+    escape_string, escape_string_for_double_quotes, glob_qualifiers::GlobQualifiers,
+    unescape_string,
 };
 use fish_feature_flags::{FeatureFlag, feature_test};
 use fish_util::wcsfilecmp_glob;
 use fish_wcstringutil::{join_strings, trim};
 use fish_widestring::{
     ANY_CHAR, ANY_STRING, ANY_STRING_RECURSIVE, BRACE_BEGIN, BRACE_END, BRACE_SEP, BRACE_SPACE,
-    HOME_DIRECTORY, INTERNAL_SEPARATOR, PROCESS_EXPAND_SELF, VARIABLE_EXPAND,
+    GLOB_QUALIFIER, HOME_DIRECTORY, INTERNAL_SEPARATOR, PROCESS_EXPAND_SELF, VARIABLE_EXPAND,
     VARIABLE_EXPAND_EMPTY, VARIABLE_EXPAND_SINGLE, osstr2wcstring,
 };
 use nix::unistd::{User, getpid};
@@ -521,6 +524,25 @@ fn parse_slice(
     Ok(pos)
 }
 
+/// Parse the keys of a map slice like `[key1 key2]`, which are separated by whitespace.
+/// Returns the length of the slice including the brackets, or None if there is no closing bracket.
+fn parse_map_keys(input: &wstr, keys: &mut Vec<WString>) -> Option<usize> {
+    let mut key = WString::new();
+    for (pos, c) in input.chars().enumerate().skip(1) {
+        if c == ']' || c.is_whitespace() || c == INTERNAL_SEPARATOR || c == VARIABLE_EXPAND_EMPTY {
+            if !key.is_empty() {
+                keys.push(std::mem::take(&mut key));
+            }
+            if c == ']' {
+                return Some(pos + 1);
+            }
+        } else {
+            key.push(c);
+        }
+    }
+    None
+}
+
 /// Expand all environment variables in the string *ptr.
 ///
 /// This function is slow, fragile and complicated. There are lots of little corner cases, like
@@ -624,8 +646,21 @@ fn expand_variables(
     let mut all_values = true;
     let slice_start = var_name_stop;
     let mut var_idx_list = vec![];
+    let mut map_keys = None;
 
-    if instr.as_char_slice().get(slice_start) == Some(&'[') {
+    if instr.as_char_slice().get(slice_start) == Some(&'[')
+        && var.as_ref().is_some_and(|var| var.is_map())
+    {
+        // Maps are indexed by keys instead of positions.
+        all_values = false;
+        let mut keys = vec![];
+        let Some(offset) = parse_map_keys(&instr[slice_start..], &mut keys) else {
+            append_syntax_error!(errors, slice_start, "Invalid index value");
+            return ExpandResult::make_error(STATUS_EXPAND_ERROR);
+        };
+        var_name_and_slice_stop = slice_start + offset;
+        map_keys = Some(keys);
+    } else if instr.as_char_slice().get(slice_start) == Some(&'[') {
         all_values = false;
         // If a variable is missing, behave as though we have one value, so that $var[1] always
         // works.
@@ -703,6 +738,10 @@ fn expand_variables(
                     var_item_list.push(item.clone());
                 }
             }
+        } else if let Some(keys) = map_keys {
+            // Missing keys expand to nothing, like indexes that are out of bounds.
+            let var = var.as_ref().unwrap();
+            var_item_list.extend(keys.iter().filter_map(|key| var.get_by_key(key)).cloned());
         } else {
             let all_var_items = var.as_ref().unwrap().as_list();
             for item_index in var_idx_list {
@@ -844,6 +883,25 @@ fn expand_braces(
     };
     let brace_end = brace_end.unwrap();
 
+    // A range like {1..10} expands to each of its values.
+    if let Some(range) = BraceRange::parse(&input[brace_begin + 1..brace_end]) {
+        // Check the size up front, so a huge range fails instead of making values until the
+        // limit is hit.
+        if range.value_count() > out.remaining_capacity() as u64 {
+            return append_overflow_error(errors, None);
+        }
+        for value in range.values() {
+            let mut whole_item = input[..brace_begin].to_owned();
+            whole_item.push_utfstr(&value);
+            whole_item.push_utfstr(&input[brace_end + 1..]);
+            let res = expand_braces(whole_item, flags, out, errors);
+            if res != ExpandResultCode::Ok {
+                return res;
+            }
+        }
+        return ExpandResult::ok();
+    }
+
     let length_preceding_braces = brace_begin;
     let length_following_braces = input.len() - brace_end - 1;
     let tot_len = length_preceding_braces + length_following_braces;
@@ -920,6 +978,8 @@ pub fn expand_cmdsubst(
         }
         MaybeParentheses::CommandSubstitution(parens) => parens,
     };
+    let collected = has_dollar && !is_quoted && cmdsubst_is_collected(&input, parens.start());
+    let prefix_end = parens.start() - usize::from(has_dollar) - usize::from(collected);
 
     let mut sub_res = vec![];
     let job_group = ctx.job_group.clone();
@@ -1055,7 +1115,7 @@ pub fn expand_cmdsubst(
             whole_item.reserve(
                 parens.start() + 1 + sub_res_joined.len() + 1 + tail_item.completion.len(),
             );
-            whole_item.push_utfstr(&input[..parens.start() - if has_dollar { 1 } else { 0 }]);
+            whole_item.push_utfstr(&input[..prefix_end]);
             whole_item.push(INTERNAL_SEPARATOR);
             whole_item.push_utfstr(&sub_res_joined);
             whole_item.push(INTERNAL_SEPARATOR);
@@ -1068,13 +1128,24 @@ pub fn expand_cmdsubst(
         return ExpandResult::ok();
     }
 
+    if collected {
+        // Like a quoted command substitution, the output is one argument without trailing newlines.
+        let mut sub_res_joined = join_strings(&sub_res, '\n');
+        let mut i = sub_res_joined.len();
+        while i > 0 && sub_res_joined.as_char_slice()[i - 1] == '\n' {
+            i -= 1;
+        }
+        sub_res_joined.truncate(i);
+        sub_res = vec![sub_res_joined];
+    }
+
     for sub_item in sub_res {
         let sub_item2 = escape_string(&sub_item, EscapeStringStyle::Script(EscapeFlags::COMMA));
         for tail_item in &*tail_expand {
             let mut whole_item = WString::new();
             whole_item
                 .reserve(parens.start() + 1 + sub_item2.len() + 1 + tail_item.completion.len());
-            whole_item.push_utfstr(&input[..parens.start() - if has_dollar { 1 } else { 0 }]);
+            whole_item.push_utfstr(&input[..prefix_end]);
             whole_item.push(INTERNAL_SEPARATOR);
             whole_item.push_utfstr(&sub_item2);
             whole_item.push(INTERNAL_SEPARATOR);
@@ -1152,8 +1223,12 @@ fn remove_internal_separator(s: &mut WString, conv: bool) {
     s.retain(|c| c != INTERNAL_SEPARATOR);
 
     // If conv is true, replace all instances of ANY_STRING with '*',
-    // ANY_STRING_RECURSIVE with '*'.
+    // ANY_STRING_RECURSIVE with '*', and restore the parentheses around glob qualifiers.
     if conv {
+        if let Some(idx) = s.find_char(GLOB_QUALIFIER) {
+            s.as_char_slice_mut()[idx] = '(';
+            s.push(')');
+        }
         for idx in s.as_char_slice_mut() {
             match *idx {
                 ANY_CHAR => {
@@ -1368,6 +1443,23 @@ impl<'a, 'b, 'c> Expander<'a, 'b, 'c> {
     ) -> ExpandResult {
         let mut result = ExpandResult::ok();
 
+        // Split off glob qualifiers, like the "(.)" in "*(.)".
+        let (path_to_expand, qualifiers) = match path_to_expand.find_char(GLOB_QUALIFIER) {
+            Some(idx) => {
+                let Some(qualifiers) = GlobQualifiers::parse(&path_to_expand[idx + 1..]) else {
+                    append_syntax_error!(
+                        self.errors,
+                        SOURCE_LOCATION_UNKNOWN,
+                        "Invalid glob qualifiers '%s'",
+                        &path_to_expand[idx + 1..]
+                    );
+                    return ExpandResult::make_error(STATUS_EXPAND_ERROR);
+                };
+                (path_to_expand[..idx].to_owned(), qualifiers)
+            }
+            None => (path_to_expand, GlobQualifiers::default()),
+        };
+
         let has_wildcard = wildcard_has_internal(&path_to_expand); // e.g. ANY_STRING
         let for_completions = self.flags.contains(ExpandFlags::FOR_COMPLETIONS);
         let skip_wildcards = self.flags.contains(ExpandFlags::SKIP_WILDCARDS);
@@ -1431,12 +1523,19 @@ impl<'a, 'b, 'c> Expander<'a, 'b, 'c> {
             }
 
             result = ExpandResult::new(ExpandResultCode::WildcardNoMatch);
+            let globstar = if path_to_expand.contains(ANY_STRING_RECURSIVE) {
+                GlobstarOptions::from_vars(self.ctx.vars())
+            } else {
+                GlobstarOptions::default()
+            };
             let mut expanded_recv = out.subreceiver();
             for effective_working_dir in effective_working_dirs {
                 let expand_res = wildcard_expand_string(
                     &path_to_expand,
                     &effective_working_dir,
                     self.flags,
+                    &globstar,
+                    &qualifiers,
                     &*self.ctx.cancel_checker,
                     &mut expanded_recv,
                 );
@@ -1970,8 +2069,8 @@ mod tests {
         });
 
         // Helper to expand an abbreviation, enforcing we have no more than one result.
-        let abbr_expand_1 = |token, pos| -> Option<WString> {
-            let result = with_abbrs(|abbrset| abbrset.r#match(token, pos, L!("")));
+        let abbr_expand_1 = |token: &wstr, pos| -> Option<WString> {
+            let result = with_abbrs(|abbrset| abbrset.r#match(&[token], pos, L!("")));
             if result.is_empty() {
                 return None;
             }
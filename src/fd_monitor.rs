@@ -1,3 +1,5 @@
+#[cfg(target_os = "linux")]
+use crate::fd_readable_set::IoUringReadableSet;
 use crate::fd_readable_set::{FdReadableSet, Timeout};
 use crate::flog::flog;
 use crate::portable_atomic::AtomicU64;
@@ -179,6 +181,9 @@ impl From<u64> for FdMonitorItemId {
     }
 }
 
+/// The token identifying the change signaller in the readable set. Item IDs start at 1.
+const CHANGE_SIGNALLER_TOKEN: u64 = 0;
+
 /// The callback type used by [`FdMonitorItem`]. It is passed a mutable reference to the
 /// `FdMonitorItem`'s [`FdMonitorItem::fd`]. If the fd is closed, the callback will not
 /// be invoked again.
@@ -344,13 +349,77 @@ impl FdMonitor {
     }
 }
 
+/// The mechanism used by the background thread to wait for fds to become readable. On Linux we
+/// prefer io_uring, which keeps polls armed across wakeups; if it is unavailable (old kernel, or
+/// disabled by a seccomp policy), we fall back to poll() or select().
+enum ReadableSet {
+    Poll(FdReadableSet),
+    #[cfg(target_os = "linux")]
+    IoUring(Box<IoUringReadableSet>),
+}
+
+impl ReadableSet {
+    fn new() -> Self {
+        #[cfg(target_os = "linux")]
+        match IoUringReadableSet::new() {
+            Ok(set) => {
+                flog!(fd_monitor, "Using io_uring");
+                return Self::IoUring(Box::new(set));
+            }
+            Err(err) => {
+                flog!(
+                    fd_monitor,
+                    "io_uring unavailable, falling back to poll:",
+                    err
+                );
+            }
+        }
+        Self::Poll(FdReadableSet::new())
+    }
+
+    fn clear(&mut self) {
+        match self {
+            Self::Poll(set) => set.clear(),
+            #[cfg(target_os = "linux")]
+            Self::IoUring(set) => set.clear(),
+        }
+    }
+
+    /// Add an fd, along with a token which uniquely identifies it for the lifetime of the fd.
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    fn add(&mut self, fd: RawFd, token: u64) {
+        match self {
+            Self::Poll(set) => set.add(fd),
+            #[cfg(target_os = "linux")]
+            Self::IoUring(set) => set.add(fd, token),
+        }
+    }
+
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    fn test(&self, fd: RawFd, token: u64) -> bool {
+        match self {
+            Self::Poll(set) => set.test(fd),
+            #[cfg(target_os = "linux")]
+            Self::IoUring(set) => set.test(token),
+        }
+    }
+
+    fn check_readable(&mut self, timeout: Timeout) -> libc::c_int {
+        match self {
+            Self::Poll(set) => set.check_readable(timeout),
+            #[cfg(target_os = "linux")]
+            Self::IoUring(set) => set.check_readable(timeout),
+        }
+    }
+}
+
 impl BackgroundFdMonitor {
     /// Starts monitoring the fd set and listening for new fds to add to the set. Takes ownership
     /// over its instance so that this method cannot be called again.
     fn run(self) {
         assert_is_background_thread();
 
-        let mut fds = FdReadableSet::new();
+        let mut fds = ReadableSet::new();
         let mut item_ids: Vec<FdMonitorItemId> = Vec::new();
 
         loop {
@@ -390,10 +459,10 @@ impl BackgroundFdMonitor {
             // prepared to handle EBADF. This race is otherwise considered benign.
 
             // Construct the set of fds to monitor.
-            // Our change_signaller is special-cased.
+            // Our change_signaller is special-cased, using the sentinel ID.
             fds.clear();
             let change_signal_fd = self.change_signaller.read_fd();
-            fds.add(change_signal_fd);
+            fds.add(change_signal_fd, CHANGE_SIGNALLER_TOKEN);
 
             // Grab the lock and snapshot the item_ids. Skip items with invalid fds.
             let mut data = self.data.locked.lock().expect("Mutex poisoned!");
@@ -401,7 +470,7 @@ impl BackgroundFdMonitor {
             item_ids.reserve(data.items.len());
             for (item_id, item) in &data.items {
                 if let Some(fd) = &item.fd {
-                    fds.add(fd.as_raw_fd());
+                    fds.add(fd.as_raw_fd(), item_id.0);
                     item_ids.push(*item_id);
                 }
             }
@@ -454,14 +523,18 @@ impl BackgroundFdMonitor {
                     // Note there is no risk of an ABA problem because ItemIDs are never recycled.
                     continue;
                 };
-                if item.fd.as_ref().is_some_and(|fd| fds.test(fd.as_raw_fd())) {
+                if item
+                    .fd
+                    .as_ref()
+                    .is_some_and(|fd| fds.test(fd.as_raw_fd(), item_id.0))
+                {
                     item.service();
                 }
             }
 
             // Handle any changes if the change signaller was set. Alternatively, this may be the
             // wait lap, in which case we might want to commit to exiting.
-            let change_signalled = fds.test(change_signal_fd);
+            let change_signalled = fds.test(change_signal_fd, CHANGE_SIGNALLER_TOKEN);
             if change_signalled || is_wait_lap {
                 // Clear the change signaller before processing incoming changes
                 self.change_signaller.try_consume();
@@ -223,3 +223,257 @@ impl FdReadableSet {
         Self::is_fd_readable(fd, Timeout::ZERO)
     }
 }
+
+/// An alternative to [`FdReadableSet`] backed by io_uring, used by the fd monitor on Linux.
+///
+/// Unlike poll(), polls stay armed in the kernel across calls to
+/// [`check_readable()`](Self::check_readable), so an fd only costs a submission when it is first
+/// added or after it has fired. Because of this, each fd is tagged with a caller-provided token which
+/// must never be reused for a different fd; a closed and recycled fd with a fresh token will get a
+/// fresh poll.
+#[cfg(target_os = "linux")]
+pub struct IoUringReadableSet {
+    ring: io_uring::IoUring,
+    /// The (token, fd) pairs which should be polled in the next call to check_readable().
+    wanted: Vec<(u64, RawFd)>,
+    /// The tokens which currently have a poll pending in the kernel.
+    armed: std::collections::HashSet<u64>,
+    /// The tokens which were found readable by the last call to check_readable().
+    ready: std::collections::HashSet<u64>,
+}
+
+#[cfg(target_os = "linux")]
+impl IoUringReadableSet {
+    /// The user_data of entries whose completions we don't care about.
+    const IGNORED_TOKEN: u64 = u64::MAX;
+
+    /// Try to set up an io_uring instance. This fails if io_uring is unavailable, which may be
+    /// because the kernel is too old, or because it is disabled (e.g. via seccomp in containers).
+    pub fn new() -> std::io::Result<Self> {
+        let ring = io_uring::IoUring::new(64)?;
+        // We rely on IORING_ENTER_EXT_ARG (Linux 5.11) for timeouts.
+        if !ring.params().is_feature_ext_arg() {
+            return Err(std::io::Error::from_raw_os_error(libc::ENOSYS));
+        }
+        Ok(Self {
+            ring,
+            wanted: Vec::new(),
+            armed: Default::default(),
+            ready: Default::default(),
+        })
+    }
+
+    /// Reset back to an empty set. Polls stay armed until the next call to check_readable().
+    pub fn clear(&mut self) {
+        self.wanted.clear();
+    }
+
+    /// Add an fd to the set, identified by `token`. The fd is ignored if negative.
+    pub fn add(&mut self, fd: RawFd, token: u64) {
+        assert_ne!(token, Self::IGNORED_TOKEN, "Reserved token");
+        if fd >= 0 {
+            self.wanted.push((token, fd));
+        }
+    }
+
+    /// Returns `true` if the fd with the given token has input available to read or has been
+    /// HUP'd.
+    pub fn test(&self, token: u64) -> bool {
+        self.ready.contains(&token)
+    }
+
+    fn push(&mut self, entry: &io_uring::squeue::Entry) -> std::io::Result<()> {
+        // If the submission queue is full, flush it to the kernel and try again.
+        while unsafe { self.ring.submission().push(entry) }.is_err() {
+            self.ring.submit()?;
+        }
+        Ok(())
+    }
+
+    /// Arm polls for new fds, and cancel the ones which are no longer wanted.
+    fn arm(&mut self) -> std::io::Result<()> {
+        use io_uring::opcode;
+
+        let wanted: std::collections::HashSet<u64> =
+            self.wanted.iter().map(|&(token, _)| token).collect();
+        for (token, fd) in std::mem::take(&mut self.wanted) {
+            if self.armed.insert(token) {
+                let flags = (libc::POLLIN | libc::POLLHUP) as u32;
+                let entry = opcode::PollAdd::new(io_uring::types::Fd(fd), flags)
+                    .build()
+                    .user_data(token);
+                self.push(&entry)?;
+            }
+            self.wanted.push((token, fd));
+        }
+        let stale: Vec<u64> = self.armed.difference(&wanted).copied().collect();
+        for token in stale {
+            self.armed.remove(&token);
+            let entry = opcode::PollRemove::new(token)
+                .build()
+                .user_data(Self::IGNORED_TOKEN);
+            self.push(&entry)?;
+        }
+        Ok(())
+    }
+
+    /// Submit pending entries and wait for at least one completion, for at most `timeout` if
+    /// given. Returns false if the timeout expired.
+    fn wait(&mut self, timeout: Option<Duration>) -> std::io::Result<bool> {
+        let Some(timeout) = timeout else {
+            self.ring.submit_and_wait(1)?;
+            return Ok(true);
+        };
+        let ts = io_uring::types::Timespec::new()
+            .sec(timeout.as_secs())
+            .nsec(timeout.subsec_nanos());
+        let args = io_uring::types::SubmitArgs::new().timespec(&ts);
+        match self.ring.submitter().submit_with_args(1, &args) {
+            Err(err) if err.raw_os_error() == Some(libc::ETIME) => Ok(false),
+            res => res.map(|_| true),
+        }
+    }
+
+    /// Record the polls which have fired.
+    fn reap(&mut self) {
+        for cqe in self.ring.completion() {
+            let token = cqe.user_data();
+            // Polls are one-shot, so this one needs to be re-armed. Completions for polls we
+            // removed are not interesting.
+            if token == Self::IGNORED_TOKEN || !self.armed.remove(&token) {
+                continue;
+            }
+            let revents = cqe.result();
+            // A negative result is an error, most likely ECANCELED or EBADF. As with poll(), a
+            // closed fd is not reported as readable.
+            if revents > 0 && (revents & c_int::from(libc::POLLIN | libc::POLLHUP)) != 0 {
+                self.ready.insert(token);
+            }
+        }
+    }
+
+    /// Wait until at least one fd is readable or the timeout expires. Returns the number of
+    /// readable fds, or -1 on error, with errno set. This mirrors
+    /// [`FdReadableSet::check_readable()`].
+    pub fn check_readable(&mut self, timeout: Timeout) -> c_int {
+        self.ready.clear();
+        let deadline = match timeout {
+            Timeout::Forever => None,
+            Timeout::Duration(duration) => Some(std::time::Instant::now() + duration),
+        };
+        // Completions don't necessarily mean that an fd is readable (e.g. those of removed polls),
+        // so keep waiting until one is, or until the timeout expires.
+        let res = self.arm().and_then(|()| {
+            loop {
+                let remaining = deadline
+                    .map(|deadline| deadline.saturating_duration_since(std::time::Instant::now()));
+                let res = self.wait(remaining);
+                // Reap completions even on error, so that we know which polls are still armed.
+                self.reap();
+                match res {
+                    Ok(true) if self.ready.is_empty() => continue,
+                    res => break res,
+                }
+            }
+        });
+
+        match res {
+            Ok(_) => self.ready.len().try_into().unwrap_or(c_int::MAX),
+            Err(err) => {
+                errno::set_errno(errno::Errno(err.raw_os_error().unwrap_or(libc::EIO)));
+                -1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FdReadableSet, Timeout};
+    use crate::fds::make_autoclose_pipes;
+    use std::os::fd::AsRawFd as _;
+    use std::time::Duration;
+
+    const SHORT: Timeout = Timeout::Duration(Duration::from_millis(10));
+    const LONG: Timeout = Timeout::Duration(Duration::from_secs(5));
+
+    #[test]
+    fn test_fd_readable_set() {
+        let pipes = make_autoclose_pipes().unwrap();
+        let fd = pipes.read.as_raw_fd();
+        let mut set = FdReadableSet::new();
+
+        // Nothing to read yet, so we time out.
+        set.add(fd);
+        assert_eq!(set.check_readable(SHORT), 0);
+        assert!(!set.test(fd));
+
+        nix::unistd::write(&pipes.write, b"x").unwrap();
+        set.clear();
+        set.add(fd);
+        assert_eq!(set.check_readable(LONG), 1);
+        assert!(set.test(fd));
+
+        // A closed write end is reported as readable, even after the data is read.
+        let mut buf = [0u8; 1];
+        nix::unistd::read(&pipes.read, &mut buf).unwrap();
+        drop(pipes.write);
+        set.clear();
+        set.add(fd);
+        assert_eq!(set.check_readable(LONG), 1);
+        assert!(set.test(fd));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_io_uring_readable_set() {
+        use super::IoUringReadableSet;
+
+        // io_uring may be missing or disabled, for example in containers.
+        let Ok(mut set) = IoUringReadableSet::new() else {
+            return;
+        };
+        let pipes = make_autoclose_pipes().unwrap();
+        let fd = pipes.read.as_raw_fd();
+        let other = make_autoclose_pipes().unwrap();
+
+        // Nothing to read yet, so we time out. The poll stays armed.
+        set.add(fd, 1);
+        set.add(other.read.as_raw_fd(), 2);
+        assert_eq!(set.check_readable(SHORT), 0);
+        assert!(!set.test(1));
+
+        nix::unistd::write(&pipes.write, b"x").unwrap();
+        set.clear();
+        set.add(fd, 1);
+        set.add(other.read.as_raw_fd(), 2);
+        assert_eq!(set.check_readable(LONG), 1);
+        assert!(set.test(1));
+        assert!(!set.test(2));
+
+        // The poll fired, so it is armed again, and sees the data that is still there.
+        set.clear();
+        set.add(fd, 1);
+        assert_eq!(set.check_readable(LONG), 1);
+        assert!(set.test(1));
+
+        // An fd that is no longer in the set has its poll removed, so it is not reported once it
+        // becomes readable. The completion of the removal does not end the wait early.
+        set.clear();
+        let start = std::time::Instant::now();
+        assert_eq!(set.check_readable(SHORT), 0);
+        assert!(start.elapsed() >= Duration::from_millis(10));
+        nix::unistd::write(&other.write, b"x").unwrap();
+        assert_eq!(set.check_readable(SHORT), 0);
+        assert!(!set.test(2));
+
+        // A closed write end is reported as readable, even after the data is read.
+        let mut buf = [0u8; 1];
+        nix::unistd::read(&pipes.read, &mut buf).unwrap();
+        drop(pipes.write);
+        set.clear();
+        set.add(fd, 1);
+        assert_eq!(set.check_readable(LONG), 1);
+        assert!(set.test(1));
+    }
+}
@@ -14,6 +14,7 @@ use std::{
         fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd},
         unix::prelude::*,
     },
+    sync::OnceLock,
 };
 
 localizable_consts!(
@@ -241,6 +242,43 @@ pub fn make_fd_blocking(fd: RawFd) -> Result<(), io::Error> {
     Ok(())
 }
 
+/// The fd above every fd that fish inherited without CLOEXEC, or None if it is not known.
+static INHERITED_FDS_END: OnceLock<Option<RawFd>> = OnceLock::new();
+
+/// Remember which fds fish inherited without CLOEXEC. Those are meant for our children (like the
+/// pipes of a make jobserver), so they must survive closing leftover fds in bulk.
+/// This should be called at startup.
+pub fn record_inherited_fds() {
+    inherited_fds_end();
+}
+
+/// Return the lowest fd which is above stdin, stdout, stderr and every fd that fish inherited
+/// without CLOEXEC. Anything from here on was opened by fish itself. Returns None where the open
+/// fds can't be listed.
+pub fn inherited_fds_end() -> Option<RawFd> {
+    *INHERITED_FDS_END.get_or_init(|| {
+        let mut end = 3;
+        // Opening the directory takes an fd too, but that one is CLOEXEC.
+        for entry in std::fs::read_dir("/proc/self/fd").ok()? {
+            let Ok(entry) = entry else {
+                return None;
+            };
+            let Some(fd) = entry
+                .file_name()
+                .to_str()
+                .and_then(|s| s.parse::<RawFd>().ok())
+            else {
+                continue;
+            };
+            let flags = unsafe { libc::fcntl(fd, F_GETFD, 0) };
+            if flags >= 0 && flags & FD_CLOEXEC == 0 {
+                end = end.max(fd + 1);
+            }
+        }
+        Some(end)
+    })
+}
+
 /// A helper type for a File that does not close on drop.
 /// Note the underlying file is never dropped; this is equivalent to mem::forget.
 pub struct BorrowedFdFile(ManuallyDrop<File>);
@@ -144,6 +144,8 @@ pub mod categories {
 
         (refcell, "refcell", "Refcell dynamic borrowing");
         (autoload, "autoload", "autoloading");
+
+        (lsp, "lsp", "The language server");
     );
 }
 
@@ -188,6 +188,43 @@ pub fn child_setup_process(
     0
 }
 
+/// Close all fds starting at `first_fd`, except for the flog fd, which is still needed to report
+/// exec errors. Everything fish opens is CLOEXEC anyway, so this is only an optimization: a single
+/// close_range() call is cheaper than having exec walk a large fd table. Without close_range() this
+/// does nothing.
+pub fn close_fds_from(first_fd: i32) {
+    #[cfg(have_close_range)]
+    {
+        unsafe extern "C" {
+            fn close_range(
+                first: libc::c_uint,
+                last: libc::c_uint,
+                flags: libc::c_int,
+            ) -> libc::c_int;
+        }
+        let Ok(first_fd) = libc::c_uint::try_from(first_fd) else {
+            return;
+        };
+        let flog_fd = crate::flog::get_flog_file_fd();
+        // Errors are ignored: the fds are CLOEXEC and will be closed by exec regardless.
+        unsafe {
+            match libc::c_uint::try_from(flog_fd) {
+                Ok(flog_fd) if flog_fd >= first_fd => {
+                    if flog_fd > first_fd {
+                        close_range(first_fd, flog_fd - 1, 0);
+                    }
+                    close_range(flog_fd + 1, libc::c_uint::MAX, 0);
+                }
+                _ => {
+                    close_range(first_fd, libc::c_uint::MAX, 0);
+                }
+            }
+        }
+    }
+    #[cfg(not(have_close_range))]
+    let _ = first_fd;
+}
+
 /// This function is a wrapper around fork. If the fork calls fails with EAGAIN, it is retried
 /// FORK_LAPS times, with a very slight delay between each lap. If fork fails even then, the process
 /// will exit with an error message.
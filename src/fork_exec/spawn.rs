@@ -5,7 +5,7 @@ use super::blocked_signals_for_job;
 use crate::exec::{PgroupPolicy, is_thompson_shell_script};
 use crate::proc::Job;
 use crate::redirection::Dup2List;
-use crate::signal::SIGNALS_TO_DEFAULT;
+use crate::signal::signals_to_default;
 use errno::Errno;
 use libc::{c_char, posix_spawn_file_actions_t, posix_spawnattr_t};
 use std::ffi::{CStr, CString};
@@ -82,6 +82,16 @@ impl FileActions {
             ))
         }
     }
+
+    #[cfg(have_posix_spawn_file_actions_addclosefrom_np)]
+    fn add_closefrom(&mut self, fd: libc::c_int) -> Result<(), Errno> {
+        unsafe {
+            check_fail(libc::posix_spawn_file_actions_addclosefrom_np(
+                &mut self.0,
+                fd,
+            ))
+        }
+    }
 }
 
 impl Drop for FileActions {
@@ -128,8 +138,8 @@ impl PosixSpawner {
             attr.set_pgroup(desired_pgid)?;
         }
 
-        // Everybody gets default handlers.
-        attr.set_sigdefault(&SIGNALS_TO_DEFAULT)?;
+        // Everybody gets default handlers, except for signals ignored with `trap`.
+        attr.set_sigdefault(&signals_to_default())?;
 
         // Reset the sigmask.
         let mut sigmask = MaybeUninit::uninit();
@@ -146,6 +156,16 @@ impl PosixSpawner {
                 actions.add_dup2(act.src, act.target)?;
             }
         }
+
+        // Close everything above the fds the child is meant to see, including those we inherited
+        // without CLOEXEC. The rest are all CLOEXEC, but letting the implementation close them in
+        // one go (typically via close_range) avoids having exec iterate over a potentially huge fd
+        // table when RLIMIT_NOFILE is raised. Where this is not available, or we don't know which
+        // fds were inherited, we simply rely on CLOEXEC.
+        #[cfg(have_posix_spawn_file_actions_addclosefrom_np)]
+        if let Some(end) = crate::fds::inherited_fds_end() {
+            actions.add_closefrom(end.max(dup2s.first_unused_fd()))?;
+        }
         Ok(PosixSpawner { attr, actions })
     }
 
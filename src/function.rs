@@ -33,6 +33,9 @@ pub struct FunctionProperties {
     /// List of all named arguments for this function.
     pub named_arguments: Vec<WString>,
 
+    /// Parameters declared with `--param`, which are bound to the arguments in order.
+    pub params: Vec<FunctionParam>,
+
     /// Description of the function.
     pub description: LocalizableString,
 
@@ -60,6 +63,46 @@ pub struct FunctionProperties {
     pub copy_definition_lineno: Option<NonZeroU32>,
 }
 
+/// A function parameter, like `--param name`, `--param greeting=hello` or `--param files...`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionParam {
+    pub name: WString,
+    /// The value if there is no argument for the parameter, or None if it is required.
+    pub default: Option<WString>,
+    /// Whether the parameter takes all remaining arguments. Only the last one may.
+    pub variadic: bool,
+}
+
+impl FunctionParam {
+    /// Parse the argument of `--param`. The name is not validated.
+    pub fn parse(s: &wstr) -> Self {
+        if let Some(eq) = s.find_char('=') {
+            Self {
+                name: s[..eq].to_owned(),
+                default: Some(s[eq + 1..].to_owned()),
+                variadic: false,
+            }
+        } else if s.ends_with("...") {
+            Self {
+                name: s[..s.len() - 3].to_owned(),
+                default: None,
+                variadic: true,
+            }
+        } else {
+            Self {
+                name: s.to_owned(),
+                default: None,
+                variadic: false,
+            }
+        }
+    }
+
+    /// Return whether calls must pass an argument for this parameter.
+    pub fn is_required(&self) -> bool {
+        self.default.is_none() && !self.variadic
+    }
+}
+
 /// FunctionProperties are safe to share between threads.
 const _: () = assert_sync::<FunctionProperties>();
 
@@ -475,6 +518,18 @@ impl FunctionProperties {
             }
         }
 
+        for param in &self.params {
+            out.push_str(" --param ");
+            let mut spec = param.name.clone();
+            if let Some(default) = &param.default {
+                spec.push('=');
+                spec.push_utfstr(default);
+            } else if param.variadic {
+                spec.push_str("...");
+            }
+            out.push_utfstr(&escape(&spec));
+        }
+
         let named = &self.named_arguments;
         if !named.is_empty() {
             sprintf!(=> &mut out, " --argument-names");
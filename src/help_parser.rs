@@ -0,0 +1,344 @@
+//! Generating completions for a command from the option listing in its `--help` output.
+
+use crate::{
+    complete::CompleteOptionType,
+    env::{EnvMode, Environment},
+    prelude::*,
+    threads,
+};
+use fish_widestring::{bytes2wcstring, wcs2osstring};
+use std::{
+    collections::HashSet,
+    os::unix::process::CommandExt as _,
+    process::{Command, Stdio},
+    sync::mpsc,
+    time::Duration,
+};
+
+/// How long a command may take to print its help.
+const HELP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Lines listing options are indented by at most this much; more indented lines that start with a
+/// dash are part of a description.
+const MAX_OPTION_INDENT: usize = 16;
+
+/// The maximum width of a description, longer ones are cut after a sentence or truncated.
+const MAX_DESCRIPTION_WIDTH: usize = 78;
+
+/// The name of an option and how it is written, like `("a", Short)` for `-a` and
+/// `("all", DoubleLong)` for `--all`.
+pub type OptionName = (WString, CompleteOptionType);
+
+/// An option found in an option listing.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedOption {
+    /// The names of the option.
+    pub names: Vec<OptionName>,
+    /// Whether the option takes an argument, which is not optional.
+    pub requires_param: bool,
+    /// The description of the option, cut to a few sentences.
+    pub description: WString,
+}
+
+/// Run `path --help` and return what it printed, or `None` if it could not be run or did not
+/// finish in time. It runs in its own process group without standard input, so it can neither read
+/// from nor take over the terminal, and is killed after a timeout.
+pub fn run_help(path: &wstr, vars: &dyn Environment) -> Option<WString> {
+    let mut command = Command::new(wcs2osstring(path));
+    command
+        .arg("--help")
+        .env_clear()
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0);
+    for name in vars.get_names(EnvMode::EXPORT) {
+        if let Some(var) = vars.get(&name) {
+            command.env(wcs2osstring(&name), wcs2osstring(&var.as_string()));
+        }
+    }
+    // Don't let commands that show their help in a pager wait for input.
+    command.env("PAGER", "cat").env("MANPAGER", "cat");
+
+    let child = command.spawn().ok()?;
+    let pgid = libc::pid_t::try_from(child.id()).unwrap();
+    let (sender, receiver) = mpsc::channel();
+    if !threads::spawn(move || {
+        let _ = sender.send(child.wait_with_output());
+    }) {
+        unsafe { libc::kill(-pgid, libc::SIGKILL) };
+        return None;
+    }
+    match receiver.recv_timeout(HELP_TIMEOUT) {
+        Ok(Ok(output)) => {
+            let mut text = bytes2wcstring(&output.stdout);
+            text.push('\n');
+            text.push_utfstr(&bytes2wcstring(&output.stderr));
+            Some(text)
+        }
+        Ok(Err(_)) => None,
+        Err(_) => {
+            // The thread reaps the process once it is gone.
+            unsafe { libc::kill(-pgid, libc::SIGKILL) };
+            None
+        }
+    }
+}
+
+/// Parse the options listed in the output of `--help`. This recognizes the usual format, where
+/// each option is on a line starting with its names and the names of its argument, followed by
+/// its description, which may continue on more indented lines:
+///
+/// ```text
+///   -a, --all                  do not ignore entries starting with .
+///       --block-size=SIZE      with -l, scale sizes by SIZE when printing them;
+///                                e.g., '--block-size=M'; see SIZE format below
+///   -o FILE
+///          write the output to FILE
+/// ```
+pub fn parse_help_output(text: &wstr) -> Vec<ParsedOption> {
+    let lines: Vec<&[char]> = text.as_char_slice().split(|&c| c == '\n').collect();
+    let mut seen = HashSet::new();
+    let mut result = vec![];
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let indent = indentation(line);
+        i += 1;
+        let rest = trim(line);
+        if rest.first() != Some(&'-') || indent > MAX_OPTION_INDENT {
+            continue;
+        }
+
+        let (names, requires_param, description) = parse_option_line(rest);
+        let mut description = description.to_vec();
+        // The description continues on more indented lines.
+        while i < lines.len() {
+            let next = trim(lines[i]);
+            if next.is_empty() || next[0] == '-' || indentation(lines[i]) <= indent {
+                break;
+            }
+            description.push(' ');
+            description.extend_from_slice(next);
+            i += 1;
+        }
+
+        let names: Vec<_> = names
+            .into_iter()
+            .filter(|name| seen.insert(name.clone()))
+            .collect();
+        if names.is_empty() {
+            continue;
+        }
+        result.push(ParsedOption {
+            names,
+            requires_param,
+            description: truncate_description(&description),
+        });
+    }
+    result
+}
+
+/// The width of the leading whitespace of a line, with tab stops every eight columns.
+fn indentation(line: &[char]) -> usize {
+    let mut width = 0;
+    for &c in line {
+        match c {
+            ' ' => width += 1,
+            '\t' => width = (width / 8 + 1) * 8,
+            _ => break,
+        }
+    }
+    width
+}
+
+fn trim(s: &[char]) -> &[char] {
+    let start = s.iter().position(|c| !c.is_whitespace()).unwrap_or(s.len());
+    let end = s
+        .iter()
+        .rposition(|c| !c.is_whitespace())
+        .map_or(start, |i| i + 1);
+    &s[start..end]
+}
+
+/// Split a line starting with options into the option names, whether they take an argument, and
+/// the rest of the line, which is the start of the description.
+fn parse_option_line(line: &[char]) -> (Vec<OptionName>, bool, &[char]) {
+    let mut names = vec![];
+    let mut requires_param = false;
+    let mut pos = 0;
+    loop {
+        while pos < line.len() && line[pos].is_whitespace() {
+            pos += 1;
+        }
+        let end = line[pos..]
+            .iter()
+            .position(|c| c.is_whitespace())
+            .map_or(line.len(), |len| pos + len);
+        if pos == end {
+            break;
+        }
+        let word = &line[pos..end];
+        let word = word.strip_suffix(&[',']).unwrap_or(word);
+        if word.first() == Some(&'-') {
+            let Some((name, arg)) = parse_option_word(word) else {
+                break;
+            };
+            names.push(name);
+            requires_param |= arg.is_some_and(|arg| arg.first() != Some(&'['));
+        } else if !names.is_empty() && is_argument_name(word) {
+            requires_param |= word[0] != '[';
+        } else {
+            break;
+        }
+        pos = end;
+    }
+    (names, requires_param, &line[pos..])
+}
+
+/// Parse a word like `--block-size=SIZE` into the option and its argument, if any.
+fn parse_option_word(word: &[char]) -> Option<(OptionName, Option<&[char]>)> {
+    let dashes = if word.starts_with(&['-', '-']) { 2 } else { 1 };
+    let name_end = word[dashes..]
+        .iter()
+        .position(|&c| !(c.is_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .map_or(word.len(), |len| dashes + len);
+    let name = &word[dashes..name_end];
+    if !name.first().is_some_and(|c| c.is_alphanumeric()) {
+        return None;
+    }
+    let arg = &word[name_end..];
+    let arg = arg.strip_prefix(&['=']).unwrap_or(arg);
+    let arg = match arg {
+        [] => None,
+        ['[', ..] | ['<', ..] => Some(arg),
+        _ if is_argument_name(arg) => Some(arg),
+        _ => return None,
+    };
+    let typ = match (dashes, name.len()) {
+        (2, _) => CompleteOptionType::DoubleLong,
+        (_, 1) => CompleteOptionType::Short,
+        _ => CompleteOptionType::SingleLong,
+    };
+    Some(((WString::from_chars(name), typ), arg))
+}
+
+/// Whether this looks like the name of an argument, like `FILE`, `<file>` or `[=WHEN]`.
+fn is_argument_name(word: &[char]) -> bool {
+    match word {
+        ['<', .., '>'] | ['[', .., ']'] | ['{', .., '}'] => true,
+        _ => {
+            word.iter().any(|c| c.is_alphabetic())
+                && word
+                    .iter()
+                    .all(|&c| c.is_uppercase() || c.is_ascii_digit() || matches!(c, '_' | '-'))
+        }
+    }
+}
+
+/// Compress whitespace and keep as many whole sentences as fit into the maximum width, or
+/// truncate the first sentence if it doesn't.
+fn truncate_description(description: &[char]) -> WString {
+    let mut compressed = WString::new();
+    for c in trim(description) {
+        if c.is_whitespace() {
+            if compressed.as_char_slice().last() != Some(&' ') {
+                compressed.push(' ');
+            }
+        } else {
+            compressed.push(*c);
+        }
+    }
+
+    // A sentence ends with a dot followed by a space, so abbreviations like "e.g.," don't count.
+    let chars = compressed.as_char_slice();
+    let mut result = WString::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let end = chars[start..]
+            .windows(2)
+            .position(|w| w == ['.', ' '])
+            .map_or(chars.len(), |len| start + len + 1);
+        if !result.is_empty() && end > MAX_DESCRIPTION_WIDTH {
+            break;
+        }
+        result.push_utfstr(wstr::from_char_slice(&chars[start..end]));
+        start = end;
+    }
+    while result
+        .as_char_slice()
+        .last()
+        .is_some_and(|&c| c == '.' || c == ' ')
+    {
+        result.pop();
+    }
+    if result.len() > MAX_DESCRIPTION_WIDTH {
+        result.truncate(MAX_DESCRIPTION_WIDTH - 1);
+        result.push('…');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ParsedOption, parse_help_output};
+    use crate::complete::CompleteOptionType::{DoubleLong, Short, SingleLong};
+    use crate::prelude::*;
+
+    #[test]
+    fn test_parse_help_output() {
+        let help = L!("Usage: ls [OPTION]... [FILE]...
+List information about the FILEs (the current directory by default).
+
+Mandatory arguments to long options are mandatory for short options too.
+  -a, --all                  do not ignore entries starting with .
+      --block-size=SIZE      with -l, scale sizes by SIZE when printing them;
+                               e.g., '--block-size=M'; see SIZE format below
+      --color[=WHEN]         color the output WHEN; more info below
+  -o FILE, --output FILE
+         write the output to FILE. This is a second sentence that is rather long, so it is cut
+         off.
+  -x <n>  Use n. Really.
+  -h --help Show help
+  -name  An old-style option
+      -- also not an option
+  -a     A duplicate
+");
+        let option = |names: &[(&str, _)], requires_param, description: &str| ParsedOption {
+            names: names
+                .iter()
+                .map(|(name, typ)| (WString::from(*name), *typ))
+                .collect(),
+            requires_param,
+            description: WString::from(description),
+        };
+        assert_eq!(
+            parse_help_output(help),
+            vec![
+                option(
+                    &[("a", Short), ("all", DoubleLong)],
+                    false,
+                    "do not ignore entries starting with"
+                ),
+                option(
+                    &[("block-size", DoubleLong)],
+                    true,
+                    "with -l, scale sizes by SIZE when printing them; e.g., '--block-size=M'; see …"
+                ),
+                option(
+                    &[("color", DoubleLong)],
+                    false,
+                    "color the output WHEN; more info below"
+                ),
+                option(
+                    &[("o", Short), ("output", DoubleLong)],
+                    true,
+                    "write the output to FILE"
+                ),
+                option(&[("x", Short)], true, "Use n. Really"),
+                option(&[("h", Short), ("help", DoubleLong)], false, "Show help"),
+                option(&[("name", SingleLong)], false, "An old-style option"),
+            ]
+        );
+    }
+}
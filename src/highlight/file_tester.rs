@@ -50,7 +50,7 @@ pub type FileTestResult = Result<IsFile, IsErr>;
 
 pub struct FileTester<'src, 'opctx> {
     // The working directory, for resolving paths against.
-    working_directory: WString,
+    pub(super) working_directory: WString,
     // The operation context.
     pub(super) ctx: &'opctx mut OperationContext<'src>,
 }
@@ -146,6 +146,8 @@ impl<'src, 'opctx> FileTester<'src, 'opctx> {
         // redirections). Note that the target is now unescaped.
         let target_path = path_apply_working_directory(&target, &self.working_directory);
         match mode {
+            // These take no file.
+            RedirectionMode::HereDoc | RedirectionMode::HereString => Ok(IsFile(false)),
             RedirectionMode::Fd => {
                 if target == "-" {
                     return Ok(IsFile(false));
@@ -21,10 +21,12 @@ use crate::{
         MaybeParentheses, get_process_first_token_offset, locate_cmdsubst_range, slice_length,
     },
     path::{path_as_implicit_cd, path_get_cdpath, path_get_path, paths_are_same_file},
+    redirection::RedirectionMode,
     terminal::Outputter,
     text_face::{ResettableStyle, SpecifiedTextFace, TextFace, UnderlineStyle, parse_text_face},
     threads::assert_is_background_thread,
-    tokenizer::{PipeOrRedir, variable_assignment_equals_pos},
+    tokenizer::{PipeOrRedir, heredoc_delimiter_line_end, variable_assignment_equals_pos},
+    wildcard::glob_qualifier_len,
 };
 use fish_color::Color;
 use fish_feature_flags::{FeatureFlag, feature_test};
@@ -36,7 +38,9 @@ use fish_widestring::{
 use std::collections::{HashMap, hash_map::Entry};
 use strum_macros::Display;
 
-use super::file_tester::IsFile;
+use super::file_tester::{FileTestResult, IsFile};
+use super::rules::{apply_highlight_rules, rule_color_var_name};
+use super::validity_cache::{ValidityCheck, ValidityChecker};
 
 impl HighlightSpec {
     pub fn new() -> Self {
@@ -107,6 +111,22 @@ pub fn highlight_shell<'src, 'ctx>(
     *color = highlighter.highlight();
 }
 
+/// Like [`highlight_shell`] with I/O, but without waiting long for checks that do I/O. Their results
+/// are cached by the given checker, and are only used if they are known in time. This must be
+/// called on a background thread.
+pub fn highlight_shell_async<'src, 'ctx>(
+    buff: &'src wstr,
+    color: &mut Vec<HighlightSpec>,
+    ctx: &'ctx mut OperationContext<'src>,
+    validity_checker: ValidityChecker,
+    cursor: Option<usize>,
+) {
+    let working_directory = ctx.vars().get_pwd_slash();
+    let mut highlighter = Highlighter::new(buff, cursor, ctx, working_directory, true);
+    highlighter.validity_checker = Some(validity_checker);
+    *color = highlighter.highlight();
+}
+
 pub fn highlight_and_colorize<'src, 'ctx>(
     text: &'src wstr,
     ctx: &'ctx mut OperationContext<'src>,
@@ -187,20 +207,26 @@ impl HighlightColorResolver {
         }
 
         // Handle modifiers.
-        if highlight.valid_path {
-            if let Some(valid_path_var) = vars.get(L!("fish_color_valid_path")) {
-                let valid_path_face = parse_text_face(valid_path_var.as_list());
-                if let Some(fg) = valid_path_face.fg {
+        let mut apply_modifier = |var_name: &wstr| {
+            if let Some(var) = vars.get(var_name) {
+                let modifier_face = parse_text_face(var.as_list());
+                if let Some(fg) = modifier_face.fg {
                     face.fg = fg;
                 }
-                if let Some(bg) = valid_path_face.bg {
+                if let Some(bg) = modifier_face.bg {
                     face.bg = bg;
                 }
-                if let Some(underline_color) = valid_path_face.underline_color {
+                if let Some(underline_color) = modifier_face.underline_color {
                     face.underline_color = underline_color;
                 }
-                face.style = face.style.union_prefer_right(valid_path_face.style);
+                face.style = face.style.union_prefer_right(modifier_face.style);
             }
+        };
+        if highlight.valid_path {
+            apply_modifier(L!("fish_color_valid_path"));
+        }
+        if let Some(var_name) = highlight.rule.and_then(rule_color_var_name) {
+            apply_modifier(&var_name);
         }
 
         if highlight.force_underline {
@@ -233,8 +259,7 @@ pub(crate) fn parse_text_face_for_highlight(var: &EnvVar) -> Option<TextFace> {
 fn command_is_valid(
     cmd: &wstr,
     decoration: StatementDecoration,
-    working_directory: &wstr,
-    vars: &dyn Environment,
+    run_check: impl FnOnce(ValidityCheck) -> Option<FileTestResult>,
 ) -> bool {
     // Determine which types we check, based on the decoration.
     let mut builtin_ok = true;
@@ -277,20 +302,32 @@ fn command_is_valid(
         is_valid = with_abbrs(|set| set.has_match(cmd, abbrs::Position::Command, L!("")));
     }
 
-    // Regular commands
-    if !is_valid && command_ok {
-        is_valid = path_get_path(cmd, vars).is_some();
-    }
-
-    // Implicit cd
-    if !is_valid && implicit_cd_ok {
-        is_valid = path_as_implicit_cd(cmd, working_directory, vars).is_some();
+    // Regular commands and implicit cd, which need I/O. If we don't know yet, assume it's valid.
+    if !is_valid && (command_ok || implicit_cd_ok) {
+        is_valid = run_check(ValidityCheck::Command {
+            name: cmd.to_owned(),
+            command_ok,
+            implicit_cd_ok,
+        })
+        .is_none_or(|result| result.is_ok());
     }
 
     // Return what we got.
     is_valid
 }
 
+/// Return whether a command is found in $PATH, or is a directory for implicit cd. This does I/O!
+pub(super) fn command_exists(
+    cmd: &wstr,
+    command_ok: bool,
+    implicit_cd_ok: bool,
+    working_directory: &wstr,
+    vars: &dyn Environment,
+) -> bool {
+    (command_ok && path_get_path(cmd, vars).is_some())
+        || (implicit_cd_ok && path_as_implicit_cd(cmd, working_directory, vars).is_some())
+}
+
 fn has_expand_reserved(s: &wstr) -> bool {
     for wc in s.chars() {
         if (EXPAND_RESERVED_BASE..=EXPAND_RESERVED_END).contains(&wc) {
@@ -505,6 +542,7 @@ fn color_string_internal(buffstr: &wstr, base_color: HighlightSpec, colors: &mut
     let mut mode = Mode::Unquoted;
     let mut unclosed_quote_offset = None;
     let mut bracket_count = 0;
+    let mut has_wildcard = false;
     let mut in_pos = 0;
     while in_pos < buff_len {
         let c = buffstr.as_char_slice()[in_pos];
@@ -609,8 +647,20 @@ fn color_string_internal(buffstr: &wstr, base_color: HighlightSpec, colors: &mut
                         }
                         '?' if !feature_test(FeatureFlag::QuestionMarkNoGlob) => {
                             colors[in_pos] = HighlightSpec::with_fg(HighlightRole::Operat);
+                            has_wildcard = true;
                         }
-                        '*' | '(' | ')' => {
+                        '*' => {
+                            colors[in_pos] = HighlightSpec::with_fg(HighlightRole::Operat);
+                            has_wildcard = true;
+                        }
+                        '(' if has_wildcard => {
+                            // Color glob qualifiers like the "(.)" in "*(.)" as a whole.
+                            let len = glob_qualifier_len(buffstr, in_pos).unwrap_or(1);
+                            colors[in_pos..in_pos + len]
+                                .fill(HighlightSpec::with_fg(HighlightRole::Operat));
+                            in_pos += len - 1;
+                        }
+                        '(' | ')' => {
                             colors[in_pos] = HighlightSpec::with_fg(HighlightRole::Operat);
                         }
                         '{' => {
@@ -708,6 +758,8 @@ struct Highlighter<'src, 'ctx> {
     working_directory: WString,
     // Our component for testing strings for being potential file paths.
     file_tester: FileTester<'src, 'ctx>,
+    // If set, checks that do I/O are run in the background and their results cached.
+    validity_checker: Option<ValidityChecker>,
     // The resulting colors.
     color_array: ColorArray,
     // A stack of variables that the current commandline probably defines.  We mark redirections
@@ -731,6 +783,7 @@ impl<'src, 'ctx> Highlighter<'src, 'ctx> {
             io_ok: can_do_io,
             working_directory,
             file_tester,
+            validity_checker: None,
             color_array: vec![],
             pending_variables: vec![],
             done: false,
@@ -788,6 +841,12 @@ impl<'src, 'ctx> Highlighter<'src, 'ctx> {
             self.color_range(*range, HighlightSpec::with_fg(HighlightRole::Error));
         }
 
+        apply_highlight_rules(
+            self.buff,
+            &mut self.color_array,
+            self.file_tester.ctx.vars(),
+        );
+
         std::mem::take(&mut self.color_array)
     }
 
@@ -802,6 +861,15 @@ impl<'src, 'ctx> Highlighter<'src, 'ctx> {
         self.io_ok && !self.ctx().check_cancel()
     }
 
+    /// Run a check that does I/O, or get its result from the validity checker, if any.
+    /// Returns None if the result is not known yet.
+    fn run_check(&mut self, check: ValidityCheck) -> Option<FileTestResult> {
+        match &self.validity_checker {
+            Some(checker) => checker.check(check, &self.working_directory),
+            None => Some(check.run(&mut self.file_tester)),
+        }
+    }
+
     // Color a command.
     fn color_command(&mut self, node: &ast::String_) {
         let source_range = node.source_range();
@@ -950,11 +1018,12 @@ impl<'src, 'ctx> Highlighter<'src, 'ctx> {
             if is_cd_option(&token) {
                 Ok(IsFile(false))
             } else {
-                self.file_tester.test_cd_path(&token, is_prefix)
+                self.run_check(ValidityCheck::CdPath { token, is_prefix })
+                    .unwrap_or(Ok(IsFile(false)))
             }
         } else {
-            let is_path = self.file_tester.test_path(&token, is_prefix);
-            Ok(IsFile(is_path))
+            self.run_check(ValidityCheck::Path { token, is_prefix })
+                .unwrap_or(Ok(IsFile(false)))
         };
         match test_result {
             Ok(IsFile(false)) => (),
@@ -987,6 +1056,28 @@ impl<'src, 'ctx> Highlighter<'src, 'ctx> {
             HighlightSpec::with_fg(HighlightRole::Redirection),
         );
 
+        // The target of a here-string is the input, not a file.
+        if oper.mode == RedirectionMode::HereString {
+            self.color_as_argument(&redir.target, true);
+            return;
+        }
+        // A here-document's body is colored like a quoted string, and the line ending it like
+        // its delimiter.
+        if oper.mode == RedirectionMode::HereDoc {
+            self.color_node(
+                &redir.target,
+                HighlightSpec::with_fg(HighlightRole::Redirection),
+            );
+            if let Some(body) = redir.heredoc_body {
+                let body = body.as_usize();
+                let end_line = heredoc_delimiter_line_end(self.buff, body.clone());
+                self.color_array[body.clone()].fill(HighlightSpec::with_fg(HighlightRole::Quote));
+                self.color_array[body.end..end_line]
+                    .fill(HighlightSpec::with_fg(HighlightRole::Redirection));
+            }
+            return;
+        }
+
         // Color the target part.
         // Check if the argument contains a command substitution. If so, highlight it as a param
         // even though it's a command redirection, and don't try to do any other validation.
@@ -1004,10 +1095,12 @@ impl<'src, 'ctx> Highlighter<'src, 'ctx> {
             // Target uses a variable defined by the current commandline. Assume it's valid.
             (HighlightRole::Redirection, false)
         } else {
-            // Validate the redirection target..
-            if let Ok(IsFile(file_exists)) =
-                self.file_tester.test_redirection_target(&target, oper.mode)
-            {
+            // Validate the redirection target. If we don't know yet, assume it's valid.
+            let check = ValidityCheck::RedirectionTarget {
+                target,
+                mode: oper.mode,
+            };
+            if let Ok(IsFile(file_exists)) = self.run_check(check).unwrap_or(Ok(IsFile(false))) {
                 (HighlightRole::Redirection, file_exists)
             } else {
                 (HighlightRole::Error, false)
@@ -1065,12 +1158,9 @@ impl<'src, 'ctx> Highlighter<'src, 'ctx> {
             {
                 expanded_cmd = expanded;
                 if !has_expand_reserved(&expanded_cmd) {
-                    is_valid_cmd = command_is_valid(
-                        &expanded_cmd,
-                        stmt.decoration(),
-                        &self.working_directory,
-                        self.file_tester.ctx.vars(),
-                    );
+                    is_valid_cmd = command_is_valid(&expanded_cmd, stmt.decoration(), |check| {
+                        self.run_check(check)
+                    });
                 }
             }
         }
@@ -1204,7 +1294,7 @@ fn statement_get_expanded_command(
     (err == ExpandResultCode::Ok).then_some(out_cmd)
 }
 
-fn get_highlight_var_name(role: HighlightRole) -> &'static wstr {
+pub(super) fn get_highlight_var_name(role: HighlightRole) -> &'static wstr {
     match role {
         HighlightRole::Normal => L!("fish_color_normal"),
         HighlightRole::Error => L!("fish_color_error"),
@@ -1321,6 +1411,8 @@ pub struct HighlightSpec {
     pub background: HighlightRole,
     pub valid_path: bool,
     pub force_underline: bool,
+    /// The index of the user-defined highlighting rule matching this, if any.
+    pub rule: Option<u8>,
 }
 
 #[cfg(test)]
@@ -1633,6 +1725,21 @@ mod tests {
                 ("$x_but_its_an_impostor", fg(HighlightRole::Error)),
             );
 
+            validate!(
+                ("echo", fg(HighlightRole::Command)),
+                ("<<", fg(HighlightRole::Redirection)),
+                ("EOF", fg(HighlightRole::Redirection), ns),
+                ("\n", fg(HighlightRole::StatementTerminator), ns),
+                ("body $x\n", fg(HighlightRole::Quote), ns),
+                ("  EOF", fg(HighlightRole::Redirection), ns),
+            );
+            validate!(
+                ("string", fg(HighlightRole::Command)),
+                ("upper", fg(HighlightRole::Param)),
+                ("<<<", fg(HighlightRole::Redirection)),
+                ("hello", fg(HighlightRole::Param)),
+            );
+
             validate!(
                 ("x", fg(HighlightRole::Param), ns),
                 ("=", fg(HighlightRole::Operat), ns),
@@ -1814,6 +1921,22 @@ mod tests {
                 ("$VARIABLE_IN_COMMAND2", fg(HighlightRole::Operat), ns)
             );
 
+            // Glob qualifiers are not command substitutions.
+            validate!(
+                ("echo", fg(HighlightRole::Command)),
+                ("fo", fg(HighlightRole::Param)),
+                ("*", fg(HighlightRole::Operat), ns),
+                ("(.m-7)", fg(HighlightRole::Operat), ns),
+            );
+
+            validate!(
+                ("echo", fg(HighlightRole::Command)),
+                ("*", fg(HighlightRole::Operat)),
+                ("(", fg(HighlightRole::Operat), ns),
+                ("echo", fg(HighlightRole::Command), ns),
+                (")", fg(HighlightRole::Operat), ns),
+            );
+
             validate!(("$EMPTY_VARIABLE", fg(HighlightRole::Error)));
             validate!(("\"$EMPTY_VARIABLE\"", fg(HighlightRole::Error)));
 
@@ -1,5 +1,9 @@
 mod file_tester;
 #[allow(clippy::module_inception)]
 mod highlight;
+mod rules;
+mod validity_cache;
 pub use file_tester::is_potential_path;
 pub use highlight::*;
+pub(crate) use rules::highlight_rule_var_changed;
+pub use validity_cache::{ValidityCache, ValidityChecker};
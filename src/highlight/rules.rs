@@ -0,0 +1,172 @@
+//! Highlighting rules defined by the user, which color the parts of the command line matching a
+//! regular expression, like `set -g fish_highlight_rule_issue '#\d+'`.
+
+use super::highlight::{HighlightRole, HighlightSpec, get_highlight_var_name};
+use crate::env::{EnvMode, Environment};
+use crate::flog::flog;
+use crate::prelude::*;
+use crate::re::to_boxed_chars;
+use pcre2::utf32::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Rules are variables with this prefix followed by the name of the rule.
+const RULE_VAR_PREFIX: &wstr = L!("fish_highlight_rule_");
+
+/// Elements of a rule with this prefix restrict it to text highlighted with the given role.
+const ROLE_OPTION_PREFIX: &wstr = L!("--role=");
+
+/// The roles a rule can be restricted to, which are named like their `fish_color_` variable.
+const RESTRICTABLE_ROLES: &[HighlightRole] = &[
+    HighlightRole::Normal,
+    HighlightRole::Error,
+    HighlightRole::Command,
+    HighlightRole::Keyword,
+    HighlightRole::StatementTerminator,
+    HighlightRole::Param,
+    HighlightRole::Option,
+    HighlightRole::Comment,
+    HighlightRole::Operat,
+    HighlightRole::Escape,
+    HighlightRole::Quote,
+    HighlightRole::Redirection,
+];
+
+/// The names of the rules that were applied, so a highlight spec can refer to one by its index.
+/// This only grows, as old highlight specs may still refer to a rule that was removed.
+static RULE_NAMES: Mutex<Vec<WString>> = Mutex::new(Vec::new());
+
+/// The names of the variables that may define rules, or `None` if one was added or removed since
+/// they were last listed. Listing all variables for every highlighting is too slow, as the pager
+/// highlights each of its entries.
+static RULE_VAR_NAMES: Mutex<Option<Vec<WString>>> = Mutex::new(None);
+
+/// Compiled patterns, or `None` if they are invalid, so every pattern is compiled only once.
+static REGEX_CACHE: Mutex<Option<HashMap<WString, Option<Arc<Regex>>>>> = Mutex::new(None);
+
+/// Return the name of the variable with the color of the rule with the given index.
+pub(crate) fn rule_color_var_name(rule: u8) -> Option<WString> {
+    let names = RULE_NAMES.lock().unwrap();
+    let name = names.get(usize::from(rule))?;
+    Some(L!("fish_color_").to_owned() + name.as_utfstr())
+}
+
+/// Called when the variable with the given name changes, to notice new and removed rules.
+pub(crate) fn highlight_rule_var_changed(name: &wstr) {
+    if name.starts_with(RULE_VAR_PREFIX) {
+        *RULE_VAR_NAMES.lock().unwrap() = None;
+    }
+}
+
+fn rule_index(name: &wstr) -> Option<u8> {
+    let mut names = RULE_NAMES.lock().unwrap();
+    let index = match names.iter().position(|n| n == name) {
+        Some(index) => index,
+        None => {
+            names.push(name.to_owned());
+            names.len() - 1
+        }
+    };
+    u8::try_from(index).ok()
+}
+
+fn compile(pattern: &wstr) -> Option<Arc<Regex>> {
+    let mut cache = REGEX_CACHE.lock().unwrap();
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if let Some(regex) = cache.get(pattern) {
+        return regex.clone();
+    }
+    let mut builder = RegexBuilder::new();
+    builder.block_utf_pattern_directive(true);
+    let regex = match builder.build(to_boxed_chars(pattern)) {
+        Ok(regex) => Some(Arc::new(regex)),
+        Err(error) => {
+            flog!(
+                warning,
+                "Invalid highlighting rule",
+                pattern,
+                error.error_message()
+            );
+            None
+        }
+    };
+    cache.insert(pattern.to_owned(), regex.clone());
+    regex
+}
+
+/// Mark the parts of the text matching the rules defined in `vars`, so they are colored with the
+/// rule's `fish_color_` variable on top of their usual color. A rule may be restricted to text with
+/// certain roles, in which case a match must only cover text with those roles.
+pub(crate) fn apply_highlight_rules(
+    text: &wstr,
+    colors: &mut [HighlightSpec],
+    vars: &dyn Environment,
+) {
+    let names = RULE_VAR_NAMES
+        .lock()
+        .unwrap()
+        .get_or_insert_with(|| {
+            let mut names: Vec<WString> = vars
+                .get_names(EnvMode::empty())
+                .into_iter()
+                .filter(|name| {
+                    name.len() > RULE_VAR_PREFIX.len() && name.starts_with(RULE_VAR_PREFIX)
+                })
+                .collect();
+            // Later rules take precedence, so the order must be stable.
+            names.sort();
+            names
+        })
+        .clone();
+    for var_name in names {
+        let Some(var) = vars.get(&var_name) else {
+            continue;
+        };
+        let mut roles = vec![];
+        let mut patterns = vec![];
+        let mut valid = true;
+        for element in var.as_list() {
+            if let Some(role_name) = element.strip_prefix(ROLE_OPTION_PREFIX) {
+                let role = RESTRICTABLE_ROLES.iter().find(|&&role| {
+                    get_highlight_var_name(role).strip_prefix(L!("fish_color_")) == Some(role_name)
+                });
+                // Ignore rules with unknown roles instead of applying them everywhere.
+                match role {
+                    Some(&role) => roles.push(role),
+                    None => valid = false,
+                }
+            } else if !element.is_empty() {
+                patterns.push(element);
+            }
+        }
+        if !valid || patterns.is_empty() {
+            continue;
+        }
+        let Some(rule) = rule_index(&var_name[RULE_VAR_PREFIX.len()..]) else {
+            continue;
+        };
+
+        for pattern in patterns {
+            let Some(regex) = compile(pattern) else {
+                continue;
+            };
+            for m in regex.find_iter(text.as_char_slice()) {
+                let Ok(m) = m else {
+                    break;
+                };
+                let range = m.start()..m.end();
+                if range.is_empty()
+                    || (!roles.is_empty()
+                        && !colors[range.clone()]
+                            .iter()
+                            .all(|color| roles.contains(&color.foreground)))
+                {
+                    continue;
+                }
+                for color in &mut colors[range] {
+                    color.rule = Some(rule);
+                }
+            }
+        }
+    }
+}
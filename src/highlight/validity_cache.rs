@@ -0,0 +1,305 @@
+//! Checks for highlighting which may block on I/O, like whether a command or a path exists.
+//! The reader runs them on background threads and caches their results, so that a slow filesystem
+//! (like a network mount) only delays the highlighting of the affected tokens, instead of all of it.
+//! A highlighting pass waits for checks for a short time and uses what it knows afterwards; checks
+//! that finish later cause the command line to be highlighted again.
+
+use super::file_tester::{FileTestResult, FileTester, IsErr, IsFile};
+use super::highlight::command_exists;
+use crate::env::EnvDyn;
+use crate::fd_monitor::FdEventSignaller;
+use crate::operation_context::{EXPANSION_LIMIT_BACKGROUND, OperationContext};
+use crate::prelude::*;
+use crate::redirection::RedirectionMode;
+use crate::threads::ThreadPool;
+use lru::LruCache;
+use std::collections::HashSet;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long a highlighting pass waits for checks in total.
+const CHECK_BUDGET: Duration = Duration::from_millis(30);
+
+/// How long a result is used without checking again.
+const RESULT_LIFETIME: Duration = Duration::from_secs(2);
+
+/// The number of results to keep.
+const CACHE_SIZE: usize = 1024;
+
+/// The maximum number of threads running checks. Checks stuck on an unresponsive filesystem
+/// occupy a thread each, so this should leave room for checks on other filesystems.
+const MAX_THREADS: usize = 8;
+
+/// A check for highlighting which may block on I/O.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(super) enum ValidityCheck {
+    /// Whether a command is found in $PATH, or is a directory that can be changed to implicitly.
+    Command {
+        name: WString,
+        command_ok: bool,
+        implicit_cd_ok: bool,
+    },
+    /// Whether an argument is a path, or the prefix of one.
+    Path { token: WString, is_prefix: bool },
+    /// Whether an argument to cd is a directory, or the prefix of one.
+    CdPath { token: WString, is_prefix: bool },
+    /// Whether a redirection target is valid, and whether it is an existing file.
+    RedirectionTarget {
+        target: WString,
+        mode: RedirectionMode,
+    },
+}
+
+impl ValidityCheck {
+    /// Run the check. This does I/O!
+    pub(super) fn run(&self, tester: &mut FileTester) -> FileTestResult {
+        match self {
+            ValidityCheck::Command {
+                name,
+                command_ok,
+                implicit_cd_ok,
+            } => {
+                if command_exists(
+                    name,
+                    *command_ok,
+                    *implicit_cd_ok,
+                    &tester.working_directory,
+                    tester.ctx.vars(),
+                ) {
+                    Ok(IsFile(false))
+                } else {
+                    Err(IsErr)
+                }
+            }
+            ValidityCheck::Path { token, is_prefix } => {
+                Ok(IsFile(tester.test_path(token, *is_prefix)))
+            }
+            ValidityCheck::CdPath { token, is_prefix } => tester.test_cd_path(token, *is_prefix),
+            ValidityCheck::RedirectionTarget { target, mode } => {
+                tester.test_redirection_target(target, *mode)
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    check: ValidityCheck,
+    working_directory: WString,
+}
+
+struct CachedResult {
+    result: FileTestResult,
+    /// The epoch in which the check was started.
+    epoch: u64,
+    /// When the check finished.
+    time: Instant,
+}
+
+struct CacheData {
+    results: LruCache<CacheKey, CachedResult>,
+    /// The checks which are running.
+    running: HashSet<CacheKey>,
+    /// The running checks that a highlighting pass stopped waiting for.
+    late: HashSet<CacheKey>,
+    /// Results from earlier epochs are checked again before they are used.
+    epoch: u64,
+    /// Whether a late check finished since this was last reset.
+    finished_late: bool,
+}
+
+/// The results of checks for highlighting, shared by all highlighting passes of a reader.
+pub struct ValidityCache {
+    data: Mutex<CacheData>,
+    /// Notified when a check finishes.
+    finished: Condvar,
+    pool: Arc<ThreadPool>,
+    /// Posted when a late check finishes.
+    event_signaller: Arc<FdEventSignaller>,
+}
+
+impl ValidityCache {
+    pub fn new(event_signaller: &Arc<FdEventSignaller>) -> Arc<Self> {
+        Arc::new(Self {
+            data: Mutex::new(CacheData {
+                results: LruCache::new(NonZeroUsize::new(CACHE_SIZE).unwrap()),
+                running: HashSet::new(),
+                late: HashSet::new(),
+                epoch: 0,
+                finished_late: false,
+            }),
+            finished: Condvar::new(),
+            pool: ThreadPool::new(0, MAX_THREADS),
+            event_signaller: Arc::clone(event_signaller),
+        })
+    }
+
+    /// Check everything again before using the cached results, as running a command may have
+    /// changed the filesystem or variables. The old results are still used while that happens.
+    pub fn invalidate(&self) {
+        self.data.lock().unwrap().epoch += 1;
+    }
+
+    /// Return whether a check that highlighting stopped waiting for has finished since the last
+    /// call. If so, the command line should be highlighted again to show its result.
+    pub fn take_finished_late(&self) -> bool {
+        std::mem::take(&mut self.data.lock().unwrap().finished_late)
+    }
+
+    /// Return the result of a check, starting it if there is no recent result, and waiting for it
+    /// until the deadline. Return an outdated result or `None` if it does not finish in time.
+    fn check(
+        self: &Arc<Self>,
+        key: CacheKey,
+        vars: &Arc<EnvDyn>,
+        deadline: Instant,
+    ) -> Option<FileTestResult> {
+        let mut data = self.data.lock().unwrap();
+        let epoch = data.epoch;
+        let outdated = match data.results.get(&key) {
+            Some(cached) if cached.epoch == epoch && cached.time.elapsed() < RESULT_LIFETIME => {
+                return Some(cached.result);
+            }
+            cached => cached.map(|cached| cached.result),
+        };
+
+        if data.running.insert(key.clone()) {
+            let cache = Arc::clone(self);
+            let vars = Arc::clone(vars);
+            let key = key.clone();
+            self.pool
+                .perform(move || cache.run_check(key, &vars, epoch));
+        }
+
+        while data.running.contains(&key) {
+            let now = Instant::now();
+            if now >= deadline {
+                data.late.insert(key);
+                return outdated;
+            }
+            data = self.finished.wait_timeout(data, deadline - now).unwrap().0;
+        }
+        data.results
+            .peek(&key)
+            .map(|cached| cached.result)
+            .or(outdated)
+    }
+
+    fn run_check(&self, key: CacheKey, vars: &EnvDyn, epoch: u64) {
+        let mut ctx = OperationContext::background(vars, EXPANSION_LIMIT_BACKGROUND);
+        let result = key.check.run(&mut FileTester::new(
+            key.working_directory.clone(),
+            &mut ctx,
+        ));
+
+        let mut data = self.data.lock().unwrap();
+        data.running.remove(&key);
+        let late = data.late.remove(&key);
+        data.finished_late |= late;
+        data.results.put(
+            key,
+            CachedResult {
+                result,
+                epoch,
+                time: Instant::now(),
+            },
+        );
+        drop(data);
+
+        self.finished.notify_all();
+        if late {
+            self.event_signaller.post();
+        }
+    }
+}
+
+/// Runs the checks of one highlighting pass through a [`ValidityCache`].
+pub struct ValidityChecker {
+    cache: Arc<ValidityCache>,
+    vars: Arc<EnvDyn>,
+    deadline: Instant,
+}
+
+impl ValidityChecker {
+    /// Create a checker for a highlighting pass starting now, which checks with the given
+    /// variables.
+    pub fn new(cache: &Arc<ValidityCache>, vars: &Arc<EnvDyn>) -> Self {
+        Self {
+            cache: Arc::clone(cache),
+            vars: Arc::clone(vars),
+            deadline: Instant::now() + CHECK_BUDGET,
+        }
+    }
+
+    /// Return the result of a check, or `None` if it is not known yet.
+    pub(super) fn check(
+        &self,
+        check: ValidityCheck,
+        working_directory: &wstr,
+    ) -> Option<FileTestResult> {
+        let key = CacheKey {
+            check,
+            working_directory: working_directory.to_owned(),
+        };
+        self.cache.check(key, &self.vars, self.deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheKey, ValidityCache, ValidityCheck};
+    use crate::env::EnvStack;
+    use crate::fd_monitor::FdEventSignaller;
+    use crate::highlight::file_tester::IsFile;
+    use crate::prelude::*;
+    use crate::tests::prelude::*;
+    use fish_widestring::osstr2wcstring;
+    use std::fs::File;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    #[serial]
+    fn test_validity_cache() {
+        test_init();
+        let tempdir = fish_tempfile::new_dir().unwrap();
+        File::create(tempdir.path().join("file.txt")).unwrap();
+
+        let event_signaller = Arc::new(FdEventSignaller::new());
+        let cache = ValidityCache::new(&event_signaller);
+        let vars = Arc::new(EnvStack::globals().snapshot());
+        let key = CacheKey {
+            check: ValidityCheck::Path {
+                token: L!("file.txt").to_owned(),
+                is_prefix: false,
+            },
+            working_directory: osstr2wcstring(tempdir.path()),
+        };
+
+        // With no time to wait, the result is not known, and the check finishes later.
+        assert_eq!(cache.check(key.clone(), &vars, Instant::now()), None);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !cache.take_finished_late() {
+            assert!(Instant::now() < deadline, "check did not finish");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(event_signaller.try_consume());
+
+        // Now it is cached.
+        assert_eq!(
+            cache.check(key.clone(), &vars, Instant::now()),
+            Some(Ok(IsFile(true)))
+        );
+
+        // Invalidated results are still used if the check does not finish in time.
+        std::fs::remove_file(tempdir.path().join("file.txt")).unwrap();
+        cache.invalidate();
+        let result = cache.check(key.clone(), &vars, Instant::now());
+        assert_eq!(result, Some(Ok(IsFile(true))));
+
+        // But checked again if there is time.
+        let result = cache.check(key, &vars, Instant::now() + Duration::from_secs(10));
+        assert_eq!(result, Some(Ok(IsFile(false))));
+    }
+}
@@ -293,6 +293,19 @@ impl HistoryItem {
         writer.write_all(&cmd)?;
         writer.write_all(b"\n")?;
         writeln!(writer, "  when: {}", time_to_seconds(self.timestamp()))?;
+        if let Some(cwd) = self.cwd() {
+            let mut cwd = wcs2bytes(cwd);
+            escape_yaml_fish_2_0(&mut cwd);
+            writer.write_all(b"  cwd: ")?;
+            writer.write_all(&cwd)?;
+            writer.write_all(b"\n")?;
+        }
+        if let Some(status) = self.exit_status() {
+            writeln!(writer, "  status: {status}")?;
+        }
+        if let Some(duration) = self.duration() {
+            writeln!(writer, "  duration: {}", duration.as_millis())?;
+        }
 
         let paths = self.get_required_paths();
         if !paths.is_empty() {
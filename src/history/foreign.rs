@@ -0,0 +1,391 @@
+//! Importing the history of other shells and tools, and exporting ours in their formats, so
+//! moving to or away from fish keeps the history.
+//!
+//! Bash stores one command per line. With `HISTTIMEFORMAT` set, every command is preceded by a
+//! `#<seconds>` line and extends to the next one, which keeps multi-line commands together. Zsh's
+//! extended history stores `: <start>:<elapsed>;<command>`, ends every line but the last of a
+//! multi-line command with a backslash, and "metafies" bytes which are special to zsh. Atuin
+//! stores its history in an SQLite database.
+
+use super::file::time_to_seconds;
+use super::yaml_backend::time_from_seconds;
+use super::{History, HistoryItem, PersistenceMode};
+use crate::prelude::*;
+use fish_widestring::{bytes2wcstring, wcs2bytes};
+use std::io;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Zsh precedes metafied bytes with this, and xors them with 0x20.
+const ZSH_META: u8 = 0x83;
+
+/// The formats which can be imported and exported.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ForeignFormat {
+    Bash,
+    Zsh,
+    Atuin,
+}
+
+impl ForeignFormat {
+    pub fn from_name(name: &wstr) -> Option<Self> {
+        match name {
+            _ if name == "bash" => Some(Self::Bash),
+            _ if name == "zsh" => Some(Self::Zsh),
+            _ if name == "atuin" => Some(Self::Atuin),
+            _ => None,
+        }
+    }
+}
+
+/// A command read from another shell's history file, which may lack a timestamp.
+struct Entry {
+    command: WString,
+    start: Option<i64>,
+    duration: Option<Duration>,
+}
+
+/// Adds the commands from the history file or database at `path` to `history`. Returns how many
+/// of them were not known yet.
+pub fn import_history(history: &History, format: ForeignFormat, path: &Path) -> io::Result<usize> {
+    let items = match format {
+        ForeignFormat::Bash | ForeignFormat::Zsh => {
+            let data = std::fs::read(path)?;
+            let entries = if format == ForeignFormat::Bash {
+                parse_bash(&data)
+            } else {
+                parse_zsh(&data)
+            };
+            // Commands without a timestamp were run at some point before the file was written.
+            let modified = std::fs::metadata(path)?
+                .modified()
+                .unwrap_or_else(|_| SystemTime::now());
+            entries_to_items(entries, modified)
+        }
+        ForeignFormat::Atuin => read_atuin(path)?,
+    };
+    history.import_items(items)
+}
+
+/// Returns the history of all sessions in the given format, oldest first, or `None` if the format
+/// can't be exported to.
+pub fn export_history(history: &History, format: ForeignFormat) -> Option<Vec<u8>> {
+    let items = history.items_run_after(UNIX_EPOCH - Duration::from_secs(1));
+    match format {
+        ForeignFormat::Bash => Some(write_bash(&items)),
+        ForeignFormat::Zsh => Some(write_zsh(&items)),
+        // Atuin reads fish's history itself, via `atuin import fish`.
+        ForeignFormat::Atuin => None,
+    }
+}
+
+/// Gives the entries without a timestamp consecutive ones ending at `end`, so they keep their
+/// order.
+fn entries_to_items(entries: Vec<Entry>, end: SystemTime) -> Vec<HistoryItem> {
+    let untimed = entries.iter().filter(|entry| entry.start.is_none()).count();
+    let mut next_untimed = time_to_seconds(end) - i64::try_from(untimed).unwrap() + 1;
+    entries
+        .into_iter()
+        .map(|entry| {
+            let start = entry.start.unwrap_or_else(|| {
+                next_untimed += 1;
+                next_untimed - 1
+            });
+            let mut item = HistoryItem::new(
+                entry.command,
+                time_from_seconds(start),
+                PersistenceMode::Disk,
+            );
+            item.set_result(None, entry.duration);
+            item
+        })
+        .collect()
+}
+
+/// Parses a bash timestamp line, `#` followed by the seconds since the epoch.
+fn parse_bash_timestamp(line: &[u8]) -> Option<i64> {
+    let digits = line.strip_prefix(b"#")?;
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+/// Adds a command, which may span multiple lines.
+fn push_entry(entries: &mut Vec<Entry>, lines: &[&[u8]], start: Option<i64>) {
+    let command = lines.join(&b'\n');
+    let command = command.trim_ascii_end();
+    if !command.trim_ascii_start().is_empty() {
+        entries.push(Entry {
+            command: bytes2wcstring(command),
+            start,
+            duration: None,
+        });
+    }
+}
+
+fn parse_bash(data: &[u8]) -> Vec<Entry> {
+    let mut entries = vec![];
+    // The timestamp and lines of the command being read, once a timestamp was seen.
+    let mut current: Option<(i64, Vec<&[u8]>)> = None;
+    for line in data.split(|&c| c == b'\n') {
+        if let Some(start) = parse_bash_timestamp(line) {
+            if let Some((start, lines)) = current.take() {
+                push_entry(&mut entries, &lines, Some(start));
+            }
+            current = Some((start, vec![]));
+        } else if let Some((_, lines)) = &mut current {
+            lines.push(line);
+        } else {
+            push_entry(&mut entries, &[line], None);
+        }
+    }
+    if let Some((start, lines)) = current {
+        push_entry(&mut entries, &lines, Some(start));
+    }
+    entries
+}
+
+fn write_bash(items: &[HistoryItem]) -> Vec<u8> {
+    let mut out = vec![];
+    for item in items {
+        out.extend_from_slice(format!("#{}\n", time_to_seconds(item.timestamp())).as_bytes());
+        out.extend_from_slice(&wcs2bytes(item.str()));
+        out.push(b'\n');
+    }
+    out
+}
+
+fn zsh_unmetafy(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut bytes = data.iter();
+    while let Some(&c) = bytes.next() {
+        if c == ZSH_META {
+            if let Some(&next) = bytes.next() {
+                out.push(next ^ 0x20);
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn zsh_metafy(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    for &c in data {
+        // NUL, Meta itself and zsh's tokens.
+        if c == 0 || (ZSH_META..=0xa2).contains(&c) {
+            out.push(ZSH_META);
+            out.push(c ^ 0x20);
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn parse_number<T: std::str::FromStr>(digits: &[u8]) -> Option<T> {
+    std::str::from_utf8(digits).ok()?.trim().parse().ok()
+}
+
+/// Parses the `: <start>:<elapsed>;` prefix of zsh's extended history.
+fn parse_zsh_prefix(line: &[u8]) -> Option<(i64, u64, &[u8])> {
+    let rest = line.strip_prefix(b": ")?;
+    let colon = rest.iter().position(|&c| c == b':')?;
+    let semicolon = rest.iter().position(|&c| c == b';')?;
+    if semicolon < colon {
+        return None;
+    }
+    let start = parse_number(&rest[..colon])?;
+    let elapsed = parse_number(&rest[colon + 1..semicolon])?;
+    Some((start, elapsed, &rest[semicolon + 1..]))
+}
+
+fn parse_zsh(data: &[u8]) -> Vec<Entry> {
+    let data = zsh_unmetafy(data);
+    let mut entries = vec![];
+    let mut lines = data.split(|&c| c == b'\n');
+    while let Some(first) = lines.next() {
+        // A trailing backslash continues the command on the next line.
+        let mut record = first.to_vec();
+        while record.last() == Some(&b'\\') {
+            let Some(next) = lines.next() else {
+                break;
+            };
+            *record.last_mut().unwrap() = b'\n';
+            record.extend_from_slice(next);
+        }
+        let (command, start, duration) = match parse_zsh_prefix(&record) {
+            Some((start, elapsed, command)) => {
+                (command, Some(start), Some(Duration::from_secs(elapsed)))
+            }
+            None => (&record[..], None, None),
+        };
+        if command.trim_ascii().is_empty() {
+            continue;
+        }
+        entries.push(Entry {
+            command: bytes2wcstring(command),
+            start,
+            duration,
+        });
+    }
+    entries
+}
+
+fn write_zsh(items: &[HistoryItem]) -> Vec<u8> {
+    let mut out = vec![];
+    for item in items {
+        let elapsed = item.duration().map_or(0, |duration| duration.as_secs());
+        out.extend_from_slice(
+            format!(": {}:{};", time_to_seconds(item.timestamp()), elapsed).as_bytes(),
+        );
+        for &c in &zsh_metafy(&wcs2bytes(item.str())) {
+            if c == b'\n' {
+                out.push(b'\\');
+            }
+            out.push(c);
+        }
+        out.push(b'\n');
+    }
+    out
+}
+
+/// Reads the commands from an atuin database, which records timestamps and durations in
+/// nanoseconds, and -1 for an unknown exit status or duration.
+#[cfg(feature = "sqlite-history")]
+fn read_atuin(path: &Path) -> io::Result<Vec<HistoryItem>> {
+    use rusqlite::{Connection, OpenFlags};
+
+    let to_io = |err: rusqlite::Error| io::Error::other(err.to_string());
+    let conn =
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(to_io)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT command, timestamp, duration, exit, cwd FROM history
+             WHERE deleted_at IS NULL ORDER BY timestamp",
+        )
+        .map_err(to_io)?;
+    let rows = stmt
+        .query_map([], |row| {
+            let command: String = row.get(0)?;
+            let timestamp: i64 = row.get(1)?;
+            let duration: i64 = row.get(2)?;
+            let exit: i64 = row.get(3)?;
+            let cwd: String = row.get(4)?;
+            let mut item = HistoryItem::new(
+                WString::from_str(&command),
+                time_from_seconds(timestamp.div_euclid(1_000_000_000)),
+                PersistenceMode::Disk,
+            );
+            item.set_result(
+                i32::try_from(exit).ok().filter(|&exit| exit >= 0),
+                u64::try_from(duration).ok().map(Duration::from_nanos),
+            );
+            if cwd.starts_with('/') {
+                item.set_cwd(Some(WString::from_str(&cwd)));
+            }
+            Ok(item)
+        })
+        .map_err(to_io)?;
+    rows.collect::<rusqlite::Result<Vec<_>>>().map_err(to_io)
+}
+
+#[cfg(not(feature = "sqlite-history"))]
+fn read_atuin(_path: &Path) -> io::Result<Vec<HistoryItem>> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Entry, HistoryItem, PersistenceMode, parse_bash, parse_zsh, time_from_seconds, write_bash,
+        write_zsh, zsh_metafy, zsh_unmetafy,
+    };
+    use crate::prelude::*;
+    use std::time::Duration;
+
+    fn commands(entries: &[Entry]) -> Vec<(&wstr, Option<i64>)> {
+        entries
+            .iter()
+            .map(|entry| (entry.command.as_utfstr(), entry.start))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_bash() {
+        let plain = parse_bash(b"ls\n\necho hi\n");
+        assert_eq!(commands(&plain), [(L!("ls"), None), (L!("echo hi"), None)]);
+
+        let timed =
+            parse_bash(b"#100\nls\n#101\nfor i in 1 2\ndo echo $i\ndone\n#102\n# comment\n");
+        assert_eq!(
+            commands(&timed),
+            [
+                (L!("ls"), Some(100)),
+                (L!("for i in 1 2\ndo echo $i\ndone"), Some(101)),
+                (L!("# comment"), Some(102)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_zsh() {
+        let entries = parse_zsh(b": 100:3;sleep 3\n: 101:0;echo a\\\nb\nplain\n");
+        assert_eq!(
+            commands(&entries),
+            [
+                (L!("sleep 3"), Some(100)),
+                (L!("echo a\nb"), Some(101)),
+                (L!("plain"), None),
+            ]
+        );
+        assert_eq!(entries[0].duration, Some(Duration::from_secs(3)));
+        assert_eq!(entries[2].duration, None);
+    }
+
+    #[test]
+    fn test_zsh_metafy() {
+        let bytes = b"echo \xc3\xa4 \x90 \x83 \x00".to_vec();
+        let metafied = zsh_metafy(&bytes);
+        assert_eq!(metafied, b"echo \xc3\xa4 \x83\xb0 \x83\xa3 \x83\x20");
+        assert_eq!(zsh_unmetafy(&metafied), bytes);
+    }
+
+    #[test]
+    fn test_export_roundtrip() {
+        let mut first = HistoryItem::new(
+            L!("echo one").to_owned(),
+            time_from_seconds(100),
+            PersistenceMode::Disk,
+        );
+        first.set_result(Some(0), Some(Duration::from_secs(2)));
+        let second = HistoryItem::new(
+            L!("echo 'two\nlines' ä").to_owned(),
+            time_from_seconds(200),
+            PersistenceMode::Disk,
+        );
+        let items = [first, second];
+
+        let bash = write_bash(&items);
+        assert_eq!(
+            commands(&parse_bash(&bash)),
+            [
+                (L!("echo one"), Some(100)),
+                (L!("echo 'two\nlines' ä"), Some(200))
+            ]
+        );
+
+        let zsh = write_zsh(&items);
+        let entries = parse_zsh(&zsh);
+        assert_eq!(
+            commands(&entries),
+            [
+                (L!("echo one"), Some(100)),
+                (L!("echo 'two\nlines' ä"), Some(200))
+            ]
+        );
+        assert_eq!(entries[0].duration, Some(Duration::from_secs(2)));
+    }
+}
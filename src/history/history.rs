@@ -93,12 +93,17 @@ pub enum SearchDirection {
 }
 
 use super::file::time_to_seconds;
+#[cfg(feature = "sqlite-history")]
+use super::sqlite_backend::HistoryDatabase;
 
 /// This is the history session ID we use by default if the user has not set env var fish_history.
 const DFLT_FISH_HISTORY_SESSION_ID: &wstr = L!("fish");
 
 pub const VACUUM_FREQUENCY: usize = 25;
 
+/// When we rewrite the history, the number of items we keep.
+const HISTORY_SAVE_MAX: NonZeroUsize = NonZeroUsize::new(1024 * 256).unwrap();
+
 struct TimeProfiler {
     what: &'static str,
     start: SystemTime,
@@ -148,6 +153,12 @@ impl LruCacheExt for LruCache<WString, HistoryItem> {
         if let Some(node) = self.get_mut(key) {
             node.creation_timestamp = SystemTime::max(node.timestamp(), item.timestamp());
             // What to do about paths here? Let's just ignore them.
+            // Items are added from the oldest to the newest, so this describes the latest run.
+            if item.cwd.is_some() {
+                node.cwd = item.cwd;
+            }
+            node.exit_status = item.exit_status;
+            node.duration = item.duration;
         } else {
             self.put(key.to_owned(), item);
         }
@@ -166,6 +177,12 @@ pub struct HistoryItem {
     required_paths: Vec<WString>,
     /// Whether to write this item to disk.
     persist_mode: PersistenceMode,
+    /// The working directory the command was run in, if known.
+    cwd: Option<WString>,
+    /// The exit status of the command, once it finished.
+    exit_status: Option<i32>,
+    /// How long the command ran, once it finished.
+    duration: Option<Duration>,
 }
 
 impl HistoryItem {
@@ -181,6 +198,9 @@ impl HistoryItem {
             creation_timestamp: when,
             required_paths: vec![],
             persist_mode,
+            cwd: None,
+            exit_status: None,
+            duration: None,
         }
     }
 
@@ -196,43 +216,7 @@ impl HistoryItem {
 
     /// Returns whether our contents matches a search term.
     pub fn matches_search(&self, term: &wstr, typ: SearchType, case_sensitive: bool) -> bool {
-        // Note that 'term' has already been lowercased when constructing the
-        // search object if we're doing a case insensitive search.
-        let content_to_match = if case_sensitive {
-            Cow::Borrowed(&self.contents)
-        } else {
-            Cow::Owned(self.contents.to_lowercase())
-        };
-
-        match typ {
-            SearchType::Exact => term == *content_to_match,
-            SearchType::Contains => {
-                subslice_position(content_to_match.as_slice(), term.as_slice()).is_some()
-            }
-            SearchType::Prefix => content_to_match.as_slice().starts_with(term.as_slice()),
-            SearchType::LinePrefix => content_to_match
-                .as_char_slice()
-                .split(|&c| c == '\n')
-                .any(|line| line.starts_with(term.as_char_slice())),
-            SearchType::ContainsGlob => {
-                let mut pat = unescape_wildcards(term);
-                if !pat.starts_with(ANY_STRING) {
-                    pat.insert(0, ANY_STRING);
-                }
-                if !pat.ends_with(ANY_STRING) {
-                    pat.push(ANY_STRING);
-                }
-                wildcard_match(content_to_match.as_ref(), &pat, false)
-            }
-            SearchType::PrefixGlob => {
-                let mut pat = unescape_wildcards(term);
-                if !pat.ends_with(ANY_STRING) {
-                    pat.push(ANY_STRING);
-                }
-                wildcard_match(content_to_match.as_ref(), &pat, false)
-            }
-            SearchType::ContainsSubsequence => subsequence_in_string(term, &content_to_match),
-        }
+        text_matches_search(&self.contents, term, typ, case_sensitive)
     }
 
     /// Returns the timestamp for creating this history item.
@@ -257,6 +241,30 @@ impl HistoryItem {
         self.required_paths = paths;
     }
 
+    /// Returns the working directory the command was run in, if known.
+    pub fn cwd(&self) -> Option<&wstr> {
+        self.cwd.as_deref()
+    }
+
+    pub(super) fn set_cwd(&mut self, cwd: Option<WString>) {
+        self.cwd = cwd;
+    }
+
+    /// Returns the exit status of the command, if it finished and that was recorded.
+    pub fn exit_status(&self) -> Option<i32> {
+        self.exit_status
+    }
+
+    /// Returns how long the command ran, if it finished and that was recorded.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    pub(super) fn set_result(&mut self, exit_status: Option<i32>, duration: Option<Duration>) {
+        self.exit_status = exit_status;
+        self.duration = duration;
+    }
+
     /// We can merge two items if they are the same command. We use the more recent timestamp, more
     /// recent identifier, and the longer list of required paths.
     fn merge(&mut self, item: &HistoryItem) -> bool {
@@ -270,37 +278,164 @@ impl HistoryItem {
         if self.required_paths.len() < item.required_paths.len() {
             self.required_paths.clone_from(&item.required_paths);
         }
+        // The command was run again, so describe that run.
+        if item.cwd.is_some() {
+            self.cwd.clone_from(&item.cwd);
+        }
+        self.exit_status = item.exit_status;
+        self.duration = item.duration;
         true
     }
 }
 
+/// Returns whether `text` matches a search term.
+fn text_matches_search(text: &wstr, term: &wstr, typ: SearchType, case_sensitive: bool) -> bool {
+    // Note that 'term' has already been lowercased when constructing the
+    // search object if we're doing a case insensitive search.
+    let content_to_match = if case_sensitive {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(text.to_lowercase())
+    };
+
+    match typ {
+        SearchType::Exact => *term == *content_to_match,
+        SearchType::Contains => {
+            subslice_position(content_to_match.as_slice(), term.as_slice()).is_some()
+        }
+        SearchType::Prefix => content_to_match.as_slice().starts_with(term.as_slice()),
+        SearchType::LinePrefix => content_to_match
+            .as_char_slice()
+            .split(|&c| c == '\n')
+            .any(|line| line.starts_with(term.as_char_slice())),
+        SearchType::ContainsGlob => {
+            let mut pat = unescape_wildcards(term);
+            if !pat.starts_with(ANY_STRING) {
+                pat.insert(0, ANY_STRING);
+            }
+            if !pat.ends_with(ANY_STRING) {
+                pat.push(ANY_STRING);
+            }
+            wildcard_match(content_to_match.as_ref(), &pat, false)
+        }
+        SearchType::PrefixGlob => {
+            let mut pat = unescape_wildcards(term);
+            if !pat.ends_with(ANY_STRING) {
+                pat.push(ANY_STRING);
+            }
+            wildcard_match(content_to_match.as_ref(), &pat, false)
+        }
+        SearchType::ContainsSubsequence => subsequence_in_string(term, &content_to_match),
+    }
+}
+
 #[derive(Clone, Eq, Ord, PartialEq, PartialOrd)]
 pub enum MemoryHistoryId {
     PrivateMode,
     BuiltinRead,
 }
 
+/// Where a history on disk is stored, chosen with `fish_history_backend`.
+#[derive(Clone, Copy, Debug, Default, Eq, Ord, PartialEq, PartialOrd)]
+pub enum HistoryBackend {
+    /// The history file, `{name}_history`.
+    #[default]
+    File,
+    /// An SQLite database, `{name}_history.db`.
+    Sqlite,
+}
+
+impl HistoryBackend {
+    /// Return the backend with the given name, if fish supports it.
+    pub fn from_name(name: &wstr) -> Option<Self> {
+        if name == "file" {
+            Some(Self::File)
+        } else if name == "sqlite" && cfg!(feature = "sqlite-history") {
+            Some(Self::Sqlite)
+        } else {
+            None
+        }
+    }
+}
+
 #[derive(Clone, Eq, Ord, PartialEq, PartialOrd)]
 pub enum HistoryId {
     Memory(MemoryHistoryId),
-    Disk { session_id: WString },
+    Disk {
+        session_id: WString,
+        backend: HistoryBackend,
+    },
 }
 
 static HISTORIES: Mutex<BTreeMap<HistoryId, Arc<History>>> = Mutex::new(BTreeMap::new());
 
 /// When deleting, whether the deletion should be only for this session or for all sessions.
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum DeletionScope {
+pub(super) enum DeletionScope {
     SessionOnly,
     AllSessions,
 }
 
+/// The commands to delete with `history redact`, as a search term which has been lowercased for a
+/// case insensitive search.
+struct Redaction {
+    term: WString,
+    search_type: SearchType,
+    case_sensitive: bool,
+}
+
+impl Redaction {
+    fn matches(&self, text: &wstr) -> bool {
+        text_matches_search(text, &self.term, self.search_type, self.case_sensitive)
+    }
+}
+
+/// The items from before a history was created, as read from its file or database.
+enum OldItems {
+    File(HistoryFile),
+    #[cfg(feature = "sqlite-history")]
+    Database(Vec<HistoryItem>),
+}
+
+impl OldItems {
+    fn len(&self) -> usize {
+        match self {
+            OldItems::File(file) => file.offsets().len(),
+            #[cfg(feature = "sqlite-history")]
+            OldItems::Database(items) => items.len(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            OldItems::File(file) => file.is_empty(),
+            #[cfg(feature = "sqlite-history")]
+            OldItems::Database(items) => items.is_empty(),
+        }
+    }
+
+    /// Return the item at the given index, where 0 is the oldest item.
+    fn item(&self, index: usize) -> Option<HistoryItem> {
+        match self {
+            OldItems::File(file) => file.decode_item(*file.offsets().get(index)?),
+            #[cfg(feature = "sqlite-history")]
+            OldItems::Database(items) => items.get(index).cloned(),
+        }
+    }
+}
+
 struct HistoryImpl {
     /// The name of this list. Used for picking a suitable filename and for switching modes.
     name: WString,
     /// Optional custom directory for the history file. If None, uses path_get_data().
     /// Primarily for testing.
     custom_directory: Option<WString>,
+    /// Whether the history is stored in the history file or a database.
+    #[cfg_attr(not(feature = "sqlite-history"), allow(dead_code))]
+    backend: HistoryBackend,
+    /// The history database if that is used, once it was opened.
+    #[cfg(feature = "sqlite-history")]
+    database: Option<HistoryDatabase>,
     /// New items. Note that these are NOT discarded on save. We need to keep these around so we can
     /// distinguish between items in our history and items in the history of other shells that were
     /// started after we were started.
@@ -314,8 +449,10 @@ struct HistoryImpl {
     disable_automatic_save_counter: u32, // 0
     /// Deleted item contents, and the scope of the deletion.
     deleted_items: HashMap<WString, DeletionScope>,
-    /// The history file contents.
-    file_contents: Option<HistoryFile>,
+    /// The commands being redacted while the history file is rewritten.
+    redaction: Option<Redaction>,
+    /// The old items, once they were loaded.
+    old_items: Option<OldItems>,
     /// The file ID of the history file.
     history_file_id: FileId, // INVALID_FILE_ID
     /// The boundary timestamp distinguishes old items from new items. Items whose timestamps are <=
@@ -327,6 +464,9 @@ struct HistoryImpl {
     countdown_to_vacuum: Option<usize>,
     /// Thread pool for background operations.
     thread_pool: Arc<ThreadPool>,
+    /// The contents and timestamp of the last item added as pending, until the result of running
+    /// its command is recorded.
+    awaiting_result: Option<(WString, SystemTime)>,
 }
 
 impl HistoryImpl {
@@ -378,6 +518,7 @@ impl HistoryImpl {
         if item.contents.is_empty() {
             return;
         }
+        self.awaiting_result = pending.then(|| (item.contents.clone(), item.creation_timestamp));
 
         // Try merging with the last item.
         if let Some(last) = self.new_items.last_mut() {
@@ -400,7 +541,7 @@ impl HistoryImpl {
     /// Internal function.
     fn clear_file_state(&mut self) {
         // Erase everything we know about our file.
-        self.file_contents = None;
+        self.old_items = None;
     }
 
     /// Returns a timestamp for new items - see the implementation for a subtlety.
@@ -423,13 +564,20 @@ impl HistoryImpl {
     }
 
     /// Loads old items if necessary.
-    /// Return a reference to the loaded history file.
-    fn load_old_if_needed(&mut self) -> &HistoryFile {
-        if let Some(ref file_contents) = self.file_contents {
-            return file_contents;
+    /// Return a reference to the loaded items.
+    fn load_old_if_needed(&mut self) -> &OldItems {
+        if let Some(ref old_items) = self.old_items {
+            return old_items;
+        }
+        #[cfg(feature = "sqlite-history")]
+        if self.backend == HistoryBackend::Sqlite {
+            let items = self.load_old_from_database();
+            return self.old_items.insert(OldItems::Database(items));
         }
         let Ok(Some(history_path)) = self.history_file_path() else {
-            return self.file_contents.insert(HistoryFile::create_empty());
+            return self
+                .old_items
+                .insert(OldItems::File(HistoryFile::create_empty()));
         };
 
         let _profiler = TimeProfiler::new("load_old");
@@ -450,7 +598,71 @@ impl HistoryImpl {
                 HistoryFile::create_empty()
             }
         };
-        self.file_contents.insert(file_contents)
+        self.old_items.insert(OldItems::File(file_contents))
+    }
+
+    /// Returns the history database, opening it if necessary, or `None` in private mode or if it
+    /// cannot be opened. A new database gets the items of the history file.
+    #[cfg(feature = "sqlite-history")]
+    fn database(&mut self) -> Option<&mut HistoryDatabase> {
+        if self.database.is_none() {
+            let history_path = match self.history_file_path() {
+                Ok(history_path) => history_path?,
+                Err(e) => {
+                    flog!(history_file, "Error opening history database:", e);
+                    return None;
+                }
+            };
+            let mut database_path = history_path.clone();
+            database_path.push_utfstr(L!(".db"));
+            let import = || match lock_and_load(&history_path, RawHistoryFile::create) {
+                Ok((_, history_file)) => {
+                    let file_contents = history_file.decode(None);
+                    file_contents
+                        .offsets()
+                        .iter()
+                        .filter_map(|&offset| file_contents.decode_item(offset))
+                        .collect()
+                }
+                Err(_) => vec![],
+            };
+            match HistoryDatabase::open(&database_path, import) {
+                Ok(database) => self.database = Some(database),
+                Err(e) => {
+                    flog!(
+                        history_file,
+                        "Error opening history database:",
+                        e.to_string()
+                    );
+                    return None;
+                }
+            }
+        }
+        self.database.as_mut()
+    }
+
+    /// Loads old items from the history database.
+    #[cfg(feature = "sqlite-history")]
+    fn load_old_from_database(&mut self) -> Vec<HistoryItem> {
+        let _profiler = TimeProfiler::new("load_old");
+        let boundary_timestamp = self.boundary_timestamp;
+        let Some(database) = self.database() else {
+            return vec![];
+        };
+        match database.load(boundary_timestamp) {
+            Ok(items) => {
+                flogf!(history, "Loaded %u old items", items.len());
+                items
+            }
+            Err(e) => {
+                flog!(
+                    history_file,
+                    "Error reading from history database:",
+                    e.to_string()
+                );
+                vec![]
+            }
+        }
     }
 
     /// Deletes duplicates in new_items.
@@ -498,18 +710,20 @@ impl HistoryImpl {
     }
 
     /// Given an existing history file, write a new history file to `dst`.
+    /// Also adds `extra_items`, which do not belong to this session.
     fn rewrite_to_temporary_file(
         &self,
         existing_file: &File,
         dst: &mut File,
+        extra_items: &[HistoryItem],
     ) -> std::io::Result<()> {
         // We are reading FROM existing_file and writing TO dst
 
         // Make an LRU cache to save only the last N elements.
-
-        /// When we rewrite the history, the number of items we keep.
-        const HISTORY_SAVE_MAX: NonZeroUsize = NonZeroUsize::new(1024 * 256).unwrap();
         let mut lru = LruCache::new(HISTORY_SAVE_MAX);
+        for item in extra_items {
+            lru.add_item(item.clone());
+        }
 
         // Read in existing items (which may have changed out from underneath us, so don't trust our
         // old file contents).
@@ -520,7 +734,12 @@ impl HistoryImpl {
                 let Some(old_item) = local_file.decode_item(offset) else {
                     continue;
                 };
-                if old_item.is_empty() {
+                if old_item.is_empty()
+                    || self
+                        .redaction
+                        .as_ref()
+                        .is_some_and(|redaction| redaction.matches(old_item.str()))
+                {
                     continue;
                 }
 
@@ -567,7 +786,11 @@ impl HistoryImpl {
     }
 
     /// Saves history by rewriting the file.
-    fn save_internal_via_rewrite(&mut self, history_path: &wstr) -> std::io::Result<()> {
+    fn save_internal_via_rewrite(
+        &mut self,
+        history_path: &wstr,
+        extra_items: &[HistoryItem],
+    ) -> std::io::Result<()> {
         flogf!(
             history,
             "Saving %u items via rewrite",
@@ -576,7 +799,7 @@ impl HistoryImpl {
 
         let rewrite =
             |old_file: &File, tmp_file: &mut File| -> std::io::Result<PotentialUpdate<()>> {
-                let result = self.rewrite_to_temporary_file(old_file, tmp_file);
+                let result = self.rewrite_to_temporary_file(old_file, tmp_file, extra_items);
                 if let Err(err) = result {
                     flog!(
                         history_file,
@@ -690,6 +913,12 @@ impl HistoryImpl {
             return;
         }
 
+        #[cfg(feature = "sqlite-history")]
+        if self.backend == HistoryBackend::Sqlite {
+            self.save_to_database(vacuum);
+            return;
+        }
+
         let history_path = match self.history_file_path() {
             Ok(history_path) => history_path.unwrap(),
             Err(e) => {
@@ -711,12 +940,45 @@ impl HistoryImpl {
         }
         if !ok {
             // We did not or could not append; rewrite the file ("vacuum" it).
-            if let Err(e) = self.save_internal_via_rewrite(&history_path) {
+            if let Err(e) = self.save_internal_via_rewrite(&history_path, &[]) {
                 flog!(history, "Rewriting history failed:", e);
             }
         }
     }
 
+    /// Saves history to the database. Like rewriting the history file, vacuuming deletes the items
+    /// beyond the most recent [`HISTORY_SAVE_MAX`].
+    #[cfg(feature = "sqlite-history")]
+    fn save_to_database(&mut self, vacuum: bool) {
+        let boundary_timestamp = self.boundary_timestamp;
+        if self.database().is_none() {
+            return;
+        }
+        let database = self.database.as_mut().unwrap();
+        let new_items = self.new_items[self.first_unwritten_new_item_index..]
+            .iter()
+            .filter(|item| item.should_write_to_disk());
+        flogf!(
+            history,
+            "Saving %u items to the database",
+            new_items.clone().count()
+        );
+        if let Err(e) = database.save(
+            new_items,
+            &self.deleted_items,
+            boundary_timestamp,
+            vacuum.then_some(HISTORY_SAVE_MAX.get()),
+        ) {
+            flog!(history, "Saving history failed:", e.to_string());
+            return;
+        }
+        self.first_unwritten_new_item_index = self.new_items.len();
+        if vacuum || !self.deleted_items.is_empty() {
+            self.deleted_items.clear();
+            self.clear_file_state();
+        }
+    }
+
     /// Saves history unless doing so is disabled.
     fn save_unless_disabled(&mut self) {
         // Respect disable_automatic_save_counter.
@@ -752,21 +1014,26 @@ impl HistoryImpl {
         self.save(vacuum);
     }
 
-    fn new(name: WString, custom_directory: Option<WString>) -> Self {
+    fn new(name: WString, custom_directory: Option<WString>, backend: HistoryBackend) -> Self {
         Self {
             name,
             custom_directory,
+            backend,
+            #[cfg(feature = "sqlite-history")]
+            database: None,
             new_items: vec![],
             first_unwritten_new_item_index: 0,
             has_pending_item: false,
             disable_automatic_save_counter: 0,
             deleted_items: HashMap::new(),
-            file_contents: None,
+            redaction: None,
+            old_items: None,
             history_file_id: INVALID_FILE_ID,
             boundary_timestamp: SystemTime::now(),
             countdown_to_vacuum: None,
             // Up to 8 threads, no soft min.
             thread_pool: ThreadPool::new(0, 8),
+            awaiting_result: None,
         }
     }
 
@@ -783,10 +1050,17 @@ impl HistoryImpl {
             return false;
         }
 
-        if let Some(file_contents) = &self.file_contents {
-            // If we've loaded old items, see if we have any offsets.
-            file_contents.is_empty()
+        if let Some(old_items) = &self.old_items {
+            // If we've loaded old items, see if we have any.
+            old_items.is_empty()
         } else {
+            #[cfg(feature = "sqlite-history")]
+            if self.backend == HistoryBackend::Sqlite {
+                return self
+                    .database()
+                    .is_none_or(|database| database.is_empty().unwrap_or(true));
+            }
+
             // If we have not loaded old items, don't actually load them (which may be expensive); just
             // stat the file and see if it exists and is nonempty.
 
@@ -825,6 +1099,115 @@ impl HistoryImpl {
         assert!(self.first_unwritten_new_item_index <= self.new_items.len());
     }
 
+    /// Remove the items matching a search from memory, and rewrite the history on disk right away,
+    /// which also removes those that other shells saved.
+    fn redact(
+        &mut self,
+        term: &wstr,
+        search_type: SearchType,
+        case_sensitive: bool,
+    ) -> std::io::Result<()> {
+        let redaction = Redaction {
+            term: if case_sensitive {
+                term.to_owned()
+            } else {
+                term.to_lowercase()
+            },
+            search_type,
+            case_sensitive,
+        };
+        // The command which is running right now is likely to contain the term as well.
+        if self.has_pending_item
+            && self
+                .new_items
+                .last()
+                .is_some_and(|item| redaction.matches(item.str()))
+        {
+            self.has_pending_item = false;
+        }
+        let mut redacted: HashSet<WString> = self
+            .new_items
+            .iter()
+            .filter(|item| redaction.matches(item.str()))
+            .map(|item| item.str().to_owned())
+            .collect();
+        let old_items = self.load_old_if_needed();
+        redacted.extend(
+            (0..old_items.len())
+                .filter_map(|index| old_items.item(index))
+                .filter(|item| redaction.matches(item.str()))
+                .map(|item| item.contents),
+        );
+        for contents in &redacted {
+            self.remove(contents);
+        }
+
+        if self.name.is_empty() {
+            // There is nothing on disk in private mode.
+            self.save(false);
+            return Ok(());
+        }
+        #[cfg(feature = "sqlite-history")]
+        if self.backend == HistoryBackend::Sqlite {
+            self.save(false);
+            let Some(database) = self.database() else {
+                return Err(std::io::Error::other("Opening the history database failed"));
+            };
+            database
+                .redact(|command| redaction.matches(command))
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            self.clear_file_state();
+            return Ok(());
+        }
+        let Some(history_path) = self.history_file_path()? else {
+            return Ok(());
+        };
+        self.compact_new_items();
+        self.redaction = Some(redaction);
+        let result = self.save_internal_via_rewrite(&history_path, &[]);
+        self.redaction = None;
+        result
+    }
+
+    /// Records the exit status and duration of the command of the last item added as pending.
+    fn record_result(&mut self, exit_status: i32, duration: Duration) {
+        let Some((contents, timestamp)) = self.awaiting_result.take() else {
+            return;
+        };
+        let Some(idx) = self
+            .new_items
+            .iter()
+            .rposition(|item| item.contents == contents && item.creation_timestamp == timestamp)
+        else {
+            return;
+        };
+        self.new_items[idx].set_result(Some(exit_status), Some(duration));
+
+        // Nothing else to do if the item was not saved yet.
+        if idx >= self.first_unwritten_new_item_index || !self.new_items[idx].should_write_to_disk()
+        {
+            return;
+        }
+        #[cfg(feature = "sqlite-history")]
+        if self.backend == HistoryBackend::Sqlite {
+            let item = self.new_items[idx].clone();
+            if let Some(database) = self.database() {
+                if let Err(e) = database.update(&item) {
+                    flog!(history, "Updating history failed:", e.to_string());
+                }
+            }
+            return;
+        }
+        // The file is only appended to, so the item is written again, now with its result. Reading
+        // the file prefers the later entry, and vacuuming drops the earlier one.
+        if idx + 1 == self.first_unwritten_new_item_index {
+            self.first_unwritten_new_item_index = idx;
+            if self.disable_automatic_save_counter == 0 {
+                self.save(false);
+            }
+        }
+    }
+
     /// Resolves any pending history items, so that they may be returned in history searches.
     fn resolve_pending(&mut self) {
         self.has_pending_item = false;
@@ -846,7 +1229,16 @@ impl HistoryImpl {
         self.new_items.clear();
         self.deleted_items.clear();
         self.first_unwritten_new_item_index = 0;
-        self.file_contents = None;
+        self.old_items = None;
+        #[cfg(feature = "sqlite-history")]
+        if self.backend == HistoryBackend::Sqlite {
+            if let Some(database) = self.database() {
+                if let Err(e) = database.clear() {
+                    flog!(history, "Clearing history failed:", e.to_string());
+                }
+            }
+            return;
+        }
         if let Ok(Some(filename)) = self.history_file_path() {
             let _ = wunlink(&filename);
         }
@@ -915,6 +1307,75 @@ impl HistoryImpl {
         }
     }
 
+    /// Returns the items of all shells which were run after `since`, to sync or export them.
+    fn items_run_after(&mut self, since: SystemTime) -> Vec<HistoryItem> {
+        self.incorporate_external_changes();
+        let old_items = self.load_old_if_needed();
+        let mut items: Vec<_> = (0..old_items.len())
+            .filter_map(|index| old_items.item(index))
+            .collect();
+        // Our own items may be too new to be read from the file.
+        let pending = usize::from(self.has_pending_item);
+        items.extend(
+            self.new_items[..self.new_items.len() - pending]
+                .iter()
+                .filter(|item| item.should_write_to_disk())
+                .cloned(),
+        );
+        let mut seen = HashSet::new();
+        items.retain(|item| {
+            item.timestamp() > since && seen.insert((item.contents.clone(), item.timestamp()))
+        });
+        items
+    }
+
+    /// Adds items synced from other machines or imported from other shells, skipping those which
+    /// were run at the same time as a known item with the same command. Returns the number of
+    /// items added.
+    fn import_items(&mut self, items: Vec<HistoryItem>) -> std::io::Result<usize> {
+        self.incorporate_external_changes();
+        let old_items = self.load_old_if_needed();
+        let mut known: HashSet<_> = (0..old_items.len())
+            .filter_map(|index| old_items.item(index))
+            .map(|item| (item.contents, item.creation_timestamp))
+            .collect();
+        known.extend(
+            self.new_items
+                .iter()
+                .map(|item| (item.contents.clone(), item.creation_timestamp)),
+        );
+        let items: Vec<_> = items
+            .into_iter()
+            .filter(|item| {
+                !item.is_empty() && known.insert((item.contents.clone(), item.creation_timestamp))
+            })
+            .collect();
+        if items.is_empty() {
+            return Ok(0);
+        }
+
+        #[cfg(feature = "sqlite-history")]
+        if self.backend == HistoryBackend::Sqlite {
+            let Some(database) = self.database() else {
+                return Err(std::io::Error::other("Opening the history database failed"));
+            };
+            let count = database
+                .import(&items)
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            self.clear_file_state();
+            self.incorporate_external_changes();
+            return Ok(count);
+        }
+        let Some(history_path) = self.history_file_path()? else {
+            return Ok(0);
+        };
+        // Rewrite the file, so the items are sorted by when they were run.
+        self.save_internal_via_rewrite(&history_path, &items)?;
+        // Make the items available in this session, like `history merge` does.
+        self.incorporate_external_changes();
+        Ok(items.len())
+    }
+
     /// Gets all the history into a list. This is intended for the $history environment variable.
     /// This may be long!
     fn get_history(&mut self) -> Vec<WString> {
@@ -937,9 +1398,9 @@ impl HistoryImpl {
         }
 
         // Append old items.
-        let file_contents = self.load_old_if_needed();
-        for &offset in file_contents.offsets().iter().rev() {
-            let Some(item) = file_contents.decode_item(offset) else {
+        let old_items = self.load_old_if_needed();
+        for index in (0..old_items.len()).rev() {
+            let Some(item) = old_items.item(index) else {
                 continue;
             };
             if seen.insert(item.str().to_owned()) {
@@ -1012,13 +1473,11 @@ impl HistoryImpl {
 
         // Now look in our old items.
         idx -= resolved_new_item_count;
-        let file_contents = self.load_old_if_needed();
-        let old_item_offsets = file_contents.offsets();
-        let old_item_count = old_item_offsets.len();
+        let old_items = self.load_old_if_needed();
+        let old_item_count = old_items.len();
         if idx < old_item_count {
-            // idx == 0 corresponds to last item in old_item_offsets.
-            let offset = old_item_offsets[old_item_count - idx - 1];
-            return file_contents.decode_item(offset).map(Cow::Owned);
+            // idx == 0 corresponds to the last old item.
+            return old_items.item(old_item_count - idx - 1).map(Cow::Owned);
         }
 
         // Index past the valid range, so return None.
@@ -1031,8 +1490,7 @@ impl HistoryImpl {
         if self.has_pending_item && new_item_count > 0 {
             new_item_count -= 1;
         }
-        let old_item_offsets = self.load_old_if_needed().offsets();
-        new_item_count + old_item_offsets.len()
+        new_item_count + self.load_old_if_needed().len()
     }
 }
 
@@ -1049,6 +1507,7 @@ fn do_1_history_search(
     search_type: SearchType,
     search_string: WString,
     case_sensitive: bool,
+    filter: &HistoryFilter,
     mut func: impl FnMut(&mut Parser, &HistoryItem) -> ControlFlow<(), ()>,
 ) {
     let mut searcher = HistorySearch::new_with(
@@ -1061,7 +1520,8 @@ fn do_1_history_search(
             SearchFlags::IGNORE_CASE
         },
         0,
-    );
+    )
+    .with_filter(filter.clone());
 
     while !(parser.context().cancel_checker)()
         && searcher.go_to_next_match(SearchDirection::Backward)
@@ -1180,15 +1640,14 @@ impl History {
     /// The history file will be stored at `{directory}/{name}_history`.
     /// If the directory is None, it will be stored at path_get_data().
     fn new_with_directory(id: HistoryId, directory: Option<WString>) -> Arc<Self> {
-        Arc::new(Self(Mutex::new(HistoryImpl::new(
-            match id {
-                HistoryId::Memory(_) => WString::new(),
-                HistoryId::Disk {
-                    session_id: filename,
-                } => filename,
-            },
-            directory,
-        ))))
+        let (name, backend) = match id {
+            HistoryId::Memory(_) => (WString::new(), HistoryBackend::File),
+            HistoryId::Disk {
+                session_id: filename,
+                backend,
+            } => (filename, backend),
+        };
+        Arc::new(Self(Mutex::new(HistoryImpl::new(name, directory, backend))))
     }
 
     /// Returns the history with the given name, creating it if necessary, using the default data directory.
@@ -1221,6 +1680,17 @@ impl History {
         self.imp().remove(s);
     }
 
+    /// Remove every item matching a search, in memory and on disk, including those saved by other
+    /// shells.
+    pub fn redact(
+        &self,
+        term: &wstr,
+        search_type: SearchType,
+        case_sensitive: bool,
+    ) -> std::io::Result<()> {
+        self.imp().redact(term, search_type, case_sensitive)
+    }
+
     /// Remove any trailing ephemeral items.
     pub fn remove_ephemeral_items(&self) {
         self.imp().remove_ephemeral_items();
@@ -1278,7 +1748,8 @@ impl History {
 
         // Make our history item.
         let when = imp.timestamp_now();
-        let item = HistoryItem::new(s.to_owned(), when, persist_mode);
+        let mut item = HistoryItem::new(s.to_owned(), when, persist_mode);
+        item.set_cwd(vars.get(L!("PWD")).map(|pwd| pwd.as_string()));
         let to_disk = persist_mode == PersistenceMode::Disk;
 
         if wants_file_detection {
@@ -1316,6 +1787,12 @@ impl History {
         }
     }
 
+    /// Records the exit status and duration of the command of the last item added as pending,
+    /// after it ran.
+    pub fn record_pending_result(&self, exit_status: i32, duration: Duration) {
+        self.imp().record_result(exit_status, duration);
+    }
+
     /// Resolves any pending history items, so that they may be returned in history searches.
     pub fn resolve_pending(&self) {
         self.imp().resolve_pending();
@@ -1334,6 +1811,7 @@ impl History {
         streams: &mut IoStreams,
         search_type: SearchType,
         search_args: &[&wstr],
+        filter: &HistoryFilter,
         show_time_format: Option<&str>,
         max_items: usize,
         case_sensitive: bool,
@@ -1381,6 +1859,7 @@ impl History {
                 SearchType::Contains,
                 WString::new(),
                 true,
+                filter,
                 &mut func,
             );
         } else {
@@ -1398,6 +1877,7 @@ impl History {
                     search_type,
                     search_string.to_owned(),
                     case_sensitive,
+                    filter,
                     &mut func,
                 );
             }
@@ -1441,6 +1921,24 @@ impl History {
         self.imp().incorporate_external_changes();
     }
 
+    /// Returns the path of the history file, or `None` in private mode. The database, if used, is
+    /// next to it.
+    #[cfg(feature = "history-sync")]
+    pub(super) fn file_path(&self) -> std::io::Result<Option<WString>> {
+        self.imp().history_file_path()
+    }
+
+    /// Returns the items of all shells which were run after `since`.
+    pub(super) fn items_run_after(&self, since: SystemTime) -> Vec<HistoryItem> {
+        self.imp().items_run_after(since)
+    }
+
+    /// Adds items from other machines or shells which are not known yet, and returns how many
+    /// there were.
+    pub(super) fn import_items(&self, items: Vec<HistoryItem>) -> std::io::Result<usize> {
+        self.imp().import_items(items)
+    }
+
     /// Gets all the history into a list. This is intended for the $history environment variable.
     /// This may be long!
     pub fn get_history(&self) -> Vec<WString> {
@@ -1480,6 +1978,28 @@ bitflags! {
     }
 }
 
+/// Restricts a history search to commands run in a directory, or to those which failed.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HistoryFilter {
+    /// If set, only match commands run in this directory.
+    pub cwd: Option<WString>,
+    /// If set, only match commands which finished with a nonzero exit status.
+    pub failed_only: bool,
+}
+
+impl HistoryFilter {
+    /// Returns whether the item passes the filter. Items with an unknown working directory or exit
+    /// status do not pass the corresponding restriction.
+    pub fn matches(&self, item: &HistoryItem) -> bool {
+        if let Some(cwd) = &self.cwd {
+            if item.cwd() != Some(cwd.as_utfstr()) {
+                return false;
+            }
+        }
+        !self.failed_only || item.exit_status().is_some_and(|status| status != 0)
+    }
+}
+
 /// Support for searching a history backwards.
 /// Note this does NOT de-duplicate; it is the caller's responsibility to do so.
 pub struct HistorySearch {
@@ -1499,6 +2019,8 @@ pub struct HistorySearch {
     current_index: usize, // 0
     /// If deduping, the items we've seen.
     deduper: HashSet<WString>,
+    /// Restrictions beyond the search term.
+    filter: HistoryFilter,
 }
 
 impl HistorySearch {
@@ -1531,6 +2053,7 @@ impl HistorySearch {
             current_item: None,
             current_index: starting_index,
             deduper: HashSet::new(),
+            filter: HistoryFilter::default(),
         };
 
         if search.ignores_case() {
@@ -1540,6 +2063,12 @@ impl HistorySearch {
         search
     }
 
+    /// Only find items which pass the given filter.
+    pub fn with_filter(mut self, filter: HistoryFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
     /// Returns the original search term.
     pub fn original_term(&self) -> &wstr {
         &self.orig_term
@@ -1585,7 +2114,9 @@ impl HistorySearch {
             };
 
             // Look for an item that matches and (if deduping) that we haven't seen before.
-            if !item.matches_search(&self.canon_term, self.search_type, !self.ignores_case()) {
+            if !item.matches_search(&self.canon_term, self.search_type, !self.ignores_case())
+                || !self.filter.matches(&item)
+            {
                 continue;
             }
 
@@ -1654,13 +2185,29 @@ pub fn save_all() {
 
 /// Return the prefix for the files to be used for command and read history.
 pub fn history_id(vars: &dyn Environment) -> HistoryId {
-    history_id_from_var(vars.get(L!("fish_history")))
+    history_id_from_var(
+        vars.get(L!("fish_history")),
+        vars.get(L!("fish_history_backend")),
+    )
 }
 
-pub fn history_id_from_var(history_name_var: Option<EnvVar>) -> HistoryId {
+/// Return the history backend named by `fish_history_backend`, which is the history file if it is
+/// unset or names an unsupported backend.
+pub fn history_backend_from_var(backend_var: Option<EnvVar>) -> HistoryBackend {
+    backend_var
+        .and_then(|var| HistoryBackend::from_name(&var.as_string()))
+        .unwrap_or_default()
+}
+
+pub fn history_id_from_var(
+    history_name_var: Option<EnvVar>,
+    backend_var: Option<EnvVar>,
+) -> HistoryId {
     use HistoryId::*;
+    let backend = history_backend_from_var(backend_var);
     let default = || Disk {
         session_id: DFLT_FISH_HISTORY_SESSION_ID.to_owned(),
+        backend,
     };
     let Some(var) = history_name_var else {
         return default();
@@ -1669,7 +2216,10 @@ pub fn history_id_from_var(history_name_var: Option<EnvVar>) -> HistoryId {
     if session_id.is_empty() {
         Memory(MemoryHistoryId::PrivateMode)
     } else if valid_var_name(&session_id) {
-        Disk { session_id }
+        Disk {
+            session_id,
+            backend,
+        }
     } else {
         flog!(
             error,
@@ -1759,14 +2309,71 @@ pub fn in_private_mode(vars: &dyn Environment) -> bool {
     vars.get_unless_empty(L!("fish_private_mode")).is_some()
 }
 
+/// Return the closest directory containing `dir` (including itself) which contains a file or
+/// directory named like one of the `markers`, which makes it the root of a project.
+fn find_project_root(dir: &wstr, markers: &[WString]) -> Option<WString> {
+    let mut dir = dir.to_owned();
+    while dir.len() > 1 && dir.ends_with('/') {
+        dir.pop();
+    }
+    loop {
+        let is_root = markers.iter().any(|marker| {
+            !marker.is_empty()
+                && !marker.contains('/')
+                && wstat(&(dir.clone() + L!("/") + &marker[..])).is_ok()
+        });
+        if is_root {
+            return Some(dir);
+        }
+        let parent_len = dir.as_char_slice().iter().rposition(|&c| c == '/')?;
+        if parent_len == 0 {
+            if dir.len() == 1 {
+                return None;
+            }
+            dir.truncate(1);
+        } else {
+            dir.truncate(parent_len);
+        }
+    }
+}
+
+/// Return the session ID of the history of the project at `root`. It is derived from the path of
+/// the project with a hash that is stable between versions of fish.
+fn project_session_id(root: &wstr) -> WString {
+    // FNV-1a.
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for c in root.chars() {
+        hash ^= u64::from(u32::from(c));
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    sprintf!("project_%016x", hash)
+}
+
+/// Return the history of the project the current directory is in, if `fish_history_project_markers`
+/// names the files which mark the root of a project. Commands are added to this history in addition
+/// to the main history.
+pub fn project_history(vars: &dyn Environment) -> Option<Arc<History>> {
+    if in_private_mode(vars) {
+        return None;
+    }
+    let markers = vars.get_unless_empty(L!("fish_history_project_markers"))?;
+    let pwd = vars.get_unless_empty(L!("PWD"))?;
+    let root = find_project_root(&pwd.as_string(), markers.as_list())?;
+    Some(History::new(HistoryId::Disk {
+        session_id: project_session_id(&root),
+        backend: history_backend_from_var(vars.get(L!("fish_history_backend"))),
+    }))
+}
+
 #[cfg(test)]
 mod tests {
     use super::{
-        History, HistoryItem, HistorySearch, PathList, PersistenceMode, SearchDirection,
-        SearchFlags, SearchType, VACUUM_FREQUENCY,
+        History, HistoryBackend, HistoryFilter, HistoryItem, HistorySearch, PathList,
+        PersistenceMode, SearchDirection, SearchFlags, SearchType, VACUUM_FREQUENCY,
+        find_project_root, project_session_id,
     };
     use crate::{
-        common::ESCAPE_TEST_CHAR,
+        common::{ESCAPE_TEST_CHAR, valid_var_name},
         env::{EnvMode, EnvSetMode, EnvStack},
         fs::{LockedFile, WriteMethod},
         history::HistoryId,
@@ -1801,9 +2408,18 @@ mod tests {
 
     // Helper to create a history with a custom directory, for testing.
     fn create_test_history(name: &wstr, custom_dir: &wstr) -> Arc<History> {
+        create_test_history_with_backend(name, custom_dir, HistoryBackend::File)
+    }
+
+    fn create_test_history_with_backend(
+        name: &wstr,
+        custom_dir: &wstr,
+        backend: HistoryBackend,
+    ) -> Arc<History> {
         History::new_with_directory(
             HistoryId::Disk {
                 session_id: name.to_owned(),
+                backend,
             },
             Some(custom_dir.to_owned()),
         )
@@ -2146,8 +2762,20 @@ mod tests {
 
     #[test]
     fn test_history_merge() {
+        check_history_merge(HistoryBackend::File);
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite-history")]
+    fn test_history_merge_database() {
+        check_history_merge(HistoryBackend::Sqlite);
+    }
+
+    fn check_history_merge(backend: HistoryBackend) {
         let tmpdir = fish_tempfile::new_dir().unwrap();
         let hist_dir = osstr2wcstring(tmpdir.path());
+        let create_test_history =
+            |name, hist_dir| create_test_history_with_backend(name, hist_dir, backend);
 
         // In a single fish process, only one history is allowed to exist with the given name But it's
         // common to have multiple history instances with the same name active in different processes,
@@ -2258,6 +2886,174 @@ mod tests {
         everything.clear();
     }
 
+    #[test]
+    fn test_history_redact() {
+        check_history_redact(HistoryBackend::File);
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite-history")]
+    fn test_history_redact_database() {
+        check_history_redact(HistoryBackend::Sqlite);
+    }
+
+    fn check_history_redact(backend: HistoryBackend) {
+        let tmpdir = fish_tempfile::new_dir().unwrap();
+        let hist_dir = osstr2wcstring(tmpdir.path());
+        let name = L!("redact_test");
+        let create_test_history = || create_test_history_with_backend(name, &hist_dir, backend);
+
+        let other = create_test_history();
+        other.add_commandline(L!("export TOKEN=hunter2").to_owned());
+        other.add_commandline(L!("ls").to_owned());
+        other.save();
+        time_barrier();
+
+        let hist = create_test_history();
+        hist.add_commandline(L!("curl -H 'Authorization: HUNTER2'").to_owned());
+        hist.save();
+        // Another shell saves a command which we do not see.
+        other.add_commandline(L!("echo hunter2").to_owned());
+        other.save();
+        hist.add(
+            HistoryItem::new(
+                L!("history redact hunter2").to_owned(),
+                SystemTime::now(),
+                PersistenceMode::Disk,
+            ),
+            /*pending=*/ true,
+        );
+        hist.redact(L!("hunter2"), SearchType::Contains, false)
+            .unwrap();
+        hist.resolve_pending();
+        assert_eq!(hist.get_history(), [L!("ls").to_owned()]);
+
+        time_barrier();
+        let hist = create_test_history();
+        assert_eq!(hist.get_history(), [L!("ls").to_owned()]);
+        hist.clear();
+    }
+
+    #[test]
+    #[cfg(feature = "sqlite-history")]
+    fn test_history_database() {
+        let tmpdir = fish_tempfile::new_dir().unwrap();
+        let hist_dir = osstr2wcstring(tmpdir.path());
+        let name = L!("database_test");
+
+        let file_hist = create_test_history(name, &hist_dir);
+        file_hist.add_commandline(L!("from the file").to_owned());
+        file_hist.add_commandline(L!("deleted later").to_owned());
+        file_hist.save();
+        time_barrier();
+
+        // A new database gets the items of the history file.
+        let hist = create_test_history_with_backend(name, &hist_dir, HistoryBackend::Sqlite);
+        assert!(history_contains(&hist, L!("from the file")));
+        assert!(history_contains(&hist, L!("deleted later")));
+
+        // The result of running a pending item is recorded, even after it was saved.
+        let mut item = HistoryItem::new(
+            L!("false").to_owned(),
+            SystemTime::now(),
+            PersistenceMode::Disk,
+        );
+        item.set_cwd(Some(L!("/tmp").to_owned()));
+        hist.add(item, /*pending=*/ true);
+        hist.record_pending_result(1, Duration::from_millis(42));
+        hist.resolve_pending();
+        hist.remove(L!("deleted later"));
+        hist.save();
+        time_barrier();
+
+        let hist = create_test_history_with_backend(name, &hist_dir, HistoryBackend::Sqlite);
+        let item = hist.item_at_index(1).unwrap();
+        assert_eq!(item.str(), "false");
+        assert_eq!(item.cwd(), Some(L!("/tmp")));
+        assert_eq!(item.exit_status(), Some(1));
+        assert_eq!(item.duration(), Some(Duration::from_millis(42)));
+        assert!(history_contains(&hist, L!("from the file")));
+        assert!(!history_contains(&hist, L!("deleted later")));
+
+        // The history file is left alone.
+        let file_hist = create_test_history(name, &hist_dir);
+        assert!(history_contains(&file_hist, L!("deleted later")));
+        assert!(!history_contains(&file_hist, L!("false")));
+
+        hist.clear();
+        assert!(hist.is_empty());
+        let hist = create_test_history_with_backend(name, &hist_dir, HistoryBackend::Sqlite);
+        assert!(hist.is_empty());
+    }
+
+    #[test]
+    fn test_history_results() {
+        let tmpdir = fish_tempfile::new_dir().unwrap();
+        let hist_dir = osstr2wcstring(tmpdir.path());
+        let name = L!("results_test");
+
+        let add = |hist: &History, cmd: &str, cwd: &str, status: i32| {
+            let mut item = HistoryItem::new(
+                WString::from_str(cmd),
+                hist.imp().timestamp_now(),
+                PersistenceMode::Disk,
+            );
+            item.set_cwd(Some(WString::from_str(cwd)));
+            hist.add(item, /*pending=*/ true);
+            hist.record_pending_result(status, Duration::from_millis(7));
+            hist.resolve_pending();
+        };
+        let search = |hist: &Arc<History>, filter: HistoryFilter| {
+            let mut search =
+                HistorySearch::new(Arc::clone(hist), WString::new()).with_filter(filter);
+            let mut found = vec![];
+            while search.go_to_next_match(SearchDirection::Backward) {
+                found.push(search.current_string().to_string());
+            }
+            found
+        };
+
+        let hist = create_test_history(name, &hist_dir);
+        // Don't vacuum before we check that all runs were saved.
+        hist.imp().countdown_to_vacuum = Some(VACUUM_FREQUENCY);
+        add(&hist, "make", "/src/a", 2);
+        add(&hist, "make test", "/src/a", 0);
+        add(&hist, "ls", "/src/b", 1);
+        add(&hist, "make", "/src/b", 0);
+        time_barrier();
+
+        // The results are written to the file, although the items were written before them.
+        let hist = create_test_history(name, &hist_dir);
+        let item = hist.item_at_index(1).unwrap();
+        assert_eq!(item.str(), "make");
+        assert_eq!(item.cwd(), Some(L!("/src/b")));
+        assert_eq!(item.exit_status(), Some(0));
+        assert_eq!(item.duration(), Some(Duration::from_millis(7)));
+
+        let in_a = HistoryFilter {
+            cwd: Some(L!("/src/a").to_owned()),
+            ..Default::default()
+        };
+        let failed = HistoryFilter {
+            failed_only: true,
+            ..Default::default()
+        };
+        assert_eq!(search(&hist, in_a.clone()), ["make test", "make"]);
+        assert_eq!(search(&hist, failed.clone()), ["ls", "make"]);
+
+        // Vacuuming keeps the result of the latest run.
+        let item = HistoryItem::new(
+            L!("exit").to_owned(),
+            hist.imp().timestamp_now(),
+            PersistenceMode::Disk,
+        );
+        hist.imp().add(item, /*pending=*/ false, /*do_save=*/ false);
+        hist.imp().save(/*vacuum=*/ true);
+        let hist = create_test_history(name, &hist_dir);
+        assert_eq!(search(&hist, in_a), ["make test"]);
+        assert_eq!(search(&hist, failed), ["ls"]);
+    }
+
     #[test]
     fn test_history_path_detection() {
         test_init();
@@ -2428,4 +3224,77 @@ mod tests {
         assert_eq!(test_history_imported_from_corrupted.get_history(), expected);
         test_history_imported_from_corrupted.clear();
     }
+
+    #[test]
+    #[cfg(feature = "history-sync")]
+    fn test_history_sync() {
+        use crate::history::{SyncError, sync_history};
+
+        let tmpdir = fish_tempfile::new_dir().unwrap();
+        let hist_dir = osstr2wcstring(tmpdir.path());
+        let remote = hist_dir.clone() + L!("/remote");
+
+        // Both histories are in the same directory, so they share the key.
+        let first = create_test_history(L!("sync_first"), &hist_dir);
+        first.add_commandline(L!("echo first 1").into());
+        first.add_commandline(L!("echo first 2").into());
+        first.save();
+        time_barrier();
+        let key = sync_history(&first, &remote).unwrap();
+        assert_eq!(key, Some(hist_dir.clone() + L!("/history_sync_key")));
+
+        let second = create_test_history(L!("sync_second"), &hist_dir);
+        second.add_commandline(L!("echo second").into());
+        second.save();
+        time_barrier();
+        assert_eq!(sync_history(&second, &remote).unwrap(), None);
+        assert!(history_contains(&second, L!("echo first 1")));
+        assert!(history_contains(&second, L!("echo first 2")));
+
+        sync_history(&first, &remote).unwrap();
+        assert!(history_contains(&first, L!("echo second")));
+        // Syncing again does not add anything twice.
+        sync_history(&second, &remote).unwrap();
+        let second = create_test_history(L!("sync_second"), &hist_dir);
+        assert_eq!(second.size(), 3);
+
+        // A machine with another key can't read the deltas.
+        let other_dir = hist_dir.clone() + L!("/other");
+        std::fs::create_dir(tmpdir.path().join("other")).unwrap();
+        let other = create_test_history(L!("sync_other"), &other_dir);
+        assert!(matches!(
+            sync_history(&other, &remote),
+            Err(SyncError::WrongKey(_, _))
+        ));
+
+        first.clear();
+        second.clear();
+    }
+
+    #[test]
+    fn test_project_root() {
+        let tmpdir = fish_tempfile::new_dir().unwrap();
+        let root = osstr2wcstring(tmpdir.path()) + L!("/project");
+        std::fs::create_dir_all(tmpdir.path().join("project/.git")).unwrap();
+        std::fs::create_dir_all(tmpdir.path().join("project/src/nested")).unwrap();
+        let markers = [WString::from(".git")];
+
+        assert_eq!(find_project_root(&root, &markers), Some(root.clone()));
+        let nested = root.clone() + L!("/src/nested/");
+        assert_eq!(find_project_root(&nested, &markers), Some(root.clone()));
+        assert_eq!(
+            find_project_root(&osstr2wcstring(tmpdir.path()), &markers),
+            None
+        );
+        assert_eq!(
+            find_project_root(&nested, &[WString::from("Cargo.toml")]),
+            None
+        );
+
+        // Project histories are named after their root, which makes for a valid session ID.
+        let id = project_session_id(&root);
+        assert!(valid_var_name(&id));
+        assert_eq!(id, project_session_id(&root));
+        assert_ne!(id, project_session_id(&nested));
+    }
 }
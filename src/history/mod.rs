@@ -1,6 +1,14 @@
 mod file;
+mod foreign;
 #[allow(clippy::module_inception)]
 mod history;
+#[cfg(feature = "sqlite-history")]
+mod sqlite_backend;
+#[cfg(feature = "history-sync")]
+mod sync;
 mod yaml_backend;
 
+pub use foreign::{ForeignFormat, export_history, import_history};
 pub use history::*;
+#[cfg(feature = "history-sync")]
+pub use sync::{SyncError, sync_history};
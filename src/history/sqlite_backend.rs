@@ -0,0 +1,296 @@
+//! Storage of the history in an SQLite database, which is used instead of the history file if
+//! `fish_history_backend` is set to `sqlite`.
+//!
+//! Besides what the history file stores, every entry records how long its command ran, its exit
+//! status, the working directory and the session which ran it, so other tools can query it. SQLite
+//! takes care of concurrent access by many shells, so entries are added as they are run, and the
+//! database never needs to be rewritten. Unlike the file, it keeps an entry for every time a
+//! command was run; reading the history only uses the most recent one.
+
+use super::file::time_to_seconds;
+use super::history::DeletionScope;
+use super::yaml_backend::time_from_seconds;
+use super::{HistoryItem, PersistenceMode};
+use crate::flog::flogf;
+use crate::prelude::*;
+use fish_widestring::{bytes2wcstring, wcs2bytes, wcs2osstring};
+use rusqlite::types::{FromSql, FromSqlResult, Value, ValueRef};
+use rusqlite::{Connection, Row, TransactionBehavior, params};
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, SystemTime};
+
+/// The version of the schema, which is stored as the database's `user_version`. That is 0 for a
+/// database which was just created.
+const SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS history (
+    id INTEGER PRIMARY KEY,
+    command TEXT NOT NULL,
+    -- Seconds since the epoch.
+    timestamp INTEGER NOT NULL,
+    duration_ms INTEGER,
+    exit_status INTEGER,
+    cwd TEXT,
+    -- NULL for entries imported from the history file or other machines.
+    session_id TEXT,
+    -- The arguments which referred to files, separated by NUL bytes.
+    paths BLOB
+);
+CREATE INDEX IF NOT EXISTS history_timestamp ON history (timestamp);
+CREATE INDEX IF NOT EXISTS history_command ON history (command);
+";
+
+/// How long to wait for another shell which is writing to the database.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Identifies the entries added by this shell.
+static SESSION_ID: LazyLock<String> = LazyLock::new(|| {
+    format!(
+        "{}-{}",
+        std::process::id(),
+        time_to_seconds(SystemTime::now())
+    )
+});
+
+/// Text as stored in the database. That is TEXT, unless it contains bytes which are not valid
+/// UTF-8, in which case it is a BLOB, so it is read back unchanged.
+struct DbText(WString);
+
+impl DbText {
+    fn value(s: &wstr) -> Value {
+        match String::from_utf8(wcs2bytes(s)) {
+            Ok(s) => Value::Text(s),
+            Err(err) => Value::Blob(err.into_bytes()),
+        }
+    }
+}
+
+impl FromSql for DbText {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_bytes().map(|bytes| DbText(bytes2wcstring(bytes)))
+    }
+}
+
+fn paths_value(paths: &[WString]) -> Option<Vec<u8>> {
+    if paths.is_empty() {
+        return None;
+    }
+    let mut value = vec![];
+    for path in paths {
+        if !value.is_empty() {
+            value.push(b'\0');
+        }
+        value.extend(wcs2bytes(path));
+    }
+    Some(value)
+}
+
+fn duration_ms(item: &HistoryItem) -> Option<i64> {
+    item.duration()
+        .map(|d| i64::try_from(d.as_millis()).unwrap_or(i64::MAX))
+}
+
+fn item_from_row(row: &Row<'_>) -> rusqlite::Result<HistoryItem> {
+    let DbText(command) = row.get(0)?;
+    let mut item = HistoryItem::new(
+        command,
+        time_from_seconds(row.get(1)?),
+        PersistenceMode::Disk,
+    );
+    let duration: Option<i64> = row.get(2)?;
+    let duration = duration.and_then(|ms| u64::try_from(ms).ok());
+    item.set_result(row.get(3)?, duration.map(Duration::from_millis));
+    let cwd: Option<DbText> = row.get(4)?;
+    item.set_cwd(cwd.map(|DbText(cwd)| cwd));
+    let paths: Option<Vec<u8>> = row.get(5)?;
+    if let Some(paths) = paths {
+        item.set_required_paths(paths.split(|&b| b == b'\0').map(bytes2wcstring).collect());
+    }
+    Ok(item)
+}
+
+fn insert_item(
+    conn: &Connection,
+    item: &HistoryItem,
+    session_id: Option<&str>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO history (command, timestamp, duration_ms, exit_status, cwd, session_id, paths)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            DbText::value(item.str()),
+            time_to_seconds(item.timestamp()),
+            duration_ms(item),
+            item.exit_status(),
+            item.cwd().map(DbText::value),
+            session_id,
+            paths_value(item.get_required_paths()),
+        ],
+    )?;
+    Ok(())
+}
+
+/// An open history database.
+pub(super) struct HistoryDatabase {
+    conn: Connection,
+}
+
+impl HistoryDatabase {
+    /// Open the database at the given path, creating it if necessary. A new database is filled
+    /// with the items returned by `import`, so switching to it keeps the existing history.
+    pub(super) fn open(
+        path: &wstr,
+        import: impl FnOnce() -> Vec<HistoryItem>,
+    ) -> rusqlite::Result<Self> {
+        let mut conn = Connection::open(wcs2osstring(path))?;
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // With write-ahead logging, shells can read while another one writes.
+        conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))?;
+
+        // Take the write lock first, so only one shell imports the history file.
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let version: i64 = tx.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version == 0 {
+            tx.execute_batch(SCHEMA)?;
+            let items = import();
+            for item in &items {
+                insert_item(&tx, item, None)?;
+            }
+            flogf!(
+                history,
+                "Imported %u items into the history database",
+                items.len()
+            );
+            tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        }
+        tx.commit()?;
+        Ok(Self { conn })
+    }
+
+    /// Return whether the database has no entries.
+    pub(super) fn is_empty(&self) -> rusqlite::Result<bool> {
+        self.conn
+            .query_row("SELECT NOT EXISTS (SELECT 1 FROM history)", [], |row| {
+                row.get(0)
+            })
+    }
+
+    /// Return the most recent entry of every command, from the oldest to the newest, ignoring
+    /// entries newer than `cutoff`.
+    pub(super) fn load(&self, cutoff: SystemTime) -> rusqlite::Result<Vec<HistoryItem>> {
+        // With a single MAX(), SQLite takes the other columns from the row with the maximum.
+        let mut stmt = self.conn.prepare(
+            "SELECT command, MAX(timestamp) AS time, duration_ms, exit_status, cwd, paths, id
+             FROM history WHERE timestamp <= ?1 GROUP BY command ORDER BY time, id",
+        )?;
+        stmt.query_map([time_to_seconds(cutoff)], item_from_row)?
+            .collect()
+    }
+
+    /// Add new items, and delete commands. Commands deleted only for this session keep their
+    /// entries from before `boundary`. If `max_commands` is given, also delete the commands which
+    /// were not run recently enough to be among that many.
+    pub(super) fn save<'a>(
+        &mut self,
+        items: impl IntoIterator<Item = &'a HistoryItem>,
+        deleted_items: &HashMap<WString, DeletionScope>,
+        boundary: SystemTime,
+        max_commands: Option<usize>,
+    ) -> rusqlite::Result<()> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        for (command, &scope) in deleted_items {
+            let newer_than = match scope {
+                DeletionScope::AllSessions => i64::MIN,
+                DeletionScope::SessionOnly => time_to_seconds(boundary),
+            };
+            tx.execute(
+                "DELETE FROM history WHERE command = ?1 AND timestamp > ?2",
+                params![DbText::value(command), newer_than],
+            )?;
+        }
+        for item in items {
+            insert_item(&tx, item, Some(&SESSION_ID))?;
+        }
+        if let Some(max_commands) = max_commands {
+            tx.execute(
+                "DELETE FROM history WHERE command IN (
+                     SELECT command FROM history GROUP BY command
+                     ORDER BY MAX(timestamp) DESC LIMIT -1 OFFSET ?1
+                 )",
+                [i64::try_from(max_commands).unwrap_or(i64::MAX)],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Update the last entry this shell added for the item's command, after the command finished
+    /// or was run again.
+    pub(super) fn update(&self, item: &HistoryItem) -> rusqlite::Result<()> {
+        self.conn.execute(
+            "UPDATE history SET timestamp = ?1, duration_ms = ?2, exit_status = ?3, cwd = ?4
+             WHERE id = (SELECT MAX(id) FROM history WHERE session_id = ?5 AND command = ?6)",
+            params![
+                time_to_seconds(item.timestamp()),
+                duration_ms(item),
+                item.exit_status(),
+                item.cwd().map(DbText::value),
+                SESSION_ID.as_str(),
+                DbText::value(item.str()),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Add entries from other machines or shells, skipping those for a command which was run at
+    /// the same time already. Returns the number of entries added.
+    pub(super) fn import(&mut self, items: &[HistoryItem]) -> rusqlite::Result<usize> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let mut count = 0;
+        for item in items {
+            let known: bool = tx.query_row(
+                "SELECT EXISTS (SELECT 1 FROM history WHERE command = ?1 AND timestamp = ?2)",
+                params![DbText::value(item.str()), time_to_seconds(item.timestamp())],
+                |row| row.get(0),
+            )?;
+            if !known {
+                insert_item(&tx, item, None)?;
+                count += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Delete the entries of every command for which `matches` returns true.
+    pub(super) fn redact(&mut self, matches: impl Fn(&wstr) -> bool) -> rusqlite::Result<()> {
+        let tx = self
+            .conn
+            .transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let commands = {
+            let mut stmt = tx.prepare("SELECT DISTINCT command FROM history")?;
+            stmt.query_map([], |row| row.get::<_, DbText>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?
+        };
+        for DbText(command) in commands {
+            if matches(&command) {
+                tx.execute(
+                    "DELETE FROM history WHERE command = ?1",
+                    [DbText::value(&command)],
+                )?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Delete all entries.
+    pub(super) fn clear(&self) -> rusqlite::Result<()> {
+        self.conn.execute("DELETE FROM history", [])?;
+        Ok(())
+    }
+}
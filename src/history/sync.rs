@@ -0,0 +1,543 @@
+//! Syncing the history between machines, which is enabled by setting `fish_history_sync` to a
+//! location every machine can reach: a directory (for example in a synced folder), a directory on
+//! an SSH server, or an HTTP endpoint.
+//!
+//! `history sync` pulls the "deltas" other machines stored there into the history, and stores the
+//! commands run on this machine since the last sync as a new delta. Deltas are encrypted with a
+//! key shared by all machines, so the location does not need to be trusted with the history. An
+//! item is only added if no item with the same command was run at the same time, so deltas may
+//! overlap.
+
+use super::file::time_to_seconds;
+use super::yaml_backend::{decode_item_fish_2_0, offset_of_next_item_fish_2_0, time_from_seconds};
+use super::{History, HistoryItem};
+use crate::prelude::*;
+use chacha20poly1305::aead::{Aead as _, KeyInit as _, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use fish_widestring::{osstr2wcstring, wcs2osstring};
+use rand::Rng as _;
+use rand::rand_core::UnwrapErr;
+use rand::rngs::SysRng;
+use std::collections::HashSet;
+use std::ffi::OsString;
+use std::fs::OpenOptions;
+use std::io::{self, Write as _};
+use std::os::unix::fs::OpenOptionsExt as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+
+/// Deltas start with this, followed by the nonce and the encrypted items in the format of the
+/// history file.
+const DELTA_MAGIC: &[u8] = b"fish-history-delta-1\n";
+const DELTA_SUFFIX: &str = ".delta";
+const NONCE_LEN: usize = 24;
+const KEY_LEN: usize = 32;
+
+/// The key is stored in this file next to the history file.
+const KEY_FILE_NAME: &str = "history_sync_key";
+
+/// Why syncing failed.
+#[derive(Debug)]
+pub enum SyncError {
+    /// `fish_history_sync` is not a location that can be synced with.
+    InvalidLocation,
+    /// Reading or writing the key, the sync state or the history failed.
+    Local(io::Error),
+    /// Storing or fetching deltas failed.
+    Remote(io::Error),
+    /// The number of deltas which could not be decrypted, which were probably stored with
+    /// another key, and the path of the key.
+    WrongKey(usize, WString),
+}
+
+/// Where the deltas are stored.
+#[derive(Debug, Eq, PartialEq)]
+enum Remote {
+    Directory(PathBuf),
+    /// A directory on an SSH server. A path starting with `~/` is relative to the home directory.
+    Ssh {
+        destination: String,
+        port: Option<String>,
+        dir: String,
+    },
+    /// An HTTP endpoint, which lists the names of the deltas for a GET request to the URL, and
+    /// stores and returns them for PUT and GET requests to the URL followed by a slash and the
+    /// name.
+    Http(String),
+}
+
+/// Quote a string for a POSIX shell.
+fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Run a command, passing `input` on stdin, and return its output.
+fn run(mut command: Command, input: Option<&[u8]>) -> io::Result<Vec<u8>> {
+    command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped());
+    let mut child = command.spawn().map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("{}: {e}", command.get_program().to_string_lossy()),
+        )
+    })?;
+    if let Some(input) = input {
+        // Close stdin afterwards, so the command sees the end of the input.
+        child.stdin.take().unwrap().write_all(input)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} failed with {}",
+            command.get_program().to_string_lossy(),
+            output.status
+        )));
+    }
+    Ok(output.stdout)
+}
+
+fn is_delta_name(name: &str) -> bool {
+    name.len() > DELTA_SUFFIX.len()
+        && name.ends_with(DELTA_SUFFIX)
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        && !name.starts_with('.')
+}
+
+impl Remote {
+    /// Parse a location: an absolute path or `file://` URL, an `ssh://[USER@]HOST[:PORT]/PATH`
+    /// URL, or an HTTP(S) URL.
+    fn parse(location: &wstr) -> Option<Self> {
+        if location.starts_with('/') {
+            return Some(Remote::Directory(PathBuf::from(wcs2osstring(location))));
+        }
+        if let Some(path) = location.strip_prefix(L!("file://")) {
+            return path
+                .starts_with('/')
+                .then(|| Remote::Directory(PathBuf::from(wcs2osstring(path))));
+        }
+        let location = location.to_string();
+        if location.starts_with("http://") || location.starts_with("https://") {
+            return Some(Remote::Http(location.trim_end_matches('/').to_owned()));
+        }
+        let rest = location.strip_prefix("ssh://")?;
+        let (authority, path) = rest.split_at(rest.find('/')?);
+        let host_start = authority.rfind('@').map_or(0, |at| at + 1);
+        let (destination, port) = match authority[host_start..].split_once(':') {
+            Some((_, port)) => (
+                &authority[..authority.len() - port.len() - 1],
+                Some(port.to_owned()),
+            ),
+            None => (authority, None),
+        };
+        // "ssh://host/~/dir" is relative to the home directory.
+        let dir = path.strip_prefix("/~/").map_or(path.to_owned(), |dir| {
+            format!("~/{}", dir.trim_end_matches('/'))
+        });
+        if destination.is_empty() || destination.starts_with('-') || dir == "/" || dir == "~/" {
+            return None;
+        }
+        Some(Remote::Ssh {
+            destination: destination.to_owned(),
+            port,
+            dir,
+        })
+    }
+
+    /// Run a command on the SSH server, in the directory of the deltas.
+    fn ssh(&self, script: &str, input: Option<&[u8]>) -> io::Result<Vec<u8>> {
+        let Remote::Ssh {
+            destination,
+            port,
+            dir,
+        } = self
+        else {
+            unreachable!();
+        };
+        // Keep a leading "~/" unquoted, so the remote shell expands it.
+        let dir = match dir.strip_prefix("~/") {
+            Some(dir) => format!("~/{}", sh_quote(dir)),
+            None => sh_quote(dir),
+        };
+        let mut command = Command::new("ssh");
+        if let Some(port) = port {
+            command.args(["-p", port]);
+        }
+        command
+            .arg("--")
+            .arg(destination)
+            .arg(script.replace("DIR", &dir));
+        run(command, input)
+    }
+
+    fn curl(url: &str, input: Option<&[u8]>) -> io::Result<Vec<u8>> {
+        let mut command = Command::new("curl");
+        command.args(["--fail", "--silent", "--show-error"]);
+        if input.is_some() {
+            command.args(["--upload-file", "-"]);
+        }
+        command.arg(url);
+        run(command, input)
+    }
+
+    /// Return the names of the stored deltas.
+    fn list(&self) -> io::Result<Vec<String>> {
+        let names = match self {
+            Remote::Directory(dir) => {
+                let entries = match std::fs::read_dir(dir) {
+                    Ok(entries) => entries,
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+                    Err(e) => return Err(e),
+                };
+                let mut names = vec![];
+                for entry in entries {
+                    names.push(entry?.file_name().to_string_lossy().into_owned());
+                }
+                names
+            }
+            Remote::Ssh { .. } => {
+                let output = self.ssh("cd DIR 2>/dev/null || exit 0; ls -1", None)?;
+                String::from_utf8_lossy(&output)
+                    .lines()
+                    .map(str::to_owned)
+                    .collect()
+            }
+            Remote::Http(url) => {
+                let output = Self::curl(&format!("{url}/"), None)?;
+                String::from_utf8_lossy(&output)
+                    .lines()
+                    .map(|line| line.trim().to_owned())
+                    .collect()
+            }
+        };
+        Ok(names
+            .into_iter()
+            .filter(|name| is_delta_name(name))
+            .collect())
+    }
+
+    /// Return the contents of a delta.
+    fn get(&self, name: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Remote::Directory(dir) => std::fs::read(dir.join(name)),
+            Remote::Ssh { .. } => self.ssh(&format!("cd DIR && cat -- {name}"), None),
+            Remote::Http(url) => Self::curl(&format!("{url}/{name}"), None),
+        }
+    }
+
+    /// Store a delta.
+    fn put(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        match self {
+            Remote::Directory(dir) => {
+                // Write to a temporary file first, so other machines never see half a delta.
+                std::fs::create_dir_all(dir)?;
+                let tmp_path = dir.join(format!(".{name}.tmp"));
+                std::fs::write(&tmp_path, data)?;
+                std::fs::rename(&tmp_path, dir.join(name))
+            }
+            Remote::Ssh { .. } => self
+                .ssh(
+                    &format!(
+                        "mkdir -p DIR && cd DIR && cat >.{name}.tmp && mv -f .{name}.tmp {name}"
+                    ),
+                    Some(data),
+                )
+                .map(drop),
+            Remote::Http(url) => Self::curl(&format!("{url}/{name}"), Some(data)).map(drop),
+        }
+    }
+}
+
+/// What this machine knows about the deltas, stored next to the history file.
+struct SyncState {
+    /// Starts the names of the deltas of this machine.
+    machine_id: String,
+    /// Items run after this (in seconds since the epoch) were not pushed yet.
+    pushed: i64,
+    /// The deltas of other machines which were added to the history.
+    pulled: HashSet<String>,
+}
+
+impl SyncState {
+    fn load(path: &Path) -> io::Result<Self> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let mut state = SyncState {
+            machine_id: String::new(),
+            pushed: 0,
+            pulled: HashSet::new(),
+        };
+        for line in contents.lines() {
+            match line.split_once(' ') {
+                Some(("machine", id)) => id.clone_into(&mut state.machine_id),
+                Some(("pushed", time)) => state.pushed = time.parse().unwrap_or(0),
+                Some(("pulled", name)) => {
+                    state.pulled.insert(name.to_owned());
+                }
+                _ => (),
+            }
+        }
+        if state.machine_id.is_empty() {
+            let mut id = [0; 8];
+            UnwrapErr(SysRng).fill_bytes(&mut id);
+            state.machine_id = to_hex(&id);
+        }
+        Ok(state)
+    }
+
+    fn save(&self, path: &Path) -> io::Result<()> {
+        let mut contents = format!("machine {}\npushed {}\n", self.machine_id, self.pushed);
+        for name in &self.pulled {
+            contents.push_str("pulled ");
+            contents.push_str(name);
+            contents.push('\n');
+        }
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        std::fs::write(&tmp_path, contents)?;
+        std::fs::rename(&tmp_path, path)
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn key_from_hex(s: &str) -> Option<[u8; KEY_LEN]> {
+    let mut key = [0; KEY_LEN];
+    if s.len() != 2 * KEY_LEN || !s.is_ascii() {
+        return None;
+    }
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(key)
+}
+
+/// Read the key shared by all machines, creating it if there is none. Returns whether it was
+/// created.
+fn read_or_create_key(path: &Path) -> io::Result<([u8; KEY_LEN], bool)> {
+    match std::fs::read_to_string(path) {
+        Ok(contents) => key_from_hex(contents.trim())
+            .map(|key| (key, false))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: invalid key", path.display()),
+                )
+            }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let mut key = [0; KEY_LEN];
+            UnwrapErr(SysRng).fill_bytes(&mut key);
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(path)?;
+            writeln!(file, "{}", to_hex(&key))?;
+            Ok((key, true))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+fn encrypt(key: &[u8; KEY_LEN], plaintext: &[u8]) -> Vec<u8> {
+    let mut nonce = [0; NONCE_LEN];
+    UnwrapErr(SysRng).fill_bytes(&mut nonce);
+    let ciphertext = XChaCha20Poly1305::new(key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: DELTA_MAGIC,
+            },
+        )
+        .expect("Encrypting only fails for huge inputs");
+    [DELTA_MAGIC, &nonce, &ciphertext].concat()
+}
+
+/// Decrypt a delta, returning `None` if it was encrypted with another key or is corrupted.
+fn decrypt(key: &[u8; KEY_LEN], delta: &[u8]) -> Option<Vec<u8>> {
+    let rest = delta.strip_prefix(DELTA_MAGIC)?;
+    if rest.len() < NONCE_LEN {
+        return None;
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    XChaCha20Poly1305::new(key.into())
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: DELTA_MAGIC,
+            },
+        )
+        .ok()
+}
+
+fn encode_items(items: &[HistoryItem]) -> Vec<u8> {
+    let mut data = vec![];
+    for item in items {
+        // Can't error writing to a buffer.
+        item.write_to(&mut data).unwrap();
+    }
+    data
+}
+
+fn decode_items(data: &[u8]) -> Vec<HistoryItem> {
+    let mut items = vec![];
+    let mut cursor = 0;
+    while let Some(offset) = offset_of_next_item_fish_2_0(data, &mut cursor, None) {
+        items.extend(decode_item_fish_2_0(&data[offset..]));
+    }
+    items
+}
+
+/// Sync the history with the deltas at the given location. If the key was created, returns its
+/// path, which needs to be copied to the other machines.
+pub fn sync_history(history: &History, location: &wstr) -> Result<Option<WString>, SyncError> {
+    let remote = Remote::parse(location).ok_or(SyncError::InvalidLocation)?;
+    let Some(history_path) = history.file_path().map_err(SyncError::Local)? else {
+        return Ok(None);
+    };
+    let history_path = PathBuf::from(wcs2osstring(&history_path));
+    let key_path = history_path.with_file_name(KEY_FILE_NAME);
+    let mut state_path = OsString::from(history_path);
+    state_path.push(".sync");
+    let state_path = PathBuf::from(state_path);
+
+    let (key, created_key) = read_or_create_key(&key_path).map_err(SyncError::Local)?;
+    let created_key = created_key.then(|| osstr2wcstring(&key_path));
+    let mut state = SyncState::load(&state_path).map_err(SyncError::Local)?;
+
+    // Pull first, so a new key is not used to push if there are deltas with another one.
+    let own_prefix = format!("{}-", state.machine_id);
+    let mut pulled_items = vec![];
+    let mut pulled_names = vec![];
+    let mut undecryptable = 0;
+    for name in remote.list().map_err(SyncError::Remote)? {
+        if name.starts_with(&own_prefix) || state.pulled.contains(&name) {
+            continue;
+        }
+        let delta = remote.get(&name).map_err(SyncError::Remote)?;
+        match decrypt(&key, &delta) {
+            Some(data) => {
+                pulled_items.extend(decode_items(&data));
+                pulled_names.push(name);
+            }
+            None => undecryptable += 1,
+        }
+    }
+    if undecryptable != 0 {
+        return Err(SyncError::WrongKey(
+            undecryptable,
+            osstr2wcstring(&key_path),
+        ));
+    }
+    let pulled: HashSet<_> = pulled_items
+        .iter()
+        .map(|item| (item.str().to_owned(), item.timestamp()))
+        .collect();
+    if !pulled_names.is_empty() {
+        history
+            .import_items(pulled_items)
+            .map_err(SyncError::Local)?;
+        state.pulled.extend(pulled_names);
+        state.save(&state_path).map_err(SyncError::Local)?;
+    }
+
+    // Push what was run since the last sync, except for what was just pulled. Items are stored
+    // with a resolution of seconds, so push the items of the current second again next time.
+    let now = time_to_seconds(SystemTime::now());
+    let items: Vec<_> = history
+        .items_run_after(time_from_seconds(state.pushed))
+        .into_iter()
+        .filter(|item| !pulled.contains(&(item.str().to_owned(), item.timestamp())))
+        .collect();
+    if !items.is_empty() {
+        let name = format!("{}{now}-{}{DELTA_SUFFIX}", own_prefix, std::process::id());
+        remote
+            .put(&name, &encrypt(&key, &encode_items(&items)))
+            .map_err(SyncError::Remote)?;
+    }
+    state.pushed = now - 1;
+    state.save(&state_path).map_err(SyncError::Local)?;
+    Ok(created_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Remote, decode_items, decrypt, encode_items, encrypt, sh_quote, time_from_seconds,
+    };
+    use crate::history::{HistoryItem, PersistenceMode};
+    use crate::prelude::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_sync_location() {
+        assert_eq!(
+            Remote::parse(L!("/mnt/sync/fish")),
+            Some(Remote::Directory(PathBuf::from("/mnt/sync/fish")))
+        );
+        assert_eq!(
+            Remote::parse(L!("file:///mnt/sync")),
+            Some(Remote::Directory(PathBuf::from("/mnt/sync")))
+        );
+        assert_eq!(
+            Remote::parse(L!("ssh://me@example.com:2222/~/fish-sync/")),
+            Some(Remote::Ssh {
+                destination: "me@example.com".into(),
+                port: Some("2222".into()),
+                dir: "~/fish-sync".into(),
+            })
+        );
+        assert_eq!(
+            Remote::parse(L!("ssh://example.com/srv/fish")),
+            Some(Remote::Ssh {
+                destination: "example.com".into(),
+                port: None,
+                dir: "/srv/fish".into(),
+            })
+        );
+        assert_eq!(
+            Remote::parse(L!("https://example.com/fish/")),
+            Some(Remote::Http("https://example.com/fish".into()))
+        );
+        assert_eq!(Remote::parse(L!("relative/path")), None);
+        assert_eq!(Remote::parse(L!("ssh://-oProxyCommand=x/dir")), None);
+        assert_eq!(Remote::parse(L!("ssh://example.com")), None);
+        assert_eq!(sh_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn test_sync_encryption() {
+        let key = [7; 32];
+        let mut item = HistoryItem::new(
+            L!("echo 'multi\nline'").to_owned(),
+            time_from_seconds(1_700_000_000),
+            PersistenceMode::Disk,
+        );
+        item.set_cwd(Some(L!("/home/me").to_owned()));
+        let delta = encrypt(&key, &encode_items(&[item]));
+        assert!(decrypt(&[8; 32], &delta).is_none());
+        let mut corrupted = delta.clone();
+        *corrupted.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&key, &corrupted).is_none());
+
+        let items = decode_items(&decrypt(&key, &delta).unwrap());
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].str(), "echo 'multi\nline'");
+        assert_eq!(items[0].timestamp(), time_from_seconds(1_700_000_000));
+        assert_eq!(items[0].cwd(), Some(L!("/home/me")));
+    }
+}
@@ -12,11 +12,16 @@ use std::{
 //
 //   - cmd: ssh blah blah blah
 //     when: 2348237
+//     cwd: /home/me
+//     status: 0
+//     duration: 1250
 //     paths:
 //       - /path/to/something
 //       - /path/to/something_else
 //
 //   Newlines are replaced by \n. Backslashes are replaced by \\.
+//   The working directory, exit status and duration (in milliseconds) are optional; versions which
+//   don't know them ignore them.
 
 /// Read one line, stripping off any newline, returning the number of bytes consumed.
 fn read_line(data: &[u8]) -> (usize, &[u8]) {
@@ -129,7 +134,7 @@ pub fn extract_prefix_and_unescape_yaml(line: &[u8]) -> Option<(Cow<'_, [u8]>, C
     Some((key, value))
 }
 
-fn time_from_seconds(offset: i64) -> SystemTime {
+pub fn time_from_seconds(offset: i64) -> SystemTime {
     if let Ok(n) = u64::try_from(offset) {
         UNIX_EPOCH + Duration::from_secs(n)
     } else {
@@ -137,6 +142,10 @@ fn time_from_seconds(offset: i64) -> SystemTime {
     }
 }
 
+fn parse_number<T: std::str::FromStr>(value: &[u8]) -> Option<T> {
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
 /// Decode an item via the fish 2.0 format.
 pub fn decode_item_fish_2_0(mut data: &[u8]) -> Option<HistoryItem> {
     let (advance, line) = read_line(data);
@@ -154,6 +163,9 @@ pub fn decode_item_fish_2_0(mut data: &[u8]) -> Option<HistoryItem> {
     let mut indent = None;
     let mut when = UNIX_EPOCH;
     let mut paths = Vec::new();
+    let mut cwd = None;
+    let mut status = None;
+    let mut duration = None;
     loop {
         let (advance, line) = read_line(data);
 
@@ -178,6 +190,12 @@ pub fn decode_item_fish_2_0(mut data: &[u8]) -> Option<HistoryItem> {
                     .and_then(|s| s.parse().ok())
                     .unwrap_or(0),
             );
+        } else if *key == *b"cwd" {
+            cwd = Some(bytes2wcstring(&value));
+        } else if *key == *b"status" {
+            status = parse_number(&value);
+        } else if *key == *b"duration" {
+            duration = parse_number(&value).map(Duration::from_millis);
         } else if *key == *b"paths" {
             // Read lines starting with " - " until we can't read any more.
             loop {
@@ -202,6 +220,8 @@ pub fn decode_item_fish_2_0(mut data: &[u8]) -> Option<HistoryItem> {
 
     let mut result = HistoryItem::new(cmd, when, PersistenceMode::Disk);
     result.set_required_paths(paths);
+    result.set_cwd(cwd);
+    result.set_result(status, duration);
     Some(result)
 }
 
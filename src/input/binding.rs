@@ -15,6 +15,7 @@ use std::{
         Mutex, MutexGuard,
         atomic::{AtomicU32, Ordering},
     },
+    time::{Duration, Instant},
 };
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -87,6 +88,16 @@ pub enum KeyNameStyle {
     RawEscapeSequence,
 }
 
+/// How quickly the keys of a sequence must follow each other for a binding to match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceTimeout {
+    /// The longest time allowed between two consecutive keys of the sequence.
+    pub delay: Duration,
+    /// Commands to evaluate instead if the sequence was started but not completed in time.
+    /// If empty, the keys typed so far are handled as if the binding did not exist.
+    pub fallback: Vec<WString>,
+}
+
 /// Struct representing a keybinding.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Binding {
@@ -104,6 +115,8 @@ pub struct Binding {
     pub key_name_style: KeyNameStyle,
     /// The file from which the binding was created, or None if not from a file.
     pub definition_file: Option<FilenameRef>,
+    /// If set, the keys after the first one must be typed within this timeout.
+    pub timeout: Option<SequenceTimeout>,
 }
 
 impl Binding {
@@ -115,6 +128,7 @@ impl Binding {
         sets_mode: Option<WString>,
         key_name_style: KeyNameStyle,
         definition_file: Option<FilenameRef>,
+        timeout: Option<SequenceTimeout>,
     ) -> Binding {
         static LAST_INPUT_MAP_SPEC_ORDER: AtomicU32 = AtomicU32::new(0);
         let specification_order = 1 + LAST_INPUT_MAP_SPEC_ORDER.fetch_add(1, Ordering::Relaxed);
@@ -130,6 +144,7 @@ impl Binding {
             sets_mode,
             key_name_style,
             definition_file,
+            timeout,
         }
     }
 
@@ -139,6 +154,14 @@ impl Binding {
     }
 }
 
+/// A named input function defined in fish script with `bind --define-function`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserInputFunction {
+    pub name: WString,
+    /// The commands to evaluate when the function is invoked.
+    pub script: WString,
+}
+
 /// Input function metadata. This list should be kept in sync with the key code list in
 /// input_common.rs.
 struct InputFunctionMetadata {
@@ -196,6 +219,7 @@ define_readline_cmds! {
     ("backward-kill-token", BackwardKillToken),
     ("backward-kill-word", BackwardKillWord),
     ("backward-path-component", BackwardPathComponent),
+    ("backward-placeholder", BackwardPlaceholder),
     ("backward-token", BackwardToken),
     ("backward-word", BackwardWord),
     ("backward-word-end", BackwardWordEnd),
@@ -216,6 +240,7 @@ define_readline_cmds! {
     ("down-line", DownLine),
     ("downcase-selection", DowncaseSelection),
     ("downcase-word", DowncaseWord),
+    ("edit-command-buffer", EditCommandBuffer),
     ("end-of-buffer", EndOfBuffer),
     ("end-of-history", EndOfHistory),
     ("end-of-line", EndOfLine),
@@ -233,6 +258,7 @@ define_readline_cmds! {
     ("forward-jump", ForwardJump),
     ("forward-jump-till", ForwardJumpTill),
     ("forward-path-component", ForwardPathComponent),
+    ("forward-placeholder", ForwardPlaceholder),
     ("forward-single-char", ForwardSingleChar),
     ("forward-token", ForwardToken),
     ("forward-word", ForwardWordEmacs),
@@ -240,12 +266,18 @@ define_readline_cmds! {
     ("forward-word-vi", ForwardWordVi),
     ("get-key", GetKey),
     ("history-delete", HistoryDelete),
+    ("history-directory-search-backward", HistoryDirectorySearchBackward),
+    ("history-directory-search-forward", HistoryDirectorySearchForward),
+    ("history-finder", HistoryFinder),
     ("history-last-token-search-backward", HistoryLastTokenSearchBackward),
     ("history-last-token-search-forward", HistoryLastTokenSearchForward),
     ("history-pager", HistoryPager),
     ("history-pager-delete", HistoryPagerDelete),
     ("history-prefix-search-backward", HistoryPrefixSearchBackward),
     ("history-prefix-search-forward", HistoryPrefixSearchForward),
+    ("history-project-pager", HistoryProjectPager),
+    ("history-project-search-backward", HistoryProjectSearchBackward),
+    ("history-project-search-forward", HistoryProjectSearchForward),
     ("history-search-backward", HistorySearchBackward),
     ("history-search-forward", HistorySearchForward),
     ("history-token-search-backward", HistoryTokenSearchBackward),
@@ -268,8 +300,12 @@ define_readline_cmds! {
     ("kill-whole-line", KillWholeLine),
     ("kill-word", KillWordEmacs),
     ("kill-word-vi", KillWordVi),
+    ("move-to-mouse", MoveToMouse),
     ("nextd-or-forward-word", NextdOrForwardWordEmacs),
     ("or", FuncOr),
+    ("pager-page-down", PagerPageDown),
+    ("pager-page-up", PagerPageUp),
+    ("pager-toggle-mark", PagerToggleMark),
     ("pager-toggle-search", PagerToggleSearch),
     ("prevd-or-backward-word", PrevdOrBackwardWord),
     ("redo", Redo),
@@ -287,6 +323,8 @@ define_readline_cmds! {
     ("transpose-chars", TransposeChars),
     ("transpose-words", TransposeWords),
     ("undo", Undo),
+    ("undo-tree-next-branch", UndoTreeNextBranch),
+    ("undo-tree-prev-branch", UndoTreePrevBranch),
     ("up-line", UpLine),
     ("upcase-selection", UpcaseSelection),
     ("upcase-word", UpcaseWord),
@@ -299,6 +337,8 @@ define_readline_cmds! {
 pub struct BindingSet {
     bindings: Vec<Binding>,
     preset_bindings: Vec<Binding>,
+    /// User-defined input functions, sorted by name.
+    functions: Vec<UserInputFunction>,
 }
 
 impl BindingSet {
@@ -306,6 +346,7 @@ impl BindingSet {
         Self {
             bindings: Vec::new(),
             preset_bindings: Vec::new(),
+            functions: Vec::new(),
         }
     }
 }
@@ -316,6 +357,114 @@ pub fn bindings() -> MutexGuard<'static, BindingSet> {
     BINDINGS.lock().unwrap()
 }
 
+/// Whether any binding has ever used a key release. Until then, we drop release events as soon as
+/// they are decoded, so they don't get in the way of anything else.
+static HAVE_RELEASE_BINDINGS: RelaxedAtomicBool = RelaxedAtomicBool::new(false);
+
+pub(crate) fn have_release_bindings() -> bool {
+    HAVE_RELEASE_BINDINGS.load()
+}
+
+/// A raw byte sequence which a terminal sends for a key, declared by the user for terminals whose
+/// sequences the decoder doesn't understand or gets wrong.
+#[derive(Clone, Debug)]
+pub struct KeyTranslation {
+    /// The value of $TERM for which this translation applies.
+    pub term: WString,
+    pub sequence: Vec<u8>,
+    pub key: Key,
+}
+
+/// The result of looking up a partially read sequence among the key translations.
+#[derive(Debug, Default)]
+pub(crate) struct KeyTranslationMatch {
+    /// The key whose sequence is exactly the one looked up.
+    pub key: Option<Key>,
+    /// Whether there are longer sequences starting with the one looked up.
+    pub extendable: bool,
+}
+
+/// The key translations, kept separately from the bindings because they are consulted while
+/// reading input, when the binding set may be locked.
+#[derive(Default)]
+pub struct KeyTranslationSet {
+    /// The current value of $TERM.
+    term: WString,
+    /// Sorted by term and then by sequence.
+    translations: Vec<KeyTranslation>,
+}
+
+/// Access the singleton key translation set.
+pub fn key_translations() -> MutexGuard<'static, KeyTranslationSet> {
+    static TRANSLATIONS: Mutex<KeyTranslationSet> = Mutex::new(KeyTranslationSet {
+        term: WString::new(),
+        translations: Vec::new(),
+    });
+    TRANSLATIONS.lock().unwrap()
+}
+
+impl KeyTranslationSet {
+    fn index(&self, term: &wstr, sequence: &[u8]) -> Result<usize, usize> {
+        self.translations.binary_search_by(|t| {
+            (t.term.as_utfstr(), t.sequence.as_slice()).cmp(&(term, sequence))
+        })
+    }
+
+    /// Set the terminal whose translations are used by the decoder.
+    pub fn set_term(&mut self, term: &wstr) {
+        self.term = term.to_owned();
+    }
+
+    /// Return the terminal whose translations are used by the decoder.
+    pub fn term(&self) -> &wstr {
+        &self.term
+    }
+
+    /// Add a translation, replacing any earlier one for the same terminal and sequence.
+    pub fn add(&mut self, translation: KeyTranslation) {
+        match self.index(&translation.term, &translation.sequence) {
+            Ok(idx) => self.translations[idx] = translation,
+            Err(idx) => self.translations.insert(idx, translation),
+        }
+    }
+
+    /// Erase the translation of the sequence for the given terminal. Returns false if there was
+    /// none.
+    pub fn erase(&mut self, term: &wstr, sequence: &[u8]) -> bool {
+        match self.index(term, sequence) {
+            Ok(idx) => {
+                self.translations.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Erase all translations for the given terminal, or for all terminals.
+    pub fn clear(&mut self, term: Option<&wstr>) {
+        self.translations
+            .retain(|t| term.is_some_and(|term| t.term != term));
+    }
+
+    /// Returns all translations, sorted by terminal and sequence.
+    pub fn all(&self) -> &[KeyTranslation] {
+        &self.translations
+    }
+
+    /// Look up a sequence read from the terminal among the translations for the current terminal.
+    pub(crate) fn lookup(&self, sequence: &[u8]) -> KeyTranslationMatch {
+        let mut result = KeyTranslationMatch::default();
+        for t in self.translations.iter().filter(|t| t.term == self.term) {
+            if t.sequence == sequence {
+                result.key = Some(t.key);
+            } else if t.sequence.starts_with(sequence) {
+                result.extendable = true;
+            }
+        }
+        result
+    }
+}
+
 /// Return the current bind mode.
 pub fn input_get_bind_mode(vars: &dyn Environment) -> WString {
     if let Some(mode) = vars.get(FISH_BIND_MODE_VAR) {
@@ -358,7 +507,11 @@ impl BindingSet {
         sets_mode: Option<WString>,
         user: bool,
         definition_file: Option<FilenameRef>,
+        timeout: Option<SequenceTimeout>,
     ) {
+        if sequence.iter().any(|key| key.release) {
+            HAVE_RELEASE_BINDINGS.store(true);
+        }
         // Update any existing binding with this sequence.
         // FIXME: this makes adding multiple bindings quadratic.
         let ml = if user {
@@ -371,6 +524,7 @@ impl BindingSet {
                 m.commands = commands;
                 m.sets_mode = sets_mode;
                 m.definition_file = definition_file;
+                m.timeout = timeout;
                 return;
             }
         }
@@ -383,6 +537,7 @@ impl BindingSet {
             sets_mode,
             key_name_style,
             definition_file,
+            timeout,
         );
         binding_insert_sorted(ml, new_binding);
     }
@@ -407,6 +562,7 @@ impl BindingSet {
             sets_mode,
             user,
             definition_file,
+            None,
         );
     }
 }
@@ -486,9 +642,16 @@ struct EventQueuePeeker<'q, Queuer: InputEventQueuer + ?Sized> {
     /// The list of events which have been dequeued.
     peeked: Vec<CharEvent>,
 
+    /// When each of the peeked events was dequeued.
+    peeked_at: Vec<Instant>,
+
     /// If set, then some previous timed event timed out.
     had_timeout: bool,
 
+    /// If set, the last key failed to match because it was not typed within the timeout of the
+    /// binding we were trying.
+    sequence_timed_out: bool,
+
     /// The current index. This never exceeds peeked.len().
     idx: usize,
     /// The current index within a the raw characters within a single key event.
@@ -502,7 +665,9 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
     fn new(event_queue: &'q mut Queuer) -> Self {
         EventQueuePeeker {
             peeked: Vec::new(),
+            peeked_at: Vec::new(),
             had_timeout: false,
+            sequence_timed_out: false,
             idx: 0,
             subidx: 0,
             event_queue,
@@ -519,6 +684,7 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
         if self.idx == self.peeked.len() {
             let event = self.event_queue.readch();
             self.peeked.push(event);
+            self.peeked_at.push(Instant::now());
         }
         let res = self.peeked[self.idx].clone();
         self.idx += 1;
@@ -528,11 +694,14 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
 
     /// Check if the next event is the given character. This advances the index on success only.
     /// If `escaped` is set, then return false if this (or any other) character had a timeout.
+    /// If `delay` is set, then return false if the event did not arrive within that time of the
+    /// previous one.
     fn next_is_char(
         &mut self,
         style: &KeyNameStyle,
         key: Key,
         escaped: bool,
+        delay: Option<Duration>,
     ) -> Option<KeyMatchQuality> {
         assert!(
             self.idx <= self.peeked.len(),
@@ -542,10 +711,39 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
         if escaped && self.had_timeout {
             return None;
         }
+        // The time by which the next event must have arrived, if any.
+        // This only applies between events, not within the raw sequence of a single event.
+        let deadline = match delay {
+            Some(delay) if self.subidx == 0 && self.idx > 0 => {
+                Some(self.peeked_at[self.idx - 1] + delay)
+            }
+            _ => None,
+        };
+        if deadline.is_some_and(|deadline| {
+            self.peeked_at
+                .get(self.idx)
+                .is_some_and(|&arrival| arrival > deadline)
+        }) {
+            self.sequence_timed_out = true;
+            return None;
+        }
         // Grab a new event if we have exhausted what we have already peeked.
         // Use either readch or readch_timed, per our param.
         if self.idx == self.peeked.len() {
-            let newevt = if escaped {
+            let newevt = if let Some(deadline) = deadline {
+                flog!(reader, "readch timed sequence key with binding timeout");
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                match self
+                    .event_queue
+                    .readch_timed(usize::try_from(remaining.as_millis()).unwrap())
+                {
+                    Some(evt) => evt,
+                    None => {
+                        self.sequence_timed_out = true;
+                        return None;
+                    }
+                }
+            } else if escaped {
                 flog!(reader, "reading timed escape");
                 match self.event_queue.readch_timed_esc() {
                     Some(evt) => evt,
@@ -566,16 +764,22 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
             };
             flog!(reader, format!("adding peeked {:?}", newevt));
             self.peeked.push(newevt);
+            self.peeked_at.push(Instant::now());
         }
         // Now we have peeked far enough; check the event.
         // If it matches the char, then increment the index.
         let evt = &self.peeked[self.idx];
         let kevt = evt.get_key()?;
+        if kevt.key.release && !key.release && self.idx > 0 && self.subidx == 0 {
+            // Releasing the previous key of a sequence should not interrupt it.
+            self.idx += 1;
+            return self.next_is_char(style, key, escaped, delay);
+        }
         if kevt.seq == L!("\x1b") && key.modifiers == Modifiers::ALT {
             self.idx += 1;
             self.subidx = 0;
             flog!(reader, "matched delayed escape prefix in alt sequence");
-            return self.next_is_char(style, Key::from_raw(key.codepoint), true);
+            return self.next_is_char(style, Key::from_raw(key.codepoint), true, None);
         }
         if *style == KeyNameStyle::Plain {
             let result = match_key_event_to_key(&kevt.key, &key);
@@ -611,7 +815,7 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
                     self.idx += 1;
                     self.subidx = 0;
                     flog!(reader, "matched escape prefix in raw escape sequence");
-                    return self.next_is_char(style, Key::from_raw(key.codepoint), true);
+                    return self.next_is_char(style, Key::from_raw(key.codepoint), true, None);
                 } else if actual_seq
                     .get(self.subidx + 1)
                     .copied()
@@ -631,12 +835,21 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
         None
     }
 
+    /// Return true if the first peeked event is a key release.
+    fn next_is_release(&self) -> bool {
+        self.peeked
+            .first()
+            .and_then(|evt| evt.get_key())
+            .is_some_and(|kevt| kevt.key.release)
+    }
+
     /// Consume all events up to the current index.
     /// Remaining events are returned to the queue.
     fn consume(mut self) {
         // Note this deliberately takes 'self' by value.
         self.event_queue.insert_front(self.peeked.drain(self.idx..));
         self.peeked.clear();
+        self.peeked_at.clear();
         self.idx = 0;
         self.subidx = 0;
     }
@@ -653,13 +866,16 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
     fn restart(&mut self) {
         self.idx = 0;
         self.subidx = 0;
+        self.sequence_timed_out = false;
     }
 
     /// Return true if this `peeker` matches a given sequence of char events given by `str`.
+    /// If `delay` is set, each key after the first must follow the previous one within that time.
     fn try_peek_sequence(
         &mut self,
         style: &KeyNameStyle,
         seq: &[Key],
+        delay: Option<Duration>,
         quality: &mut Vec<KeyMatchQuality>,
     ) -> bool {
         assert!(
@@ -671,7 +887,7 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
             // If we just read an escape, we need to add a timeout for the next char,
             // to distinguish between the actual escape key and an "alt"-modifier.
             let escaped = *style != KeyNameStyle::Plain && prev == Some(Key::from_raw(key::ESCAPE));
-            let Some(spec) = self.next_is_char(style, *key, escaped) else {
+            let Some(spec) = self.next_is_char(style, *key, escaped, delay) else {
                 return false;
             };
             quality.push(spec);
@@ -724,7 +940,8 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
             }
 
             // flog!(reader, "trying binding", format!("{:?}", m));
-            if self.try_peek_sequence(&m.key_name_style, &m.seq, &mut quality) {
+            let delay = m.timeout.as_ref().map(|timeout| timeout.delay);
+            if self.try_peek_sequence(&m.key_name_style, &m.seq, delay, &mut quality) {
                 // // A binding for just escape should also be deferred
                 // // so escape sequences take precedence.
                 let is_escape = m.seq == vec![Key::from_raw(key::ESCAPE)];
@@ -745,6 +962,19 @@ impl<'q, Queuer: InputEventQueuer + ?Sized> EventQueuePeeker<'q, Queuer> {
                         subidx: self.subidx,
                     });
                 }
+            } else if self.sequence_timed_out {
+                if let Some(timeout) = m.timeout.as_ref().filter(|t| !t.fallback.is_empty()) {
+                    // The sequence was started but not completed in time.
+                    // Run the fallback for the keys typed so far, without changing the mode.
+                    flog!(reader, "binding timed out after", quality.len(), "keys");
+                    let mut fallback = m.clone();
+                    fallback.seq.truncate(quality.len());
+                    fallback.commands.clone_from(&timeout.fallback);
+                    fallback.sets_mode = None;
+                    fallback.timeout = None;
+                    self.sequence_timed_out = false;
+                    return Some(fallback);
+                }
             }
             quality.clear();
             self.restart();
@@ -801,7 +1031,7 @@ impl<'a> Reader<'a> {
                             use ImplicitEvent::*;
                             match evt {
                                 Key(_) => true,
-                                Implicit(Eof) => true,
+                                Implicit(Eof | DeadlineElapsed) => true,
                                 Readline(_) | Command(_) | Implicit(_) | QueryResult(_) => false,
                             }
                         });
@@ -870,11 +1100,21 @@ impl<'a> Reader<'a> {
         // Check for ordinary bindings.
         let bindings = bindings();
         if let Some(binding) = peeker.find_binding(&bindings) {
+            if binding.is_generic() && peeker.next_is_release() {
+                // Key releases only do something if they are bound explicitly.
+                flog!(reader, "ignoring unbound key release");
+                std::mem::drop(bindings);
+                peeker.restart();
+                let _ = peeker.next();
+                peeker.consume();
+                return;
+            }
             flog!(
                 reader,
                 format!("Found binding {:?} from {:?}", &binding, &peeker.peeked)
             );
             peeker.consume();
+            std::mem::drop(bindings);
             self.binding_execute(&binding);
             return;
         }
@@ -917,14 +1157,24 @@ impl<'a> Reader<'a> {
 
     /// Perform the action of the specified binding.
     fn binding_execute(&mut self, m: &Binding) {
-        let has_command = m
-            .commands
+        // User-defined input functions run their script in place of their name.
+        let commands: Vec<WString> = {
+            let bindings = bindings();
+            m.commands
+                .iter()
+                .map(|cmd| match bindings.get_function(cmd) {
+                    Some(function) => function.script.clone(),
+                    None => cmd.clone(),
+                })
+                .collect()
+        };
+        let has_command = commands
             .iter()
             .any(|cmd| input_function_get_code(cmd).is_none());
         if has_command {
             self.push_front(CharEvent::from_check_exit());
         }
-        for cmd in m.commands.iter().rev() {
+        for cmd in commands.iter().rev() {
             let evt = match input_function_get_code(cmd) {
                 Some(code) => {
                     self.function_push_args(code);
@@ -953,6 +1203,35 @@ impl<'a> Reader<'a> {
         }
     }
 
+    /// Perform the action bound to a terminal event like focus changes in the current mode, if any.
+    /// The arguments are passed to each command which is not an input function.
+    /// Return true if there was a binding.
+    pub(crate) fn event_binding_execute(&mut self, event_key: Key, args: &[WString]) -> bool {
+        let seq = [event_key];
+        let bind_mode = self.get_bind_mode();
+        let bindings = bindings();
+        let binding = [true, false].into_iter().find_map(|user| {
+            let found = bindings.get(&seq, Some(&bind_mode), user);
+            found.first().map(|&binding| binding.clone())
+        });
+        let Some(mut binding) = binding else {
+            return false;
+        };
+        flog!(reader, format!("Found binding {:?} for event", &binding));
+        for cmd in &mut binding.commands {
+            if bindings.is_input_function(cmd) {
+                continue;
+            }
+            for arg in args {
+                cmd.push(' ');
+                cmd.push_utfstr(&escape(arg));
+            }
+        }
+        std::mem::drop(bindings);
+        self.binding_execute(&binding);
+        true
+    }
+
     fn function_push_arg(&mut self, arg: char) {
         self.get_input_data_mut().input_function_args.push(arg);
     }
@@ -1039,6 +1318,53 @@ impl BindingSet {
         result
     }
 
+    /// Binary search for the user-defined input function with the given name.
+    fn function_index(&self, name: &wstr) -> Result<usize, usize> {
+        self.functions
+            .binary_search_by(|f| f.name.as_utfstr().cmp(name))
+    }
+
+    /// Define an input function, replacing any earlier definition with the same name.
+    pub fn define_function(&mut self, function: UserInputFunction) {
+        match self.function_index(&function.name) {
+            Ok(idx) => self.functions[idx] = function,
+            Err(idx) => self.functions.insert(idx, function),
+        }
+    }
+
+    /// Erase the input function with the given name. Returns false if there was none.
+    pub fn erase_function(&mut self, name: &wstr) -> bool {
+        match self.function_index(name) {
+            Ok(idx) => {
+                self.functions.remove(idx);
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Erase all user-defined input functions.
+    pub fn clear_functions(&mut self) {
+        self.functions.clear();
+    }
+
+    /// Returns the user-defined input function with the given name.
+    pub fn get_function(&self, name: &wstr) -> Option<&UserInputFunction> {
+        self.function_index(name)
+            .ok()
+            .map(|idx| &self.functions[idx])
+    }
+
+    /// Returns all user-defined input functions, sorted by name.
+    pub fn functions(&self) -> &[UserInputFunction] {
+        &self.functions
+    }
+
+    /// Returns whether the name refers to a built-in or user-defined input function.
+    pub fn is_input_function(&self, name: &wstr) -> bool {
+        input_function_get_code(name).is_some() || self.get_function(name).is_some()
+    }
+
     /// Returns the command bound to the specified bind mode.
     ///
     /// If bind_mode is None, then binds from all modes are returned.
@@ -1124,11 +1450,12 @@ pub(super) use mock::MockInputEventQueuer;
 mod tests {
     use super::{
         BindingSet, EventQueuePeeker, KeyMatchQuality, KeyNameStyle, MockInputEventQueuer,
-        match_key_event_to_key,
+        SequenceTimeout, match_key_event_to_key,
     };
     use crate::input::{CharEvent, InputEventQueuer as _, KeyEvent};
     use crate::key::{Key, Modifiers};
     use crate::prelude::*;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_match_key_event_to_key() {
@@ -1257,4 +1584,98 @@ mod tests {
         assert_eq!(binding.unwrap().commands, ["down-line"]);
         peeker.restart();
     }
+
+    #[test]
+    fn test_sequence_timeout() {
+        let mut input = MockInputEventQueuer::new();
+        let seq: Vec<Key> = "jk".chars().map(Key::from_raw).collect();
+
+        let mut bindings = BindingSet::default();
+        bindings.add(
+            seq.clone(),
+            KeyNameStyle::Plain,
+            vec![L!("chord").to_owned()],
+            input.get_bind_mode(),
+            None,
+            true,
+            None,
+            Some(SequenceTimeout {
+                delay: Duration::from_millis(100),
+                fallback: vec![L!("fallback").to_owned()],
+            }),
+        );
+
+        // Keys typed in quick succession match the binding.
+        let events = || {
+            seq.iter()
+                .map(|&key| CharEvent::from_key(KeyEvent::from(key)))
+        };
+        let mut peeker = EventQueuePeeker::new(&mut input);
+        peeker.peeked.extend(events());
+        let now = Instant::now();
+        peeker
+            .peeked_at
+            .extend([now, now + Duration::from_millis(50)]);
+        let binding = peeker.find_binding(&bindings).unwrap();
+        assert_eq!(binding.commands, ["chord"]);
+        peeker.restart();
+
+        // If the second key came too late, the fallback replaces the first one.
+        peeker.peeked_at[1] = now + Duration::from_millis(150);
+        let binding = peeker.find_binding(&bindings).unwrap();
+        assert_eq!(binding.commands, ["fallback"]);
+        assert_eq!(binding.seq, seq[..1]);
+        assert_eq!(peeker.idx, 1);
+        peeker.restart();
+    }
+
+    #[test]
+    fn test_key_release() {
+        let mut input = MockInputEventQueuer::new();
+        let release = |codepoint| {
+            let mut key = Key::from_raw(codepoint);
+            key.release = true;
+            key
+        };
+
+        let mut bindings = BindingSet::default();
+        for (seq, command) in [
+            (vec![Key::from_raw('x'), Key::from_raw('y')], "chord"),
+            (vec![release('z')], "released"),
+        ] {
+            bindings.add(
+                seq,
+                KeyNameStyle::Plain,
+                vec![WString::from_str(command)],
+                input.get_bind_mode(),
+                None,
+                true,
+                None,
+                None,
+            );
+        }
+
+        // Releasing the first key does not interrupt a sequence.
+        let mut peeker = EventQueuePeeker::new(&mut input);
+        for key in [Key::from_raw('x'), release('x'), Key::from_raw('y')] {
+            peeker.peeked.push(CharEvent::from_key(KeyEvent::from(key)));
+            peeker.peeked_at.push(Instant::now());
+        }
+        let binding = peeker.find_binding(&bindings).unwrap();
+        assert_eq!(binding.commands, ["chord"]);
+        assert_eq!(peeker.idx, 3);
+        peeker.restart();
+        peeker.consume();
+
+        // A release only matches a release binding.
+        let mut peeker = EventQueuePeeker::new(&mut input);
+        peeker
+            .peeked
+            .push(CharEvent::from_key(KeyEvent::from(release('z'))));
+        peeker.peeked_at.push(Instant::now());
+        let binding = peeker.find_binding(&bindings).unwrap();
+        assert_eq!(binding.commands, ["released"]);
+        peeker.restart();
+        peeker.consume();
+    }
 }
@@ -1,5 +1,5 @@
 use super::{
-    binding::match_key_event_to_key,
+    binding::{have_release_bindings, key_translations, match_key_event_to_key},
     input::{
         CharEvent, ImplicitEvent, InputEventQueuer, InputEventTrigger, KeyEvent, QueryResponse,
         QueryResultEvent, is_event_blocked_when_querying, next_input_event, stop_query,
@@ -13,9 +13,13 @@ use crate::{
         self, Key, Modifiers, ViewportPosition, alt, canonicalize_control_char,
         canonicalize_keyed_control_char, char_to_symbol, function_key, shift,
     },
+    terminal::base64_decode,
     tty_handoff::{
-        SCROLL_CONTENT_UP_TERMINFO_CODE, TERMINAL_OS_NAME, XTGETTCAP_QUERY_OS_NAME, XTVERSION,
-        maybe_set_kitty_keyboard_capability, maybe_set_scroll_content_up_capability,
+        CLIPBOARD_TERMINFO_CODE, SCROLL_CONTENT_UP_TERMINFO_CODE, SYNCHRONIZED_OUTPUT_MODE,
+        TERMINAL_OS_NAME, TRUECOLOR_TERMINFO_CODES, XTGETTCAP_QUERY_OS_NAME, XTVERSION,
+        maybe_set_clipboard_capability, maybe_set_kitty_keyboard_capability,
+        maybe_set_scroll_content_up_capability, maybe_set_synchronized_output_capability,
+        maybe_set_truecolor_capability,
     },
 };
 use fish_widestring::{WString, bytes2wcstring, encode_byte_to_char, fish_reserved_codepoint};
@@ -43,6 +47,12 @@ impl<T: InputEventQueuer + ?Sized> InputEventQueuerExt for T {}
 
 trait InputEventQueuerExt: InputEventQueuer {
     fn on_byte_read(&mut self, read_byte: u8) {
+        if !self.paste_is_buffering() {
+            if let Some(evt) = self.parse_key_translation(read_byte) {
+                self.enqueue_event(evt);
+                return;
+            }
+        }
         let mut buffer = vec![read_byte];
         let escape_sequence = if read_byte == b'\x1b' {
             self.parse_escape_sequence(&mut buffer, false)
@@ -57,12 +67,18 @@ trait InputEventQueuerExt: InputEventQueuer {
             }
         };
         let (decoded_buffer, mut is_err) = match decode_utf8_at_least_one(&mut buffer, |buffer| {
-            buffer.push(
-                match next_input_event(self.get_in_fd(), self.get_ioport_fd(), Timeout::Forever) {
-                    InputEventTrigger::Byte(b) => b,
-                    _ => 0,
-                },
-            );
+            let next = self
+                .get_input_data_mut()
+                .unread_bytes
+                .pop_front()
+                .unwrap_or_else(|| {
+                    match next_input_event(self.get_in_fd(), self.get_ioport_fd(), Timeout::Forever)
+                    {
+                        InputEventTrigger::Byte(b) => b,
+                        _ => 0,
+                    }
+                });
+            buffer.push(next);
         }) {
             Ok(decoded) => (decoded, false),
             Err(evt) => {
@@ -118,6 +134,36 @@ trait InputEventQueuerExt: InputEventQueuer {
         }
     }
 
+    /// Match the input against the user's key translations for the current terminal, which take
+    /// precedence over the built-in decoding. Returns None if there is no match, in which case
+    /// any bytes read after the given one are left to be decoded as usual.
+    fn parse_key_translation(&mut self, read_byte: u8) -> Option<CharEvent> {
+        let mut buffer = vec![read_byte];
+        let mut matched = None;
+        loop {
+            let found = key_translations().lookup(&buffer);
+            if let Some(key) = found.key {
+                matched = Some((buffer.len(), key));
+            }
+            if !found.extendable || self.read_sequence_byte(&mut buffer).is_none() {
+                break;
+            }
+        }
+        let len = matched.map_or(1, |(len, _key)| len);
+        let unread = &mut self.get_input_data_mut().unread_bytes;
+        for &b in buffer[len..].iter().rev() {
+            unread.push_front(b);
+        }
+        let (_len, key) = matched?;
+        buffer.truncate(len);
+        let seq = bytes2wcstring(&buffer);
+        flog!(
+            reader,
+            format!("Translated sequence {:?} to key {}", seq, key)
+        );
+        Some(CharEvent::from_key_seq(KeyEvent::from(key), seq))
+    }
+
     fn enqueue_event(&mut self, evt: CharEvent) {
         if !self.is_blocked_querying() || !is_event_blocked_when_querying(&evt) {
             self.push_front(evt);
@@ -273,20 +319,31 @@ trait InputEventQueuerExt: InputEventQueuer {
             if caps_lock && modifiers == Modifiers::SHIFT && !key.to_uppercase().eq(Some(key)) {
                 modifiers.shift = false;
             }
-            KeyEvent::new_with(
+            let mut key_evt = KeyEvent::new_with(
                 modifiers,
                 explicit_modifiers,
                 key,
                 shifted_key,
                 base_layout_key,
-            )
+            );
+            // The event type is 1 for press, 2 for repeat and 3 for release.
+            // Repeats are treated like presses.
+            key_evt.key.release = params[1][1] == 3;
+            key_evt
         };
         let masked_key = |key: char| kitty_key(key, None, None);
 
         let key = match c {
             b'$' => {
                 if next_char(self) == b'y' {
-                    // DECRPM/DECRQM
+                    // DECRPM, the answer to DECRQM. The second parameter is 0 if the mode is not
+                    // recognized, 1 or 2 if it is set or reset, and 3 or 4 if it can't be changed.
+                    if private_mode == Some(b'?')
+                        && params[0][0] == SYNCHRONIZED_OUTPUT_MODE
+                        && matches!(params[1][0], 1 | 2)
+                    {
+                        maybe_set_synchronized_output_capability();
+                    }
                     return None;
                 }
                 match params[0][0] {
@@ -336,11 +393,23 @@ trait InputEventQueuerExt: InputEventQueuer {
                 };
                 let position = ViewportPosition { x, y };
                 let (modifiers, _caps_lock) = parse_mask((button >> 2) & 0x07);
-                let code = button & 0x43;
-                if code != 0 || c != b'M' || modifiers.is_some() {
+                // Button releases carry no information we use.
+                if c != b'M' {
                     return None;
                 }
-                return Some(CharEvent::Implicit(ImplicitEvent::MouseLeft(position)));
+                let motion = button & 0x20 != 0;
+                let codepoint = match button & 0x43 {
+                    0..=2 if motion => key::MOUSE_DRAG,
+                    0 => key::MOUSE_LEFT_CLICK,
+                    1 => key::MOUSE_MIDDLE_CLICK,
+                    2 => key::MOUSE_RIGHT_CLICK,
+                    0x40 => key::SCROLL_UP,
+                    0x41 => key::SCROLL_DOWN,
+                    // Motion without a button held, or horizontal scrolling.
+                    _ => return None,
+                };
+                let key = Key::new(modifiers, codepoint);
+                return Some(CharEvent::Implicit(ImplicitEvent::Mouse(key, position)));
             }
             b't' => {
                 flog!(reader, "mouse event");
@@ -425,6 +494,10 @@ trait InputEventQueuerExt: InputEventQueuer {
             },
             b'c' if private_mode == Some(b'?') => {
                 flog!(reader, "Received Primary Device Attribute response");
+                // Attribute 52 advertises clipboard access.
+                if params[1..].iter().any(|param| param[0] == 52) {
+                    maybe_set_clipboard_capability();
+                }
                 return Some(query_response(QueryResponse::PrimaryDeviceAttribute));
             }
             b'n' if private_mode == Some(b'?') && params[0] == [997, 0, 0, 0] => {
@@ -500,6 +573,9 @@ trait InputEventQueuerExt: InputEventQueuer {
             }
             _ => return None,
         };
+        if key.release && !have_release_bindings() {
+            return None;
+        }
         char_event(buffer, key)
     }
 
@@ -593,6 +669,13 @@ trait InputEventQueuerExt: InputEventQueuer {
         assert_eq!(buffer, osc_prefix);
         self.read_until_sequence_terminator(buffer, /*allow_bel=*/ true)?;
         let buffer = &buffer[osc_prefix.len()..];
+        if let Some(selection) = buffer.strip_prefix(b"52;") {
+            // The clipboard contents, after the list of selections they were taken from.
+            let start = selection.iter().position(|&c| c == b';')? + 1;
+            flog!(reader, "Received clipboard contents");
+            let contents = base64_decode(&selection[start..])?;
+            return Some(query_response(QueryResponse::Clipboard(contents)));
+        }
         let buffer = buffer.strip_prefix(b"11;")?;
         let c = xterm_color::Color::parse(buffer).ok()?;
         flog!(reader, format!("Received background color {c:?}"));
@@ -656,6 +739,13 @@ trait InputEventQueuerExt: InputEventQueuer {
         };
         if key == SCROLL_CONTENT_UP_TERMINFO_CODE.as_bytes() {
             maybe_set_scroll_content_up_capability();
+        } else if key == CLIPBOARD_TERMINFO_CODE.as_bytes() {
+            maybe_set_clipboard_capability();
+        } else if TRUECOLOR_TERMINFO_CODES
+            .iter()
+            .any(|code| key == code.as_bytes())
+        {
+            maybe_set_truecolor_capability();
         } else if key == XTGETTCAP_QUERY_OS_NAME.as_bytes() {
             if let Some(value) = value {
                 TERMINAL_OS_NAME.get_or_init(|| Some(bytes2wcstring(&value)));
@@ -667,7 +757,7 @@ trait InputEventQueuerExt: InputEventQueuer {
 
 fn decode_utf8_at_least_one(
     buffer: &mut Vec<u8>,
-    on_incomplete: impl Fn(&mut Vec<u8>),
+    mut on_incomplete: impl FnMut(&mut Vec<u8>),
 ) -> Result<WString, CharEvent> {
     let mut decoded_input = WString::new();
     loop {
@@ -817,10 +907,10 @@ mod tests {
     use super::parse_hex;
     use crate::{
         input::{
-            CharEvent, KeyEvent, MockInputEventQueuer, QueryResponse,
+            CharEvent, ImplicitEvent, KeyEvent, MockInputEventQueuer, QueryResponse,
             decode::{InputEventQueuerExt as _, query_response},
         },
-        key::{self, Key, Modifiers, alt},
+        key::{self, Key, Modifiers, ViewportPosition, alt, ctrl},
     };
 
     #[test]
@@ -867,6 +957,20 @@ mod tests {
             b"\x1b\x1b[97;3u",
             &[legacy_escape(), e(kitty_alt_a, "\x1b[97;3u")]
         );
+        // Repeats are treated like presses.
+        validate!(b"\x1b[97;1:2u", &[e(kitty_a, "\x1b[97;1:2u")]);
+        let mouse =
+            |key, x, y| CharEvent::Implicit(ImplicitEvent::Mouse(key, ViewportPosition { x, y }));
+        validate!(
+            b"\x1b[<0;5;3M",
+            &[mouse(Key::from_raw(key::MOUSE_LEFT_CLICK), 4, 2)]
+        );
+        validate!(b"\x1b[<0;5;3m", &[]);
+        validate!(
+            b"\x1b[<32;6;3M",
+            &[mouse(Key::from_raw(key::MOUSE_DRAG), 5, 2)]
+        );
+        validate!(b"\x1b[<80;1;1M", &[mouse(ctrl(key::SCROLL_UP), 0, 0)]);
         validate!(
             b"\x1b\x1b]11;rgb:ffff/ffff/ffff\x1b\\",
             &[
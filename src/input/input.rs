@@ -1,6 +1,7 @@
 use super::{
     binding::ReadlineCmd,
     decode::{DisplayBytes, LONG_READ_TIMEOUT, on_byte_read},
+    recording::record_input_byte,
 };
 use crate::{
     common::{WSL, is_windows_subsystem_for_linux, shell_modes},
@@ -20,7 +21,7 @@ use std::{
     collections::VecDeque,
     os::fd::{BorrowedFd, RawFd},
     sync::atomic::{AtomicUsize, Ordering},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 #[derive(Clone, Debug, PartialEq)]
@@ -55,12 +56,21 @@ pub enum ImplicitEvent {
     FocusIn,
     /// Our terminal window lost focus.
     FocusOut,
-    /// Mouse left click.
-    MouseLeft(ViewportPosition),
+    /// A bracketed paste started.
+    PasteStart,
+    /// The text of a bracketed paste, to be inserted.
+    Paste(WString),
+    /// A bracketed paste ended.
+    PasteEnd,
+    /// A mouse click, drag or scroll, given as one of the [`key::MOUSE_KEYS`] with the modifiers
+    /// that were held, and the cell where it happened.
+    Mouse(Key, ViewportPosition),
     /// Terminal color theme change (light/dark mode).
     NewColorTheme,
     /// Window height changed.
     NewWindowHeight,
+    /// The deadline for reading input has passed.
+    DeadlineElapsed,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -68,6 +78,8 @@ pub enum QueryResponse {
     PrimaryDeviceAttribute,
     BackgroundColor(xterm_color::Color),
     CursorPosition(ViewportPosition),
+    /// The clipboard contents, in response to an OSC 52 query.
+    Clipboard(Vec<u8>),
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -206,6 +218,9 @@ impl KeyEvent {
     }
 
     pub fn codepoint_text(&self) -> Option<char> {
+        if self.release {
+            return None;
+        }
         let mut modifiers = self.modifiers;
         let mut c = self.codepoint;
         if self.shifted_codepoint != '\0' && modifiers.shift {
@@ -233,7 +248,12 @@ impl KeyEvent {
 
 impl From<Key> for KeyEvent {
     fn from(key: Key) -> Self {
-        Self::new_with(key.modifiers, false, key.codepoint, None, None)
+        Self {
+            key,
+            explicit_modifiers: false,
+            shifted_codepoint: '\0',
+            base_layout_codepoint: '\0',
+        }
     }
 }
 
@@ -346,6 +366,9 @@ pub struct InputData {
 
     // If set, events will be buffered until the query finishes.
     pub blocking_query: Option<TerminalQuery>,
+
+    // Bytes which were read but not consumed when trying to match a key translation.
+    pub unread_bytes: VecDeque<u8>,
 }
 
 impl InputData {
@@ -360,6 +383,7 @@ impl InputData {
             event_storage: Vec::new(),
             blocking_query_timeout,
             blocking_query: None,
+            unread_bytes: VecDeque::new(),
         }
     }
 
@@ -411,12 +435,14 @@ pub struct RecurrentQuery {
 pub enum TerminalQuery {
     Initial,
     Recurrent(RecurrentQuery),
+    /// Reading the clipboard from outside the reader, see the fish_terminal_clipboard builtin.
+    Clipboard,
 }
 
 pub(super) fn is_event_blocked_when_querying(evt: &CharEvent) -> bool {
     use ImplicitEvent::*;
     match evt {
-        CharEvent::QueryResult(_) | CharEvent::Implicit(CheckExit | Eof) => false,
+        CharEvent::QueryResult(_) | CharEvent::Implicit(CheckExit | Eof | DeadlineElapsed) => false,
         CharEvent::Key(_)
         | CharEvent::Readline(_)
         | CharEvent::Command(_)
@@ -449,21 +475,26 @@ pub trait InputEventQueuer {
                 return mevt;
             }
 
+            // Decode bytes left over from an attempt to match a key translation.
+            if let Some(read_byte) = self.get_input_data_mut().unread_bytes.pop_front() {
+                on_byte_read(self, read_byte);
+                continue;
+            }
+
             // We are going to block; but first allow any override to inject events.
             self.prepare_to_select();
             if let Some(mevt) = self.try_pop() {
                 return mevt;
             }
 
-            match next_input_event(
-                self.get_in_fd(),
-                self.get_ioport_fd(),
-                if self.is_blocked_querying() {
-                    Timeout::Duration(self.get_input_data().blocking_query_timeout.unwrap())
-                } else {
-                    Timeout::Forever
-                },
-            ) {
+            let timeout = if self.is_blocked_querying() {
+                Timeout::Duration(self.get_input_data().blocking_query_timeout.unwrap())
+            } else if let Some(deadline) = self.get_deadline() {
+                Timeout::Duration(deadline.saturating_duration_since(Instant::now()))
+            } else {
+                Timeout::Forever
+            };
+            match next_input_event(self.get_in_fd(), self.get_ioport_fd(), timeout) {
                 InputEventTrigger::Eof => {
                     return CharEvent::Implicit(ImplicitEvent::Eof);
                 }
@@ -484,6 +515,9 @@ pub trait InputEventQueuer {
                     on_byte_read(self, read_byte);
                 }
                 InputEventTrigger::TimeoutElapsed => {
+                    if !self.is_blocked_querying() {
+                        return CharEvent::Implicit(ImplicitEvent::DeadlineElapsed);
+                    }
                     return CharEvent::QueryResult(QueryResultEvent::Timeout);
                 }
             }
@@ -509,6 +543,9 @@ pub trait InputEventQueuer {
         if let Some(evt) = self.try_pop() {
             return Some(evt);
         }
+        if !self.get_input_data().unread_bytes.is_empty() {
+            return Some(self.readch());
+        }
 
         check_fd_readable(
             unsafe { BorrowedFd::borrow_raw(self.get_in_fd()) },
@@ -527,6 +564,11 @@ pub trait InputEventQueuer {
         -1
     }
 
+    /// Return the point in time after which we stop waiting for input, if any.
+    fn get_deadline(&self) -> Option<Instant> {
+        None
+    }
+
     /// Return the input data. This is to be implemented by the concrete type.
     fn get_input_data(&self) -> &InputData;
     fn get_input_data_mut(&mut self) -> &mut InputData;
@@ -674,6 +716,10 @@ pub trait InputEventQueuer {
     }
 
     fn read_sequence_byte(&mut self, buffer: &mut Vec<u8>) -> Option<u8> {
+        if let Some(next) = self.get_input_data_mut().unread_bytes.pop_front() {
+            buffer.push(next);
+            return Some(next);
+        }
         let fd = self.get_in_fd();
         let strict = feature_test(FeatureFlag::OmitTermWorkarounds);
         let historical_millis = |ms| {
@@ -748,7 +794,7 @@ pub(super) enum InputEventTrigger {
     // Our ioport reported a change, so service main thread requests.
     IOPortNotified,
 
-    // No file descriptor was ready within the query timeout.
+    // No file descriptor was ready within the query timeout or before the deadline.
     TimeoutElapsed,
 }
 
@@ -761,6 +807,7 @@ pub(super) fn readb(in_fd: RawFd) -> Option<u8> {
     }
     let c = arr[0];
     flog!(reader, "Read byte", char_to_symbol(char::from(c), true));
+    record_input_byte(c);
     // The common path is to return a u8.
     Some(c)
 }
@@ -2,7 +2,9 @@ mod binding;
 mod decode;
 #[allow(clippy::module_inception)]
 mod input;
+mod recording;
 
 pub(crate) use binding::*;
 pub(crate) use decode::*;
 pub(crate) use input::*;
+pub use recording::{parse_input_recording, replay_input_recording, start_input_recording};
@@ -0,0 +1,160 @@
+//! Recording the raw input read from the terminal, and replaying it later.
+//!
+//! This helps with debugging key decoding issues that only happen in some terminals: users run
+//! `fish --record-input FILE`, and we replay the file with `fish_key_reader --replay FILE`.
+//!
+//! A recording is a text file with one line per point in time: the number of milliseconds since
+//! the recording started, a tab, and the bytes read at that time. Bytes other than printable
+//! ASCII are written as `\xHH`, and backslashes as `\\`. Lines starting with `#` are comments.
+
+use crate::fds::make_autoclose_pipes;
+use crate::flog::flog;
+use crate::prelude::*;
+use fish_common::write_loop;
+use std::fs::File;
+use std::io::Write as _;
+use std::os::fd::OwnedFd;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// When replaying, we wait at most this long between reads, so long pauses in a recording don't
+/// slow things down. This is longer than any timeout used when decoding keys.
+const MAX_REPLAY_DELAY: Duration = Duration::from_secs(1);
+
+struct InputRecorder {
+    file: File,
+    start: Instant,
+    /// The timestamp of the current line, in milliseconds.
+    last_millis: Option<u128>,
+}
+
+static RECORDER: Mutex<Option<InputRecorder>> = Mutex::new(None);
+
+/// Start writing all input we read to the given file.
+pub fn start_input_recording(mut file: File) -> std::io::Result<()> {
+    file.write_all(b"# fish input recording")?;
+    *RECORDER.lock().unwrap() = Some(InputRecorder {
+        file,
+        start: Instant::now(),
+        last_millis: None,
+    });
+    Ok(())
+}
+
+fn escape_byte(out: &mut Vec<u8>, byte: u8) {
+    match byte {
+        b'\\' => out.extend_from_slice(b"\\\\"),
+        b' '..=b'~' => out.push(byte),
+        _ => out.extend_from_slice(format!("\\x{:02x}", byte).as_bytes()),
+    }
+}
+
+/// Add a byte we just read to the recording, if there is one.
+pub(super) fn record_input_byte(byte: u8) {
+    let mut maybe_recorder = RECORDER.lock().unwrap();
+    let Some(recorder) = maybe_recorder.as_mut() else {
+        return;
+    };
+    let millis = recorder.start.elapsed().as_millis();
+    let mut line = vec![];
+    if recorder.last_millis != Some(millis) {
+        recorder.last_millis = Some(millis);
+        line.extend_from_slice(format!("\n{}\t", millis).as_bytes());
+    }
+    escape_byte(&mut line, byte);
+    // Write right away, so the recording is complete even if we crash.
+    if let Err(err) = recorder.file.write_all(&line) {
+        flog!(
+            warning,
+            wgettext_fmt!("Could not record input: %s", err.to_string())
+        );
+        *maybe_recorder = None;
+    }
+}
+
+fn unescape(escaped: &[u8]) -> Option<Vec<u8>> {
+    let mut bytes = vec![];
+    let mut iter = escaped.iter().copied();
+    while let Some(byte) = iter.next() {
+        if byte != b'\\' {
+            bytes.push(byte);
+            continue;
+        }
+        match iter.next()? {
+            b'\\' => bytes.push(b'\\'),
+            b'x' => {
+                let hex = [iter.next()?, iter.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            _ => return None,
+        }
+    }
+    Some(bytes)
+}
+
+/// Parse a recording into the points in time (relative to the start) and the bytes read then.
+/// On failure, return the number of the offending line.
+pub fn parse_input_recording(contents: &[u8]) -> Result<Vec<(Duration, Vec<u8>)>, usize> {
+    let mut reads = vec![];
+    for (i, line) in contents.split(|&c| c == b'\n').enumerate() {
+        if line.is_empty() || line.starts_with(b"#") {
+            continue;
+        }
+        let parse_line = || {
+            let tab = line.iter().position(|&c| c == b'\t')?;
+            let millis = std::str::from_utf8(&line[..tab]).ok()?.parse().ok()?;
+            Some((Duration::from_millis(millis), unescape(&line[tab + 1..])?))
+        };
+        reads.push(parse_line().ok_or(i + 1)?);
+    }
+    Ok(reads)
+}
+
+/// Write the recorded input to a pipe with the recorded timing, from a background thread.
+/// Return the read end of the pipe, which sees EOF once everything has been written.
+pub fn replay_input_recording(reads: Vec<(Duration, Vec<u8>)>) -> nix::Result<OwnedFd> {
+    let pipes = make_autoclose_pipes()?;
+    let write_end = pipes.write;
+    std::thread::spawn(move || {
+        let mut last_time = Duration::ZERO;
+        for (time, bytes) in reads {
+            std::thread::sleep(time.saturating_sub(last_time).min(MAX_REPLAY_DELAY));
+            last_time = time;
+            if write_loop(&write_end, &bytes).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(pipes.read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_byte, parse_input_recording};
+    use std::time::Duration;
+
+    #[test]
+    fn test_input_recording_format() {
+        let mut escaped = vec![];
+        for &byte in b"a\x1b[A\\\x7f " {
+            escape_byte(&mut escaped, byte);
+        }
+        assert_eq!(escaped, b"a\\x1b[A\\\\\\x7f ");
+
+        let mut recording = b"# fish input recording\n0\t".to_vec();
+        recording.extend_from_slice(&escaped);
+        recording.extend_from_slice(b"\n\n1500\t\\x03");
+        assert_eq!(
+            parse_input_recording(&recording),
+            Ok(vec![
+                (Duration::ZERO, b"a\x1b[A\\\x7f ".to_vec()),
+                (Duration::from_millis(1500), b"\x03".to_vec()),
+            ])
+        );
+
+        assert_eq!(parse_input_recording(b"0\tok\nnot a number\tx"), Err(2));
+        assert_eq!(parse_input_recording(b"0\t\\x1"), Err(1));
+        assert_eq!(parse_input_recording(b"0\t\\q"), Err(1));
+    }
+}
@@ -1,7 +1,10 @@
 use crate::{
     builtins::{STATUS_CMD_ERROR, STATUS_CMD_OK, STATUS_READ_TOO_MUCH},
     fd_monitor::{Callback, FdMonitor, FdMonitorItemId},
-    fds::{BorrowedFdFile, PIPE_ERROR, make_autoclose_pipes, make_fd_nonblocking, wopen_cloexec},
+    fds::{
+        BorrowedFdFile, PIPE_ERROR, heightenize_fd, make_autoclose_pipes, make_fd_nonblocking,
+        wopen_cloexec,
+    },
     flog::{flog, flogf, should_flog},
     nix::isatty,
     path::path_apply_working_directory,
@@ -11,13 +14,14 @@ use crate::{
     wutil::{perror_io, unescape_bytes_and_write_to_fd, wdirname, wstat},
 };
 use errno::Errno;
+use fish_common::write_loop;
 use fish_util::perror;
 use fish_widestring::{bytes2wcstring, wcs2bytes};
 use libc::{EAGAIN, EINTR, ENOENT, ENOTDIR, EWOULDBLOCK, STDOUT_FILENO};
 use nix::{fcntl::OFlag, sys::stat::Mode};
 use std::{
     fs::File,
-    io,
+    io::{self, Seek as _, SeekFrom, Write as _},
     os::fd::{AsFd as _, AsRawFd as _, BorrowedFd, OwnedFd, RawFd},
     sync::{Arc, LazyLock, Mutex, MutexGuard},
 };
@@ -366,6 +370,16 @@ impl IoBufferfill {
         let fd = fd_monitor().remove_item(filler.item_id);
         filler.buffer.complete_and_take_buffer(fd)
     }
+
+    /// Complete the fillthread like [`Self::finish`], but keep the read end of the pipe open and
+    /// return it with the buffer, so later output may be passed on with [`forward_to_stdout`].
+    pub fn finish_keeping_pipe(filler: Arc<Self>) -> (SeparatedBuffer, Option<OwnedFd>) {
+        let fd = fd_monitor().remove_item(filler.item_id);
+        if let Some(fd) = &fd {
+            filler.buffer.read_all_available(fd.as_fd());
+        }
+        (filler.buffer.complete_and_take_buffer(None), fd)
+    }
 }
 impl IoData for IoBufferfill {
     fn io_mode(&self) -> IoMode {
@@ -409,6 +423,11 @@ impl IoBuffer {
         self.0.lock().unwrap().discarded()
     }
 
+    /// Return a copy of the contents received so far, leaving the fillthread running.
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.lock().unwrap().newline_serialized()
+    }
+
     /// Read some, filling the buffer. The buffer is passed in to enforce that the append lock is
     /// held. Return positive on success, 0 if closed, -1 on error (in which case errno will be
     /// set).
@@ -497,6 +516,39 @@ fn begin_filling(iobuffer: IoBuffer, fd: OwnedFd) -> FdMonitorItemId {
     fd_monitor().add(fd, item_callback)
 }
 
+/// Copy everything read from the given fd to stdout, in the background, until the write end of the
+/// pipe is closed.
+pub fn forward_to_stdout(fd: OwnedFd) {
+    let item_callback: Callback = Box::new(|fd: &mut Option<OwnedFd>| {
+        let mut bytes = [b'\0'; 4096 * 4];
+        let amt = unsafe {
+            libc::read(
+                fd.as_ref().unwrap().as_raw_fd(),
+                bytes.as_mut_ptr().cast(),
+                size_of_val(&bytes),
+            )
+        };
+        if amt > 0 {
+            let _ = write_loop(&STDOUT_FILENO, &bytes[0..usize::try_from(amt).unwrap()]);
+        } else if amt == 0 || ![EAGAIN, EWOULDBLOCK, EINTR].contains(&errno::errno().0) {
+            drop(fd.take());
+        }
+    });
+
+    fd_monitor().add(fd, item_callback);
+}
+
+/// Return a file to read the input of a here-document or here-string from. The input is written to
+/// a temporary file, which is removed right away, so it is gone once the file is closed.
+fn here_input_file(input: &wstr) -> io::Result<File> {
+    let mut tmp = fish_tempfile::new_file()?;
+    let file = tmp.get_mut();
+    file.write_all(&wcs2bytes(input))?;
+    file.seek(SeekFrom::Start(0))?;
+    let fd = heightenize_fd(OwnedFd::from(file.try_clone()?), true)?;
+    Ok(File::from(fd))
+}
+
 pub type IoDataRef = Arc<dyn IoData>;
 
 #[derive(Clone, Default)]
@@ -579,6 +631,17 @@ impl IoChain {
                         self.push(Arc::new(IoFd::new(spec.fd, target_fd)));
                     }
                 }
+                RedirectionMode::HereDoc | RedirectionMode::HereString => {
+                    // The target is the input itself.
+                    match here_input_file(&spec.target) {
+                        Ok(file) => self.push(Arc::new(IoFile::new(spec.fd, file))),
+                        Err(err) => {
+                            perror_io("here-document", &err);
+                            self.push(Arc::new(IoClose::new(spec.fd)));
+                            have_error = true;
+                        }
+                    }
+                }
                 _ => {
                     // We have a path-based redirection. Resolve it to a file.
                     // Mark it as CLOEXEC because we don't want it to be open in any child.
@@ -42,8 +42,45 @@ define_special_keys! {
     SPACE: 14
     MENU: 15
     PRINT_SCREEN: 16
+    FOCUS_IN: 17
+    FOCUS_OUT: 18
+    PASTE_START: 19
+    PASTE: 20
+    PASTE_END: 21
+    MOUSE_LEFT_CLICK: 22
+    MOUSE_MIDDLE_CLICK: 23
+    MOUSE_RIGHT_CLICK: 24
+    MOUSE_DRAG: 25
+    SCROLL_UP: 26
+    SCROLL_DOWN: 27
 }
 
+/// Pseudo-keys which stand for terminal events rather than key presses.
+/// They can only be bound on their own.
+pub(crate) const EVENT_KEYS: &[char] = &[
+    FOCUS_IN,
+    FOCUS_OUT,
+    PASTE_START,
+    PASTE,
+    PASTE_END,
+    MOUSE_LEFT_CLICK,
+    MOUSE_MIDDLE_CLICK,
+    MOUSE_RIGHT_CLICK,
+    MOUSE_DRAG,
+    SCROLL_UP,
+    SCROLL_DOWN,
+];
+
+/// The event keys for mouse events. Unlike the other ones, these can have modifiers.
+pub(crate) const MOUSE_KEYS: &[char] = &[
+    MOUSE_LEFT_CLICK,
+    MOUSE_MIDDLE_CLICK,
+    MOUSE_RIGHT_CLICK,
+    MOUSE_DRAG,
+    SCROLL_UP,
+    SCROLL_DOWN,
+];
+
 pub(crate) const MAX_FUNCTION_KEY: u8 = 12;
 pub(crate) fn function_key(n: u8) -> char {
     assert!((1..=MAX_FUNCTION_KEY).contains(&n));
@@ -70,6 +107,17 @@ pub(crate) const KEY_NAMES: &[(char, &wstr)] = &[
     (SPACE, L!("space")),
     (MENU, L!("menu")),
     (PRINT_SCREEN, L!("printscreen")),
+    (FOCUS_IN, L!("focusin")),
+    (FOCUS_OUT, L!("focusout")),
+    (PASTE_START, L!("pastestart")),
+    (PASTE, L!("paste")),
+    (PASTE_END, L!("pasteend")),
+    (MOUSE_LEFT_CLICK, L!("mouse-left-click")),
+    (MOUSE_MIDDLE_CLICK, L!("mouse-middle-click")),
+    (MOUSE_RIGHT_CLICK, L!("mouse-right-click")),
+    (MOUSE_DRAG, L!("mouse-drag")),
+    (SCROLL_UP, L!("scroll-up")),
+    (SCROLL_DOWN, L!("scroll-down")),
 ];
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
@@ -125,6 +173,9 @@ impl FloggableDebug for ViewportPosition {}
 pub struct Key {
     pub modifiers: Modifiers,
     pub codepoint: char,
+    /// Whether this is the key being released rather than pressed.
+    /// Only terminals implementing the kitty keyboard protocol report these.
+    pub release: bool,
 }
 
 impl Key {
@@ -132,6 +183,7 @@ impl Key {
         Self {
             modifiers,
             codepoint,
+            release: false,
         }
     }
     pub(crate) fn from_raw(codepoint: char) -> Self {
@@ -285,15 +337,22 @@ pub(crate) fn parse_keys(value: &wstr) -> Result<Vec<Key>, WString> {
                 continue;
             }
             let mut modifiers = Modifiers::default();
-            let num_keys = full_key_name.split('-').count();
-            let mut components = full_key_name.split('-');
-            for _i in 0..num_keys.checked_sub(1).unwrap() {
-                let modifier = components.next().unwrap();
+            let mut release = false;
+            // Some key names contain dashes themselves, so only split off modifiers until we
+            // have one of those.
+            let mut key_name = full_key_name;
+            while !KEY_NAMES.iter().any(|(_codepoint, name)| name == key_name) {
+                let Some(dash) = key_name.find_char('-') else {
+                    break;
+                };
+                let modifier = key_name.slice_to(dash);
+                key_name = key_name.slice_from(dash + 1);
                 match modifier {
                     _ if modifier == "ctrl" => modifiers.ctrl = true,
                     _ if modifier == "alt" => modifiers.alt = true,
                     _ if modifier == "shift" => modifiers.shift = true,
                     _ if modifier == "super" => modifiers.sup = true,
+                    _ if modifier == "release" => release = true,
                     _ => {
                         return Err(wgettext_fmt!(
                             "unknown modifier '%s' in '%s'",
@@ -303,12 +362,11 @@ pub(crate) fn parse_keys(value: &wstr) -> Result<Vec<Key>, WString> {
                     }
                 }
             }
-            let key_name = components.next().unwrap();
             let codepoint = KEY_NAMES
                 .iter()
                 .find_map(|(codepoint, name)| (name == key_name).then_some(*codepoint))
                 .or_else(|| (key_name.len() == 1).then(|| key_name.as_char_slice()[0]));
-            let key = if let Some(codepoint) = codepoint {
+            let mut key = if let Some(codepoint) = codepoint {
                 canonicalize_key(Key::new(modifiers, codepoint))?
             } else if codepoint.is_none() && key_name.starts_with('f') && key_name.len() <= 3 {
                 let num = key_name.strip_prefix('f').unwrap();
@@ -331,6 +389,7 @@ pub(crate) fn parse_keys(value: &wstr) -> Result<Vec<Key>, WString> {
                     escape_nonprintables(full_key_name)
                 ));
             };
+            key.release = release;
             res.push(key);
         }
     }
@@ -409,6 +468,9 @@ impl From<Key> for WString {
         if key.modifiers.sup {
             res.insert_utfstr(0, L!("super-"));
         }
+        if key.release {
+            res.insert_utfstr(0, L!("release-"));
+        }
 
         res
     }
@@ -491,5 +553,14 @@ mod tests {
             Ok(vec![Key::from_raw(function_key(1))])
         );
         assert!(parse_keys(L!("F1")).is_err());
+        let mut release_ctrl_a = ctrl('a');
+        release_ctrl_a.release = true;
+        assert_eq!(parse_keys(L!("release-ctrl-a")), Ok(vec![release_ctrl_a]));
+        assert_eq!(WString::from(release_ctrl_a), L!("release-ctrl-a"));
+        assert_eq!(
+            parse_keys(L!("ctrl-scroll-up")),
+            Ok(vec![ctrl(key::SCROLL_UP)])
+        );
+        assert!(parse_keys(L!("scroll-ctrl-up")).is_err());
     }
 }
@@ -2,9 +2,13 @@
 //!
 //! Works like the killring in emacs and readline. The killring is cut and paste with a memory of
 //! previous cuts.
+//!
+//! Like in Vim, there are also registers: the named registers `a` to `z` only change when a kill
+//! explicitly targets them, and the numbered registers `0` to `9` are the entries of the killring.
 
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::prelude::*;
 
@@ -82,9 +86,73 @@ pub fn kill_entries() -> Vec<WString> {
     KILL_RING.lock().unwrap().entries()
 }
 
+static REGISTERS: Mutex<BTreeMap<char, WString>> = Mutex::new(BTreeMap::new());
+
+/// The register that the next kill or yank uses.
+static PENDING_REGISTER: Mutex<Option<char>> = Mutex::new(None);
+
+/// Whether kills are also copied to the system clipboard.
+static KILLRING_CLIPBOARD: AtomicBool = AtomicBool::new(false);
+
+/// Return whether this is the name of a register.
+pub fn is_register(name: char) -> bool {
+    name.is_ascii_alphanumeric()
+}
+
+/// Return the contents of a register. Uppercase names refer to the same register as lowercase
+/// ones.
+pub fn register_get(name: char) -> WString {
+    assert!(is_register(name));
+    if let Some(index) = name.to_digit(10) {
+        let kill_ring = KILL_RING.lock().unwrap();
+        return kill_ring.0.get(index as usize).cloned().unwrap_or_default();
+    }
+    let registers = REGISTERS.lock().unwrap();
+    registers
+        .get(&name.to_ascii_lowercase())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Set a register, or append to it if the name is uppercase. Return false if the register can't
+/// be set, because it is one of the numbered registers.
+pub fn register_set(name: char, text: &wstr) -> bool {
+    assert!(is_register(name));
+    if name.is_ascii_digit() {
+        return false;
+    }
+    let mut registers = REGISTERS.lock().unwrap();
+    let contents = registers.entry(name.to_ascii_lowercase()).or_default();
+    if name.is_ascii_uppercase() {
+        contents.push_utfstr(text);
+    } else {
+        *contents = text.to_owned();
+    }
+    true
+}
+
+/// Make the next kill or yank use the given register.
+pub fn kill_select_register(name: char) {
+    assert!(is_register(name));
+    *PENDING_REGISTER.lock().unwrap() = Some(name);
+}
+
+/// Return the register the current kill or yank should use, if one was selected.
+pub fn kill_take_register() -> Option<char> {
+    PENDING_REGISTER.lock().unwrap().take()
+}
+
+pub fn set_killring_clipboard(enabled: bool) {
+    KILLRING_CLIPBOARD.store(enabled, Ordering::Relaxed);
+}
+
+pub fn killring_clipboard() -> bool {
+    KILLRING_CLIPBOARD.load(Ordering::Relaxed)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::KillRing;
+    use super::{KILL_RING, KillRing, kill_add, register_get, register_set};
     use crate::prelude::*;
 
     #[test]
@@ -112,4 +180,23 @@ mod tests {
         assert_eq!(kr.yank_rotate(), "a");
         assert_eq!(kr.entries(), [L!("a"), L!("c"), L!("b"), L!("d")]);
     }
+
+    #[test]
+    fn test_registers() {
+        assert!(register_set('q', L!("foo")));
+        assert!(register_set('Q', L!(" bar")));
+        assert_eq!(register_get('q'), "foo bar");
+        assert_eq!(register_get('Q'), "foo bar");
+        assert!(register_set('q', L!("baz")));
+        assert_eq!(register_get('q'), "baz");
+        assert_eq!(register_get('z'), "");
+
+        *KILL_RING.lock().unwrap() = KillRing::new();
+        kill_add(L!("older").to_owned());
+        kill_add(L!("newer").to_owned());
+        assert_eq!(register_get('0'), "newer");
+        assert_eq!(register_get('1'), "older");
+        assert_eq!(register_get('2'), "");
+        assert!(!register_set('1', L!("x")));
+    }
 }
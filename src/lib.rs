@@ -17,6 +17,7 @@ pub mod ast;
 pub mod autoload;
 pub mod builtins;
 pub mod complete;
+pub mod debugger;
 pub mod editable_line;
 pub mod env;
 pub mod env_dispatch;
@@ -32,6 +33,7 @@ pub mod fork_exec;
 pub mod fs;
 pub mod function;
 pub mod global_safety;
+pub mod help_parser;
 pub mod highlight;
 pub mod history;
 pub mod input;
@@ -39,8 +41,11 @@ pub mod io;
 pub mod job_group;
 pub mod key;
 pub mod kill;
+pub mod lint;
 pub mod locale;
 pub mod localization;
+pub mod lsp;
+pub mod manpage_completions;
 pub mod nix;
 pub mod null_terminated_array;
 pub mod operation_context;
@@ -64,6 +69,7 @@ pub mod screen;
 pub mod signal;
 pub mod stdx;
 pub mod terminal;
+pub mod terminal_capabilities;
 pub mod termsize;
 pub mod text_face;
 pub mod threads;
@@ -0,0 +1,519 @@
+//! Static checks for fish scripts, run with `fish --lint`.
+//!
+//! The checks walk the AST and report code that parses fine but probably does not do what was
+//! meant. A check can be turned off for a line with a comment like `# fish-lint: ignore=CHECK`,
+//! either at the end of that line or on the line before.
+
+use crate::{
+    ast::{self, Ast, Kind, Node as _},
+    builtins::{json_string, test},
+    common::valid_var_name_char,
+    parse_constants::{ParseErrorList, ParseTreeFlags},
+    parse_util::detect_parse_errors_in_ast,
+    prelude::*,
+    printf,
+};
+use fish_common::{UnescapeStringStyle, unescape_string};
+use fish_widestring::{bytes2wcstring, wcs2bytes};
+use std::{collections::HashMap, ffi::OsStr, io::Read as _, ops::Range, os::unix::ffi::OsStrExt};
+
+/// A kind of problem that is reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Check {
+    /// The script does not parse. This can not be turned off.
+    SyntaxError,
+    /// `eval` of a fixed string, which could be run directly.
+    UselessEval,
+    /// An unquoted variable where the number of arguments matters.
+    UnquotedVariable,
+    /// Arguments to `test` or `[` that it would reject or misread.
+    TestArguments,
+    /// Syntax that fish no longer supports or will remove.
+    DeprecatedSyntax,
+}
+
+impl Check {
+    pub const ALL: &[Check] = &[
+        Check::SyntaxError,
+        Check::UselessEval,
+        Check::UnquotedVariable,
+        Check::TestArguments,
+        Check::DeprecatedSyntax,
+    ];
+
+    pub fn name(self) -> &'static wstr {
+        match self {
+            Check::SyntaxError => L!("syntax-error"),
+            Check::UselessEval => L!("useless-eval"),
+            Check::UnquotedVariable => L!("unquoted-variable"),
+            Check::TestArguments => L!("test-arguments"),
+            Check::DeprecatedSyntax => L!("deprecated-syntax"),
+        }
+    }
+
+    pub fn from_name(name: &wstr) -> Option<Check> {
+        Check::ALL
+            .iter()
+            .copied()
+            .find(|check| check.name() == name)
+    }
+}
+
+/// A problem found in a script.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Issue {
+    pub check: Check,
+    /// The range of the offending code, in characters.
+    pub range: Range<usize>,
+    pub message: WString,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LintFormat {
+    /// `file:line:column: message [check]`, one per line.
+    #[default]
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// Variables which always have exactly one element, so they are safe to use unquoted.
+const SINGLE_VALUE_VARS: &[&str] = &["status", "fish_pid", "SHLVL", "PWD"];
+
+/// What expanding an argument can do.
+#[derive(Default)]
+struct ArgExpansion {
+    /// Unquoted variables, as their offsets in the argument and their names.
+    unquoted_vars: Vec<(usize, WString)>,
+    /// Whether the argument may become several arguments or none.
+    may_split: bool,
+    /// Whether the value is only known when running the script.
+    dynamic: bool,
+}
+
+/// Look at the source of an argument to see what it may expand to.
+fn scan_argument(arg: &wstr) -> ArgExpansion {
+    let chars = arg.as_char_slice();
+    let mut result = ArgExpansion::default();
+    let mut quote = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match (quote, c) {
+            (Some('\''), '\\') => i += 1,
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => (),
+            (_, '\\') => i += 1,
+            (Some('"'), '"') => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (_, '$') if chars.get(i + 1) == Some(&'(') => {
+                result.dynamic = true;
+                result.may_split |= quote.is_none();
+            }
+            (_, '$') => {
+                result.dynamic = true;
+                if quote.is_none() {
+                    let name: WString = chars[i + 1..]
+                        .iter()
+                        .take_while(|&&c| valid_var_name_char(c))
+                        .collect();
+                    if !SINGLE_VALUE_VARS.iter().any(|&var| name == var) {
+                        result.may_split = true;
+                        if !name.is_empty() {
+                            result.unquoted_vars.push((i, name));
+                        }
+                    }
+                }
+            }
+            (None, '(') => {
+                result.dynamic = true;
+                result.may_split = true;
+                // Skip the command substitution, whose contents are a script of their own.
+                let mut depth = 0;
+                while i < chars.len() {
+                    match chars[i] {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    i += 1;
+                }
+            }
+            (None, '*' | '{') => {
+                result.dynamic = true;
+                result.may_split = true;
+            }
+            (None, '~') if i == 0 => result.dynamic = true,
+            _ => (),
+        }
+        i += 1;
+    }
+    result
+}
+
+/// Find the suppression comments. Returns the checks to skip for each line, counting from 0,
+/// where None means all checks.
+fn ignored_lines(ast: &Ast, src: &wstr) -> HashMap<usize, Option<Vec<WString>>> {
+    let mut result = HashMap::new();
+    for comment in &ast.extras.comments {
+        let text = &src[comment.start()..comment.end()];
+        let Some(rest) = text
+            .strip_prefix(L!("#"))
+            .map(|rest| rest.trim_matches(' '))
+            .and_then(|rest| rest.strip_prefix(L!("fish-lint:")))
+        else {
+            continue;
+        };
+        let rest = rest.trim_matches(' ');
+        let checks = if rest == "ignore" {
+            None
+        } else if let Some(names) = rest.strip_prefix(L!("ignore=")) {
+            Some(names.split(',').map(|name| name.to_owned()).collect())
+        } else {
+            continue;
+        };
+        let line_start = src[..comment.start()]
+            .as_char_slice()
+            .iter()
+            .rposition(|&c| c == '\n')
+            .map_or(0, |i| i + 1);
+        let mut line = line_number(src, comment.start());
+        // A comment on a line of its own applies to the next line.
+        if src[line_start..comment.start()]
+            .chars()
+            .all(|c| c == ' ' || c == '\t')
+        {
+            line += 1;
+        }
+        result.insert(line, checks);
+    }
+    result
+}
+
+/// The line number of an offset, counting from 0.
+fn line_number(src: &wstr, offset: usize) -> usize {
+    src[..offset].chars().filter(|&c| c == '\n').count()
+}
+
+struct Linter<'a> {
+    src: &'a wstr,
+    issues: Vec<Issue>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, check: Check, range: Range<usize>, message: WString) {
+        self.issues.push(Issue {
+            check,
+            range,
+            message,
+        });
+    }
+
+    fn visit_statement(&mut self, stmt: &ast::DecoratedStatement) {
+        let src = self.src;
+        let command = stmt.command.source(src);
+        let command = unescape_string(command, UnescapeStringStyle::default())
+            .unwrap_or_else(|| command.to_owned());
+        let command_range = stmt.command.source_range();
+        let args: Vec<&ast::Argument> = stmt
+            .args_or_redirs
+            .iter()
+            .filter_map(|arg_or_redir| match arg_or_redir {
+                ast::ArgumentOrRedirection::Argument(arg) => Some(arg),
+                ast::ArgumentOrRedirection::Redirection(_) => None,
+            })
+            .collect();
+
+        for arg in &args {
+            let range = arg.source_range();
+            let source = arg.source(src);
+            if source == "%self" {
+                self.report(
+                    Check::DeprecatedSyntax,
+                    range.start()..range.end(),
+                    wgettext_fmt!("'%s' is no longer expanded, use $fish_pid", source),
+                );
+            } else if source.starts_with("^/")
+                || source.starts_with("^&")
+                || source.starts_with("^^")
+            {
+                self.report(
+                    Check::DeprecatedSyntax,
+                    range.start()..range.end(),
+                    wgettext!("'^' no longer redirects stderr, use '2>'").to_owned(),
+                );
+            }
+        }
+
+        if command == "." {
+            self.report(
+                Check::DeprecatedSyntax,
+                command_range.start()..command_range.end(),
+                wgettext!("'.' is deprecated, use 'source'").to_owned(),
+            );
+        } else if command == "eval" {
+            if !args.is_empty()
+                && args
+                    .iter()
+                    .all(|arg| !scan_argument(arg.source(src)).dynamic)
+            {
+                self.report(
+                    Check::UselessEval,
+                    command_range.start()..command_range.end(),
+                    wgettext!("eval of a fixed string, run the command directly").to_owned(),
+                );
+            }
+        } else if command == "test" || command == "[" {
+            self.check_test(&command, command_range.start()..command_range.end(), &args);
+        }
+    }
+
+    /// Report unquoted variables in an argument whose number of values matters.
+    fn check_unquoted_variables(&mut self, arg: &ast::Argument) {
+        let range = arg.source_range();
+        for (offset, name) in scan_argument(arg.source(self.src)).unquoted_vars {
+            let start = range.start() + offset;
+            self.report(
+                Check::UnquotedVariable,
+                start..start + name.len() + 1,
+                wgettext_fmt!(
+                    "$%s is not quoted, so it becomes no argument if it is empty, or several if it has several elements",
+                    name
+                ),
+            );
+        }
+    }
+
+    fn check_test(&mut self, command: &wstr, command_range: Range<usize>, args: &[&ast::Argument]) {
+        let src = self.src;
+        for arg in args {
+            self.check_unquoted_variables(arg);
+        }
+        let mut args = args;
+        if command == "[" {
+            match args.split_last() {
+                Some((last, rest)) if last.source(src) == "]" => args = rest,
+                _ => {
+                    self.report(
+                        Check::TestArguments,
+                        command_range,
+                        wgettext!("the last argument must be ']'").to_owned(),
+                    );
+                    return;
+                }
+            }
+        }
+
+        // Only check the arguments if we know how many there will be. Values that are only known
+        // when running are kept as written, which makes them operands.
+        let mut values = vec![];
+        for arg in args {
+            let source = arg.source(src);
+            let expansion = scan_argument(source);
+            if expansion.may_split {
+                return;
+            }
+            values.push(if expansion.dynamic {
+                source.to_owned()
+            } else {
+                unescape_string(source, UnescapeStringStyle::default())
+                    .unwrap_or_else(|| source.to_owned())
+            });
+        }
+        let range = match (args.first(), args.last()) {
+            (Some(first), Some(last)) => first.source_range().start()..last.source_range().end(),
+            _ => command_range,
+        };
+        let error = match values.len() {
+            0 => Some(wgettext_fmt!("%s without arguments is an error", command)),
+            1 => Some(wgettext_fmt!(
+                "%s with one argument only checks that it is not empty, use -n",
+                command
+            )),
+            _ => test::syntax_error(&values),
+        };
+        if let Some(error) = error {
+            self.report(Check::TestArguments, range, error);
+        }
+    }
+}
+
+/// Check a script, skipping the given checks and those turned off by comments.
+pub fn lint(src: &wstr, skip: &[Check]) -> Vec<Issue> {
+    let flags = ParseTreeFlags {
+        include_comments: true,
+        ..Default::default()
+    };
+    let mut errors = ParseErrorList::new();
+    let ast = ast::parse(src, flags, Some(&mut errors));
+    if !ast.errored() {
+        let _ = detect_parse_errors_in_ast(&ast, src, Some(&mut errors));
+    }
+    if !errors.is_empty() {
+        return errors
+            .into_iter()
+            .map(|err| {
+                let start = err.source_start.min(src.len());
+                let end = (start + err.source_length).min(src.len());
+                Issue {
+                    check: Check::SyntaxError,
+                    range: start..end,
+                    message: err.text,
+                }
+            })
+            .collect();
+    }
+
+    let mut linter = Linter {
+        src,
+        issues: vec![],
+    };
+    for node in ast.walk() {
+        match node.kind() {
+            Kind::DecoratedStatement(stmt) => linter.visit_statement(stmt),
+            Kind::SwitchStatement(switch) => linter.check_unquoted_variables(&switch.argument),
+            _ => (),
+        }
+    }
+
+    let ignored = ignored_lines(&ast, src);
+    let mut issues = linter.issues;
+    issues.retain(|issue| {
+        if skip.contains(&issue.check) {
+            return false;
+        }
+        match ignored.get(&line_number(src, issue.range.start)) {
+            Some(None) => false,
+            Some(Some(names)) => !names.iter().any(|name| name == issue.check.name()),
+            None => true,
+        }
+    });
+    issues.sort_by_key(|issue| issue.range.start);
+    issues
+}
+
+/// Lint the given files, or stdin if there are none, and print the issues to stdout.
+/// Returns 0 if there were no issues, 1 if there were some, and 2 if a file could not be read.
+pub fn run(files: &[WString], format: LintFormat, skip: &[Check]) -> i32 {
+    let mut sources = vec![];
+    if files.is_empty() {
+        let mut buf = vec![];
+        if let Err(err) = std::io::stdin().read_to_end(&mut buf) {
+            eprintf!(
+                "%s\n",
+                wgettext_fmt!("fish: Error reading stdin: %s", err.to_string())
+            );
+            return 2;
+        }
+        sources.push((L!("-").to_owned(), bytes2wcstring(&buf)));
+    }
+    for file in files {
+        match std::fs::read(OsStr::from_bytes(&wcs2bytes(file))) {
+            Ok(buf) => sources.push((file.clone(), bytes2wcstring(&buf))),
+            Err(err) => {
+                eprintf!(
+                    "%s\n",
+                    wgettext_fmt!(
+                        "fish: Error reading script file '%s': %s",
+                        file,
+                        err.to_string()
+                    )
+                );
+                return 2;
+            }
+        }
+    }
+
+    let mut status = 0;
+    for (file, src) in &sources {
+        for issue in lint(src, skip) {
+            status = 1;
+            let line = line_number(src, issue.range.start) + 1;
+            let line_start = src[..issue.range.start]
+                .as_char_slice()
+                .iter()
+                .rposition(|&c| c == '\n')
+                .map_or(0, |i| i + 1);
+            let column = issue.range.start - line_start + 1;
+            match format {
+                LintFormat::Text => printf!(
+                    "%s:%d:%d: %s [%s]\n",
+                    file,
+                    line,
+                    column,
+                    issue.message,
+                    issue.check.name()
+                ),
+                LintFormat::Json => printf!(
+                    "{\"file\":%s,\"line\":%d,\"column\":%d,\"check\":%s,\"message\":%s}\n",
+                    json_string(file),
+                    line,
+                    column,
+                    json_string(issue.check.name()),
+                    json_string(&issue.message)
+                ),
+            }
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Check, lint};
+    use crate::prelude::*;
+    use crate::tests::prelude::*;
+
+    fn checks(src: &str) -> Vec<Check> {
+        lint(&WString::from_str(src), &[])
+            .into_iter()
+            .map(|issue| issue.check)
+            .collect()
+    }
+
+    #[test]
+    #[serial]
+    fn test_lint() {
+        let _cleanup = test_init();
+        assert_eq!(checks("echo hello"), vec![]);
+        assert_eq!(checks("echo (echo"), vec![Check::SyntaxError]);
+
+        assert_eq!(checks("eval echo hi"), vec![Check::UselessEval]);
+        assert_eq!(checks("eval 'echo hi'"), vec![Check::UselessEval]);
+        assert_eq!(checks("eval $cmd"), vec![]);
+        assert_eq!(checks("eval \"echo $x\""), vec![]);
+
+        assert_eq!(checks("test -n $x"), vec![Check::UnquotedVariable]);
+        assert_eq!(checks("test -n \"$x\""), vec![]);
+        assert_eq!(checks("test $status -eq 0"), vec![]);
+        assert_eq!(checks("[ -z $x[1] ]"), vec![Check::UnquotedVariable]);
+        assert_eq!(checks("switch $x; end"), vec![Check::UnquotedVariable]);
+        assert_eq!(checks("test -n (echo)"), vec![]);
+
+        assert_eq!(checks("test -n \"$x\" \"$y\""), vec![Check::TestArguments]);
+        assert_eq!(checks("test foo"), vec![Check::TestArguments]);
+        assert_eq!(checks("test"), vec![Check::TestArguments]);
+        assert_eq!(checks("[ -n foo"), vec![Check::TestArguments]);
+        assert_eq!(checks("test \"$a\" = \"$b\" -a -d /"), vec![]);
+        assert_eq!(checks("test -n *"), vec![]);
+
+        assert_eq!(checks(". ./config.fish"), vec![Check::DeprecatedSyntax]);
+        assert_eq!(checks("echo %self"), vec![Check::DeprecatedSyntax]);
+        assert_eq!(checks("cat foo ^/dev/null"), vec![Check::DeprecatedSyntax]);
+        assert_eq!(checks("string match -r ^foo bar"), vec![]);
+
+        assert_eq!(checks("eval echo hi # fish-lint: ignore"), vec![]);
+        assert_eq!(
+            checks("# fish-lint: ignore=useless-eval\neval echo hi\neval echo hi"),
+            vec![Check::UselessEval]
+        );
+        assert_eq!(
+            checks("eval echo hi # fish-lint: ignore=unquoted-variable"),
+            vec![Check::UselessEval]
+        );
+        assert_eq!(lint(L!("eval echo hi"), &[Check::UselessEval]), vec![]);
+    }
+}
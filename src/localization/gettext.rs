@@ -54,6 +54,16 @@ fn gettext(message: MaybeStatic) -> &'static wstr {
     message_static_wstr
 }
 
+/// Localize a message with plural forms, choosing the form which is grammatically correct for the
+/// count `n`. If there is no localization, `singular` is used for a count of 1, `plural` otherwise.
+pub fn localize_plural(singular: &wstr, plural: &wstr, n: u64) -> WString {
+    #[cfg(feature = "localize-messages")]
+    if let Some(localized_str) = fish_gettext::lookup_plural(&singular.to_string(), n) {
+        return fish_widestring::str2wcstring(localized_str);
+    }
+    if n == 1 { singular } else { plural }.to_owned()
+}
+
 /// A type that can be either a static or local string.
 enum MaybeStatic<'a> {
     Static(&'static wstr),
@@ -116,6 +126,9 @@ impl std::fmt::Display for LocalizableString {
 /// This macro takes a string literal and produces a [`LocalizableString`].
 /// The essential part is the invocation of the proc macro,
 /// which ensures that the string gets extracted for localization.
+/// A note for translators can be added as `translator_note = "..."`, which ends up as a comment
+/// on the message in the PO files. Use it when the message alone does not provide enough context,
+/// e.g. for single words.
 #[macro_export]
 #[cfg(feature = "gettext-extract")]
 macro_rules! localizable_string {
@@ -124,11 +137,16 @@ macro_rules! localizable_string {
             fish_gettext_extraction::gettext_extract!($string)
         ))
     };
+    ($string:literal, translator_note = $note:literal) => {
+        $crate::localization::LocalizableString::Static(fish_widestring::L!(
+            fish_gettext_extraction::gettext_extract!($string, $note)
+        ))
+    };
 }
 #[macro_export]
 #[cfg(not(feature = "gettext-extract"))]
 macro_rules! localizable_string {
-    ($string:literal) => {
+    ($string:literal $(, translator_note = $note:literal)?) => {
         $crate::localization::LocalizableString::Static(fish_widestring::L!($string))
     };
 }
@@ -156,13 +174,15 @@ pub use localizable_consts;
 
 /// Takes a string literal of a [`LocalizableString`].
 /// Given a string literal, it is extracted for localization.
+/// Like for [`localizable_string`], a `translator_note` can follow the literal.
 /// Returns a possibly localized `&'static wstr`.
 #[macro_export]
 macro_rules! wgettext {
     (
         $string:literal
+        $(, translator_note = $note:literal)?
     ) => {
-        localizable_string!($string).localize()
+        localizable_string!($string $(, translator_note = $note)?).localize()
     };
     (
         $string:expr // format string (LocalizableString)
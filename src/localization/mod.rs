@@ -1,22 +1,24 @@
 mod gettext;
 pub use gettext::{
-    LocalizableString, localizable_consts, localizable_string, wgettext, wgettext_fmt,
+    LocalizableString, localizable_consts, localizable_string, localize_plural, wgettext,
+    wgettext_fmt,
 };
 #[cfg(feature = "localize-messages")]
 mod settings;
 #[cfg(feature = "localize-messages")]
 pub use settings::{
-    list_available_languages, status_language, unset_from_status_language_builtin, update_from_env,
-    update_from_status_language_builtin,
+    list_available_languages, message_language, status_language,
+    unset_from_status_language_builtin, update_from_env, update_from_status_language_builtin,
 };
 
 #[cfg(feature = "localize-messages")]
 /// This function only exists to provide a way for initializing gettext before an `EnvStack` is
 /// available. Without this, early error messages cannot be localized.
 pub fn initialize_localization() {
-    use crate::env::EnvStack;
+    use crate::env::{EnvStack, config_paths::locale_dir};
     use fish_widestring::L;
 
+    fish_gettext::set_installed_catalog_dir(locale_dir());
     let env = EnvStack::new();
     env_stack_set_from_env!(env, "LANGUAGE");
     env_stack_set_from_env!(env, "LC_ALL");
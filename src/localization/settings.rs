@@ -147,44 +147,44 @@ impl LocalizationState {
     /// Tries to find catalogs for `language`.
     /// `language` must be an ISO 639 language code, optionally followed by an underscore and an ISO
     /// 3166 country/territory code.
-    /// Uses the catalog with the exact same name as `language` if it exists.
-    /// If a country code is present (`ll_CC`), only the catalog named `ll` will be considered as a fallback.
-    /// If no country code is present (`ll`), all catalogs whose names start with `ll_` will be used in
-    /// arbitrary order.
+    /// The result is ordered by precedence, and messages missing from one catalog are looked up in
+    /// the next one.
+    /// If a country code is present (`ll_CC`), the catalog with the exact same name is used if it
+    /// exists, followed by the one named `ll`, so that partial translations of a variant can be
+    /// complemented by the general language.
+    /// If no country code is present (`ll`), the catalog named `ll` is used if it exists.
+    /// Otherwise, all catalogs whose names start with `ll_` will be used in arbitrary order.
     fn find_best_matches<'a, 'b: 'a, L: Copy>(
         language: &str,
         available_languages: &'a HashMap<&'b str, L>,
     ) -> Vec<(&'b str, L)> {
-        // Try the exact name first.
-        // If there already is a corresponding catalog return the language.
-        if let Some((&lang_str, &lang_value)) = available_languages.get_key_value(language) {
-            return vec![(lang_str, lang_value)];
-        }
+        let get = |lang: &str| {
+            available_languages
+                .get_key_value(lang)
+                .map(|(&lang_str, &lang_value)| (lang_str, lang_value))
+        };
         let language_without_country_code =
             language.split_once('_').map_or(language, |(ll, _cc)| ll);
-        if language == language_without_country_code {
-            // We have `ll` format. In this case, try to find any catalog whose name starts with `ll_`.
-            // Note that it is important to include the underscore in the pattern, otherwise `ll` might
-            // fall back to `llx_CC`, where `llx` is a 3-letter language identifier.
-            let ll_prefix = format!("{language}_");
-            let mut lang_catalogs = vec![];
-            for (&lang_str, &localization_lang) in available_languages.iter() {
-                if lang_str.starts_with(&ll_prefix) {
-                    lang_catalogs.push((lang_str, localization_lang));
-                }
-            }
-            lang_catalogs
-        } else {
-            // If `language` contained a country code, we only try to fall back to a catalog
-            // without a country code.
-            if let Some((&lang_str, &lang_value)) =
-                available_languages.get_key_value(language_without_country_code)
-            {
-                vec![(lang_str, lang_value)]
-            } else {
-                vec![]
+        if language != language_without_country_code {
+            return get(language)
+                .into_iter()
+                .chain(get(language_without_country_code))
+                .collect();
+        }
+        if let Some(exact_match) = get(language) {
+            return vec![exact_match];
+        }
+        // We have `ll` format. In this case, try to find any catalog whose name starts with `ll_`.
+        // Note that it is important to include the underscore in the pattern, otherwise `ll` might
+        // fall back to `llx_CC`, where `llx` is a 3-letter language identifier.
+        let ll_prefix = format!("{language}_");
+        let mut lang_catalogs = vec![];
+        for (&lang_str, &localization_lang) in available_languages.iter() {
+            if lang_str.starts_with(&ll_prefix) {
+                lang_catalogs.push((lang_str, localization_lang));
             }
         }
+        lang_catalogs
     }
 
     fn update_from_env(&mut self, localization_vars: LocalizationVariables) {
@@ -355,11 +355,11 @@ static LOCALIZATION_STATE: Mutex<LocalizationState> = Mutex::new(LocalizationSta
 ///    There, we normalize locale names by stripping off the suffix, leaving only the `ll_CC` part.
 /// 5. Otherwise, localization will not happen.
 ///
-/// If users specify `ll_CC` as a language and we don't have a catalog for this language, but we
-/// have one for `ll`, that will be used instead. If users specify `ll` (without specifying a
-/// language variant), which we discourage, and we don't have a catalog for `ll`, but we do have
-/// one for `ll_CC`, that will be used as a fallback. If we have multiple `ll_*` catalogs, all of
-/// them will be used, in arbitrary order.
+/// If users specify `ll_CC` as a language and we have a catalog for `ll`, it will be used for
+/// messages which are not translated in the `ll_CC` catalog, or instead of it if there is none.
+/// If users specify `ll` (without specifying a language variant), which we discourage, and we
+/// don't have a catalog for `ll`, but we do have one for `ll_CC`, that will be used as a fallback.
+/// If we have multiple `ll_*` catalogs, all of them will be used, in arbitrary order.
 pub fn update_from_env(env: &EnvStack) {
     let mut localization_state = LOCALIZATION_STATE.lock().unwrap();
     localization_state.update_from_env(LocalizationVariables::from_env(env));
@@ -409,6 +409,12 @@ pub fn status_language() -> WString {
     result
 }
 
+/// The language in which `message` is currently shown, or `None` if it is not localized, meaning it
+/// is shown in English.
+pub fn message_language(message: &wstr) -> Option<&'static str> {
+    fish_gettext::localizing_language(&message.to_string())
+}
+
 pub fn list_available_languages() -> WString {
     fish_gettext::get_available_languages()
         .keys()
@@ -416,3 +422,35 @@ pub fn list_available_languages() -> WString {
         .flat_map(|lang| [lang, "\n"])
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::LocalizationState;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_find_best_matches() {
+        let available: HashMap<&str, u8> = HashMap::from([
+            ("pt", 0),
+            ("pt_BR", 1),
+            ("de", 2),
+            ("zh_CN", 3),
+            ("zh_TW", 4),
+        ]);
+        let find = |language| -> Vec<&str> {
+            LocalizationState::find_best_matches(language, &available)
+                .into_iter()
+                .map(|(lang, _)| lang)
+                .collect()
+        };
+        assert_eq!(find("pt_BR"), ["pt_BR", "pt"]);
+        assert_eq!(find("pt_PT"), ["pt"]);
+        assert_eq!(find("pt"), ["pt"]);
+        assert_eq!(find("de_AT"), ["de"]);
+        assert_eq!(find("fr_FR"), Vec::<&str>::new());
+        let mut zh = find("zh");
+        zh.sort_unstable();
+        assert_eq!(zh, ["zh_CN", "zh_TW"]);
+        assert_eq!(find("zh_TW"), ["zh_TW"]);
+    }
+}
@@ -0,0 +1,732 @@
+//! A language server for fish script, started with `fish --lsp`.
+//!
+//! This speaks the Language Server Protocol as JSON-RPC over stdin and stdout, and answers
+//! requests with the same parser, highlighter and completion engine that the interactive shell
+//! uses. Documents are synced in full, and positions count UTF-16 code units as the protocol
+//! requires.
+
+use crate::{
+    ast::{self, Ast, Kind, Node as _},
+    builtins::{
+        builtin_exists, builtin_get_desc,
+        fish_indent::{format_script, format_script_lines},
+        fish_json::{Value, parse_json},
+    },
+    complete::{CompleteFlags, CompletionRequestOptions, complete, sort_and_prioritize},
+    flog::flog,
+    function,
+    highlight::{HighlightRole, highlight_shell},
+    lint::{Check, lint},
+    parse_constants::{ParseTreeFlags, SourceRange},
+    parse_util::{get_job_extent, get_token_extent},
+    parser::Parser,
+    path::path_get_path,
+    prelude::*,
+    reader::completion_apply_to_command_line,
+    wutil::fish_wcstoul,
+};
+use fish_common::{UnescapeStringStyle, unescape_string};
+use fish_widestring::{bytes2wcstring, wcs2bytes};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufWriter, Read as _, Write as _},
+    ops::{ControlFlow, Range},
+};
+
+// JSON-RPC error codes.
+const PARSE_ERROR: i32 = -32700;
+const INVALID_PARAMS: i32 = -32602;
+const METHOD_NOT_FOUND: i32 = -32601;
+
+/// The largest message body we accept, so a bogus Content-Length can't make us allocate
+/// arbitrary amounts of memory.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// The kinds of semantic tokens we report, in the order given to the client.
+const TOKEN_TYPES: &[&str] = &[
+    "function",
+    "keyword",
+    "parameter",
+    "comment",
+    "string",
+    "operator",
+];
+
+/// Serve requests read from stdin, writing responses to `output`, until the client asks us to
+/// exit or closes stdin.
+/// Returns the exit status: 0 if the client shut the server down properly, 1 otherwise.
+pub fn run(parser: &mut Parser, output: File) -> i32 {
+    let mut server = Server {
+        parser,
+        output: BufWriter::new(output),
+        documents: HashMap::new(),
+        shutdown_requested: false,
+    };
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    loop {
+        let body = match read_message(&mut input) {
+            Ok(Some(body)) => body,
+            Ok(None) => return server.exit_status(),
+            Err(err) => {
+                flog!(lsp, "Failed to read message:", err);
+                return 1;
+            }
+        };
+        let message = match parse_json(&bytes2wcstring(&body)) {
+            Ok(message) => message,
+            Err(err) => {
+                server.respond(&Value::Null, Err((PARSE_ERROR, err.describe())));
+                continue;
+            }
+        };
+        if let ControlFlow::Break(status) = server.handle(&message) {
+            return status;
+        }
+    }
+}
+
+/// Read the body of one message, after its headers. Returns None at the end of the input.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    loop {
+        let mut line = vec![];
+        if input.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = String::from_utf8_lossy(&line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let Some(content_length) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "missing Content-Length header",
+        ));
+    };
+    if content_length > MAX_MESSAGE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Content-Length exceeds the maximum message size",
+        ));
+    }
+    let mut body = vec![0; content_length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+fn write_message(output: &mut impl io::Write, message: &Value) -> io::Result<()> {
+    let body = wcs2bytes(&message.to_json());
+    write!(output, "Content-Length: {}\r\n\r\n", body.len())?;
+    output.write_all(&body)?;
+    output.flush()
+}
+
+fn object<const N: usize>(members: [(&str, Value); N]) -> Value {
+    Value::Object(
+        members
+            .into_iter()
+            .map(|(key, value)| (WString::from_str(key), value))
+            .collect(),
+    )
+}
+
+fn string(s: impl Into<WString>) -> Value {
+    Value::String(s.into())
+}
+
+fn number(n: usize) -> Value {
+    Value::Number(n.to_wstring())
+}
+
+fn member<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    let Value::Object(members) = value else {
+        return None;
+    };
+    members.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
+
+/// Look up a value by following the given keys through nested objects.
+fn lookup<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter().try_fold(value, |value, key| member(value, key))
+}
+
+fn lookup_str<'a>(value: &'a Value, path: &[&str]) -> Option<&'a wstr> {
+    match lookup(value, path)? {
+        Value::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+fn lookup_usize(value: &Value, path: &[&str]) -> Option<usize> {
+    match lookup(value, path)? {
+        Value::Number(n) => fish_wcstoul(n).ok()?.try_into().ok(),
+        _ => None,
+    }
+}
+
+/// Convert a character offset into a position, which counts lines and the UTF-16 code units
+/// within the line.
+fn position_from_offset(text: &wstr, offset: usize) -> Value {
+    let chars = &text.as_char_slice()[..offset.min(text.len())];
+    let line_start = chars.iter().rposition(|&c| c == '\n').map_or(0, |i| i + 1);
+    let line = chars[..line_start].iter().filter(|&&c| c == '\n').count();
+    let character = chars[line_start..].iter().map(|c| c.len_utf16()).sum();
+    object([("line", number(line)), ("character", number(character))])
+}
+
+/// Convert a position into a character offset. Positions past the end of a line refer to the
+/// end of the line.
+fn offset_from_position(text: &wstr, line: usize, character: usize) -> usize {
+    let chars = text.as_char_slice();
+    let mut offset = 0;
+    for _ in 0..line {
+        match chars[offset..].iter().position(|&c| c == '\n') {
+            Some(newline) => offset += newline + 1,
+            None => return chars.len(),
+        }
+    }
+    let mut units = 0;
+    while offset < chars.len() && chars[offset] != '\n' && units < character {
+        units += chars[offset].len_utf16();
+        offset += 1;
+    }
+    offset
+}
+
+fn range_value(text: &wstr, range: Range<usize>) -> Value {
+    object([
+        ("start", position_from_offset(text, range.start)),
+        ("end", position_from_offset(text, range.end)),
+    ])
+}
+
+/// A `file://` URI for an absolute path.
+fn path_to_uri(path: &wstr) -> WString {
+    let mut uri = WString::from_str("file://");
+    for b in wcs2bytes(path) {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            uri.push(char::from(b));
+        } else {
+            sprintf!(=> &mut uri, "%%%02X", b);
+        }
+    }
+    uri
+}
+
+/// Return the name of the command or function definition at the given offset, with its range.
+fn command_at(ast: &Ast, src: &wstr, offset: usize) -> Option<(WString, SourceRange)> {
+    for node in ast.walk() {
+        let name = match node.kind() {
+            Kind::DecoratedStatement(stmt) => stmt.command.try_source_range(),
+            Kind::FunctionHeader(header) => header.first_arg.try_source_range(),
+            _ => continue,
+        };
+        let Some(range) = name else {
+            continue;
+        };
+        if range.start() <= offset && offset <= range.end() {
+            let source = &src[range.start()..range.end()];
+            let name = unescape_string(source, UnescapeStringStyle::default())
+                .unwrap_or_else(|| source.to_owned());
+            return Some((name, range));
+        }
+    }
+    None
+}
+
+/// Find where a function is defined in the script itself.
+fn local_function_definition(ast: &Ast, src: &wstr, name: &wstr) -> Option<SourceRange> {
+    ast.walk().find_map(|node| {
+        let Kind::FunctionHeader(header) = node.kind() else {
+            return None;
+        };
+        let range = header.first_arg.try_source_range()?;
+        let source = &src[range.start()..range.end()];
+        let unescaped = unescape_string(source, UnescapeStringStyle::default());
+        (unescaped.as_deref() == Some(name)).then_some(range)
+    })
+}
+
+fn token_type(role: HighlightRole) -> Option<usize> {
+    match role {
+        HighlightRole::Command => Some(0),
+        HighlightRole::Keyword => Some(1),
+        HighlightRole::Param | HighlightRole::Option => Some(2),
+        HighlightRole::Comment => Some(3),
+        HighlightRole::Quote => Some(4),
+        HighlightRole::Operat | HighlightRole::Redirection | HighlightRole::StatementTerminator => {
+            Some(5)
+        }
+        _ => None,
+    }
+}
+
+struct Server<'a> {
+    parser: &'a mut Parser,
+    output: BufWriter<File>,
+    /// The text of the open documents, by URI.
+    documents: HashMap<WString, WString>,
+    shutdown_requested: bool,
+}
+
+type RequestResult = Result<Value, (i32, WString)>;
+
+impl Server<'_> {
+    fn exit_status(&self) -> i32 {
+        if self.shutdown_requested { 0 } else { 1 }
+    }
+
+    fn send(&mut self, message: Value) {
+        if let Err(err) = write_message(&mut self.output, &message) {
+            flog!(lsp, "Failed to write message:", err);
+        }
+    }
+
+    fn respond(&mut self, id: &Value, result: RequestResult) {
+        let payload = match result {
+            Ok(result) => ("result", result),
+            Err((code, message)) => (
+                "error",
+                object([
+                    ("code", Value::Number(code.to_wstring())),
+                    ("message", string(message)),
+                ]),
+            ),
+        };
+        self.send(object([
+            ("jsonrpc", string(L!("2.0"))),
+            ("id", id.clone()),
+            payload,
+        ]));
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(object([
+            ("jsonrpc", string(L!("2.0"))),
+            ("method", string(WString::from_str(method))),
+            ("params", params),
+        ]));
+    }
+
+    fn handle(&mut self, message: &Value) -> ControlFlow<i32> {
+        // Messages without a method are responses, but we never send requests.
+        let Some(method) = lookup_str(message, &["method"]) else {
+            return ControlFlow::Continue(());
+        };
+        let method: String = method.chars().collect();
+        let params = member(message, "params").unwrap_or(&Value::Null);
+        let result = match method.as_str() {
+            "initialize" => Ok(Self::capabilities()),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            }
+            "exit" => return ControlFlow::Break(self.exit_status()),
+            "textDocument/didOpen" => {
+                if let (Some(uri), Some(text)) = (
+                    lookup_str(params, &["textDocument", "uri"]),
+                    lookup_str(params, &["textDocument", "text"]),
+                ) {
+                    self.update_document(uri.to_owned(), text.to_owned());
+                }
+                return ControlFlow::Continue(());
+            }
+            "textDocument/didChange" => {
+                // We ask for full syncs, so the last change has the whole text.
+                let text = match lookup(params, &["contentChanges"]) {
+                    Some(Value::Array(changes)) => changes
+                        .last()
+                        .and_then(|change| lookup_str(change, &["text"])),
+                    _ => None,
+                };
+                if let (Some(uri), Some(text)) =
+                    (lookup_str(params, &["textDocument", "uri"]), text)
+                {
+                    self.update_document(uri.to_owned(), text.to_owned());
+                }
+                return ControlFlow::Continue(());
+            }
+            "textDocument/didClose" => {
+                if let Some(uri) = lookup_str(params, &["textDocument", "uri"]) {
+                    self.documents.remove(uri);
+                    self.notify(
+                        "textDocument/publishDiagnostics",
+                        object([("uri", string(uri)), ("diagnostics", Value::Array(vec![]))]),
+                    );
+                }
+                return ControlFlow::Continue(());
+            }
+            "textDocument/hover" => self.with_position(params, Self::hover),
+            "textDocument/definition" => self.with_position(params, Self::definition),
+            "textDocument/completion" => self.with_position(params, Self::completion),
+            "textDocument/semanticTokens/full" => {
+                self.with_document(params, |this, _, text| Ok(this.semantic_tokens(text)))
+            }
+            "textDocument/formatting" => {
+                self.with_document(params, |_, _, text| Ok(formatting_edits(text, None)))
+            }
+            "textDocument/rangeFormatting" => self.with_document(params, |_, _, text| {
+                let start = lookup_usize(params, &["range", "start", "line"]);
+                let end = lookup_usize(params, &["range", "end", "line"]);
+                let end_character = lookup_usize(params, &["range", "end", "character"]);
+                let (Some(start), Some(mut end)) = (start, end) else {
+                    return Err((INVALID_PARAMS, L!("Missing range").to_owned()));
+                };
+                // A range ending at the start of a line does not include that line.
+                if end_character != Some(0) || end == start {
+                    end += 1;
+                }
+                Ok(formatting_edits(text, Some(start..end)))
+            }),
+            _ => Err((
+                METHOD_NOT_FOUND,
+                sprintf!("Unknown method '%s'", method.as_str()),
+            )),
+        };
+        // Notifications get no response, even if we don't know them.
+        if let Some(id) = member(message, "id") {
+            self.respond(id, result);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn capabilities() -> Value {
+        let token_types = TOKEN_TYPES
+            .iter()
+            .map(|&name| string(WString::from_str(name)))
+            .collect();
+        object([
+            (
+                "capabilities",
+                object([
+                    // Full document sync.
+                    ("textDocumentSync", number(1)),
+                    ("hoverProvider", Value::Bool(true)),
+                    ("definitionProvider", Value::Bool(true)),
+                    (
+                        "completionProvider",
+                        object([(
+                            "triggerCharacters",
+                            Value::Array(vec![string(L!("-")), string(L!("$"))]),
+                        )]),
+                    ),
+                    (
+                        "semanticTokensProvider",
+                        object([
+                            (
+                                "legend",
+                                object([
+                                    ("tokenTypes", Value::Array(token_types)),
+                                    ("tokenModifiers", Value::Array(vec![])),
+                                ]),
+                            ),
+                            ("full", Value::Bool(true)),
+                        ]),
+                    ),
+                    ("documentFormattingProvider", Value::Bool(true)),
+                    ("documentRangeFormattingProvider", Value::Bool(true)),
+                ]),
+            ),
+            (
+                "serverInfo",
+                object([
+                    ("name", string(L!("fish"))),
+                    ("version", string(WString::from_str(crate::BUILD_VERSION))),
+                ]),
+            ),
+        ])
+    }
+
+    fn update_document(&mut self, uri: WString, text: WString) {
+        let diagnostics = diagnostics(&text);
+        self.notify(
+            "textDocument/publishDiagnostics",
+            object([
+                ("uri", string(uri.clone())),
+                ("diagnostics", Value::Array(diagnostics)),
+            ]),
+        );
+        self.documents.insert(uri, text);
+    }
+
+    /// Run a request on the document named in its parameters.
+    fn with_document(
+        &mut self,
+        params: &Value,
+        f: impl FnOnce(&mut Self, &wstr, &wstr) -> RequestResult,
+    ) -> RequestResult {
+        let Some(uri) = lookup_str(params, &["textDocument", "uri"]) else {
+            return Err((INVALID_PARAMS, L!("Missing document").to_owned()));
+        };
+        let Some(text) = self.documents.get(uri).cloned() else {
+            return Err((INVALID_PARAMS, sprintf!("Unknown document '%s'", uri)));
+        };
+        f(self, uri, &text)
+    }
+
+    /// Run a request on the document and position named in its parameters.
+    fn with_position(
+        &mut self,
+        params: &Value,
+        f: impl FnOnce(&mut Self, &wstr, &wstr, usize) -> Value,
+    ) -> RequestResult {
+        let line = lookup_usize(params, &["position", "line"]);
+        let character = lookup_usize(params, &["position", "character"]);
+        let (Some(line), Some(character)) = (line, character) else {
+            return Err((INVALID_PARAMS, L!("Missing position").to_owned()));
+        };
+        self.with_document(params, |this, uri, text| {
+            let offset = offset_from_position(text, line, character);
+            Ok(f(this, uri, text, offset))
+        })
+    }
+
+    fn hover(&mut self, _uri: &wstr, text: &wstr, offset: usize) -> Value {
+        let ast = ast::parse(text, ParseTreeFlags::default(), None);
+        let Some((name, range)) = command_at(&ast, text, offset) else {
+            return Value::Null;
+        };
+        let contents = if let Some(def) = local_function_definition(&ast, text, &name) {
+            let line = text[..def.start()].chars().filter(|&c| c == '\n').count() + 1;
+            sprintf!("**%s** (function, defined on line %d)", name, line)
+        } else if builtin_exists(&name) {
+            let mut contents = sprintf!("**%s** (builtin)", name);
+            if let Some(desc) = builtin_get_desc(&name) {
+                sprintf!(=> &mut contents, "\n\n%s", desc);
+            }
+            contents
+        } else if let Some(props) = function::get_props_autoload(&name, self.parser) {
+            let mut contents = sprintf!("**%s** (function)", name);
+            let desc = props.description.localize();
+            if !desc.is_empty() {
+                sprintf!(=> &mut contents, "\n\n%s", desc);
+            }
+            if let Some(file) = props.definition_file() {
+                sprintf!(
+                    => &mut contents,
+                    "\n\nDefined in `%s`, line %d",
+                    file,
+                    props.definition_lineno()
+                );
+            }
+            contents
+        } else if let Some(path) = path_get_path(&name, self.parser.vars()) {
+            sprintf!("**%s** (command `%s`)", name, path)
+        } else {
+            return Value::Null;
+        };
+        object([
+            (
+                "contents",
+                object([
+                    ("kind", string(L!("markdown"))),
+                    ("value", string(contents)),
+                ]),
+            ),
+            ("range", range_value(text, range.start()..range.end())),
+        ])
+    }
+
+    fn definition(&mut self, uri: &wstr, text: &wstr, offset: usize) -> Value {
+        let ast = ast::parse(text, ParseTreeFlags::default(), None);
+        let Some((name, _)) = command_at(&ast, text, offset) else {
+            return Value::Null;
+        };
+        if let Some(def) = local_function_definition(&ast, text, &name) {
+            return object([
+                ("uri", string(uri)),
+                ("range", range_value(text, def.start()..def.end())),
+            ]);
+        }
+        let Some(props) = function::get_props_autoload(&name, self.parser) else {
+            return Value::Null;
+        };
+        // Functions from the embedded files or from `source -` have no file we could show.
+        let Some(file) = props.definition_file().filter(|file| file.starts_with("/")) else {
+            return Value::Null;
+        };
+        let line_start = object([
+            ("line", number(props.definition_lineno() as usize - 1)),
+            ("character", number(0)),
+        ]);
+        object([
+            ("uri", string(path_to_uri(file))),
+            (
+                "range",
+                object([("start", line_start.clone()), ("end", line_start)]),
+            ),
+        ])
+    }
+
+    fn completion(&mut self, _uri: &wstr, text: &wstr, offset: usize) -> Value {
+        // Complete the job up to the cursor, like the reader does for the command line.
+        let job = get_job_extent(text, offset, None);
+        let cmdline = &text[job.start..offset];
+        let (token, _) = get_token_extent(cmdline, cmdline.len());
+        let edit_range = range_value(text, job.start + token.start..offset);
+
+        let flags = CompletionRequestOptions::normal();
+        let mut ctx = self.parser.context();
+        let (mut completions, _) = complete(cmdline, flags, &mut ctx);
+        sort_and_prioritize(&mut completions, flags);
+
+        let mut items = vec![];
+        for (i, comp) in completions.iter().enumerate() {
+            if comp.replaces_line() {
+                continue;
+            }
+            let mut cursor = cmdline.len();
+            let applied = completion_apply_to_command_line(
+                &mut ctx,
+                &comp.completion,
+                comp.flags | CompleteFlags::NO_SPACE,
+                cmdline,
+                &mut cursor,
+                /*append_only=*/ false,
+                /*is_unique=*/ false,
+            );
+            let new_text = applied[token.start..cursor].to_owned();
+            items.push(object([
+                ("label", string(new_text.clone())),
+                ("detail", string(comp.description.clone())),
+                // Keep our order instead of the client's.
+                ("sortText", Value::String(sprintf!("%08d", i))),
+                (
+                    "textEdit",
+                    object([("range", edit_range.clone()), ("newText", string(new_text))]),
+                ),
+            ]));
+        }
+        Value::Array(items)
+    }
+
+    fn semantic_tokens(&mut self, text: &wstr) -> Value {
+        let mut colors = vec![];
+        highlight_shell(text, &mut colors, &mut self.parser.context(), true, None);
+
+        // Each token is given as five numbers: the line relative to the previous token, the start
+        // relative to the previous token if on the same line, the length, the type and modifiers.
+        let chars = text.as_char_slice();
+        let mut data = vec![];
+        let (mut line, mut column) = (0, 0);
+        let (mut prev_line, mut prev_start) = (0, 0);
+        let mut i = 0;
+        while i < chars.len() {
+            if chars[i] == '\n' {
+                line += 1;
+                column = 0;
+                i += 1;
+                continue;
+            }
+            let typ = token_type(colors[i].foreground);
+            let start = column;
+            while i < chars.len() && chars[i] != '\n' && token_type(colors[i].foreground) == typ {
+                column += chars[i].len_utf16();
+                i += 1;
+            }
+            let Some(typ) = typ else {
+                continue;
+            };
+            let delta_start = if line == prev_line {
+                start - prev_start
+            } else {
+                start
+            };
+            data.extend([line - prev_line, delta_start, column - start, typ, 0]);
+            (prev_line, prev_start) = (line, start);
+        }
+        object([("data", Value::Array(data.into_iter().map(number).collect()))])
+    }
+}
+
+/// Parse errors, and warnings from the linter.
+fn diagnostics(text: &wstr) -> Vec<Value> {
+    lint(text, &[])
+        .into_iter()
+        .map(|issue| {
+            let range = ("range", range_value(text, issue.range));
+            let source = ("source", string(L!("fish")));
+            let message = ("message", string(issue.message));
+            if issue.check == Check::SyntaxError {
+                // Error.
+                object([range, ("severity", number(1)), source, message])
+            } else {
+                // Warning.
+                let code = ("code", string(issue.check.name()));
+                object([range, ("severity", number(2)), code, source, message])
+            }
+        })
+        .collect()
+}
+
+/// The edits to format the given lines, or the whole document. We replace the whole text, which
+/// is simple and what editors cope with best.
+fn formatting_edits(text: &wstr, lines: Option<Range<usize>>) -> Value {
+    let formatted = match lines {
+        Some(lines) => format_script_lines(text, lines),
+        None => format_script(text),
+    };
+    if formatted.as_utfstr() == text {
+        return Value::Array(vec![]);
+    }
+    Value::Array(vec![object([
+        ("range", range_value(text, 0..text.len())),
+        ("newText", string(formatted)),
+    ])])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{offset_from_position, position_from_offset, read_message};
+    use crate::builtins::fish_json::Value;
+    use crate::prelude::*;
+
+    fn position(text: &wstr, offset: usize) -> (WString, WString) {
+        let Value::Object(members) = position_from_offset(text, offset) else {
+            panic!("position is not an object");
+        };
+        match &members[..] {
+            [(_, Value::Number(line)), (_, Value::Number(character))] => {
+                (line.clone(), character.clone())
+            }
+            _ => panic!("unexpected position {:?}", members),
+        }
+    }
+
+    #[test]
+    fn test_positions() {
+        let text = L!("echo a\nécho 😀 b\n");
+        assert_eq!(position(text, 0), (L!("0").into(), L!("0").into()));
+        assert_eq!(position(text, 7), (L!("1").into(), L!("0").into()));
+        // The emoji takes two UTF-16 code units.
+        assert_eq!(position(text, 13), (L!("1").into(), L!("7").into()));
+        assert_eq!(offset_from_position(text, 1, 7), 13);
+        assert_eq!(offset_from_position(text, 0, 100), 6);
+        assert_eq!(offset_from_position(text, 5, 0), text.len());
+    }
+
+    #[test]
+    fn test_read_message() {
+        let mut input: &[u8] =
+            b"Content-Length: 2\r\nContent-Type: x\r\n\r\n{}content-length: 4\r\n\r\nnull";
+        assert_eq!(read_message(&mut input).unwrap(), Some(b"{}".to_vec()));
+        assert_eq!(read_message(&mut input).unwrap(), Some(b"null".to_vec()));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+
+        let mut input: &[u8] = b"\r\n{}";
+        assert!(read_message(&mut input).is_err());
+
+        let mut input: &[u8] = b"Content-Length: 18446744073709551615\r\n\r\n{}";
+        let err = read_message(&mut input).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
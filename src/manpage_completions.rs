@@ -0,0 +1,1176 @@
+//! Generating completions from manual pages, for `fish_update_completions`.
+//!
+//! There is no single format for the options in a man page, so this tries a series of parsers for
+//! the layouts produced by the usual generators (help2man, pod2man, asciidoc, scdoc and mdoc), and
+//! finally strips all roff formatting and looks for lines that start with a dash.
+
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fs,
+    io::{self, BufRead as _, BufReader},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+/// Commands whose man pages are not worth parsing, because the generated completions would be
+/// wrong or hide better ones.
+const IGNORED_COMMANDS: &[&str] = &[
+    "awk", "c++", "cc", "cpp", "emacs", "g++", "gcc", "gprof", "ld", "wget",
+];
+
+/// Prefixes of commands with lots of man pages for subcommands, most of which we already complete.
+const IGNORED_PREFIXES: &[&str] = &[
+    "bundle-", "cargo-", "ffmpeg-", "flatpak-", "gcloud_", "git-", "jj-", "npm-", "openssl-",
+    "ostree-", "perf-", "perl", "pip-", "zsh",
+];
+
+/// Generated files say this in their leading comment, so we know we may overwrite them.
+const AUTOGENERATED_MARKER: &str = "Autogenerated";
+
+/// The longest description we generate, longer ones are cut after a sentence or truncated.
+const MAX_DESCRIPTION_WIDTH: usize = 78;
+
+/// The sections of the manual with commands.
+const COMMAND_SECTIONS: &[&str] = &["man1", "man6", "man8"];
+
+/// Return the command documented by the man page at this path, like `mkfs.xfs` for
+/// `mkfs.xfs.8.gz`.
+pub fn command_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    let name = match name.rsplit_once('.') {
+        Some((name, "gz" | "bz2" | "xz" | "lzma" | "zst")) => name,
+        _ => &name,
+    };
+    match name.rsplit_once('.') {
+        Some((name, section)) if is_section(section) => Some(name.to_owned()),
+        _ => None,
+    }
+}
+
+/// Whether this is a section of the manual, which may have a suffix, like `1` or `1ssl`.
+fn is_section(s: &str) -> bool {
+    s.starts_with(|c: char| ('1'..='9').contains(&c))
+        && s.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Read the man page at this path, decompressing it with the usual tools if needed. Pages that
+/// aren't UTF-8 are read as Latin-1.
+pub fn read_manpage(path: &Path) -> io::Result<String> {
+    let decompressor = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => Some("gzip"),
+        Some("bz2") => Some("bzip2"),
+        Some("xz" | "lzma") => Some("xz"),
+        Some("zst") => Some("zstd"),
+        _ => None,
+    };
+    let bytes = match decompressor {
+        Some(program) => {
+            let output = Command::new(program)
+                .arg("-dc")
+                .arg(path)
+                .stdin(Stdio::null())
+                .stderr(Stdio::null())
+                .output()?;
+            if !output.status.success() {
+                return Err(io::Error::other(format!("{program} failed")));
+            }
+            output.stdout
+        }
+        None => fs::read(path)?,
+    };
+    Ok(String::from_utf8(bytes)
+        .unwrap_or_else(|err| err.into_bytes().into_iter().map(char::from).collect()))
+}
+
+/// Generate the completions for `cmd` from its man page, which was read from `path`. Returns
+/// `None` if the page should be skipped, or it has no options we understand.
+pub fn manpage_completions(cmd: &str, path: &str, manpage: &str) -> Option<String> {
+    if IGNORED_COMMANDS.contains(&cmd)
+        || IGNORED_PREFIXES
+            .iter()
+            .any(|prefix| cmd.starts_with(prefix))
+        // Ignore the many links to BUILTIN(1).
+        || manpage.contains("BUILTIN 1")
+        || manpage.contains("builtin.1")
+    {
+        return None;
+    }
+
+    let mut completions = Completions::new(cmd);
+    // Parsers that don't find what they expect add what they can and let the next one try, until
+    // one succeeds.
+    let parsers: &[ManpageParser] = &[
+        (is_scdoc, parse_scdoc),
+        (|m| m.contains(".SH \"OPTIONS\""), parse_quoted_options),
+        (|m| m.contains(".SH OPTIONS"), parse_options),
+        (
+            |m| m.contains(".SH FUNCTION LETTERS"),
+            parse_function_letters,
+        ),
+        (|m| m.contains(".SH DESCRIPTION"), parse_description),
+        (
+            |m| m.contains(".Sh DESCRIPTION") || m.contains(".SH DESCRIPTION"),
+            parse_mdoc,
+        ),
+        (|_| true, parse_deroffed),
+    ];
+    let success = parsers
+        .iter()
+        .filter(|(is_my_type, _)| is_my_type(manpage))
+        .any(|(_, parse)| parse(&mut completions, manpage) && !completions.lines.is_empty());
+    if !success {
+        return None;
+    }
+
+    let mut result = format!("# {cmd}\n# {AUTOGENERATED_MARKER} from man page {path}\n");
+    for line in &completions.lines {
+        result.push_str(line);
+        result.push('\n');
+    }
+    Some(result)
+}
+
+/// Return the man pages for commands in the manpath, as told by `man --path` or `manpath`, or
+/// `$MANPATH`, or the man configuration. `env` are the exported variables.
+pub fn manpath_pages(env: &[(OsString, OsString)]) -> Vec<PathBuf> {
+    let mut directories = vec![];
+    for (program, args) in [("man", &["--path"][..]), ("manpath", &[])] {
+        let output = Command::new(program)
+            .args(args)
+            .env_clear()
+            .envs(env.iter().map(|(name, value)| (name, value)))
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        if let Ok(output) = output {
+            if output.status.success() {
+                let output = String::from_utf8_lossy(&output.stdout);
+                directories = output.trim().split(':').map(PathBuf::from).collect();
+                break;
+            }
+        }
+    }
+    if directories.is_empty() {
+        if let Some((_, manpath)) = env.iter().find(|(name, _)| name == "MANPATH") {
+            let manpath = manpath.to_string_lossy();
+            directories = manpath.trim().split(':').map(PathBuf::from).collect();
+        }
+    }
+    if directories.is_empty() {
+        // mandoc and NetBSD's man only have the default manpath in their configuration.
+        if let Ok(config) = fs::read_to_string("/etc/man.conf") {
+            for line in config.lines() {
+                let Some((key, value)) = line.split_once(char::is_whitespace) else {
+                    continue;
+                };
+                if key.eq_ignore_ascii_case("MANPATH") || key.eq_ignore_ascii_case("_default") {
+                    directories.push(PathBuf::from(value.trim()));
+                }
+            }
+        }
+    }
+    if directories.is_empty() {
+        directories = ["/usr/share/man", "/usr/local/man", "/usr/local/share/man"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+    }
+
+    let mut result = vec![];
+    for directory in directories {
+        for section in COMMAND_SECTIONS {
+            let Ok(entries) = fs::read_dir(directory.join(section)) else {
+                continue;
+            };
+            let mut pages: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            pages.sort();
+            result.extend(pages);
+        }
+    }
+    result
+}
+
+/// Whether the completion file at this path was generated from a man page, so we may overwrite it.
+pub fn is_autogenerated(path: &Path) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    for line in BufReader::new(file).lines() {
+        let Ok(line) = line else {
+            return false;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // Only look at the leading comments.
+        if !line.starts_with('#') {
+            return false;
+        }
+        if line.contains(AUTOGENERATED_MARKER) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Remove the generated completion files in this directory.
+pub fn remove_autogenerated(directory: &Path) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "fish") && is_autogenerated(&path) {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// A check whether a man page looks like one kind of page, and the parser for that kind, which
+/// returns whether it found what it expected.
+type ManpageParser = (fn(&str) -> bool, fn(&mut Completions, &str) -> bool);
+
+/// The completions generated for a command.
+struct Completions {
+    cmd: String,
+    lines: Vec<String>,
+    /// The options we already have completions for, like `-s a`.
+    seen: HashSet<String>,
+}
+
+impl Completions {
+    fn new(cmd: &str) -> Self {
+        Completions {
+            cmd: cmd.to_owned(),
+            lines: vec![],
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Add a completion for the options in `options`, like `-a, --all`, with this description.
+    fn add(&mut self, options: &str, description: &str) {
+        let mut args = vec![];
+        for word in options.split([' ', ',', '"', '=', '|']) {
+            let word = remove_brackets(word);
+            let option = word.trim_matches(|c: char| c.is_whitespace() || "[](){}.,:!".contains(c));
+            if option == "-" || option == "--" || option.contains(['{', '}', '(', ')']) {
+                continue;
+            }
+            let arg = if let Some(long) = option.strip_prefix("--") {
+                format!("-l {}", escape(long))
+            } else if let Some(short) = option.strip_prefix('-') {
+                if short.chars().count() == 1 {
+                    format!("-s {}", escape(short))
+                } else {
+                    format!("-o {}", escape(short))
+                }
+            } else {
+                continue;
+            };
+            if self.seen.insert(arg.clone()) {
+                args.push(arg);
+            }
+        }
+        if args.is_empty() {
+            return;
+        }
+
+        let mut line = format!("complete -c {} {}", escape(&self.cmd), args.join(" "));
+        let description = truncate_description(description);
+        if !description.is_empty() {
+            line.push_str(" -d ");
+            line.push_str(&escape(&description));
+        }
+        self.lines.push(line);
+    }
+
+    /// Add a completion for the text of an option item, whose first line lists the options and
+    /// the rest describes them. Returns `false` if there is no description.
+    fn add_item(&mut self, item: &str) -> bool {
+        let item = remove_groff_formatting(item);
+        let Some((options, description)) = item.trim().split_once('\n') else {
+            return false;
+        };
+        let options = options.trim();
+        if options.contains('-') {
+            self.add(unquote(options), &description.trim().replace('\n', " "));
+        }
+        true
+    }
+}
+
+/// Remove everything from the first opening bracket to the last closing one.
+fn remove_brackets(word: &str) -> Cow<'_, str> {
+    match (word.find('['), word.rfind(']')) {
+        (Some(start), Some(end)) if start < end => {
+            Cow::Owned(format!("{}{}", &word[..start], &word[end + 1..]))
+        }
+        _ => Cow::Borrowed(word),
+    }
+}
+
+/// Keep as many whole sentences of the description as fit, or truncate the first one.
+fn truncate_description(description: &str) -> String {
+    let description = description.replace("\\'", "'").replace("\\.", ".");
+    let mut sentences = description.split('.').filter(|s| !s.trim().is_empty());
+    let mut result = format!("{}.", sentences.next().unwrap_or("").trim());
+    for sentence in sentences {
+        let proposed = format!("{result} {}.", sentence.trim());
+        if proposed.chars().count() > MAX_DESCRIPTION_WIDTH {
+            break;
+        }
+        result = proposed;
+    }
+    let result = result.trim_matches('.');
+    if result.chars().count() > MAX_DESCRIPTION_WIDTH {
+        let mut truncated: String = result.chars().take(MAX_DESCRIPTION_WIDTH - 1).collect();
+        truncated.push('…');
+        truncated
+    } else {
+        result.to_owned()
+    }
+}
+
+/// Quote a string for a fish script, unless it only has characters that don't need quoting.
+fn escape(s: &str) -> Cow<'_, str> {
+    if s.chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_+-/:=@".contains(c))
+    {
+        return Cow::Borrowed(s);
+    }
+    Cow::Owned(format!(
+        "'{}'",
+        s.replace('\\', "\\\\").replace('\'', "\\'")
+    ))
+}
+
+/// Remove surrounding double quotes or `quotes'.
+fn unquote(s: &str) -> &str {
+    let s = s
+        .strip_prefix('"')
+        .and_then(|s| s.strip_suffix('"'))
+        .unwrap_or(s);
+    s.strip_prefix('`')
+        .and_then(|s| s.strip_suffix('\''))
+        .unwrap_or(s)
+}
+
+/// Remove the roff formatting that is common in option lists.
+fn remove_groff_formatting(data: &str) -> String {
+    let mut result = String::with_capacity(data.len());
+    let mut rest = data;
+    // Remove \X'...' device control escapes (used for hyperlinks by help2man) and \Z'...' escapes.
+    while let Some(start) = rest.find('\\') {
+        result.push_str(&rest[..start]);
+        let escape = &rest[start..];
+        let device_control_len = (escape.starts_with("\\X'") || escape.starts_with("\\Z'"))
+            .then(|| escape[3..].find('\''))
+            .flatten();
+        if let Some(len) = device_control_len {
+            rest = &escape[3 + len + 1..];
+        } else {
+            result.push('\\');
+            rest = &escape[1..];
+        }
+    }
+    result.push_str(rest);
+
+    // Remove paragraph distance requests like ".PD 0".
+    while let Some(start) = result.find(".PD ") {
+        let digits = result[start + 4..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(result.len() - start - 4);
+        if digits == 0 {
+            break;
+        }
+        result.replace_range(start..start + 4 + digits, "");
+    }
+
+    for (from, to) in [
+        ("\\fI", ""),
+        ("\\fP", ""),
+        ("\\f1", ""),
+        ("\\fB", ""),
+        ("\\fR", ""),
+        ("\\e", ""),
+        (".BI", ""),
+        (".BR", ""),
+        ("0.5i", ""),
+        (".rb", ""),
+        ("\\^", ""),
+        ("{ ", ""),
+        (" }", ""),
+        ("\\ ", ""),
+        ("\\-", "-"),
+        ("\\&", ""),
+        (".B", ""),
+        (".I", ""),
+        ("\x0c", ""),
+        ("\\(oq", "'"),
+        ("\\(cq", "'"),
+        ("\\(aq", "'"),
+        ("\\(dq", "\""),
+        ("\\(lq", "\""),
+        ("\\(rq", "\""),
+    ] {
+        result = result.replace(from, to);
+    }
+    result
+}
+
+/// The text of the section starting with this heading, up to the next section.
+fn section<'a>(manpage: &'a str, heading: &str) -> Option<&'a str> {
+    let rest = &manpage[manpage.find(heading)? + heading.len()..];
+    Some(&rest[..rest.find(".SH").unwrap_or(rest.len())])
+}
+
+/// Find the first of `needles` in `haystack`, returning where it starts and ends.
+fn find_any(haystack: &str, needles: &[&str]) -> Option<(usize, usize)> {
+    needles
+        .iter()
+        .filter_map(|needle| {
+            haystack
+                .find(needle)
+                .map(|start| (start, start + needle.len()))
+        })
+        .min_by_key(|&(start, _)| start)
+}
+
+/// Call `f` with the text between each of the `open` markers and the next `close` marker. The
+/// search for the next item starts at the closing marker, so it can open the next item. Stops
+/// when `f` returns `false`, or there are no more items. Returns `false` if there were none.
+fn for_each_item(
+    mut s: &str,
+    open: &[&str],
+    close: &[&str],
+    mut f: impl FnMut(&str) -> bool,
+) -> bool {
+    let mut found = false;
+    while let Some((_, start)) = find_any(s, open) {
+        let Some((end, _)) = find_any(&s[start..], close) else {
+            break;
+        };
+        found = true;
+        if !f(&s[start..start + end]) {
+            break;
+        }
+        s = &s[start + end..];
+    }
+    found
+}
+
+/// Remove an indentation argument, like the ` 4` in `.TP 4`.
+fn skip_indent(item: &str) -> &str {
+    let Some(rest) = item.strip_prefix(' ') else {
+        return item;
+    };
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    if digits == 0 {
+        return item;
+    }
+    let mut rest = &rest[digits..];
+    if let Some(fraction) = rest.strip_prefix('.') {
+        if fraction.starts_with(|c: char| c.is_ascii_digit()) {
+            rest = &fraction[1..];
+        }
+    }
+    rest.strip_prefix('i').unwrap_or(rest)
+}
+
+fn is_scdoc(manpage: &str) -> bool {
+    manpage
+        .find(".\\\" Generated by scdoc")
+        .is_some_and(|start| manpage[start..].contains(".SH OPTIONS"))
+}
+
+/// Pages generated by scdoc, where each option is an item ending in `.RE`.
+fn parse_scdoc(completions: &mut Completions, manpage: &str) -> bool {
+    let Some(start) = manpage.find(".SH OPTIONS") else {
+        return false;
+    };
+    let options = &manpage[start + ".SH OPTIONS".len()..];
+    let Some(end) = options.find(".SH") else {
+        return false;
+    };
+    let mut items = options[..end].split(".RE").collect::<Vec<_>>();
+    // The text after the last item.
+    items.pop();
+    for item in items {
+        let item = remove_groff_formatting(item);
+        let mut lines = item
+            .split('\n')
+            .filter(|line| !matches!(*line, "" | ".P" | ".RS 4"));
+        if let (Some(options), Some(description)) = (lines.next(), lines.next()) {
+            completions.add(unquote(options), description);
+        }
+    }
+    true
+}
+
+/// Pages with a `.SH "OPTIONS"` section, as generated by pod2man and asciidoc.
+fn parse_quoted_options(completions: &mut Completions, manpage: &str) -> bool {
+    let Some(options) = section(manpage, ".SH \"OPTIONS\"") else {
+        return false;
+    };
+    let found = for_each_item(options, &[".PP"], &[".RE"], |item| {
+        let item = item.rfind(".PP").map_or(item, |last| &item[last + 3..]);
+        let item = remove_groff_formatting(item);
+        let mut parts = item.split(".RS 4");
+        let (Some(options), Some(description)) = (parts.next(), parts.next()) else {
+            return false;
+        };
+        let options = options.trim();
+        if options.contains('-') {
+            completions.add(unquote(options), &description.trim().replace('\n', " "));
+        }
+        true
+    });
+    if found {
+        return false;
+    }
+
+    // Items of tagged paragraphs.
+    let mut ok = true;
+    let found = for_each_item(options, &[".TP"], &[".TP"], |item| {
+        let item = skip_indent(item);
+        ok = has_description(item);
+        ok && completions.add_item(item)
+    });
+    if found && ok {
+        return true;
+    }
+
+    // Items of indented paragraphs, without index entries.
+    let options = options
+        .split('\n')
+        .map(|line| line.find(".IX").map_or(line, |start| &line[..start]))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let mut ok = true;
+    for_each_item(&options, &[".IP "], &[".IP"], |item| {
+        ok = has_description(item);
+        if !ok {
+            return false;
+        }
+        let item = remove_groff_formatting(item);
+        let (options, description) = item.trim().split_once('\n').unwrap();
+        let options = options
+            .trim()
+            .trim_end_matches(|c: char| c.is_ascii_digit());
+        if options.contains('-') {
+            completions.add(
+                unquote(options.trim()),
+                &description.trim().replace('\n', " "),
+            );
+        }
+        true
+    }) && ok
+}
+
+/// Whether the item has a non-empty description after the line with the options.
+fn has_description(item: &str) -> bool {
+    remove_groff_formatting(item)
+        .trim()
+        .split_once('\n')
+        .is_some_and(|(_, description)| !description.trim().is_empty())
+}
+
+/// Pages with an `.SH OPTIONS` section, with tagged or indented paragraphs.
+fn parse_options(completions: &mut Completions, manpage: &str) -> bool {
+    let Some(options) = section(manpage, ".SH OPTIONS") else {
+        return false;
+    };
+    for_each_item(
+        options,
+        &[".IP", ".TP"],
+        &[".IP", ".TP", ".UNINDENT", ".UN", ".SH"],
+        |item| {
+            let item = skip_indent(item);
+            if has_description(item) {
+                completions.add_item(item);
+            }
+            true
+        },
+    );
+    false
+}
+
+/// Pages for tar-like commands, which call their options function letters.
+fn parse_function_letters(completions: &mut Completions, manpage: &str) -> bool {
+    if let Some(options) = section(manpage, ".SH FUNCTION LETTERS") {
+        for_each_item(options, &[".TP"], &[".TP"], |item| {
+            completions.add_item(item)
+        });
+    }
+    false
+}
+
+/// Pages that list the options in tagged paragraphs in their description.
+fn parse_description(completions: &mut Completions, manpage: &str) -> bool {
+    if let Some(options) = section(manpage, ".SH DESCRIPTION") {
+        for_each_item(options, &[".TP"], &[".TP"], |item| {
+            completions.add_item(item)
+        });
+    }
+    false
+}
+
+/// Pages written with the mdoc macros, as on BSD and macOS, where options are list items like
+/// `.It Fl a Ar file`.
+fn parse_mdoc(completions: &mut Completions, manpage: &str) -> bool {
+    fn is_option(line: &str) -> bool {
+        line.starts_with(".It Fl")
+    }
+    fn count_dashes(line: &str) -> usize {
+        let mut rest = line.get(4..).unwrap_or("");
+        let mut count = 0;
+        while let Some(r) = rest.strip_prefix("Fl ") {
+            count += 1;
+            rest = r;
+        }
+        count
+    }
+    fn replace_escapes(line: &str, cmd: &str) -> String {
+        line.replace(".Nm", cmd)
+            .replace("\\ ", " ")
+            .replace("\\& ", "")
+            .replace(".Pp", "")
+    }
+    fn trim_groff(line: &str) -> String {
+        let mut line = line.strip_prefix('.').unwrap_or(line);
+        // Skip the leading macros.
+        while let [upper, lower, space, ..] = line.as_bytes() {
+            if !(upper.is_ascii_uppercase()
+                && lower.is_ascii_lowercase()
+                && space.is_ascii_whitespace())
+            {
+                break;
+            }
+            line = &line[3..];
+        }
+        // A macro without arguments, like `.El`.
+        if let [upper, lower] = line.as_bytes() {
+            if upper.is_ascii_uppercase() && lower.is_ascii_lowercase() {
+                return String::new();
+            }
+        }
+        // Lines like `.Ar projectname .` have a space before the punctuation.
+        let mut line = line.to_owned();
+        if line.ends_with(" ,") || line.ends_with(" .") {
+            line.remove(line.len() - 2);
+        }
+        line
+    }
+
+    let cmd = completions.cmd.clone();
+    let mut lines = manpage
+        .lines()
+        .skip_while(|line| {
+            !(line.starts_with(".Sh DESCRIPTION") || line.starts_with(".SH DESCRIPTION"))
+        })
+        .peekable();
+    let mut found = false;
+    while let Some(line) = lines.find(|line| is_option(line)) {
+        let dashes = count_dashes(line);
+        let line = trim_groff(&replace_escapes(line, &cmd));
+        let Some(name) = line.split_whitespace().next() else {
+            continue;
+        };
+
+        let mut description = vec![];
+        while let Some(line) = lines.next_if(|line| !is_option(line)) {
+            let line = line.trim();
+            if line.starts_with(".\\\"") {
+                continue;
+            }
+            let line = if line.starts_with('.') {
+                trim_groff(&replace_escapes(line, &cmd)).trim().to_owned()
+            } else {
+                line.to_owned()
+            };
+            if !line.is_empty() {
+                description.push(line);
+            }
+        }
+        let description = description.join(" ");
+
+        if name == "-" {
+            // The option is `--`.
+            continue;
+        }
+        let dashes = if name.chars().count() == 1 { 1 } else { dashes };
+        completions.add(&format!("{}{name}", "-".repeat(dashes)), &description);
+        found = true;
+    }
+    found
+}
+
+/// The last resort: strip all formatting and look for lines starting with a dash in the
+/// description or options.
+fn parse_deroffed(completions: &mut Completions, manpage: &str) -> bool {
+    let text = deroff(manpage);
+    let mut lines = text
+        .split('\n')
+        .skip_while(|line| {
+            !(line.starts_with("DESCRIPTION")
+                || line.starts_with("OPTIONS")
+                || line.starts_with("COMMAND OPTIONS"))
+        })
+        .take_while(|line| !line.starts_with("BUGS"))
+        .peekable();
+    let mut found = false;
+    while let Some(options) = lines.find(|line| line.starts_with('-')) {
+        let mut description = String::new();
+        while let Some(line) = lines.next_if(|line| !line.is_empty() && !line.starts_with('-')) {
+            if !description.is_empty() {
+                description.push(' ');
+            }
+            description.push_str(line);
+        }
+        completions.add(options, &description);
+        found = true;
+    }
+    found
+}
+
+/// Roff special characters, like `\(em` or `\[em]`.
+const SPECIAL_CHARACTERS: &[(&str, &str)] = &[
+    ("mi", "-"),
+    ("en", "-"),
+    ("hy", "-"),
+    ("em", "--"),
+    ("lq", "“"),
+    ("rq", "”"),
+    ("Bq", ",,"),
+    ("oq", "`"),
+    ("cq", "'"),
+    ("aq", "'"),
+    ("dq", "\""),
+    ("or", "|"),
+    ("at", "@"),
+    ("sh", "#"),
+    ("Eu", "€"),
+    ("eu", "€"),
+    ("Do", "$"),
+    ("ct", "¢"),
+    ("Fo", "«"),
+    ("Fc", "»"),
+    ("fo", "<"),
+    ("fc", ">"),
+    ("r!", "¡"),
+    ("r?", "¿"),
+    ("<-", "<-"),
+    ("->", "->"),
+    ("<>", "<->"),
+    ("lA", "<="),
+    ("rA", "=>"),
+    ("hA", "<=>"),
+    ("ua", "^"),
+    ("da", "v"),
+    ("ba", "|"),
+    ("bb", "|"),
+    ("br", "|"),
+    ("bv", "|"),
+    ("ru", "_"),
+    ("ul", "_"),
+    ("bu", "o"),
+    ("co", "©"),
+    ("rg", "®"),
+    ("tm", "(TM)"),
+    ("ps", "¶"),
+    ("sc", "§"),
+    ("de", "°"),
+    ("12", "½"),
+    ("14", "¼"),
+    ("34", "¾"),
+    ("f/", "/"),
+    ("sl", "/"),
+    ("rs", "\\"),
+    ("sq", "[]"),
+    ("fm", "'"),
+    ("ha", "^"),
+    ("ti", "~"),
+    ("lB", "["),
+    ("rB", "]"),
+    ("lC", "{"),
+    ("rC", "}"),
+    ("la", "<"),
+    ("ra", ">"),
+    ("**", "*"),
+    ("+-", "±"),
+    ("<=", "<="),
+    (">=", ">="),
+    ("!=", "!="),
+    ("==", "=="),
+    ("mu", "×"),
+    ("di", "÷"),
+    ("pl", "+"),
+    ("eq", "="),
+    ("ap", "~"),
+    ("AE", "Æ"),
+    ("ae", "æ"),
+    ("ss", "ß"),
+    (":a", "ä"),
+    (":o", "ö"),
+    (":u", "ü"),
+    (":A", "Ä"),
+    (":O", "Ö"),
+    (":U", "Ü"),
+    ("'a", "á"),
+    ("'e", "é"),
+    ("'i", "í"),
+    ("'o", "ó"),
+    ("'u", "ú"),
+    ("`a", "à"),
+    ("`e", "è"),
+    ("^e", "ê"),
+    (",c", "ç"),
+    ("~n", "ñ"),
+    ("fi", "fi"),
+    ("fl", "fl"),
+    ("ff", "ff"),
+    ("Fi", "ffi"),
+    ("Fl", "ffl"),
+];
+
+/// The state of a table (in `.TS`), whose options and formats come before the data.
+#[derive(Clone, Copy)]
+enum TableState {
+    Options,
+    Format { tab: Option<char> },
+    Data { tab: Option<char> },
+}
+
+/// Converts roff to plain text, keeping the line structure.
+#[derive(Default)]
+struct Deroffer {
+    output: String,
+    /// Strings defined with `.ds`.
+    strings: HashMap<String, String>,
+    /// Whether we are in a macro definition, which ends at `..`.
+    in_definition: bool,
+    /// Whether we are in a picture or equation, which we don't show.
+    in_figure: bool,
+    table: Option<TableState>,
+}
+
+/// Convert the roff source of a man page to plain text.
+fn deroff(manpage: &str) -> String {
+    let mut deroffer = Deroffer::default();
+    for line in manpage.split('\n') {
+        deroffer.line(line);
+    }
+    // Collapse long runs of empty lines.
+    let mut result = String::with_capacity(deroffer.output.len());
+    let mut newlines = 0;
+    for c in deroffer.output.chars() {
+        if c == '\n' {
+            newlines += 1;
+            continue;
+        }
+        match newlines {
+            0 => (),
+            1 | 2 => result.push_str(&"\n".repeat(newlines)),
+            _ => result.push('\n'),
+        }
+        newlines = 0;
+        result.push(c);
+    }
+    match newlines {
+        0 => (),
+        1 | 2 => result.push_str(&"\n".repeat(newlines)),
+        _ => result.push('\n'),
+    }
+    result
+}
+
+impl Deroffer {
+    fn line(&mut self, line: &str) {
+        if self.in_definition {
+            if line.starts_with("..") {
+                self.in_definition = false;
+                self.output.push('\n');
+            }
+            return;
+        }
+        if let Some(request) = line.strip_prefix(['.', '\'']) {
+            self.request(request);
+            return;
+        }
+        if self.in_figure {
+            return;
+        }
+        match self.table {
+            None => self.text(line),
+            Some(TableState::Data { tab }) => {
+                let line = match tab {
+                    Some(tab) => line.replace(tab, "\t"),
+                    None => line.to_owned(),
+                };
+                self.text(&line);
+            }
+            Some(TableState::Options) if line.contains(';') => {
+                let tab = line
+                    .find("tab(")
+                    .and_then(|start| line[start + 4..].chars().next());
+                self.table = Some(TableState::Format { tab });
+            }
+            // The format ends with a dot. The options are optional.
+            Some(TableState::Options | TableState::Format { .. }) => {
+                let tab = match self.table {
+                    Some(TableState::Format { tab }) => tab,
+                    _ => None,
+                };
+                self.table = Some(if line.trim_end().ends_with('.') {
+                    TableState::Data { tab }
+                } else {
+                    TableState::Format { tab }
+                });
+            }
+        }
+        self.output.push('\n');
+    }
+
+    fn request(&mut self, request: &str) {
+        let name_end = request.find(char::is_whitespace).unwrap_or(request.len());
+        let (name, args) = request.split_at(name_end);
+        match name {
+            // Requests whose arguments are text.
+            "SH" | "SS" | "IP" | "H" | "I" | "IR" | "IB" | "B" | "BR" | "BI" | "R" | "RB"
+            | "RI" | "AB" | "SM" | "SB"
+                if !self.in_figure =>
+            {
+                self.text(&args.trim_start().replace('"', ""));
+            }
+            "PS" | "EQ" => self.in_figure = true,
+            "PE" | "EN" => self.in_figure = false,
+            "TS" => self.table = Some(TableState::Options),
+            "T&" => {
+                let tab = match self.table {
+                    Some(TableState::Format { tab } | TableState::Data { tab }) => tab,
+                    _ => None,
+                };
+                self.table = Some(TableState::Format { tab });
+            }
+            "TE" => self.table = None,
+            "de" | "de1" | "am" | "ig" => self.in_definition = true,
+            "ds" => {
+                if let Some((name, value)) = args.trim_start().split_once(char::is_whitespace) {
+                    let value = value.trim();
+                    let value = value.strip_prefix('"').unwrap_or(value);
+                    self.strings.insert(name.to_owned(), value.to_owned());
+                }
+            }
+            // We don't follow includes.
+            "so" | "nx" => return,
+            _ => (),
+        }
+        self.output.push('\n');
+    }
+
+    /// Output text, interpreting escapes.
+    fn text(&mut self, text: &str) {
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            if c != '\\' {
+                self.output.push(c);
+                continue;
+            }
+            let Some(&c) = chars.get(i) else {
+                self.output.push('\\');
+                break;
+            };
+            i += 1;
+            match c {
+                // A comment.
+                '"' => break,
+                // Font and size changes.
+                'f' | 's' | 'F' | 'm' => {
+                    if chars.get(i) == Some(&'(') {
+                        i += 3;
+                    } else if chars.get(i) == Some(&'[') {
+                        i = skip_past(&chars, i, ']');
+                    } else if c == 's' {
+                        if matches!(chars.get(i), Some('+' | '-')) {
+                            i += 1;
+                        }
+                        while chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+                            i += 1;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                }
+                // Escapes with a quoted argument, like motions and device control.
+                'h' | 'v' | 'w' | 'u' | 'd' | 'X' | 'Z' | 'o' | 'l' | 'L' | 'D' | 'b' | 'x'
+                    if chars.get(i) == Some(&'\'') =>
+                {
+                    i = skip_past(&chars, i + 1, '\'');
+                }
+                // Number registers.
+                'n' => {
+                    if matches!(chars.get(i), Some('+' | '-')) {
+                        i += 1;
+                    }
+                    i = skip_name(&chars, i).1;
+                }
+                // Strings.
+                '*' => {
+                    let (name, end) = skip_name(&chars, i);
+                    i = end;
+                    if let Some(value) = self.strings.get(&name).cloned() {
+                        self.text(&value);
+                    } else if matches!(name.as_str(), "lq" | "rq" | "Lq" | "Rq") {
+                        self.output.push('"');
+                    }
+                }
+                // Special characters.
+                '(' | '[' => {
+                    let (name, end) = skip_name(&chars, i - 1);
+                    i = end;
+                    if let Some((_, value)) = SPECIAL_CHARACTERS.iter().find(|(n, _)| *n == name) {
+                        self.output.push_str(value);
+                    }
+                }
+                'e' | 'E' | '\\' => self.output.push('\\'),
+                't' => self.output.push('\t'),
+                '0' | '~' | ' ' => self.output.push(' '),
+                '|' | '^' | '&' | ':' | '%' | ',' | '/' | ')' | 'c' => (),
+                c => self.output.push(c),
+            }
+        }
+    }
+}
+
+/// Return the index after the next `end` character at or after `i`.
+fn skip_past(chars: &[char], i: usize, end: char) -> usize {
+    chars[i.min(chars.len())..]
+        .iter()
+        .position(|&c| c == end)
+        .map_or(chars.len(), |len| i + len + 1)
+}
+
+/// Read the name of a register, string or special character at `i`, which is either a single
+/// character, two characters after `(`, or anything in brackets. Returns the name and the index
+/// after it.
+fn skip_name(chars: &[char], i: usize) -> (String, usize) {
+    match chars.get(i) {
+        Some('(') => {
+            let end = (i + 3).min(chars.len());
+            (chars[i + 1..end].iter().collect(), end)
+        }
+        Some('[') => {
+            let end = skip_past(chars, i + 1, ']');
+            let name_end = end.saturating_sub(1).max(i + 1);
+            (chars[i + 1..name_end].iter().collect(), end)
+        }
+        Some(&c) => (c.to_string(), i + 1),
+        None => (String::new(), i),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{command_name, deroff, manpage_completions, truncate_description};
+    use std::path::Path;
+
+    #[test]
+    fn test_command_name() {
+        assert_eq!(
+            command_name(Path::new("/usr/share/man/man8/mkfs.xfs.8.gz")),
+            Some("mkfs.xfs".to_owned())
+        );
+        assert_eq!(command_name(Path::new("ls.1")), Some("ls".to_owned()));
+        assert_eq!(
+            command_name(Path::new("CA.pl.1ssl.gz")),
+            Some("CA.pl".to_owned())
+        );
+        assert_eq!(command_name(Path::new("README")), None);
+        assert_eq!(command_name(Path::new("foo.gz")), None);
+    }
+
+    #[test]
+    fn test_truncate_description() {
+        assert_eq!(truncate_description("Show all."), "Show all");
+        assert_eq!(
+            truncate_description(
+                "Show all. Really all of them, even the ones that are hidden unless you ask for them."
+            ),
+            "Show all"
+        );
+        assert_eq!(truncate_description("One. Two."), "One. Two");
+        assert_eq!(truncate_description(&"a".repeat(100)).chars().count(), 78);
+    }
+
+    #[test]
+    fn test_deroff() {
+        assert_eq!(
+            deroff(
+                ".SH DESCRIPTION\n.TP\n\\fB\\-a\\fR, \\fB\\-\\-all\\fR\nshow \\(lqall\\(rq\\*(Tx\n.\\\" a comment\n"
+            ),
+            "DESCRIPTION\n\n-a, --all\nshow “all”\n"
+        );
+    }
+
+    #[test]
+    fn test_manpage_completions() {
+        let pod2man = r#".SH "OPTIONS"
+.IX Header "OPTIONS"
+.PP
+\fB\-v\fR, \fB\-\-verbose\fR
+.RS 4
+Print more. Much more than usual.
+.RE
+.PP
+\fB\-o\fR \fIfile\fR
+.RS 4
+Write the output to \fIfile\fR.
+.RE
+.SH "SEE ALSO"
+"#;
+        assert_eq!(
+            manpage_completions("foo", "foo.1", pod2man).as_deref(),
+            Some(
+                "# foo
+# Autogenerated from man page foo.1
+complete -c foo -s v -l verbose -d 'Print more. Much more than usual'
+complete -c foo -s o -d 'Write the output to file'
+"
+            )
+        );
+
+        let mdoc = r#".Sh DESCRIPTION
+The
+.Nm
+utility does things.
+.Bl -tag -width indent
+.It Fl a
+Show all entries.
+.It Fl Fl long Ar value
+A long option for
+.Nm .
+.El
+"#;
+        assert_eq!(
+            manpage_completions("bar", "bar.1", mdoc).as_deref(),
+            Some(
+                "# bar
+# Autogenerated from man page bar.1
+complete -c bar -s a -d 'Show all entries'
+complete -c bar -l long -d 'A long option for bar'
+"
+            )
+        );
+
+        assert_eq!(manpage_completions("git-log", "git-log.1", pod2man), None);
+        assert_eq!(manpage_completions("foo", "foo.1", ".SH NAME\nfoo\n"), None);
+    }
+}
@@ -6,7 +6,9 @@ use crate::{
     highlight::{HighlightRole, HighlightSpec, highlight_shell},
     operation_context::OperationContext,
     prelude::*,
-    screen::{CharOffset, Line, ScreenData, wcswidth_rendered, wcwidth_rendered},
+    screen::{
+        CharOffset, Line, ScreenData, escape_code_length, wcswidth_rendered, wcwidth_rendered,
+    },
     termsize::Termsize,
 };
 use fish_common::{EscapeFlags, EscapeStringStyle, escape_string};
@@ -78,6 +80,9 @@ const PAGER_MAX_COLS: usize = 6;
 /// Width of the search field.
 const PAGER_SEARCH_FIELD_WIDTH: usize = 12;
 
+/// The separator between the completions and the preview of the selected completion.
+const PAGER_PREVIEW_SEPARATOR: &wstr = L!(" │ ");
+
 localizable_consts!(
     /// Text we use for the search field.
     SEARCH_FIELD_PROMPT
@@ -106,8 +111,8 @@ pub struct Pager {
     // The unfiltered list. Note there's a lot of duplication here.
     unfiltered_completion_infos: Vec<PagerComp>,
 
-    // This tracks if the completion list has been changed since we last rendered. If yes,
-    // then we definitely need to re-render.
+    // This tracks if the completion list or the preview has been changed since we last rendered.
+    // If yes, then we definitely need to re-render.
     have_unrendered_completions: bool,
 
     prefix: Cow<'static, wstr>,
@@ -118,6 +123,12 @@ pub struct Pager {
 
     // Extra text to display at the bottom of the pager.
     pub extra_progress_text: WString,
+
+    // The lines of the preview of the selected completion, shown next to the completions.
+    preview: Vec<WString>,
+
+    // The token the preview was made for, if any.
+    preview_token: Option<WString>,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -180,6 +191,19 @@ impl Pager {
 
         // Compute the effective term width and term height, accounting for disclosure.
         let term_width = self.available_term_width;
+        // Split off the right half for the preview, if there is one and it fits.
+        let preview_width = if self.preview.is_empty()
+            || term_width < 2 * PAGER_MIN_WIDTH + PAGER_PREVIEW_SEPARATOR.len()
+        {
+            0
+        } else {
+            term_width / 2
+        };
+        let list_width = if preview_width == 0 {
+            term_width
+        } else {
+            term_width - preview_width - PAGER_PREVIEW_SEPARATOR.len()
+        };
         let mut term_height = self.available_term_height
             // we always subtract 1 to make room for a comment row
                 - 1 - if self.search_field_shown { 1 } else { 0 };
@@ -228,7 +252,7 @@ impl Pager {
             }
             *col = Column {
                 // Force-fit to term width, useful when one column.
-                width: (max_comp + max_desc).min(term_width),
+                width: (max_comp + max_desc).min(list_width),
                 desc_align: max_comp + 2,
             };
         }
@@ -237,7 +261,7 @@ impl Pager {
         // Compute total preferred width, plus spacing
         let total_width_needed = cols.iter().map(|c| c.width).sum::<usize>()
             + (col_count - 1) * PAGER_SPACER_STRING.len();
-        if term_width < total_width_needed {
+        if list_width < total_width_needed {
             assert!(col_count > 1, "single col force-fit to termwidth above");
             return false; // no need to continue
         }
@@ -266,6 +290,10 @@ impl Pager {
         self.completion_print(
             col_count, &cols, start_row, stop_row, prefix, lst, rendering,
         );
+        if preview_width != 0 {
+            let line_count = (stop_row - start_row).max(self.preview.len().min(term_height));
+            self.preview_print(list_width, preview_width, line_count, rendering);
+        }
 
         // Add the progress line. It's a "more to disclose" line if necessary, or a row listing if
         // it's scrollable; otherwise ignore it.
@@ -284,6 +312,13 @@ impl Pager {
             // Everything is filtered.
             progress_text = wgettext!("(no matches)").to_owned();
         }
+        let marked_count = self.marked_completions().count();
+        if marked_count != 0 {
+            if !progress_text.is_empty() {
+                progress_text.push_str(". ");
+            }
+            progress_text.push_utfstr(&wgettext_fmt!("%u marked", marked_count));
+        }
         if !self.extra_progress_text.is_empty() {
             if !progress_text.is_empty() {
                 progress_text.push_str(". ");
@@ -344,6 +379,36 @@ impl Pager {
         true
     }
 
+    /// Print the preview to the right of the first `line_count` lines of the rendering, padding the
+    /// completions to `list_width`.
+    fn preview_print(
+        &self,
+        list_width: usize,
+        preview_width: usize,
+        line_count: usize,
+        rendering: &mut PageRendering,
+    ) {
+        let border = HighlightSpec::with_fg(HighlightRole::PagerDescription);
+        for idx in 0..line_count {
+            let line = rendering.screen_data.create_line(idx);
+            let width = line.wcswidth_min_0(usize::MAX);
+            for _ in width..list_width {
+                line.append(' ', HighlightSpec::new(), CharOffset::None);
+            }
+            line.append_str(PAGER_PREVIEW_SEPARATOR, border, CharOffset::None);
+            if let Some(text) = self.preview.get(idx) {
+                print_max(
+                    CharOffset::None,
+                    text.chars(),
+                    HighlightSpec::new(),
+                    preview_width,
+                    false,
+                    line,
+                );
+            }
+        }
+    }
+
     fn measure_completion_infos(&mut self) {
         let prefix_len = wcswidth_rendered(&self.prefix);
         for comp in &mut self.unfiltered_completion_infos {
@@ -516,7 +581,7 @@ impl Pager {
 
         let bg_role = modify_role(HighlightRole::PagerBackground);
         let bg = HighlightSpec::with_bg(bg_role);
-        let prefix_col = HighlightSpec::with_fg_bg(
+        let mut prefix_col = HighlightSpec::with_fg_bg(
             modify_role(if self.highlight_prefix {
                 HighlightRole::PagerPrefix
             } else {
@@ -524,8 +589,11 @@ impl Pager {
             }),
             bg_role,
         );
-        let comp_col =
+        let mut comp_col =
             HighlightSpec::with_fg_bg(modify_role(HighlightRole::PagerCompletion), bg_role);
+        // Marked completions are underlined.
+        prefix_col.force_underline = c.marked;
+        comp_col.force_underline = c.marked;
         let desc_col =
             HighlightSpec::with_fg_bg(modify_role(HighlightRole::PagerDescription), bg_role);
 
@@ -564,7 +632,9 @@ impl Pager {
                         // Rendered in reverse video, so avoid highlighting.
                         return comp_col;
                     }
-                    *c.colors.get(i).unwrap_or(c.colors.last().unwrap())
+                    let mut color = *c.colors.get(i).unwrap_or(c.colors.last().unwrap());
+                    color.force_underline = c.marked;
+                    color
                 },
                 comp_remaining,
                 i + 1 < c.comp.len(),
@@ -683,6 +753,39 @@ impl Pager {
         self.have_unrendered_completions = true;
     }
 
+    // Returns the token the preview was made for.
+    pub fn preview_token(&self) -> Option<&wstr> {
+        self.preview_token.as_deref()
+    }
+
+    // Sets the preview of the selected completion, which is the given token. Escape sequences are
+    // removed from the lines, since we color them ourselves.
+    pub fn set_preview(&mut self, token: Option<WString>, lines: Vec<WString>) {
+        self.preview = lines
+            .into_iter()
+            .map(|line| {
+                let mut result = WString::new();
+                let mut i = 0;
+                while i < line.len() {
+                    if line.as_char_slice()[i] == '\x1B' {
+                        if let Some(len) = escape_code_length(&line[i..]) {
+                            i += len;
+                            continue;
+                        }
+                    }
+                    result.push(match line.as_char_slice()[i] {
+                        '\t' => ' ',
+                        c => c,
+                    });
+                    i += 1;
+                }
+                result
+            })
+            .collect();
+        self.preview_token = token;
+        self.have_unrendered_completions = true;
+    }
+
     // Sets the prefix.
     pub fn set_prefix(&mut self, prefix: Cow<'static, wstr>, highlight: bool /* = true */) {
         self.prefix = prefix;
@@ -897,6 +1000,34 @@ impl Pager {
             .map(|idx| &self.completion_infos[idx].representative)
     }
 
+    // Toggles whether the currently selected completion is marked. Returns false if there is no
+    // selection.
+    pub fn toggle_mark_selected_completion(&mut self, rendering: &PageRendering) -> bool {
+        let Some(idx) = self.visual_selected_completion_index(rendering.rows, rendering.cols)
+        else {
+            return false;
+        };
+        let info = &mut self.completion_infos[idx];
+        info.marked = !info.marked;
+        // Also mark the unfiltered completion, so it stays marked when the filter changes.
+        let (comp, marked) = (&info.comp, info.marked);
+        for unfiltered in &mut self.unfiltered_completion_infos {
+            if unfiltered.comp == *comp {
+                unfiltered.marked = marked;
+            }
+        }
+        self.have_unrendered_completions = true;
+        true
+    }
+
+    // Returns the marked completions, in the order they are listed, even if they are filtered.
+    pub fn marked_completions(&self) -> impl Iterator<Item = &Completion> {
+        self.unfiltered_completion_infos
+            .iter()
+            .filter(|info| info.marked)
+            .map(|info| &info.representative)
+    }
+
     pub fn selected_completion_index(&self) -> Option<usize> {
         self.selected_completion_idx
     }
@@ -1030,6 +1161,8 @@ impl Pager {
         self.search_field_shown = false;
         self.extra_progress_text.clear();
         self.suggested_row_start = 0;
+        self.preview.clear();
+        self.preview_token = None;
     }
 
     // Updates the completions list per the filter.
@@ -1094,6 +1227,8 @@ pub struct PagerComp {
     pub comp_width: usize,
     /// On-screen width of the description information.
     pub desc_width: usize,
+    /// Whether this was marked to be inserted together with the other marked completions.
+    pub marked: bool,
 }
 
 impl PagerComp {
@@ -1656,5 +1791,25 @@ mod tests {
         pager.set_prefix(Cow::Borrowed(L!("{\\\n")), false); // }
         pager.set_completions(&completions(&[("Hello", "")]), true);
         validate!(&mut pager, 30, L!("{\\␊Hello")); // }
+
+        // A preview is shown to the right, without escape sequences, if the terminal is wide enough.
+        pager.set_prefix(Cow::Borrowed(L!("")), false);
+        pager.set_completions(&completions(&[("alpha", ""), ("beta", "")]), true);
+        pager.set_preview(
+            Some(WString::from("alpha")),
+            vec![
+                WString::from("line 1"),
+                WString::from("\x1b[1mbold\x1b[m\tx"),
+                WString::from("a line that is too long"),
+            ],
+        );
+        validate!(
+            &mut pager,
+            40,
+            "alpha  beta       │ line 1",
+            "                  │ bold x",
+            "                  │ a line that is too …",
+        );
+        validate!(&mut pager, 30, "alpha  beta");
     }
 }
@@ -134,6 +134,7 @@ pub enum ParseErrorCode {
     TokenizerUnterminatedSubshell,
     TokenizerUnterminatedSlice,
     TokenizerUnterminatedEscape,
+    TokenizerUnterminatedHeredoc,
     TokenizerOther,
 
     UnbalancingEnd,         // end outside of block
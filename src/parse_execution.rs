@@ -12,6 +12,7 @@ use crate::{
     },
     common::valid_var_name,
     complete::CompletionList,
+    debugger,
     env::{EnvMode, EnvStackSetResult, EnvVar, EnvVarFlags, Environment as _, Statuses},
     err_fmt,
     event::{self, Event},
@@ -42,20 +43,22 @@ use crate::{
     prelude::*,
     proc::{
         ConcreteAssignment, Job, JobControl, JobProperties, JobRef, Process, ProcessType,
-        get_job_control_mode, job_reap, no_exec,
+        get_job_control_mode, job_reap, no_exec, reaped_cpu_time,
     },
     reader::fish_is_unwinding_for_exit,
     redirection::{RedirectionMode, RedirectionSpec, RedirectionSpecList},
     signal::RawSignal,
     timer::push_timer,
-    tokenizer::{PipeOrRedir, TokenType, variable_assignment_equals_pos},
+    tokenizer::{
+        PipeOrRedir, TokenType, heredoc_contents, heredoc_delimiter, variable_assignment_equals_pos,
+    },
     trace::{trace_if_enabled, trace_if_enabled_with_args},
     wildcard::wildcard_match,
 };
 use fish_common::{ScopeGuard, escape, help_section, truncate_at_nul};
 use fish_widestring::WExt as _;
 use libc::{ENOTDIR, EXIT_SUCCESS, STDERR_FILENO, STDOUT_FILENO, c_int};
-use std::{io::ErrorKind, rc::Rc, sync::Arc};
+use std::{io::ErrorKind, rc::Rc, sync::Arc, time::Instant};
 
 /// An eval_result represents evaluation errors including wildcards which failed to match, syntax
 /// errors, or other expansion errors. It also tracks when evaluation was skipped due to signal
@@ -809,6 +812,14 @@ impl ExecutionContext {
                 return arg_result;
             }
 
+            // Check the number of arguments for functions with parameters.
+            if matches!(process_type, ProcessType::Function) {
+                let ret = self.check_function_arity(ctx, statement, &cmd_args);
+                if ret != EndExecutionReason::Ok {
+                    return ret;
+                }
+            }
+
             // The set of IO redirections that we construct for the process.
             let reason =
                 self.determine_redirections(ctx, &statement.args_or_redirs, &mut redirections);
@@ -825,6 +836,44 @@ impl ExecutionContext {
         EndExecutionReason::Ok
     }
 
+    /// Report an error if the arguments don't fit the parameters of the function `cmd_args[0]`.
+    fn check_function_arity(
+        &mut self,
+        ctx: &mut OperationContext<'_>,
+        statement: &ast::DecoratedStatement,
+        cmd_args: &[WString],
+    ) -> EndExecutionReason {
+        let Some(props) = function::get_props(&cmd_args[0]) else {
+            return EndExecutionReason::Ok;
+        };
+        let params = &props.params;
+        let argc = cmd_args.len() - 1;
+        if let Some(param) = params.get(argc).filter(|param| param.is_required()) {
+            return report_error!(
+                self,
+                ctx,
+                STATUS_INVALID_ARGS,
+                statement,
+                "%s: missing argument for parameter '%s'",
+                cmd_args[0],
+                param.name
+            );
+        }
+        if params.last().is_some_and(|param| !param.variadic) && argc > params.len() {
+            return report_error!(
+                self,
+                ctx,
+                STATUS_INVALID_ARGS,
+                statement,
+                "%s: expected at most %d arguments; got %d",
+                cmd_args[0],
+                params.len(),
+                argc
+            );
+        }
+        EndExecutionReason::Ok
+    }
+
     fn populate_block_process(
         &mut self,
         ctx: &mut OperationContext<'_>,
@@ -1441,6 +1490,25 @@ impl ExecutionContext {
                 }
             };
 
+            // The input of here-documents and here-strings is given in place of a file.
+            if matches!(
+                oper.mode,
+                RedirectionMode::HereDoc | RedirectionMode::HereString
+            ) {
+                let Some(input) = self.expand_here_input(ctx, redir_node, oper.mode) else {
+                    return report_error!(
+                        self,
+                        ctx,
+                        STATUS_INVALID_ARGS,
+                        redir_node,
+                        "Invalid redirection target: %s",
+                        self.node_source(&redir_node.target)
+                    );
+                };
+                out_redirections.push(RedirectionSpec::new(oper.fd, oper.mode, input));
+                continue;
+            }
+
             // PCA: I can't justify this skip_variables flag. It was like this when I got here.
             let mut target = self.node_source_owned(&redir_node.target);
             let target_expanded = expand_one(
@@ -1515,6 +1583,46 @@ impl ExecutionContext {
         EndExecutionReason::Ok
     }
 
+    /// Return the input of a here-document or here-string, or None if it failed to expand.
+    fn expand_here_input(
+        &self,
+        ctx: &mut OperationContext<'_>,
+        redir_node: &ast::Redirection,
+        mode: RedirectionMode,
+    ) -> Option<WString> {
+        let flags = if no_exec() {
+            ExpandFlags::SKIP_VARIABLES
+        } else {
+            ExpandFlags::default()
+        };
+        let target = self.node_source_owned(&redir_node.target);
+        if mode == RedirectionMode::HereString {
+            // Each value of the expanded text is a line.
+            let mut values = CompletionList::new();
+            if expand_string(target, &mut values, flags, ctx, None) != ExpandResultCode::Ok {
+                return None;
+            }
+            let mut input = WString::new();
+            for value in values {
+                input.push_utfstr(&value.completion);
+                input.push('\n');
+            }
+            return Some(input);
+        }
+
+        let body = redir_node
+            .heredoc_body
+            .map(|body| heredoc_contents(&self.pstree().src, body.as_usize()))
+            .unwrap_or_default();
+        let (_, literal) = heredoc_delimiter(&target);
+        if literal {
+            return Some(body);
+        }
+        // Otherwise the body is expanded like a double-quoted string.
+        let mut input = heredoc_as_double_quoted(&body);
+        expand_one(&mut input, flags, ctx, None).then_some(input)
+    }
+
     fn run_1_job(
         &mut self,
         ctx: &mut OperationContext<'_>,
@@ -1540,6 +1648,11 @@ impl ExecutionContext {
             .current_node()
             .scoped_replace(Some(executing_node));
 
+        // Let the debugger stop before the job, now that the current line is known.
+        if debugger::is_active() {
+            debugger::before_job(ctx.parser());
+        }
+
         // Profiling support.
         let finalize_profile_item = {
             let profile_item_id = ctx.parser().create_profile_item();
@@ -1663,7 +1776,12 @@ impl ExecutionContext {
                 parser.job_add(job.clone());
 
                 // Actually execute the job.
-                if !exec_job(parser, &job, self.block_io.clone()) {
+                let (start, start_cpu) = (Instant::now(), reaped_cpu_time());
+                let launched = exec_job(parser, &job, self.block_io.clone());
+                if job.is_completed() {
+                    parser.libdata_mut().last_job_timing = Some(job.timing(start, start_cpu));
+                }
+                if !launched {
                     // No process in the job successfully launched.
                     // Ensure statuses are set (#7540).
                     if let Some(statuses) = job.statuses() {
@@ -1691,6 +1809,10 @@ impl ExecutionContext {
         );
 
         job_reap(ctx.parser(), false, Some(&self.block_io)); // clean up jobs
+
+        // Run the handlers for signals that arrived in the meantime, like other shells do between
+        // commands. Otherwise a script which only runs builtins would never see them.
+        event::fire_pending_signals(ctx.parser());
         pop_result
     }
 
@@ -1964,6 +2086,28 @@ fn get_stderr_merge() -> RedirectionSpec {
     RedirectionSpec::new(STDERR_FILENO, RedirectionMode::Fd, stdout_fileno_str)
 }
 
+/// Quote the body of a here-document so that it expands like it would as a double-quoted string.
+/// A backslash only escapes '$', a backslash or a newline, and double quotes are literal.
+fn heredoc_as_double_quoted(body: &wstr) -> WString {
+    let mut result = L!("\"").to_owned();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some('$' | '\\' | '\n')) => {
+                result.push(c);
+                result.push(chars.next().unwrap());
+            }
+            '\\' | '"' => {
+                result.push('\\');
+                result.push(c);
+            }
+            _ => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
 /// Decide if a job node should be 'time'd.
 /// For historical reasons the 'not' and 'time' prefix are "inside out". That is, it's
 /// 'not time cmd'. Note that a time appearing anywhere in the pipeline affects the whole job.
@@ -32,6 +32,8 @@ pub struct ParseToken {
     pub may_be_variable_assignment: bool,
     /// If this is a tokenizer error, that error.
     pub tok_error: TokenizerError,
+    /// If this is the delimiter of a here-document, the range of its body.
+    pub heredoc_body: Option<SourceRange>,
     source_start: SourceOffset,
     source_length: SourceOffset,
 }
@@ -46,6 +48,7 @@ impl ParseToken {
             is_newline: false,
             may_be_variable_assignment: false,
             tok_error: TokenizerError::None,
+            heredoc_body: None,
             source_start: SOURCE_OFFSET_INVALID.try_into().unwrap(),
             source_length: 0,
         }
@@ -92,6 +95,7 @@ impl From<TokenizerError> for ParseErrorCode {
             TokenizerError::UnterminatedSubshell => ParseErrorCode::TokenizerUnterminatedSubshell,
             TokenizerError::UnterminatedSlice => ParseErrorCode::TokenizerUnterminatedSlice,
             TokenizerError::UnterminatedEscape => ParseErrorCode::TokenizerUnterminatedEscape,
+            TokenizerError::UnterminatedHeredoc => ParseErrorCode::TokenizerUnterminatedHeredoc,
             // To-do: maybe also unbalancing brace?
             _ => ParseErrorCode::TokenizerOther,
         }
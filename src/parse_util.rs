@@ -19,12 +19,13 @@ use crate::{
     prelude::*,
     tokenizer::{
         TOK_ACCEPT_UNFINISHED, TOK_SHOW_COMMENTS, Tok, TokenType, Tokenizer, comment_end,
-        is_token_delimiter, quote_end,
+        heredoc_delimiter_line_end, is_token_delimiter, quote_end, tok_is_string_character,
     },
+    wildcard::glob_qualifier_len,
 };
 use fish_common::{UnescapeFlags, UnescapeStringStyle, help_section, unescape_string};
 use fish_feature_flags::{FeatureFlag, feature_test};
-use fish_wcstringutil::{count_newlines, truncate};
+use fish_wcstringutil::{count_newlines, count_preceding_backslashes, truncate};
 use fish_widestring::{
     ANY_CHAR, ANY_STRING, ANY_STRING_RECURSIVE, BRACE_BEGIN, BRACE_END, BRACE_SEP,
     INTERNAL_SEPARATOR, VARIABLE_EXPAND, VARIABLE_EXPAND_EMPTY, VARIABLE_EXPAND_SINGLE,
@@ -159,6 +160,16 @@ pub fn locate_cmdsubst_range(
     ret
 }
 
+/// Return whether the command substitution whose opening parenthesis is at `paren_start` is
+/// written with two leading dollars, like `$$(cmd)`. Its output is then a single argument, like in
+/// double quotes.
+pub fn cmdsubst_is_collected(s: &wstr, paren_start: usize) -> bool {
+    paren_start >= 2
+        && s.char_at(paren_start - 1) == '$'
+        && s.char_at(paren_start - 2) == '$'
+        && count_preceding_backslashes(s, paren_start - 2) % 2 == 0
+}
+
 /// Find the beginning and end of the command substitution under the cursor. If no subshell is
 /// found, the entire string is returned. If the current command substitution is not ended, i.e. the
 /// closing parenthesis is missing, then the string from the beginning of the substitution to the
@@ -214,6 +225,7 @@ fn locate_cmdsub(
 
     let mut escaped = false;
     let mut is_token_begin = true;
+    let mut token_has_wildcard = false;
     let mut syntax_error = false;
     let mut paran_count = 0;
     let mut quoted_cmdsubs = vec![];
@@ -298,7 +310,18 @@ fn locate_cmdsub(
                 pos = comment_end(input.into(), pos) - 1;
             } else if c == '$' {
                 last_dollar = Some(pos);
+            } else if c == '*' || (c == '?' && !feature_test(FeatureFlag::QuestionMarkNoGlob)) {
+                token_has_wildcard = true;
             } else if c == '(' {
+                // Glob qualifiers like the "(.)" in "*(.)" are not a command substitution.
+                if paran_count == 0 && token_has_wildcard {
+                    if let Some(len) = glob_qualifier_len(input.into(), pos) {
+                        pos += len;
+                        is_token_begin = true;
+                        token_has_wildcard = false;
+                        continue;
+                    }
+                }
                 if paran_count == 0 && paran_begin.is_none() {
                     paran_begin = Some(pos);
                     out_has_dollar
@@ -341,6 +364,9 @@ fn locate_cmdsub(
                 }
             }
             is_token_begin = is_token_delimiter(c, input.get(pos + 1).copied());
+            if !tok_is_string_character(c, input.get(pos + 1).copied()) {
+                token_has_wildcard = false;
+            }
         } else {
             escaped = false;
             is_token_begin = false;
@@ -751,6 +777,7 @@ fn compute_indents_from(src: &wstr, initial_indent: i32) -> Vec<i32> {
                 next_indent = iv.indents[idx];
             }
         }
+        let heredocs = iv.heredocs;
         // Add an extra level of indentation to continuation lines.
         for mut idx in iv.line_continuations {
             loop {
@@ -761,6 +788,12 @@ fn compute_indents_from(src: &wstr, initial_indent: i32) -> Vec<i32> {
                 }
             }
         }
+        // Here-documents are not indented, from the newline before the body to the line with the
+        // delimiter.
+        for body in heredocs {
+            let end = heredoc_delimiter_line_end(wstr::from_char_slice(src), body.clone());
+            indents[body.start.saturating_sub(1)..end].fill(0);
+        }
     }
 
     indents
@@ -785,6 +818,34 @@ pub fn apply_indents(src: &wstr, indents: &[i32]) -> WString {
     indented
 }
 
+/// The inverse of [`apply_indents`]. Returns the source unchanged unless every line is indented
+/// by at least what `indents` prescribes.
+pub fn remove_indents(src: &wstr, indents: &[i32]) -> WString {
+    for (i, c) in src.chars().enumerate() {
+        if c != '\n' || i + 1 == src.len() {
+            continue;
+        }
+        let num_spaces = SPACES_PER_INDENT * usize::try_from(indents[i + 1]).unwrap();
+        if src.len() < i + 1 + num_spaces
+            || !src[i + 1..].chars().take(num_spaces).all(|c| c == ' ')
+        {
+            return src.to_owned();
+        }
+    }
+    let mut out = WString::new();
+    let mut i = 0;
+    while i < src.len() {
+        let c = src.as_char_slice()[i];
+        out.push(c);
+        i += 1;
+        if c != '\n' || i == src.len() {
+            continue;
+        }
+        i += SPACES_PER_INDENT * usize::try_from(indents[i]).unwrap();
+    }
+    out
+}
+
 // Visit all of our nodes. When we get a job_list or case_item_list, increment indent while
 // visiting its children.
 struct IndentVisitor<'a> {
@@ -814,6 +875,9 @@ struct IndentVisitor<'a> {
 
     // List of locations of escaped newline characters.
     line_continuations: Vec<usize>,
+
+    // The bodies of here-documents.
+    heredocs: Vec<Range<usize>>,
 }
 
 impl<'a> IndentVisitor<'a> {
@@ -827,6 +891,7 @@ impl<'a> IndentVisitor<'a> {
             indents,
             indent: initial_indent - 1,
             line_continuations: vec![],
+            heredocs: vec![],
         }
     }
     /// Return whether a maybe_newlines node contains at least one newline.
@@ -1002,6 +1067,12 @@ impl<'a> NodeVisitor<'a> for IndentVisitor<'a> {
                 inc_dec = (1, dec);
             }
 
+            Kind::Redirection(redir) => {
+                if let Some(body) = redir.heredoc_body {
+                    self.heredocs.push(body.as_usize());
+                }
+            }
+
             Kind::Token(node) => {
                 let token_type = node.token_type();
                 let parent_kind = self.parent.unwrap().kind();
@@ -1084,6 +1155,7 @@ pub fn detect_parse_errors(
             if [
                 ParseErrorCode::TokenizerUnterminatedQuote,
                 ParseErrorCode::TokenizerUnterminatedSubshell,
+                ParseErrorCode::TokenizerUnterminatedHeredoc,
             ]
             .contains(&parse_error.code)
             {
@@ -1411,9 +1483,11 @@ pub fn detect_errors_in_argument(
                 do_loop = false;
             }
             MaybeParentheses::CommandSubstitution(parens) => {
+                let collected =
+                    has_dollar && !is_quoted && cmdsubst_is_collected(arg_src, parens.start());
                 issue.error |= check_subtoken(
                     checked,
-                    parens.start() - if has_dollar { 1 } else { 0 },
+                    parens.start() - usize::from(has_dollar) - usize::from(collected),
                     out_errors,
                 );
                 let mut subst_errors = ParseErrorList::new();
@@ -2103,6 +2177,15 @@ mod tests {
                 1, "\n"
             );
 
+            // Here-documents are not indented.
+            validate!(
+                0, "begin",
+                1, "\ncat <<EOF",
+                0, "\nbody\nEOF",
+                1, "\necho",
+                0, "\nend"
+            );
+
             validate!(
                 0, "begin",
                 1, "\n;",
@@ -14,7 +14,7 @@ use crate::{
     fds::{BEST_O_SEARCH, open_dir},
     flog, flogf, function,
     io::IoChain,
-    job_group::MaybeJobId,
+    job_group::{JobId, MaybeJobId},
     operation_context::{EXPANSION_LIMIT_DEFAULT, OperationContext},
     parse_constants::{
         FISH_MAX_EVAL_DEPTH, FISH_MAX_STACK_DEPTH, ParseError, ParseErrorList, ParseTreeFlags,
@@ -23,7 +23,7 @@ use crate::{
     parse_execution::{EndExecutionReason, ExecutionContext},
     parse_tree::{NodeRef, ParsedSourceRef, SourceLineCache, parse_source},
     prelude::*,
-    proc::{InternalJobId, JobGroupRef, JobList, JobRef, Pid, ProcStatus, job_reap},
+    proc::{InternalJobId, JobGroupRef, JobList, JobRef, JobTiming, Pid, ProcStatus, job_reap},
     signal::{RawSignal, signal_check_cancel, signal_clear_cancel},
     wait_handle::WaitHandleStore,
     wutil::perror_nix,
@@ -35,6 +35,7 @@ use fish_common::{
 use fish_util::get_time;
 use fish_widestring::{WExt as _, wcs2bytes};
 use libc::c_int;
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::Write as _;
@@ -293,6 +294,9 @@ pub struct LibraryData {
     /// A counter incremented every time a command produces a $status.
     pub status_count: u64,
 
+    /// The timing of the last foreground job, for `status last-duration`.
+    pub last_job_timing: Option<JobTiming>,
+
     /// Last reader run count.
     pub last_exec_run_counter: u64,
 
@@ -317,6 +321,10 @@ pub struct LibraryData {
     /// This is set by the 'return' command.
     pub returning: bool,
 
+    /// The values passed to 'return --value' in the current function, if any.
+    /// They become $fish_return_value once the function is done.
+    pub return_value: Option<Vec<WString>>,
+
     /// Whether we should stop executing.
     /// This is set by the 'exit' command, and unset after 'reader_read'.
     /// Note this only exits up to the "current script boundary." That is, a call to exit within a
@@ -403,6 +411,10 @@ pub struct Parser {
     /// and have been reaped, but may still be wait'ed on.
     wait_handles: WaitHandleStore,
 
+    /// Output captured from background jobs which have since completed, keyed by job ID.
+    /// See `fish_background_output`.
+    captured_output: BTreeMap<JobId, Vec<u8>>,
+
     /// The list of blocks.
     /// This is a stack; the topmost block is at the end. This is to avoid invalidating block
     /// indexes during recursive evaluation.
@@ -462,6 +474,7 @@ impl Parser {
             current_filename: ScopedRefCell::new(None),
             job_list: Default::default(),
             wait_handles: Default::default(),
+            captured_output: Default::default(),
             block_list: Default::default(),
             variables,
             scoped_data: ScopedCell::new(ScopedData::default()),
@@ -923,6 +936,14 @@ impl Parser {
         &mut self.wait_handles
     }
 
+    /// Get the output captured from completed background jobs.
+    pub fn captured_output(&self) -> &BTreeMap<JobId, Vec<u8>> {
+        &self.captured_output
+    }
+    pub fn mut_captured_output(&mut self) -> &mut BTreeMap<JobId, Vec<u8>> {
+        &mut self.captured_output
+    }
+
     /// Get and set the last proc statuses.
     pub fn last_status(&self) -> c_int {
         self.vars().last_status()
@@ -967,6 +988,18 @@ impl Parser {
         self.vars().set(key, mode, vals)
     }
 
+    /// Cover of vars().set_map(), without firing events
+    pub fn set_map_var(
+        &mut self,
+        key: &wstr,
+        mode: ParserEnvSetMode,
+        keys: Vec<WString>,
+        vals: Vec<WString>,
+    ) -> EnvStackSetResult {
+        let mode = self.convert_env_set_mode(mode);
+        self.vars().set_map(key, mode, keys, vals)
+    }
+
     /// Cover of vars().set_one(), without firing events
     pub fn set_one(
         &mut self,
@@ -1691,6 +1724,16 @@ mod tests {
             "Bad variable expansion not reported as error"
         );
 
+        assert!(
+            detect_argument_errors("foo$$(cat)").is_ok(),
+            "Collected command substitution reported as error"
+        );
+
+        assert!(
+            detect_argument_errors("foo$$$(cat)").unwrap_err().error,
+            "Bad variable expansion not reported as error"
+        );
+
         // Within command substitutions, we should be able to detect everything that
         // detect_errors! can detect.
         assert!(
@@ -2101,6 +2144,10 @@ mod tests {
         validate!("echo 'abc", ParseErrorCode::TokenizerUnterminatedQuote);
         validate!("'", ParseErrorCode::TokenizerUnterminatedQuote);
         validate!("echo (abc", ParseErrorCode::TokenizerUnterminatedSubshell);
+        validate!(
+            "cat <<EOF\nabc",
+            ParseErrorCode::TokenizerUnterminatedHeredoc
+        );
 
         validate!("end", ParseErrorCode::UnbalancingEnd);
         validate!("echo hi ; end", ParseErrorCode::UnbalancingEnd);
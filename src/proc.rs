@@ -3,14 +3,14 @@
 //! the exec library will call proc to create representations of the running jobs as needed.
 
 use crate::{
-    ast,
+    ast::{self, Node as _},
     common::{WSL, is_windows_subsystem_for_linux},
     env::Statuses,
     event::{self, Event},
     flog::{flog, flogf},
     global_safety::RelaxedAtomicBool,
-    io::IoChain,
-    job_group::{JobGroup, MaybeJobId},
+    io::{IoBufferfill, IoChain, forward_to_stdout},
+    job_group::{JobGroup, JobId, MaybeJobId},
     parse_tree::NodeRef,
     parser::{Block, Parser},
     portable_atomic::AtomicU64,
@@ -24,6 +24,7 @@ use crate::{
 };
 use cfg_if::cfg_if;
 use fish_common::{Timepoint, escape, timef};
+use fish_wcstringutil::join_strings;
 use fish_widestring::ToWString;
 use libc::{
     _SC_CLK_TCK, EXIT_SUCCESS, SIG_IGN, SIGABRT, SIGBUS, SIGFPE, SIGILL, SIGINT, SIGPIPE, SIGQUIT,
@@ -39,6 +40,7 @@ use nix::{
 };
 use std::{
     cell::{Cell, Ref, RefCell, RefMut},
+    collections::BTreeMap,
     fs,
     io::Write as _,
     num::NonZeroU32,
@@ -48,6 +50,7 @@ use std::{
         Arc, LazyLock, Mutex, OnceLock,
         atomic::{AtomicU8, Ordering},
     },
+    time::{Duration, Instant},
 };
 
 /// Types of processes.
@@ -410,6 +413,15 @@ pub struct Process {
 
     pub last_times: Cell<ProcTimes>,
 
+    /// When the process was launched and when it completed, for `status last-duration`.
+    pub launch_time: Cell<Option<Instant>>,
+    pub completion_time: Cell<Option<Instant>>,
+
+    /// CPU time used by the process. For external processes, this is reported by wait4().
+    /// Internal processes run inside fish, so they are charged with the CPU time of the external
+    /// processes reaped while they ran. Until they complete, this holds the total at launch.
+    cpu_time: Cell<CpuTime>,
+
     argv: Vec<WString>,
     proc_redirection_specs: RedirectionSpecList,
 
@@ -426,6 +438,72 @@ pub struct ProcTimes {
     pub jiffies: ClockTicks,
 }
 
+/// CPU time used by a process, split into user and system time.
+#[derive(Default, Clone, Copy)]
+pub struct CpuTime {
+    pub user: Duration,
+    pub sys: Duration,
+}
+
+impl CpuTime {
+    const ZERO: Self = Self {
+        user: Duration::ZERO,
+        sys: Duration::ZERO,
+    };
+
+    #[cfg_attr(cygwin, allow(dead_code))]
+    fn from_rusage(usage: &libc::rusage) -> Self {
+        let duration = |tv: libc::timeval| {
+            Duration::from_secs(tv.tv_sec.try_into().unwrap_or(0))
+                + Duration::from_micros(tv.tv_usec.try_into().unwrap_or(0))
+        };
+        Self {
+            user: duration(usage.ru_utime),
+            sys: duration(usage.ru_stime),
+        }
+    }
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            user: self.user + other.user,
+            sys: self.sys + other.sys,
+        }
+    }
+
+    pub fn saturating_sub(self, other: Self) -> Self {
+        Self {
+            user: self.user.saturating_sub(other.user),
+            sys: self.sys.saturating_sub(other.sys),
+        }
+    }
+}
+
+/// The total CPU time of all external processes reaped so far.
+static REAPED_CPU_TIME: Mutex<CpuTime> = Mutex::new(CpuTime::ZERO);
+
+pub fn reaped_cpu_time() -> CpuTime {
+    *REAPED_CPU_TIME.lock().unwrap()
+}
+
+/// The largest peak resident set size in bytes of the external processes reaped since the last
+/// call to [`take_reaped_peak_rss`].
+static REAPED_PEAK_RSS: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the largest peak resident set size in bytes of the external processes reaped since the
+/// last call, or None if there were none, and starts over.
+pub fn take_reaped_peak_rss() -> Option<u64> {
+    Some(REAPED_PEAK_RSS.swap(0, Ordering::Relaxed)).filter(|&rss| rss != 0)
+}
+
+/// The timing of a completed job, as reported by `status last-duration`.
+#[derive(Default, Clone)]
+pub struct JobTiming {
+    pub wall: Duration,
+    pub cpu: CpuTime,
+    /// The description, wall-clock and CPU time of each process.
+    pub processes: Vec<(WString, Duration, CpuTime)>,
+}
+
 pub struct ConcreteAssignment {
     pub variable_name: WString,
     pub values: Vec<WString>,
@@ -503,10 +581,48 @@ impl Process {
             .update(&topic_monitor_principal().current_generations());
     }
 
+    /// Record that the process is about to be launched.
+    pub fn mark_launched(&self) {
+        self.launch_time.set(Some(Instant::now()));
+        self.cpu_time.set(reaped_cpu_time());
+    }
+
+    /// Mark the process as completed, and record when that happened.
+    pub fn mark_completed(&self) {
+        self.completed.store(true);
+        if self.launch_time.get().is_none() || self.completion_time.get().is_some() {
+            return;
+        }
+        self.completion_time.set(Some(Instant::now()));
+        if !self.has_pid() {
+            self.cpu_time
+                .set(reaped_cpu_time().saturating_sub(self.cpu_time.get()));
+        }
+    }
+
+    /// Return the wall-clock and CPU time of the process, if it was launched and has completed.
+    pub fn timing(&self) -> Option<(Duration, CpuTime)> {
+        let wall = self
+            .completion_time
+            .get()?
+            .saturating_duration_since(self.launch_time.get()?);
+        Some((wall, self.cpu_time.get()))
+    }
+
+    /// A one-line description of the process: its arguments, or the first line of its block.
+    pub fn describe(&self) -> WString {
+        if let ProcessType::BlockNode(node) = &self.typ {
+            let source = node.try_source(node.source_str()).unwrap_or_default();
+            return source.split('\n').next().unwrap_or_default().to_owned();
+        }
+        let args: Vec<WString> = self.argv.iter().map(|arg| escape(arg)).collect();
+        join_strings(&args, ' ')
+    }
+
     /// Mark that this process was part of a pipeline which was aborted.
     /// The process was never successfully launched; give it a status of EXIT_FAILURE.
     pub fn mark_aborted_before_launch(&self) {
-        self.completed.store(true);
+        self.mark_completed();
         // The status may have already been set to e.g. STATUS_NOT_EXECUTABLE.
         // Only stomp a successful status.
         if self.status().is_success() {
@@ -596,6 +712,9 @@ pub struct JobFlags {
     /// Whether the user has been notified that this job is stopped (if it is).
     pub notified_of_stop: bool,
 
+    /// Whether the fish_job_complete event has been posted for this job.
+    pub posted_job_complete: bool,
+
     /// Whether the exit status should be negated. This flag can only be set by the not builtin.
     /// Two "not" prefixes on a single job cancel each other out.
     pub negate: bool,
@@ -634,6 +753,13 @@ pub struct Job {
 
     /// Flags associated with the job.
     pub job_flags: RefCell<JobFlags>,
+
+    /// When the job was created, used to report its wall duration.
+    start_time: Option<Instant>,
+
+    /// If this background job's output is being captured (see `fish_background_output`), the
+    /// buffer receiving its stdout and stderr.
+    pub captured_output: RefCell<Option<Arc<IoBufferfill>>>,
 }
 
 impl Job {
@@ -643,6 +769,7 @@ impl Job {
             properties,
             command_str,
             internal_job_id: InternalJobId(NEXT_INTERNAL_JOB_ID.fetch_add(1, Ordering::Relaxed)),
+            start_time: Some(Instant::now()),
             ..Default::default()
         }
     }
@@ -714,6 +841,12 @@ impl Job {
         self.external_procs().last().and_then(|proc| proc.pid())
     }
 
+    /// Return how long it has been since this job was created.
+    pub fn wall_duration(&self) -> Duration {
+        self.start_time
+            .map_or(Duration::ZERO, |start| start.elapsed())
+    }
+
     /// The id of this job.
     /// This is user-visible, is recycled, and may be -1.
     pub fn job_id(&self) -> MaybeJobId {
@@ -839,9 +972,17 @@ impl Job {
         );
 
         // Wait for the status of our own job to change.
+        // Signal handlers get to know which job this was.
+        let waits = !fish_is_unwinding_for_exit() && !self.is_stopped() && !self.is_completed();
+        if waits {
+            event::enter_foreground_job(self);
+        }
         while !fish_is_unwinding_for_exit() && !self.is_stopped() && !self.is_completed() {
             process_mark_finished_children(parser, /*block_ok=*/ true, block_io);
         }
+        if waits {
+            event::leave_foreground_job();
+        }
         if self.is_completed() {
             // Set $status only if we are in the foreground and the last process in the job has
             // finished.
@@ -895,6 +1036,23 @@ impl Job {
         true
     }
 
+    /// Returns the timing of this completed job, which was started at `start`, when the total CPU
+    /// time of reaped processes was `start_cpu`.
+    pub fn timing(&self, start: Instant, start_cpu: CpuTime) -> JobTiming {
+        JobTiming {
+            wall: start.elapsed(),
+            cpu: reaped_cpu_time().saturating_sub(start_cpu),
+            processes: self
+                .processes()
+                .iter()
+                .map(|p| {
+                    let (wall, cpu) = p.timing().unwrap_or_default();
+                    (p.describe(), wall, cpu)
+                })
+                .collect(),
+        }
+    }
+
     /// Returns the statuses for this job.
     pub fn statuses(&self) -> Option<Statuses> {
         let mut st = Statuses::default();
@@ -1091,7 +1249,7 @@ fn handle_child_status(job: &Job, proc: &Process, status: ProcStatus) {
     } else if status.continued() {
         proc.stopped.store(false);
     } else {
-        proc.completed.store(true);
+        proc.mark_completed();
     }
 
     // If the child was killed by SIGINT or SIGQUIT, then cancel the entire group if interactive. If
@@ -1196,6 +1354,29 @@ fn reap_disowned_pids() {
 /// we exit. Poll these from time-to-time to prevent zombie processes from happening (#5342).
 static DISOWNED_PIDS: Mutex<Vec<Pid>> = Mutex::new(Vec::new());
 
+/// Call waitpid(), additionally returning the CPU time of the child where the platform reports it.
+fn wait_with_cpu_time(
+    pid: libc::pid_t,
+    statusv: &mut libc::c_int,
+    options: libc::c_int,
+) -> (libc::pid_t, Option<CpuTime>) {
+    cfg_if! {
+        if #[cfg(cygwin)] {
+            (unsafe { libc::waitpid(pid, statusv, options) }, None)
+        } else {
+            let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+            let pid = unsafe { libc::wait4(pid, statusv, options, &mut usage) };
+            if pid > 0 {
+                // Macs report bytes, others KiB.
+                let rss = u64::try_from(usage.ru_maxrss).unwrap_or(0);
+                let rss = if cfg!(apple) { rss } else { rss * 1024 };
+                REAPED_PEAK_RSS.fetch_max(rss, Ordering::Relaxed);
+            }
+            (pid, Some(CpuTime::from_rusage(&usage)))
+        }
+    }
+}
+
 /// See if any reapable processes have exited, and mark them accordingly.
 /// \param block_ok if no reapable processes have exited, block until one is (or until we receive a
 /// signal).
@@ -1252,13 +1433,11 @@ fn process_mark_finished_children(parser: &mut Parser, block_ok: bool, block_io:
 
             // Ok, we are reapable. Run waitpid()!
             let mut statusv: libc::c_int = -1;
-            let pid = unsafe {
-                libc::waitpid(
-                    proc.pid().unwrap().as_pid_t(),
-                    &mut statusv,
-                    WNOHANG | WUNTRACED | WCONTINUED,
-                )
-            };
+            let (pid, cpu_time) = wait_with_cpu_time(
+                proc.pid().unwrap().as_pid_t(),
+                &mut statusv,
+                WNOHANG | WUNTRACED | WCONTINUED,
+            );
             if pid == 0 {
                 continue;
             }
@@ -1267,6 +1446,13 @@ fn process_mark_finished_children(parser: &mut Parser, block_ok: bool, block_io:
 
             // The process has stopped or exited! Update its status.
             let status = ProcStatus::from_waitpid(statusv);
+            if let Some(cpu_time) = cpu_time {
+                if status.normal_exited() || status.signal_exited() {
+                    proc.cpu_time.set(cpu_time);
+                    let mut total = REAPED_CPU_TIME.lock().unwrap();
+                    *total = total.add(cpu_time);
+                }
+            }
             handle_child_status(j, proc, status);
             if status.stopped() {
                 j.group().set_is_foreground(false);
@@ -1387,6 +1573,30 @@ fn generate_job_exit_events(j: &Job, out_evts: &mut Vec<Event>) {
     out_evts.push(Event::caller_exit(j.internal_job_id, j.job_id()));
 }
 
+/// Given a job, generate a fish_job_complete event if it is a background job which has just
+/// completed. Unlike job_exit events, this does not wait for the job to be removed from the job
+/// list, which may be deferred until fish is interactive. Jobs from event handlers are skipped, so
+/// that a handler which starts a background job does not trigger itself.
+fn generate_job_complete_event(j: &Job, out_evts: &mut Vec<Event>) {
+    if !j.is_constructed()
+        || !j.is_completed()
+        || j.is_foreground()
+        || j.from_event_handler()
+        || j.job_id().is_none()
+        || j.flags().posted_job_complete
+    {
+        return;
+    }
+    j.flags_mut().posted_job_complete = true;
+    out_evts.push(Event::job_complete(
+        j.job_id(),
+        j.pgid(),
+        j.command().to_owned(),
+        j.statuses().map_or(0, |st| st.status),
+        j.wall_duration(),
+    ));
+}
+
 /// Return whether to emit a fish_job_summary call for a process.
 fn proc_wants_summary(j: &Job, p: &Process) -> bool {
     // Are we completed with a pid?
@@ -1527,8 +1737,18 @@ fn summarize_jobs(parser: &mut Parser, jobs: &[JobRef]) -> bool {
 
 /// Remove all disowned jobs whose job chain is fully constructed (that is, do not erase disowned
 /// jobs that still have an in-flight parent job). Note we never print statuses for such jobs.
-fn remove_disowned_jobs(jobs: &mut JobList) {
-    jobs.retain(|j| !j.flags().disown_requested || !j.is_constructed());
+fn remove_disowned_jobs(parser: &mut Parser) {
+    let mut disowned = vec![];
+    parser.jobs_mut().retain(|j| {
+        if j.flags().disown_requested && j.is_constructed() {
+            disowned.push(j.clone());
+            return false;
+        }
+        true
+    });
+    for j in disowned {
+        save_captured_output(&j, parser.mut_captured_output());
+    }
 }
 
 /// Given that a job has completed, check if it may be wait'ed on; if so add it to the wait handle
@@ -1552,6 +1772,33 @@ fn save_wait_handle_for_completed_job(job: &Job, store: &mut WaitHandleStore) {
     }
 }
 
+/// Given that a job has completed or was disowned, stop capturing its output and remember what it
+/// printed, so it may be viewed with `jobs --output`. This replaces output of an earlier job with
+/// the same ID. A disowned job may still be running, its further output goes to our stdout.
+fn save_captured_output(job: &Job, store: &mut BTreeMap<JobId, Vec<u8>>) {
+    let Some(filler) = job.captured_output.borrow_mut().take() else {
+        return;
+    };
+    let output = if job.is_completed() {
+        IoBufferfill::finish(filler)
+    } else {
+        let (buffer, pipe) = IoBufferfill::finish_keeping_pipe(filler);
+        if let Some(pipe) = pipe {
+            forward_to_stdout(pipe);
+        }
+        buffer
+    }
+    .newline_serialized();
+    let Some(job_id) = job.job_id().0 else {
+        return;
+    };
+    if output.is_empty() {
+        store.remove(&job_id);
+    } else {
+        store.insert(job_id, output);
+    }
+}
+
 /// Remove completed jobs from the job list, printing status messages as appropriate.
 /// Return whether something was printed.
 fn process_clean_after_marking(parser: &mut Parser, interactive: bool) -> bool {
@@ -1564,7 +1811,7 @@ fn process_clean_after_marking(parser: &mut Parser, interactive: bool) -> bool {
     let _cleaning = parser.push_scope(|s| s.is_cleaning_procs = true);
 
     // Remove all disowned jobs.
-    remove_disowned_jobs(parser.jobs_mut());
+    remove_disowned_jobs(parser);
 
     // Accumulate exit events into a new list, which we fire after the list manipulation is
     // complete.
@@ -1595,9 +1842,11 @@ fn process_clean_after_marking(parser: &mut Parser, interactive: bool) -> bool {
         }
     }
 
-    // Generate process_exit events for finished processes.
+    // Generate process_exit events for finished processes, and fish_job_complete events for
+    // finished background jobs.
     for j in parser.jobs() {
         generate_process_exit_events(j, &mut exit_events);
+        generate_job_complete_event(j, &mut exit_events);
     }
 
     // Remove completed, processable jobs from our job list.
@@ -1618,6 +1867,7 @@ fn process_clean_after_marking(parser: &mut Parser, interactive: bool) -> bool {
     });
     for j in completed_jobs {
         save_wait_handle_for_completed_job(&j, parser.mut_wait_handles());
+        save_captured_output(&j, parser.mut_captured_output());
     }
 
     // Emit calls to fish_job_summary.
@@ -0,0 +1,192 @@
+//! Rendering slow prompts in the background. The prompt functions listed in `$fish_async_prompt`
+//! are run by a separate fish process, which gets the global variables, the definition of the
+//! function and the last exit status. Until it finishes, the prompt shows the output from the
+//! last time in the same directory, or else the output of the function run with `--placeholder`,
+//! which should skip the slow parts. The prompt is repainted once the output arrives.
+
+use crate::env::config_paths::{FishPath, get_fish_path};
+use crate::env::{EnvMode, Environment, is_read_only};
+use crate::flog::flog;
+use crate::function;
+use crate::parser::Parser;
+use crate::prelude::*;
+use fish_common::escape;
+use fish_widestring::{bytes2wcstring, wcs2bytes, wcs2osstring};
+use std::ffi::OsString;
+use std::io::{Read as _, Write as _};
+use std::os::unix::process::{CommandExt as _, ExitStatusExt as _};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+
+/// The variable listing the prompt functions to render in the background.
+const ASYNC_PROMPT_VAR: &wstr = L!("fish_async_prompt");
+
+/// The argument for a quick run of an async prompt function, while its output is not known.
+pub(super) const PLACEHOLDER_ARG: &wstr = L!("--placeholder");
+
+/// The process groups of the fish processes rendering prompts, so they can be killed once their
+/// output is no longer needed.
+static RUNNING: Mutex<Vec<libc::pid_t>> = Mutex::new(Vec::new());
+
+/// The output of an async prompt function.
+pub(super) struct CachedPrompt {
+    /// The directory the prompt was rendered in. The output is not shown in other directories.
+    pub pwd: WString,
+    pub lines: Vec<WString>,
+    /// Whether this was rendered for the current prompt and not shown yet. Showing it does not
+    /// start another render.
+    pub fresh: bool,
+}
+
+/// Returns whether the prompt function `name` should be rendered in the background.
+pub(super) fn is_async_prompt(vars: &dyn Environment, name: &wstr) -> bool {
+    vars.get(ASYNC_PROMPT_VAR).is_some_and(|var| {
+        var.as_list()
+            .iter()
+            .any(|prompt| prompt.as_utfstr() == name)
+    })
+}
+
+/// What rendering a prompt function in another process needs.
+pub(super) struct AsyncPromptJob {
+    name: WString,
+    pwd: WString,
+    script: WString,
+    env: Vec<(OsString, OsString)>,
+}
+
+impl AsyncPromptJob {
+    pub(super) fn new(parser: &Parser, name: &wstr) -> Self {
+        let vars = parser.vars();
+        let mut script = WString::new();
+        // Exported variables are passed in the environment.
+        for var_name in vars.get_names(EnvMode::GLOBAL | EnvMode::UNEXPORT) {
+            if is_read_only(&var_name) {
+                continue;
+            }
+            let Some(var) = vars.getf(&var_name, EnvMode::GLOBAL) else {
+                continue;
+            };
+            script.push_str("set -g ");
+            script.push_utfstr(&escape(&var_name));
+            for value in var.as_list() {
+                script.push(' ');
+                script.push_utfstr(&escape(value));
+            }
+            script.push('\n');
+        }
+        // The function may not have been saved.
+        if let Some(props) = function::get_props(name) {
+            script.push_utfstr(&props.annotated_definition(name));
+            script.push('\n');
+        }
+        script.push_utfstr(&sprintf!(
+            "function __fish_async_prompt_status\n    return %d\nend\n__fish_async_prompt_status\n",
+            parser.last_status()
+        ));
+        script.push_utfstr(&escape(name));
+        script.push('\n');
+
+        let env = vars
+            .get_names(EnvMode::EXPORT)
+            .into_iter()
+            .filter_map(|var_name| {
+                let var = vars.get(&var_name)?;
+                Some((wcs2osstring(&var_name), wcs2osstring(&var.as_string())))
+            })
+            .collect();
+        Self {
+            name: name.to_owned(),
+            pwd: vars.get_pwd_slash(),
+            script,
+            env,
+        }
+    }
+
+    /// Start the fish process, in its own process group. It reads the script from stdin, which
+    /// is passed on to the prompt's commands once the script has been read.
+    fn spawn(&self) -> std::io::Result<Child> {
+        let fish = match get_fish_path() {
+            FishPath::Absolute(path) => path.as_os_str().to_owned(),
+            FishPath::LookUpInPath => OsString::from("fish"),
+        };
+        let mut child = Command::new(fish)
+            .args(["-c", "source -"])
+            .current_dir(wcs2osstring(&self.pwd))
+            .env_clear()
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn()?;
+        RUNNING
+            .lock()
+            .unwrap()
+            .push(libc::pid_t::try_from(child.id()).unwrap());
+        // Dropping stdin closes it, so fish sees the end of the script. If fish is gone already,
+        // waiting for it reports that.
+        let _ = child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(&wcs2bytes(&self.script));
+        Ok(child)
+    }
+}
+
+/// Kill the processes of earlier renders, whose output would not be shown anyway. Otherwise a
+/// prompt function that does not finish would keep later renders from starting.
+pub(super) fn kill_stale_renders() {
+    for pgid in RUNNING.lock().unwrap().drain(..) {
+        unsafe { libc::kill(-pgid, libc::SIGKILL) };
+    }
+}
+
+/// Runs the prompt functions in parallel, and returns their names and output. Functions which
+/// could not be run or were killed are left out. This is called from a background thread.
+pub(super) fn render_async_prompts(jobs: Vec<AsyncPromptJob>) -> Vec<(WString, CachedPrompt)> {
+    let children: Vec<_> = jobs
+        .iter()
+        .map(|job| {
+            job.spawn()
+                .map_err(|err| {
+                    flog!(
+                        warning,
+                        wgettext_fmt!(
+                            "Could not render async prompt '%s': %s",
+                            job.name,
+                            err.to_string()
+                        )
+                    );
+                })
+                .ok()
+        })
+        .collect();
+    jobs.into_iter()
+        .zip(children)
+        .filter_map(|(job, child)| {
+            let mut child = child?;
+            let pgid = libc::pid_t::try_from(child.id()).unwrap();
+            let mut output = vec![];
+            let read = child.stdout.take().unwrap().read_to_end(&mut output);
+            // Once the child is reaped, its pid may be reused, so stop killing its group first.
+            RUNNING.lock().unwrap().retain(|&pid| pid != pgid);
+            let status = child.wait().ok()?;
+            read.ok()?;
+            if status.signal().is_some() {
+                return None;
+            }
+            let stdout: &[u8] = &output;
+            // Like command substitutions, split the output into lines.
+            let stdout = stdout.strip_suffix(b"\n").unwrap_or(stdout);
+            let lines = stdout.split(|&c| c == b'\n').map(bytes2wcstring).collect();
+            let prompt = CachedPrompt {
+                pwd: job.pwd,
+                lines,
+                fresh: true,
+            };
+            Some((job.name, prompt))
+        })
+        .collect()
+}
@@ -0,0 +1,136 @@
+//! The sources of autosuggestions, which users can choose and order with
+//! `$fish_autosuggestion_providers`.
+
+use crate::env::Environment;
+use crate::exec::exec_subshell;
+use crate::function;
+use crate::parser::Parser;
+use crate::prelude::*;
+use crate::wutil::wstat;
+use fish_common::{EscapeFlags, EscapeStringStyle, escape_string};
+
+/// The variable listing the providers, from the highest priority to the lowest.
+const PROVIDERS_VAR: &wstr = L!("fish_autosuggestion_providers");
+
+/// Where an autosuggestion may come from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum AutosuggestionProvider {
+    /// Earlier commands starting with the command line.
+    History,
+    /// The completion for the last token.
+    Completion,
+    /// Changing to one of the directories in `$dirprev`.
+    DirectoryHistory,
+    /// A function printing the suggested command line.
+    Function(WString),
+}
+
+/// Return the providers to ask for autosuggestions, from the highest priority to the lowest. By
+/// default, these are history and completions.
+pub(super) fn autosuggestion_providers(vars: &dyn Environment) -> Vec<AutosuggestionProvider> {
+    let Some(var) = vars.get(PROVIDERS_VAR) else {
+        return vec![
+            AutosuggestionProvider::History,
+            AutosuggestionProvider::Completion,
+        ];
+    };
+    let mut providers = vec![];
+    for name in var.as_list() {
+        let provider = if name.is_empty() {
+            continue;
+        } else if name == "history" {
+            AutosuggestionProvider::History
+        } else if name == "completion" {
+            AutosuggestionProvider::Completion
+        } else if name == "directory-history" {
+            AutosuggestionProvider::DirectoryHistory
+        } else {
+            AutosuggestionProvider::Function(name.clone())
+        };
+        if !providers.contains(&provider) {
+            providers.push(provider);
+        }
+    }
+    providers
+}
+
+/// Suggest changing to a previously visited directory, starting with the most recent one, if the
+/// command line is `cd` followed by the start of the directory's path. This does I/O!
+pub(super) fn directory_history_autosuggestion(
+    command_line: &wstr,
+    vars: &dyn Environment,
+) -> Option<WString> {
+    if command_line.contains('\n') {
+        return None;
+    }
+    let rest = command_line.strip_prefix(L!("cd "))?;
+    let arg = &rest[rest.chars().take_while(|&c| c == ' ').count()..];
+    let prefix = &command_line[..command_line.len() - arg.len()];
+    let dirprev = vars.get(L!("dirprev"))?;
+    let pwd = vars.get_pwd_slash();
+    let home = vars
+        .get_unless_empty(L!("HOME"))
+        .map(|home| home.as_string());
+
+    let escape =
+        |path: &wstr| escape_string(path, EscapeStringStyle::Script(EscapeFlags::NO_QUOTED));
+    for dir in dirprev.as_list().iter().rev() {
+        let mut dir_slash = dir.clone();
+        if !dir_slash.ends_with('/') {
+            dir_slash.push('/');
+        }
+        if dir.is_empty() || dir_slash == pwd {
+            continue;
+        }
+        let mut candidates = vec![escape(dir)];
+        if let Some(rest) = home
+            .as_ref()
+            .and_then(|home| dir.strip_prefix(home.as_utfstr()))
+        {
+            if rest.is_empty() || rest.starts_with('/') {
+                candidates.push(L!("~").to_owned() + escape(rest).as_utfstr());
+            }
+        }
+        let Some(candidate) = candidates
+            .into_iter()
+            .find(|candidate| candidate.len() > arg.len() && candidate.starts_with(arg))
+        else {
+            continue;
+        };
+        if wstat(dir).is_ok_and(|md| md.is_dir()) {
+            return Some(prefix.to_owned() + candidate.as_utfstr());
+        }
+    }
+    None
+}
+
+/// Run an autosuggestion function with the command line as its argument, and return the first line
+/// it printed, if that extends the command line.
+pub(super) fn function_autosuggestion(
+    parser: &mut Parser,
+    function_name: &wstr,
+    command_line: &wstr,
+) -> Option<WString> {
+    if !function::exists(function_name, parser) {
+        return None;
+    }
+    let mut cmd = escape_string(function_name, EscapeStringStyle::default());
+    cmd.push(' ');
+    cmd.push_utfstr(&escape_string(command_line, EscapeStringStyle::default()));
+
+    let _scoped = parser.push_scope(|s| {
+        s.is_interactive = false;
+        s.suppress_fish_trace = true;
+    });
+    let mut output = vec![];
+    let _ = exec_subshell(
+        &cmd,
+        parser,
+        Some(&mut output),
+        /*apply_exit_status=*/ false,
+    );
+
+    let suggestion = output.into_iter().next()?;
+    (suggestion.len() > command_line.len() && suggestion.starts_with(command_line))
+        .then_some(suggestion)
+}
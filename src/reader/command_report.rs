@@ -0,0 +1,147 @@
+//! Reporting commands which ran for longer than `$fish_command_duration_threshold`. The report is
+//! printed, or passed to the `fish_postexec_report` function if it is defined, and it may also be
+//! sent as a desktop notification.
+
+use crate::env::Environment;
+use crate::function;
+use crate::io::IoChain;
+use crate::parser::Parser;
+use crate::prelude::*;
+use crate::wutil::fish_wcstoul;
+use fish_common::escape;
+use std::time::Duration;
+
+/// The variable with the duration in milliseconds above which commands are reported.
+const THRESHOLD_VAR: &wstr = L!("fish_command_duration_threshold");
+
+/// The variable enabling desktop notifications about the reported commands.
+pub(super) const NOTIFICATION_VAR: &wstr = L!("fish_command_notification");
+
+/// The function which is given the report, instead of printing it.
+const REPORT_FUNCTION_NAME: &wstr = L!("fish_postexec_report");
+
+/// A command which ran for longer than the threshold.
+pub(super) struct CommandReport {
+    command: WString,
+    duration: Duration,
+    exit_status: i32,
+    /// The largest peak memory use of the external processes of the command, in bytes.
+    peak_rss: Option<u64>,
+}
+
+impl CommandReport {
+    /// Returns the report for a command, or None if it should not be reported.
+    pub(super) fn new(
+        vars: &dyn Environment,
+        command: &wstr,
+        duration: Duration,
+        exit_status: i32,
+        peak_rss: Option<u64>,
+    ) -> Option<Self> {
+        let threshold = vars
+            .get_unless_empty(THRESHOLD_VAR)
+            .and_then(|var| fish_wcstoul(&var.as_string()).ok())?;
+        if duration < Duration::from_millis(threshold) {
+            return None;
+        }
+        Some(Self {
+            command: command.to_owned(),
+            duration,
+            exit_status,
+            peak_rss,
+        })
+    }
+
+    /// Print the report, or pass it to `fish_postexec_report` if it is defined.
+    pub(super) fn run(&self, parser: &mut Parser) {
+        if !function::exists(REPORT_FUNCTION_NAME, parser) {
+            eprintf!("%s\n", self.describe());
+            return;
+        }
+        // The function gets the command, the duration in milliseconds, the exit status and the
+        // peak memory use in bytes, which is empty if it is not known.
+        let mut cmd = REPORT_FUNCTION_NAME.to_owned();
+        for arg in [
+            self.command.clone(),
+            self.duration.as_millis().to_wstring(),
+            self.exit_status.to_wstring(),
+            self.peak_rss
+                .map(|rss| rss.to_wstring())
+                .unwrap_or_default(),
+        ] {
+            cmd.push(' ');
+            cmd.push_utfstr(&escape(&arg));
+        }
+        // Like event handlers, the function must not change $status.
+        let _non_interactive = parser.push_scope(|s| s.is_interactive = false);
+        let saved_statuses = parser.last_statuses();
+        parser.eval(&cmd, &IoChain::new());
+        parser.set_last_statuses(saved_statuses);
+    }
+
+    /// The body of the desktop notification about the command.
+    pub(super) fn notification(&self) -> WString {
+        // Only the first line of a multiline command.
+        let command = self.command.split('\n').next().unwrap();
+        sprintf!("%s: %s", command, self.describe())
+    }
+
+    fn describe(&self) -> WString {
+        let duration = format_duration(self.duration);
+        match self.peak_rss {
+            Some(rss) => wgettext_fmt!(
+                "took %s, exit status %d, peak memory %s",
+                duration,
+                self.exit_status,
+                format_bytes(rss)
+            ),
+            None => wgettext_fmt!("took %s, exit status %d", duration, self.exit_status),
+        }
+    }
+}
+
+/// Return the duration like "1h 2m 3s", with tenths of a second below a minute.
+fn format_duration(duration: Duration) -> WString {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        return sprintf!("%.1fs", duration.as_secs_f64());
+    }
+    let (hours, minutes, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        sprintf!("%dh %dm %ds", hours, minutes, secs)
+    } else {
+        sprintf!("%dm %ds", minutes, secs)
+    }
+}
+
+/// Return `bytes` with the largest binary prefix that keeps the number at least 1.
+fn format_bytes(bytes: u64) -> WString {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        sprintf!("%d B", bytes)
+    } else {
+        sprintf!("%.1f %s", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_bytes, format_duration};
+    use crate::prelude::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_report() {
+        assert_eq!(format_duration(Duration::from_millis(2345)), L!("2.3s"));
+        assert_eq!(format_duration(Duration::from_secs(125)), L!("2m 5s"));
+        assert_eq!(format_duration(Duration::from_secs(3725)), L!("1h 2m 5s"));
+        assert_eq!(format_bytes(512), L!("512 B"));
+        assert_eq!(format_bytes(3 * 1024 * 1024 / 2), L!("1.5 MiB"));
+    }
+}
@@ -0,0 +1,268 @@
+//! Editing the command line in an external editor. The command line is written, indented, to a
+//! file in a private temporary directory, which is removed when the edit is done. The editor is
+//! told where the cursor is, and for some editors we read back where it was when they exited.
+
+use crate::complete::complete_get_wrap_targets;
+use crate::exec::exec_subshell;
+use crate::parse_util::{apply_indents, compute_indents, remove_indents};
+use crate::parser::Parser;
+use crate::prelude::*;
+use crate::tokenizer::{TOK_ACCEPT_UNFINISHED, TOK_ARGUMENT_LIST, TokenType, Tokenizer};
+use crate::wutil::{fish_wcstoul, wbasename};
+use fish_common::{UnescapeStringStyle, escape, unescape_string};
+use fish_tempfile::TempDir;
+use fish_widestring::{bytes2wcstring, osstr2wcstring, wcs2bytes, wcs2osstring};
+use std::process::{Command, Stdio};
+
+/// The name of the file holding the command line. The extension lets editors pick fish syntax.
+const FILE_NAME: &str = "command-line.fish";
+
+/// The name of the file some editors write their final cursor position to.
+const CURSOR_FILE_NAME: &str = "cursor";
+
+/// Returns the editor command from `$VISUAL` or `$EDITOR`, or None if neither is set, in which
+/// case the user has been told so.
+pub(super) fn editor_command(parser: &mut Parser) -> Option<Vec<WString>> {
+    let mut editor = vec![];
+    let _ = exec_subshell(L!("__fish_anyeditor"), parser, Some(&mut editor), false);
+    (!editor.is_empty()).then_some(editor)
+}
+
+/// The result of editing the command line.
+pub(super) struct EditResult {
+    pub text: WString,
+    /// The cursor position in `text`, if the editor reported it.
+    pub cursor: Option<usize>,
+}
+
+/// A command line being edited.
+pub(super) struct ExternalEdit {
+    /// The private directory with the files, which is removed on drop.
+    dir: TempDir,
+    file: WString,
+    cursor_file: WString,
+    /// The line and column of the cursor in the file, starting at 1.
+    line: usize,
+    column: usize,
+    private_mode: bool,
+}
+
+impl ExternalEdit {
+    /// Write the command line to a new file, with `cursor` as the position to open it at.
+    pub(super) fn new(text: &wstr, cursor: usize, private_mode: bool) -> std::io::Result<Self> {
+        let dir = fish_tempfile::new_dir()?;
+        let file = dir.path().join(FILE_NAME);
+        let cursor_file = dir.path().join(CURSOR_FILE_NAME);
+        let indented = apply_indents(text, &compute_indents(text));
+        let mut contents = wcs2bytes(&indented);
+        contents.push(b'\n');
+        std::fs::write(&file, contents)?;
+
+        let (line, column) = line_and_column(text, cursor);
+        let indent = line_at(&indented, line).len() - line_at(text, line).len();
+        Ok(Self {
+            file: osstr2wcstring(file),
+            cursor_file: osstr2wcstring(cursor_file),
+            dir,
+            line: line + 1,
+            column: indent + column + 1,
+            private_mode,
+        })
+    }
+
+    /// Returns the command which runs `editor` on the file.
+    pub(super) fn command(&self, editor: &[WString]) -> WString {
+        let mut args = editor.to_vec();
+        let name = wbasename(&editor[0]);
+        // Wrapping commands may take the arguments of the editor they wrap.
+        let wrapped = complete_get_wrap_targets(name)
+            .into_iter()
+            .filter_map(|target| unescaped_tokens(&target).into_iter().next());
+        let editor_args = std::iter::once(name.to_owned())
+            .chain(wrapped)
+            .find_map(|name| self.editor_args(&name));
+        args.extend(editor_args.unwrap_or_else(|| vec![self.file.clone()]));
+
+        let mut cmd = WString::new();
+        for arg in &args {
+            if !cmd.is_empty() {
+                cmd.push(' ');
+            }
+            cmd.push_utfstr(&escape(arg));
+        }
+        cmd
+    }
+
+    /// Returns the arguments which open the file in the editor called `name` with the cursor in
+    /// place, or None if the editor is not known.
+    fn editor_args(&self, name: &wstr) -> Option<Vec<WString>> {
+        let (file, line, column) = (&self.file, self.line, self.column);
+        let args = match name.to_string().as_str() {
+            "vi" | "vim" | "nvim" => {
+                // Not all vi implementations are vim.
+                if name == L!("vi") && !vi_is_vim() {
+                    return Some(vec![sprintf!("+%d", line), file.clone()]);
+                }
+                let mut args = vec![];
+                if self.private_mode {
+                    // No swap file and no saved command history.
+                    args.extend([
+                        L!("-n").to_owned(),
+                        L!("-i").to_owned(),
+                        L!("NONE").to_owned(),
+                    ]);
+                }
+                args.extend([
+                    sprintf!("+%d", line),
+                    sprintf!("+norm! %d|", column),
+                    file.clone(),
+                    sprintf!(
+                        "+au VimLeave * ++once call writefile([printf(\"%%s %%s %%s\", shellescape(bufname()), line(\".\"), col(\".\"))], \"%s\")",
+                        &self.cursor_file
+                    ),
+                ]);
+                args
+            }
+            "emacs" | "emacsclient" | "gedit" => {
+                vec![sprintf!("+%d:%d", line, column), file.clone()]
+            }
+            "kak" => vec![
+                sprintf!("+%d:%d", line, column),
+                file.clone(),
+                L!("-e").to_owned(),
+                sprintf!(
+                    "hook -always -once global ClientClose %%val{client} %%{ echo -to-file %s -quoting shell %%val{buffile} %%val{cursor_line} %%val{cursor_column} }",
+                    &self.cursor_file
+                ),
+            ],
+            "nano" => vec![sprintf!("+%d,%d", line, column), file.clone()],
+            "joe" | "ee" => vec![sprintf!("+%d", line), file.clone()],
+            "code" | "code-oss" => vec![
+                L!("--goto").to_owned(),
+                sprintf!("%s:%d:%d", file, line, column),
+                L!("--wait").to_owned(),
+            ],
+            "subl" => vec![
+                sprintf!("%s:%d:%d", file, line, column),
+                L!("--wait").to_owned(),
+            ],
+            "micro" => vec![file.clone(), sprintf!("+%d:%d", line, column)],
+            "helix" | "hx" => vec![sprintf!("%s:%d:%d", file, line, column)],
+            _ => return None,
+        };
+        Some(args)
+    }
+
+    /// Read back the edited command line. Returns None if the file is empty or gone.
+    pub(super) fn finish(self) -> Option<EditResult> {
+        let contents = std::fs::read(wcs2osstring(&self.file)).ok()?;
+        if contents.is_empty() {
+            return None;
+        }
+        let contents = contents.strip_suffix(b"\n").unwrap_or(&contents);
+        let raw = bytes2wcstring(contents);
+        let text = remove_indents(&raw, &compute_indents(&raw));
+
+        // The editor may have told us where its cursor was, as a quoted file name, line and
+        // column.
+        let cursor = std::fs::read(wcs2osstring(&self.cursor_file))
+            .ok()
+            .and_then(|report| {
+                let report = unescaped_tokens(&bytes2wcstring(&report));
+                let [file, line, column] = report.as_slice() else {
+                    return None;
+                };
+                if *file != self.file {
+                    return None;
+                }
+                let line = fish_wcstoul(line).ok()?.checked_sub(1)?;
+                let column = fish_wcstoul(column).ok()?.checked_sub(1)?;
+                let (line, column) = (usize::try_from(line).ok()?, usize::try_from(column).ok()?);
+                let indent = line_at(&raw, line).len() - line_at(&text, line).len();
+                Some(offset_at(&text, line, column.saturating_sub(indent)))
+            });
+        drop(self.dir);
+        Some(EditResult { text, cursor })
+    }
+}
+
+/// Returns whether `vi` is vim, which unlike other implementations supports `--version`.
+fn vi_is_vim() -> bool {
+    Command::new("vi")
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Split `s` into arguments like `read --tokenize` does.
+fn unescaped_tokens(s: &wstr) -> Vec<WString> {
+    let mut tokenizer = Tokenizer::new(s, TOK_ACCEPT_UNFINISHED | TOK_ARGUMENT_LIST);
+    let mut tokens = vec![];
+    while let Some(token) = tokenizer.next() {
+        if token.type_ != TokenType::String {
+            continue;
+        }
+        let text = tokenizer.text_of(&token);
+        tokens.push(
+            unescape_string(text, UnescapeStringStyle::default())
+                .unwrap_or_else(|| text.to_owned()),
+        );
+    }
+    tokens
+}
+
+/// Returns the line of `text` with the given index, or an empty string if there is none.
+fn line_at(text: &wstr, line: usize) -> &wstr {
+    text.split('\n').nth(line).unwrap_or(L!(""))
+}
+
+/// Returns the line and column of `offset` in `text`, starting at 0.
+pub(super) fn line_and_column(text: &wstr, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.chars().filter(|&c| c == '\n').count();
+    let line_start = before
+        .as_char_slice()
+        .iter()
+        .rposition(|&c| c == '\n')
+        .map_or(0, |newline| newline + 1);
+    (line, before.len() - line_start)
+}
+
+/// Returns the offset of the given line and column in `text`, clamped to the last line and to the
+/// end of the line.
+pub(super) fn offset_at(text: &wstr, line: usize, column: usize) -> usize {
+    let mut offset = 0;
+    let mut lines = text.split('\n').peekable();
+    for _ in 0..line {
+        let Some(current) = lines.next() else {
+            break;
+        };
+        if lines.peek().is_none() {
+            // This is the last line.
+            return offset + current.len();
+        }
+        offset += current.len() + 1;
+    }
+    offset + column.min(lines.next().map_or(0, |current| current.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{line_and_column, offset_at};
+    use crate::prelude::*;
+
+    #[test]
+    fn test_line_and_column() {
+        let text = L!("if true\n    echo\nend");
+        assert_eq!(line_and_column(text, 0), (0, 0));
+        assert_eq!(line_and_column(text, 12), (1, 4));
+        assert_eq!(line_and_column(text, text.len()), (2, 3));
+        assert_eq!(offset_at(text, 1, 4), 12);
+        // Positions past the end of a line or of the text are clamped.
+        assert_eq!(offset_at(text, 0, 100), 7);
+        assert_eq!(offset_at(text, 5, 0), text.len());
+    }
+}
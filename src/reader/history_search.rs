@@ -1,6 +1,8 @@
 //! Encapsulation of the reader's history search functionality.
 
-use crate::history::{self, History, HistorySearch, SearchDirection, SearchFlags, SearchType};
+use crate::history::{
+    self, History, HistoryFilter, HistorySearch, SearchDirection, SearchFlags, SearchType,
+};
 use crate::parse_constants::SourceRange;
 use crate::prelude::*;
 use crate::tokenizer::{TOK_ACCEPT_UNFINISHED, TokenType, Tokenizer};
@@ -38,6 +40,10 @@ pub enum SearchMode {
     Inactive,
     /// searching by line
     Line,
+    /// searching by line among the commands run in the current directory
+    Directory,
+    /// searching by line in the history of the current project, and then in the history
+    Project,
     /// searching by prefix
     Prefix,
     /// searching by token
@@ -55,6 +61,9 @@ pub struct ReaderHistorySearch {
     /// Our history search itself.
     search: Option<HistorySearch>,
 
+    /// The search to continue with once the first one has no more matches.
+    fallback_search: Option<HistorySearch>,
+
     /// The ordered list of matches. This may grow long.
     matches: Vec<SearchMatch>,
 
@@ -154,13 +163,16 @@ impl ReaderHistorySearch {
         self.move_backwards();
     }
 
-    /// Reset, beginning a new line or token mode search.
+    /// Reset, beginning a new line or token mode search, which only finds items passing the filter.
+    /// Once there are no more matches in `hist`, the search continues in `fallback_hist`.
     pub fn reset_to_mode(
         &mut self,
         text: WString,
         hist: Arc<History>,
+        fallback_hist: Option<Arc<History>>,
         mode: SearchMode,
         token_offset: usize,
+        filter: HistoryFilter,
     ) {
         assert_ne!(
             mode,
@@ -174,17 +186,17 @@ impl ReaderHistorySearch {
         self.token_offset = token_offset;
         let flags = SearchFlags::NO_DEDUP | smartcase_flags(&text);
         // We can skip dedup in history_search_t because we do it ourselves in skips_.
-        self.search = Some(HistorySearch::new_with(
-            hist,
-            text,
-            if self.by_prefix() {
-                SearchType::Prefix
-            } else {
-                SearchType::Contains
-            },
-            flags,
-            0,
-        ));
+        let search_type = if self.by_prefix() {
+            SearchType::Prefix
+        } else {
+            SearchType::Contains
+        };
+        self.fallback_search = fallback_hist.map(|hist| {
+            HistorySearch::new_with(hist, text.clone(), search_type, flags, 0)
+                .with_filter(filter.clone())
+        });
+        self.search =
+            Some(HistorySearch::new_with(hist, text, search_type, flags, 0).with_filter(filter));
     }
 
     /// Reset to inactive search.
@@ -195,6 +207,7 @@ impl ReaderHistorySearch {
         self.mode = SearchMode::Inactive;
         self.token_offset = 0;
         self.search = None;
+        self.fallback_search = None;
     }
 
     /// Adds the given match if we haven't seen it before.
@@ -218,7 +231,10 @@ impl ReaderHistorySearch {
         let before = self.matches.len();
         let text = self.search().current_string();
         let needle = self.search_string();
-        if matches!(self.mode, SearchMode::Line | SearchMode::Prefix) {
+        if matches!(
+            self.mode,
+            SearchMode::Line | SearchMode::Directory | SearchMode::Project | SearchMode::Prefix
+        ) {
             let offset = find(text, needle).unwrap();
             self.add_if_new(SearchMatch::new(text.to_owned(), offset));
         } else if matches!(self.mode, SearchMode::Token | SearchMode::LastToken) {
@@ -263,19 +279,25 @@ impl ReaderHistorySearch {
             return true;
         }
 
-        // Add more items from our search.
-        while self
-            .search_mut()
-            .go_to_next_match(SearchDirection::Backward)
-        {
-            if self.append_matches_from_search() {
-                self.match_index += 1;
-                assert!(
-                    self.match_index < self.matches.len(),
-                    "Should have found more matches"
-                );
-                return true;
+        // Add more items from our search, and then from the fallback search.
+        loop {
+            while self
+                .search_mut()
+                .go_to_next_match(SearchDirection::Backward)
+            {
+                if self.append_matches_from_search() {
+                    self.match_index += 1;
+                    assert!(
+                        self.match_index < self.matches.len(),
+                        "Should have found more matches"
+                    );
+                    return true;
+                }
             }
+            let Some(fallback_search) = self.fallback_search.take() else {
+                break;
+            };
+            self.search = Some(fallback_search);
         }
 
         // Here we failed to go backwards past the last history item.
@@ -2,13 +2,14 @@
 use super::{Reader, reader_reading_interrupted, reader_schedule_prompt_repaint};
 use crate::{
     event,
-    input::{CharEvent, InputData, InputEventQueuer, ReadlineCmd, input_get_bind_mode},
+    input::{
+        CharEvent, ImplicitEvent, InputData, InputEventQueuer, ReadlineCmd, input_get_bind_mode,
+    },
     proc::job_reap,
     signal::signal_clear_cancel,
 };
-use fish_common::escape;
 use fish_widestring::{WString, bytes2wcstring};
-use std::os::fd::RawFd;
+use std::{os::fd::RawFd, time::Instant};
 
 impl<'a> InputEventQueuer for Reader<'a> {
     fn get_input_data(&self) -> &InputData {
@@ -23,6 +24,10 @@ impl<'a> InputEventQueuer for Reader<'a> {
         self.debouncers.event_signaller_read_fd()
     }
 
+    fn get_deadline(&self) -> Option<Instant> {
+        self.conf.deadline
+    }
+
     fn prepare_to_select(&mut self) {
         // Fire any pending events and reap stray processes, including printing exit status messages.
         event::fire_delayed(self.parser);
@@ -64,17 +69,18 @@ impl<'a> InputEventQueuer for Reader<'a> {
     fn paste_start_buffering(&mut self) {
         self.input_data.paste_buffer = Some(vec![]);
         self.push_front(CharEvent::from_readline(ReadlineCmd::BeginUndoGroup));
+        self.push_front(CharEvent::Implicit(ImplicitEvent::PasteStart));
     }
 
     fn paste_commit(&mut self) {
+        self.push_front(CharEvent::Implicit(ImplicitEvent::PasteEnd));
         self.push_front(CharEvent::from_readline(ReadlineCmd::EndUndoGroup));
         let Some(buffer) = self.input_data.paste_buffer.take() else {
             return;
         };
-        self.push_front(CharEvent::Command(sprintf!(
-            "__fish_paste %s",
-            escape(&bytes2wcstring(&buffer))
-        )));
+        self.push_front(CharEvent::Implicit(ImplicitEvent::Paste(bytes2wcstring(
+            &buffer,
+        ))));
     }
 
     fn get_bind_mode(&self) -> WString {
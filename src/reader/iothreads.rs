@@ -3,6 +3,7 @@
 
 use super::{Reader, reader};
 use crate::fd_monitor::FdEventSignaller;
+use crate::highlight::ValidityCache;
 use crate::threads::{Debounce, ThreadPool};
 use std::os::unix::io::RawFd;
 use std::sync::Arc;
@@ -25,6 +26,10 @@ pub(super) struct Debouncers {
     // Debounce history pager computations. This holds a callback, not a single value,
     // both to demonstrate the technique and because the callback can capture local variables.
     pub history_pager: Debounce<Callback>,
+    // Debounce rendering the prompts listed in $fish_async_prompt.
+    pub async_prompt: Debounce<Callback>,
+    // Cached results of checks for syntax highlighting, which run in their own threads.
+    pub validity_cache: Arc<ValidityCache>,
 }
 
 impl Debouncers {
@@ -36,10 +41,14 @@ impl Debouncers {
         const HIGHLIGHT_TIMEOUT: Duration = Duration::from_millis(500);
         const HISTORY_PAGER_TIMEOUT: Duration = Duration::from_millis(500);
         const AUTOSUGGEST_TIMEOUT: Duration = Duration::from_millis(500);
+        // Async prompts are expected to be slow.
+        const ASYNC_PROMPT_TIMEOUT: Duration = Duration::from_secs(5);
         Self {
             autosuggestions: Debounce::new(&pool, &event_signaller, AUTOSUGGEST_TIMEOUT),
             highlight: Debounce::new(&pool, &event_signaller, HIGHLIGHT_TIMEOUT),
             history_pager: Debounce::new(&pool, &event_signaller, HISTORY_PAGER_TIMEOUT),
+            async_prompt: Debounce::new(&pool, &event_signaller, ASYNC_PROMPT_TIMEOUT),
+            validity_cache: ValidityCache::new(&event_signaller),
             event_signaller,
         }
     }
@@ -1,4 +1,9 @@
+mod async_prompt;
+mod autosuggest;
+mod command_report;
+mod external_editor;
 mod history_search;
+pub mod snippet;
 
 mod input;
 pub mod iothreads;
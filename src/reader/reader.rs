@@ -18,24 +18,35 @@
 //! expansion, etc.
 
 use super::{
+    async_prompt::{
+        AsyncPromptJob, CachedPrompt, PLACEHOLDER_ARG, is_async_prompt, kill_stale_renders,
+        render_async_prompts,
+    },
+    autosuggest::{
+        AutosuggestionProvider, autosuggestion_providers, directory_history_autosuggestion,
+        function_autosuggestion,
+    },
+    command_report::{CommandReport, NOTIFICATION_VAR},
+    external_editor::{self, ExternalEdit, line_and_column, offset_at},
     history_search::{ReaderHistorySearch, SearchMode, smartcase_flags},
     iothreads::{self, Debouncers},
+    snippet::{Snippet, SnippetState},
     word_motion::{MoveWordDir, MoveWordStateMachine, MoveWordStyle},
 };
 use crate::{
     abbrs::{self, abbrs_match},
     ast::{self, Kind, is_same_node},
-    builtins::{ErrorCode, STATUS_CMD_ERROR, STATUS_CMD_OK},
+    builtins::{ErrorCode, STATUS_CMD_ERROR, STATUS_CMD_OK, fish_terminal_clipboard::MAX_COPY_LEN},
     common::{get_program_name, shell_modes},
     complete::{
         CompleteFlags, Completion, CompletionList, CompletionRequestOptions, complete,
         complete_load, sort_and_prioritize,
     },
     editable_line::{Edit, EditableLine, line_at_cursor, range_of_line_at_cursor},
-    env::{EnvMode, EnvStack, Environment, Statuses},
+    env::{EnvDyn, EnvMode, EnvStack, Environment, Statuses},
     env_dispatch::{
         MIDNIGHT_COMMANDER_SID, handle_emoji_width, handle_fish_cursor_end_mode_change,
-        handle_fish_cursor_selection_mode_change,
+        handle_fish_cursor_selection_mode_change, update_fish_color_support,
     },
     event,
     exec::exec_subshell,
@@ -46,12 +57,13 @@ use crate::{
     function,
     global_safety::RelaxedAtomicBool,
     highlight::{
-        HighlightRole, HighlightSpec, autosuggest_validate_from_history, highlight_shell,
+        HighlightRole, HighlightSpec, ValidityCache, ValidityChecker,
+        autosuggest_validate_from_history, highlight_shell, highlight_shell_async,
         parse_text_face_for_highlight,
     },
     history::{
-        History, HistoryId, HistorySearch, MemoryHistoryId, PersistenceMode, SearchDirection,
-        SearchFlags, SearchType, history_id, in_private_mode,
+        History, HistoryFilter, HistoryId, HistorySearch, MemoryHistoryId, PersistenceMode,
+        SearchDirection, SearchFlags, SearchType, history_id, in_private_mode, project_history,
     },
     input::{
         BackgroundColorQuery, CharEvent, CharInputStyle, CursorPositionQuery,
@@ -60,8 +72,11 @@ use crate::{
         RecurrentQuery, TerminalQuery, stop_query,
     },
     io::IoChain,
-    key::ViewportPosition,
-    kill::{kill_add, kill_replace, kill_yank, kill_yank_rotate},
+    key::{self, Key, ViewportPosition},
+    kill::{
+        kill_add, kill_replace, kill_take_register, kill_yank, kill_yank_rotate,
+        killring_clipboard, register_get, register_set,
+    },
     nix::isatty,
     operation_context::{OperationContext, get_bg_context},
     pager::{PageRendering, Pager, SelectionMotion},
@@ -78,10 +93,10 @@ use crate::{
     prelude::*,
     proc::{
         HAVE_PROC_STAT, hup_jobs, is_interactive_session, job_reap, jobs_requiring_warning_on_exit,
-        print_exit_warning_for_jobs, proc_update_jiffies,
+        print_exit_warning_for_jobs, proc_update_jiffies, take_reaped_peak_rss,
     },
     reader::word_motion::bigword_class,
-    screen::{CharOffset, Screen, is_dumb, screen_force_clear_to_end},
+    screen::{CharOffset, Screen, is_dumb, screen_force_clear_to_end, screen_remove_status_line},
     signal::{
         signal_check_cancel, signal_clear_cancel, signal_reset_handlers, signal_set_handlers,
         signal_set_handlers_once,
@@ -90,12 +105,13 @@ use crate::{
         BufferedOutputter, Outputter,
         TerminalCommand::{
             self, ClearScreen, DecrstAlternateScreenBuffer, DecsetAlternateScreenBuffer,
-            DecsetShowCursor, Osc0WindowTitle, Osc1TabTitle, Osc133CommandFinished,
-            Osc133CommandStart, QueryBackgroundColor, QueryCursorPosition,
-            QueryKittyKeyboardProgressiveEnhancements, QueryPrimaryDeviceAttribute, QueryXtgettcap,
-            QueryXtversion,
+            DecsetShowCursor, DesktopNotification, Osc0WindowTitle, Osc1TabTitle,
+            Osc52CopyToClipboard, Osc133CommandFinished, Osc133CommandStart, QueryBackgroundColor,
+            QueryCursorPosition, QueryDecrqm, QueryKittyKeyboardProgressiveEnhancements,
+            QueryPrimaryDeviceAttribute, QueryXtgettcap, QueryXtversion,
         },
     },
+    terminal_capabilities::{CacheKey, load_probed_capabilities, save_probed_capabilities},
     termsize::{signal_safe_termsize_invalidate_tty, termsize_last, termsize_update},
     text_face::{TextFace, parse_text_face},
     threads::{assert_is_background_thread, assert_is_main_thread},
@@ -104,28 +120,32 @@ use crate::{
         variable_assignment_equals_pos,
     },
     tty_handoff::{
-        SCROLL_CONTENT_UP_TERMINFO_CODE, TtyHandoff, XTGETTCAP_QUERY_OS_NAME,
-        deactivate_tty_protocols, get_tty_protocols_active, initialize_tty_protocols,
+        CLIPBOARD_TERMINFO_CODE, SCROLL_CONTENT_UP_TERMINFO_CODE, SYNCHRONIZED_OUTPUT_MODE,
+        TRUECOLOR_TERMINFO_CODES, TtyHandoff, XTGETTCAP_QUERY_OS_NAME, deactivate_tty_protocols,
+        get_clipboard_capability, get_tty_protocols_active, initialize_tty_protocols,
     },
     wildcard::wildcard_has,
-    wutil::{fstat, perror_nix, wstat},
+    wutil::{fstat, perror_io, perror_nix, wstat},
 };
 use assert_matches::assert_matches;
 use errno::{Errno, errno};
 use fish_common::{
-    EscapeFlags, EscapeStringStyle, ScopeGuard, escape, escape_string, escape_string_with_quote,
-    exit_without_destructors, get_obfuscation_read_char, help_section,
-    restore_term_foreground_process_group_for_exit, write_loop,
+    EscapeFlags, EscapeStringStyle, ScopeGuard, UnescapeFlags, UnescapeStringStyle, escape,
+    escape_string, escape_string_with_quote, exit_without_destructors, get_obfuscation_read_char,
+    help_section, restore_term_foreground_process_group_for_exit, unescape_string, write_loop,
 };
 use fish_fallback::{fish_wcwidth, lowercase};
 use fish_feature_flags::FeatureFlag;
 use fish_util::{perror, write_to_fd};
 use fish_wcstringutil::{
-    CaseSensitivity, IsPrefix, StringFuzzyMatch, count_preceding_backslashes, is_prefix,
-    join_strings, string_prefixes_string, string_prefixes_string_case_insensitive,
-    string_prefixes_string_maybe_case_insensitive,
+    CaseSensitivity, IsPrefix, StringFuzzyMatch, count_preceding_backslashes, ifind, is_prefix,
+    join_strings, split_string_tok, string_prefixes_string,
+    string_prefixes_string_case_insensitive, string_prefixes_string_maybe_case_insensitive,
+    subsequence_score,
+};
+use fish_widestring::{
+    ELLIPSIS_CHAR, UTF8_BOM_WCHAR, bytes2wcstring, subslice_position, wcs2bytes,
 };
-use fish_widestring::{ELLIPSIS_CHAR, UTF8_BOM_WCHAR, bytes2wcstring};
 use libc::{
     _POSIX_VDISABLE, EIO, EISDIR, ENOTTY, ESRCH, O_NONBLOCK, O_RDONLY, SIGINT, STDERR_FILENO,
     STDIN_FILENO, STDOUT_FILENO, VMIN, VQUIT, VSUSP, VTIME, c_char,
@@ -143,6 +163,7 @@ use std::{
     borrow::Cow,
     cell::UnsafeCell,
     cmp,
+    collections::HashMap,
     io::BufReader,
     num::NonZeroUsize,
     ops::{ControlFlow, DerefMut, Range},
@@ -233,7 +254,7 @@ fn redirect_tty_after_sighup() {
     }
 }
 
-fn querying_allowed(vars: &dyn Environment) -> bool {
+pub(crate) fn querying_allowed(vars: &dyn Environment) -> bool {
     fish_feature_flags::feature_test(FeatureFlag::QueryTerm)
         && !is_dumb()
         && {
@@ -276,13 +297,52 @@ pub fn terminal_init(vars: &dyn Environment, inputfd: RawFd) -> TerminalInitResu
         out.write_command(QueryKittyKeyboardProgressiveEnhancements);
         out.write_command(QueryXtversion);
         out.write_command(QueryBackgroundColor);
-        query_capabilities_via_dcs(&mut out, vars);
+        query_capabilities_via_dcs(
+            &mut out,
+            vars,
+            &[
+                SCROLL_CONTENT_UP_TERMINFO_CODE,
+                CLIPBOARD_TERMINFO_CODE,
+                XTGETTCAP_QUERY_OS_NAME,
+            ],
+        );
         out.write_command(QueryPrimaryDeviceAttribute);
     }
     input_queue
         .blocking_query_mut()
         .replace(TerminalQuery::Initial);
 
+    let answered = read_initial_query_responses(&mut input_queue, &mut background_color);
+    stop_query(input_queue.blocking_query_mut());
+
+    if answered {
+        probe_capabilities(vars, &mut input_queue, &mut background_color);
+    }
+
+    let input_data = input_queue.get_input_data();
+    // We blocked execution of code and mappings so input function args must be empty.
+    assert!(input_data.input_function_args.is_empty());
+    assert!(input_data.event_storage.is_empty());
+    // N.B We might drop bracketed paste data here but that's unlikely since we didn't ask for
+    // it yet.
+    flogf!(
+        reader,
+        "Returning %u pending input events",
+        input_data.queue.len()
+    );
+
+    TerminalInitResult {
+        input_queue,
+        background_color,
+    }
+}
+
+/// Read responses to the queries sent by [`terminal_init`] until the terminal answers the final
+/// Primary Device Attribute query. Return false if it never did.
+fn read_initial_query_responses(
+    input_queue: &mut InputEventQueue,
+    background_color: &mut Option<xterm_color::Color>,
+) -> bool {
     while !check_exit_loop_maybe_warning(None) {
         use CharEvent::{Command, Implicit, Key, Readline};
         use ImplicitEvent::{CheckExit, Eof};
@@ -291,14 +351,16 @@ pub fn terminal_init(vars: &dyn Environment, inputfd: RawFd) -> TerminalInitResu
             Implicit(Eof) => signal_safe_reader_set_exit_signal(libc::SIGHUP),
             Implicit(CheckExit) => {}
             CharEvent::QueryResult(Response(QueryResponse::PrimaryDeviceAttribute)) => {
-                break;
+                return true;
             }
             CharEvent::QueryResult(Response(QueryResponse::BackgroundColor(bg))) => {
                 if background_color.is_none() {
-                    background_color = Some(bg);
+                    *background_color = Some(bg);
                 }
             }
-            CharEvent::QueryResult(Response(QueryResponse::CursorPosition(_))) => (),
+            CharEvent::QueryResult(Response(
+                QueryResponse::CursorPosition(_) | QueryResponse::Clipboard(_),
+            )) => (),
             CharEvent::QueryResult(Timeout) => {
                 let program = get_program_name();
                 flog!(
@@ -319,31 +381,41 @@ pub fn terminal_init(vars: &dyn Environment, inputfd: RawFd) -> TerminalInitResu
                     .get_input_data_mut()
                     .blocking_query_timeout
                     .replace(Duration::from_millis(30));
-                break;
+                return false;
             }
-            CharEvent::QueryResult(Interrupted) => break,
+            CharEvent::QueryResult(Interrupted) => return false,
             Key(_) | Readline(_) | Command(_) | Implicit(_) => panic!(),
         }
     }
+    false
+}
 
-    stop_query(input_queue.blocking_query_mut());
-
-    let input_data = input_queue.get_input_data();
-    // We blocked execution of code and mappings so input function args must be empty.
-    assert!(input_data.input_function_args.is_empty());
-    assert!(input_data.event_storage.is_empty());
-    // N.B We might drop bracketed paste data here but that's unlikely since we didn't ask for
-    // it yet.
-    flogf!(
-        reader,
-        "Returning %u pending input events",
-        input_data.queue.len()
-    );
-
-    TerminalInitResult {
-        input_queue,
-        background_color,
+/// Probe the features we don't need before the first prompt, unless an earlier session in the
+/// same terminal already did.
+fn probe_capabilities(
+    vars: &dyn Environment,
+    input_queue: &mut InputEventQueue,
+    background_color: &mut Option<xterm_color::Color>,
+) {
+    let Some(cache_key) = CacheKey::new(vars) else {
+        return;
+    };
+    if load_probed_capabilities(&cache_key) {
+        return;
     }
+    {
+        let mut out = BufferedOutputter::new(Outputter::stdoutput());
+        out.write_command(QueryDecrqm(SYNCHRONIZED_OUTPUT_MODE));
+        query_capabilities_via_dcs(&mut out, vars, &TRUECOLOR_TERMINFO_CODES);
+        out.write_command(QueryPrimaryDeviceAttribute);
+    }
+    input_queue
+        .blocking_query_mut()
+        .replace(TerminalQuery::Initial);
+    if read_initial_query_responses(input_queue, background_color) {
+        save_probed_capabilities(&cache_key);
+    }
+    stop_query(input_queue.blocking_query_mut());
 }
 
 /// The stack of current interactive reading contexts.
@@ -390,6 +462,8 @@ pub fn reader_push<'a>(
         } = terminal_init(parser.vars(), inputfd);
         let input_data = input_queue.get_input_data_mut();
         handle_emoji_width(parser.vars());
+        // The terminal may have told us that it supports 24-bit color.
+        update_fish_color_support(parser.vars());
 
         // Provide value for `status current-command`
         parser.libdata_mut().status_vars.command = L!("fish").to_owned();
@@ -489,6 +563,9 @@ pub struct ReaderConfig {
 
     /// The fd for stdin, default to actual stdin.
     pub inputfd: RawFd,
+
+    /// If set, stop reading once this point in time is reached.
+    pub deadline: Option<Instant>,
 }
 
 /// Snapshotted state from the reader.
@@ -643,6 +720,7 @@ struct LayoutData {
     left_prompt_buff: WString,
     mode_prompt_buff: WString,
     right_prompt_buff: WString,
+    status_line_buff: Option<WString>,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -662,7 +740,7 @@ enum TransientEdit {
 /// ReaderData does not contain a Parser - by itself it cannot execute fish script.
 pub struct ReaderData {
     /// Configuration for the reader.
-    conf: ReaderConfig,
+    pub(super) conf: ReaderConfig,
     /// String containing the whole current commandline.
     command_line: EditableLine,
     /// Whether the most recent modification to the command line was done by either history search
@@ -707,6 +785,14 @@ pub struct ReaderData {
     history_search: ReaderHistorySearch,
     /// In-pager history search.
     history_pager: Option<Range<usize>>,
+    /// Whether the history pager is the full-screen history finder.
+    history_finder: bool,
+    /// The history searched by the history pager, if not the history, like the project's history.
+    history_pager_source: Option<Arc<History>>,
+    /// The project history the command being run was added to.
+    pending_project_history: Option<Arc<History>>,
+    /// Whether the pager shows the branches of the undo tree.
+    undo_tree_pager: bool,
 
     /// The cursor selection mode.
     cursor_selection_mode: CursorSelectionMode,
@@ -719,6 +805,9 @@ pub struct ReaderData {
     mode_prompt_buff: WString,
     /// The output of the last evaluation of the right prompt command.
     right_prompt_buff: WString,
+    /// The output of the last evaluation of `fish_status_line`, or None if this reader does not
+    /// show the status line.
+    status_line_buff: Option<WString>,
 
     /// When navigating the pager, we modify the command line.
     /// This is the saved command line before modification.
@@ -730,6 +819,12 @@ pub struct ReaderData {
     /// If this is true, exit reader even if there are running jobs. This happens if we press e.g.
     /// ^D twice.
     did_warn_for_bg_jobs: bool,
+    /// The desktop notification about the last command, to send if the terminal reports that it
+    /// is not focused.
+    pending_notification: Option<WString>,
+    /// The placeholders of the snippet inserted by `commandline --insert-snippet`, until the
+    /// cursor has visited them.
+    snippet: Option<SnippetState>,
     /// The current contents of the top item in the kill ring.
     kill_item: WString,
 
@@ -742,6 +837,9 @@ pub struct ReaderData {
     last_jump_direction: JumpDirection,
     last_jump_precision: JumpPrecision,
 
+    /// Where the most recent mouse event happened, for move-to-mouse.
+    mouse_position: Option<ViewportPosition>,
+
     /// The text of the most recent asynchronous highlight and autosuggestion requests.
     /// If these differs from the text of the command line, then we must kick off a new request.
     in_flight_highlight_request: WString,
@@ -749,6 +847,11 @@ pub struct ReaderData {
 
     rls: Option<ReadlineLoopState>,
 
+    /// The last output of the prompt functions in `$fish_async_prompt`, by name.
+    async_prompt_cache: HashMap<WString, CachedPrompt>,
+    /// Counts the requests to render the async prompts, so results of older ones are ignored.
+    async_prompt_request: u64,
+
     /// Support for I/O threads associated with this reader state, including debouncers.
     pub(super) debouncers: Debouncers,
 }
@@ -789,9 +892,17 @@ impl<'a> Reader<'a> {
         if let Some(r) = self.debouncers.highlight.take_result() {
             self.highlight_completed(r);
         }
+        if self.debouncers.validity_cache.take_finished_late() {
+            // Highlighting did not wait for some checks, so highlight again with their results.
+            self.in_flight_highlight_request.clear();
+            self.super_highlight_me_plenty();
+        }
         if let Some(cb) = self.debouncers.history_pager.take_result() {
             cb(self);
         }
+        if let Some(cb) = self.debouncers.async_prompt.take_result() {
+            cb(self);
+        }
     }
 }
 
@@ -863,6 +974,7 @@ fn read_i(parser: &mut Parser) {
 
         // Got a command. Disable tty protocols while we execute it.
         tty.disable_tty_protocols();
+        reader.pending_notification = None;
         reader.clear(EditableLineTag::Commandline);
         reader.update_buff_pos(EditableLineTag::Commandline, None);
         BufferedOutputter::new(Outputter::stdoutput()).write_command(Osc133CommandStart(&command));
@@ -871,7 +983,9 @@ fn read_i(parser: &mut Parser) {
             L!("fish_preexec").to_owned(),
             vec![command.clone()],
         );
-        let eval_res = reader_run_command(reader.parser, &command);
+        take_reaped_peak_rss();
+        let (eval_res, duration) = reader_run_command(reader.parser, &command);
+        let peak_rss = take_reaped_peak_rss();
         signal_clear_cancel();
         if !eval_res.no_status {
             STATUS_COUNT.fetch_add(1, Ordering::Relaxed);
@@ -881,12 +995,33 @@ fn read_i(parser: &mut Parser) {
         reader.data.exit_loop_requested |= reader.parser.libdata().exit_current_script;
         reader.parser.libdata_mut().exit_current_script = false;
 
-        BufferedOutputter::new(Outputter::stdoutput()).write_command(Osc133CommandFinished {
-            exit_status: reader.parser.last_status(),
-        });
+        let exit_status = reader.parser.last_status();
+        BufferedOutputter::new(Outputter::stdoutput())
+            .write_command(Osc133CommandFinished { exit_status });
+        reader.history.record_pending_result(exit_status, duration);
+        let project_history = reader.pending_project_history.take();
+        if let Some(project) = &project_history {
+            project.record_pending_result(exit_status, duration);
+        }
+        let report = CommandReport::new(
+            reader.parser.vars(),
+            &command,
+            duration,
+            exit_status,
+            peak_rss,
+        );
         event::fire_generic(reader.parser, L!("fish_postexec").to_owned(), vec![command]);
+        if let Some(report) = report {
+            report.run(reader.parser);
+            if check_bool_var(reader.parser.vars(), NOTIFICATION_VAR, false) {
+                reader.pending_notification = Some(report.notification());
+            }
+        }
         // Allow any pending history items to be returned in the history array.
         reader.history.resolve_pending();
+        if let Some(project) = project_history {
+            project.resolve_pending();
+        }
 
         // Make cursor visible. Every even vaguely used terminal agrees on this sequence.
         reader.screen.write_command(DecsetShowCursor);
@@ -1047,6 +1182,7 @@ pub fn reader_init(will_restore_foreground_pgroup: bool) {
 }
 
 pub fn reader_deinit(restore_foreground_pgroup: bool) {
+    screen_remove_status_line();
     restore_term_mode();
     deactivate_tty_protocols();
     if restore_foreground_pgroup {
@@ -1195,6 +1331,18 @@ pub fn reader_jump(direction: JumpDirection, precision: JumpPrecision, target: c
     data.jump_and_remember_last_jump(direction, precision, elt, target, false)
 }
 
+/// Insert a snippet at the cursor. Returns false if there is no command line to insert it into.
+pub fn reader_insert_snippet(parser: &Parser, snippet: &Snippet) -> bool {
+    if parser.scope().readonly_commandline {
+        return false;
+    }
+    let Some(data) = current_data() else {
+        return false;
+    };
+    data.insert_snippet(snippet);
+    true
+}
+
 pub fn reader_showing_suggestion(parser: &mut Parser) -> bool {
     if !is_interactive_session() {
         return false;
@@ -1310,12 +1458,21 @@ const LEFT_PROMPT_FUNCTION_NAME: &wstr = L!("fish_prompt");
 /// The name of the function that prints the fish right prompt (RPROMPT).
 const RIGHT_PROMPT_FUNCTION_NAME: &wstr = L!("fish_right_prompt");
 
+/// The name of the function which replaces the left prompt of commands once they are run.
+const TRANSIENT_PROMPT_FUNCTION_NAME: &wstr = L!("fish_transient_prompt");
+
+/// The name of the function which replaces the right prompt of commands once they are run.
+const TRANSIENT_RIGHT_PROMPT_FUNCTION_NAME: &wstr = L!("fish_transient_right_prompt");
+
 /// The name of the function to use in place of the left prompt if we're in the debugger context.
 const DEBUG_PROMPT_FUNCTION_NAME: &wstr = L!("fish_breakpoint_prompt");
 
 /// The name of the function for getting the input mode indicator.
 const MODE_PROMPT_FUNCTION_NAME: &wstr = L!("fish_mode_prompt");
 
+/// The name of the function which prints the status line at the bottom of the terminal.
+const STATUS_LINE_FUNCTION_NAME: &wstr = L!("fish_status_line");
+
 /// The default title for the reader. This is used by reader_readline.
 const DEFAULT_TITLE: &wstr = L!("echo (status current-command) \" \" $PWD");
 
@@ -1407,24 +1564,34 @@ impl ReaderData {
             history,
             history_search: Default::default(),
             history_pager: None,
+            history_finder: false,
+            history_pager_source: None,
+            pending_project_history: None,
+            undo_tree_pager: false,
             cursor_selection_mode: CursorSelectionMode::Exclusive,
             cursor_end_mode: CursorEndMode::Exclusive,
             selection: Default::default(),
             left_prompt_buff: Default::default(),
             mode_prompt_buff: Default::default(),
             right_prompt_buff: Default::default(),
+            status_line_buff: Default::default(),
             cycle_command_line: Default::default(),
             cycle_cursor_pos: Default::default(),
             exit_loop_requested: Default::default(),
             did_warn_for_bg_jobs: Default::default(),
+            pending_notification: Default::default(),
+            snippet: None,
             kill_item: Default::default(),
             force_exec_prompt_and_repaint: Default::default(),
             last_jump_target: Default::default(),
             last_jump_direction: JumpDirection::Forward,
             last_jump_precision: JumpPrecision::To,
+            mouse_position: None,
             in_flight_highlight_request: Default::default(),
             in_flight_autosuggest_request: Default::default(),
             rls: None,
+            async_prompt_cache: HashMap::new(),
+            async_prompt_request: 0,
             debouncers: Debouncers::new(),
         }))
     }
@@ -1633,13 +1800,11 @@ impl ReaderData {
         true
     }
 
-    pub fn mouse_left_click(&mut self, click_position: ViewportPosition) {
-        flog!(
-            reader,
-            "Received left mouse click at",
-            format!("{:?}", click_position),
-        );
-        match self.screen.offset_in_cmdline_given_cursor(click_position) {
+    fn move_to_mouse(&mut self) {
+        let Some(position) = self.mouse_position else {
+            return;
+        };
+        match self.screen.offset_in_cmdline_given_cursor(position) {
             CharOffset::Cmd(new_pos) | CharOffset::Pointer(new_pos) => {
                 let (elt, _el) = self.active_edit_line();
                 self.update_buff_pos(elt, Some(new_pos));
@@ -1791,6 +1956,10 @@ impl<'a> Reader<'a> {
                 self.right_prompt_buff != last.right_prompt_buff,
                 "right_prompt",
             )
+            || check(
+                self.status_line_buff != last.status_line_buff,
+                "status_line",
+            )
             || check(
                 self.pager
                     .rendering_needs_update(&self.current_page_rendering),
@@ -1814,6 +1983,7 @@ impl<'a> Reader<'a> {
         result.left_prompt_buff = self.left_prompt_buff.clone();
         result.mode_prompt_buff = self.mode_prompt_buff.clone();
         result.right_prompt_buff = self.right_prompt_buff.clone();
+        result.status_line_buff = self.status_line_buff.clone();
         result
     }
 
@@ -1919,11 +2089,17 @@ impl<'a> Reader<'a> {
         let pager = &mut self.data.pager;
         let current_page_rendering = &mut self.data.current_page_rendering;
         let curr_termsize = termsize_last();
+        screen.right_prompt_on_every_line = check_bool_var(
+            self.parser.vars(),
+            L!("fish_right_prompt_on_every_line"),
+            false,
+        );
         screen.write(
             curr_termsize,
             // Prepend the mode prompt to the left prompt.
             &(self.data.mode_prompt_buff.clone() + &self.data.left_prompt_buff[..]),
             &self.data.right_prompt_buff,
+            self.data.status_line_buff.as_deref(),
             &full_line,
             autosuggested_range,
             colors,
@@ -1954,8 +2130,10 @@ impl ReaderData {
             EditableLineTag::SearchField => &self.pager.search_field_line,
         }
         .text();
+        let register = kill_take_register();
         let kill_item = &mut self.kill_item;
-        if newv {
+        // A kill into a register always starts a new killring entry.
+        if newv || register.is_some() {
             *kill_item = text[range.clone()].to_owned();
             kill_add(kill_item.clone());
         } else {
@@ -1970,6 +2148,16 @@ impl ReaderData {
 
             kill_replace(&old, kill_item.clone());
         }
+        if let Some(register) = register {
+            register_set(register, &text[range.clone()]);
+        }
+        if killring_clipboard() && get_clipboard_capability() == Some(true) {
+            let bytes = wcs2bytes(&*kill_item);
+            if bytes.len() <= MAX_COPY_LEN {
+                BufferedOutputter::new(Outputter::stdoutput())
+                    .write_command(Osc52CopyToClipboard(&bytes));
+            }
+        }
         self.erase_substring(elt, range);
     }
 
@@ -1979,9 +2167,20 @@ impl ReaderData {
     fn insert_string(&mut self, elt: EditableLineTag, s: &wstr) {
         let history_search_active = self.history_search.active();
         let el = self.edit_line(elt);
+        let mut range = el.position()..el.position();
+        // Typing at the start of a snippet placeholder replaces its default text.
+        if elt == EditableLineTag::Commandline {
+            if let Some(placeholder) = self
+                .snippet
+                .as_mut()
+                .and_then(|snippet| snippet.take_pristine_range(range.start))
+            {
+                range = placeholder;
+            }
+        }
         self.push_edit_internal(
             elt,
-            Edit::new(el.position()..el.position(), s.to_owned()),
+            Edit::new(range, s.to_owned()),
             /*allow_coalesce=*/ !history_search_active,
         );
         if elt == EditableLineTag::Commandline {
@@ -2133,6 +2332,13 @@ impl ReaderData {
             {
                 autosuggestion_update = AutosuggestionUpdate::Restore;
             }
+            if self
+                .snippet
+                .as_mut()
+                .is_some_and(|snippet| !snippet.apply_edit(&edit.range, edit.replacement.len()))
+            {
+                self.snippet = None;
+            }
         }
         self.edit_line_mut(elt).push_edit(edit, allow_coalesce);
         self.command_line_changed(elt, autosuggestion_update);
@@ -2141,6 +2347,9 @@ impl ReaderData {
     fn undo(&mut self, elt: EditableLineTag) -> bool {
         let ok = self.edit_line_mut(elt).undo();
         if ok {
+            if elt == EditableLineTag::Commandline {
+                self.snippet = None;
+            }
             self.command_line_changed(elt, AutosuggestionUpdate::Remove);
         }
         ok
@@ -2148,6 +2357,9 @@ impl ReaderData {
     fn redo(&mut self, elt: EditableLineTag) -> bool {
         let ok = self.edit_line_mut(elt).redo();
         if ok {
+            if elt == EditableLineTag::Commandline {
+                self.snippet = None;
+            }
             self.command_line_changed(elt, AutosuggestionUpdate::Remove);
         }
         ok
@@ -2589,6 +2801,7 @@ impl<'a> Reader<'a> {
         self.cycle_cursor_pos = 0;
 
         self.history_search.reset();
+        self.snippet = None;
 
         // HACK: Use a simple \r for the first prompt, because if we're started with the terminal
         // it might not have settled, so the width is quite likely to be in flight.
@@ -2607,6 +2820,9 @@ impl<'a> Reader<'a> {
         }
         self.exec_prompt(true, false);
 
+        // Commands may have changed what is valid, so check again before trusting old results.
+        self.debouncers.validity_cache.invalidate();
+
         // Start out as initially dirty.
         self.force_exec_prompt_and_repaint = true;
 
@@ -2628,7 +2844,7 @@ impl<'a> Reader<'a> {
         // Disable tty protocols now that we're going to execute a command.
         tty.disable_tty_protocols();
 
-        if self.conf.transient_prompt {
+        if self.conf.transient_prompt || self.has_transient_prompt_function() {
             self.exec_prompt(true, true);
         }
 
@@ -2697,6 +2913,67 @@ impl<'a> Reader<'a> {
         self.parser.set_last_statuses(last_statuses);
     }
 
+    /// Edit the command line in an external editor, keeping the cursor and selection in place.
+    /// Returns false if the command line was left alone.
+    fn edit_command_buffer(&mut self) -> bool {
+        let Some(editor) = external_editor::editor_command(self.parser) else {
+            return false;
+        };
+        let text = self.command_line.text().to_owned();
+        let cursor = self.command_line.position();
+        let private_mode = in_private_mode(self.parser.vars());
+        let edit = match ExternalEdit::new(&text, cursor, private_mode) {
+            Ok(edit) => edit,
+            Err(err) => {
+                perror_io("edit-command-buffer", &err);
+                return false;
+            }
+        };
+        let status = {
+            // Like commands run from bindings, the editor takes over the terminal.
+            let mut scoped_tty = TtyHandoff::new(reader_save_screen_state);
+            scoped_tty.disable_tty_protocols();
+            let last_statuses = self.parser.vars().last_statuses();
+            self.parser.eval(&edit.command(&editor), &IoChain::new());
+            let status = self.parser.last_status();
+            self.parser.set_last_statuses(last_statuses);
+            status
+        };
+        set_shell_modes(STDIN_FILENO, "external editor");
+        signal_safe_termsize_invalidate_tty();
+        // The editor has probably drawn over the screen.
+        self.schedule_prompt_repaint();
+
+        let result = if status == 0 { edit.finish() } else { None };
+        let Some(result) = result else {
+            eprintf!(
+                "\n%s\n%s\n",
+                wgettext!("Ignoring the output of your editor since its exit status was non-zero"),
+                wgettext!("or the file was empty")
+            );
+            self.screen
+                .reset_abandoning_line(Some(termsize_last().width()));
+            return false;
+        };
+        // Without a position from the editor, the cursor stays at the same line and column, and so
+        // does the start of the selection.
+        let (line, column) = line_and_column(&text, cursor);
+        let new_cursor = result
+            .cursor
+            .unwrap_or_else(|| offset_at(&result.text, line, column));
+        let selection_begin = self.selection.as_ref().map(|selection| {
+            let (line, column) = line_and_column(&text, selection.begin);
+            offset_at(&result.text, line, column)
+        });
+        self.clear_pager();
+        self.set_buffer_maintaining_pager(&result.text, new_cursor);
+        if let (Some(selection), Some(begin)) = (self.selection.as_mut(), selection_begin) {
+            selection.begin = begin;
+            self.update_buff_pos(EditableLineTag::Commandline, Some(new_cursor));
+        }
+        true
+    }
+
     /// Run a sequence of commands from an input binding.
     fn run_input_command_scripts(&mut self, cmd: &wstr) {
         self.eval_bind_cmd(cmd);
@@ -2769,6 +3046,7 @@ impl<'a> Reader<'a> {
         if self.conf.inputfd == STDIN_FILENO {
             self.update_autosuggestion();
             self.super_highlight_me_plenty();
+            self.update_pager_preview();
         }
         if self.is_repaint_needed(None) {
             self.layout_and_repaint(L!("toplevel"));
@@ -2847,6 +3125,16 @@ impl<'a> Reader<'a> {
                 {
                     self.clear_pager();
                 }
+                // The undo tree is only shown until the next command.
+                if self.undo_tree_pager
+                    && !matches!(
+                        readline_cmd,
+                        ReadlineCmd::UndoTreeNextBranch | ReadlineCmd::UndoTreePrevBranch
+                    )
+                    && !command_only_affects_rendering(readline_cmd)
+                {
+                    self.clear_pager();
+                }
 
                 self.handle_readline_command(readline_cmd);
 
@@ -2895,16 +3183,45 @@ impl<'a> Reader<'a> {
                     Eof => signal_safe_reader_set_exit_signal(libc::SIGHUP),
                     CheckExit => (),
                     FocusIn => {
+                        self.pending_notification = None;
                         event::fire_generic(self.parser, L!("fish_focus_in").to_owned(), vec![]);
                         self.save_screen_state();
+                        self.event_binding_execute(Key::from_raw(key::FOCUS_IN), &[]);
                     }
                     FocusOut => {
+                        if let Some(body) = self.pending_notification.take() {
+                            BufferedOutputter::new(Outputter::stdoutput()).write_command(
+                                DesktopNotification {
+                                    title: L!("fish"),
+                                    body: &body,
+                                },
+                            );
+                        }
                         event::fire_generic(self.parser, L!("fish_focus_out").to_owned(), vec![]);
                         self.save_screen_state();
+                        self.event_binding_execute(Key::from_raw(key::FOCUS_OUT), &[]);
+                    }
+                    PasteStart => {
+                        self.event_binding_execute(Key::from_raw(key::PASTE_START), &[]);
                     }
-                    MouseLeft(position) => {
-                        flog!(reader, "Mouse left click", position);
-                        self.mouse_left_click(position);
+                    Paste(text) => {
+                        // Without a binding, insert the text as-is.
+                        let paste = Key::from_raw(key::PASTE);
+                        if !self.event_binding_execute(paste, std::slice::from_ref(&text)) {
+                            self.push_front(CharEvent::Command(sprintf!(
+                                "__fish_paste %s",
+                                escape(&text)
+                            )));
+                        }
+                    }
+                    PasteEnd => {
+                        self.event_binding_execute(Key::from_raw(key::PASTE_END), &[]);
+                    }
+                    Mouse(key, position) => {
+                        flog!(reader, "Mouse event", key, position);
+                        self.mouse_position = Some(position);
+                        let args = [position.x.to_wstring(), position.y.to_wstring()];
+                        self.event_binding_execute(key, &args);
                     }
                     NewColorTheme => {
                         self.query(RecurrentQuery {
@@ -2921,6 +3238,10 @@ impl<'a> Reader<'a> {
                             ..Default::default()
                         });
                     }
+                    DeadlineElapsed => {
+                        flog!(reader, "Deadline for reading input elapsed");
+                        self.exit_loop_requested = true;
+                    }
                 }
             }
             CharEvent::QueryResult(query_result) => {
@@ -2928,7 +3249,7 @@ impl<'a> Reader<'a> {
                 use QueryResponse::*;
                 use QueryResultEvent::*;
                 let query = match (query, query_result) {
-                    (Some(TerminalQuery::Initial), _) => panic!(),
+                    (Some(TerminalQuery::Initial | TerminalQuery::Clipboard), _) => panic!(),
                     (
                         Some(TerminalQuery::Recurrent(RecurrentQuery {
                             background_color: Some(color_query),
@@ -2990,7 +3311,7 @@ impl<'a> Reader<'a> {
     }
 }
 
-fn query_capabilities_via_dcs(out: &mut Outputter, vars: &dyn Environment) {
+fn query_capabilities_via_dcs(out: &mut Outputter, vars: &dyn Environment, codes: &[&'static str]) {
     // TODO(term-workaround)
     if vars.get_unless_empty(L!("STY")).is_some()
         || vars.get_unless_empty(L!("TERM")).is_some_and(|term| {
@@ -3001,8 +3322,9 @@ fn query_capabilities_via_dcs(out: &mut Outputter, vars: &dyn Environment) {
         return;
     }
     out.write_command(DecsetAlternateScreenBuffer); // enable alternative screen buffer
-    out.write_command(QueryXtgettcap(SCROLL_CONTENT_UP_TERMINFO_CODE));
-    out.write_command(QueryXtgettcap(XTGETTCAP_QUERY_OS_NAME));
+    for &code in codes {
+        out.write_command(QueryXtgettcap(code));
+    }
     out.write_command(DecrstAlternateScreenBuffer); // disable alternative screen buffer
 }
 
@@ -3212,6 +3534,33 @@ impl<'a> Reader<'a> {
                     self.compute_and_apply_completions(c);
                 }
             }
+            rl::PagerPageUp | rl::PagerPageDown => {
+                if !self.pager.is_empty() {
+                    self.select_completion_in_direction(
+                        if c == rl::PagerPageUp {
+                            SelectionMotion::PageNorth
+                        } else {
+                            SelectionMotion::PageSouth
+                        },
+                        false,
+                    );
+                }
+            }
+            rl::PagerToggleMark => {
+                let is_completion_pager = self.history_pager.is_none() && !self.undo_tree_pager;
+                let success = is_completion_pager
+                    && self
+                        .data
+                        .pager
+                        .toggle_mark_selected_completion(&self.data.current_page_rendering);
+                if success {
+                    self.select_completion_in_direction(SelectionMotion::Next, false);
+                }
+                self.input_data.function_set_status(success);
+            }
+            rl::MoveToMouse => {
+                self.move_to_mouse();
+            }
             rl::PagerToggleSearch => {
                 if let Some(history_pager) = &self.history_pager {
                     if history_pager.start == 0 {
@@ -3345,7 +3694,10 @@ impl<'a> Reader<'a> {
                 }
             }
             rl::Yank => {
-                let yank_str = kill_yank();
+                let yank_str = match kill_take_register() {
+                    Some(register) => register_get(register),
+                    None => kill_yank(),
+                };
                 self.data
                     .insert_string(self.active_edit_line_tag(), &yank_str);
                 self.rls_mut().yank_len = yank_str.len();
@@ -3409,6 +3761,10 @@ impl<'a> Reader<'a> {
             | rl::HistoryPrefixSearchForward
             | rl::HistorySearchBackward
             | rl::HistorySearchForward
+            | rl::HistoryDirectorySearchBackward
+            | rl::HistoryDirectorySearchForward
+            | rl::HistoryProjectSearchBackward
+            | rl::HistoryProjectSearchForward
             | rl::HistoryTokenSearchBackward
             | rl::HistoryTokenSearchForward
             | rl::HistoryLastTokenSearchBackward
@@ -3424,36 +3780,62 @@ impl<'a> Reader<'a> {
                         SearchMode::Prefix
                     }
                     rl::HistorySearchBackward | rl::HistorySearchForward => SearchMode::Line,
+                    rl::HistoryDirectorySearchBackward | rl::HistoryDirectorySearchForward => {
+                        SearchMode::Directory
+                    }
+                    rl::HistoryProjectSearchBackward | rl::HistoryProjectSearchForward => {
+                        SearchMode::Project
+                    }
                     _ => unreachable!(),
                 };
 
                 let was_active_before = self.history_search.active();
 
                 if self.history_search.is_at_present() && mode != self.history_search.mode() {
+                    let filter = HistoryFilter {
+                        cwd: (mode == SearchMode::Directory)
+                            .then(|| self.parser.vars().get(L!("PWD")))
+                            .flatten()
+                            .map(|pwd| pwd.as_string()),
+                        ..Default::default()
+                    };
+                    // A project search goes through the project's history first.
+                    let (hist, fallback_hist) = match project_history(self.parser.vars()) {
+                        Some(project) if mode == SearchMode::Project => {
+                            (project, Some(self.history.clone()))
+                        }
+                        _ => (self.history.clone(), None),
+                    };
                     let el = &self.data.command_line;
                     if matches!(mode, SearchMode::Token | SearchMode::LastToken) {
                         // Searching by token.
                         let (token_range, _) = get_token_extent(el.text(), el.position());
                         self.data.history_search.reset_to_mode(
                             el.text()[token_range.clone()].to_owned(),
-                            self.history.clone(),
+                            hist,
+                            fallback_hist,
                             mode,
                             token_range.start,
+                            filter,
                         );
                     } else {
                         // Searching by line.
                         self.data.history_search.reset_to_mode(
                             el.text().to_owned(),
-                            self.history.clone(),
+                            hist,
+                            fallback_hist,
                             mode,
                             0,
+                            filter,
                         );
 
-                        // Skip the autosuggestion in the history unless it was truncated.
+                        // Skip the autosuggestion in the history unless it was truncated. It may
+                        // come from another directory or project, so a directory or project search
+                        // does not skip it.
                         let suggest = &self.data.autosuggestion.text;
                         if !suggest.is_empty()
                             && !self.data.screen.autosuggestion_is_truncated
-                            && mode != SearchMode::Prefix
+                            && mode == SearchMode::Line
                         {
                             self.data.history_search.add_skip(suggest.clone());
                         }
@@ -3462,10 +3844,14 @@ impl<'a> Reader<'a> {
                 assert!(self.history_search.active());
                 let dir = match c {
                     rl::HistorySearchBackward
+                    | rl::HistoryDirectorySearchBackward
+                    | rl::HistoryProjectSearchBackward
                     | rl::HistoryTokenSearchBackward
                     | rl::HistoryLastTokenSearchBackward
                     | rl::HistoryPrefixSearchBackward => SearchDirection::Backward,
                     rl::HistorySearchForward
+                    | rl::HistoryDirectorySearchForward
+                    | rl::HistoryProjectSearchForward
                     | rl::HistoryTokenSearchForward
                     | rl::HistoryLastTokenSearchForward
                     | rl::HistoryPrefixSearchForward => SearchDirection::Forward,
@@ -3489,9 +3875,9 @@ impl<'a> Reader<'a> {
                     self.history_search.reset();
                 }
             }
-            rl::HistoryPager => {
+            rl::HistoryPager | rl::HistoryProjectPager => {
                 if let Some(history_pager) = &self.history_pager {
-                    if history_pager.end > self.history.size() {
+                    if history_pager.end > self.history_pager_history().size() {
                         self.flash(0..self.command_line.len());
                         return;
                     }
@@ -3503,33 +3889,29 @@ impl<'a> Reader<'a> {
                     return;
                 }
 
-                // Record our cycle_command_line.
-                self.cycle_command_line = self.command_line.text().to_owned();
-                self.cycle_cursor_pos = self.command_line.position();
-
-                self.history_pager = Some(0..1);
-                // Update the pager data.
-                self.pager.set_search_field_shown(true);
-                self.pager.set_prefix(Cow::Borrowed(L!("► ")), false);
-                // Update the search field, which triggers the actual history search.
-                let search_string = if !self.history_search.active()
-                    || self.history_search.search_string().is_empty()
-                {
-                    let cmdsub =
-                        get_cmdsubst_extent(self.command_line.text(), self.command_line.position());
-                    let cmdsub = &self.command_line.text()[cmdsub];
-                    let needle = if !cmdsub.contains('\n') {
-                        cmdsub
-                    } else {
-                        line_at_cursor(self.command_line.text(), self.command_line.position())
-                    };
-                    escape_wildcards(needle)
-                } else {
-                    // If we have an actual history search already going, reuse that term
-                    // - this is if the user looks around a bit and decides to switch to the pager.
-                    self.history_search.search_string().to_owned()
-                };
-                self.insert_string(EditableLineTag::SearchField, &search_string);
+                if c == rl::HistoryProjectPager {
+                    self.history_pager_source = project_history(self.parser.vars());
+                }
+                self.open_history_pager(false);
+            }
+            rl::HistoryFinder => {
+                if self.history_pager.is_some() {
+                    if self.history_finder {
+                        // All matches are shown already.
+                        self.flash(0..self.command_line.len());
+                        return;
+                    }
+                    // Switch from the history pager to the finder, keeping the search string.
+                    self.history_finder = true;
+                    self.pager.set_fully_disclosed();
+                    self.fill_history_pager(
+                        HistoryPagerInvocation::Anew,
+                        Some(SelectionMotion::Next),
+                        SearchDirection::Backward,
+                    );
+                    return;
+                }
+                self.open_history_pager(true);
             }
             #[allow(deprecated)]
             rl::HistoryDelete | rl::HistoryPagerDelete => {
@@ -3549,6 +3931,12 @@ impl<'a> Reader<'a> {
                         &self.autosuggestion.text
                     });
                     self.history.save();
+                    if is_history_search && self.history_search.mode() == SearchMode::Project {
+                        if let Some(project) = project_history(self.parser.vars()) {
+                            project.remove(self.history_search.current_result());
+                            project.save();
+                        }
+                    }
                     if is_history_search {
                         self.history_search.handle_deletion();
                         self.update_command_line_from_history_search();
@@ -3567,6 +3955,10 @@ impl<'a> Reader<'a> {
                 {
                     self.history.remove(&completion.completion);
                     self.history.save();
+                    if let Some(source) = &self.history_pager_source {
+                        source.remove(&completion.completion);
+                        source.save();
+                    }
                     self.fill_history_pager(
                         HistoryPagerInvocation::Refresh,
                         None,
@@ -4292,6 +4684,14 @@ impl<'a> Reader<'a> {
                 };
                 self.input_data.function_set_status(success);
             }
+            rl::EditCommandBuffer => {
+                let success = self.edit_command_buffer();
+                self.input_data.function_set_status(success);
+            }
+            rl::ForwardPlaceholder | rl::BackwardPlaceholder => {
+                let success = self.move_to_placeholder(c == rl::ForwardPlaceholder);
+                self.input_data.function_set_status(success);
+            }
             rl::RepeatJump => {
                 let (elt, _el) = self.active_edit_line();
                 let mut success = false;
@@ -4357,6 +4757,20 @@ impl<'a> Reader<'a> {
                 }
                 self.update_buff_pos(elt, None);
             }
+            rl::UndoTreeNextBranch | rl::UndoTreePrevBranch => {
+                let (elt, _el) = self.active_edit_line();
+                let forward = c == rl::UndoTreeNextBranch;
+                if !self.edit_line_mut(elt).switch_undo_branch(forward) {
+                    self.flash(0..self.command_line.len());
+                    return;
+                }
+                self.command_line_changed(elt, AutosuggestionUpdate::Remove);
+                self.suppress_autosuggestion = false;
+                if elt == EditableLineTag::Commandline {
+                    self.show_undo_tree();
+                }
+                self.update_buff_pos(elt, None);
+            }
             rl::BeginUndoGroup => {
                 let (_elt, el) = self.active_edit_line_mut();
                 el.begin_edit_group();
@@ -4386,13 +4800,16 @@ impl<'a> Reader<'a> {
         // and *then* reexecute the prompt and overdraw it.
         // This removes the flicker,
         // while keeping the prompt up-to-date.
-        Outputter::stdoutput()
-            .borrow_mut()
-            .write_command(ClearScreen);
+        // Where supported, the terminal shows the cleared screen only once the old prompt is
+        // drawn again.
+        let outp = Outputter::stdoutput();
+        outp.borrow_mut().begin_synchronized_update();
+        outp.borrow_mut().write_command(ClearScreen);
         self.screen
             .set_position_in_viewport("screen clear", Some(0));
         self.screen.reset_line(/*repaint_prompt=*/ true);
         self.layout_and_repaint(L!("readline"));
+        outp.borrow_mut().end_synchronized_update();
 
         self.exec_prompt(true, false);
         self.screen.reset_line(/*repaint_prompt=*/ true);
@@ -4487,6 +4904,8 @@ impl<'a> Reader<'a> {
                 );
                 self.replace_substring(EditableLineTag::Commandline, range, updated);
                 self.command_line.set_position(cursor - offset_from_end);
+            } else if self.pager.marked_completions().next().is_some() {
+                self.insert_marked_completions();
             } else if self
                 .pager
                 .selected_completion(&self.data.current_page_rendering)
@@ -4593,10 +5012,44 @@ impl ReaderData {
     fn clear_pager(&mut self) {
         self.pager.clear();
         self.history_pager = None;
+        self.history_finder = false;
+        self.history_pager_source = None;
+        self.undo_tree_pager = false;
         self.clear(EditableLineTag::SearchField);
         self.command_line_transient_edit = None;
     }
 
+    /// Show the command lines at the ends of the branches of the undo tree in the pager.
+    fn show_undo_tree(&mut self) {
+        self.clear_pager();
+        let Some((branches, current)) = self.command_line.undo_branches() else {
+            return;
+        };
+        let completions: Vec<Completion> = branches
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let description = if i == current {
+                    wgettext!("current").to_owned()
+                } else {
+                    WString::new()
+                };
+                Completion::new(
+                    text,
+                    description,
+                    StringFuzzyMatch::exact_match(),
+                    CompleteFlags::REPLACES_LINE
+                        | CompleteFlags::DONT_ESCAPE
+                        | CompleteFlags::DONT_SORT,
+                )
+            })
+            .collect();
+        self.pager.extra_progress_text =
+            wgettext_fmt!("Undo branch %u of %u", current + 1, completions.len());
+        self.pager.set_completions(&completions, false);
+        self.undo_tree_pager = true;
+    }
+
     fn get_selection(&self) -> Option<Range<usize>> {
         let selection = self.selection?;
         let start = std::cmp::min(selection.start, self.command_line.len());
@@ -4697,6 +5150,51 @@ impl ReaderData {
         }
     }
 
+    /// Replace the token the completions were for with all completions marked in the pager,
+    /// separated by spaces, instead of the selected one.
+    fn insert_marked_completions(&mut self) {
+        if let Some(transient_edit) = self.command_line_transient_edit.take() {
+            if transient_edit == TransientEdit::Pager {
+                self.undo(EditableLineTag::Commandline);
+            }
+        }
+
+        let (token_range, _) = get_token_extent(&self.cycle_command_line, self.cycle_cursor_pos);
+        let mut ctx = OperationContext::background_interruptible(EnvStack::globals());
+        let mut new_cmd_line: Option<WString> = None;
+        let mut cursor_pos = self.cycle_cursor_pos;
+        for completion in self.pager.marked_completions() {
+            let mut completion_cursor = self.cycle_cursor_pos;
+            let applied = completion_apply_to_command_line(
+                &mut ctx,
+                &completion.completion,
+                completion.flags,
+                &self.cycle_command_line,
+                &mut completion_cursor,
+                false,
+                /*is_unique=*/ false,
+            );
+            let Some(cmd_line) = &mut new_cmd_line else {
+                new_cmd_line = Some(applied);
+                cursor_pos = completion_cursor;
+                continue;
+            };
+            // Completions that replace the whole line change what comes before the token.
+            if applied[..token_range.start] != self.cycle_command_line[..token_range.start] {
+                continue;
+            }
+            let mut inserted = applied[token_range.start..completion_cursor].to_owned();
+            if cursor_pos > 0 && !cmd_line.char_at(cursor_pos - 1).is_whitespace() {
+                inserted.insert(0, ' ');
+            }
+            cmd_line.insert_utfstr(cursor_pos, &inserted);
+            cursor_pos += inserted.len();
+        }
+        if let Some(new_cmd_line) = new_cmd_line {
+            self.set_buffer_maintaining_pager(&new_cmd_line, cursor_pos);
+        }
+    }
+
     /// Sets the command line contents, without clearing the pager.
     fn set_buffer_maintaining_pager(&mut self, new_cmd_line: &wstr, pos: usize) {
         self.replace_substring(
@@ -4713,6 +5211,39 @@ impl ReaderData {
 
         // Clear history search.
         self.history_search.reset();
+        self.snippet = None;
+    }
+
+    /// Insert a snippet at the cursor, and move the cursor to its first placeholder.
+    fn insert_snippet(&mut self, snippet: &Snippet) {
+        self.clear_pager();
+        let offset = self.command_line.position();
+        self.snippet = None;
+        self.push_edit(
+            EditableLineTag::Commandline,
+            Edit::new(offset..offset, snippet.text.clone()),
+        );
+        self.snippet = SnippetState::new(snippet, offset);
+        let pos = offset + snippet.placeholders[0].start;
+        self.update_buff_pos(EditableLineTag::Commandline, Some(pos));
+        self.reset_loop_state = true;
+    }
+
+    /// Move the cursor to the next or previous placeholder of the inserted snippet. Returns false
+    /// if there is none.
+    fn move_to_placeholder(&mut self, forward: bool) -> bool {
+        let Some(snippet) = &mut self.snippet else {
+            return false;
+        };
+        if !snippet.advance(forward) {
+            return false;
+        }
+        let pos = snippet.current().start;
+        if snippet.is_at_end() {
+            self.snippet = None;
+        }
+        self.update_buff_pos(EditableLineTag::Commandline, Some(pos));
+        true
     }
 
     fn select_completion_in_direction(
@@ -5125,11 +5656,22 @@ fn exec_prompt_cmd(parser: &mut Parser, prompt_cmd: &wstr, final_prompt: bool) -
     } else {
         Cow::Borrowed(prompt_cmd)
     };
+    // The prompt is a function call too, but it should not hide the values returned by the
+    // user's last command.
+    let saved_return_value = parser.vars().last_return_value();
     let _ = exec_subshell(&prompt_cmd, parser, Some(&mut output), false);
+    parser.vars().set_last_return_value(saved_return_value);
     output
 }
 
 impl<'a> Reader<'a> {
+    /// Whether this reader shows the regular prompt, and `fish_transient_prompt` is defined to
+    /// replace it once a command is run.
+    fn has_transient_prompt_function(&mut self) -> bool {
+        self.conf.left_prompt_cmd == LEFT_PROMPT_FUNCTION_NAME
+            && function::exists(TRANSIENT_PROMPT_FUNCTION_NAME, self.parser)
+    }
+
     /// Execute prompt commands based on the provided arguments. The output is inserted into prompt_buff.
     fn exec_prompt(&mut self, full_prompt: bool, final_prompt: bool) {
         // Suppress fish_trace while in the prompt.
@@ -5146,8 +5688,11 @@ impl<'a> Reader<'a> {
         // This allows prompts to react to $COLUMNS.
         reader_update_termsize(self.parser);
 
+        // The transient prompt functions replace all prompts of commands which are run.
+        let transient = final_prompt && self.has_transient_prompt_function();
+
         self.mode_prompt_buff.clear();
-        if function::exists(MODE_PROMPT_FUNCTION_NAME, self.parser) {
+        if !transient && function::exists(MODE_PROMPT_FUNCTION_NAME, self.parser) {
             // We do not support multiline mode indicators, so just concatenate all of them.
             self.mode_prompt_buff = WString::from_iter(exec_prompt_cmd(
                 self.parser,
@@ -5159,23 +5704,29 @@ impl<'a> Reader<'a> {
         if full_prompt {
             self.left_prompt_buff.clear();
             self.right_prompt_buff.clear();
-
-            if !self.conf.left_prompt_cmd.is_empty() {
+            let mut async_jobs = vec![];
+
+            let left_prompt_cmd = if transient {
+                Some(TRANSIENT_PROMPT_FUNCTION_NAME)
+            } else if self.conf.left_prompt_cmd.is_empty() {
+                None
+            } else if self.conf.left_prompt_cmd != LEFT_PROMPT_FUNCTION_NAME
+                || function::exists(&self.data.conf.left_prompt_cmd, self.parser)
+            {
+                Some(&self.data.conf.left_prompt_cmd[..])
+            } else {
                 // Historic compatibility hack.
                 // If the left prompt function is deleted, then use a default prompt instead of
                 // producing an error.
-                let prompt_cmd = if self.conf.left_prompt_cmd != LEFT_PROMPT_FUNCTION_NAME
-                    || function::exists(&self.data.conf.left_prompt_cmd, self.parser)
-                {
-                    &self.data.conf.left_prompt_cmd
+                Some(DEFAULT_PROMPT)
+            };
+            if let Some(prompt_cmd) = left_prompt_cmd.map(ToOwned::to_owned) {
+                let output = if !final_prompt && is_async_prompt(self.vars(), &prompt_cmd) {
+                    self.async_prompt_output(&prompt_cmd, &mut async_jobs)
                 } else {
-                    DEFAULT_PROMPT
+                    exec_prompt_cmd(self.parser, &prompt_cmd, final_prompt && !transient)
                 };
-
-                self.left_prompt_buff = join_strings(
-                    &exec_prompt_cmd(self.parser, prompt_cmd, final_prompt),
-                    '\n',
-                );
+                self.left_prompt_buff = join_strings(&output, '\n');
 
                 // Support the SHELL_PROMPT_PREFIX and SHELL_PROMPT_SUFFIX environment
                 // variables as standardized by systemd v257. Prepend the prefix and
@@ -5189,18 +5740,48 @@ impl<'a> Reader<'a> {
                 }
             }
 
-            // Don't execute the right prompt if it is undefined fish_right_prompt
-            if !self.conf.right_prompt_cmd.is_empty()
+            let right_prompt_cmd = if transient {
+                // Without a transient right prompt, the right prompt is erased.
+                function::exists(TRANSIENT_RIGHT_PROMPT_FUNCTION_NAME, self.parser)
+                    .then_some(TRANSIENT_RIGHT_PROMPT_FUNCTION_NAME)
+            } else if !self.conf.right_prompt_cmd.is_empty()
                 && (self.conf.right_prompt_cmd != RIGHT_PROMPT_FUNCTION_NAME
                     || function::exists(&self.data.conf.right_prompt_cmd, self.parser))
             {
+                // Don't execute the right prompt if it is undefined fish_right_prompt
+                Some(&self.data.conf.right_prompt_cmd[..])
+            } else {
+                None
+            };
+            if let Some(prompt_cmd) = right_prompt_cmd.map(ToOwned::to_owned) {
+                let output = if !final_prompt && is_async_prompt(self.vars(), &prompt_cmd) {
+                    self.async_prompt_output(&prompt_cmd, &mut async_jobs)
+                } else {
+                    exec_prompt_cmd(self.parser, &prompt_cmd, final_prompt && !transient)
+                };
                 // Right prompt does not support multiple lines, so just concatenate all of them.
-                self.right_prompt_buff = WString::from_iter(exec_prompt_cmd(
-                    self.parser,
-                    &self.data.conf.right_prompt_cmd,
-                    final_prompt,
-                ));
+                self.right_prompt_buff = WString::from_iter(output);
             }
+
+            if !async_jobs.is_empty() {
+                self.render_async_prompts(async_jobs);
+            }
+        }
+
+        // The status line belongs to the regular prompt, and stays as it is while a command runs.
+        if !final_prompt {
+            self.status_line_buff = if self.conf.left_prompt_cmd != LEFT_PROMPT_FUNCTION_NAME {
+                None
+            } else if function::exists(STATUS_LINE_FUNCTION_NAME, self.parser) {
+                // The status line is a single line, so just concatenate all of them.
+                Some(WString::from_iter(exec_prompt_cmd(
+                    self.parser,
+                    STATUS_LINE_FUNCTION_NAME,
+                    false,
+                )))
+            } else {
+                Some(WString::new())
+            };
         }
 
         // Write the screen title. Do not reset the cursor position: exec_prompt is called when there
@@ -5220,6 +5801,133 @@ impl<'a> Reader<'a> {
     }
 }
 
+impl<'a> Reader<'a> {
+    /// Returns the output of the async prompt function `name` from the last time in this directory,
+    /// or else of running it with `--placeholder`. Adds a job for rendering it, unless the output
+    /// was rendered for this prompt already.
+    fn async_prompt_output(
+        &mut self,
+        name: &wstr,
+        async_jobs: &mut Vec<AsyncPromptJob>,
+    ) -> Vec<WString> {
+        let pwd = self.vars().get_pwd_slash();
+        let cached = self
+            .async_prompt_cache
+            .get_mut(name)
+            .filter(|cached| cached.pwd == pwd)
+            .map(|cached| (cached.lines.clone(), std::mem::take(&mut cached.fresh)));
+        match cached {
+            Some((lines, true)) => lines,
+            Some((lines, false)) => {
+                async_jobs.push(AsyncPromptJob::new(self.parser, name));
+                lines
+            }
+            None => {
+                async_jobs.push(AsyncPromptJob::new(self.parser, name));
+                let mut output = vec![];
+                let placeholder_cmd = name.to_owned() + L!(" ") + PLACEHOLDER_ARG;
+                let _ = exec_subshell(&placeholder_cmd, self.parser, Some(&mut output), false);
+                output
+            }
+        }
+    }
+
+    /// Render the async prompts in the background, and repaint once they are done.
+    fn render_async_prompts(&mut self, jobs: Vec<AsyncPromptJob>) {
+        kill_stale_renders();
+        self.async_prompt_request += 1;
+        let request = self.async_prompt_request;
+        let performer = move || -> iothreads::Callback {
+            let prompts = render_async_prompts(jobs);
+            Box::new(move |r: &mut Reader| {
+                // Results for an older prompt may arrive late.
+                if request != r.async_prompt_request {
+                    return;
+                }
+                r.async_prompt_cache.extend(prompts);
+                r.schedule_prompt_repaint();
+            })
+        };
+        self.debouncers.async_prompt.perform(performer);
+    }
+
+    /// Run `$fish_completion_preview_command` for the completion selected in the pager, if the
+    /// selection changed since the last time.
+    fn update_pager_preview(&mut self) {
+        if self.history_finder {
+            self.update_history_finder_preview();
+            return;
+        }
+        let is_completion_pager = self.history_pager.is_none() && !self.undo_tree_pager;
+        let token = self
+            .pager
+            .selected_completion(&self.current_page_rendering)
+            .filter(|_| is_completion_pager)
+            .map(|_| {
+                // The selected completion was applied to the token at the cursor in the command
+                // line from before the selection, which still starts at the same place.
+                let (cycle_range, _) =
+                    get_token_extent(&self.cycle_command_line, self.cycle_cursor_pos);
+                let el = &self.command_line;
+                let (token_range, _) = get_token_extent(el.text(), cycle_range.start.min(el.len()));
+                el.text()[token_range].to_owned()
+            });
+        if token.as_deref() == self.pager.preview_token() {
+            return;
+        }
+        let Some(token) = token else {
+            self.pager.set_preview(None, vec![]);
+            return;
+        };
+        let Some(preview_cmd) = self
+            .vars()
+            .get_unless_empty(L!("fish_completion_preview_command"))
+        else {
+            self.pager.set_preview(Some(token), vec![]);
+            return;
+        };
+
+        let _suppress_trace = self.parser.push_scope(|s| s.suppress_fish_trace = true);
+        let _noninteractive = self.parser.push_scope(|s| s.is_interactive = false);
+        let mut scoped_tty = TtyHandoff::new(reader_save_screen_state);
+        scoped_tty.disable_tty_protocols();
+
+        // The command gets the selected token, which is unescaped to not run any expansions in it,
+        // except for the tilde.
+        let arg = unescape_string(
+            &token,
+            UnescapeStringStyle::Script(UnescapeFlags::INCOMPLETE),
+        )
+        .unwrap_or_else(|| token.clone());
+        let cmd = preview_cmd.as_string()
+            + L!(" ")
+            + &escape_string(&arg, EscapeStringStyle::Script(EscapeFlags::NO_TILDE))[..];
+        let mut output = vec![];
+        let _ = exec_subshell(&cmd, self.parser, Some(&mut output), false);
+        job_reap(self.parser, true, None);
+        self.pager.set_preview(Some(token), output);
+    }
+
+    /// Show the command selected in the history finder in full next to the pager, if it has
+    /// several lines.
+    fn update_history_finder_preview(&mut self) {
+        let command = self
+            .pager
+            .selected_completion(&self.current_page_rendering)
+            .map(|completion| completion.completion.clone());
+        if command.as_deref() == self.pager.preview_token() {
+            return;
+        }
+        let lines = match &command {
+            Some(command) if command.contains('\n') => {
+                command.split('\n').map(|line| line.to_owned()).collect()
+            }
+            _ => vec![],
+        };
+        self.pager.set_preview(command, lines);
+    }
+}
+
 #[derive(Default, Clone, PartialEq, Debug)]
 pub(super) struct Autosuggestion {
     /// The text to use, as an extension/replacement of the current line.
@@ -5260,6 +5968,9 @@ pub(super) struct AutosuggestionResult {
 
     // The list of completions which may need loading.
     needs_load: Vec<WString>,
+
+    // The functions to ask for a suggestion first, as they come before the provider of this one.
+    functions: Vec<WString>,
 }
 
 impl std::ops::Deref for AutosuggestionResult {
@@ -5286,6 +5997,7 @@ impl AutosuggestionResult {
             },
             command_line,
             needs_load: vec![],
+            functions: vec![],
         }
     }
 
@@ -5306,197 +6018,273 @@ fn get_autosuggestion_performer(
     let generation_count = read_generation_count();
     let vars = parser.vars().snapshot();
     let working_directory = parser.vars().get_pwd_slash();
+    let providers = autosuggestion_providers(parser.vars());
     move || {
         assert_is_background_thread();
         let nothing = AutosuggestionResult::default();
         let ctx = &mut get_bg_context(&vars, generation_count);
-        if ctx.check_cancel() {
-            return nothing;
-        }
-
-        // Only to be used if no case-sensitive suggestions are found.
-        let mut icase_history_result = None;
-
-        let line_range = range_of_line_at_cursor(&command_line, cursor_pos);
-        // Search history for a matching item unless this line is not a continuation line or quoted.
-        for (search_type, range) in [
-            (SearchType::Prefix, 0..command_line.len()),
-            (SearchType::LinePrefix, line_range.clone()),
-        ] {
-            if range.is_empty() {
-                continue;
-            }
-            let search_string = &command_line[range.clone()];
-            if search_type == SearchType::LinePrefix {
-                let cursor_line_has_process_start = {
-                    let mut tokens = vec![];
-                    get_process_extent(&command_line, cursor_pos, Some(&mut tokens));
-                    range_of_line_at_cursor(
-                        &command_line,
-                        get_process_first_token_offset(&command_line, cursor_pos)
-                            .unwrap_or(cursor_pos),
-                    ) == range
-                };
-                if !cursor_line_has_process_start {
-                    continue;
-                }
-            }
-            let mut searcher = HistorySearch::new_with(
-                history.clone(),
-                search_string.to_owned(),
-                search_type,
-                SearchFlags::IGNORE_CASE,
-                0,
-            );
 
-            while !ctx.check_cancel() && searcher.go_to_next_match(SearchDirection::Backward) {
-                let item = searcher.current_item();
-
-                let full = item.str();
-                let (suggested_range, icase) = if search_type == SearchType::Prefix {
-                    let mut suggested_range =
-                        full.starts_with(search_string).then_some(0..full.len());
-                    let mut icase = false;
-                    // Only check for a case-insensitive match if we haven't already found one
-                    if suggested_range.is_none() && icase_history_result.is_none() {
-                        icase = true;
-                        suggested_range =
-                            string_prefixes_string_case_insensitive(search_string, full)
-                                .then_some(0..full.len());
-                    }
-
-                    (suggested_range, icase)
-                } else {
-                    // The history items may have multiple lines of text.
-                    // Only suggest the line that actually contains the search string.
-                    let newlines = full
-                        .char_indices()
-                        .filter_map(|(i, c)| (c == '\n').then_some(i));
-                    let line_ranges = std::iter::once(0)
-                        .chain(newlines.clone().map(|i| i + 1))
-                        .zip(newlines.chain(std::iter::once(full.char_count())))
-                        .map(|(start, end)| start..end);
-
-                    let mut icase = false;
-                    let mut suggested_range = line_ranges
-                        .clone()
-                        .find(|range| full[range.clone()].starts_with(search_string));
-
-                    // Only check for a case-insensitive match if we haven't already found one
-                    if suggested_range.is_none() && icase_history_result.is_none() {
-                        icase = true;
-                        suggested_range = line_ranges.into_iter().find(|range| {
-                            string_prefixes_string_case_insensitive(
-                                search_string,
-                                &full[range.clone()],
-                            )
-                        });
-                    }
-
-                    (suggested_range, icase)
-                };
-                let Some(suggested_range) = suggested_range else {
-                    assert!(
-                        icase_history_result.is_some(),
-                        "couldn't find line matching search {search_string:?} in history item {item:?} (did history search yield a bogus result?)"
-                    );
-                    continue;
-                };
-
-                if autosuggest_validate_from_history(
-                    full,
-                    suggested_range.clone(),
-                    item.get_required_paths(),
+        // The suggestion of the first provider wins, except that case-insensitive suggestions are
+        // only used if no provider has a case-sensitive one.
+        let mut icase_result: Option<(usize, AutosuggestionResult)> = None;
+        let mut needs_load = vec![];
+        let mut found = None;
+        for (i, provider) in providers.iter().enumerate() {
+            if ctx.check_cancel() {
+                return nothing;
+            }
+            let suggestion = match provider {
+                AutosuggestionProvider::History => history_autosuggestion(
+                    &history,
+                    &command_line,
+                    cursor_pos,
                     &working_directory,
                     ctx,
-                ) {
-                    // The command autosuggestion was handled specially, so we're done.
-                    let is_whole = suggested_range.len() == item.str().len();
-                    let result = AutosuggestionResult::new(
-                        command_line.clone(),
-                        range.clone(),
-                        full[suggested_range].into(),
-                        icase.then(|| searcher.canon_term().char_count()),
-                        is_whole,
-                    );
-                    if icase {
-                        icase_history_result = Some(result);
-                    } else {
-                        return result;
-                    }
+                ),
+                AutosuggestionProvider::Completion => {
+                    let (suggestion, to_load) =
+                        completion_autosuggestion(&command_line, cursor_pos, &vars, ctx);
+                    needs_load = to_load;
+                    suggestion
+                }
+                AutosuggestionProvider::DirectoryHistory => {
+                    directory_history_autosuggestion(&command_line, ctx.vars()).map(|text| {
+                        let result = AutosuggestionResult::new(
+                            command_line.clone(),
+                            0..command_line.len(),
+                            text,
+                            None,
+                            /*is_whole_item_from_history=*/ false,
+                        );
+                        (result, true)
+                    })
+                }
+                // Functions are run on the main thread once we are done.
+                AutosuggestionProvider::Function(_) => None,
+            };
+            match suggestion {
+                Some((result, true)) => {
+                    found = Some((i, result));
+                    break;
                 }
+                Some((result, false)) if icase_result.is_none() => {
+                    icase_result = Some((i, result));
+                }
+                _ => (),
             }
         }
 
-        // Maybe cancel here.
         if ctx.check_cancel() {
             return nothing;
         }
 
-        let Some(last_char) = command_line[line_range.clone()].chars().next_back() else {
-            // Let's make sure we aren't using the empty string.
-            return nothing;
+        // Functions may still provide a case-sensitive suggestion, if they come first.
+        let (last_provider, mut result) = match found {
+            Some(found) => found,
+            None => (
+                providers.len(),
+                icase_result.map_or_else(
+                    || AutosuggestionResult {
+                        command_line: command_line.clone(),
+                        ..Default::default()
+                    },
+                    |(_, result)| result,
+                ),
+            ),
         };
+        result.functions = providers[..last_provider]
+            .iter()
+            .filter_map(|provider| match provider {
+                AutosuggestionProvider::Function(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+        result.needs_load = needs_load;
+        result
+    }
+}
 
-        // Here we do something a little funny. If the line ends with a space, and the cursor is not
-        // at the end, don't use completion autosuggestions. It ends up being pretty weird seeing
-        // stuff get spammed on the right while you go back to edit a line
-        let cursor_at_end =
-            cursor_pos == command_line.len() || command_line.as_char_slice()[cursor_pos] == '\n';
-        if !cursor_at_end && last_char.is_whitespace() {
-            return nothing;
+/// Search history for an item starting with the command line, or the line at the cursor. Returns
+/// the suggestion and whether it matches case-sensitively.
+fn history_autosuggestion(
+    history: &Arc<History>,
+    command_line: &wstr,
+    cursor_pos: usize,
+    working_directory: &wstr,
+    ctx: &mut OperationContext,
+) -> Option<(AutosuggestionResult, bool)> {
+    // Only to be used if no case-sensitive suggestions are found.
+    let mut icase_history_result = None;
+
+    let line_range = range_of_line_at_cursor(command_line, cursor_pos);
+    // Search history for a matching item unless this line is not a continuation line or quoted.
+    for (search_type, range) in [
+        (SearchType::Prefix, 0..command_line.len()),
+        (SearchType::LinePrefix, line_range.clone()),
+    ] {
+        if range.is_empty() {
+            continue;
         }
-
-        // On the other hand, if the line ends with a quote, don't go dumping stuff after the quote.
-        if matches!(last_char, '\'' | '"') && cursor_at_end {
-            return nothing;
+        let search_string = &command_line[range.clone()];
+        if search_type == SearchType::LinePrefix {
+            let cursor_line_has_process_start = {
+                let mut tokens = vec![];
+                get_process_extent(command_line, cursor_pos, Some(&mut tokens));
+                range_of_line_at_cursor(
+                    command_line,
+                    get_process_first_token_offset(command_line, cursor_pos).unwrap_or(cursor_pos),
+                ) == range
+            };
+            if !cursor_line_has_process_start {
+                continue;
+            }
         }
+        let mut searcher = HistorySearch::new_with(
+            history.clone(),
+            search_string.to_owned(),
+            search_type,
+            SearchFlags::IGNORE_CASE,
+            0,
+        );
 
-        // Try normal completions.
-        let complete_flags = CompletionRequestOptions::autosuggest();
-        let mut would_be_cursor = line_range.end;
-        let (mut completions, needs_load) =
-            complete(&command_line[..would_be_cursor], complete_flags, ctx);
+        while !ctx.check_cancel() && searcher.go_to_next_match(SearchDirection::Backward) {
+            let item = searcher.current_item();
+
+            let full = item.str();
+            let (suggested_range, icase) = if search_type == SearchType::Prefix {
+                let mut suggested_range = full.starts_with(search_string).then_some(0..full.len());
+                let mut icase = false;
+                // Only check for a case-insensitive match if we haven't already found one
+                if suggested_range.is_none() && icase_history_result.is_none() {
+                    icase = true;
+                    suggested_range = string_prefixes_string_case_insensitive(search_string, full)
+                        .then_some(0..full.len());
+                }
 
-        let suggestion = if completions.is_empty() {
-            // If there are no completions to suggest, fall back to icase history.
-            if let Some(result) = icase_history_result {
-                return result;
-            }
-            WString::new()
-        } else {
-            sort_and_prioritize(&mut completions, complete_flags);
-            let comp = &completions[0];
+                (suggested_range, icase)
+            } else {
+                // The history items may have multiple lines of text.
+                // Only suggest the line that actually contains the search string.
+                let newlines = full
+                    .char_indices()
+                    .filter_map(|(i, c)| (c == '\n').then_some(i));
+                let line_ranges = std::iter::once(0)
+                    .chain(newlines.clone().map(|i| i + 1))
+                    .zip(newlines.chain(std::iter::once(full.char_count())))
+                    .map(|(start, end)| start..end);
+
+                let mut icase = false;
+                let mut suggested_range = line_ranges
+                    .clone()
+                    .find(|range| full[range.clone()].starts_with(search_string));
+
+                // Only check for a case-insensitive match if we haven't already found one
+                if suggested_range.is_none() && icase_history_result.is_none() {
+                    icase = true;
+                    suggested_range = line_ranges.into_iter().find(|range| {
+                        string_prefixes_string_case_insensitive(search_string, &full[range.clone()])
+                    });
+                }
 
-            // Prefer icase history over smartcase/icase completions.
-            if let (Some(result), CaseSensitivity::Smart | CaseSensitivity::Insensitive) =
-                (icase_history_result, comp.r#match.case_fold)
-            {
-                return result;
-            }
+                (suggested_range, icase)
+            };
+            let Some(suggested_range) = suggested_range else {
+                assert!(
+                    icase_history_result.is_some(),
+                    "couldn't find line matching search {search_string:?} in history item {item:?} (did history search yield a bogus result?)"
+                );
+                continue;
+            };
 
-            let full_line = completion_apply_to_command_line(
-                &mut OperationContext::background_interruptible(&vars),
-                &comp.completion,
-                comp.flags,
-                &command_line,
-                &mut would_be_cursor,
-                /*append_only=*/ true,
-                /*is_unique=*/ false,
-            );
-            line_at_cursor(&full_line, would_be_cursor).to_owned()
-        };
-        let lowercase_char_count = lowercase(command_line[line_range.clone()].chars()).count();
-        let mut result = AutosuggestionResult::new(
-            command_line,
-            line_range,
-            suggestion,
-            Some(lowercase_char_count), // normal completions are case-insensitive
-            /*is_whole_item_from_history=*/ false,
-        );
-        result.needs_load = needs_load;
-        result
+            if autosuggest_validate_from_history(
+                full,
+                suggested_range.clone(),
+                item.get_required_paths(),
+                working_directory,
+                ctx,
+            ) {
+                // The command autosuggestion was handled specially, so we're done.
+                let is_whole = suggested_range.len() == item.str().len();
+                let result = AutosuggestionResult::new(
+                    command_line.to_owned(),
+                    range.clone(),
+                    full[suggested_range].into(),
+                    icase.then(|| searcher.canon_term().char_count()),
+                    is_whole,
+                );
+                if icase {
+                    icase_history_result = Some(result);
+                } else {
+                    return Some((result, true));
+                }
+            }
+        }
     }
+    icase_history_result.map(|result| (result, false))
+}
+
+/// Complete the line at the cursor. Returns the suggestion and whether it matches
+/// case-sensitively, and the commands whose completions may need loading.
+fn completion_autosuggestion(
+    command_line: &wstr,
+    cursor_pos: usize,
+    vars: &EnvDyn,
+    ctx: &mut OperationContext,
+) -> (Option<(AutosuggestionResult, bool)>, Vec<WString>) {
+    let line_range = range_of_line_at_cursor(command_line, cursor_pos);
+    let Some(last_char) = command_line[line_range.clone()].chars().next_back() else {
+        // Let's make sure we aren't using the empty string.
+        return (None, vec![]);
+    };
+
+    // Here we do something a little funny. If the line ends with a space, and the cursor is not
+    // at the end, don't use completion autosuggestions. It ends up being pretty weird seeing
+    // stuff get spammed on the right while you go back to edit a line
+    let cursor_at_end =
+        cursor_pos == command_line.len() || command_line.as_char_slice()[cursor_pos] == '\n';
+    if !cursor_at_end && last_char.is_whitespace() {
+        return (None, vec![]);
+    }
+
+    // On the other hand, if the line ends with a quote, don't go dumping stuff after the quote.
+    if matches!(last_char, '\'' | '"') && cursor_at_end {
+        return (None, vec![]);
+    }
+
+    // Try normal completions.
+    let complete_flags = CompletionRequestOptions::autosuggest();
+    let mut would_be_cursor = line_range.end;
+    let (mut completions, needs_load) =
+        complete(&command_line[..would_be_cursor], complete_flags, ctx);
+    if completions.is_empty() {
+        return (None, needs_load);
+    }
+
+    sort_and_prioritize(&mut completions, complete_flags);
+    let comp = &completions[0];
+    let full_line = completion_apply_to_command_line(
+        &mut OperationContext::background_interruptible(vars),
+        &comp.completion,
+        comp.flags,
+        command_line,
+        &mut would_be_cursor,
+        /*append_only=*/ true,
+        /*is_unique=*/ false,
+    );
+    let suggestion = line_at_cursor(&full_line, would_be_cursor).to_owned();
+    let lowercase_char_count = lowercase(command_line[line_range.clone()].chars()).count();
+    let result = AutosuggestionResult::new(
+        command_line.to_owned(),
+        line_range,
+        suggestion,
+        Some(lowercase_char_count), // normal completions are case-insensitive
+        /*is_whole_item_from_history=*/ false,
+    );
+    let case_sensitive = !matches!(
+        comp.r#match.case_fold,
+        CaseSensitivity::Smart | CaseSensitivity::Insensitive
+    );
+    (Some((result, case_sensitive)), needs_load)
 }
 
 enum AutosuggestionPortion {
@@ -5524,7 +6312,7 @@ impl<'a> Reader<'a> {
     }
 
     // Called after an autosuggestion has been computed on a background thread.
-    fn autosuggest_completed(&mut self, result: AutosuggestionResult) {
+    fn autosuggest_completed(&mut self, mut result: AutosuggestionResult) {
         assert_is_main_thread();
         if result.command_line == self.data.in_flight_autosuggest_request {
             self.data.in_flight_autosuggest_request.clear();
@@ -5549,8 +6337,26 @@ impl<'a> Reader<'a> {
             // We loaded new completions for this command.
             // Re-do our autosuggestion.
             self.update_autosuggestion();
-        } else if !result.is_empty()
-            && self.can_autosuggest()
+            return;
+        }
+        if !self.can_autosuggest() {
+            return;
+        }
+        for function in std::mem::take(&mut result.functions) {
+            if let Some(text) =
+                function_autosuggestion(self.parser, &function, &result.command_line)
+            {
+                let search_string_range = 0..result.command_line.len();
+                result.autosuggestion = Autosuggestion {
+                    text,
+                    search_string_range,
+                    icase_matched_codepoints: None,
+                    is_whole_item_from_history: false,
+                };
+                break;
+            }
+        }
+        if !result.is_empty()
             && string_prefixes_string_maybe_case_insensitive(
                 result.icase_matched_codepoints.is_some(),
                 result.search_string(),
@@ -5723,24 +6529,37 @@ pub(super) struct HighlightResult {
     text: WString,
 }
 
-// Given text and  whether IO is allowed, return a function that performs highlighting. The function
-// may be invoked on a background thread.
+// Given text and the cache for checks that do IO if IO is allowed, return a function that performs
+// highlighting. The function may be invoked on a background thread.
 fn get_highlight_performer(
     parser: &Parser,
     el: &EditableLine,
-    io_ok: bool,
+    validity_cache: Option<&Arc<ValidityCache>>,
 ) -> impl FnOnce() -> HighlightResult + use<> {
-    let vars = parser.vars().snapshot();
+    let vars = Arc::new(parser.vars().snapshot());
     let generation_count = read_generation_count();
     let position = el.position();
     let text = el.text().to_owned();
+    let validity_cache = validity_cache.cloned();
     move || {
         if text.is_empty() {
             return HighlightResult::default();
         }
         let ctx = &mut get_bg_context(&vars, generation_count);
         let mut colors = vec![];
-        highlight_shell(&text, &mut colors, ctx, io_ok, Some(position));
+        match validity_cache {
+            Some(cache) => {
+                let checker = ValidityChecker::new(&cache, &vars);
+                highlight_shell_async(&text, &mut colors, ctx, checker, Some(position));
+            }
+            None => highlight_shell(
+                &text,
+                &mut colors,
+                ctx,
+                /*io_ok=*/ false,
+                Some(position),
+            ),
+        }
         HighlightResult { colors, text }
     }
 }
@@ -5771,8 +6590,11 @@ impl<'a> Reader<'a> {
         self.in_flight_highlight_request = self.command_line.text().to_owned();
 
         flog!(reader_render, "Highlighting");
-        let highlight_performer =
-            get_highlight_performer(self.parser, &self.command_line, /*io_ok=*/ true);
+        let highlight_performer = get_highlight_performer(
+            self.parser,
+            &self.command_line,
+            Some(&self.debouncers.validity_cache),
+        );
         self.debouncers.highlight.perform(highlight_performer);
     }
 
@@ -5815,8 +6637,7 @@ impl<'a> Reader<'a> {
 
         if !current_highlight_ok {
             // We need to do a quick highlight without I/O.
-            let highlight_no_io =
-                get_highlight_performer(self.parser, &self.command_line, /*io_ok=*/ false);
+            let highlight_no_io = get_highlight_performer(self.parser, &self.command_line, None);
             self.highlight_completed(highlight_no_io());
         }
     }
@@ -5827,6 +6648,9 @@ pub(super) struct HistoryPagerResult {
     range: Range<usize>,
     first_shown: usize,
     motion: Option<SelectionMotion>,
+    /// For the history finder, the number of matches, of which at most
+    /// [`HISTORY_FINDER_MAX_MATCHES`] are shown.
+    finder_match_count: Option<usize>,
 }
 
 #[derive(Eq, PartialEq)]
@@ -5901,17 +6725,157 @@ fn history_pager_search(
             range,
             first_shown,
             motion,
+            finder_match_count: None,
+        }
+    }
+}
+
+/// The history finder shows at most this many of the best matches, since the pager lays out all
+/// of them.
+const HISTORY_FINDER_MAX_MATCHES: usize = 1000;
+
+/// Whether `haystack` contains `needle`, ignoring case unless `needle` has uppercase characters.
+fn contains_smartcase(haystack: &wstr, needle: &wstr) -> bool {
+    if needle.chars().any(|c| c.is_uppercase()) {
+        subslice_position(haystack, needle).is_some()
+    } else {
+        ifind(haystack, needle, false).is_some()
+    }
+}
+
+/// Score a command against the search terms of the history finder, which must all match. A term
+/// matches like in fuzzy finders, if the command contains its characters in order. A term starting
+/// with `'` only matches if the command contains the rest of it as is, and one starting with `!`
+/// only matches if the command does not contain the rest of it.
+/// Returns None if a term does not match.
+fn history_finder_score(terms: &[&wstr], command: &wstr) -> Option<u32> {
+    let mut score = 0;
+    for term in terms {
+        if let Some(excluded) = term.strip_prefix('!') {
+            if !excluded.is_empty() && contains_smartcase(command, excluded) {
+                return None;
+            }
+        } else if let Some(exact) = term.strip_prefix('\'') {
+            if !contains_smartcase(command, exact) {
+                return None;
+            }
+            score += subsequence_score(exact, command)?;
+        } else {
+            score += subsequence_score(term, command)?;
         }
     }
+    Some(score)
+}
+
+/// Search the whole history for the history finder. The matches are ordered by their score, and
+/// then by how recent they are.
+fn history_finder_search(
+    history: &History,
+    search_string: &wstr,
+    motion: Option<SelectionMotion>,
+) -> HistoryPagerResult {
+    let terms = split_string_tok(search_string, L!(" \t\n"), None);
+    let mut matches: Vec<(u32, WString)> = history
+        .get_history()
+        .into_iter()
+        .filter_map(|command| Some((history_finder_score(&terms, &command)?, command)))
+        .collect();
+    // This sort is stable, so equal scores stay ordered from the most recent.
+    matches.sort_by_key(|&(score, _)| cmp::Reverse(score));
+    let match_count = matches.len();
+    let completions = matches
+        .into_iter()
+        .take(HISTORY_FINDER_MAX_MATCHES)
+        .map(|(_, command)| {
+            Completion::new(
+                command,
+                L!("").to_owned(),
+                StringFuzzyMatch::exact_match(),
+                CompleteFlags::REPLACES_LINE
+                    | CompleteFlags::DONT_ESCAPE
+                    | CompleteFlags::DONT_SORT,
+            )
+        })
+        .collect();
+    HistoryPagerResult {
+        matched_commands: completions,
+        // All of the history was searched.
+        range: 0..history.size() + 1,
+        first_shown: 0,
+        motion,
+        finder_match_count: Some(match_count),
+    }
 }
 
 impl ReaderData {
+    /// The history the history pager searches.
+    fn history_pager_history(&self) -> &Arc<History> {
+        self.history_pager_source.as_ref().unwrap_or(&self.history)
+    }
+
+    /// Open the history pager, or the full-screen history finder, and search for the command
+    /// substitution or line at the cursor.
+    fn open_history_pager(&mut self, finder: bool) {
+        // Record our cycle_command_line.
+        self.cycle_command_line = self.command_line.text().to_owned();
+        self.cycle_cursor_pos = self.command_line.position();
+
+        self.history_pager = Some(0..1);
+        self.history_finder = finder;
+        // Update the pager data.
+        self.pager.set_search_field_shown(true);
+        if finder {
+            self.pager.set_fully_disclosed();
+        }
+        self.pager.set_prefix(Cow::Borrowed(L!("► ")), false);
+        // Update the search field, which triggers the actual history search.
+        let search_string =
+            if !self.history_search.active() || self.history_search.search_string().is_empty() {
+                let cmdsub =
+                    get_cmdsubst_extent(self.command_line.text(), self.command_line.position());
+                let cmdsub = &self.command_line.text()[cmdsub];
+                let needle = if !cmdsub.contains('\n') {
+                    cmdsub
+                } else {
+                    line_at_cursor(self.command_line.text(), self.command_line.position())
+                };
+                // The finder does not support wildcards.
+                if finder {
+                    needle.to_owned()
+                } else {
+                    escape_wildcards(needle)
+                }
+            } else {
+                // If we have an actual history search already going, reuse that term
+                // - this is if the user looks around a bit and decides to switch to the pager.
+                self.history_search.search_string().to_owned()
+            };
+        self.insert_string(EditableLineTag::SearchField, &search_string);
+    }
+
     fn fill_history_pager(
         &mut self,
         why: HistoryPagerInvocation,
         motion: Option<SelectionMotion>,
         mut direction: SearchDirection, /* = Backward */
     ) {
+        if self.history_finder {
+            let old_pager_index = if why == HistoryPagerInvocation::Refresh {
+                self.pager.selected_completion_index()
+            } else {
+                None
+            };
+            let history = self.history_pager_history().clone();
+            let search_term = self.pager.search_field_line.text().to_owned();
+            let performer = move || -> iothreads::Callback {
+                let result = history_finder_search(&history, &search_term, motion);
+                Box::new(move |r: &mut Reader| {
+                    r.fill_history_pager_complete(result, why, old_pager_index);
+                })
+            };
+            self.debouncers.history_pager.perform(performer);
+            return;
+        }
         let index;
         let mut old_pager_index = None;
         match why {
@@ -5936,7 +6900,7 @@ impl ReaderData {
         }
         let search_term = self.pager.search_field_line.text().to_owned();
         // Get a performer that produces the history pager result.
-        let history = self.history.clone();
+        let history = self.history_pager_history().clone();
         let search_term = search_term.clone();
         let performer = move || -> iothreads::Callback {
             let result = history_pager_search(&history, direction, motion, index, &search_term);
@@ -5955,26 +6919,35 @@ impl<'a> Reader<'a> {
         why: HistoryPagerInvocation,
         old_pager_index: Option<usize>,
     ) {
-        let history_size = self.history.size();
+        let history_size = self.history_pager_history().size();
         let Some(history_pager) = self.history_pager.as_mut() else {
             return; // Pager has been closed.
         };
         assert!(result.range.start < result.range.end);
         *history_pager = result.range;
-        self.pager.extra_progress_text =
-            if !result.matched_commands.is_empty() && *history_pager != (0..history_size + 1) {
+        self.pager.extra_progress_text = if let Some(match_count) = result.finder_match_count {
+            if match_count > result.matched_commands.len() {
                 wgettext_fmt!(
-                    "Items %u to %u of %u",
-                    match history_pager.start {
-                        0 => 1,
-                        _ => result.first_shown,
-                    },
-                    history_pager.end - 1,
-                    history_size
+                    "Best %u of %u matches",
+                    result.matched_commands.len(),
+                    match_count
                 )
             } else {
                 L!("").to_owned()
-            };
+            }
+        } else if !result.matched_commands.is_empty() && *history_pager != (0..history_size + 1) {
+            wgettext_fmt!(
+                "Items %u to %u of %u",
+                match history_pager.start {
+                    0 => 1,
+                    _ => result.first_shown,
+                },
+                history_pager.end - 1,
+                history_size
+            )
+        } else {
+            L!("").to_owned()
+        };
         self.pager.set_completions(&result.matched_commands, false);
         if why == HistoryPagerInvocation::Refresh {
             self.pager.set_selected_completion_index(old_pager_index);
@@ -5988,7 +6961,7 @@ impl<'a> Reader<'a> {
     }
 }
 
-/// Expand an abbreviation replacer, which may mean running its function.
+/// Expand an abbreviation replacer, which may mean running its condition and function.
 /// Return the replacement, or none to skip it. This may run fish script!
 fn expand_replacer(
     range: SourceRange,
@@ -5996,6 +6969,22 @@ fn expand_replacer(
     repl: &abbrs::Replacer,
     parser: &mut Parser,
 ) -> Option<abbrs::Replacement> {
+    if let Some(condition) = &repl.condition {
+        let _not_interactive = parser.push_scope(|s| {
+            s.is_interactive = false;
+            s.readonly_commandline = true;
+        });
+        if exec_subshell(condition, parser, None, /*apply_exit_status=*/ false).is_err() {
+            flogf!(
+                abbrs,
+                "Skipped abbreviation for <%s> because its condition <%s> failed",
+                token,
+                condition
+            );
+            return None;
+        }
+    }
+
     if !repl.is_function {
         // Literal replacement cannot fail.
         flogf!(
@@ -6116,6 +7105,8 @@ fn extract_tokens(s: &wstr) -> Vec<PositionedToken> {
 /// abbreviation wants to move the cursor. Use the parser to run any abbreviations which want
 /// function calls. Return none if no abbreviations were expanded, otherwise the resulting
 /// replacement.
+/// Abbreviations of several words match the tokens ending at the cursor, and take precedence over
+/// those matching fewer tokens.
 pub fn reader_expand_abbreviation_at_cursor(
     cmdline: &wstr,
     cursor_pos: usize,
@@ -6123,41 +7114,53 @@ pub fn reader_expand_abbreviation_at_cursor(
 ) -> Option<abbrs::Replacement> {
     // Find the token containing the cursor. Usually users edit from the end, so walk backwards.
     let tokens = extract_tokens(cmdline);
-    let mut token: Option<_> = None;
-    let mut cmdtok: Option<_> = None;
+    let mut token_idx: Option<usize> = None;
+    let mut cmd_idx: Option<usize> = None;
 
-    for t in tokens.into_iter().rev() {
-        let range = t.range;
-        let is_cmd = t.is_cmd;
+    for (idx, t) in tokens.iter().enumerate().rev() {
         if t.range.contains_inclusive(cursor_pos) {
-            token = Some(t);
+            token_idx = Some(idx);
         }
         // The command is at or *before* the token the cursor is on,
         // and once we have a command we can stop.
-        if token.is_some() && is_cmd {
-            cmdtok = Some(range);
+        if token_idx.is_some() && t.is_cmd {
+            cmd_idx = Some(idx);
             break;
         }
     }
-    let token = token?;
-    let range = token.range;
-    let position = if token.is_cmd {
-        abbrs::Position::Command
-    } else {
-        abbrs::Position::Anywhere
-    };
-    // If the token itself is the command, we have no command to pass.
-    let cmd = if !token.is_cmd {
-        cmdtok.map(|t| &cmdline[Range::<usize>::from(t)])
-    } else {
-        None
-    };
+    let token_idx = token_idx?;
 
-    let token_str = &cmdline[Range::<usize>::from(range)];
-    let replacers = abbrs_match(token_str, position, cmd.unwrap_or(L!("")));
-    for replacer in replacers {
-        if let Some(replacement) = expand_replacer(range, token_str, &replacer, parser) {
-            return Some(replacement);
+    let max_words = abbrs::with_abbrs(|set| set.max_word_count());
+    for nwords in (1..=max_words.min(token_idx + 1)).rev() {
+        let first = token_idx + 1 - nwords;
+        // All tokens must be part of the same statement, so only the first may be the command.
+        if cmd_idx.is_some_and(|cmd_idx| first < cmd_idx)
+            || tokens[first + 1..=token_idx].iter().any(|t| t.is_cmd)
+        {
+            continue;
+        }
+        let position = if tokens[first].is_cmd {
+            abbrs::Position::Command
+        } else {
+            abbrs::Position::Anywhere
+        };
+        // If the first token itself is the command, we have no command to pass.
+        let cmd = match cmd_idx {
+            Some(cmd_idx) if cmd_idx != first => &cmdline[tokens[cmd_idx].range.as_usize()],
+            _ => L!(""),
+        };
+
+        let start = tokens[first].range.start();
+        let range = SourceRange::new(start, tokens[token_idx].range.end() - start);
+        let words: Vec<&wstr> = tokens[first..=token_idx]
+            .iter()
+            .map(|t| &cmdline[t.range.as_usize()])
+            .collect();
+        let token_str = &cmdline[range.as_usize()];
+        for replacer in abbrs_match(&words, position, cmd) {
+            if let Some(replacement) = expand_replacer(range, token_str, &replacer, parser) {
+                return Some(replacement);
+            }
         }
     }
     None
@@ -6193,6 +7196,10 @@ fn command_ends_paging(c: ReadlineCmd, focused_on_search_field: bool) -> bool {
         | rl::HistoryPrefixSearchForward
         | rl::HistorySearchBackward
         | rl::HistorySearchForward
+        | rl::HistoryDirectorySearchBackward
+        | rl::HistoryDirectorySearchForward
+        | rl::HistoryProjectSearchBackward
+        | rl::HistoryProjectSearchForward
         | rl::HistoryTokenSearchBackward
         | rl::HistoryTokenSearchForward
         | rl::HistoryLastTokenSearchBackward
@@ -6209,6 +7216,8 @@ fn command_ends_paging(c: ReadlineCmd, focused_on_search_field: bool) -> bool {
         rl::Complete
         | rl::CompleteAndSearch
         | rl::HistoryPager
+        | rl::HistoryProjectPager
+        | rl::HistoryFinder
         | rl::BackwardChar
         | rl::BackwardCharPassive
         | rl::ForwardChar
@@ -6219,7 +7228,11 @@ fn command_ends_paging(c: ReadlineCmd, focused_on_search_field: bool) -> bool {
         | rl::Repaint
         | rl::SuppressAutosuggestion
         | rl::BeginningOfHistory
-        | rl::EndOfHistory =>
+        | rl::EndOfHistory
+        | rl::PagerPageUp
+        | rl::PagerPageDown
+        | rl::PagerToggleMark
+        | rl::MoveToMouse =>
         // These commands never end paging.
         {
             false
@@ -6270,7 +7283,9 @@ fn command_ends_paging(c: ReadlineCmd, focused_on_search_field: bool) -> bool {
         | rl::BeginningOfBuffer
         | rl::EndOfBuffer
         | rl::Undo
-        | rl::Redo =>
+        | rl::Redo
+        | rl::UndoTreeNextBranch
+        | rl::UndoTreePrevBranch =>
         // These commands operate on the search field if that's where the focus is.
         {
             !focused_on_search_field
@@ -6290,6 +7305,10 @@ fn command_ends_history_search(c: ReadlineCmd) -> bool {
             | rl::HistoryPrefixSearchForward
             | rl::HistorySearchBackward
             | rl::HistorySearchForward
+            | rl::HistoryDirectorySearchBackward
+            | rl::HistoryDirectorySearchForward
+            | rl::HistoryProjectSearchBackward
+            | rl::HistoryProjectSearchForward
             | rl::HistoryTokenSearchBackward
             | rl::HistoryTokenSearchForward
             | rl::HistoryLastTokenSearchBackward
@@ -6365,7 +7384,8 @@ fn check_for_orphaned_process(loop_count: usize, shell_pgid: libc::pid_t) -> boo
 
 /// Run the specified command with the correct terminal modes, and while taking care to perform job
 /// notification, set the title, etc.
-fn reader_run_command(parser: &mut Parser, cmd: &wstr) -> EvalRes {
+/// Run a command from the command line. Return its result, and how long it ran.
+fn reader_run_command(parser: &mut Parser, cmd: &wstr) -> (EvalRes, Duration) {
     assert!(
         !get_tty_protocols_active(),
         "TTY protocols should not be active"
@@ -6389,12 +7409,11 @@ fn reader_run_command(parser: &mut Parser, cmd: &wstr) -> EvalRes {
     let time_before = Instant::now();
     let eval_res = parser.eval(cmd, &IoChain::new());
     job_reap(parser, true, None);
+    let duration = time_before.elapsed();
 
     // Update the execution duration iff a command is requested for execution
     // issue - #4926
     if !ft.is_empty() {
-        let time_after = Instant::now();
-        let duration = time_after.duration_since(time_before);
         parser.set_one(
             ENV_CMD_DURATION,
             ParserEnvSetMode::new(EnvMode::UNEXPORT),
@@ -6419,7 +7438,7 @@ fn reader_run_command(parser: &mut Parser, cmd: &wstr) -> EvalRes {
         proc_update_jiffies(parser);
     }
 
-    eval_res
+    (eval_res, duration)
 }
 
 fn reader_shell_test(parser: &Parser, bstr: &wstr) -> Result<(), ParseIssue> {
@@ -6509,6 +7528,13 @@ impl<'a> Reader<'a> {
         };
         self.history
             .add_pending_with_file_detection(&text, &self.parser.variables, mode);
+        // Also add the command to the history of the project we are in.
+        self.pending_project_history = (mode == PersistenceMode::Disk)
+            .then(|| project_history(self.vars()))
+            .flatten();
+        if let Some(project) = &self.pending_project_history {
+            project.add_pending_with_file_detection(&text, &self.parser.variables, mode);
+        }
     }
 
     /// Check if we have background jobs that we have not warned about.
@@ -7224,7 +8250,9 @@ impl<'a> Reader<'a> {
 
 #[cfg(test)]
 mod tests {
-    use super::{combine_command_and_autosuggestion, completion_apply_to_command_line};
+    use super::{
+        combine_command_and_autosuggestion, completion_apply_to_command_line, history_finder_score,
+    };
     use crate::complete::CompleteFlags;
     use crate::operation_context::{OperationContext, no_cancel};
     use crate::prelude::*;
@@ -7541,4 +8569,27 @@ mod tests {
             None,
         );
     }
+    #[test]
+    fn test_history_finder_score() {
+        let score = |terms: &[&str], command: &str| {
+            let terms: Vec<WString> = terms.iter().map(|term| WString::from(*term)).collect();
+            let terms: Vec<&wstr> = terms.iter().map(|term| term.as_ref()).collect();
+            history_finder_score(&terms, &WString::from(command))
+        };
+        assert!(score(&[], "anything").is_some());
+        // All terms need to match, in any order.
+        assert!(score(&["gco", "main"], "git checkout main").is_some());
+        assert!(score(&["main", "gco"], "git checkout main").is_some());
+        assert!(score(&["gco", "dev"], "git checkout main").is_none());
+        // Exact terms need to appear as is, ignoring case unless they have uppercase characters.
+        assert!(score(&["'chk"], "git checkout").is_none());
+        assert!(score(&["'CHECK"], "git checkout").is_none());
+        assert!(score(&["'check"], "git CHECKOUT").is_some());
+        // Excluded terms must not appear.
+        assert!(score(&["git", "!push"], "git push").is_none());
+        assert!(score(&["git", "!push"], "git pull").is_some());
+        assert!(score(&["!"], "git pull").is_some());
+        // Better matches score higher.
+        assert!(score(&["make"], "make install") > score(&["make"], "cmake --build ."));
+    }
 }
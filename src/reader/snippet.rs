@@ -0,0 +1,256 @@
+//! Command line snippets, which are text with placeholders that the cursor can jump between.
+//! A placeholder is written `${1:default text}`, `${1}` or `$1`. The placeholders are visited in
+//! order of their number, and `$0` marks where the cursor ends up after the last one, which is the
+//! end of the snippet if there is no `$0`. `\$`, `\}` and `\\` stand for the literal characters.
+
+use crate::prelude::*;
+use crate::wutil::fish_wcstoul;
+use std::ops::Range;
+
+/// A parsed snippet.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Snippet {
+    /// The text with the placeholder syntax removed.
+    pub text: WString,
+    /// The ranges of the placeholders' default text, in the order they are visited. The last one
+    /// is the final cursor position.
+    pub placeholders: Vec<Range<usize>>,
+}
+
+/// Parse a snippet. On error, returns the offset of the placeholder which is not terminated or
+/// has no number.
+pub fn parse_snippet(snippet: &wstr) -> Result<Snippet, usize> {
+    let chars = snippet.as_char_slice();
+    let mut text = WString::new();
+    // The number and range of each placeholder.
+    let mut placeholders: Vec<(u64, Range<usize>)> = vec![];
+    let mut final_stop = None;
+    // The offset, number and text start of the placeholder whose default text we are in.
+    let mut open: Option<(usize, u64, usize)> = None;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && matches!(chars.get(i + 1), Some('$' | '}' | '\\')) {
+            text.push(chars[i + 1]);
+            i += 2;
+            continue;
+        }
+        if c == '}' {
+            if let Some((_, number, start)) = open.take() {
+                let range = start..text.len();
+                if number == 0 {
+                    final_stop = Some(range);
+                } else {
+                    placeholders.push((number, range));
+                }
+                i += 1;
+                continue;
+            }
+        }
+        if c != '$' || open.is_some() {
+            text.push(c);
+            i += 1;
+            continue;
+        }
+        let braced = chars.get(i + 1) == Some(&'{');
+        let digits_start = i + 1 + usize::from(braced);
+        let digits_end = chars[digits_start..]
+            .iter()
+            .position(|c| !c.is_ascii_digit())
+            .map_or(chars.len(), |len| digits_start + len);
+        let digits = wstr::from_char_slice(&chars[digits_start..digits_end]);
+        let Ok(number) = fish_wcstoul(digits) else {
+            if braced {
+                return Err(i);
+            }
+            // A lone '$' is literal.
+            text.push(c);
+            i += 1;
+            continue;
+        };
+        if !braced {
+            let range = text.len()..text.len();
+            if number == 0 {
+                final_stop = Some(range);
+            } else {
+                placeholders.push((number, range));
+            }
+            i = digits_end;
+            continue;
+        }
+        match chars.get(digits_end) {
+            Some('}') => {
+                let range = text.len()..text.len();
+                if number == 0 {
+                    final_stop = Some(range);
+                } else {
+                    placeholders.push((number, range));
+                }
+                i = digits_end + 1;
+            }
+            Some(':') => {
+                open = Some((i, number, text.len()));
+                i = digits_end + 1;
+            }
+            _ => return Err(i),
+        }
+    }
+    if let Some((offset, _, _)) = open {
+        return Err(offset);
+    }
+    // Placeholders with the same number are visited from left to right.
+    placeholders.sort_by_key(|(number, _)| *number);
+    let mut placeholders: Vec<_> = placeholders.into_iter().map(|(_, range)| range).collect();
+    placeholders.push(final_stop.unwrap_or(text.len()..text.len()));
+    Ok(Snippet { text, placeholders })
+}
+
+/// The placeholders of a snippet that was inserted into the command line.
+pub(super) struct SnippetState {
+    /// The placeholder ranges in the command line, in the order they are visited.
+    placeholders: Vec<Range<usize>>,
+    /// The index of the placeholder the cursor was moved to.
+    current: usize,
+    /// Whether the current placeholder still has its default text, which is replaced by typing at
+    /// its start.
+    pristine: bool,
+}
+
+impl SnippetState {
+    /// Returns the state for a snippet inserted at `offset`, unless it has nothing to visit.
+    pub(super) fn new(snippet: &Snippet, offset: usize) -> Option<Self> {
+        if snippet.placeholders.len() < 2 {
+            return None;
+        }
+        let placeholders = snippet
+            .placeholders
+            .iter()
+            .map(|range| range.start + offset..range.end + offset)
+            .collect();
+        Some(Self {
+            placeholders,
+            current: 0,
+            pristine: true,
+        })
+    }
+
+    /// The placeholder the cursor was moved to.
+    pub(super) fn current(&self) -> Range<usize> {
+        self.placeholders[self.current].clone()
+    }
+
+    /// Whether this is the final cursor position, after which the snippet is done.
+    pub(super) fn is_at_end(&self) -> bool {
+        self.current + 1 == self.placeholders.len()
+    }
+
+    /// If the default text of the current placeholder is untouched and `pos` is at its start,
+    /// returns the range that text being inserted at `pos` should replace.
+    pub(super) fn take_pristine_range(&mut self, pos: usize) -> Option<Range<usize>> {
+        let pristine = std::mem::replace(&mut self.pristine, false);
+        let current = self.current();
+        (pristine && pos == current.start && !current.is_empty()).then_some(current)
+    }
+
+    /// Move to the next or previous placeholder. Returns false if there is none.
+    pub(super) fn advance(&mut self, forward: bool) -> bool {
+        let next = if forward {
+            self.current + 1
+        } else {
+            match self.current.checked_sub(1) {
+                Some(prev) => prev,
+                None => return false,
+            }
+        };
+        if next >= self.placeholders.len() {
+            return false;
+        }
+        self.current = next;
+        self.pristine = true;
+        true
+    }
+
+    /// Adjust the placeholders to an edit replacing `range` with `replacement_len` characters.
+    /// Returns false if the edit removed the current placeholder, which ends the snippet.
+    pub(super) fn apply_edit(&mut self, range: &Range<usize>, replacement_len: usize) -> bool {
+        let current = self.current();
+        if (range.start < current.start && range.end >= current.end)
+            || (range.start <= current.start && range.end > current.end)
+        {
+            return false;
+        }
+        let map = |pos: usize| {
+            if pos < range.start || (pos == range.start && !range.is_empty()) {
+                pos
+            } else if pos >= range.end {
+                pos - range.len() + replacement_len
+            } else {
+                range.start + replacement_len
+            }
+        };
+        for (i, placeholder) in self.placeholders.iter_mut().enumerate() {
+            // Text typed into the current placeholder becomes part of it.
+            if i == self.current && range.start >= placeholder.start && range.end <= placeholder.end
+            {
+                placeholder.end = placeholder.end - range.len() + replacement_len;
+                continue;
+            }
+            *placeholder = map(placeholder.start)..map(placeholder.end);
+        }
+        self.pristine = false;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Snippet, SnippetState, parse_snippet};
+    use crate::prelude::*;
+
+    #[test]
+    fn test_parse_snippet() {
+        assert_eq!(
+            parse_snippet(L!("git commit -m \"${1:message}\"")),
+            Ok(Snippet {
+                text: L!("git commit -m \"message\"").to_owned(),
+                placeholders: vec![15..22, 23..23],
+            })
+        );
+        assert_eq!(
+            parse_snippet(L!("cp $2 ${1:src} $0; \\$HOME ${3}")),
+            Ok(Snippet {
+                text: L!("cp  src ; $HOME ").to_owned(),
+                placeholders: vec![4..7, 3..3, 16..16, 8..8],
+            })
+        );
+        assert_eq!(
+            parse_snippet(L!("echo $ ${1:a\\}b} $HOME")),
+            Ok(Snippet {
+                text: L!("echo $ a}b $HOME").to_owned(),
+                placeholders: vec![7..10, 16..16],
+            })
+        );
+        assert_eq!(parse_snippet(L!("echo ${1:unterminated")), Err(5));
+        assert_eq!(parse_snippet(L!("echo ${x}")), Err(5));
+    }
+
+    #[test]
+    fn test_snippet_edits() {
+        let snippet = parse_snippet(L!("echo ${1:a} ${2:b}")).unwrap();
+        let mut state = SnippetState::new(&snippet, 2).unwrap();
+        assert_eq!(state.current(), 7..8);
+        // Typing at the start replaces the default text.
+        assert_eq!(state.take_pristine_range(7), Some(7..8));
+        assert!(state.apply_edit(&(7..8), 3));
+        assert!(state.apply_edit(&(10..10), 1));
+        assert_eq!(state.current(), 7..11);
+        assert!(state.advance(true));
+        assert_eq!(state.current(), 12..13);
+        assert_eq!(state.take_pristine_range(13), None);
+        assert!(state.advance(true));
+        assert!(state.is_at_end());
+        assert!(!state.advance(true));
+        // Removing the current placeholder ends the snippet.
+        assert!(!state.apply_edit(&(0..14), 0));
+    }
+}
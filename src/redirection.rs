@@ -8,12 +8,14 @@ use std::os::fd::RawFd;
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
 pub enum RedirectionMode {
-    Overwrite, // normal redirection: > file.txt
-    Append,    // appending redirection: >> file.txt
-    Input,     // input redirection: < file.txt
-    TryInput,  // try-input redirection: <? file.txt
-    Fd,        // fd redirection: 2>&1
-    NoClob,    // noclobber redirection: >? file.txt
+    Overwrite,  // normal redirection: > file.txt
+    Append,     // appending redirection: >> file.txt
+    Input,      // input redirection: < file.txt
+    TryInput,   // try-input redirection: <? file.txt
+    Fd,         // fd redirection: 2>&1
+    NoClob,     // noclobber redirection: >? file.txt
+    HereDoc,    // here-document: <<EOF
+    HereString, // here-string: <<< text
 }
 
 /// A type that represents the action dup2(src, target).
@@ -135,6 +137,13 @@ impl Dup2List {
         cursor
     }
 
+    /// Return the lowest fd which is above every fd that the actions leave open in the child.
+    /// This is never less than 3, so that stdin, stdout and stderr are always preserved.
+    pub fn first_unused_fd(&self) -> RawFd {
+        let max_target = self.actions.iter().map(|act| act.target).max();
+        max_target.map_or(3, |fd| (fd + 1).max(3))
+    }
+
     /// Append a dup2 action.
     pub fn add_dup2(&mut self, src: RawFd, target: RawFd) {
         assert!(src >= 0 && target >= 0, "Invalid fd in add_dup2");
@@ -198,4 +207,20 @@ mod tests {
         assert_eq!(list.fd_for_target_fd(9), -1);
         assert_eq!(list.fd_for_target_fd(10), -1);
     }
+
+    #[test]
+    fn test_dup2s_first_unused_fd() {
+        let list = dup2_list_resolve_chain(&IoChain::new());
+        assert_eq!(list.first_unused_fd(), 3);
+
+        let mut chain = IoChain::new();
+        chain.push(Arc::new(IoClose::new(17)));
+        chain.push(Arc::new(IoFd::new(1, 19)));
+        let list = dup2_list_resolve_chain(&chain);
+        assert_eq!(list.first_unused_fd(), 3);
+
+        chain.push(Arc::new(IoFd::new(7, 20)));
+        let list = dup2_list_resolve_chain(&chain);
+        assert_eq!(list.first_unused_fd(), 8);
+    }
 }
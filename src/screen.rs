@@ -19,10 +19,11 @@ use crate::prelude::*;
 use crate::terminal::SgrTerminalCommand::EnterDimMode;
 use crate::terminal::TerminalCommand::{
     self, ClearToEndOfLine, ClearToEndOfScreen, CursorDown, CursorLeft, CursorMove, CursorRight,
-    CursorUp, Osc133PromptEnd, Osc133PromptStart, ScrollContentUp,
+    CursorToLine, CursorUp, DecrcRestoreCursor, DecscSaveCursor, DecstbmResetScrollRegion,
+    DecstbmSetScrollRegion, Osc133PromptEnd, Osc133PromptStart, ScrollContentUp,
 };
 use crate::terminal::{BufferedOutputter, CardinalDirection, Outputter};
-use crate::termsize::Termsize;
+use crate::termsize::{Termsize, termsize_last};
 use crate::wutil::fstat;
 use fish_common::write_loop;
 use fish_fallback::{fish_wcswidth_canonicalizing, fish_wcwidth};
@@ -36,7 +37,7 @@ use std::io::Write as _;
 use std::num::NonZeroU16;
 use std::ops::Range;
 use std::sync::Mutex;
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::time::SystemTime;
 
 #[derive(Copy, Clone, Default)]
@@ -63,6 +64,8 @@ pub struct Line {
     pub text: Vec<HighlightedChar>,
     pub is_soft_wrapped: bool,
     pub indentation: usize,
+    /// Whether the right prompt was repeated at the end of this line.
+    pub has_right_prompt: bool,
 }
 
 impl Line {
@@ -159,6 +162,9 @@ pub struct ScreenData {
 
     /// Number of prompt lines rendered on the screen.
     visible_prompt_lines: usize,
+
+    /// Number of lines of the prompt and command line, not counting the pager.
+    commandline_lines: usize,
 }
 
 impl ScreenData {
@@ -215,6 +221,8 @@ pub struct Screen {
     pub autosuggestion_is_truncated: bool,
     /// True if the last rendering was so large we could only display part of the command line.
     pub scrolled: bool,
+    /// Whether to repeat the right prompt on every line of a multiline command line.
+    pub right_prompt_on_every_line: bool,
 
     /// Receiver for our output.
     outp: &'static RefCell<Outputter>,
@@ -229,6 +237,8 @@ pub struct Screen {
     actual_left_prompt: Option<WString>,
     /// Last right prompt width.
     last_right_prompt_width: usize,
+    /// The status line which was last printed to the bottom line of the terminal.
+    actual_status_line: Option<WString>,
     /// If we support soft wrapping, we can output to this location without any cursor motion.
     soft_wrap_location: Option<Cursor>,
     /// This flag is set to true when there is reason to suspect that the parts of the screen lines
@@ -252,12 +262,14 @@ impl Default for Screen {
         Self {
             autosuggestion_is_truncated: Default::default(),
             scrolled: Default::default(),
+            right_prompt_on_every_line: Default::default(),
             outp: Outputter::stdoutput(),
             viewport_y: Default::default(),
             desired: Default::default(),
             actual: Default::default(),
             actual_left_prompt: Default::default(),
             last_right_prompt_width: Default::default(),
+            actual_status_line: Default::default(),
             soft_wrap_location: Default::default(),
             need_clear_lines: Default::default(),
             need_clear_screen: Default::default(),
@@ -274,6 +286,7 @@ impl Screen {
     ///
     /// \param left_prompt the prompt to prepend to the command line
     /// \param right_prompt the right prompt, or NULL if none
+    /// \param status_line the status line for the bottom of the terminal, or None to leave it as is
     /// \param commandline the command line
     /// \param explicit_len the number of characters of the "explicit" (non-autosuggestion) portion
     /// of the command line \param colors the colors to use for the commanad line \param indent the
@@ -285,6 +298,7 @@ impl Screen {
         curr_termsize: Termsize,
         left_prompt: &wstr,
         right_prompt: &wstr,
+        status_line: Option<&wstr>,
         commandline: &wstr,
         autosuggested_range: Range<usize>,
         mut colors: Vec<HighlightSpec>,
@@ -336,6 +350,22 @@ impl Screen {
             return;
         }
 
+        // The bottom line is kept for the status line, if any.
+        if let Some(status_line) = status_line {
+            self.with_synchronized_output(|zelf| {
+                zelf.update_status_line(screen_width, screen_height, status_line);
+            });
+        }
+        let (screen_height, curr_termsize) = if screen_height > 1 && has_status_line() {
+            let height = NonZeroU16::new(curr_termsize.height_u16().get() - 1).unwrap();
+            (
+                screen_height - 1,
+                Termsize::new(curr_termsize.width_u16(), height),
+            )
+        } else {
+            (screen_height, curr_termsize)
+        };
+
         // Compute a layout.
         let layout = compute_layout(
             screen_width,
@@ -514,6 +544,7 @@ impl Screen {
         ));
 
         pager.update_rendering(page_rendering);
+        self.desired.commandline_lines = self.desired.line_count();
         // Append pager_data (none if empty).
         self.desired.append_lines(&page_rendering.screen_data);
 
@@ -527,7 +558,8 @@ impl Screen {
                     scrolled_cursor.scroll_amount
                 });
 
-        self.with_buffered_output(|zelf| {
+        // The prompt and pager may span many lines, so make sure they show up all at once.
+        self.with_synchronized_output(|zelf| {
             zelf.update(vars, &layout.left_prompt, &layout.right_prompt);
         });
         self.save_status();
@@ -545,6 +577,7 @@ impl Screen {
 
         if repaint_prompt {
             self.actual_left_prompt = None;
+            self.actual_status_line = None;
             self.need_clear_screen = true;
         }
         self.actual.clear_lines();
@@ -589,6 +622,7 @@ impl Screen {
         let Some(viewport_y) = self.viewport_y else {
             return;
         };
+        let screen_height = screen_height.saturating_sub(usize::from(has_status_line()));
         let actual_lines = self.actual.line_count();
         let remaining_vertical_space = screen_height.saturating_sub(actual_lines);
         if viewport_y > remaining_vertical_space {
@@ -669,6 +703,8 @@ impl Screen {
         self.actual.cursor.y = 0;
         self.actual.clear_lines();
         self.actual_left_prompt = None;
+        // Commands may have cleared the screen or reset the scroll region.
+        self.actual_status_line = None;
         self.need_clear_lines = true;
 
         let _ = write_loop(&STDOUT_FILENO, &abandon_line_string(screen_width));
@@ -1004,6 +1040,69 @@ impl Screen {
         self.outp.borrow_mut().end_buffering();
     }
 
+    fn with_synchronized_output(&mut self, f: impl FnOnce(&mut Self)) {
+        self.outp.borrow_mut().begin_synchronized_update();
+        (f)(self);
+        self.outp.borrow_mut().end_synchronized_update();
+    }
+
+    /// Print the status line to the bottom line of the terminal, and keep other output from
+    /// scrolling over it. An empty status line gives the line back.
+    fn update_status_line(
+        &mut self,
+        screen_width: usize,
+        screen_height: usize,
+        status_line: &wstr,
+    ) {
+        let region_height = STATUS_LINE_HEIGHT.load(Ordering::Relaxed);
+        if status_line.is_empty() || screen_height < STATUS_LINE_MIN_SCREEN_HEIGHT {
+            screen_remove_status_line();
+            self.actual_status_line = None;
+            return;
+        }
+        let resized = region_height != screen_height;
+        if !resized && self.actual_status_line.as_deref() == Some(status_line) {
+            return;
+        }
+
+        let mut out = BufferedOutputter::new(self.outp);
+        if resized {
+            if region_height != 0 && region_height < screen_height {
+                // The old status line is now in the middle of the screen.
+                out.write_command(DecscSaveCursor);
+                out.write_command(CursorToLine(region_height - 1));
+                out.write_command(ClearToEndOfLine);
+                out.write_command(DecrcRestoreCursor);
+            }
+            // If the cursor is on the bottom line, scroll up to make room for the status line.
+            out.write_command(CursorDown);
+            out.write_command(CursorUp);
+            out.write_bytes(b"\r");
+            self.actual.cursor.x = 0;
+        }
+
+        // Don't write to the last column, to avoid wrapping.
+        let mut truncated = WString::new();
+        LAYOUT_CACHE_SHARED.lock().unwrap().calc_prompt_layout(
+            status_line,
+            Some(&mut truncated),
+            screen_width - 1,
+        );
+        // Setting the scroll region moves the cursor, and restoring it also restores the colors.
+        out.write_command(DecscSaveCursor);
+        out.write_command(DecstbmSetScrollRegion {
+            bottom: screen_height - 1,
+        });
+        out.write_command(CursorToLine(screen_height - 1));
+        out.write_command(ClearToEndOfLine);
+        out.write_wstr(&truncated);
+        out.write_command(DecrcRestoreCursor);
+        drop(out);
+
+        STATUS_LINE_HEIGHT.store(screen_height, Ordering::Relaxed);
+        self.actual_status_line = Some(status_line.to_owned());
+    }
+
     /// Update the screen to match the desired output.
     fn update(&mut self, vars: &dyn Environment, left_prompt: &wstr, right_prompt: &wstr) {
         // Helper function to set a resolved color, using the caching resolver.
@@ -1259,7 +1358,9 @@ impl Screen {
                 clear_remainder = false;
             } else if need_clear_lines && screen_width.is_some_and(|sw| current_width < sw) {
                 clear_remainder = true;
-            } else if right_prompt_width < self.last_right_prompt_width {
+            } else if right_prompt_width < self.last_right_prompt_width
+                || s_line(self, i).has_right_prompt
+            {
                 clear_remainder = true;
             } else {
                 // This wcswidth shows up strong in the profile.
@@ -1280,8 +1381,17 @@ impl Screen {
                 self.write_command(ClearToEndOfLine);
             }
 
-            // Output any rprompt if this is the first line.
-            if is_prompt_line && right_prompt_width > 0 {
+            // Output any rprompt if this is the prompt line. If it is repeated, also output it at
+            // the end of the other lines of the command line, unless they are wrapped or too long.
+            let is_repeated_right_prompt_line = self.right_prompt_on_every_line
+                && i > prompt_last_line
+                && i < self.desired.commandline_lines
+                && !o_line(self, i - 1).is_soft_wrapped
+                && screen_width.is_some_and(|sw| current_width + right_prompt_width < sw);
+            if is_repeated_right_prompt_line && right_prompt_width > 0 {
+                self.desired.line_mut(i).has_right_prompt = true;
+            }
+            if (is_prompt_line || is_repeated_right_prompt_line) && right_prompt_width > 0 {
                 // Move the cursor to the beginning of the line first to be independent of the width.
                 // This helps prevent staircase effects if fish and the terminal disagree.
                 self.r#move(0, i);
@@ -1333,6 +1443,33 @@ impl Screen {
     }
 }
 
+/// The status line is only shown if the terminal has at least this many lines.
+const STATUS_LINE_MIN_SCREEN_HEIGHT: usize = 3;
+
+/// The height of the terminal for which the bottom line was kept for the status line, or 0 if
+/// there is no status line.
+static STATUS_LINE_HEIGHT: AtomicUsize = AtomicUsize::new(0);
+
+fn has_status_line() -> bool {
+    STATUS_LINE_HEIGHT.load(Ordering::Relaxed) != 0
+}
+
+/// Clear the status line and let output scroll over the whole terminal again, e.g. before exiting.
+pub fn screen_remove_status_line() {
+    let region_height = STATUS_LINE_HEIGHT.swap(0, Ordering::Relaxed);
+    if region_height == 0 {
+        return;
+    }
+    let mut out = BufferedOutputter::new(Outputter::stdoutput());
+    out.write_command(DecscSaveCursor);
+    out.write_command(DecstbmResetScrollRegion);
+    if region_height <= termsize_last().height() {
+        out.write_command(CursorToLine(region_height - 1));
+        out.write_command(ClearToEndOfLine);
+    }
+    out.write_command(DecrcRestoreCursor);
+}
+
 /// Helper to get the mtime of stdout and stderr.
 pub fn mtime_stdout_stderr() -> (Option<SystemTime>, Option<SystemTime>) {
     let mtime_out = fstat(STDOUT_FILENO).and_then(|md| md.modified()).ok();
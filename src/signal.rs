@@ -17,8 +17,8 @@ use std::{
     mem::MaybeUninit,
     num::NonZeroI32,
     sync::{
-        LazyLock,
-        atomic::{AtomicI32, Ordering},
+        LazyLock, Mutex,
+        atomic::{AtomicI32, AtomicU64, Ordering},
     },
 };
 
@@ -120,7 +120,7 @@ extern "C" fn fish_signal_handler(
     set_errno(saved_errno);
 }
 
-/// Set all signal handlers to SIG_DFL.
+/// Set all signal handlers to SIG_DFL, except for signals ignored with `trap`.
 /// This is called after fork - it should be async signal safe.
 pub fn signal_reset_handlers() {
     let act = SigAction::new(SigHandler::SigDfl, SaFlags::empty(), SigSet::empty()).into();
@@ -129,6 +129,9 @@ pub fn signal_reset_handlers() {
         if data.signal == libc::SIGHUP && sighup_disposition() == libc::SIG_IGN {
             continue;
         }
+        if signal_is_ignored(data.signal) {
+            continue;
+        }
         unsafe {
             libc::sigaction(data.signal.code(), &act, std::ptr::null_mut());
         };
@@ -250,12 +253,17 @@ pub fn signal_set_handlers_once(interactive: bool) {
 
 /// Mark that a signal is being handled.
 pub fn signal_handle(sig: RawSignal) {
+    // An ignored signal stays ignored until its trap is reset.
+    if signal_is_ignored(sig) {
+        return;
+    }
+
     let sig = sig.code();
     let mut act: libc::sigaction = unsafe { std::mem::zeroed() };
 
     // These should always be handled.
+    // SIGQUIT is ignored by default, but gets our handler if a script listens for it.
     if sig == libc::SIGINT
-        || sig == libc::SIGQUIT
         || sig == libc::SIGTSTP
         || sig == libc::SIGTTIN
         || sig == libc::SIGTTOU
@@ -271,7 +279,80 @@ pub fn signal_handle(sig: RawSignal) {
     sigaction(sig, &act, std::ptr::null_mut());
 }
 
-pub static SIGNALS_TO_DEFAULT: LazyLock<libc::sigset_t> = LazyLock::new(|| {
+/// Signals ignored with `trap '' SIGNAL`, one bit per signal.
+/// As in other shells, child processes inherit these as ignored.
+/// This is read after fork, so it must be async signal safe.
+static IGNORED_SIGNALS: AtomicU64 = AtomicU64::new(0);
+
+/// The dispositions the ignored signals had before, to restore when they are no longer ignored.
+static SAVED_DISPOSITIONS: Mutex<Vec<(i32, libc::sigaction)>> = Mutex::new(Vec::new());
+
+fn ignored_signal_bit(sig: RawSignal) -> u64 {
+    u32::try_from(sig.code())
+        .ok()
+        .and_then(|code| 1_u64.checked_shl(code))
+        .unwrap_or(0)
+}
+
+/// Return whether `sig` is ignored through `trap`.
+pub fn signal_is_ignored(sig: RawSignal) -> bool {
+    IGNORED_SIGNALS.load(Ordering::Relaxed) & ignored_signal_bit(sig) != 0
+}
+
+/// Return the signals ignored through `trap`, in ascending order.
+pub fn signal_ignored_signals() -> Vec<RawSignal> {
+    let ignored = IGNORED_SIGNALS.load(Ordering::Relaxed);
+    (1..64)
+        .filter(|code| ignored & (1_u64 << code) != 0)
+        .map(RawSignal::new)
+        .collect()
+}
+
+/// Ignore `sig` in fish and in the processes it starts, or stop ignoring it.
+/// When it is no longer ignored, it gets back the disposition it had before, or our handler if
+/// there is an event handler for it now.
+pub fn signal_set_ignored(sig: RawSignal, ignored: bool) {
+    let bit = ignored_signal_bit(sig);
+    if bit == 0 {
+        return;
+    }
+    let code = sig.code();
+    let mut saved = SAVED_DISPOSITIONS.lock().expect("Mutex poisoned!");
+    if ignored {
+        if IGNORED_SIGNALS.fetch_or(bit, Ordering::Relaxed) & bit != 0 {
+            return;
+        }
+        let mut act: libc::sigaction = unsafe { std::mem::zeroed() };
+        let mut oact: libc::sigaction = unsafe { std::mem::zeroed() };
+        unsafe { libc::sigemptyset(&mut act.sa_mask) };
+        act.sa_sigaction = libc::SIG_IGN;
+        sigaction(code, &act, &mut oact);
+        saved.push((code, oact));
+    } else {
+        if IGNORED_SIGNALS.fetch_and(!bit, Ordering::Relaxed) & bit == 0 {
+            return;
+        }
+        let Some(idx) = saved.iter().position(|(saved_code, _)| *saved_code == code) else {
+            return;
+        };
+        let (_, oact) = saved.remove(idx);
+        sigaction(code, &oact, std::ptr::null_mut());
+        if is_signal_observed(code) {
+            signal_handle(sig);
+        }
+    }
+}
+
+/// Return the signals which child processes should get the default handler for.
+pub fn signals_to_default() -> libc::sigset_t {
+    let mut set = *SIGNALS_TO_DEFAULT;
+    for sig in signal_ignored_signals() {
+        unsafe { libc::sigdelset(&mut set, sig.code()) };
+    }
+    set
+}
+
+static SIGNALS_TO_DEFAULT: LazyLock<libc::sigset_t> = LazyLock::new(|| {
     let mut set = MaybeUninit::uninit();
     unsafe { libc::sigemptyset(set.as_mut_ptr()) };
     for data in SIGNAL_TABLE.iter() {
@@ -520,6 +601,11 @@ impl RawSignal {
     }
 }
 
+/// Return the names of all signals we know, like `SIGHUP`.
+pub fn signal_names() -> impl Iterator<Item = &'static wstr> {
+    SIGNAL_TABLE.iter().map(|entry| entry.name)
+}
+
 // Allow signals to be compared against i32.
 impl PartialEq<i32> for RawSignal {
     fn eq(&self, other: &i32) -> bool {
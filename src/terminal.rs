@@ -4,6 +4,7 @@ use crate::{
     screen::{is_dumb, only_grayscale},
     text_face::{ResettableStyle, TextFace, TextStyling, UnderlineStyle},
     threads::MainThread,
+    tty_handoff::{get_synchronized_output_capability, xtversion},
 };
 use bitflags::bitflags;
 use fish_color::{Color, Color24};
@@ -80,11 +81,15 @@ pub(crate) enum TerminalCommand<'a> {
     CursorLeft,
     CursorRight,
     CursorMove(CardinalDirection, usize),
+    CursorToLine(usize),
+    DecscSaveCursor,
+    DecrcRestoreCursor,
 
     // Commands related to querying (used mainly for backwards-incompatible features).
     QueryPrimaryDeviceAttribute,
     QueryXtversion,
     QueryXtgettcap(&'static str),
+    QueryDecrqm(u32),
 
     DecsetAlternateScreenBuffer,
     DecrstAlternateScreenBuffer,
@@ -102,19 +107,24 @@ pub(crate) enum TerminalCommand<'a> {
 
     // OSC sequences
     //
-    // Note that OSC 7 and OSC 52 are written from fish script, and OSC 8 is written in our
-    // man pages (via "man_show_urls").
+    // Note that OSC 7 is written from fish script, and OSC 8 is written in our man pages (via
+    // "man_show_urls").
     Osc0WindowTitle(&'a [WString]),
     Osc1TabTitle(&'a [WString]),
     Osc133PromptStart,
     Osc133PromptEnd,
     Osc133CommandStart(&'a wstr),
     Osc133CommandFinished { exit_status: libc::c_int },
+    Osc52CopyToClipboard(&'a [u8]),
+    QueryClipboard,
+    DesktopNotification { title: &'a wstr, body: &'a wstr },
 
     // Other terminal features
     QueryCursorPosition,
     QueryBackgroundColor,
     ScrollContentUp { lines: usize },
+    DecstbmSetScrollRegion { bottom: usize },
+    DecstbmResetScrollRegion,
 
     DecsetShowCursor,
     DecsetFocusReporting,
@@ -123,6 +133,10 @@ pub(crate) enum TerminalCommand<'a> {
     DecrstBracketedPaste,
     DecsetColorThemeReporting,
     DecrstColorThemeReporting,
+    DecsetMouseTracking,
+    DecrstMouseTracking,
+    DecsetSynchronizedOutput,
+    DecrstSynchronizedOutput,
 }
 
 #[derive(Debug, Clone)]
@@ -145,11 +159,22 @@ fn cursor_move(out: &mut Outputter, direction: CardinalDirection, steps: usize)
     true
 }
 
+/// Move the cursor to the start of the given line, counted from 0 at the top of the window.
+fn cursor_to_line(out: &mut Outputter, line: usize) -> bool {
+    write_to_output!(out, "\x1b[{};1H", line + 1);
+    true
+}
+
 fn query_xtgettcap(out: &mut Outputter, cap: &str) -> bool {
     write_to_output!(out, "\x1bP+q{}\x1b\\", DisplayAsHex(cap));
     true
 }
 
+fn query_decrqm(out: &mut Outputter, mode: u32) -> bool {
+    write_to_output!(out, "\x1b[?{}$p", mode);
+    true
+}
+
 struct DisplayAsHex<'a>(&'a str);
 
 impl<'a> std::fmt::Display for DisplayAsHex<'a> {
@@ -218,11 +243,90 @@ fn osc_133_command_finished(out: &mut Outputter, exit_status: libc::c_int) -> bo
     true
 }
 
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encode bytes as padded base64.
+pub(crate) fn base64_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let mut group = [0; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let bits = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64_ALPHABET[(bits >> (18 - 6 * i)) as usize & 0x3f]);
+            } else {
+                encoded.push(b'=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decode base64, with or without padding. Return None if the input is malformed.
+pub(crate) fn base64_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let data = data
+        .strip_suffix(b"==")
+        .or(data.strip_suffix(b"="))
+        .unwrap_or(data);
+    if data.len() % 4 == 1 {
+        return None;
+    }
+    let mut decoded = Vec::with_capacity(data.len() / 4 * 3 + 2);
+    for chunk in data.chunks(4) {
+        let mut bits = 0_u32;
+        for (i, &c) in chunk.iter().enumerate() {
+            let value = BASE64_ALPHABET.iter().position(|&a| a == c)?;
+            bits |= u32::try_from(value).unwrap() << (18 - 6 * i);
+        }
+        decoded.extend_from_slice(&bits.to_be_bytes()[1..chunk.len()]);
+    }
+    Some(decoded)
+}
+
+fn osc_52_copy_to_clipboard(out: &mut Outputter, data: &[u8]) -> bool {
+    out.write_bytes(b"\x1b]52;c;");
+    // Encode a multiple of three bytes at a time, so the pieces join up without padding.
+    for chunk in data.chunks(3 * 1024) {
+        out.write_bytes(&base64_encode(chunk));
+    }
+    out.write_bytes(b"\x07");
+    true
+}
+
+/// Ask the terminal to show a desktop notification, with OSC 9 in iTerm2 and OSC 777 elsewhere.
+fn desktop_notification(out: &mut Outputter, title: &wstr, body: &wstr) -> bool {
+    // Control characters would end the sequence.
+    let sanitize = |s: &wstr| -> WString { s.chars().filter(|c| !c.is_control()).collect() };
+    if xtversion().is_some_and(|version| version.starts_with("iTerm2")) {
+        out.write_bytes(b"\x1b]9;");
+    } else {
+        out.write_bytes(b"\x1b]777;notify;");
+        // The title is terminated by a semicolon.
+        let title: WString = sanitize(title)
+            .chars()
+            .map(|c| if c == ';' { ',' } else { c })
+            .collect();
+        out.write_wstr(&title);
+        out.write_bytes(b";");
+    }
+    out.write_wstr(&sanitize(body));
+    out.write_bytes(b"\x1b\\");
+    true
+}
+
 fn scroll_content_up(out: &mut Outputter, lines: usize) -> bool {
     write_to_output!(out, "\x1b[{}S", lines);
     true
 }
 
+/// Restrict scrolling to the lines above `bottom`, counted from 0 at the top of the window.
+fn set_scroll_region(out: &mut Outputter, bottom: usize) -> bool {
+    write_to_output!(out, "\x1b[1;{}r", bottom);
+    true
+}
+
 fn index_for_color(c: Color) -> u8 {
     if c.is_named() || !(get_color_support().contains(ColorSupport::TERM_256COLOR)) {
         return c.to_name_index();
@@ -237,6 +341,12 @@ pub struct Outputter {
     /// Count of how many outstanding begin_buffering() calls there are.
     buffer_count: u32,
 
+    /// Count of how many outstanding begin_synchronized_update() calls there are.
+    synchronized_update_count: u32,
+
+    /// Whether the outermost begin_synchronized_update() told the terminal about it.
+    synchronized_update_sent: bool,
+
     /// fd to output to, or -1 for none.
     fd: RawFd,
 
@@ -253,6 +363,8 @@ impl Outputter {
         Self {
             contents: Vec::new(),
             buffer_count: 0,
+            synchronized_update_count: 0,
+            synchronized_update_sent: false,
             fd,
             last: TextFace::terminal_default(),
         }
@@ -295,12 +407,16 @@ impl Outputter {
             CursorLeft => write(self, b"\x08"),
             CursorRight => write(self, b"\x1b[C"),
             CursorMove(direction, steps) => cursor_move(self, direction, steps),
+            CursorToLine(line) => cursor_to_line(self, line),
+            DecscSaveCursor => write(self, b"\x1b7"),
+            DecrcRestoreCursor => write(self, b"\x1b8"),
             QueryPrimaryDeviceAttribute => write(self, b"\x1b[0c"),
             QueryXtversion => write(self, b"\x1b[>0q"),
             QueryXtgettcap(cap) => query_xtgettcap(self, cap),
+            QueryDecrqm(mode) => query_decrqm(self, mode),
             DecsetAlternateScreenBuffer => write(self, b"\x1b[?1049h"),
             DecrstAlternateScreenBuffer => write(self, b"\x1b[?1049l"),
-            KittyKeyboardProgressiveEnhancementsEnable => write(self, b"\x1b[=5u"),
+            KittyKeyboardProgressiveEnhancementsEnable => write(self, b"\x1b[=7u"),
             KittyKeyboardProgressiveEnhancementsDisable => write(self, b"\x1b[=0u"),
             QueryKittyKeyboardProgressiveEnhancements => query_kitty_progressive_enhancements(self),
             ModifyOtherKeysEnable => write(self, b"\x1b[>4;1m"),
@@ -313,9 +429,14 @@ impl Outputter {
             Osc133PromptEnd => osc_133_prompt_end(self),
             Osc133CommandStart(command) => osc_133_command_start(self, command),
             Osc133CommandFinished { exit_status } => osc_133_command_finished(self, exit_status),
+            Osc52CopyToClipboard(data) => osc_52_copy_to_clipboard(self, data),
+            QueryClipboard => write(self, b"\x1b]52;c;?\x07"),
+            DesktopNotification { title, body } => desktop_notification(self, title, body),
             QueryCursorPosition => write(self, b"\x1b[6n"),
             QueryBackgroundColor => write(self, b"\x1b]11;?\x1b\\"),
             ScrollContentUp { lines } => scroll_content_up(self, lines),
+            DecstbmSetScrollRegion { bottom } => set_scroll_region(self, bottom),
+            DecstbmResetScrollRegion => write(self, b"\x1b[r"),
             DecsetShowCursor => write(self, b"\x1b[?25h"),
             DecsetFocusReporting => write(self, b"\x1b[?1004h"),
             DecrstFocusReporting => write(self, b"\x1b[?1004l"),
@@ -323,6 +444,11 @@ impl Outputter {
             DecrstBracketedPaste => write(self, b"\x1b[?2004l"),
             DecsetColorThemeReporting => write(self, b"\x1b[?2031h"),
             DecrstColorThemeReporting => write(self, b"\x1b[?2031l"),
+            // Report button presses and motion while a button is held, in SGR encoding.
+            DecsetMouseTracking => write(self, b"\x1b[?1002h\x1b[?1006h"),
+            DecrstMouseTracking => write(self, b"\x1b[?1002l\x1b[?1006l"),
+            DecsetSynchronizedOutput => write(self, b"\x1b[?2026h"),
+            DecrstSynchronizedOutput => write(self, b"\x1b[?2026l"),
         }
     }
 
@@ -564,6 +690,33 @@ impl Outputter {
         self.maybe_flush();
     }
 
+    /// Begins buffering, and asks the terminal to show everything up to the corresponding
+    /// end_synchronized_update() call at once, if it supports synchronized output. This way, a
+    /// repaint is never seen half-done, even if the terminal receives it in pieces.
+    pub fn begin_synchronized_update(&mut self) {
+        self.begin_buffering();
+        if self.synchronized_update_count == 0 {
+            self.synchronized_update_sent = get_synchronized_output_capability() == Some(true);
+            if self.synchronized_update_sent {
+                self.write_command(TerminalCommand::DecsetSynchronizedOutput);
+            }
+        }
+        self.synchronized_update_count += 1;
+    }
+
+    /// Balance a begin_synchronized_update() call.
+    pub fn end_synchronized_update(&mut self) {
+        assert!(
+            self.synchronized_update_count > 0,
+            "synchronized_update_count underflow"
+        );
+        self.synchronized_update_count -= 1;
+        if self.synchronized_update_count == 0 && self.synchronized_update_sent {
+            self.write_command(TerminalCommand::DecrstSynchronizedOutput);
+        }
+        self.end_buffering();
+    }
+
     pub fn write_bytes(&mut self, buf: &[u8]) {
         self.contents.extend_from_slice(buf);
         self.maybe_flush();
@@ -801,8 +954,38 @@ mod tests {
         Outputter,
         Paintable::{Background, Foreground, Underline},
         SgrTerminalCommand::ExitAttributeMode,
+        TerminalCommand::Osc52CopyToClipboard,
+        base64_decode, base64_encode,
     };
 
+    #[test]
+    fn test_base64() {
+        for (plain, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("\u{1F41F} fish", "8J+QnyBmaXNo"),
+        ] {
+            assert_eq!(base64_encode(plain.as_bytes()), encoded.as_bytes());
+            assert_eq!(base64_decode(encoded.as_bytes()).unwrap(), plain.as_bytes());
+        }
+        assert_eq!(base64_decode(b"Zm8").unwrap(), b"fo");
+        assert_eq!(base64_decode(b"Zm!v"), None);
+        assert_eq!(base64_decode(b"Zm9vY"), None);
+
+        // Long input is encoded in pieces that join up seamlessly.
+        let data = vec![0xfb; 10_000];
+        let mut outp = Outputter::new_buffering();
+        outp.write_command(Osc52CopyToClipboard(&data));
+        let contents = outp.contents();
+        let encoded = contents.strip_prefix(b"\x1b]52;c;").unwrap();
+        let encoded = encoded.strip_suffix(b"\x07").unwrap();
+        assert_eq!(encoded, base64_encode(&data));
+        assert_eq!(base64_decode(encoded).unwrap(), data);
+    }
+
     #[test]
     fn sgr_combining() {
         // No style, no content
@@ -0,0 +1,252 @@
+//! The optional terminal features we know about, as reported by the terminal itself rather than
+//! by terminfo.
+//!
+//! Most of them are queried on every startup, along with the other initial queries. Features we
+//! don't need before the first prompt are probed in a second round of queries. Its results are
+//! cached in the fish cache directory, keyed by $TERM and the terminal's XTVERSION response, so
+//! the extra round trip only happens once per terminal version.
+
+use crate::env::Environment;
+use crate::flog::{flog, flogf};
+use crate::fs::{PotentialUpdate, rewrite_via_temporary_file};
+use crate::path::path_get_cache;
+use crate::prelude::*;
+use crate::tty_handoff::{
+    get_clipboard_capability, get_kitty_keyboard_capability, get_scroll_content_up_capability,
+    get_synchronized_output_capability, get_truecolor_capability,
+    maybe_set_synchronized_output_capability, maybe_set_truecolor_capability, xtversion,
+};
+use fish_widestring::{bytes2wcstring, wcs2bytes, wcs2osstring};
+use std::io::{Read as _, Write as _};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Capability {
+    KittyKeyboard,
+    SynchronizedOutput,
+    Truecolor,
+    ScrollContentUp,
+    Clipboard,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 5] = [
+        Capability::KittyKeyboard,
+        Capability::SynchronizedOutput,
+        Capability::Truecolor,
+        Capability::ScrollContentUp,
+        Capability::Clipboard,
+    ];
+
+    /// The name used by `status terminal-capabilities` and in the cache file.
+    pub fn name(self) -> &'static wstr {
+        use Capability::*;
+        match self {
+            KittyKeyboard => L!("kitty-keyboard"),
+            SynchronizedOutput => L!("synchronized-output"),
+            Truecolor => L!("truecolor"),
+            ScrollContentUp => L!("scroll-content-up"),
+            Clipboard => L!("clipboard"),
+        }
+    }
+
+    pub fn from_name(name: &wstr) -> Option<Self> {
+        Self::ALL.into_iter().find(|cap| cap.name() == name)
+    }
+
+    /// Whether the terminal said it supports this. This is false until we have asked.
+    pub fn is_supported(self) -> bool {
+        use Capability::*;
+        let supported = match self {
+            KittyKeyboard => get_kitty_keyboard_capability(),
+            SynchronizedOutput => get_synchronized_output_capability(),
+            Truecolor => get_truecolor_capability(),
+            ScrollContentUp => get_scroll_content_up_capability(),
+            Clipboard => get_clipboard_capability(),
+        };
+        supported == Some(true)
+    }
+
+    /// Whether this is probed in the second round of queries, whose results are cached.
+    fn is_probed(self) -> bool {
+        matches!(self, Capability::SynchronizedOutput | Capability::Truecolor)
+    }
+}
+
+/// Identifies a terminal in the capability cache.
+#[derive(Debug, Eq, PartialEq)]
+pub struct CacheKey {
+    term: WString,
+    version: WString,
+}
+
+impl CacheKey {
+    /// Return None if the terminal did not tell us its version, since then we can't know whether
+    /// a cached result still applies.
+    pub fn new(vars: &dyn Environment) -> Option<Self> {
+        let version = xtversion().filter(|version| !version.is_empty())?;
+        let term = vars
+            .get_unless_empty(L!("TERM"))
+            .map(|term| term.as_list()[0].clone())
+            .unwrap_or_default();
+        // Both end up as fields of a tab-separated line.
+        let is_separator = |c| c == '\t' || c == '\n';
+        if term.chars().any(is_separator) || version.chars().any(is_separator) {
+            return None;
+        }
+        Some(Self {
+            term,
+            version: version.to_owned(),
+        })
+    }
+}
+
+/// How many terminals we remember. The oldest entries are dropped first.
+const MAX_CACHE_ENTRIES: usize = 32;
+
+fn cache_path() -> Option<WString> {
+    let cache_dir = path_get_cache();
+    if !cache_dir.ok {
+        return None;
+    }
+    Some(cache_dir.path.to_owned() + L!("/terminal_capabilities"))
+}
+
+/// Parse a line of the cache file, which consists of $TERM, the terminal version and the
+/// space-separated names of the supported capabilities, separated by tabs.
+fn parse_cache_line(line: &wstr) -> Option<(CacheKey, Vec<Capability>)> {
+    let mut fields = line.split('\t');
+    let term = fields.next()?.to_owned();
+    let version = fields.next()?.to_owned();
+    let capabilities = fields.next()?;
+    if fields.next().is_some() || version.is_empty() {
+        return None;
+    }
+    let capabilities = capabilities
+        .split(' ')
+        .filter(|name| !name.is_empty())
+        // Ignore names we don't know, they might come from a different version of fish.
+        .filter_map(Capability::from_name)
+        .collect();
+    Some((CacheKey { term, version }, capabilities))
+}
+
+fn format_cache_line(key: &CacheKey, capabilities: &[Capability]) -> WString {
+    let mut line = key.term.clone();
+    line.push('\t');
+    line.push_utfstr(&key.version);
+    line.push('\t');
+    for (i, capability) in capabilities.iter().enumerate() {
+        if i > 0 {
+            line.push(' ');
+        }
+        line.push_utfstr(capability.name());
+    }
+    line
+}
+
+fn parse_cache(contents: &[u8]) -> Vec<(CacheKey, Vec<Capability>)> {
+    let contents = bytes2wcstring(contents);
+    contents.split('\n').filter_map(parse_cache_line).collect()
+}
+
+/// Use the cached results of an earlier probe for this terminal, if there are any.
+/// Return false if the terminal needs to be probed.
+pub fn load_probed_capabilities(key: &CacheKey) -> bool {
+    let Some(path) = cache_path() else {
+        return false;
+    };
+    let Ok(contents) = std::fs::read(wcs2osstring(&path)) else {
+        return false;
+    };
+    let Some((_key, capabilities)) = parse_cache(&contents)
+        .into_iter()
+        .find(|(entry_key, _)| entry_key == key)
+    else {
+        return false;
+    };
+    flogf!(
+        reader,
+        "Using cached capabilities for terminal '%s'",
+        &key.version
+    );
+    for capability in capabilities {
+        match capability {
+            Capability::SynchronizedOutput => maybe_set_synchronized_output_capability(),
+            Capability::Truecolor => maybe_set_truecolor_capability(),
+            _ => (),
+        }
+    }
+    true
+}
+
+/// Remember the results of probing this terminal, replacing any earlier entry for it.
+pub fn save_probed_capabilities(key: &CacheKey) {
+    let Some(path) = cache_path() else {
+        return;
+    };
+    let supported: Vec<Capability> = Capability::ALL
+        .into_iter()
+        .filter(|capability| capability.is_probed() && capability.is_supported())
+        .collect();
+    let rewrite = |old_file: &std::fs::File, tmp_file: &mut std::fs::File| {
+        let mut contents = vec![];
+        let mut old_file = old_file;
+        old_file.read_to_end(&mut contents)?;
+        let mut entries = parse_cache(&contents);
+        entries.retain(|(entry_key, _)| entry_key != key);
+        let excess = (entries.len() + 1).saturating_sub(MAX_CACHE_ENTRIES);
+        let mut new_contents = WString::new();
+        for (entry_key, capabilities) in &entries[excess..] {
+            new_contents.push_utfstr(&format_cache_line(entry_key, capabilities));
+            new_contents.push('\n');
+        }
+        new_contents.push_utfstr(&format_cache_line(key, &supported));
+        new_contents.push('\n');
+        tmp_file.write_all(&wcs2bytes(&new_contents))?;
+        Ok(PotentialUpdate {
+            do_save: true,
+            data: (),
+        })
+    };
+    if let Err(err) = rewrite_via_temporary_file(&path, rewrite) {
+        flog!(reader, "Failed to save terminal capabilities:", err);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CacheKey, Capability, format_cache_line, parse_cache};
+    use crate::prelude::*;
+    use fish_widestring::wcs2bytes;
+
+    #[test]
+    fn test_capability_cache_format() {
+        let key = CacheKey {
+            term: L!("xterm-256color").to_owned(),
+            version: L!("foot(1.20.0)").to_owned(),
+        };
+        let line = format_cache_line(
+            &key,
+            &[Capability::SynchronizedOutput, Capability::Truecolor],
+        );
+        assert_eq!(
+            line,
+            L!("xterm-256color\tfoot(1.20.0)\tsynchronized-output truecolor")
+        );
+
+        let mut contents = wcs2bytes(&line);
+        contents.extend_from_slice(b"\nxterm\tXTerm(390)\t\n");
+        // Malformed lines and unknown capabilities are skipped.
+        contents.extend_from_slice(b"garbage\nxterm\tXTerm(400)\tfancy truecolor\n");
+        let entries = parse_cache(&contents);
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].0, key);
+        assert_eq!(
+            entries[0].1,
+            [Capability::SynchronizedOutput, Capability::Truecolor]
+        );
+        assert_eq!(entries[1].0.version, L!("XTerm(390)"));
+        assert!(entries[1].1.is_empty());
+        assert_eq!(entries[2].1, [Capability::Truecolor]);
+    }
+}
@@ -42,6 +42,11 @@ enum Function {
     Fn1(fn(f64) -> f64),
     Fn2(fn(f64, f64) -> f64),
     FnN(fn(&[f64]) -> f64),
+    /// A user-defined function, as an index into the definitions of the current scope.
+    User {
+        index: usize,
+        arity: usize,
+    },
 }
 
 impl Debug for Function {
@@ -51,6 +56,13 @@ impl Debug for Function {
             Function::Fn1(_) => "Fn1",
             Function::Fn2(_) => "Fn2",
             Function::FnN(_) => "FnN",
+            Function::User { index, arity } => {
+                return f
+                    .debug_struct("Function::User")
+                    .field("index", index)
+                    .field("arity", arity)
+                    .finish();
+            }
         };
 
         write!(f, "Function::{variant}(_)")
@@ -64,6 +76,7 @@ impl Function {
             Function::Fn1(_) => Some(1),
             Function::Fn2(_) => Some(2),
             Function::FnN(_) => None,
+            Function::User { arity, .. } => Some(*arity),
         }
     }
 
@@ -89,6 +102,8 @@ pub enum ErrorKind {
     LogicalOperator,
     DivByZero,
     NumberTooLarge,
+    InvalidDefinition,
+    BuiltinRedefinition,
     Unknown,
 }
 
@@ -106,6 +121,12 @@ impl ErrorKind {
             }
             ErrorKind::DivByZero => wgettext!("Division by zero"),
             ErrorKind::NumberTooLarge => wgettext!("Number is too large"),
+            ErrorKind::InvalidDefinition => {
+                wgettext!(
+                    "Invalid definition, expected NAME=EXPRESSION or NAME(PARAMETERS)=EXPRESSION"
+                )
+            }
+            ErrorKind::BuiltinRedefinition => wgettext!("Builtin functions cannot be redefined"),
             ErrorKind::Unknown => wgettext!("Expression is bogus"),
         }
     }
@@ -153,11 +174,37 @@ enum Token {
     Infix(Operator),
 }
 
+/// A user-defined function or constant. Constants are functions without parameters.
+#[derive(Debug)]
+struct Definition {
+    name: WString,
+    params: Vec<WString>,
+    body: WString,
+}
+
+/// The functions and constants defined via `math --define` or `$fish_math_functions`.
+/// A definition can only refer to the ones that were added before it, so there is no recursion.
+#[derive(Debug, Default)]
+pub struct Definitions {
+    definitions: Vec<Definition>,
+}
+
+/// The names an expression can refer to in addition to the builtin functions.
+#[derive(Clone, Copy)]
+struct Scope<'s> {
+    definitions: &'s [Definition],
+    /// The arguments of the user-defined function being evaluated.
+    params: &'s [(&'s wstr, f64)],
+}
+
 struct State<'s> {
     start: &'s wstr,
     pos: usize,
+    /// Where the current token starts.
+    token_start: usize,
     current: Token,
     error: Option<Error>,
+    scope: Scope<'s>,
 }
 
 fn bitwise_op(a: f64, b: f64, f: fn(u64, u64) -> u64) -> f64 {
@@ -310,6 +357,48 @@ const BUILTINS: &[(&wstr, Function)] = &[
 
 assert_sorted_by_name!(BUILTINS, 0);
 
+const KIB: f64 = 1024.0;
+const MINUTE: f64 = 60.0;
+const HOUR: f64 = 60.0 * MINUTE;
+const DAY: f64 = 24.0 * HOUR;
+
+/// Units that can directly follow a number, like `3GiB` or `2h`.
+/// Sizes are converted to bytes and durations to seconds.
+const UNITS: &[(&wstr, f64)] = &[
+    // must be in alphabetical order
+    (L!("B"), 1.0),
+    (L!("EB"), 1e18),
+    (L!("EiB"), KIB * KIB * KIB * KIB * KIB * KIB),
+    (L!("GB"), 1e9),
+    (L!("GiB"), KIB * KIB * KIB),
+    (L!("KiB"), KIB),
+    (L!("MB"), 1e6),
+    (L!("MiB"), KIB * KIB),
+    (L!("PB"), 1e15),
+    (L!("PiB"), KIB * KIB * KIB * KIB * KIB),
+    (L!("TB"), 1e12),
+    (L!("TiB"), KIB * KIB * KIB * KIB),
+    (L!("d"), DAY),
+    (L!("h"), HOUR),
+    (L!("kB"), 1e3),
+    (L!("m"), MINUTE),
+    (L!("min"), MINUTE),
+    (L!("ms"), 1e-3),
+    (L!("s"), 1.0),
+    (L!("w"), 7.0 * DAY),
+];
+
+assert_sorted_by_name!(UNITS, 0);
+
+fn find_unit(name: &[char]) -> Option<f64> {
+    let name = wstr::from_char_slice(name);
+    let idx = UNITS
+        .binary_search_by_key(&name, |(name, _factor)| name)
+        .ok()?;
+
+    Some(UNITS[idx].1)
+}
+
 fn find_builtin(name: &wstr) -> Option<Function> {
     let idx = BUILTINS
         .binary_search_by_key(&name, |(name, _expr)| name)
@@ -318,13 +407,131 @@ fn find_builtin(name: &wstr) -> Option<Function> {
     Some(BUILTINS[idx].1)
 }
 
+impl Scope<'_> {
+    fn find(&self, name: &wstr) -> Option<Function> {
+        if let Some(&(_, value)) = self.params.iter().find(|(param, _)| *param == name) {
+            return Some(Function::Constant(value));
+        }
+        // Later definitions take precedence.
+        if let Some(index) = self.definitions.iter().rposition(|def| def.name == name) {
+            let arity = self.definitions[index].params.len();
+            return Some(Function::User { index, arity });
+        }
+        find_builtin(name)
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic()
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphabetic() || c.is_ascii_digit() || c == '_'
+}
+
+impl Definitions {
+    /// Add a definition of the form `NAME=EXPRESSION` or `NAME(PARAM, ...)=EXPRESSION`.
+    /// The expression is checked for syntax errors right away, positions in the returned error
+    /// refer to `definition`.
+    pub fn add(&mut self, definition: &wstr) -> Result<(), Error> {
+        let chars = definition.as_char_slice();
+        let invalid = |position: usize| Error {
+            kind: ErrorKind::InvalidDefinition,
+            position,
+            len: 0,
+        };
+        let skip_whitespace = |pos: usize| {
+            pos + chars[pos..]
+                .iter()
+                .take_while(|c| c.is_whitespace())
+                .count()
+        };
+        let ident = |pos: usize| {
+            if !chars.get(pos).copied().is_some_and(is_ident_start) {
+                return None;
+            }
+            let len = chars[pos..]
+                .iter()
+                .position(|&c| !is_ident_char(c))
+                .unwrap_or(chars.len() - pos);
+            Some(&definition[pos..pos + len])
+        };
+
+        let name_start = skip_whitespace(0);
+        let name = ident(name_start).ok_or_else(|| invalid(name_start))?;
+        if find_builtin(name).is_some() {
+            return Err(Error {
+                kind: ErrorKind::BuiltinRedefinition,
+                position: name_start,
+                len: name.len(),
+            });
+        }
+
+        let mut pos = skip_whitespace(name_start + name.len());
+        let mut params: Vec<WString> = vec![];
+        if chars.get(pos) == Some(&'(') {
+            pos = skip_whitespace(pos + 1);
+            if chars.get(pos) != Some(&')') {
+                loop {
+                    let param = ident(pos).ok_or_else(|| invalid(pos))?;
+                    if params.iter().any(|p| p == param) {
+                        return Err(Error {
+                            len: param.len(),
+                            ..invalid(pos)
+                        });
+                    }
+                    params.push(param.to_owned());
+                    pos = skip_whitespace(pos + param.len());
+                    match chars.get(pos) {
+                        Some(',') => pos = skip_whitespace(pos + 1),
+                        Some(')') => break,
+                        _ => return Err(invalid(pos)),
+                    }
+                }
+            }
+            pos = skip_whitespace(pos + 1);
+        }
+        if chars.get(pos) != Some(&'=') {
+            return Err(invalid(pos));
+        }
+        let body_start = pos + 1;
+        let body = &definition[body_start..];
+
+        // Check the body by evaluating it with arbitrary arguments.
+        // Division by zero depends on the arguments, so it is only an error once called.
+        let args: Vec<(&wstr, f64)> = params.iter().map(|p| (p.as_utfstr(), 1.0)).collect();
+        let scope = Scope {
+            definitions: &self.definitions,
+            params: &args,
+        };
+        match interp(body, scope) {
+            Err(err) if err.kind != ErrorKind::DivByZero => {
+                return Err(Error {
+                    position: body_start + err.position,
+                    ..err
+                });
+            }
+            _ => (),
+        }
+
+        self.definitions.push(Definition {
+            name: name.to_owned(),
+            params,
+            body: body.to_owned(),
+        });
+        Ok(())
+    }
+}
+
 impl<'s> State<'s> {
-    pub fn new(input: &'s wstr) -> Self {
+    fn new(input: &'s wstr, scope: Scope<'s>) -> Self {
         let mut state = Self {
             start: input,
             pos: 0,
+            token_start: 0,
             current: Token::End,
             error: None,
+            scope,
         };
         state.next_token();
         state
@@ -350,6 +557,43 @@ impl<'s> State<'s> {
         self.expr()
     }
 
+    /// Call `f`, whose name is at `call_site`, which is where errors in a user-defined function
+    /// are reported.
+    fn call(&mut self, f: Function, args: &[f64], call_site: (usize, usize)) -> f64 {
+        let Function::User { index, .. } = f else {
+            return f.call(args);
+        };
+        let definition = &self.scope.definitions[index];
+        let params: Vec<(&wstr, f64)> = definition
+            .params
+            .iter()
+            .map(|p| p.as_utfstr())
+            .zip(args.iter().copied())
+            .collect();
+        let scope = Scope {
+            definitions: &self.scope.definitions[..index],
+            params: &params,
+        };
+        match interp(&definition.body, scope) {
+            Ok(result) => result,
+            Err(err) => {
+                if self.no_specific_error() {
+                    self.set_error(err.kind, Some(call_site));
+                }
+                f64::NAN
+            }
+        }
+    }
+
+    /// Whether the previous token ends an operand, so an operator is expected next.
+    fn after_operand(&self) -> bool {
+        match self.current {
+            Token::Number(_) | Token::Close => true,
+            Token::Function(f) => f.arity() == Some(0),
+            _ => false,
+        }
+    }
+
     fn set_error(&mut self, kind: ErrorKind, pos_len: Option<(usize, usize)>) {
         self.current = Token::Error;
         let (position, len) = pos_len.unwrap_or((self.pos, 0));
@@ -383,7 +627,19 @@ impl<'s> State<'s> {
         if matches!(next.first(), Some('0'..='9') | Some('.')) {
             let mut consumed = 0;
             match wcstod_underscores(*next, &mut consumed) {
-                Ok(num) => Some((consumed, Some(Token::Number(num)))),
+                Ok(num) => {
+                    // A unit directly following the number scales it.
+                    let unit_len = next[consumed..]
+                        .iter()
+                        .take_while(|c| c.is_alphabetic())
+                        .count();
+                    if unit_len > 0 {
+                        if let Some(factor) = find_unit(&next[consumed..consumed + unit_len]) {
+                            return Some((consumed + unit_len, Some(Token::Number(num * factor))));
+                        }
+                    }
+                    Some((consumed, Some(Token::Number(num))))
+                }
                 Err(wcstodError::InvalidChar) => {
                     self.set_error(ErrorKind::Unknown, Some((self.pos + consumed, 1)));
                     Some((consumed, Some(Token::Error)))
@@ -400,19 +656,26 @@ impl<'s> State<'s> {
         } else {
             // Look for a function call.
             // But not when it's an "x" followed by whitespace
-            // - that's the alternative multiplication operator.
+            // - that's the alternative multiplication operator,
+            // unless a parameter is called "x" and we are expecting an operand.
             // We look for alphabetic here even tho all our function names are ASCII,
             // in order to give a nicer error.
-            if next.first()?.is_alphabetic()
-                && !(*next.first()? == 'x' && next.len() > 1 && next[1].is_whitespace())
+            let x_is_param = || {
+                self.scope.params.iter().any(|(param, _)| *param == "x") && !self.after_operand()
+            };
+            if is_ident_start(*next.first()?)
+                && !(*next.first()? == 'x'
+                    && next.len() > 1
+                    && next[1].is_whitespace()
+                    && !x_is_param())
             {
                 let ident_len = next
                     .iter()
-                    .position(|&c| !(c.is_alphabetic() || c.is_ascii_digit() || c == '_'))
+                    .position(|&c| !is_ident_char(c))
                     .unwrap_or(next.len());
 
                 let ident = &next[..ident_len];
-                if let Some(var) = find_builtin(wstr::from_char_slice(ident)) {
+                if let Some(var) = self.scope.find(wstr::from_char_slice(ident)) {
                     return Some((ident_len, Some(Token::Function(var))));
                 } else if self.no_specific_error() {
                     // Our error is more specific, so it takes precedence.
@@ -450,6 +713,7 @@ impl<'s> State<'s> {
 
     fn next_token(&mut self) {
         self.current = loop {
+            self.token_start = self.pos;
             let Some((consumed, token)) = self.get_token() else {
                 break Token::End;
             };
@@ -496,6 +760,7 @@ impl<'s> State<'s> {
                 n
             }
             Token::Function(f) => {
+                let call_site = (self.token_start, self.pos - self.token_start);
                 self.next_token();
                 let have_open = matches!(self.current, Token::Open);
                 if have_open {
@@ -516,6 +781,7 @@ impl<'s> State<'s> {
 
                     return match f {
                         Function::Constant(n) => n,
+                        Function::User { .. } => self.call(f, &[], call_site),
                         _ => unreachable!("unhandled function type with arity 0"),
                     };
                 }
@@ -537,12 +803,12 @@ impl<'s> State<'s> {
 
                 if f.arity().is_none() || f.arity() == Some(i + 1) {
                     if !have_open {
-                        return f.call(&parameters);
+                        return self.call(f, &parameters, call_site);
                     }
                     if let Token::Close = self.current {
                         // We have an opening and a closing paren, consume the closing one and done.
                         self.next_token();
-                        return f.call(&parameters);
+                        return self.call(f, &parameters, call_site);
                     }
                     if !matches!(self.current, Token::Error) {
                         // If we had the right number of arguments, we're missing a closing paren.
@@ -686,8 +952,8 @@ impl<'s> State<'s> {
     }
 }
 
-pub fn te_interp(expression: &wstr) -> Result<f64, Error> {
-    let mut s = State::new(expression);
+fn interp(expression: &wstr, scope: Scope) -> Result<f64, Error> {
+    let mut s = State::new(expression, scope);
     let ret = s.eval();
 
     match s.error() {
@@ -695,3 +961,11 @@ pub fn te_interp(expression: &wstr) -> Result<f64, Error> {
         Err(e) => Err(e),
     }
 }
+
+pub fn te_interp(expression: &wstr, definitions: &Definitions) -> Result<f64, Error> {
+    let scope = Scope {
+        definitions: &definitions.definitions,
+        params: &[],
+    };
+    interp(expression, scope)
+}
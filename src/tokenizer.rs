@@ -7,6 +7,7 @@ use crate::parse_constants::SOURCE_OFFSET_INVALID;
 use crate::parser_keywords::parser_keywords_is_subcommand;
 use crate::prelude::*;
 use crate::redirection::RedirectionMode;
+use fish_common::{UnescapeStringStyle, unescape_string};
 use fish_feature_flags::{FeatureFlag, feature_test};
 use libc::{STDIN_FILENO, STDOUT_FILENO};
 use nix::fcntl::OFlag;
@@ -55,6 +56,7 @@ pub enum TokenizerError {
     UnterminatedBrace,
     ExpectedPcloseFoundBclose,
     ExpectedBcloseFoundPclose,
+    UnterminatedHeredoc,
 }
 
 #[derive(Debug)]
@@ -74,6 +76,10 @@ pub struct Tok {
 
     pub is_unterminated_brace: bool,
 
+    // If this is the delimiter of a here-document, the range of its body. This is on the lines
+    // after the redirection, and ends before the line with the delimiter.
+    pub heredoc_body: Option<Range<u32>>,
+
     // The type of the token.
     pub type_: TokenType,
 }
@@ -180,6 +186,9 @@ impl From<TokenizerError> for &'static wstr {
             TokenizerError::ExpectedBcloseFoundPclose => {
                 wgettext!("Unexpected ')' found, expecting '}'")
             }
+            TokenizerError::UnterminatedHeredoc => {
+                wgettext!("Unexpected end of string, here-document is not terminated")
+            }
         }
     }
 }
@@ -200,6 +209,7 @@ impl Tok {
             error_length: 0,
             error: TokenizerError::None,
             is_unterminated_brace: false,
+            heredoc_body: None,
             type_: r#type,
         }
     }
@@ -240,6 +250,11 @@ impl Tok {
     pub fn set_error_length(&mut self, value: usize) {
         self.error_length = value.try_into().unwrap();
     }
+    pub fn heredoc_body(&self) -> Option<Range<usize>> {
+        self.heredoc_body
+            .as_ref()
+            .map(|body| body.start as usize..body.end as usize)
+    }
 }
 
 struct BraceStatementParser {
@@ -269,6 +284,12 @@ pub struct Tokenizer<'c> {
     continue_line_after_comment: bool,
     /// Called on every quote change.
     on_quote_toggle: Option<&'c mut dyn FnMut(usize)>,
+    /// Whether to look for the bodies of here-documents.
+    heredocs: bool,
+    /// Whether the last token was a here-document redirection.
+    expecting_heredoc_delimiter: bool,
+    /// Where the next line continues after the here-documents on the current line.
+    heredoc_resume: Option<usize>,
 }
 
 impl<'c> Tokenizer<'c> {
@@ -310,6 +331,9 @@ impl<'c> Tokenizer<'c> {
             continue_after_error: flags & TOK_CONTINUE_AFTER_ERROR,
             continue_line_after_comment: false,
             on_quote_toggle,
+            heredocs: true,
+            expecting_heredoc_delimiter: false,
+            heredoc_resume: None,
         }
     }
 }
@@ -387,6 +411,12 @@ impl<'c> Iterator for Tokenizer<'c> {
                 result.length = 1;
                 self.token_cursor += 1;
                 at_cmd_pos = true;
+                // The next line starts after the bodies of the here-documents on this one.
+                if this_char == '\n' {
+                    if let Some(resume) = self.heredoc_resume.take() {
+                        self.token_cursor = resume;
+                    }
+                }
                 // Hack: when we get a newline, swallow as many as we can. This compresses multiple
                 // subsequent newlines into a single one.
                 if !self.show_blank_lines {
@@ -569,7 +599,11 @@ impl<'c> Iterator for Tokenizer<'c> {
         if let Some(parser) = self.brace_statement_parser.as_mut() {
             parser.at_command_position = at_cmd_pos;
         }
-        token
+        if self.heredocs {
+            self.find_heredoc_body(token)
+        } else {
+            token
+        }
     }
 }
 
@@ -626,11 +660,123 @@ impl<'c> Tokenizer<'c> {
             error_length: error_len as u32,
             error: error_type,
             is_unterminated_brace: false,
+            heredoc_body: None,
             type_: TokenType::Error,
         }
     }
 }
 
+impl<'c> Tokenizer<'c> {
+    /// If `token` is the delimiter of a here-document, find the body of the here-document. It
+    /// starts on the line after the redirection, or after the previous here-document on the same
+    /// line, and the tokens there are skipped when that line ends.
+    fn find_heredoc_body(&mut self, token: Option<Tok>) -> Option<Tok> {
+        let mut token = token?;
+        let expecting = std::mem::take(&mut self.expecting_heredoc_delimiter);
+        match token.type_ {
+            TokenType::Redirect => {
+                self.expecting_heredoc_delimiter = PipeOrRedir::try_from(self.text_of(&token))
+                    .is_ok_and(|redir| redir.mode == RedirectionMode::HereDoc);
+            }
+            TokenType::String if expecting => {
+                let (delimiter, _) = heredoc_delimiter(self.text_of(&token));
+                let body_start = self
+                    .heredoc_resume
+                    .unwrap_or_else(|| self.end_of_line(self.token_cursor));
+                let (body_end, resume) = match heredoc_end(self.start, body_start, &delimiter) {
+                    Some(end) => end,
+                    None if self.accept_unfinished => (self.start.len(), self.start.len()),
+                    None => {
+                        return Some(self.call_error(
+                            TokenizerError::UnterminatedHeredoc,
+                            token.offset(),
+                            token.offset(),
+                            None,
+                            token.length(),
+                        ));
+                    }
+                };
+                token.heredoc_body = Some(body_start as u32..body_end as u32);
+                self.heredoc_resume = Some(resume);
+            }
+            _ => {}
+        }
+        Some(token)
+    }
+
+    /// Returns the offset after the newline which ends the line at `pos`, or the end of the source.
+    fn end_of_line(&self, pos: usize) -> usize {
+        // Newlines may be quoted, so look at the tokens.
+        let mut tok = Tokenizer::new(
+            &self.start[pos..],
+            TOK_ACCEPT_UNFINISHED | TOK_ARGUMENT_LIST | TOK_CONTINUE_AFTER_ERROR,
+        );
+        tok.heredocs = false;
+        while let Some(token) = tok.next() {
+            if token.type_ == TokenType::End && tok.text_of(&token) == "\n" {
+                return pos + token.end();
+            }
+        }
+        self.start.len()
+    }
+}
+
+/// Returns the delimiter of a here-document from the text of its token, and whether the body is
+/// taken literally, without expansions, which is the case if the delimiter is quoted or escaped.
+pub fn heredoc_delimiter(token: &wstr) -> (WString, bool) {
+    let literal = token.chars().any(|c| matches!(c, '\'' | '"' | '\\'));
+    let delimiter =
+        unescape_string(token, UnescapeStringStyle::default()).unwrap_or_else(|| token.to_owned());
+    (delimiter, literal)
+}
+
+/// Find the line consisting of `delimiter`, which may be indented, from `start` on. Returns the
+/// offset of that line and the offset after it.
+fn heredoc_end(src: &wstr, start: usize, delimiter: &wstr) -> Option<(usize, usize)> {
+    let mut line_start = start;
+    while line_start < src.len() {
+        let line_end = src[line_start..]
+            .find_char('\n')
+            .map_or(src.len(), |len| line_start + len);
+        let line = &src[line_start..line_end];
+        let indent = line.chars().take_while(|&c| c == ' ' || c == '\t').count();
+        if line[indent..] == *delimiter {
+            return Some((line_start, (line_end + 1).min(src.len())));
+        }
+        line_start = line_end + 1;
+    }
+    None
+}
+
+/// Returns the end of the line with the delimiter after the here-document body `body`, not
+/// including the newline.
+pub fn heredoc_delimiter_line_end(src: &wstr, body: Range<usize>) -> usize {
+    src[body.end..]
+        .find_char('\n')
+        .map_or(src.len(), |len| body.end + len)
+}
+
+/// Returns the contents of the here-document whose body is `body`. The indentation of the line
+/// with the delimiter is removed from every line.
+pub fn heredoc_contents(src: &wstr, body: Range<usize>) -> WString {
+    let indent_len = src[body.end..]
+        .chars()
+        .take_while(|&c| c == ' ' || c == '\t')
+        .count();
+    let indent = &src[body.end..body.end + indent_len];
+    let mut contents = WString::new();
+    if body.is_empty() {
+        return contents;
+    }
+    // The body is either terminated by the delimiter line, or runs to the end of the source.
+    let text = &src[body.start..body.end - usize::from(src.char_at(body.end - 1) == '\n')];
+    for line in text.split('\n') {
+        contents.push_utfstr(line.strip_prefix(indent).unwrap_or(line));
+        contents.push('\n');
+    }
+    contents
+}
+
 impl<'c> Tokenizer<'c> {
     /// Read the next token as a string.
     fn read_string(&mut self) -> Tok {
@@ -984,6 +1130,8 @@ impl TryFrom<&wstr> for PipeOrRedir {
     ///     cmd <&2          fd redirection with no explicit src fd (stdin is used)
     ///     cmd 3<&0         fd redirection with an explicit src fd
     ///     cmd &> file      redirection with stderr merge
+    ///     cmd <<EOF        here-document, whose body follows on the next lines
+    ///     cmd <<< text     here-string
     ///     cmd ^ file       caret (stderr) redirection, perhaps disabled via feature flags
     ///     cmd ^^ file      caret (stderr) redirection, perhaps disabled via feature flags
     /// ```
@@ -1084,6 +1232,13 @@ impl TryFrom<&wstr> for PipeOrRedir {
                 consume(&mut cursor, '<');
                 if try_consume(&mut cursor, '&') {
                     result.mode = RedirectionMode::Fd;
+                } else if try_consume(&mut cursor, '<') {
+                    // <<EOF is a here-document, <<< text a here-string.
+                    result.mode = if try_consume(&mut cursor, '<') {
+                        RedirectionMode::HereString
+                    } else {
+                        RedirectionMode::HereDoc
+                    };
                 } else if try_consume(&mut cursor, '?') {
                     // <? foo try-input redirection (uses /dev/null if file can't be used).
                     result.mode = RedirectionMode::TryInput;
@@ -1199,7 +1354,10 @@ pub fn variable_assignment_equals_pos(txt: &wstr) -> Option<usize> {
 
 #[cfg(test)]
 mod tests {
-    use super::{PipeOrRedir, TokFlags, TokenType, Tokenizer, TokenizerError};
+    use super::{
+        PipeOrRedir, TOK_ACCEPT_UNFINISHED, TokFlags, TokenType, Tokenizer, TokenizerError,
+        heredoc_contents, heredoc_delimiter,
+    };
     use crate::prelude::*;
     use crate::redirection::RedirectionMode;
     use libc::{STDERR_FILENO, STDOUT_FILENO};
@@ -1393,5 +1551,59 @@ mod tests {
         assert_eq!(get_redir_mode!("2>&3"), RedirectionMode::Fd);
         assert_eq!(get_redir_mode!("3<&0"), RedirectionMode::Fd);
         assert_eq!(get_redir_mode!("3</tmp/filetxt"), RedirectionMode::Input);
+        assert_eq!(get_redir_mode!("<<EOF"), RedirectionMode::HereDoc);
+        assert_eq!(get_redir_mode!("3<<<"), RedirectionMode::HereString);
+    }
+
+    #[test]
+    fn test_heredoc() {
+        let s = L!("cat <<EOF | cat <<'END'; echo\n  a $x\nEOF\n  b\n  END\necho done");
+        let mut t = Tokenizer::new(s, TokFlags(0));
+        let mut tokens = vec![];
+        while let Some(token) = t.next() {
+            let body = token
+                .heredoc_body()
+                .map(|body| heredoc_contents(s, body).to_string());
+            tokens.push((t.text_of(&token).to_string(), body));
+        }
+        let expected = [
+            ("cat", None),
+            ("<<", None),
+            ("EOF", Some("  a $x\n")),
+            ("|", None),
+            ("cat", None),
+            ("<<", None),
+            ("'END'", Some("b\n")),
+            (";", None),
+            ("echo", None),
+            ("\n", None),
+            ("echo", None),
+            ("done", None),
+        ];
+        let expected: Vec<_> = expected
+            .into_iter()
+            .map(|(text, body)| (text.to_owned(), body.map(str::to_owned)))
+            .collect();
+        assert_eq!(tokens, expected);
+
+        assert_eq!(heredoc_delimiter(L!("EOF")), (L!("EOF").to_owned(), false));
+        assert_eq!(heredoc_delimiter(L!("'EOF'")), (L!("EOF").to_owned(), true));
+        assert_eq!(heredoc_delimiter(L!("\\EOF")), (L!("EOF").to_owned(), true));
+
+        let mut t = Tokenizer::new(L!("cat <<EOF\nno end"), TokFlags(0));
+        t.next();
+        t.next();
+        let token = t.next().unwrap();
+        assert_eq!(token.type_, TokenType::Error);
+        assert_eq!(token.error, TokenizerError::UnterminatedHeredoc);
+        assert!(t.next().is_none());
+
+        // An unfinished body runs to the end.
+        let s = L!("cat <<EOF\nno end");
+        let mut t = Tokenizer::new(s, TOK_ACCEPT_UNFINISHED);
+        let token = t.nth(2).unwrap();
+        assert_eq!(token.heredoc_body(), Some(10..s.len()));
+        assert_eq!(t.next().unwrap().type_, TokenType::End);
+        assert!(t.next().is_none());
     }
 }
@@ -11,9 +11,10 @@ use crate::proc::JobGroupRef;
 use crate::terminal::Outputter;
 use crate::terminal::TerminalCommand::{
     self, ApplicationKeypadModeDisable, ApplicationKeypadModeEnable, DecrstBracketedPaste,
-    DecrstColorThemeReporting, DecrstFocusReporting, DecsetBracketedPaste,
-    DecsetColorThemeReporting, DecsetFocusReporting, KittyKeyboardProgressiveEnhancementsDisable,
-    KittyKeyboardProgressiveEnhancementsEnable, ModifyOtherKeysDisable, ModifyOtherKeysEnable,
+    DecrstColorThemeReporting, DecrstFocusReporting, DecrstMouseTracking, DecsetBracketedPaste,
+    DecsetColorThemeReporting, DecsetFocusReporting, DecsetMouseTracking,
+    KittyKeyboardProgressiveEnhancementsDisable, KittyKeyboardProgressiveEnhancementsEnable,
+    ModifyOtherKeysDisable, ModifyOtherKeysEnable,
 };
 use crate::threads::assert_is_main_thread;
 use crate::wutil::{perror_nix, wcstoi};
@@ -36,6 +37,10 @@ pub fn maybe_set_kitty_keyboard_capability() {
     KITTY_KEYBOARD_SUPPORTED.get_or_init(|| true);
 }
 
+pub fn get_kitty_keyboard_capability() -> Option<bool> {
+    KITTY_KEYBOARD_SUPPORTED.get().copied()
+}
+
 pub(crate) static SCROLL_CONTENT_UP_SUPPORTED: OnceLock<bool> = OnceLock::new();
 pub(crate) const SCROLL_CONTENT_UP_TERMINFO_CODE: &str = "indn";
 
@@ -51,6 +56,52 @@ pub fn maybe_set_scroll_content_up_capability() {
     });
 }
 
+/// Whether the terminal lets us set the clipboard via OSC 52.
+pub(crate) static CLIPBOARD_SUPPORTED: OnceLock<bool> = OnceLock::new();
+pub(crate) const CLIPBOARD_TERMINFO_CODE: &str = "Ms";
+
+pub fn get_clipboard_capability() -> Option<bool> {
+    CLIPBOARD_SUPPORTED.get().copied()
+}
+
+pub fn maybe_set_clipboard_capability() {
+    CLIPBOARD_SUPPORTED.get_or_init(|| {
+        flog!(reader, "OSC 52 clipboard is supported");
+        true
+    });
+}
+
+/// Whether the terminal supports synchronized output (DEC private mode 2026), which lets us
+/// update the screen without flicker.
+pub(crate) static SYNCHRONIZED_OUTPUT_SUPPORTED: OnceLock<bool> = OnceLock::new();
+pub(crate) const SYNCHRONIZED_OUTPUT_MODE: u32 = 2026;
+
+pub fn get_synchronized_output_capability() -> Option<bool> {
+    SYNCHRONIZED_OUTPUT_SUPPORTED.get().copied()
+}
+
+pub fn maybe_set_synchronized_output_capability() {
+    SYNCHRONIZED_OUTPUT_SUPPORTED.get_or_init(|| {
+        flog!(reader, "Synchronized output is supported");
+        true
+    });
+}
+
+/// Whether the terminal advertises 24-bit color, via either of the terminfo extensions.
+pub(crate) static TRUECOLOR_SUPPORTED: OnceLock<bool> = OnceLock::new();
+pub(crate) const TRUECOLOR_TERMINFO_CODES: [&str; 2] = ["RGB", "Tc"];
+
+pub fn get_truecolor_capability() -> Option<bool> {
+    TRUECOLOR_SUPPORTED.get().copied()
+}
+
+pub fn maybe_set_truecolor_capability() {
+    TRUECOLOR_SUPPORTED.get_or_init(|| {
+        flog!(reader, "24-bit color is supported");
+        true
+    });
+}
+
 pub static TERMINAL_OS_NAME: OnceLock<Option<WString>> = OnceLock::new();
 pub(crate) const XTGETTCAP_QUERY_OS_NAME: &str = "query-os-name";
 
@@ -238,6 +289,9 @@ pub fn initialize_tty_protocols(vars: &dyn Environment) {
     // Default missing query responses.
     KITTY_KEYBOARD_SUPPORTED.get_or_init(|| false);
     SCROLL_CONTENT_UP_SUPPORTED.get_or_init(|| false);
+    CLIPBOARD_SUPPORTED.get_or_init(|| false);
+    SYNCHRONIZED_OUTPUT_SUPPORTED.get_or_init(|| false);
+    TRUECOLOR_SUPPORTED.get_or_init(|| false);
     TERMINAL_OS_NAME.get_or_init(|| None);
     let xtversion = XTVERSION.get_or_init(WString::new);
 
@@ -261,6 +315,55 @@ static TTY_PROTOCOLS_ACTIVE: RelaxedAtomicBool = RelaxedAtomicBool::new(false);
 // A marker that the tty has been closed (SIGHUP, etc) and so we should not try to write to it.
 static TTY_INVALID: RelaxedAtomicBool = RelaxedAtomicBool::new(false);
 
+// Whether mouse tracking is enabled along with the other protocols, per $fish_mouse_reporting.
+static MOUSE_REPORTING: RelaxedAtomicBool = RelaxedAtomicBool::new(false);
+
+fn mouse_reporting_commands(enable: bool) -> Box<[u8]> {
+    serialize_commands(std::iter::once(if enable {
+        DecsetMouseTracking
+    } else {
+        DecrstMouseTracking
+    }))
+}
+
+// Enable or disable mouse tracking. If the TTY protocols are active, this takes effect right away,
+// otherwise the next time they are enabled.
+pub fn set_mouse_reporting(enable: bool) {
+    assert_is_main_thread();
+    if MOUSE_REPORTING.swap(enable) == enable {
+        return;
+    }
+    if !TTY_PROTOCOLS_ACTIVE.load() || TTY_INVALID.load() {
+        return;
+    }
+    let _ = write_loop(&libc::STDOUT_FILENO, &mouse_reporting_commands(enable));
+}
+
+// Whether focus reporting is enabled along with the other protocols, so notifications about
+// commands can be sent only while the terminal is not focused, per $fish_command_notification.
+static FOCUS_REPORTING: RelaxedAtomicBool = RelaxedAtomicBool::new(false);
+
+fn focus_reporting_commands(enable: bool) -> Box<[u8]> {
+    serialize_commands(std::iter::once(if enable {
+        DecsetFocusReporting
+    } else {
+        DecrstFocusReporting
+    }))
+}
+
+// Enable or disable focus reporting. If the TTY protocols are active, this takes effect right away,
+// otherwise the next time they are enabled.
+pub fn set_focus_reporting(enable: bool) {
+    assert_is_main_thread();
+    if FOCUS_REPORTING.swap(enable) == enable {
+        return;
+    }
+    if !TTY_PROTOCOLS_ACTIVE.load() || TTY_INVALID.load() {
+        return;
+    }
+    let _ = write_loop(&libc::STDOUT_FILENO, &focus_reporting_commands(enable));
+}
+
 // Enable or disable TTY protocols by writing the appropriate commands to the tty.
 // Note this does NOT intialize the TTY protocols if not already initialized.
 fn set_tty_protocols_active(on_write: fn(), enable: bool) {
@@ -287,6 +390,12 @@ fn set_tty_protocols_active(on_write: fn(), enable: bool) {
     // Write the commands to the tty, ignoring errors.
     let commands = protocols.get_commands(enable);
     let _ = write_loop(&libc::STDOUT_FILENO, commands);
+    if MOUSE_REPORTING.load() {
+        let _ = write_loop(&libc::STDOUT_FILENO, &mouse_reporting_commands(enable));
+    }
+    if FOCUS_REPORTING.load() {
+        let _ = write_loop(&libc::STDOUT_FILENO, &focus_reporting_commands(enable));
+    }
     if !enable {
         TTY_PROTOCOLS_ACTIVE.store(false);
     }
@@ -330,6 +439,12 @@ pub fn deactivate_tty_protocols() {
     let commands = protocols.get_commands(false);
     // Safety: just writing data to stdout.
     let _ = write_loop(&libc::STDOUT_FILENO, commands);
+    if MOUSE_REPORTING.load() {
+        let _ = write_loop(&libc::STDOUT_FILENO, &mouse_reporting_commands(false));
+    }
+    if FOCUS_REPORTING.load() {
+        let _ = write_loop(&libc::STDOUT_FILENO, &focus_reporting_commands(false));
+    }
     TTY_PROTOCOLS_ACTIVE.store(false);
 }
 
@@ -2,15 +2,23 @@
 
 use crate::{
     common::{WSL, is_windows_subsystem_for_linux},
-    complete::{CompleteFlags, Completion, CompletionReceiver, PROG_COMPLETE_SEP},
+    complete::{
+        CompleteFlags, Completion, CompletionReceiver, PROG_COMPLETE_SEP, completion_fuzzy_match,
+    },
+    env::Environment,
     expand::ExpandFlags,
     prelude::*,
+    tokenizer::tok_is_string_character,
     wutil::{
         dir_iter::{DirEntry, DirEntryType},
-        lwstat, waccess,
+        fish_wcstoi, lwstat, waccess,
     },
 };
-use fish_common::{UnescapeFlags, UnescapeStringStyle, unescape_string};
+use fish_common::{
+    UnescapeFlags, UnescapeStringStyle,
+    glob_qualifiers::{GlobFileType, GlobQualifier, GlobQualifiers},
+    unescape_string,
+};
 use fish_fallback::wcscasecmp;
 use fish_feature_flags::{FeatureFlag, feature_test};
 use fish_wcstringutil::{
@@ -42,6 +50,117 @@ pub enum WildcardResult {
     Overflow,
 }
 
+/// The largest number of threads to walk directories for `**` with.
+const GLOBSTAR_MAX_THREADS: usize = 64;
+
+/// Options for recursive wildcards (`**`), which come from variables.
+#[derive(Clone, Debug)]
+pub struct GlobstarOptions {
+    /// Wildcards for the names of directories which `**` does not descend into, from
+    /// `fish_glob_ignore`.
+    pub ignore: Vec<WString>,
+    /// The number of threads to read directories with, from `fish_glob_threads`.
+    pub threads: usize,
+}
+
+impl Default for GlobstarOptions {
+    fn default() -> Self {
+        Self {
+            ignore: vec![],
+            threads: 1,
+        }
+    }
+}
+
+impl GlobstarOptions {
+    pub fn from_vars(vars: &dyn Environment) -> Self {
+        let ignore: Vec<WString> = vars
+            .get(L!("fish_glob_ignore"))
+            .map(|var| {
+                var.as_list()
+                    .iter()
+                    .filter(|pattern| !pattern.is_empty())
+                    .map(|pattern| {
+                        pattern
+                            .chars()
+                            .map(|c| if c == '*' { ANY_STRING } else { c })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let threads = vars
+            .get(L!("fish_glob_threads"))
+            .and_then(|var| fish_wcstoi(&var.as_string()).ok())
+            .map_or(1, |threads| {
+                usize::try_from(threads).map_or(1, |threads| threads.clamp(1, GLOBSTAR_MAX_THREADS))
+            });
+        Self { ignore, threads }
+    }
+
+    /// Return whether `**` does not descend into the directory `name`.
+    fn ignores(&self, name: &wstr) -> bool {
+        self.ignore
+            .iter()
+            .any(|pattern| wildcard_match(name, pattern, false))
+    }
+}
+
+/// Return whether the file at the absolute path `path` matches the glob qualifiers. Symbolic links
+/// are not followed.
+fn glob_qualifiers_match(qualifiers: &GlobQualifiers, path: &wstr) -> bool {
+    use std::os::unix::fs::FileTypeExt as _;
+    if qualifiers.is_empty() {
+        return true;
+    }
+    let Ok(md) = lwstat(path) else {
+        return false;
+    };
+    qualifiers.iter().all(|qualifier| match *qualifier {
+        GlobQualifier::Type(file_type) => {
+            let ft = md.file_type();
+            match file_type {
+                GlobFileType::Regular => ft.is_file(),
+                GlobFileType::Directory => ft.is_dir(),
+                GlobFileType::Symlink => ft.is_symlink(),
+                GlobFileType::Socket => ft.is_socket(),
+                GlobFileType::Fifo => ft.is_fifo(),
+                GlobFileType::Executable => ft.is_file() && md.mode() & 0o111 != 0,
+            }
+        }
+        GlobQualifier::Size {
+            ordering,
+            amount,
+            unit,
+        } => md.size().div_ceil(unit).cmp(&amount) == ordering,
+        GlobQualifier::Mtime {
+            ordering,
+            amount,
+            unit,
+        } => {
+            let now = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            let age = now.saturating_sub(u64::try_from(md.mtime()).unwrap_or(0));
+            (age / unit).cmp(&amount) == ordering
+        }
+    })
+}
+
+/// If glob qualifiers like the "(.)" in "*(.)" start at the opening parenthesis at `pos`, return
+/// their length including the parentheses. Qualifiers must end the token; the caller checks that
+/// they follow a wildcard. Otherwise the parentheses are a command substitution.
+pub fn glob_qualifier_len(s: &wstr, pos: usize) -> Option<usize> {
+    assert_eq!(s.char_at(pos), '(');
+    let close = pos + 1 + s[pos + 1..].find_char(')')?;
+    GlobQualifiers::parse(&s[pos + 1..close])?;
+    let next = s.as_char_slice().get(close + 1).copied();
+    if next.is_some_and(|c| tok_is_string_character(c, s.as_char_slice().get(close + 2).copied())) {
+        return None;
+    }
+    Some(close + 1 - pos)
+}
+
 // This does something horrible refactored from an even more horrible function.
 fn resolve_description(
     full_completion: &wstr,
@@ -113,16 +232,13 @@ fn wildcard_complete_internal(
 
     // Maybe we have no more wildcards at all. This includes the empty string.
     if next_wc_char_pos.is_none() {
-        // Try matching
-        let Some(m) = string_fuzzy_match_string(wc, s, false) else {
-            return WildcardResult::NoMatch;
-        };
-
         // If we're not allowing fuzzy match, then we require a prefix match.
         let needs_prefix_match = !params.expand_flags.contains(ExpandFlags::FUZZY_MATCH);
-        if needs_prefix_match && !m.is_exact_or_prefix() {
+
+        // Try matching
+        let Some(m) = completion_fuzzy_match(wc, s, needs_prefix_match) else {
             return WildcardResult::NoMatch;
-        }
+        };
 
         // The match was successful. If the string is not requested we're done.
         let Some(out) = out else {
@@ -434,7 +550,14 @@ mod expander {
 
     use crate::{
         path::append_path_component,
-        wutil::{DevInode, dir_iter::DirIter, normalize_path},
+        wutil::{DevInode, INVALID_FILE_ID, dir_iter::DirIter, file_id_for_path, normalize_path},
+    };
+    use std::{
+        sync::{
+            Condvar, Mutex,
+            atomic::{AtomicBool, Ordering as AtomicOrdering},
+        },
+        time::Duration,
     };
 
     use super::*;
@@ -446,6 +569,8 @@ mod expander {
     pub(super) struct ParentInfo {
         // Whether some parent expansion is fuzzy, and therefore completions always prepend their prefix.
         has_fuzzy_ancestor: bool,
+        // Whether this path was found by walking the directories below a **.
+        in_globstar_walk: bool,
     }
 
     // A stateful object for expanding wildcards.
@@ -454,6 +579,10 @@ mod expander {
         cancel_checker: &'e mut dyn FnMut() -> bool,
         /// The working directory to resolve paths against
         working_directory: &'e wstr,
+        /// Options for recursive wildcards.
+        globstar: &'e GlobstarOptions,
+        /// Glob qualifiers which the expanded files must match.
+        qualifiers: &'e GlobQualifiers,
         /// The set of items we have resolved, used to efficiently avoid duplication.
         completion_set: HashSet<WString>,
         /// The set of (device, inode) pairs we have visited, used to avoid symlink loops.
@@ -474,12 +603,16 @@ mod expander {
         pub fn new(
             working_directory: &'e wstr,
             flags: ExpandFlags,
+            globstar: &'e GlobstarOptions,
+            qualifiers: &'e GlobQualifiers,
             cancel_checker: &'e mut dyn FnMut() -> bool,
             resolved_completions: &'e mut CompletionReceiver,
         ) -> Self {
             Self {
                 cancel_checker,
                 working_directory,
+                globstar,
+                qualifiers,
                 completion_set: resolved_completions
                     .iter()
                     .map(|c| c.completion.clone())
@@ -529,6 +662,23 @@ mod expander {
 
             let segment_has_wildcards = wildcard_has_internal(wc_segment);
 
+            // A literal last segment in a ** walk, like the "Cargo.toml" in "**/Cargo.toml", is
+            // missing from most directories, so don't read those. If the file exists, the directory
+            // is still read below, because only an entry with exactly this name may match, even on
+            // case-insensitive filesystems.
+            if info.in_globstar_walk
+                && is_last_segment
+                && !segment_has_wildcards
+                && !wc_segment.is_empty()
+                && !self.flags.contains(ExpandFlags::FOR_COMPLETIONS)
+            {
+                let mut abs_path = self.working_directory.to_owned();
+                append_path_component(&mut abs_path, &(base_dir.to_owned() + wc_segment));
+                if lwstat(&abs_path).is_err() {
+                    return;
+                }
+            }
+
             if wc_segment.is_empty() {
                 assert!(!segment_has_wildcards);
                 if is_last_segment {
@@ -573,23 +723,31 @@ mod expander {
                         );
                     }
                 }
-            } else {
-                // Either a wildcard intermediate segment or the last segment.
-                assert!(!wc_segment.is_empty() && (segment_has_wildcards || is_last_segment));
-
-                if !is_last_segment && matches!(wc_segment.as_char_slice(), [ANY_STRING_RECURSIVE])
-                {
-                    // Hack for #7222. This is an intermediate wc segment that is exactly **. The
-                    // tail matches in subdirectories as normal, but also the current directory.
-                    // That is, '**/bar' may match 'bar' and 'foo/bar'.
-                    // Implement this by matching the wildcard tail only, in this directory.
-                    // Note if the segment is not exactly ANY_STRING_RECURSIVE then the segment may only
-                    // match subdirectories.
-                    self.expand(base_dir, wc_remainder.unwrap(), effective_prefix, info);
+            } else if !is_last_segment
+                && matches!(wc_segment.as_char_slice(), [ANY_STRING_RECURSIVE])
+            {
+                // An intermediate wc segment that is exactly **. The tail matches in all
+                // subdirectories, but also the current directory (#7222). That is, '**/bar' may match
+                // 'bar' and 'foo/bar'. Note if the segment is not exactly ANY_STRING_RECURSIVE then
+                // the segment may only match subdirectories.
+                // Find all the directories first, so each is only visited once, and the walk may use
+                // several threads.
+                let wc_remainder = wc_remainder.unwrap();
+                let info = ParentInfo {
+                    in_globstar_walk: true,
+                    ..info
+                };
+                for subdir in self.walk_recursively(base_dir) {
                     if self.interrupted_or_overflowed() {
                         return;
                     }
+                    let dir = base_dir.to_owned() + subdir.as_utfstr();
+                    let prefix = effective_prefix.to_owned() + subdir.as_utfstr();
+                    self.expand(&dir, wc_remainder, &prefix, info);
                 }
+            } else {
+                // Either a wildcard intermediate segment or the last segment.
+                assert!(!wc_segment.is_empty() && (segment_has_wildcards || is_last_segment));
 
                 // return "." and ".." entries if we're doing completions
                 let Ok(mut dir) = self.open_dir(
@@ -717,7 +875,8 @@ mod expander {
             prefix: &wstr,
             info: ParentInfo,
         ) {
-            let is_final = wc_remainder.is_empty() && !wc_segment.contains(ANY_STRING_RECURSIVE);
+            let recursive = wc_segment.contains(ANY_STRING_RECURSIVE);
+            let is_final = wc_remainder.is_empty() && !recursive;
             while !self.interrupted_or_overflowed() {
                 let Some(Ok(entry)) = base_dir_iter.next() else {
                     break;
@@ -730,6 +889,9 @@ mod expander {
                 if !entry.is_dir() {
                     continue;
                 }
+                if recursive && self.globstar.ignores(&entry.name) {
+                    continue;
+                }
 
                 // Fast path: If this entry can't be a link (we know via d_type),
                 // we don't need to protect against symlink loops.
@@ -884,6 +1046,13 @@ mod expander {
         fn add_expansion_result(&mut self, result: WString) {
             // This function is only for the non-completions case.
             assert!(!self.flags.contains(ExpandFlags::FOR_COMPLETIONS));
+            if !self.qualifiers.is_empty() {
+                let mut abs_path = self.working_directory.to_owned();
+                append_path_component(&mut abs_path, &result);
+                if !glob_qualifiers_match(self.qualifiers, &abs_path) {
+                    return;
+                }
+            }
             #[allow(clippy::collapsible_if)]
             if self.completion_set.insert(result.clone()) {
                 if !self.resolved_completions.add(result) {
@@ -1026,6 +1195,156 @@ mod expander {
                 false => DirIter::new(&path),
             }
         }
+
+        /// Return the directories that `**/` matches in `base_dir`: the directory itself and all its
+        /// subdirectories, except for hidden and ignored ones. The results are relative to
+        /// `base_dir`, and have a trailing slash unless empty.
+        fn walk_recursively(&mut self, base_dir: &wstr) -> Vec<WString> {
+            let mut root = self.working_directory.to_owned();
+            append_path_component(&mut root, base_dir);
+            if !root.is_empty() && !root.ends_with('/') {
+                root.push('/');
+            }
+            let walk = DirWalk::new(root, self.globstar);
+            let threads = self.globstar.threads;
+            let cancel_checker = &mut *self.cancel_checker;
+            std::thread::scope(|scope| {
+                for _ in 1..threads {
+                    scope.spawn(|| walk.work(None));
+                }
+                walk.work(Some(cancel_checker));
+            });
+            if walk.cancelled.load(AtomicOrdering::Relaxed) {
+                self.did_interrupt = true;
+                return vec![];
+            }
+            let mut dirs = walk.state.into_inner().unwrap().found;
+            // The order depends on the threads, so fix it.
+            dirs.sort_unstable();
+            dirs
+        }
+    }
+
+    /// A directory waiting to be read by a [`DirWalk`].
+    struct PendingDir {
+        /// The path relative to the root of the walk.
+        path: WString,
+        /// The directories containing this one, including itself, to avoid symlink loops. Only
+        /// ancestors are considered, so a directory may be visited through different links (#2414).
+        ancestors: Vec<DevInode>,
+    }
+
+    struct DirWalkState {
+        pending: Vec<PendingDir>,
+        /// How many threads are currently reading a directory.
+        busy: usize,
+        found: Vec<WString>,
+    }
+
+    /// A walk through the subdirectories of a directory, which can be shared by several threads.
+    struct DirWalk<'a> {
+        root: WString,
+        options: &'a GlobstarOptions,
+        state: Mutex<DirWalkState>,
+        /// Signaled when directories are added, or the walk is done.
+        changed: Condvar,
+        cancelled: AtomicBool,
+    }
+
+    impl<'a> DirWalk<'a> {
+        fn new(root: WString, options: &'a GlobstarOptions) -> Self {
+            let root_id = file_id_for_path(&root);
+            let ancestors = if root_id == INVALID_FILE_ID {
+                vec![]
+            } else {
+                vec![root_id.dev_inode]
+            };
+            let state = DirWalkState {
+                pending: vec![PendingDir {
+                    path: WString::new(),
+                    ancestors,
+                }],
+                busy: 0,
+                found: vec![WString::new()],
+            };
+            Self {
+                root,
+                options,
+                state: Mutex::new(state),
+                changed: Condvar::new(),
+                cancelled: AtomicBool::new(false),
+            }
+        }
+
+        /// Read pending directories until there are none left. Only the calling thread checks for
+        /// cancellation, with `cancel_checker`; the others wait for it.
+        fn work(&self, mut cancel_checker: Option<&mut dyn FnMut() -> bool>) {
+            let mut state = self.state.lock().unwrap();
+            loop {
+                if cancel_checker.as_mut().is_some_and(|cancelled| cancelled()) {
+                    self.cancelled.store(true, AtomicOrdering::Relaxed);
+                }
+                if self.cancelled.load(AtomicOrdering::Relaxed) {
+                    break;
+                }
+                if let Some(dir) = state.pending.pop() {
+                    state.busy += 1;
+                    drop(state);
+                    let subdirs = self.read_subdirs(dir);
+                    state = self.state.lock().unwrap();
+                    state.busy -= 1;
+                    state
+                        .found
+                        .extend(subdirs.iter().map(|subdir| subdir.path.clone()));
+                    state.pending.extend(subdirs);
+                    self.changed.notify_all();
+                } else if state.busy == 0 {
+                    break;
+                } else if cancel_checker.is_some() {
+                    // Wake up now and then to check for cancellation.
+                    state = self
+                        .changed
+                        .wait_timeout(state, Duration::from_millis(10))
+                        .unwrap()
+                        .0;
+                } else {
+                    state = self.changed.wait(state).unwrap();
+                }
+            }
+            // Wake up the other threads, so they see that we are done.
+            self.changed.notify_all();
+        }
+
+        fn read_subdirs(&self, dir: PendingDir) -> Vec<PendingDir> {
+            let mut subdirs = vec![];
+            let Ok(mut iter) = DirIter::new(&(self.root.clone() + dir.path.as_utfstr())) else {
+                return subdirs;
+            };
+            while let Some(Ok(entry)) = iter.next() {
+                // Note that it's critical we ignore leading dots here, else we may descend into . and ..
+                if entry.name.is_empty()
+                    || entry.name.starts_with('.')
+                    || self.options.ignores(&entry.name)
+                    || !entry.is_dir()
+                {
+                    continue;
+                }
+                let Some(dev_inode) = entry.dev_inode() else {
+                    continue;
+                };
+                if dir.ancestors.contains(&dev_inode) {
+                    // Symlink loop! This directory contains itself, so skip it.
+                    continue;
+                }
+                let mut ancestors = dir.ancestors.clone();
+                ancestors.push(dev_inode);
+                subdirs.push(PendingDir {
+                    path: dir.path.clone() + entry.name.as_utfstr() + L!("/"),
+                    ancestors,
+                });
+            }
+            subdirs
+        }
     }
 }
 
@@ -1044,6 +1363,8 @@ mod expander {
 /// \param working_directory The working directory
 /// \param flags flags for the search. Can be any combination of for_completions and
 /// executables_only
+/// \param globstar options for recursive wildcards
+/// \param qualifiers glob qualifiers which the expanded files must match
 /// \param cancel_checker A function to call to check for cancellation
 /// \param output The completion receiver to receive expanded wildcards
 ///
@@ -1051,6 +1372,8 @@ pub fn wildcard_expand_string<'closure>(
     wc: &wstr,
     working_directory: &wstr,
     flags: ExpandFlags,
+    globstar: &GlobstarOptions,
+    qualifiers: &GlobQualifiers,
     mut cancel_checker: impl FnMut() -> bool + 'closure,
     output: &mut CompletionReceiver,
 ) -> WildcardResult {
@@ -1081,6 +1404,11 @@ pub fn wildcard_expand_string<'closure>(
         return WildcardResult::NoMatch;
     }
 
+    // Likewise we do not complete wildcards with glob qualifiers.
+    if flags.contains(ExpandFlags::FOR_COMPLETIONS) && !qualifiers.is_empty() {
+        return WildcardResult::NoMatch;
+    }
+
     // Compute the prefix and base dir. The prefix is what we prepend for filesystem operations
     // (i.e. the working directory), the base_dir is the part of the wildcard consumed thus far,
     // which we also have to append. The difference is that the base_dir is returned as part of the
@@ -1095,7 +1423,14 @@ pub fn wildcard_expand_string<'closure>(
         (working_directory, L!(""), wc)
     };
 
-    let mut expander = WildCardExpander::new(prefix, flags, &mut cancel_checker, output);
+    let mut expander = WildCardExpander::new(
+        prefix,
+        flags,
+        globstar,
+        qualifiers,
+        &mut cancel_checker,
+        output,
+    );
     expander.expand(base_dir, effective_wc, base_dir, ParentInfo::default());
     expander.status_code()
 }
@@ -1213,6 +1548,7 @@ pub fn wildcard_has(s: impl AsRef<wstr>) -> bool {
 mod tests {
     use super::*;
     use fish_feature_flags::with_overridden_feature;
+    use fish_widestring::GLOB_QUALIFIER;
 
     #[test]
     fn test_wildcards() {
@@ -1235,4 +1571,33 @@ mod tests {
             assert!(!wildcard_has(L!("\\?")));
         });
     }
+
+    #[test]
+    fn test_glob_qualifiers() {
+        assert!(GlobQualifiers::parse(L!("./@=p*")).is_some());
+        assert!(GlobQualifiers::parse(L!("m-7")).is_some());
+        assert!(GlobQualifiers::parse(L!("mh+12Lk-10.")).is_some());
+        assert!(GlobQualifiers::parse(L!("")).is_none());
+        assert!(GlobQualifiers::parse(L!("m")).is_none());
+        assert!(GlobQualifiers::parse(L!("Lx5")).is_none());
+        assert!(GlobQualifiers::parse(L!("echo")).is_none());
+        assert!(GlobQualifiers::parse(L!("m99999999999999999999")).is_none());
+
+        assert_eq!(glob_qualifier_len(L!("*(.)"), 1), Some(3));
+        assert_eq!(glob_qualifier_len(L!("*(m-7) foo"), 1), Some(5));
+        assert_eq!(glob_qualifier_len(L!("*(.)foo"), 1), None);
+        assert_eq!(glob_qualifier_len(L!("*(echo)"), 1), None);
+
+        let unescape =
+            |s| unescape_string(s, UnescapeStringStyle::Script(UnescapeFlags::SPECIAL)).unwrap();
+        assert_eq!(
+            unescape(L!("*(.)")),
+            WString::from_chars([ANY_STRING, GLOB_QUALIFIER, '.'])
+        );
+        assert_eq!(unescape(L!("foo(.)")), L!("foo(.)"));
+        assert_eq!(unescape(L!("*'(.)'")).find_char(GLOB_QUALIFIER), None);
+        // Only valid qualifiers are recognized, anything else stays a command substitution.
+        assert_eq!(unescape(L!("*(Lx5)")).find_char(GLOB_QUALIFIER), None);
+        assert_eq!(unescape(L!("*(echo)")).find_char(GLOB_QUALIFIER), None);
+    }
 }
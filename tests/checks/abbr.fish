@@ -48,9 +48,23 @@ abbr | grep __abbr3
 # CHECK: abbr -a -- --__abbr3 xyz
 abbr -e -- --__abbr3
 
-# Test that an abbr word containing spaces is rejected
+# Test that an abbr of several words is accepted
 abbr "a b c" "d e f"
 abbr | grep 'a b c'
+# CHECK: abbr -a -- 'a b c' 'd e f'
+abbr -e "a b c"
+
+# Test that words must be separated by single spaces
+abbr "a  b" "d e f"
+# CHECKERR: abbr --add: Abbreviation 'a  b' must be words separated by single spaces
+abbr " a" "d e f"
+# CHECKERR: abbr --add: Abbreviation ' a' must be words separated by single spaces
+abbr "a	b" "d e f"
+# CHECKERR: abbr --add: Abbreviation 'a	b' must be words separated by single spaces
+abbr | grep 'd e f'
+
+# Test that a regex abbr name containing spaces is rejected
+abbr "a b c" --regex 'a.*' "d e f"
 # CHECKERR: abbr --add: Abbreviation 'a b c' cannot have spaces in the word
 
 # Test renaming
@@ -209,6 +223,11 @@ abbr --add foo --set-cursor 'foo % bar'
 abbr | grep foo
 # CHECK: abbr -a --set-cursor='%' -- foo 'foo % bar'
 
+abbr --add cond_abbr --condition 'test -n "$cond_var"' expanded
+abbr | grep cond_abbr
+# CHECK: abbr -a --condition 'test -n "$cond_var"' -- cond_abbr expanded
+abbr --erase cond_abbr
+
 # Command-specific and general abbrs can coexist
 abbr __abbr_coexist "general def"
 abbr --command foo __abbr_coexist "command def"
@@ -262,6 +281,17 @@ abbr --add --set-cursor=marker --set-cursor=marker
 abbr --add foo --set-cursor= foo
 # CHECKERR: abbr: --set-cursor argument cannot be empty
 
+abbr --show --condition true
+# CHECKERR: abbr: --condition option requires --add
+
+abbr --add foo --condition true --condition false foo
+# CHECKERR: abbr: Cannot specify multiple conditions
+
+abbr --add foo --condition 'echo (' foo
+# CHECKERR: abbr: --condition 'echo (': Unexpected end of string, expecting ')'
+# CHECKERR: echo (
+# CHECKERR: ^
+
 abbr --list foo
 # CHECKERR: abbr --list: Unexpected argument -- 'foo'
 
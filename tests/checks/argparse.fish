@@ -842,5 +842,76 @@ begin
     set -l
 end
 
+# Generated help and completions
+begin
+    set -l options h/help\t'Show this help' n/name=\t'Name to greet' v/verbose color=?\t'When to use color' x=+ m#max\tMaximum
+    argparse --name greet --generate-help --max-args 1 $options -- --unknown
+    # CHECK: Usage: greet [OPTIONS] [ARG]
+    # CHECK:
+    # CHECK: Options:
+    # CHECK:   -h, --help              Show this help
+    # CHECK:   -n, --name=NAME         Name to greet
+    # CHECK:   -v, --verbose
+    # CHECK:       --color[=COLOR]     When to use color
+    # CHECK:   -x VALUE
+    # CHECK:   -NUMBER, -m, --max=MAX  Maximum
+    argparse --generate-completions greet $options --
+    # CHECK: complete -c greet -s h -l help -d 'Show this help'
+    # CHECK: complete -c greet -s n -l name -r -d 'Name to greet'
+    # CHECK: complete -c greet -s v -l verbose
+    # CHECK: complete -c greet -l color -d 'When to use color'
+    # CHECK: complete -c greet -s x -r
+    # CHECK: complete -c greet -s m -l max -r -d Maximum
+    argparse --name greet --generate-help --min-args 1 --
+    # CHECK: Usage: greet ARG ...
+    argparse --generate-help --generate-completions greet --
+    # CHECKERR: argparse: --generate-help --generate-completions: options cannot be used together
+    # CHECKERR: {{.*}}checks/argparse.fish (line {{\d+}}):
+    # CHECKERR: argparse --generate-help --generate-completions greet --
+    # CHECKERR: ^
+    # CHECKERR: (Type 'help argparse' for related documentation)
+    argparse \t'No flag' --
+    # CHECKERR: argparse: An option spec must have at least a short or a long flag
+    # CHECKERR: {{.*}}checks/argparse.fish (line {{\d+}}):
+    # CHECKERR: argparse \t'No flag' --
+    # CHECKERR: ^
+    # CHECKERR: (Type 'help argparse' for related documentation)
+    fish_opt -s n -l name -r --description 'Name to greet' | string escape
+    # CHECK: n/name=\tName\ to\ greet
+end
+
+# Subcommands
+function plugin
+    argparse --subcommand install\t'Install a plugin' --subcommand remove v/verbose -- $argv
+    or return
+    echo "sub=$_flag_subcommand argv=$argv opts=$argv_opts"
+end
+plugin -v install -f fzf
+# CHECK: sub=install argv=-f fzf opts=-v
+plugin --verbose
+# CHECK: sub= argv= opts=--verbose
+plugin frob
+# CHECKERR: plugin: frob: unknown subcommand
+begin
+    argparse --name plugin --generate-help --subcommand install\t'Install a plugin' --subcommand remove v/verbose\t'Say more' --
+    # CHECK: Usage: plugin [OPTIONS] SUBCOMMAND [ARG ...]
+    # CHECK:
+    # CHECK: Options:
+    # CHECK:   -v, --verbose  Say more
+    # CHECK:
+    # CHECK: Subcommands:
+    # CHECK:   install        Install a plugin
+    # CHECK:   remove
+    argparse --generate-completions plugin --subcommand install\t'Install a plugin' --subcommand remove --
+    # CHECK: complete -c plugin -n __fish_use_subcommand -f -a install -d 'Install a plugin'
+    # CHECK: complete -c plugin -n __fish_use_subcommand -f -a remove
+    argparse --subcommand -x --
+    # CHECKERR: argparse: Invalid subcommand '-x'
+    # CHECKERR: {{.*}}checks/argparse.fish (line {{\d+}}):
+    # CHECKERR: argparse --subcommand -x --
+    # CHECKERR: ^
+    # CHECKERR: (Type 'help argparse' for related documentation)
+end
+
 # Check that the argparse's are properly wrapped in begin blocks
 set -l
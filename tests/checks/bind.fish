@@ -202,6 +202,105 @@ bind ctrl-q
 bind --user --preset ctrl-q 'echo preset'
 # CHECKERR: bind: --preset --user: options cannot be used together
 
+bind --timeout 150 --on-timeout self-insert j,k 'set fish_bind_mode default'
+bind --timeout 150 x,y 'echo chord'
+bind j,k; bind x,y
+# CHECK: bind --timeout 150 --on-timeout self-insert j,k 'set fish_bind_mode default'
+# CHECK: bind --timeout 150 x,y 'echo chord'
+bind --erase j,k x,y
+
+bind --timeout 0 j,k true
+# CHECKERR: bind: Invalid timeout '0'
+
+bind --timeout 150 j true
+# CHECKERR: bind: --timeout requires a sequence of at least two keys
+
+bind --on-timeout self-insert j,k true
+# CHECKERR: bind: --on-timeout requires --timeout
+
+bind focusout 'echo out'
+bind paste 'echo pasted'
+bind focusout; bind paste
+# CHECK: bind focusout 'echo out'
+# CHECK: bind paste 'echo pasted'
+bind --erase focusout paste
+
+bind ctrl-focusin true
+# CHECKERR: bind: 'focusin' can only be bound on its own
+
+bind pastestart,a true
+# CHECKERR: bind: 'pastestart' can only be bound on its own
+
+bind release-f5 'echo released'
+bind release-f5
+# CHECK: bind release-f5 'echo released'
+bind --erase release-f5
+
+bind release-focusin true
+# CHECKERR: bind: 'focusin' can only be bound on its own
+
+# Mouse events may have modifiers.
+bind ctrl-scroll-up 'echo zoom'
+bind ctrl-scroll-up
+# CHECK: bind ctrl-scroll-up 'echo zoom'
+bind --erase ctrl-scroll-up
+
+bind mouse-left-click,a true
+# CHECKERR: bind: 'mouse-left-click' can only be bound on its own
+
+# User-defined input functions.
+bind --define-function wrap-echo 'commandline -r "echo "(commandline)'
+bind --define-function
+# CHECK: bind --define-function wrap-echo 'commandline -r "echo "(commandline)'
+bind --function-names | string match 'w*'
+# CHECK: wrap-echo
+bind ctrl-x wrap-echo end-of-line
+bind | string match -e wrap-echo
+# CHECK: bind --define-function wrap-echo 'commandline -r "echo "(commandline)'
+# CHECK: bind ctrl-x wrap-echo end-of-line
+bind --erase ctrl-x
+bind --erase --define-function wrap-echo
+bind --define-function wrap-echo
+# CHECKERR: bind: No input function named 'wrap-echo'
+bind --define-function up-line true
+# CHECKERR: bind: 'up-line' is a built-in input function
+bind --define-function 'my func' true
+# CHECKERR: bind: 'my func' is not a valid input function name
+bind --define-function my-func true false
+# CHECKERR: bind: too many arguments
+
+# Key translations for nonstandard terminal sequences.
+bind --translate --term xterm \e\[99\;5~ ctrl-backspace
+bind --translate --term foot \x7f ctrl-backspace
+bind --translate --term xterm \e\[99\;5~ ctrl-delete
+bind --translate
+# CHECK: bind --translate --term foot \x7f ctrl-backspace
+# CHECK: bind --translate --term xterm \e\[99\;5\~ ctrl-delete
+bind --translate --term foot
+# CHECK: bind --translate --term foot \x7f ctrl-backspace
+bind --translate --term foot \x7f
+# CHECK: bind --translate --term foot \x7f ctrl-backspace
+bind | string match -e -- --translate
+# CHECK: bind --translate --term foot \x7f ctrl-backspace
+# CHECK: bind --translate --term xterm \e\[99\;5\~ ctrl-delete
+bind --translate --term foot \x08
+# CHECKERR: bind: No translation found for sequence '\b'
+bind --translate --term foot \x08 ctrl-a,b
+# CHECKERR: bind: 'ctrl-a,b' is not a single key
+bind --translate --term foot \x08 focusin
+# CHECKERR: bind: Cannot translate to 'focusin'
+bind --translate --term foot '' ctrl-a
+# CHECKERR: bind: The sequence to translate must not be empty
+bind --term foot ctrl-a true
+# CHECKERR: bind: --term requires --translate
+bind --translate --erase --term foot \x7f \x08
+# CHECKERR: bind: No translation found for sequence '\b'
+bind --translate --erase --all --term xterm
+bind --translate
+bind --translate --term foot \x7f ctrl-backspace
+bind --translate --erase --all
+bind --translate
+
 fish_default_key_bindings
 
 exit 0
@@ -52,6 +52,33 @@ end
 echo {a(echo ,)b}
 #CHECK: {a,b}
 
+echo {1..5}
+#CHECK: 1 2 3 4 5
+
+echo {3..-1}
+#CHECK: 3 2 1 0 -1
+
+echo {0..10..5} {10..1..-3}
+#CHECK: 0 5 10 10 7 4 1
+
+echo {01..3} {-1..010..4}
+#CHECK: 01 02 03 -01 003 007
+
+echo x{1..2}{a,b} {x,{1..2}}
+#CHECK: x1a x1b x2a x2b x 1 2
+
+set -l n 3
+echo {1..$n}
+#CHECK: 1 2 3
+
+# Not ranges.
+echo {1..} {a..c} {1..2..0} {1...2} '{1..2}'
+#CHECK: {1..} {a..c} {1..2..0} {1...2} {1..2}
+
+# Ranges with more values than an expansion may produce fail right away.
+$fish -c 'echo {1..9999999999}' 2>&1 | string match -e 'too many'
+#CHECK: fish: Expansion produced too many results
+
 e{cho,cho,cho}
 # CHECK: echo echo
 
@@ -78,3 +78,17 @@ echo "\$$(echo 1)"
 # Make sure we don't error on an escaped $@ inside a quoted cmdsub.
 echo "$(echo '$@')"
 # CHECK: $@
+
+# $$(cmd) expands to a single argument, like "$(cmd)".
+count $$(printf '%s\n' a b '' '')
+# CHECK: 1
+string escape -- $$(printf '%s\n' a 'b c')
+# CHECK: 'a\nb c'
+count $$(true)
+# CHECK: 1
+echo x$$(echo a; echo b)y{1,2}
+# CHECK: xa
+# CHECK: by1 xa
+# CHECK: by2
+echo \$$(echo 1)
+# CHECK: $1
@@ -53,6 +53,44 @@ commandline --input "echo {arg1,arg2} <in >out" --tokens-raw
 # CHECK: echo
 # CHECK: {arg1,arg2}
 
+commandline --input "FOO=1 command ls -l '-x' {a,b} >out | if not grep a; end &" --tokens-json
+# CHECK: {"text":"FOO=1","expanded":["FOO=1"],"start":0,"end":5,"type":"variable-assignment"}
+# CHECK: {"text":"command","expanded":["command"],"start":6,"end":13,"type":"keyword"}
+# CHECK: {"text":"ls","expanded":["ls"],"start":14,"end":16,"type":"command"}
+# CHECK: {"text":"-l","expanded":["-l"],"start":17,"end":19,"type":"option"}
+# CHECK: {"text":"'-x'","expanded":["-x"],"start":20,"end":24,"type":"option"}
+# CHECK: {"text":"{a,b}","expanded":["a","b"],"start":25,"end":30,"type":"argument"}
+# CHECK: {"text":">","expanded":[],"start":31,"end":32,"type":"redirection"}
+# CHECK: {"text":"out","expanded":["out"],"start":32,"end":35,"type":"redirection-target"}
+# CHECK: {"text":"|","expanded":[],"start":36,"end":37,"type":"pipe"}
+# CHECK: {"text":"if","expanded":["if"],"start":38,"end":40,"type":"keyword"}
+# CHECK: {"text":"not","expanded":["not"],"start":41,"end":44,"type":"keyword"}
+# CHECK: {"text":"grep","expanded":["grep"],"start":45,"end":49,"type":"command"}
+# CHECK: {"text":"a","expanded":["a"],"start":50,"end":51,"type":"argument"}
+# CHECK: {"text":";","expanded":[],"start":51,"end":52,"type":"end"}
+# CHECK: {"text":"end","expanded":["end"],"start":53,"end":56,"type":"keyword"}
+# CHECK: {"text":"&","expanded":[],"start":57,"end":58,"type":"background"}
+
+# Offsets are relative to the selection, and the output can be read with fish_json.
+for token in (commandline --input "true; echo {a,b}c" --current-process --tokens-json)
+    echo $token | fish_json get .start
+    echo $token | fish_json get .expanded
+end
+# CHECK: 1
+# CHECK: echo
+# CHECK: 6
+# CHECK: ac
+# CHECK: bc
+commandline --input "echo a b" --cut-at-cursor --tokens-json | count
+# CHECK: 2
+
+commandline --input "echo" --tokens-json --tokens-raw
+# CHECKERR: commandline: invalid option combination, --tokens options are mutually exclusive
+# CHECKERR: {{.*}}/commandline.fish (line {{\d+}}):
+# CHECKERR: commandline --input "echo" --tokens-json --tokens-raw
+# CHECKERR: ^
+# CHECKERR: (Type 'help commandline' for related documentation)
+
 $fish -ic '
     commandline hello
     commandline
@@ -210,3 +248,44 @@ $fish -ic "commandline --cursor abc"
 # CHECKERR: commandline --cursor abc
 # CHECKERR: ^
 # CHECKERR: (Type 'help commandline' for related documentation)
+
+commandline --register a
+or echo empty
+# CHECK: empty
+commandline --register a foo
+commandline --register A -a bar
+commandline --register a
+# CHECK: foobar
+commandline --register A baz
+commandline --register a
+# CHECK: baz
+commandline --register 1 foo
+# CHECKERR: commandline: register '1' is read-only
+# CHECKERR: {{.*}}/commandline.fish (line {{\d+}}):
+# CHECKERR: commandline --register 1 foo
+# CHECKERR: ^
+# CHECKERR: (Type 'help commandline' for related documentation)
+commandline --register ab
+# CHECKERR: commandline: Invalid register 'ab'
+# CHECKERR: {{.*}}/commandline.fish (line {{\d+}}):
+# CHECKERR: commandline --register ab
+# CHECKERR: ^
+# CHECKERR: (Type 'help commandline' for related documentation)
+commandline --register a --current-token
+# CHECKERR: commandline: invalid option combination
+# CHECKERR: {{.*}}/commandline.fish (line {{\d+}}):
+# CHECKERR: commandline --register a --current-token
+# CHECKERR: ^
+# CHECKERR: (Type 'help commandline' for related documentation)
+commandline --insert-snippet 'echo ${1:unterminated'
+# CHECKERR: commandline: invalid placeholder at character 6 of snippet
+# CHECKERR: {{.*}}/commandline.fish (line {{\d+}}):
+# CHECKERR: commandline --insert-snippet 'echo ${1:unterminated'
+# CHECKERR: ^
+# CHECKERR: (Type 'help commandline' for related documentation)
+commandline --insert-snippet --current-token 'echo $1'
+# CHECKERR: commandline: invalid option combination
+# CHECKERR: {{.*}}/commandline.fish (line {{\d+}}):
+# CHECKERR: commandline --insert-snippet --current-token 'echo $1'
+# CHECKERR: ^
+# CHECKERR: (Type 'help commandline' for related documentation)
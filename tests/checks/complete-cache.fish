@@ -0,0 +1,87 @@
+# RUN: %fish %s
+
+set -g calls 0
+function slow
+    set -g calls (math $calls + 1)
+    echo one
+    echo two
+end
+complete -c foo -xa '(slow)'
+
+# Without caching, the arguments are expanded every time.
+complete -C'foo ' >/dev/null
+complete -C'foo ' >/dev/null
+echo $calls
+# CHECK: 2
+
+complete -c foo --cache-ttl 60
+complete -c foo
+# CHECK: complete foo --cache-ttl 60
+# CHECK: complete --exclusive foo -a '(slow)'
+
+complete -C'foo '
+# CHECK: one
+# CHECK: two
+complete -C'foo t'
+# CHECK: two
+echo $calls
+# CHECK: 3
+
+# A different command line before the token is cached separately.
+complete -C'foo one ' >/dev/null
+complete -C'foo one t' >/dev/null
+echo $calls
+# CHECK: 4
+
+complete --clear-cache -c foo
+complete -C'foo ' >/dev/null
+echo $calls
+# CHECK: 5
+
+# The cached arguments are only used while the key expands the same.
+set -g key a
+complete -c foo --cache-ttl 60 --cache-key '$key'
+complete -C'foo ' >/dev/null
+complete -C'foo ' >/dev/null
+echo $calls
+# CHECK: 6
+set key b
+complete -C'foo ' >/dev/null
+echo $calls
+# CHECK: 7
+
+complete --clear-cache
+complete -C'foo ' >/dev/null
+echo $calls
+# CHECK: 8
+
+complete -c foo --cache-ttl 0
+complete -C'foo ' >/dev/null
+complete -C'foo ' >/dev/null
+echo $calls
+# CHECK: 10
+complete -c foo
+# CHECK: complete --exclusive foo -a '(slow)'
+
+complete -c bar --cache-ttl 5
+complete -e -c bar
+complete -c bar
+
+complete -c foo --cache-key x
+# CHECKERR: complete: --cache-key requires --cache-ttl
+# CHECKERR: {{.*}}checks/complete-cache.fish (line {{\d+}}):
+# CHECKERR: complete -c foo --cache-key x
+# CHECKERR: ^
+# CHECKERR:
+# CHECKERR: (Type 'help complete' for related documentation)
+
+complete -c foo --cache-ttl soon
+# CHECKERR: complete: soon: invalid integer
+
+complete --cache-ttl 5 -a x
+# CHECKERR: complete: invalid option combination
+# CHECKERR: {{.*}}checks/complete-cache.fish (line {{\d+}}):
+# CHECKERR: complete --cache-ttl 5 -a x
+# CHECKERR: ^
+# CHECKERR:
+# CHECKERR: (Type 'help complete' for related documentation)
@@ -0,0 +1,51 @@
+# RUN: %fish %s
+
+set -l dir (mktemp -d)
+set -gx PATH $dir $PATH
+printf '%s\n' '#!/bin/sh' \
+    'echo run >> "$0.log"' \
+    'test "$1" = --help || exit 1' \
+    'cat <<END' \
+    'Usage: helpful [OPTION]... FILE' \
+    '  -a, --all          show all entries.' \
+    '  -o, --output=FILE  write the output to FILE' \
+    '      --color[=WHEN] colorize the output' \
+    'END' >$dir/helpful
+chmod +x $dir/helpful
+cp $dir/helpful $dir/blocked
+
+# Nothing is run unless enabled.
+complete -C'helpful -'
+cat $dir/helpful.log 2>/dev/null
+or echo not run
+# CHECK: not run
+
+set -g fish_complete_from_help 1
+complete -C'helpful --'
+# CHECK: --all	show all entries
+# CHECK: --color	colorize the output
+# CHECK: --output	write the output to FILE
+complete -C'helpful -a'
+# CHECK: -ao	write the output to FILE
+complete helpful
+# CHECK: complete helpful -l color -d 'colorize the output'
+# CHECK: complete --require-parameter helpful -l output -d 'write the output to FILE'
+# CHECK: complete --require-parameter helpful -s o -d 'write the output to FILE'
+# CHECK: complete helpful -l all -d 'show all entries'
+# CHECK: complete helpful -s a -d 'show all entries'
+
+# The command is only run once.
+complete -e helpful
+complete -C'helpful --' | count
+# CHECK: 0
+cat $dir/helpful.log
+# CHECK: run
+
+set -g fish_complete_from_help_blocklist 'block*'
+complete -C'blocked -' | count
+# CHECK: 0
+cat $dir/blocked.log 2>/dev/null
+or echo not run
+# CHECK: not run
+
+rm -r $dir
@@ -0,0 +1,86 @@
+#RUN: %fish %s
+
+function mytool; end
+
+echo '{
+    "name": "mytool",
+    "options": [
+        {"short": "v", "long": "verbose", "description": "Be verbose"},
+        {"long": "color", "description": "When to use color", "arguments": ["always", "never"]}
+    ],
+    "subcommands": [
+        {
+            "name": "checkout",
+            "description": "Switch branches",
+            "arguments": "(printf \'%s\\n\' main dev)",
+            "files": false,
+            "options": [{"short": "b", "description": "Create a branch", "requires_argument": true}]
+        },
+        {
+            "name": "remote",
+            "description": "Manage remotes",
+            "subcommands": [
+                {"name": "add", "description": "Add a remote", "files": false},
+                {"name": "remove", "arguments": [{"value": "origin", "description": "The default remote"}], "files": false}
+            ]
+        }
+    ]
+}' >$TMPDIR/mytool.json
+complete --load-json $TMPDIR/mytool.json
+
+complete -C'mytool '
+# CHECK: checkout{{\t}}Switch branches
+# CHECK: remote{{\t}}Manage remotes
+complete -C'mytool -'
+# CHECK: -v{{\t}}Be verbose
+# CHECK: --color{{\t}}When to use color
+# CHECK: --verbose{{\t}}Be verbose
+complete -C'mytool --color '
+# CHECK: always{{\t}}When to use color
+# CHECK: never{{\t}}When to use color
+complete -C'mytool -v checkout '
+# CHECK: dev
+# CHECK: main
+complete -C'mytool checkout -'
+# CHECK: -b{{\t}}Create a branch
+complete -C'mytool remote '
+# CHECK: add{{\t}}Add a remote
+# CHECK: remove
+complete -C'mytool remote remove '
+# CHECK: origin{{\t}}The default remote
+complete -C'mytool remote add '
+
+# The generated rules can be listed like any others.
+complete -c mytool | string match -e remove
+# CHECK: complete mytool -d 'The default remote' -a origin -n '__fish_seen_subcommand_path remote remove --'
+# CHECK: complete --no-files mytool -n '__fish_seen_subcommand_path remote remove --'
+# CHECK: complete mytool -a remove -n '__fish_seen_subcommand_path remote -- add remove'
+# CHECK: complete mytool -d 'Add a remote' -a add -n '__fish_seen_subcommand_path remote -- add remove'
+# CHECK: complete --no-files mytool -n '__fish_seen_subcommand_path remote -- add remove'
+
+# The command name may be given explicitly, and the document may be read from stdin.
+echo '{"arguments": ["x", "y"], "files": false}' | complete --load-json - -c othertool
+complete -C'othertool '
+# CHECK: x
+# CHECK: y
+
+echo '{"name": "broken", "options": [{"description": "No name"}]}' | complete --load-json -
+# CHECKERR: complete: Invalid completion description in '-': $.options[0]: expected at least one of 'short', 'long' or 'old'
+echo '{"name": "broken", "subcommands": [{"name": "a", "option": []}]}' | complete --load-json -
+# CHECKERR: complete: Invalid completion description in '-': $.subcommands[0]: unknown key 'option'
+echo '{"arguments": []}' | complete --load-json -
+# CHECKERR: complete: Invalid completion description in '-': $: missing 'name'
+echo '{"name": "broken",' | complete --load-json -
+# CHECKERR: complete: Invalid JSON in '-' at offset 19: unexpected end of input
+# Nothing is added from an invalid description.
+complete -c broken
+complete --load-json $TMPDIR/nonexistent.json
+# CHECKERR: complete: Could not read '{{.*}}/nonexistent.json': No such file or directory (os error 2)
+complete --load-json $TMPDIR/mytool.json -s x
+# CHECKERR: complete: invalid option combination
+# CHECKERR:
+# CHECKERR: {{.*}}checks/complete-json.fish (line {{\d+}}):
+# CHECKERR: complete --load-json $TMPDIR/mytool.json -s x
+# CHECKERR: ^
+# CHECKERR:
+# CHECKERR: (Type 'help complete' for related documentation)
@@ -746,3 +746,38 @@ if string match -rq -- '^[a-z]+$' $USER
     string match -rq -- "$USER\t.*" (complete -C "echo ~$first_letter_wrong_case")
     or echo "`complete -C'echo ~$first_letter_wrong_case'` did not yield $USER"
 end
+
+# Fuzzy matching mode: any subsequence matches, ordered by relevance.
+complete -c fuzzy -xa "recheck git-commit gitconfig checkout git-checkout"
+complete -C"fuzzy che"
+# CHECK: checkout
+begin
+    set -l fish_completion_match_mode fuzzy
+    complete -C"fuzzy che"
+    # CHECK: checkout
+    # CHECK: git-checkout
+    # CHECK: recheck
+    complete -C"fuzzy GCO"
+    # CHECK: git-commit
+    # CHECK: git-checkout
+    # CHECK: gitconfig
+    complete -C"fuzzy xyz"
+end
+complete -C"fuzzy che"
+# CHECK: checkout
+
+# Variables are described with their scope and value, functions with their description.
+set -g __test_global_var a b
+complete -C'echo $__test_global_v'
+# CHECK: $__test_global_var{{\t}}Global variable: 'a' 'b'
+begin
+    set -l __test_local_var c
+    complete -C'echo $__test_local_v'
+    # CHECK: $__test_local_var{{\t}}Local variable: c
+end
+function __test_described_function --description 'Does a thing'
+end
+complete -C'functions __test_described_f'
+# CHECK: __test_described_function{{\t}}Does a thing
+complete -C'type __test_described_f'
+# CHECK: __test_described_function{{\t}}Does a thing
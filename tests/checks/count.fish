@@ -48,3 +48,29 @@ echo -n 0 | count
 
 echo 1 | count
 # CHECK: 1
+
+# Counting modes
+echo -n 0 | count --lines
+# CHECK: 1
+printf '%s\n' a b | count --lines c
+# CHECK: 3
+printf 'ab\nc' | count --bytes
+# CHECK: 4
+count --bytes foo ü
+# CHECK: 7
+printf '%s\n' 12 1234 123 | count --max-width
+# CHECK: 4
+count --max-width 漢字 abc (set_color red)x(set_color normal)
+# CHECK: 4
+count --max-width
+# CHECK: 0
+echo $status
+# CHECK: 1
+
+# Modes are only recognized as the first argument
+count foo --lines
+# CHECK: 2
+count --lines --bytes
+# CHECK: 1
+count --line
+# CHECK: 1
@@ -0,0 +1,43 @@
+#RUN: fish=%fish %fish %s
+
+set -l tmpdir (mktemp -d)
+cd $tmpdir
+
+printf '%s\n' 'set -l total 0' 'for i in 1 2 3 4' '    set total (math $total + $i)' end \
+    'echo total $total' >count.fish
+
+printf '%s\n' 'breakpoint where | head -n 2' 'breakpoint step' 'breakpoint next' \
+    "breakpoint add --condition 'test \$i -eq 3' 3" 'breakpoint watch total' 'breakpoint list' \
+    'breakpoint continue' 'breakpoint delete 2' 'breakpoint continue' 'echo i is $i' \
+    'breakpoint continue' | $fish --debug-script count.fish
+# CHECKERR: Stopped at count.fish (line 1):
+# CHECKERR:     set -l total 0
+# CHECK: decorated_statement at count.fish (line 1):
+# CHECK:     set -l total 0
+# CHECKERR: Stopped at count.fish (line 2):
+# CHECKERR:     for i in 1 2 3 4
+# CHECKERR: Stopped at count.fish (line 3):
+# CHECKERR:     set total (math $total + $i)
+# CHECK: Breakpoint 1 at line 3
+# CHECK: Watchpoint 2: $total
+# CHECK: 1{{\t}}count.fish:3 if test $i -eq 3
+# CHECK: 2{{\t}}$total
+# CHECKERR: Watchpoint 2: $total changed to 1
+# CHECKERR: Watchpoint 2 at count.fish (line 3):
+# CHECKERR:     set total (math $total + $i)
+# CHECKERR: Breakpoint 1 at count.fish (line 3):
+# CHECKERR:     set total (math $total + $i)
+# CHECK: i is 3
+# CHECK: total 10
+
+breakpoint step
+# CHECKERR: breakpoint: No script is being debugged, see `fish --debug-script`
+
+$fish --debug-script
+echo $status
+# CHECKERR: fish: --debug-script needs a script or commands to debug
+# CHECKERR:
+# CHECK: 1
+
+cd /
+rm -r $tmpdir
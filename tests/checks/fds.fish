@@ -125,3 +125,12 @@ $fish "$tmpfile"
 echo end fish
 # CHECK: start fish
 # CHECK: end fish
+
+# fds fish inherited without CLOEXEC, like the pipes of a make jobserver, are passed on to
+# external commands, even above the fds that are redirected.
+$fish -c "$helper print_fds" 5</dev/null
+# CHECK: 0 1 2 5
+$fish -c "$helper print_fds 3</dev/null" 5</dev/null
+# CHECK: 0 1 2 3 5
+$fish -c "$helper print_fds" 12</dev/null
+# CHECK: 0 1 2 12
@@ -0,0 +1,116 @@
+# RUN: %fish %s
+
+set -l doc '{"items": [{"name": "apple"}, {"name": "pear", "tags": ["green", "sweet"], "n": 2.5e1}],
+ "ok": true, "nothing": null, "odd key.[x]": "a\"b\\\\cé🐟"}'
+
+echo $doc | fish_json get .items[1].name
+# CHECK: pear
+echo $doc | fish_json get '.items[-1].tags'
+# CHECK: green
+# CHECK: sweet
+echo $doc | fish_json get .items[1].n .ok
+# CHECKERR: fish_json get: too many arguments
+# CHECKERR: {{.*}}checks/fish_json.fish (line {{\d+}}):
+# CHECKERR: echo $doc | fish_json get .items[1].n .ok
+# CHECKERR: ^
+# CHECKERR: (Type 'help fish_json' for related documentation)
+echo $doc | fish_json get .items[1].n
+# CHECK: 2.5e1
+echo $doc | fish_json get .nothing
+# CHECK: null
+echo $doc | fish_json get '["odd key.[x]"]'
+# CHECK: a"b\cé🐟
+echo $doc | fish_json get .items[0]
+# CHECK: {"name":"apple"}
+echo $doc | fish_json get .items
+# CHECK: {"name":"apple"}
+# CHECK: {"name":"pear","tags":["green","sweet"],"n":2.5e1}
+
+echo $doc | fish_json get .items[2].name
+echo $status
+# CHECK: 1
+echo $doc | fish_json get -q .ok
+and echo found
+# CHECK: found
+
+echo $doc | fish_json get --set fruit_tags '.items[1].tags'
+set -S fruit_tags
+# CHECK: $fruit_tags: set in global scope, unexported, with 2 elements
+# CHECK: $fruit_tags[1]: |green|
+# CHECK: $fruit_tags[2]: |sweet|
+echo '"multi\nline"' | fish_json get --set multi
+count $multi
+# CHECK: 1
+echo $doc | fish_json get --set fruit_tags .missing
+count $fruit_tags
+# CHECK: 0
+
+echo $doc | fish_json keys
+# CHECK: items
+# CHECK: ok
+# CHECK: nothing
+# CHECK: odd key.[x]
+echo $doc | fish_json keys .items
+# CHECK: 0
+# CHECK: 1
+echo $doc | fish_json keys .ok
+echo $status
+# CHECK: 1
+
+echo '{"a": [1,]}' | fish_json get .a
+# CHECKERR: fish_json get: Invalid JSON at offset 9: unexpected character ']'
+echo $status
+# CHECK: 2
+echo '{"a": 1} x' | fish_json get
+# CHECKERR: fish_json get: Invalid JSON at offset 9: unexpected character 'x'
+echo '["abc' | fish_json get
+# CHECKERR: fish_json get: Invalid JSON at offset 5: unexpected character '\n'
+echo -n '["abc' | fish_json get
+# CHECKERR: fish_json get: Invalid JSON at offset 5: unexpected end of input
+
+echo '{}' | fish_json get items
+# CHECKERR: fish_json get: Invalid query 'items'
+# CHECKERR: {{.*}}checks/fish_json.fish (line {{\d+}}):
+# CHECKERR: echo '{}' | fish_json get items
+# CHECKERR: ^
+# CHECKERR: (Type 'help fish_json' for related documentation)
+
+fish_json get .a
+# CHECKERR: fish_json get: expected a JSON document on standard input
+# CHECKERR: {{.*}}checks/fish_json.fish (line {{\d+}}):
+# CHECKERR: fish_json get .a
+# CHECKERR: ^
+# CHECKERR: (Type 'help fish_json' for related documentation)
+
+fish_json encode apple 'pe"ar' \t
+# CHECK: ["apple","pe\"ar","\t"]
+fish_json encode
+# CHECK: []
+fish_json encode --object name apple count 3
+# CHECK: {"name":"apple","count":"3"}
+fish_json encode --object --raw name '"apple"' count 3 tags (fish_json encode green sweet)
+# CHECK: {"name":"apple","count":3,"tags":["green","sweet"]}
+fish_json encode --raw 1 true null
+# CHECK: [1,true,null]
+fish_json encode --object name
+# CHECKERR: fish_json encode: expected pairs of keys and values
+# CHECKERR: {{.*}}checks/fish_json.fish (line {{\d+}}):
+# CHECKERR: fish_json encode --object name
+# CHECKERR: ^
+# CHECKERR: (Type 'help fish_json' for related documentation)
+fish_json encode --raw apple
+# CHECKERR: fish_json encode: Invalid JSON 'apple': unexpected character 'a'
+
+# A round trip keeps the values.
+fish_json encode a\nb c | fish_json get --set roundtrip
+set -S roundtrip
+# CHECK: $roundtrip: set in global scope, unexported, with 2 elements
+# CHECK: $roundtrip[1]: |a\nb|
+# CHECK: $roundtrip[2]: |c|
+
+fish_json frobnicate
+# CHECKERR: fish_json frobnicate: invalid subcommand
+# CHECKERR: {{.*}}checks/fish_json.fish (line {{\d+}}):
+# CHECKERR: fish_json frobnicate
+# CHECKERR: ^
+# CHECKERR: (Type 'help fish_json' for related documentation)
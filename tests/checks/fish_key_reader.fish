@@ -17,3 +17,26 @@ echo | builtin fish_key_reader
 set -l dir (dirname $fish)
 echo | command $dir/fish_key_reader
 # CHECKERR: Stdin must be attached to a tty.
+
+# Replaying a recording made with `fish --record-input`.
+set -l recording (mktemp)
+printf '# fish input recording\n0\ta\n5\t\\\\x1b[A\\\\x1b[1;5C\n20\t\\\\x03\n' >$recording
+fish_key_reader --replay $recording
+# CHECK: bind a 'do something'
+# CHECK: bind up 'do something'
+# CHECK: bind ctrl-right 'do something'
+# CHECK: bind ctrl-c 'do something'
+echo | fish_key_reader --verbose --replay $recording | head -n2
+# CHECK: # decoded from: a
+# CHECK: bind a 'do something'
+
+printf '0\tok\n10\t\\\\q\n' >$recording
+fish_key_reader --replay $recording
+# CHECKERR: fish_key_reader: Invalid recording '{{.*}}' on line 2
+rm $recording
+
+fish_key_reader --replay /nonexistent/recording
+# CHECKERR: fish_key_reader: Could not read recording '/nonexistent/recording': {{.*}}
+
+fish_key_reader --replay
+# CHECKERR: fish_key_reader: --replay: option requires an argument
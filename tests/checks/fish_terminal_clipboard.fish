@@ -0,0 +1,46 @@
+# RUN: %fish %s
+
+# Output is not a terminal, so nothing is written unless forced.
+fish_terminal_clipboard copy foo
+echo $status
+# CHECK: 1
+
+fish_terminal_clipboard copy --force foo | string escape; echo
+# CHECK: \e\]52\;c\;Zm9v\cg
+fish_terminal_clipboard copy -f a b | string escape; echo
+# CHECK: \e\]52\;c\;YQpi\cg
+printf 'fish\n' | fish_terminal_clipboard copy -f | string escape; echo
+# CHECK: \e\]52\;c\;ZmlzaAo=\cg
+fish_terminal_clipboard copy -f -- -x | string escape; echo
+# CHECK: \e\]52\;c\;LXg=\cg
+
+# Too long for some terminals.
+fish_terminal_clipboard copy -f (string repeat -n 75001 x)
+echo $status
+# CHECK: 1
+
+# Not an interactive session, so we don't know how to talk to the terminal.
+fish_terminal_clipboard paste
+echo $status
+# CHECK: 1
+
+fish_terminal_clipboard copy
+# CHECKERR: fish_terminal_clipboard copy: missing argument
+# CHECKERR: {{.*}}checks/fish_terminal_clipboard.fish (line {{\d+}}):
+# CHECKERR: fish_terminal_clipboard copy
+# CHECKERR: ^
+# CHECKERR: (Type 'help fish_terminal_clipboard' for related documentation)
+
+fish_terminal_clipboard paste foo
+# CHECKERR: fish_terminal_clipboard paste: expected 0 arguments; got 1
+# CHECKERR: {{.*}}checks/fish_terminal_clipboard.fish (line {{\d+}}):
+# CHECKERR: fish_terminal_clipboard paste foo
+# CHECKERR: ^
+# CHECKERR: (Type 'help fish_terminal_clipboard' for related documentation)
+
+fish_terminal_clipboard frobnicate
+# CHECKERR: fish_terminal_clipboard frobnicate: invalid subcommand
+# CHECKERR: {{.*}}checks/fish_terminal_clipboard.fish (line {{\d+}}):
+# CHECKERR: fish_terminal_clipboard frobnicate
+# CHECKERR: ^
+# CHECKERR: (Type 'help fish_terminal_clipboard' for related documentation)
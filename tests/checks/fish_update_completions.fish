@@ -1,5 +1,4 @@
 # RUN: %fish %s
-# REQUIRES: command -v sphinx-build
 
 mkdir -p man/man1
 
@@ -37,3 +36,25 @@ cat ./generated_completions/dirname.fish
 # CHECK: complete -c dirname -s z -l zero -d 'end each output line with NUL, not newline'
 # CHECK: complete -c dirname -l reject-unconstrained-dependencies -d 'none|all'
 # CHECK: complete -c dirname -l tilde -d '~'
+
+# mdoc pages, which may be compressed.
+cd -
+printf '%s\n' \
+    '.Dd March 1, 2026' \
+    '.Dt LISTER 1' \
+    '.Sh NAME' \
+    '.Nm lister' \
+    '.Sh DESCRIPTION' \
+    '.Bl -tag -width indent' \
+    '.It Fl a' \
+    'List all entries.' \
+    '.It Fl Fl color Ar when' \
+    'Colorize the output of' \
+    '.Nm .' \
+    '.El' >man/man1/lister.1
+gzip man/man1/lister.1
+builtin fish_update_completions --stdout man/man1/lister.1.gz
+# CHECK: # lister
+# CHECK: # Autogenerated from man page {{.*}}/man/man1/lister.1.gz
+# CHECK: complete -c lister -s a -d 'List all entries'
+# CHECK: complete -c lister -l color -d 'Colorize the output of lister'
@@ -262,4 +262,46 @@ end
 #CHECKERR: function fn_with_args -- arg
 #CHECKERR: ^~~~~~~~~~~~~~~~~~~~~~~~~~~^
 
+# Parameters are bound in order, with defaults, and calls are checked.
+function greet --param name --param greeting=hello
+    echo $greeting, $name! (count $argv)
+end
+greet world
+# CHECK: hello, world! 1
+greet world hi
+# CHECK: hi, world! 2
+greet
+echo $status
+#CHECKERR: {{.*}}/function.fish (line {{\d+}}): greet: missing argument for parameter 'name'
+#CHECKERR: greet
+#CHECKERR: ^~~~^
+# CHECK: 2
+greet a b c
+#CHECKERR: {{.*}}/function.fish (line {{\d+}}): greet: expected at most 2 arguments; got 3
+#CHECKERR: greet a b c
+#CHECKERR: ^~~~~~~~~~^
+functions --no-details greet | head -n1
+# CHECK: function greet --param name --param greeting=hello
+
+function rest --param first --param others...
+    echo $first (count $others) $others
+end
+rest a
+# CHECK: a 0
+rest a b c
+# CHECK: a 2 b c
+
+function bad_default --param a=1 --param b; end
+#CHECKERR: {{.*}}/function.fish (line {{\d+}}): function: b: required parameter after a parameter with a default value
+#CHECKERR: function bad_default --param a=1 --param b
+#CHECKERR: ^~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~^
+function bad_rest --param a... --param b; end
+#CHECKERR: {{.*}}/function.fish (line {{\d+}}): function: a: only the last parameter may take the remaining arguments
+#CHECKERR: function bad_rest --param a... --param b
+#CHECKERR: ^~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~^
+function bad_mix --param a --argument-names b; end
+#CHECKERR: {{.*}}/function.fish (line {{\d+}}): function: --param and --argument-names cannot be used together
+#CHECKERR: function bad_mix --param a --argument-names b
+#CHECKERR: ^~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~^
+
 exit 0
@@ -318,3 +318,41 @@ functions -d desc unknown_function
 # CHECKERR: functions -d desc unknown_function
 # CHECKERR: ^
 # CHECKERR: (Type 'help functions' for related documentation)
+
+function dep_a
+    dep_b | string upper
+    set -l x (dep_c (dep_d))
+    $dynamic arg
+    command dep_external
+    if test -n "$(dep_e)"
+        dep_a
+    end
+end
+function dep_b
+    echo b
+    dep_c
+end
+function dep_c
+    printf c
+end
+functions --depends dep_a
+# CHECK: dep_b
+# CHECK: string
+# CHECK: set
+# CHECK: dep_c
+# CHECK: dep_d
+# CHECK: dep_external
+# CHECK: test
+# CHECK: dep_e
+# CHECK: dep_a
+functions --depends --recursive dep_b
+# CHECK: echo
+# CHECK: dep_c
+# CHECK: printf
+
+functions --depends unknown_function
+# CHECKERR: functions: Function 'unknown_function' does not exist
+# CHECKERR: {{.*}}/checks/functions.fish (line {{\d+}}):
+# CHECKERR: functions --depends unknown_function
+# CHECKERR: ^
+# CHECKERR: (Type 'help functions' for related documentation)
@@ -89,6 +89,84 @@ string join \n **/bar | sort
 # CHECK: bar
 # CHECK: foo/bar
 
+rm -Rf *
+
+# fish_glob_ignore keeps ** out of directories.
+mkdir -p src/node_modules/dep lib/target node_modules
+touch src/a.js src/node_modules/dep/b.js lib/target/c.js node_modules/d.js lib/e.js
+begin
+    set -l fish_glob_ignore node_modules 'targ*'
+    string join \n **/*.js
+    # CHECK: lib/e.js
+    # CHECK: src/a.js
+    string join \n **.js
+    # CHECK: lib/e.js
+    # CHECK: src/a.js
+    # Explicit paths are not ignored.
+    string join \n node_modules/**.js
+    # CHECK: node_modules/d.js
+end
+
+# Walking with threads gives the same results.
+begin
+    set -l fish_glob_threads 4
+    string join \n **/*.js
+    # CHECK: lib/e.js
+    # CHECK: lib/target/c.js
+    # CHECK: node_modules/d.js
+    # CHECK: src/a.js
+    # CHECK: src/node_modules/dep/b.js
+    string join \n **/b.js
+    # CHECK: src/node_modules/dep/b.js
+    # A literal name after other wildcards is matched against the directory entries.
+    count */b.js
+    # CHECK: 0
+    string join \n */a.js
+    # CHECK: src/a.js
+end
+
+rm -Rf *
+
+# Glob qualifiers select files by type, size and modification time.
+mkdir dir
+touch file exe old
+chmod +x exe
+touch -t 200001010000 old
+ln -s file link
+string repeat -n 2000 x >big
+string join \n *(.)
+# CHECK: big
+# CHECK: exe
+# CHECK: file
+# CHECK: old
+string join \n *(/) *(@) *(*)
+# CHECK: dir
+# CHECK: link
+# CHECK: exe
+string join \n *(m+30)
+# CHECK: old
+string join \n *(.m-1)
+# CHECK: big
+# CHECK: exe
+# CHECK: file
+string join \n *(L+1000) *(.Lk-1)
+# CHECK: big
+# CHECK: exe
+# CHECK: file
+# CHECK: old
+string join \n **(/)
+# CHECK: dir
+echo *(p)
+# CHECKERR: {{.*}}glob.fish (line {{\d+}}): No matches for wildcard '*(p)'. See `help language#wildcards-globbing`.
+# CHECKERR: echo *(p)
+# CHECKERR:      ^~~^
+echo "Qualifiers are only recognized after a wildcard:" (echo x)(echo y)
+# CHECK: Qualifiers are only recognized after a wildcard: xy
+echo *(echo ile)
+# CHECK: file
+echo 'ls *(.m-7) **(/)' | fish_indent
+# CHECK: ls *(.m-7) **(/)
+
 # Clean up.
 cd $oldpwd
 rm -Rf $tmpdir
@@ -0,0 +1,97 @@
+#RUN: fish_indent=%fish_indent %fish %s
+
+set -l name world
+set -l list a b
+
+cat <<EOF
+Hello, $name!
+"quoted" 'single' \n \$name \\ $list[2]
+$(echo substituted) (not substituted)
+EOF
+# CHECK: Hello, world!
+# CHECK: "quoted" 'single' \n $name \ b
+# CHECK: substituted (not substituted)
+
+# A quoted delimiter means no expansions.
+cat <<'EOF'
+Hello, $name! \$
+EOF
+# CHECK: Hello, $name! \$
+
+cat <<"EOF"
+$(echo no)
+EOF
+# CHECK: $(echo no)
+
+# The indentation of the delimiter line is removed.
+begin
+    cat <<END
+    indented
+      more
+    END
+end
+# CHECK: {{^}}indented
+# CHECK: {{^}}  more
+
+# Several here-documents on one line, and commands after them.
+cat <<ONE | string upper; echo after
+first
+ONE
+# CHECK: FIRST
+# CHECK: after
+
+cat <<ONE; cat <<TWO
+first
+ONE
+second
+TWO
+# CHECK: first
+# CHECK: second
+
+function heredoc_func
+    string upper <<EOF
+    $argv
+    EOF
+end
+heredoc_func from function
+# CHECK: FROM FUNCTION
+
+cat <<EOF | count
+EOF
+# CHECK: 0
+
+# Here-strings.
+string upper <<< "hello $name"
+# CHECK: HELLO WORLD
+cat <<< $list
+# CHECK: a
+# CHECK: b
+read -l var <<< text
+echo $var
+# CHECK: text
+cat 3<<< fd <&3
+# CHECK: fd
+
+$fish -c 'cat <<EOF'
+# CHECKERR: fish: Unexpected end of string, here-document is not terminated
+# CHECKERR: cat <<EOF
+# CHECKERR: {{      }}^~^
+
+$fish -c 'cat <<EOF
+not the end'
+# CHECKERR: fish: Unexpected end of string, here-document is not terminated
+# CHECKERR: cat <<EOF
+# CHECKERR: {{      }}^~^
+
+echo 'begin
+cat <<"EOF" >/dev/null
+  body   with   spaces
+EOF
+    echo  done
+end' | $fish_indent
+# CHECK: begin
+# CHECK: {{^}}    cat <<"EOF" >/dev/null
+# CHECK: {{^}}  body   with   spaces
+# CHECK: {{^}}EOF
+# CHECK: {{^}}    echo done
+# CHECK: end
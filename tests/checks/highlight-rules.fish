@@ -0,0 +1,37 @@
+#RUN: %fish %s
+
+# Colors are easier to read as escape sequences.
+function highlight
+    echo -n $argv[1] | builtin fish_indent --ansi | string escape
+end
+
+set -g fish_color_normal normal
+set -g fish_color_command blue
+set -g fish_color_param cyan
+set -g fish_color_quote yellow
+set -g fish_color_issue red --bold
+
+highlight 'git commit -m "fix #123" foo#7'
+# CHECK: \e\[34mgit\e\[39m\ \e\[36mcommit\e\[39m\ \e\[36m-m\e\[39m\ \e\[33m\"fix\ \#123\"\e\[39m\ \e\[36mfoo#7\e\[39m
+
+# Matches are colored with the color of the rule on top of their own.
+set -g fish_highlight_rule_issue '#\d+'
+highlight 'git commit -m "fix #123" foo#7'
+# CHECK: \e\[34mgit\e\[39m\ \e\[36mcommit\e\[39m\ \e\[36m-m\e\[39m\ \e\[33m\"fix\ \e\[31\;1m#123\e\[\;33m\"\e\[39m\ \e\[36mfoo\e\[31\;1m#7\e\[m
+
+# Rules can be restricted to text with some roles.
+set -g fish_highlight_rule_issue --role=quote '#\d+'
+highlight 'git commit -m "fix #123" foo#7'
+# CHECK: \e\[34mgit\e\[39m\ \e\[36mcommit\e\[39m\ \e\[36m-m\e\[39m\ \e\[33m\"fix\ \e\[31\;1m#123\e\[\;33m\"\e\[39m\ \e\[36mfoo#7\e\[39m
+
+# Rules with unknown roles or invalid patterns are ignored.
+set -g fish_highlight_rule_issue --role=nonsense '#\d+'
+highlight 'echo #1'
+# CHECK: \e\[34mecho\e\[39m\ \#1
+set -g fish_highlight_rule_issue '(('
+highlight 'echo #1'
+# CHECKERR: warning: Invalid highlighting rule (( regex parse error:
+# CHECKERR: ((
+# CHECKERR: ^
+# CHECKERR: error: unclosed group
+# CHECK: \e\[34mecho\e\[39m\ \#1
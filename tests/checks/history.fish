@@ -42,6 +42,8 @@ history --prefix clear-session
 #CHECKERR: history clear-session: subcommand takes no options
 history --show-time merge
 #CHECKERR: history merge: subcommand takes no options
+history clear --failed-only
+#CHECKERR: history: clear: subcommand takes no options
 
 # Now with the history builtin.
 builtin history --search --merge
@@ -62,6 +64,58 @@ builtin history --contains save
 #CHECKERR: history save: subcommand takes no options
 builtin history -t merge
 #CHECKERR: history merge: subcommand takes no options
+builtin history save --cwd .
+#CHECKERR: history save: subcommand takes no options
+builtin history sync --prefix
+#CHECKERR: history sync: subcommand takes no options
+history sync xyz
+#CHECKERR: history sync: expected 0 arguments; got 1
+builtin history save --format zsh
+#CHECKERR: history save: subcommand takes no options
+builtin history import ~/.zsh_history
+#CHECKERR: history import: missing --format
+builtin history export --format csh
+#CHECKERR: history export: unknown format 'csh'
+builtin history import --format zsh
+#CHECKERR: history import: expected 1 arguments; got 0
+builtin history export --format bash --prefix
+#CHECKERR: history export: subcommand takes no options
+builtin history export --format atuin
+#CHECKERR: history export: can't export to atuin, use 'atuin import fish' instead
+
+# Import zsh's extended history, which continues multi-line commands with a backslash.
+set -l zsh_history (mktemp)
+printf '%s\n' ': 1700000000:2;echo from zsh' ': 1700000001:0;echo multi\\' line >$zsh_history
+history import --format zsh $zsh_history
+builtin history export --format bash
+#CHECK: #1700000000
+#CHECK: echo from zsh
+#CHECK: #1700000001
+#CHECK: echo multi
+#CHECK: line
+# Importing again does not duplicate anything.
+history import --format zsh $zsh_history
+builtin history export --format zsh
+#CHECK: : 1700000000:2;echo from zsh
+#CHECK: : 1700000001:0;echo multi\
+#CHECK: line
+rm $zsh_history
+
+# Redacting removes every command containing the text, matched literally.
+builtin history append 'echo password=Hunter2' 'echo harmless' 'echo a*b' 'echo axb'
+builtin history redact hunter2 'a*b'
+builtin history search --reverse
+#CHECK: echo from zsh
+#CHECK: echo multi
+#CHECK: line
+#CHECK: echo harmless
+#CHECK: echo axb
+builtin history redact
+#CHECKERR: history redact: expected >= 1 arguments; got 0
+builtin history redact --show-time abc
+#CHECKERR: history redact: subcommand takes no options
+builtin history redact ''
+#CHECKERR: history redact: can't redact the empty string
 
 # Now do a history command that should succeed so we exit with a zero,
 # success, status.
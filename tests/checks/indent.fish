@@ -701,3 +701,35 @@ function __fish_print_help
 end
 PATH=hello fish_indent --help
 # CHECK: Help using PATH[1]=hello
+
+# --range only formats the given lines.
+set -l unformatted 'if true
+echo a
+echo  b
+  echo c
+end'
+echo $unformatted | $fish_indent --range 2:2
+# CHECK: if true
+# CHECK: {{^}}    echo a
+# CHECK: {{^}}echo  b
+# CHECK: {{^}}  echo c
+# CHECK: {{^}}end
+
+echo $unformatted | $fish_indent --range 3:
+# CHECK: if true
+# CHECK: {{^}}echo a
+# CHECK: {{^}}    echo b
+# CHECK: {{^}}    echo c
+# CHECK: {{^}}end
+
+echo $unformatted | $fish_indent --check --range :1
+echo $status
+# CHECK: 0
+echo $unformatted | $fish_indent --check --range 2:3
+echo $status
+# CHECK: 1
+
+echo $unformatted | $fish_indent --range 3:2
+# CHECKERR: fish_indent: Invalid line range '3:2'
+echo $unformatted | $fish_indent --range 3
+# CHECKERR: fish_indent: Invalid line range '3'
@@ -0,0 +1,29 @@
+#RUN: %fish %s
+
+function on_job_complete --on-event fish_job_complete
+    set -l jobid $argv[1]
+    set -l cmd $argv[3]
+    set -l status_ $argv[4]
+    set -l duration $argv[5]
+    echo job $jobid "'$cmd'" status $status_
+    test "$duration" -ge 0; and echo has duration
+end
+
+sh -c 'exit 3' &
+wait
+#CHECK: job 1 'sh -c 'exit 3' &' status 3
+#CHECK: has duration
+
+# Foreground jobs don't fire the event.
+true
+echo done
+#CHECK: done
+
+# Background jobs started from event handlers don't fire it either.
+function start_job --on-event start_job
+    true &
+end
+emit start_job
+wait
+echo waited
+#CHECK: waited
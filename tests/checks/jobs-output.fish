@@ -0,0 +1,73 @@
+#RUN: %fish %s
+
+# Without fish_background_output, output goes through as usual.
+sh -c 'echo uncaptured' &
+wait
+#CHECK: uncaptured
+jobs --output %1
+#CHECKERR: jobs: No captured output for job: %1
+
+set -g fish_background_output capture
+
+sh -c 'echo out; echo err >&2' &
+wait
+echo waited
+#CHECK: waited
+jobs --output %1
+#CHECK: out
+#CHECK: err
+jobs --query --output %1
+and echo has output
+#CHECK: has output
+
+# A running job shows what it has written so far.
+set -l marker (mktemp)
+rm $marker
+sh -c 'echo early; touch "$1"; sleep 10' sh $marker &
+while not test -e $marker
+    sleep 0.01
+end
+jobs --output %1
+#CHECK: early
+kill $last_pid
+wait
+rm $marker
+
+# Attaching prints the output so far and lets further output through.
+set -l marker (mktemp)
+rm $marker
+sh -c 'echo before; touch "$1"; while test -e "$1"; do sleep 0.01; done; echo after' sh $marker &
+while not test -e $marker
+    sleep 0.01
+end
+jobs --attach %1
+#CHECK: before
+rm $marker
+wait
+sleep 0.2
+#CHECK: after
+jobs --output %1
+#CHECKERR: jobs: No captured output for job: %1
+
+# A disowned job keeps the output captured until then.
+sh -c 'echo disowned; touch "$1"; sleep 10' sh $marker &
+set -l pid $last_pid
+while not test -e $marker
+    sleep 0.01
+end
+disown
+jobs --output %1
+#CHECK: disowned
+kill $pid
+rm $marker
+
+# Redirected output is not captured.
+sh -c 'echo redirected' >/dev/null 2>&1 &
+wait
+jobs --output %1
+#CHECKERR: jobs: No captured output for job: %1
+
+jobs --output
+#CHECKERR: jobs: --output requires a job ID
+jobs --output %foo
+#CHECKERR: jobs: '%foo' is not a valid job ID
@@ -139,3 +139,20 @@ disown 252
 
 jobs %abc
 # CHECKERR: jobs: '%abc' is not a valid job ID
+
+sleep 5 &
+set -l sleeppid $last_pid
+jobs --format '{id}\t{pid}\t{state}\t{command}' | string replace $sleeppid PID
+# CHECK: 1	PID	running	sleep 5 &
+jobs --format '\{{id}\}' --last
+# CHECK: {1}
+jobs --format '{start_time}' | string match -qr '^\d+$'
+and echo start time is a number
+# CHECK: start time is a number
+jobs --json | string replace $sleeppid PID | string replace -r '"start_time":\d+' '"start_time":0' | string replace -r '"pgid":(\d+|null)' '"pgid":0'
+# CHECK: {"id":1,"pids":[PID],"pgid":0,"state":"running","start_time":0,"command":"sleep 5 &"}
+jobs --format '{pid'
+# CHECKERR: jobs: Invalid format field 'pid'
+jobs --format '{nope}'
+# CHECKERR: jobs: Invalid format field 'nope'
+kill %1
@@ -0,0 +1,59 @@
+#RUN: fish=%fish %fish %s
+
+set -l tmpdir (mktemp -d)
+
+printf '%s\n' 'eval echo hi' 'test -n $x' 'test foo' '. ./config.fish' 'echo %self' \
+    'cat foo ^/dev/null' 'switch $x' end | $fish --lint
+echo $status
+# CHECK: -:1:1: eval of a fixed string, run the command directly [useless-eval]
+# CHECK: -:2:9: $x is not quoted, so it becomes no argument if it is empty, or several if it has several elements [unquoted-variable]
+# CHECK: -:3:6: test with one argument only checks that it is not empty, use -n [test-arguments]
+# CHECK: -:4:1: '.' is deprecated, use 'source' [deprecated-syntax]
+# CHECK: -:5:6: '%self' is no longer expanded, use $fish_pid [deprecated-syntax]
+# CHECK: -:6:9: '^' no longer redirects stderr, use '2>' [deprecated-syntax]
+# CHECK: -:7:8: $x is not quoted, so it becomes no argument if it is empty, or several if it has several elements [unquoted-variable]
+# CHECK: 1
+
+# Code that is fine.
+printf '%s\n' 'eval $cmd' 'test -n "$x"' 'test $status -eq 0' '[ "$a" = "$b" -a -d / ]' \
+    'test -n (echo)' 'switch "$x"' end 'string match -r ^foo bar' 'source ./config.fish' >$tmpdir/good.fish
+$fish --lint $tmpdir/good.fish
+echo $status
+# CHECK: 0
+
+printf '%s\n' 'test -n "$a" "$b"' '[ -n "$a"' | $fish --lint
+# CHECK: -:1:6: unexpected argument at index {{\d+}}: '"$b"' [test-arguments]
+# CHECK: -:2:1: the last argument must be ']' [test-arguments]
+
+# Syntax errors are reported instead of everything else.
+printf '%s\n' 'eval echo hi' 'echo (' | $fish --lint
+# CHECK: -:2:{{\d+}}: {{.*}} [syntax-error]
+
+# Skipping checks.
+printf '%s\n' 'eval echo hi # fish-lint: ignore' '# fish-lint: ignore=useless-eval' 'eval echo hi' \
+    'eval echo hi # fish-lint: ignore=deprecated-syntax' '. ./config.fish' | $fish --lint
+# CHECK: -:4:1: eval of a fixed string, run the command directly [useless-eval]
+# CHECK: -:5:1: '.' is deprecated, use 'source' [deprecated-syntax]
+
+printf '%s\n' 'eval echo hi' '. ./config.fish' 'test -n $x' | $fish --lint --lint-ignore=useless-eval,deprecated-syntax
+# CHECK: -:3:9: $x is not quoted, so it becomes no argument if it is empty, or several if it has several elements [unquoted-variable]
+
+echo 'eval echo "hi"' >$tmpdir/eval.fish
+$fish --lint --lint-format=json $tmpdir/eval.fish | string replace $tmpdir TMPDIR
+# CHECK: {"file":"TMPDIR/eval.fish","line":1,"column":1,"check":"useless-eval","message":"eval of a fixed string, run the command directly"}
+
+$fish --lint --lint-ignore=no-such-check $tmpdir/eval.fish
+echo $status
+# CHECKERR: fish: Unknown lint check 'no-such-check'
+# CHECKERR:
+# CHECK: 1
+
+$fish --lint --lint-format=xml $tmpdir/eval.fish
+# CHECKERR: fish: Unknown lint format 'xml'
+# CHECKERR:
+
+$fish --lint $tmpdir/no-such-file.fish 2>/dev/null
+echo $status
+# CHECK: 2
+
+rm -r $tmpdir
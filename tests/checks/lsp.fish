@@ -0,0 +1,48 @@
+#RUN: fish=%fish %fish %s
+
+# Frame each argument as a message.
+function messages
+    for message in $argv
+        printf 'Content-Length: %d\r\n\r\n%s' (string length -- $message) $message
+    end
+end
+
+# Print the responses one per line, and return the server's exit status.
+function lsp
+    $fish --no-config --lsp | string replace -ra 'Content-Length: \d+\r\n\r\n' \n | string match -v ''
+    return $pipestatus[1]
+end
+
+messages '{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}' \
+    '{"jsonrpc":"2.0","method":"initialized","params":{}}' \
+    '{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///a.fish","languageId":"fish","version":1,"text":"function greet\n    echo hello\nend\ngreet\n"}}}' \
+    '{"jsonrpc":"2.0","id":2,"method":"textDocument/hover","params":{"textDocument":{"uri":"file:///a.fish"},"position":{"line":1,"character":5}}}' \
+    '{"jsonrpc":"2.0","id":3,"method":"textDocument/hover","params":{"textDocument":{"uri":"file:///a.fish"},"position":{"line":3,"character":2}}}' \
+    '{"jsonrpc":"2.0","id":4,"method":"textDocument/definition","params":{"textDocument":{"uri":"file:///a.fish"},"position":{"line":3,"character":2}}}' \
+    '{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///b.fish","languageId":"fish","version":1,"text":"echo hi\nend\n"}}}' \
+    '{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"file:///c.fish","languageId":"fish","version":1,"text":"if true\necho hi\nend\n"}}}' \
+    '{"jsonrpc":"2.0","id":5,"method":"textDocument/formatting","params":{"textDocument":{"uri":"file:///c.fish"},"options":{}}}' \
+    '{"jsonrpc":"2.0","id":6,"method":"textDocument/rangeFormatting","params":{"textDocument":{"uri":"file:///c.fish"},"range":{"start":{"line":0,"character":0},"end":{"line":1,"character":0}},"options":{}}}' \
+    '{"jsonrpc":"2.0","id":7,"method":"textDocument/rangeFormatting","params":{"textDocument":{"uri":"file:///c.fish"},"range":{"start":{"line":1,"character":0},"end":{"line":1,"character":3}},"options":{}}}' \
+    '{"jsonrpc":"2.0","id":8,"method":"textDocument/foo","params":{}}' \
+    '{"jsonrpc":"2.0","id":9,"method":"shutdown"}' \
+    '{"jsonrpc":"2.0","method":"exit"}' | lsp
+echo $status
+# CHECK: {"jsonrpc":"2.0","id":1,"result":{"capabilities":{"textDocumentSync":1,"hoverProvider":true,"definitionProvider":true,{{.*}}
+# CHECK: {"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///a.fish","diagnostics":[]}}
+# CHECK: {"jsonrpc":"2.0","id":2,"result":{"contents":{"kind":"markdown","value":"**echo** (builtin)\n\nPrint arguments"},"range":{"start":{"line":1,"character":4},"end":{"line":1,"character":8}}}}
+# CHECK: {"jsonrpc":"2.0","id":3,"result":{"contents":{"kind":"markdown","value":"**greet** (function, defined on line 1)"},"range":{"start":{"line":3,"character":0},"end":{"line":3,"character":5}}}}
+# CHECK: {"jsonrpc":"2.0","id":4,"result":{"uri":"file:///a.fish","range":{"start":{"line":0,"character":9},"end":{"line":0,"character":14}}}}
+# CHECK: {"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///b.fish","diagnostics":[{"range":{"start":{"line":1,"character":0},"end":{"line":1,"character":3}},"severity":1,"source":"fish","message":"{{[^"]*}}"}]}}
+# CHECK: {"jsonrpc":"2.0","method":"textDocument/publishDiagnostics","params":{"uri":"file:///c.fish","diagnostics":[]}}
+# CHECK: {"jsonrpc":"2.0","id":5,"result":[{"range":{"start":{"line":0,"character":0},"end":{"line":3,"character":0}},"newText":"if true\n    echo hi\nend\n"}]}
+# CHECK: {"jsonrpc":"2.0","id":6,"result":[]}
+# CHECK: {"jsonrpc":"2.0","id":7,"result":[{"range":{"start":{"line":0,"character":0},"end":{"line":3,"character":0}},"newText":"if true\n    echo hi\nend\n"}]}
+# CHECK: {"jsonrpc":"2.0","id":8,"error":{"code":-32601,"message":"Unknown method 'textDocument/foo'"}}
+# CHECK: {"jsonrpc":"2.0","id":9,"result":null}
+# CHECK: 0
+
+# Exiting without a shutdown request is an error.
+messages '{"jsonrpc":"2.0","method":"exit"}' | lsp
+echo $status
+# CHECK: 1
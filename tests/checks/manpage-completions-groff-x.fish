@@ -1,11 +1,9 @@
 #RUN: %fish %s
-#REQUIRES: command -v python3
 
 # Regression test for groff \X'...' device control escapes in man pages.
 # help2man 1.50+ emits \X'tty: link URL' hyperlinks which broke the parser.
 # See: coreutils 9.10 man pages.
 
-set -l script (status dirname)/../../share/tools/create_manpage_completions.py
 set -l tmpdir (mktemp -d)
 
 # Minimal man page with \X'tty: link' escapes as produced by help2man 1.50
@@ -30,7 +28,7 @@ printf '%s\n' \
     'Nobody.' \
     > $tmpdir/testcmd.1
 
-python3 $script --stdout $tmpdir/testcmd.1 | string match -r '^complete.*'
+builtin fish_update_completions --stdout $tmpdir/testcmd.1 | string match -r '^complete.*'
 #CHECK: complete -c testcmd -s a -l all -d 'show all entries'
 #CHECK: complete -c testcmd -s v -l verbose -d 'be verbose'
 #CHECK: complete -c testcmd -l help -d 'display help'
@@ -438,3 +438,91 @@ math -s 6 --scale-mode=ceiling "2 / 3 - 1"
 
 math -s 6 --scale-mode=random "2 / 3 - 1"
 # CHECKERR: math: random: invalid mode
+
+# User-defined functions
+math --define 'f(x)=x^2+1' 'f(3)'
+# CHECK: 10
+# "x" is a parameter when an operand is expected, otherwise it multiplies.
+math -d 'sq(x) = x x x' 'sq 3'
+# CHECK: 9
+math -d 'k = 5' -d 'g(a, b) = a * k + b' 'g(2, 3) + k'
+# CHECK: 18
+# Parameters shadow constants.
+math -d 'double(e) = e * 2' 'double(3)'
+# CHECK: 6
+begin
+    set -l fish_math_functions 'clamp(v, lo, hi) = min(max(v, lo), hi)' 'percent(a, b) = clamp(100 * a / b, 0, 100)'
+    math 'percent(150, 100)'
+    # CHECK: 100
+    math -d 'used = 3' -d 'total = 12' percent used, total
+    # CHECK: 25
+    # Later definitions take precedence.
+    math -d 'percent(a, b) = a / b' 'percent(150, 100)'
+    # CHECK: 1.5
+end
+not math -d 'inv(x) = 1 / x' 'inv(0)'
+# CHECKERR: math: Error: Division by zero
+# CHECKERR: 'inv(0)'
+# CHECKERR:  ^~^
+not math -d 'sin(x) = x' 1
+# CHECKERR: math: Error: Builtin functions cannot be redefined
+# CHECKERR: 'sin(x) = x'
+# CHECKERR:  ^~^
+not math -d 'f(x) = y' 1
+# CHECKERR: math: Error: Unknown function
+# CHECKERR: 'f(x) = y'
+# CHECKERR:        ^
+not math -d 'f(x, x) = x' 1
+# CHECKERR: math: Error: Invalid definition, expected NAME=EXPRESSION or NAME(PARAMETERS)=EXPRESSION
+# CHECKERR: 'f(x, x) = x'
+# CHECKERR:       ^
+not math -d 'f(x) x' 1
+# CHECKERR: math: Error: Invalid definition, expected NAME=EXPRESSION or NAME(PARAMETERS)=EXPRESSION
+# CHECKERR: 'f(x) x'
+# CHECKERR:      ^
+# Functions can't call themselves.
+not math -d 'f(x) = f(x)' 1
+# CHECKERR: math: Error: Unknown function
+# CHECKERR: 'f(x) = f(x)'
+# CHECKERR:        ^
+
+# Units
+math 3GiB / 250MiB
+# CHECK: 12.288
+math 1kB + 1KiB
+# CHECK: 2024
+math 1h + 30min + 1m + 500ms
+# CHECK: 5460.5
+math 2w / 1d
+# CHECK: 14
+# "x" is not a unit.
+math 5x 3
+# CHECK: 15
+not math 3XB
+# CHECKERR: math: Error: Unknown function
+# CHECKERR: '3XB'
+# CHECKERR:   ^^
+math --format=bytes 3GiB + 512MiB
+# CHECK: 3.5 GiB
+math -f bytes 1000
+# CHECK: 1000 B
+math -f bytes 2kB
+# CHECK: 1.95 KiB
+math -f bytes -s0 1.7KiB
+# CHECK: 1 KiB
+math --format=duration 93784
+# CHECK: 1d 2h 3m 4s
+math -f duration 1h + 90min
+# CHECK: 2h 30m
+math -f duration 0.25
+# CHECK: 0.25s
+math -f duration -- -90
+# CHECK: -1m 30s
+math -f duration 0
+# CHECK: 0s
+math -f number 1KiB
+# CHECK: 1024
+math -f bytes -b hex 3
+# CHECKERR: math: invalid option combination, --format is only valid for base 10
+math -f nope 3
+# CHECKERR: math: nope: invalid format
@@ -50,6 +50,27 @@ echo (_ "Control disassembler syntax highlighting style")
 echo (_ untranslatable)
 # CHECK: untranslatable
 
+# Changes apply to the very next message, also within a single function.
+function __localized_files
+    set -l LANGUAGE de
+    _ file
+    echo
+    set LANGUAGE fr
+    _ file
+    echo
+    # This only erases the function's copy of the exported variable, leaving LANG=en_US.
+    set -e LANGUAGE
+    _ file
+    echo
+end
+__localized_files
+# CHECK: Datei
+# CHECK: fichier
+# CHECK: file
+# Leaving the function restores the caller's settings.
+echo (_ file)
+# CHECK: arquivo
+
 # Check that C locale disables localization
 begin
     set -l LANG C
@@ -134,6 +155,17 @@ status language
 echo (_ file)
 # CHECK: arquivo
 
+status language resolve file "Control disassembler syntax highlighting style" untranslatable
+echo $status
+# CHECK: pt_BR
+# CHECK: de
+# CHECK:
+# CHECK: 1
+status language resolve file
+echo $status
+# CHECK: pt_BR
+# CHECK: 0
+
 # We have fr but not fr_FR. For the builtin command, only exact matches are allowed.
 status language set fr_FR de pt_BR
 # CHECKERR: No catalogs available for language specifiers: fr_FR
@@ -186,3 +218,22 @@ status language set de de
 
 status language set \xff quote\"
 # CHECKERR: No catalogs available for language specifiers: \Xff 'quote"'
+
+# Plural forms
+status language unset
+set -l LANG C
+echo (_ --count 1 "%d job" "%d jobs")
+# CHECK: %d job
+echo (_ -n 3 "%d job" "%d jobs")
+# CHECK: %d jobs
+echo (_ -n 0 "%d job" "%d jobs")
+# CHECK: %d jobs
+_ -n 2 "%d job"
+# CHECKERR: _: --count requires a singular and a plural message
+_ -n two "%d job" "%d jobs"
+# CHECKERR: _: two: invalid integer
+echo (_ --count=2 "%d job" "%d jobs")
+# CHECK: %d jobs
+# Other arguments starting with a dash are messages.
+echo (_ -- -x --count)
+# CHECK: -- -x --count
@@ -405,6 +405,55 @@ if set -q nosymlinks ||
 end
 # CHECK: link resolves to link
 
+# path relative
+mkdir -p rel/a/b rel/c
+path relative --base rel/a rel/a/b rel/c rel/a rel/a/b/nonexistent
+# CHECK: b
+# CHECK: ../c
+# CHECK: .
+# CHECK: b/nonexistent
+cd rel/a
+path relative ../c/ ../.. -- -foo
+# CHECK: ../c
+# CHECK: ../..
+# CHECK: ./-foo
+cd ../..
+# Symlinks are resolved, so ".." after a symlink goes to the parent of its target.
+if not set -q nosymlinks
+    ln -s a/b rel/link
+end
+if set -q nosymlinks ||
+        test (path relative --base rel rel/link/..) = a
+    echo link resolves to its target
+end
+# CHECK: link resolves to its target
+path relative --base rel
+echo $status
+# CHECK: 1
+
+# path common-prefix
+path common-prefix /usr/local/bin /usr/local/share/fish /usr/lib
+# CHECK: /usr
+path common-prefix /foo/bar /foo/baz
+# CHECK: /foo
+path common-prefix /foo/bar/ /foo/bar/./baz/..
+# CHECK: /foo/bar
+path common-prefix /usr /etc
+# CHECK: /
+path common-prefix foo/bar foo/baz
+# CHECK: foo
+path common-prefix foo bar
+# CHECK: .
+path common-prefix /nonexistent/foo foo
+# CHECK: /
+path common-prefix (pwd)/rel/a rel/c | string replace -- (pwd) PWD
+# CHECK: PWD/rel
+printf '%s\n' /a/b/c /a/b/d | path common-prefix
+# CHECK: /a/b
+path common-prefix
+echo $status
+# CHECK: 1
+
 # path mtime
 # These tests deal with *time*, so we have to account
 # for slow systems (like CI).
@@ -487,3 +536,52 @@ path invalid-subcmd
 # CHECKERR: path invalid-subcmd
 # CHECKERR: ^
 # CHECKERR: (Type 'help path' for related documentation)
+
+# path stat
+mkdir -p stat-test
+printf 12345 > stat-test/file
+chmod 640 stat-test/file
+path stat --format type,size,perm stat-test/file stat-test nonexistent
+# CHECK: file
+# CHECK: 5
+# CHECK: 640
+# CHECK: dir
+# CHECK: {{\d+}}
+# CHECK: {{\d+}}
+test (path stat --format owner stat-test/file) = (id -un) && echo owner matches
+# CHECK: owner matches
+test (path stat --format group stat-test/file) = (id -gn) && echo group matches
+# CHECK: group matches
+test (path stat --format mtime stat-test/file) = (path mtime stat-test/file) && echo mtime matches
+# CHECK: mtime matches
+path stat stat-test/file | count
+# CHECK: 6
+if not set -q nosymlinks
+    ln -s file stat-test/link
+end
+if set -q nosymlinks ||
+        test (path stat --format type stat-test/link) = link
+    echo links are not followed
+end
+# CHECK: links are not followed
+
+path stat --set st_ stat-test/file stat-test
+set -S st_type st_size
+# CHECK: $st_type: set in global scope, unexported, with 2 elements
+# CHECK: $st_type[1]: |file|
+# CHECK: $st_type[2]: |dir|
+# CHECK: $st_size: set in global scope, unexported, with 2 elements
+# CHECK: $st_size[1]: |5|
+# CHECK: $st_size[2]: |{{\d+}}|
+path stat --set st_ --format size nonexistent
+echo $status (count $st_size)
+# CHECK: 1 0
+
+path stat --format size,color stat-test
+# CHECKERR: path stat: Invalid field 'color'
+path stat --set st- stat-test
+# CHECKERR: path stat: st-: invalid variable name. See `help language#shell-variable-and-function-names`
+# CHECKERR: {{.*}}/checks/path.fish (line {{\d+}}):
+# CHECKERR: path stat --set st- stat-test
+# CHECKERR: ^
+# CHECKERR: (Type 'help path' for related documentation)
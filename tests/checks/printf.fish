@@ -210,3 +210,34 @@ printf '\U110000'
 printf '%s' \xef\x99\x81 | display_bytes
 # CHECK: 0000000 357 231 201
 # CHECK: 0000003
+
+# %q quotes for fish
+printf '%q\n' 'a b' "it's" '' '$x'
+# CHECK: 'a b'
+# CHECK: "it's"
+# CHECK: ''
+# CHECK: '$x'
+printf '[%8q]\n' 'a b'
+# CHECK: [   'a b']
+eval set -l roundtrip (printf '%q' "a b'c\$x")
+test "$roundtrip" = "a b'c\$x"; and echo roundtrip
+# CHECK: roundtrip
+
+# %(FORMAT)T formats times
+begin
+    set -lx TZ UTC
+    printf '%(%Y-%m-%d %H:%M:%S)T\n' 0 86400
+    # CHECK: 1970-01-01 00:00:00
+    # CHECK: 1970-01-02 00:00:00
+    printf '[%12(%F)T]|[%-12(%F)T]\n' 1000000000 1000000000
+    # CHECK: [  2001-09-09]|[2001-09-09  ]
+    # No argument or -1 is the current time.
+    printf '%(%Y)T\n' | string match -qr '^\d{4}$'; and echo current
+    # CHECK: current
+    printf '%(%s)T\n' -1 | string match -qr '^\d+$'; and echo current
+    # CHECK: current
+end
+printf '%(%Y)T\n' abc
+# CHECKERR: abc: expected a numeric value
+printf '%T\n' 1
+# CHECKERR: %T: invalid conversion specification
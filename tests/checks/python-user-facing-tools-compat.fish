@@ -2,7 +2,6 @@
 #REQUIRES: command -v uvx
 
 set -l webconfig (status dirname)/../../share/tools/web_config/webconfig.py
-set -l create_manpage (status dirname)/../../share/tools/create_manpage_completions.py
 set -l min_version 3.5
 
 # use vermin to detect minimum Python version violations
@@ -16,7 +15,6 @@ set -l output (uvx vermin \
     --feature fstring-self-doc \
     --target=$min_version- \
     $webconfig \
-    $create_manpage \
     2>&1 | string collect)
 set -l exit_code $pipestatus[1]
 
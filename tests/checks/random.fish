@@ -30,6 +30,11 @@ random choice
 #CHECKERR: random: nothing to choose from
 random choic a b c
 #CHECKERR: random: too many arguments
+random --secure 5
+#CHECKERR: random: the secure generator cannot be seeded
+random shuffle
+echo $status
+#CHECK: 1
 
 function check_boundaries
     if not test "$argv[1]" -ge "$argv[2]" -a "$argv[1]" -le "$argv[3]"
@@ -109,3 +114,26 @@ if not test "$run1" = "$run2"
     printf "%s " $run2
     printf "\n"
 end
+
+# shuffle and choice with stdin
+set -l fish bass trout salmon zander perch carp
+set -l shuffled (random shuffle $fish)
+test (count $shuffled) -eq 6 && echo shuffled count matches
+#CHECK: shuffled count matches
+string join \n $shuffled | sort | string join ' '
+#CHECK: bass carp perch salmon trout zander
+printf '%s\n' $fish | random shuffle | sort | string join ' '
+#CHECK: bass carp perch salmon trout zander
+check_contains (printf '%s\n' $fish | random choice) $fish
+echo '' | random choice | count --lines
+#CHECK: 1
+true | random choice
+#CHECKERR: random: nothing to choose from
+
+# The secure generator
+for i in (seq 10)
+    check_boundaries (random --secure 1 6) 1 6
+    check_contains (random --secure choice $fish) $fish
+end
+random --secure shuffle a b c | sort | string join ' '
+#CHECK: a b c
@@ -525,3 +525,92 @@ echo foo | read -lf var
 # CHECKERR: echo foo | read -lf var
 # CHECKERR: ^
 # CHECKERR: (Type 'help read' for related documentation)
+
+# --timeout
+echo foo | read --timeout 5 var
+echo $status $var
+# CHECK: 0 foo
+
+sh -c 'sleep 0.5; echo late' | read --timeout 0.1 var
+echo $status (count $var) "[$var]"
+# CHECK: 1 0 []
+
+# Partial input is discarded.
+sh -c 'printf ab; sleep 0.5; echo c' | read --timeout 0.1 var
+echo $status "[$var]"
+# CHECK: 1 []
+
+# The timeout applies to all lines together.
+sh -c 'echo a; sleep 0.5; echo b' | read -L --timeout 0.1 first second
+echo $status "[$first]" "[$second]"
+# CHECK: 1 [a] []
+
+read --timeout nope var
+# CHECKERR: read: nope: invalid timeout
+# CHECKERR: {{.*}}checks/read.fish (line {{\d+}}):
+# CHECKERR: read --timeout nope var
+# CHECKERR: ^
+# CHECKERR: (Type 'help read' for related documentation)
+
+read --timeout=-1 var
+# CHECKERR: read: -1: invalid timeout
+# CHECKERR: {{.*}}checks/read.fish (line {{\d+}}):
+# CHECKERR: read --timeout=-1 var
+# CHECKERR: ^
+# CHECKERR: (Type 'help read' for related documentation)
+
+# --fields reads all records, one column per variable.
+printf '%s\n' root:x:0:0:root:/root:/bin/sh daemon:x:1:1::/usr/sbin:/usr/sbin/nologin |
+    read --fields -d : --int uid --int gid user pw uid gid gecos home shell
+echo $status
+set -S user uid gecos shell
+# CHECK: 0
+# CHECK: $user: set in global scope, unexported, with 2 elements
+# CHECK: $user[1]: |root|
+# CHECK: $user[2]: |daemon|
+# CHECK: $uid: set in global scope, unexported, with 2 elements
+# CHECK: $uid[1]: |0|
+# CHECK: $uid[2]: |1|
+# CHECK: $gecos: set in global scope, unexported, with 2 elements
+# CHECK: $gecos[1]: |root|
+# CHECK: $gecos[2]: ||
+# CHECK: $shell: set in global scope, unexported, with 2 elements
+# CHECK: $shell[1]: |/bin/sh|
+# CHECK: $shell[2]: |/usr/sbin/nologin|
+
+# Missing fields are empty and the last variable gets the remainder.
+printf 'a b c d\na\n' | read --fields -l first rest
+echo (count $first) (count $rest) "[$first]" "[$rest]"
+# CHECK: 2 2 [a a] [b c d ]
+
+# The last --int or --float for a variable wins.
+printf 'x, 1.5\ny,007\n' | read --fields -d , --int value --float value name value
+echo $value
+# CHECK: 1.5 007
+
+printf 'x,1.5\ny, 2e3 \n' | read --fields -d , --float value name value
+echo $value
+# CHECK: 1.5 2e3
+
+printf 'a,1\nb,2\nc,three\n' | read --fields -d , --int num name num
+echo $status (count $name)
+# CHECKERR: read: row 3, column 2 (num): three: invalid integer
+# CHECK: 1 0
+
+printf 'a,nan\n' | read --fields -d , --float num name num
+# CHECKERR: read: row 1, column 2 (num): nan: invalid number
+
+read --fields var </dev/null
+echo $status (count $var)
+# CHECK: 1 0
+
+echo a | read --int var var
+# CHECKERR: read: invalid option combination, --int and --float require --fields
+echo a | read --fields --int other var
+# CHECKERR: read: other: not one of the variables to read
+echo a | read --fields -L var
+# CHECKERR: read: Options --fields and --line cannot be used together
+echo a | read --fields -a var
+# CHECKERR: read: Options --fields and --list cannot be used together
+echo a | read --fields
+# CHECKERR: read: expected >= 1 arguments; got 0
@@ -120,3 +120,40 @@ echo 555\nsix | string match -qra '(?<text>[a-z]+)'
 set --show text
 # CHECK: $text: set in global scope, unexported, with 1 elements
 # CHECK: $text[1]: |six|
+
+# Export named groups under a prefix
+set -e name
+string match -rq --export-prefix opt_ -- '--(?<name>[^=]+)=(?<value>.*)' --color=auto
+set --show opt_name opt_value name
+# CHECK: $opt_name: set in global scope, unexported, with 1 elements
+# CHECK: $opt_name[1]: |color|
+# CHECK: $opt_value: set in global scope, unexported, with 1 elements
+# CHECK: $opt_value[1]: |auto|
+
+function prefixed_match
+    string match -rq --export-prefix local_ '(?<word>\w+)' -- "$argv"
+    set --show local_word
+end
+prefixed_match hello
+# CHECK: $local_word: set in local scope, unexported, with 1 elements
+# CHECK: $local_word[1]: |hello|
+set -q local_word
+or echo not set outside
+# CHECK: not set outside
+
+# The prefixed name is checked for read-only variables, the plain one is not.
+string match -rq --export-prefix my_ '(?<version>.*)' -- 1.0
+echo $my_version
+# CHECK: 1.0
+string match -rq --export-prefix FISH_ '(?<VERSION>.*)' -- 1.0
+# CHECKERR: Modification of read-only variable "FISH_VERSION" is not allowed
+
+string match -q --export-prefix opt_ 'a*' -- abc
+# CHECKERR: string match: invalid option combination, --export-prefix requires --regex
+
+string match -rq --export-prefix 'not-valid' '(?<x>.*)' -- abc
+# CHECKERR: string: not-valid: invalid variable name. See `help language#shell-variable-and-function-names`
+# CHECKERR: {{.*}}regex-import.fish (line {{\d+}}):
+# CHECKERR: string match -rq --export-prefix 'not-valid' '(?<x>.*)' -- abc
+# CHECKERR: ^
+# CHECKERR: (Type 'help string' for related documentation)
@@ -49,3 +49,62 @@ $fish -c "return abc"
 # CHECKERR: return abc
 # CHECKERR: ^
 # CHECKERR: (Type 'help return' for related documentation)
+
+function give_values
+    echo printed
+    return --value a "b c" -d
+    echo not reached
+end
+give_values
+# CHECK: printed
+echo $status
+# CHECK: 0
+count $fish_return_value
+# CHECK: 3
+printf '<%s>\n' $fish_return_value
+# CHECK: <a>
+# CHECK: <b c>
+# CHECK: <-d>
+
+# Any function call replaces the values.
+function give_nothing
+    true
+end
+give_nothing
+count $fish_return_value
+# CHECK: 0
+
+function outer
+    give_values >/dev/null
+    return --value outer $fish_return_value[1]
+end
+outer
+echo $fish_return_value
+# CHECK: outer a
+
+# Values keep their newlines.
+function multiline
+    return --value "one
+two"
+end
+multiline
+count $fish_return_value
+# CHECK: 1
+
+set -g fish_return_value foo
+# CHECKERR: set: Tried to change the read-only variable 'fish_return_value'
+
+# Scripts can still use their own $return_value.
+function set_return_value
+    set -l return_value local
+    echo $return_value
+end
+set_return_value
+# CHECK: local
+set -g return_value global
+echo $return_value
+# CHECK: global
+
+$fish -c 'return --value x y; echo not reached'
+echo $status
+# CHECK: 0
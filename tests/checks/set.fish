@@ -1146,4 +1146,136 @@ set -s -l
 set umask abc
 # CHECKERR: set: Tried to modify the special variable 'umask' to an invalid value
 
+# Maps
+set --map colors red ff0000 green 00ff00 red f00
+echo $colors
+# CHECK: f00 00ff00
+count $colors
+# CHECK: 2
+echo $colors[green red]
+# CHECK: 00ff00 f00
+echo "[$colors[blue]]"
+# CHECK: []
+set colors[blue] 0000ff
+set colors[red] 111
+set --keys colors
+# CHECK: red
+# CHECK: green
+# CHECK: blue
+echo $colors
+# CHECK: 111 00ff00 0000ff
+set -q colors[red blue nope]
+echo $status
+# CHECK: 1
+set -e colors[red]
+set --show colors
+# CHECK: $colors: set in global scope, unexported, a map with 2 elements
+# CHECK: $colors[green]: |00ff00|
+# CHECK: $colors[blue]: |0000ff|
+set colors[blue red] x
+# CHECKERR: set: given 2 keys but 1 values
+
+set -a colors x
+# CHECKERR: set: cannot append or prepend to a map
+# CHECKERR: {{.*}}set.fish (line {{\d+}}):
+# CHECKERR: set -a colors x
+# CHECKERR: ^
+# CHECKERR: (Type 'help set' for related documentation)
+
+set --map m a
+# CHECKERR: set: expected key-value pairs; got 1 arguments
+# CHECKERR: {{.*}}set.fish (line {{\d+}}):
+# CHECKERR: set --map m a
+# CHECKERR: ^
+# CHECKERR: (Type 'help set' for related documentation)
+
+set --map m "a b" c
+# CHECKERR: set: a b: invalid map key
+# CHECKERR: {{.*}}set.fish (line {{\d+}}):
+# CHECKERR: set --map m "a b" c
+# CHECKERR: ^
+# CHECKERR: (Type 'help set' for related documentation)
+
+set -U --map m a b
+# CHECKERR: set: maps cannot be universal
+# CHECKERR: {{.*}}set.fish (line {{\d+}}):
+# CHECKERR: set -U --map m a b
+# CHECKERR: ^
+# CHECKERR: (Type 'help set' for related documentation)
+
+set --keys nope
+echo $status
+# CHECK: 1
+set --keys colors nope colors
+echo $status
+# CHECK: green
+# CHECK: blue
+# CHECK: green
+# CHECK: blue
+# CHECK: 1
+set -l list a b c
+set --keys list
+# CHECK: 1
+# CHECK: 2
+# CHECK: 3
+
+function setmap
+    set -l --map loc k v
+    echo $loc[k]
+end
+setmap
+# CHECK: v
+
+# Maps are exported as lists of their values, and setting them normally makes them lists.
+set -x --map exported k v w x
+env | string match 'exported=*'
+# CHECK: exported=v x
+set exported z
+set --keys exported
+# CHECK: 1
+
+# Sorted and unique variables
+set --sorted --unique sorted c a b a c
+echo $sorted
+# CHECK: a b c
+set -a sorted a 0
+echo $sorted
+# CHECK: 0 a b c
+set --show sorted
+# CHECK: $sorted: set in global scope, unexported, with 4 elements, kept sorted, kept unique
+# CHECK: $sorted[1]: |0|
+# CHECK: $sorted[2]: |a|
+# CHECK: $sorted[3]: |b|
+# CHECK: $sorted[4]: |c|
+set --unique uniq b a b c a
+echo $uniq
+# CHECK: b a c
+set -p uniq c
+echo $uniq
+# CHECK: c b a
+set uniq[1] a
+echo $uniq
+# CHECK: a b
+function setsorted
+    set -l sorted z a
+    echo $sorted
+end
+setsorted
+# CHECK: a z
+set --unsorted sorted q a
+echo $sorted
+# CHECK: q a
+set -q --unique sorted
+echo $status
+# CHECK: 0
+set -q --nonunique sorted
+echo $status
+# CHECK: 1
+set --sorted --unsorted sorted
+# CHECKERR: set: invalid option combination
+# CHECKERR: {{.*}}set.fish (line {{\d+}}):
+# CHECKERR: set --sorted --unsorted sorted
+# CHECKERR: ^
+# CHECKERR: (Type 'help set' for related documentation)
+
 exit 0
@@ -140,6 +140,12 @@ and should have failed on unrecognized feature
 # CHECKERR: status test-terminal-feature: unrecognized feature 'unrecognized-feature'
 status test-terminal-feature scroll-content-up
 and should have failed when running without a TTY
+status test-terminal-feature synchronized-output
+and should have failed when running without a TTY
+
+# Without a terminal to ask, nothing is supported.
+status terminal-capabilities | count
+# CHECK: 0
 
 status -L abc
 # CHECKERR: status: abc: invalid integer
@@ -188,3 +194,30 @@ end
 
 status build-info other-arg
 # CHECKERR: status build-info: expected 0 arguments; got 1
+
+sleep 0.1
+status last-duration | read -l wall user sys
+test $wall -ge 0.1 -a $user -ge 0 -a $sys -ge 0
+and echo wall time includes sleep
+# CHECK: wall time includes sleep
+
+true | sleep 0.1 | cat
+set -l timings (status last-duration --per-process)
+string replace -r '^([0-9.]+\t){3}' '' -- $timings
+# CHECK: true
+# CHECK: sleep 0.1
+# CHECK: cat
+set -l walls (string split -f1 \t -- $timings)
+test $walls[1] -lt 0.1 -a $walls[2] -ge 0.1
+and echo per-process wall times
+# CHECK: per-process wall times
+
+begin
+    sleep 0.1
+end | string length -q
+status last-duration --per-process | string replace -r '^([0-9.]+\t){3}' ''
+# CHECK: begin
+# CHECK: string length -q
+
+status last-duration extra
+# CHECKERR: status last-duration: expected 0 arguments; got 1
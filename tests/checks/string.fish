@@ -157,6 +157,27 @@ string pad -c_ --width 5 --center --right longer-than-width-param x
 # CHECK: longer-than-width-param
 # CHECK: ___________x___________
 
+# With --truncate, the width parameter is also the maximum.
+string pad -c_ --width 5 --truncate longer-than-width-param x
+# CHECK: long…
+# CHECK: ____x
+string pad --width 6 --ellipsis ... -r longer-than-width-param x
+# CHECK: lon...
+# CHECK: x
+string pad -c_ --width 4 -t 🐟🐟🐟 abcd
+# CHECK: _🐟…
+# CHECK: abcd
+# An ellipsis wider than the width is left out.
+string pad --width 2 --ellipsis ... abc
+# CHECK: ab
+# Escape sequences are kept and do not count towards the width.
+string pad --width 3 -t (set_color red)abcd | string escape
+# CHECK: \e\[31mab…
+string pad --truncate abc
+# CHECKERR: string pad: invalid option combination, --truncate requires --width
+echo $status
+# CHECK: 2
+
 # Current behavior is that only a single padding character is supported.
 # We can support longer strings in future without breaking compatibility.
 string pad -c ab -w4 .
@@ -211,6 +232,68 @@ string length --visible \bf\b
 string length --visible \bf\b\b\b\b\b
 # CHECK: 0
 
+string width abc (set_color red)abc a\nbc
+# CHECK: 3
+# CHECK: 3
+# CHECK: 1
+# CHECK: 2
+begin
+    set -l fish_emoji_width 2
+    # Combining characters and joined emoji don't add to the width.
+    string width e\u0301 \U1F468\u200D\U1F469\u200D\U1F467 \U1F468\U1F469
+    # CHECK: 1
+    # CHECK: 2
+    # CHECK: 4
+    string width --breakdown x(set_color red)e\u0301\U1F468\u200D\U1F469\t
+    # CHECK: x{{\t}}1{{\t}}U+0078
+    # CHECK: \e\[31m{{\t}}0{{\t}}U+001B U+005B U+0033 U+0031 U+006D
+    # CHECK: {{e\u0301}}{{\t}}1{{\t}}U+0065 U+0301
+    # CHECK: {{\U0001F468\u200D\U0001F469}}{{\t}}2{{\t}}U+1F468 U+200D U+1F469
+    # CHECK: \t{{\t}}0{{\t}}U+0009
+end
+string width -q ''; or echo zero width
+# CHECK: zero width
+string width -q '' a; and echo not zero width
+# CHECK: not zero width
+
+string diff a\nb\nc\n a\nx\nc\n
+# CHECK: @@ -1,3 +1,3 @@
+# CHECK:  a
+# CHECK: -b
+# CHECK: +x
+# CHECK:  c
+echo $status
+# CHECK: 1
+string diff same same; and echo equal
+# CHECK: equal
+string diff -U0 1\n2\n3 1\n3\n4
+# CHECK: @@ -2 +1,0 @@
+# CHECK: -2
+# CHECK: @@ -3,0 +3 @@
+# CHECK: +4
+string diff --words 'the quick  fox' 'the slow fox'
+# CHECK: the [-quick  -]{+slow +}fox
+string diff --words --color=always 'a b' 'a c' | string escape
+# CHECK: a\ \e\[31mb\e\[m\e\[32mc\e\[m
+string diff a
+# CHECKERR: string diff: expected 2 arguments; got 1
+string diff --color=sometimes a b
+# CHECKERR: string: Invalid value for '--color' option: 'sometimes'. Expected 'always', 'never', or 'auto'
+
+set -l tmpdir (mktemp -d)
+printf '%s\n' one two >$tmpdir/old
+printf '%s\n' one three >$tmpdir/new
+string diff --files $tmpdir/old $tmpdir/new | string replace -- $tmpdir/ ''
+# CHECK: --- old
+# CHECK: +++ new
+# CHECK: @@ -1,2 +1,2 @@
+# CHECK:  one
+# CHECK: -two
+# CHECK: +three
+string diff --files $tmpdir/old $tmpdir/missing 2>&1 | string replace -- $tmpdir/ ''
+# CHECK: string diff: Opening "missing" failed: No such file or directory (os error 2)
+rm -r $tmpdir
+
 string sub --length 2 abcde
 # CHECK: ab
 
@@ -896,6 +979,72 @@ count (string split / /)
 count (echo -ne '\x00\x00\x00' | string split0)
 # CHECK: 3
 
+string hash abc
+# CHECK: ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad
+printf '%s\n' abc '' | string hash -a sha1
+# CHECK: a9993e364706816aba3e25717850c26c9cd0d89d
+# CHECK: da39a3ee5e6b4b0d3255bfef95601890afd80709
+string hash -a crc32 123456789 \u00e9
+# CHECK: cbf43926
+# CHECK: 0e048d3e
+string hash -a sha3 abc
+# CHECKERR: string hash: Invalid hash algorithm 'sha3'
+
+# string unicode
+set -l decomposed (string unicode --nfd caf\u00e9)
+test $decomposed = caf\u00e9; or echo different
+# CHECK: different
+string length -- $decomposed
+# CHECK: 5
+string unicode --nfc $decomposed | string escape --style=url
+# CHECK: caf%C3%A9
+string unicode -q --nfc $decomposed; and echo normalized
+# CHECK: normalized
+string unicode --nfc abc; or echo unchanged
+# CHECK: abc
+# CHECK: unchanged
+string unicode --nfkc \ufb01le\u2460
+# CHECK: file1
+string unicode --inspect a\u0301\t
+# CHECK: a{{\t}}U+0061{{\t}}Ll{{\t}}LATIN SMALL LETTER A
+# CHECK: ́{{\t}}U+0301{{\t}}Mn{{\t}}COMBINING ACUTE ACCENT
+# CHECK: \t{{\t}}U+0009{{\t}}Cc
+string unicode foo
+# CHECKERR: string unicode: expected one of --nfc, --nfd, --nfkc, --nfkd or --inspect
+string unicode --nfc --inspect foo
+# CHECKERR: string unicode: invalid option combination, --nfc, --nfd, --nfkc, --nfkd and --inspect are mutually exclusive
+
+# NUL-delimited input and output
+# (`string escape` reads lines and keeps a missing trailing newline missing, hence the echos)
+printf 'a:b\0c\nd\0' | string split --null-in --null-out : | string escape; echo
+# CHECK: a\x00b\x00c
+# CHECK: d\x00
+printf 'one\ntwo\0three\0' | string join --null-in , | string escape
+# CHECK: one
+# CHECK: two,three
+printf 'one\0two\0' | string join -z -Z , | string escape; echo
+# CHECK: one,two\x00
+printf 'x\n\n\0y\0' | string collect --null-in
+# CHECK: x
+# CHECK: y
+printf 'x\n\n' | string collect -Z | string escape; echo
+# CHECK: x\x00
+
+# string split --regex
+string split --regex '\s*[,;]\s*' 'a, b ;c'
+# CHECK: a
+# CHECK: b
+# CHECK: c
+string split --regex -r -m1 '[0-9]+' a1b22c
+# CHECK: a1b
+# CHECK: c
+string split --regex -n -f2 -- '-+' --x--y
+# CHECK: y
+string split --regex '[' a[b
+# CHECKERR: string split: Regular expression compile error: missing terminating ] for character class
+# CHECKERR: string split: [
+# CHECKERR: string split: ^
+
 # string collect
 count (echo one\ntwo\nthree\nfour | string collect)
 count (echo one | string collect)
@@ -1316,6 +1465,33 @@ string replace one
 string replace -r o '${bad_name}' foobar
 # CHECKERR: string replace: Regular expression substitute error: unknown substring
 
+# string replace --function
+function __string_double
+    math 2 x $argv[2]
+end
+string replace -r -a --function __string_double '(\d+)' 'a1 b22 c3'
+# CHECK: a2 b44 c6
+function __string_show_args
+    echo "<"(string join , -- $argv)">"
+end
+# Groups that don't participate in the match are passed as empty arguments.
+string replace -r --function __string_show_args '(a)|(b)' xbxa
+# CHECK: x<b,,b>xa
+string replace -r -a --function __string_show_args '(a)|(b)' xbxa
+# CHECK: x<b,,b>x<a,a,>
+string replace -r -q --function __string_show_args z xbxa; or echo no match
+# CHECK: no match
+function __string_two_lines
+    printf '%s\n' one two
+end
+string replace -r --function __string_two_lines b abc
+# CHECK: aone
+# CHECK: twoc
+string replace --function __string_double a abc
+# CHECKERR: string replace: invalid option combination, --function requires --regex
+string replace -r --function __string_no_such_function a abc
+# CHECKERR: string replace: Function '__string_no_such_function' does not exist
+
 string match --unknown-opt
 # CHECKERR: string match: --unknown-opt: unknown option
 # CHECKERR: {{.*}}checks/string.fish (line {{\d+}}):
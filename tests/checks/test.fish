@@ -153,3 +153,43 @@ test
 #CHECKERR: [ -z
 #CHECKERR: ^
 #CHECKERR: (Type 'help [' for related documentation)
+
+# Regex and glob matching
+set -l name fish-4.0.tar.gz
+test $name =~ '^fish-[0-9.]+\.tar' && echo regex
+#CHECK: regex
+test $name =~ '\.zip$' || echo no regex
+#CHECK: no regex
+test $name '==*' 'fish-*.tar.gz' && echo glob
+#CHECK: glob
+# The glob has to match the whole string.
+test $name '==*' 'fish-*' '-a' $name '==*' '*.gz' && echo both globs
+#CHECK: both globs
+test $name '==*' 'fish' || echo no glob
+#CHECK: no glob
+
+test foo =~ 'fo('
+echo $status
+#CHECKERR: Regular expression compile error: missing closing parenthesis
+#CHECKERR: {{.*}}test.fish (line {{\d+}}):
+#CHECKERR: test foo =~ 'fo('
+#CHECKERR: ^
+#CHECK: 2
+
+# Version comparisons
+test 3.10.2 -ver-gt 3.9 && echo newer
+#CHECK: newer
+test v4.0 -ver-eq 4.0.0 && echo same
+#CHECK: same
+test 4.0.0-beta.2 -ver-lt 4.0.0 && echo prerelease
+#CHECK: prerelease
+test 4.0 -ver-ge 4.0.1 || echo older
+#CHECK: older
+
+test 4.0 -ver-lt master
+echo $status
+#CHECKERR: Invalid version: 'master'
+#CHECKERR: {{.*}}test.fish (line {{\d+}}):
+#CHECKERR: test 4.0 -ver-lt master
+#CHECKERR: ^
+#CHECK: 2
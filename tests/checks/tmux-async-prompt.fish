@@ -0,0 +1,33 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+
+isolated-tmux-start -C '
+    set -g prompt_word rendered
+    set -g fish_async_prompt fish_prompt
+    function fish_prompt
+        if contains -- --placeholder $argv
+            printf "placeholder> "
+        else
+            printf "%s %s> " $prompt_word $status
+        end
+    end
+'
+
+isolated-tmux send-keys 'false' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: rendered 0> false
+# CHECK: rendered 1>
+
+# In another directory, the placeholder is shown until the output arrives.
+isolated-tmux send-keys C-l 'cd /' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: rendered 0>
+
+# Global variables too big for the environment are passed on.
+isolated-tmux send-keys C-l 'set -g big (string repeat -n 200000 x); set prompt_word big' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: rendered 0> set -g big (string repeat -n 200000 x); set prompt_word big
+# CHECK: big 0>
@@ -0,0 +1,39 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+
+isolated-tmux-start -C '
+    function suggest_greeting
+        string match -q "echo h*" -- $argv[1]
+        and echo "echo hi there"
+    end
+    mkdir -p dir/subdir
+    set -gx HOME $PWD
+'
+
+isolated-tmux send-keys 'echo help' Enter C-l
+isolated-tmux send-keys 'set -g fish_autosuggestion_providers suggest_greeting history' Enter C-l
+isolated-tmux send-keys 'echo h'
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt {{\d+}}> echo hi there
+
+# Providers are asked in order.
+isolated-tmux send-keys C-u 'set -g fish_autosuggestion_providers history suggest_greeting' Enter C-l
+isolated-tmux send-keys 'echo h'
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt {{\d+}}> echo help
+
+# Suggestions that don't extend the command line are ignored.
+isolated-tmux send-keys C-u 'set -g fish_autosuggestion_providers suggest_greeting' Enter C-l
+isolated-tmux send-keys 'echo hi there!'
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt {{\d+}}> echo hi there!
+
+isolated-tmux send-keys C-u 'set -g fish_autosuggestion_providers directory-history' Enter C-l
+isolated-tmux send-keys 'cd dir/subdir' Enter 'cd ../..' Enter C-l
+isolated-tmux send-keys 'cd ~'
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt {{\d+}}> cd ~/dir/subdir
@@ -0,0 +1,32 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+
+isolated-tmux-start -C '
+    set -g fish_command_duration_threshold 0
+    function fish_prompt
+        printf "> "
+    end
+'
+
+isolated-tmux send-keys 'false' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: > false
+# CHECK: took {{\d+\.\ds}}, exit status 1
+# CHECK: >
+
+isolated-tmux send-keys 'function fish_postexec_report; echo "report: $argv[1] $argv[3]"; end' Enter
+tmux-sleep
+isolated-tmux send-keys C-l 'false' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: > false
+# CHECK: report: false 1
+# CHECK: >
+
+# Commands below the threshold are not reported.
+isolated-tmux send-keys C-l 'set -g fish_command_duration_threshold 100000' Enter C-l 'true' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: > true
+# CHECK: >
@@ -0,0 +1,29 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+
+isolated-tmux-start -C '
+    set -g fish_autosuggestion_enabled 0
+    function fake_editor
+        set -l contents (string replace foo edited <$argv[-1])
+        printf "%s\n" $contents >$argv[-1]
+    end
+    set -g VISUAL fake_editor
+'
+
+# The cursor stays at the same line and column.
+isolated-tmux send-keys 'echo foo' M-e X
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 0> echo edXited
+
+# Multiline command lines are indented in the file, and unindented afterwards.
+isolated-tmux send-keys C-e C-u C-l 'if true' M-Enter 'echo foo' M-Enter end M-e
+tmux-sleep
+isolated-tmux send-keys Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 0> if true
+# CHECK:               echo edited
+# CHECK:           end
+# CHECK: edited
+# CHECK: prompt 1>
@@ -0,0 +1,31 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+
+isolated-tmux-start -C '
+    set -g fish_autosuggestion_enabled 0
+    bind ctrl-g history-directory-search-backward
+    mkdir -p a b
+'
+
+isolated-tmux send-keys 'cd a' Enter 'echo one' Enter false Enter 'cd ../b' Enter 'echo two' Enter C-l
+isolated-tmux send-keys C-g
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 5> echo two
+
+# "cd ../b" was run in a.
+isolated-tmux send-keys C-u 'cd ../a' Enter C-l C-g
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 6> cd ../b
+isolated-tmux send-keys C-g C-g
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 6> echo one
+
+isolated-tmux send-keys C-u 'history search --cwd . --failed-only | cat' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 6> history search --cwd . --failed-only | cat
+# CHECK: false
+# CHECK: prompt 7>
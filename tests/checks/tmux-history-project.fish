@@ -0,0 +1,27 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+
+isolated-tmux-start -C '
+    set -g fish_autosuggestion_enabled 0
+    set -g fish_history_project_markers .git
+    bind ctrl-g history-project-search-backward
+    mkdir -p project/.git project/sub other
+'
+
+# Only the commands run inside the project are in its history.
+isolated-tmux send-keys 'cd project' Enter 'echo inside' Enter 'cd ../other' Enter \
+    'echo outside' Enter 'cd ../project/sub' Enter C-l
+isolated-tmux send-keys C-g
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 5> cd ../other
+isolated-tmux send-keys C-g
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 5> echo inside
+
+# Then the rest of the history is searched.
+isolated-tmux send-keys C-g
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 5> cd ../project/sub
@@ -0,0 +1,42 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+#REQUIRES: test -z "$CI"
+
+isolated-tmux-start -C '
+    set -g fish_autosuggestion_enabled 0
+    complete -c foo -f -a "alpha beta gamma delta"
+    touch file1 file2 file3
+'
+
+# Mark two completions, then accept. Marking selects the next completion.
+isolated-tmux send-keys 'foo ' Tab Tab C-Space Right C-Space
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 0> foo gamma
+# CHECK: alpha  beta  delta  gamma
+# CHECK: 2 marked
+
+isolated-tmux send-keys Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 0> foo alpha delta
+# CHECK:
+# CHECK:
+
+# Unmarking works, and without marks the selected completion is inserted.
+isolated-tmux send-keys C-u 'foo ' Tab Tab C-Space Left C-Space Enter
+tmux-sleep
+isolated-tmux capture-pane -p | string match -r '.*\S.*'
+# CHECK: prompt 0> foo beta
+
+# Completions that only append to the token.
+isolated-tmux send-keys C-u ': fi' Tab Tab C-Space C-Space Enter
+tmux-sleep
+isolated-tmux capture-pane -p | string match -r '.*\S.*'
+# CHECK: prompt 0> : file1 file2
+
+# Outside the pager, ctrl-space inserts a space.
+isolated-tmux send-keys C-Space x
+tmux-sleep
+isolated-tmux capture-pane -p | string match -r '.*\S.*'
+# CHECK: prompt 0> : file1 file2  x
@@ -0,0 +1,18 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+
+isolated-tmux-start -C '
+    set -g fish_right_prompt_on_every_line 1
+    function fish_prompt
+        printf "> "
+    end
+    function fish_right_prompt
+        printf "<"
+    end
+'
+
+isolated-tmux send-keys 'echo "a' Enter 'b"'
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: > echo "a{{ +}}<
+# CHECK: b"{{ +}}<
@@ -0,0 +1,29 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+
+isolated-tmux-start -C '
+    set -g fish_autosuggestion_enabled 0
+    function insert_commit
+        commandline --insert-snippet "git commit -m \"\${1:message}\"\${2: --amend}\$0"
+    end
+    bind ctrl-g insert_commit
+    bind ctrl-n forward-placeholder
+    bind ctrl-b backward-placeholder
+'
+
+# Typing at a placeholder replaces its default text, and the last stop is $0.
+isolated-tmux send-keys 'echo x; ' C-g fix C-n C-n ' -q'
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 0> echo x; git commit -m "fix" --amend -q
+
+isolated-tmux send-keys C-u C-l C-g C-n X C-b msg
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 0> git commit -m "msg"X
+
+# Without a snippet, there is no placeholder to move to.
+isolated-tmux send-keys C-e C-u C-l 'echo ' C-n C-b 'done'
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: prompt 0> echo done
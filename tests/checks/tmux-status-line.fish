@@ -0,0 +1,43 @@
+#RUN: %fish %s
+#REQUIRES: command -v tmux
+
+isolated-tmux-start -C '
+    function fish_prompt
+        printf "> "
+    end
+    function fish_status_line
+        printf "status line"
+    end
+'
+
+# Command output scrolls above the status line.
+isolated-tmux send-keys 'seq 12' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: 5
+# CHECK: 6
+# CHECK: 7
+# CHECK: 8
+# CHECK: 9
+# CHECK: 10
+# CHECK: 11
+# CHECK: 12
+# CHECK: >
+# CHECK: status line
+
+# Without the function, the line is given back.
+isolated-tmux send-keys 'functions --erase fish_status_line' Enter
+tmux-sleep
+isolated-tmux send-keys 'seq 10' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: 2
+# CHECK: 3
+# CHECK: 4
+# CHECK: 5
+# CHECK: 6
+# CHECK: 7
+# CHECK: 8
+# CHECK: 9
+# CHECK: 10
+# CHECK: >
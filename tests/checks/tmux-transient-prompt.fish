@@ -87,3 +87,26 @@ isolated-tmux capture-pane -p
 # CHECK: foo bar
 # CHECK: transient prompt line
 # CHECK: 1>
+
+# The fish_transient_prompt function replaces all lines of the prompt, and erases the right prompt.
+isolated-tmux send-keys C-u C-l '
+    set -g fish_transient_prompt 0
+    function fish_prompt
+        echo "long prompt line"
+        echo "3> "
+    end
+    function fish_right_prompt
+        echo right
+    end
+    function fish_transient_prompt
+        echo "t> "
+    end
+'
+tmux-sleep
+isolated-tmux send-keys C-l 'echo foo' Enter
+tmux-sleep
+isolated-tmux capture-pane -p
+# CHECK: t> echo foo
+# CHECK: foo
+# CHECK: long prompt line
+# CHECK: 3> {{ *}}right
@@ -1,4 +1,4 @@
-# RUN: env fth=%fish_test_helper %fish %s
+# RUN: env fth=%fish_test_helper fish=%fish %fish %s
 #REQUIRES: command -v %fish_test_helper
 
 set -g SIGUSR1_COUNT 0
@@ -84,6 +84,60 @@ kill -INT $fish_pid
 # CHECK: start handle_int 3
 # CHECK: end handle_int 3
 
+# trap is a builtin on top of the same events.
+trap 'echo trapped' USR2
+kill -USR2 $fish_pid
+sleep .1
+# CHECK: trapped
+trap -p USR2
+# CHECK: trap -- 'echo trapped' SIGUSR2
+trap - USR2
+trap -p USR2
+kill -USR2 $fish_pid
+sleep .1
+
+trap true KILL
+# CHECKERR: trap: Cannot trap signal 'SIGKILL'
+trap true NOSUCHSIGNAL
+# CHECKERR: trap: Unknown signal 'NOSUCHSIGNAL'
+echo $status
+# CHECK: 1
+
+# Signal handlers learn about the foreground job that was running.
+function show_job --on-signal USR2
+    echo $argv[1] $argv[4]
+end
+command sh -c 'kill -USR2 $PPID; sleep .1'
+# CHECK: SIGUSR2 command sh -c 'kill -USR2 $PPID; sleep .1'
+functions --erase show_job
+
+# Without a foreground job, there is only the signal name.
+function show_job --on-signal USR2
+    echo (count $argv) $argv
+end
+command sh -c "sleep .05; kill -USR2 $fish_pid" &
+sleep .3
+# CHECK: 1 SIGUSR2
+functions --erase show_job
+
+# Signals are handled between builtins too, in non-interactive scripts.
+$fish -c 'trap "set -g caught yes" USR1
+    command sh -c "sleep .2; kill -USR1 $fish_pid" &
+    disown
+    for i in (seq 100)
+        set -q caught; and break
+        sleep .05
+    end
+    echo caught $caught'
+# CHECK: caught yes
+
+# An empty command ignores the signal, also in child processes.
+$fish -c 'trap "" USR1; trap; kill -USR1 $fish_pid; echo survived
+    sh -c "kill -USR1 \$\$; echo child survived"'
+# CHECK: trap -- '' SIGUSR1
+# CHECK: survived
+# CHECK: child survived
+
 # Remove our handler and SIGINT ourselves. Now we should exit.
 functions --erase handle_int
 kill -INT $fish_pid
@@ -7,22 +7,39 @@ trap -p
 # CHECK:
 
 # Validate both SIG, no-SIG, uppercase, and lowercase.
-trap "true" SIGTERM kill ExIT INT
+trap "true" SIGTERM hup ExIT INT
 
 trap -p
-# CHECK: # Defined via `source`
-# CHECK: function __trap_handler_EXIT --on-event fish_exit
-# CHECK:  true;
-# CHECK: end
-# CHECK: # Defined via `source`
-# CHECK: function __trap_handler_INT --on-signal SIGINT
-# CHECK:  true;
-# CHECK: end
-# CHECK: # Defined via `source`
-# CHECK: function __trap_handler_KILL --on-signal SIGKILL
-# CHECK:  true;
-# CHECK: end
-# CHECK: # Defined via `source`
-# CHECK: function __trap_handler_TERM --on-signal SIGTERM
-# CHECK:  true;
-# CHECK: end
+# CHECK: trap -- true EXIT
+# CHECK: trap -- true SIGHUP
+# CHECK: trap -- true SIGINT
+# CHECK: trap -- true SIGTERM
+
+trap -p INT exit
+# CHECK: trap -- true EXIT
+# CHECK: trap -- true SIGINT
+
+# A new trap replaces the old one.
+trap "echo 'bye now'" EXIT
+trap -p EXIT
+# CHECK: trap -- 'echo \'bye now\'' EXIT
+
+# Traps are not functions.
+functions -a | string match -q '*__trap_handler_*'
+or echo no trap functions
+# CHECK: no trap functions
+
+# Event handlers are listed with the trap command.
+functions --handlers-type signal | string match '*trap*'
+# CHECK: SIGHUP trap -- true
+# CHECK: SIGINT trap -- true
+# CHECK: SIGTERM trap -- true
+functions --handlers-type generic | string match '*trap*'
+# CHECK: fish_exit trap -- 'echo \'bye now\''
+
+# Resetting a trap, with or without "-".
+trap - SIGHUP TERM
+trap INT
+trap -p
+# CHECK: trap -- 'echo \'bye now\'' EXIT
+# CHECK: bye now
@@ -149,3 +149,24 @@ PATH=.:$PATH type -P test
 
 type -p -q type
 # CHECKERR: type: invalid option combination
+
+mkdir -p $TMPDIR/json1 $TMPDIR/json2
+for dir in $TMPDIR/json1 $TMPDIR/json2
+    echo "#!/bin/sh" >$dir/json-cmd
+    chmod +x $dir/json-cmd
+end
+function json-cmd
+end
+abbr --add json-cmd json-cmd --flag
+PATH=$TMPDIR/json1:$TMPDIR/json2 type --json json-cmd | string replace -a $TMPDIR TMP
+# CHECK: {"name":"json-cmd","steps":[{"type":"abbr","replacement":"json-cmd --flag","function":false},{"type":"function","path":"{{.*}}/type.fish","line":{{\d+}},"shadowed":false},{"type":"file","path":"TMP/json1/json-cmd","shadowed":true},{"type":"file","path":"TMP/json2/json-cmd","shadowed":true}]}
+PATH=$TMPDIR/json1 type --json -f json-cmd | string replace -a $TMPDIR TMP
+# CHECK: {"name":"json-cmd","steps":[{"type":"abbr","replacement":"json-cmd --flag","function":false},{"type":"file","path":"TMP/json1/json-cmd","shadowed":false}]}
+PATH=$TMPDIR/json1:$TMPDIR/json2 command --json json-cmd | string replace -a $TMPDIR TMP
+# CHECK: {"name":"json-cmd","steps":[{"type":"file","path":"TMP/json1/json-cmd","shadowed":false},{"type":"file","path":"TMP/json2/json-cmd","shadowed":true}]}
+PATH= type --json set
+# CHECK: {"name":"set","steps":[{"type":"builtin","shadowed":false}]}
+type --json doesnotexist-pleasedonotexist-2324242
+echo $status
+# CHECK: {"name":"doesnotexist-pleasedonotexist-2324242","steps":[]}
+# CHECK: 1
@@ -85,3 +85,21 @@ ulimit --core-size abc
 #CHECKERR: ulimit --core-size abc
 #CHECKERR: ^
 #CHECKERR: (Type 'help ulimit' for related documentation)
+
+# Sizes may be given with a unit suffix.
+ulimit -S --core-size 0K
+ulimit -S --core-size
+#CHECK: 0
+
+ulimit --file-descriptor-count 1K
+#CHECKERR: ulimit: Invalid limit '1K'
+#CHECKERR: {{.*}}checks/ulimit.fish (line {{\d+}}):
+#CHECKERR: ulimit --file-descriptor-count 1K
+#CHECKERR: ^
+#CHECKERR: (Type 'help ulimit' for related documentation)
+
+ulimit --json | string match -q '*"core-size":{"soft":0,*'
+and echo json ok
+#CHECK: json ok
+ulimit --json | count
+#CHECK: 1
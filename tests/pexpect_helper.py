@@ -155,6 +155,8 @@ class SpawnedProc(object):
         timeout=TIMEOUT_SECS,
         env=os.environ.copy(),
         scroll_content_up_supported: bool = False,
+        clipboard_supported: bool = False,
+        xtversion: str = "",
         **kwargs,
     ):
         """Construct from a name, timeout, and environment.
@@ -191,6 +193,13 @@ class SpawnedProc(object):
             key = bytes.hex(b"indn")
             value = bytes.hex(b"dont-care")
             self.spawn.send(f"\x1bP1+r{key}={value}\x1b\\")
+        if xtversion:
+            # XTVERSION
+            self.spawn.send(f"\x1bP>|{xtversion}\x1b\\")
+        if clipboard_supported:
+            # XTGETTCAP, boolean capability
+            key = bytes.hex(b"Ms")
+            self.spawn.send(f"\x1bP1+r{key}\x1b\\")
         if env.get("TERM") != "dumb":
             self.send_primary_device_attribute()
 
@@ -185,6 +185,24 @@ expect_prompt()
 sendline(r"""fruit foo""")
 expect_prompt("I am a banana")
 
+# Conditional abbreviations only expand if their condition succeeds.
+sendline(r"""abbr cond --condition 'test "$cond_var" = yes' echo expanded""")
+expect_prompt()
+sendline(r"""function cond; echo not expanded; end""")
+expect_prompt()
+sendline(r"""cond""")
+expect_prompt("not expanded")
+sendline(r"""set cond_var yes""")
+expect_prompt()
+sendline(r"""cond""")
+expect_prompt("\r\nexpanded")
+
+# Abbreviations can match several tokens.
+sendline(r"""abbr 'git co' git checkout""")
+expect_prompt()
+sendline(r"""git co main""")
+expect_prompt("git checkout main")
+
 # FIXME: This fails regularly on CI, output like
 # \r\n\x1b]133;C;cmdline_url=echo%20bar\x07bar\r\n\x1b]133;D;0\x07\x1b[?25h⏎
 # {{spaces}}\r⏎ \r\rprompt 39>\x1b[?2004h
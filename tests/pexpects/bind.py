@@ -686,6 +686,118 @@ sendline("bind ctrl-g backward-word-end")
 sendline("eco 12" + control("g") + "h")
 expect_str("12")
 expect_prompt()
+expect_prompt()
+
+# Sequences with a timeout only match if the keys are typed quickly enough.
+sendline(
+    "bind --timeout 200 --on-timeout 'commandline -i fallback' j,k 'commandline -i chord'"
+)
+expect_prompt()
+send("string upper jk\r")
+expect_str("CHORD")
+expect_prompt()
+send("string upper j")
+sleep(0.5)
+send("\r")
+expect_str("FALLBACK")
+expect_prompt()
+# Other keys are handled as usual.
+send("string upper jx\r")
+expect_str("JX")
+expect_prompt()
+
+# Without a fallback, the keys typed so far are handled as usual.
+sendline("bind --timeout 200 x,y 'commandline -i chord2'")
+expect_prompt()
+send("string upper x")
+sleep(0.5)
+send("y\r")
+expect_str("XY")
+expect_prompt()
+sendline("bind --erase j,k x,y")
+expect_prompt()
+
+# Pasted text is passed to the paste binding.
+sendline("function paste_upper; commandline -i -- (string upper -- $argv[1]); end")
+expect_prompt()
+sendline("bind paste paste_upper")
+expect_prompt()
+send("echo \x1b[200~pasted\x1b[201~\r")
+expect_str("PASTED")
+expect_prompt()
+sendline("bind --erase paste")
+expect_prompt()
+send("echo \x1b[200~pasted\x1b[201~\r")
+expect_str("pasted")
+expect_prompt()
+
+# Focus events run their binding.
+sendline("bind focusin 'echo; echo focused'")
+expect_prompt()
+send("\x1b[I")
+expect_str("focused")
+sendline("bind --erase focusin")
+expect_prompt()
+
+# Key releases only run explicit bindings, and don't interrupt sequences.
+sendline("bind release-f5 'commandline -i released'; bind ctrl-x,ctrl-y 'commandline -i chord'")
+expect_prompt()
+send("echo \x1b[15~\x1b[15;1:3~\r")
+expect_str("released")
+expect_prompt()
+send("echo \x18\x1b[120;5:3u\x19\r")
+expect_str("chord")
+expect_prompt()
+sendline("bind --erase release-f5 ctrl-x,ctrl-y")
+expect_prompt()
+
+# Mouse events pass the position to functions bound to them.
+sendline("function show_click; commandline -i \"clicked $argv\"; end")
+expect_prompt()
+sendline("bind mouse-left-click show_click")
+expect_prompt()
+send("echo \x1b[<0;3;2M\x1b[<0;3;2m\r")
+expect_str("clicked 2 1")
+expect_prompt()
+sendline("bind --erase mouse-left-click")
+expect_prompt()
+
+# User-defined input functions can change the commandline and be mixed with built-in ones.
+sendline("bind --define-function wrap-echo 'commandline -r \"echo \"(commandline)'")
+expect_prompt()
+sendline("bind ctrl-x wrap-echo end-of-line")
+expect_prompt()
+send("abc\x18 def\r")
+expect_prompt("abc def")
+# They can also be invoked via `commandline -f`.
+sendline("bind ctrl-x 'commandline -f wrap-echo end-of-line'")
+expect_prompt()
+send("ghi\x18 jkl\r")
+expect_prompt("ghi jkl")
+sendline("bind --erase ctrl-x; bind --erase --define-function wrap-echo")
+expect_prompt()
+
+# Key translations for the current terminal take precedence over the built-in decoding.
+sendline("bind --translate \\e\\[99\\;5~ ctrl-backspace")
+expect_prompt()
+sendline("bind ctrl-backspace 'commandline -i translated'")
+expect_prompt()
+send("echo \x1b[99;5~\r")
+expect_prompt("translated")
+# Translations for other terminals are ignored.
+sendline("bind --translate --term other-term \\e\\[98\\;5~ ctrl-backspace")
+expect_prompt()
+send("echo begin\x1b[98;5~end\r")
+expect_prompt("begin")
+# Input which starts like a translated sequence but doesn't match is decoded as usual.
+sendline("bind --translate abz ctrl-backspace")
+expect_prompt()
+send("echo xabc\r")
+expect_prompt("xabc")
+send("echo abz\r")
+expect_prompt("translated")
+sendline("bind --erase ctrl-backspace; bind --translate --erase --all")
+expect_prompt()
 
 # Check that the builtin version of `exit` works
 # (for obvious reasons this MUST BE LAST)
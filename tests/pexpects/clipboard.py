@@ -0,0 +1,45 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc, control
+import os
+
+env = os.environ.copy()
+env["TERM"] = "not-dumb"
+
+sp = SpawnedProc(env=env, clipboard_supported=True)
+send, sendline, expect_prompt, expect_str = (
+    sp.send,
+    sp.sendline,
+    sp.expect_prompt,
+    sp.expect_str,
+)
+expect_prompt()
+
+# Copying writes OSC 52 to the terminal.
+sendline("fish_terminal_clipboard copy hello")
+expect_str("\x1b]52;c;aGVsbG8=\x07")
+expect_prompt()
+
+# Pasting asks the terminal.
+sendline("fish_terminal_clipboard paste; echo")
+expect_str("\x1b]52;c;?\x07")
+send("\x1b]52;c;d29ybGQ=\x07")
+sp.send_primary_device_attribute()
+expect_str("world")
+expect_prompt()
+
+# No answer means failure.
+sendline("fish_terminal_clipboard paste; echo status $status")
+expect_str("\x1b]52;c;?\x07")
+sp.send_primary_device_attribute()
+expect_str("status 1")
+expect_prompt()
+
+# The default binding inserts the clipboard contents.
+send("echo pasted-")
+send(control("v"))
+expect_str("\x1b]52;c;?\x07")
+send("\x1b]52;c;c3R1ZmY=\x1b\\")
+sp.send_primary_device_attribute()
+send("\r")
+expect_str("pasted-stuff")
+expect_prompt()
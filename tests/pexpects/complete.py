@@ -90,3 +90,16 @@ send("source foo/b/baz.fish")
 send(control("b") * 9 + "\t")
 expect_str("source foo/bar/baz.fish")
 send(control("u"))
+
+# The preview command is run for the selected completion, and its output is shown next to the pager.
+sendline("set TERM xterm")
+sendline("complete -c preview -xa 'alpha beta gamma'")
+sendline("set -g fish_completion_preview_command 'echo previewing'")
+send("preview \t")
+expect_str("gamma")
+send("\t")
+expect_str("previewing alpha")
+send("\t")
+expect_str("previewing beta")
+send(control("u"))
+sendline("set -e fish_completion_preview_command; set TERM dumb")
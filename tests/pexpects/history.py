@@ -11,7 +11,7 @@
 
 # The history function might pipe output through the user's pager. We don't
 # want something like `less` to complicate matters so force the use of `cat`.
-from pexpect_helper import SpawnedProc
+from pexpect_helper import SpawnedProc, control
 import os
 
 env = os.environ.copy()
@@ -215,3 +215,19 @@ sendline("true")
 expect_prompt()
 sendline("echo a; history search '*spaced*' | cat; echo b")
 expect_prompt("a\r\n.* echo spaced\r\nb\r\n")
+
+# The history finder needs every term to match, and puts the selected match on the commandline.
+sendline("set TERM xterm; bind ctrl-g history-finder")
+expect_prompt()
+sendline("echo finder alpha")
+expect_prompt("finder alpha")
+sendline("echo finder beta")
+expect_prompt("finder beta")
+send(control("g"))
+send("fdr alp")
+sleep(0.5)
+send("\r")
+sendline("")
+expect_prompt("finder alpha")
+sendline("set TERM dumb")
+expect_prompt()
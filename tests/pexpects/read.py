@@ -115,6 +115,19 @@ expect_prompt()
 expect_marker(7)
 print_var_contents("foo", "12te")
 
+# read --timeout
+
+sendline("read -n 1 --timeout 0.2 foo; echo status $status")
+expect_read_prompt()
+expect_prompt("status 1\r\n")
+print_var_contents("foo", "")
+
+sendline("read -n 1 --timeout 10 foo; echo status $status")
+expect_read_prompt()
+send("x")
+expect_prompt("status 0\r\n")
+print_var_contents("foo", "x")
+
 # Verify we don't hang on `read | cat`. See #4540.
 sendline("read | cat")
 expect_read_prompt()
@@ -0,0 +1,32 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import os
+import pexpect
+import subprocess
+import tempfile
+
+recording = os.path.join(tempfile.mkdtemp(), "recording")
+sp = SpawnedProc(args=["--record-input", recording])
+sp.expect_prompt()
+sp.sendline("echo recorded")
+sp.expect_prompt("recorded")
+sp.send("\x1b[A")  # up-arrow
+sp.expect_str("echo recorded")
+sp.send("\r")
+sp.expect_prompt("recorded")
+sp.sendline("exit")
+sp.spawn.expect(pexpect.EOF)
+
+# Decode the recorded keys in the same way fish did.
+fish_key_reader = os.path.join(os.path.dirname(os.environ["fish"]), "fish_key_reader")
+replayed = subprocess.run(
+    [fish_key_reader, "--replay", recording],
+    capture_output=True,
+    encoding="utf-8",
+    check=True,
+).stdout
+binds = [line.split()[1] for line in replayed.splitlines()]
+# sendline() ends lines with a newline, which is ctrl-j rather than enter.
+expected = list("echo") + ["space"] + list("recorded") + ["ctrl-j", "up", "enter"]
+expected += list("exit") + ["ctrl-j"]
+assert binds == expected, binds
@@ -0,0 +1,47 @@
+#!/usr/bin/env python3
+from pexpect_helper import SpawnedProc
+import os
+
+env = os.environ.copy()
+env["TERM"] = "not-dumb"
+
+# A terminal that reports its version is probed for more features.
+sp = SpawnedProc(env=env, xtversion="TestTerm(1.0)")
+sp.expect_str("\x1b[?2026$p")
+sp.send("\x1b[?2026;2$y")  # DECRPM: synchronized output is supported but reset
+sp.send(f"\x1bP1+r{bytes.hex(b'RGB')}={bytes.hex(b'8/8/8')}\x1b\\")
+sp.send_primary_device_attribute()
+# Screen updates are now wrapped in synchronized output.
+sp.expect_str("\x1b[?2026h")
+sp.expect_prompt()
+
+sp.sendline("status terminal-capabilities")
+sp.expect_prompt("synchronized-output\r\ntruecolor\r\n")
+sp.sendline("status test-terminal-feature synchronized-output; echo $status")
+sp.expect_prompt("0")
+sp.sendline("status test-terminal-feature clipboard; echo $status")
+sp.expect_prompt("1")
+
+# Clearing the screen and redrawing the prompt is one synchronized update.
+sp.send("\x0c")
+sp.expect_str("\x1b[?2026h\x1b[H\x1b[2J")
+sp.sendline("")
+sp.expect_prompt()
+sp.sendline("exit")
+sp.spawn.wait()
+
+# The next session in the same terminal uses the cached results instead of asking again.
+sp = SpawnedProc(env=env, xtversion="TestTerm(1.0)")
+sp.expect_prompt()
+sp.sendline("status terminal-capabilities")
+sp.expect_prompt("synchronized-output\r\ntruecolor\r\n")
+sp.sendline("exit")
+sp.spawn.wait()
+
+# A different version is probed again. Without answers, nothing is supported.
+sp = SpawnedProc(env=env, xtversion="TestTerm(2.0)")
+sp.expect_str("\x1b[?2026$p")
+sp.send_primary_device_attribute()
+sp.expect_prompt()
+sp.sendline("status terminal-capabilities | count")
+sp.expect_prompt("0")
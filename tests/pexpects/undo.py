@@ -26,6 +26,25 @@ expect_str("echo")
 send("Redo\r")
 expect_prompt("echo word")
 
+# Typing after undoing starts a new branch, and the undone edits are still reachable.
+sendline("bind Z undo-tree-next-branch")
+expect_prompt()
+# The pager is not shown in dumb terminals.
+sendline("set TERM xterm")
+expect_prompt()
+sendline("bind F 'commandline -i \" foo\"'; bind B 'commandline -i \" bar\"'")
+expect_prompt()
+send("echo")
+send("F")
+send("Undo")
+send("B")
+send("Z")
+expect_str("Undo branch 1 of 2")
+send("\r")
+expect_prompt("foo\r\n")
+sendline("set TERM dumb")
+expect_prompt()
+
 sendline(
     "bind x begin-undo-group 'commandline -i \"+ \"' 'commandline -i 3' end-undo-group"
 )
@@ -43,3 +62,4 @@ send("Undo")
 send("Redo")
 send("9\r")  # 5 + 93
 expect_prompt("98")
+
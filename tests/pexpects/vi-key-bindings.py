@@ -73,3 +73,13 @@ send("3x")  # deletes 3, 4, 5
 send("p")  # pastes after 2 -> 123456
 sendline("")
 expect_re(r"\b123456\b")
+
+# "a makes the next cut or paste use register a instead of the kill ring.
+send("echo abc")
+send("\033")
+sleep(1)
+send('"ax')  # register a now contains 'c'
+send("x")  # the kill ring now contains 'b'
+send('"ap')
+sendline("")
+expect_re(r"\bac\b")